@@ -37,6 +37,9 @@ pub mod run;
 pub mod run_queue;
 pub mod sleepers;
 mod thread_manager;
+pub mod timer;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
 pub mod worker;
 
 ///
@@ -45,4 +48,5 @@ pub mod prelude {
     pub use crate::blocking::*;
     pub use crate::pool::*;
     pub use crate::run::*;
+    pub use crate::timer::{schedule_interval, schedule_once, TimerHandle};
 }
@@ -22,6 +22,27 @@ pub fn set_for_current(core_id: CoreId) {
     set_for_current_helper(core_id);
 }
 
+///
+/// How the threads of the pool are pinned onto the cores.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Affinity {
+    /// The threads aren't pinned, and can be migrated by the OS.
+    Unpinned,
+    /// Every new thread is pinned onto the next core, in a
+    /// round-robin fashion (the default, except on single core
+    /// systems).
+    RoundRobin,
+    /// Every new thread is pinned onto the next core of the given
+    /// list (identified by their ids), in a round-robin fashion.
+    Cores(Vec<usize>),
+}
+
+impl Default for Affinity {
+    fn default() -> Self {
+        Affinity::RoundRobin
+    }
+}
+
 ///
 /// CoreID implementation to identify system cores.
 #[derive(Copy, Clone, Debug)]
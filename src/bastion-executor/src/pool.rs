@@ -3,20 +3,30 @@
 //!
 //! We spawn futures onto the pool with [spawn] method of global run queue or
 //! with corresponding [Worker]'s spawn method.
+//!
+//! The processes are queued by scheduling class (see [ProcPriority]), and the
+//! threads of the pool run the queued processes of the highest class first,
+//! so that system and latency-critical processes preempt bulk work. Not to
+//! starve the lower classes under a steady load, every fourth pop starts with
+//! the normal class and every sixteenth one with the low class.
+//!
+//! [ProcPriority]: ../../lightproc/proc_stack/enum.ProcPriority.html
 
+use crate::placement::Affinity;
 use crate::thread_manager::{DynamicPoolManager, DynamicRunner};
 use crate::worker;
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use crossbeam_channel::{unbounded, Receiver, Select, Sender};
 use lazy_static::lazy_static;
 use lightproc::lightproc::LightProc;
-use lightproc::proc_stack::ProcStack;
+use lightproc::proc_stack::{ProcPriority, ProcStack};
 use lightproc::recoverable_handle::RecoverableHandle;
 use once_cell::sync::{Lazy, OnceCell};
+use std::cell::Cell;
+use std::env;
 use std::future::Future;
 use std::iter::Iterator;
 use std::sync::Arc;
 use std::time::Duration;
-use std::{env, thread};
 use tracing::trace;
 
 ///
@@ -80,6 +90,27 @@ where
     handle
 }
 
+///
+/// Configures the pool before it is started, returning the given
+/// configuration back if the pool was already configured or
+/// started (which happens when the first process is spawned).
+///
+/// # Example
+/// ```rust
+/// use bastion_executor::placement::Affinity;
+/// use bastion_executor::pool::{self, PoolConfig};
+///
+/// // Four threads, pinned onto the first two cores...
+/// let config = PoolConfig::new()
+///     .with_threads(4)
+///     .with_affinity(Affinity::Cores(vec![0, 1]));
+///
+/// pool::configure(config).expect("The pool was already started.");
+/// ```
+pub fn configure(config: PoolConfig) -> Result<(), PoolConfig> {
+    POOL_CONFIG.set(config)
+}
+
 ///
 /// Acquire the static Pool reference
 #[inline]
@@ -110,10 +141,11 @@ impl Pool {
 /// based on the previous statistics without relying on
 /// if there is not a thread ready to accept the work or not.
 pub(crate) fn schedule(t: LightProc) {
-    if let Err(err) = POOL.sender.try_send(t) {
+    let sender = &POOL.senders[class(t.stack().get_priority())];
+    if let Err(err) = sender.try_send(t) {
         // We were not able to send to the channel without
         // blocking.
-        POOL.sender.send(err.into_inner()).unwrap();
+        sender.send(err.into_inner()).unwrap();
     }
     // Add up for every incoming scheduled task
     DYNAMIC_POOL_MANAGER.get().unwrap().increment_frequency();
@@ -140,11 +172,128 @@ fn low_watermark() -> &'static u64 {
 /// This value is used for the heuristics of the scaler
 const DEFAULT_LOW_WATERMARK: u64 = 2;
 
+/// The configuration of the pool, set using [configure]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolConfig {
+    threads: Option<usize>,
+    affinity: Affinity,
+}
+
+impl PoolConfig {
+    ///
+    /// Creates a new configuration, using the default amount of
+    /// threads and affinity.
+    pub fn new() -> Self {
+        PoolConfig::default()
+    }
+
+    ///
+    /// Sets the amount of threads that are always available to run
+    /// processes (overriding `BASTION_BLOCKING_THREADS`).
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    ///
+    /// Sets how the threads of the pool are pinned onto the cores.
+    pub fn with_affinity(mut self, affinity: Affinity) -> Self {
+        self.affinity = affinity;
+        self
+    }
+
+    ///
+    /// Returns the amount of threads that are always available.
+    pub fn threads(&self) -> usize {
+        self.threads.unwrap_or(*low_watermark() as usize)
+    }
+
+    ///
+    /// Returns how the threads of the pool are pinned onto the cores.
+    pub fn affinity(&self) -> &Affinity {
+        &self.affinity
+    }
+}
+
 /// Pool interface between the scheduler and thread pool
 #[derive(Debug)]
 pub struct Pool {
-    sender: Sender<LightProc>,
-    receiver: Receiver<LightProc>,
+    // One queue per scheduling class, from the highest to the
+    // lowest.
+    senders: Vec<Sender<LightProc>>,
+    receivers: Vec<Receiver<LightProc>>,
+}
+
+thread_local! {
+    // The number of pops made by this thread, which decides the
+    // class looked at first (it is kept per thread not to have all
+    // the threads of the pool contending on it).
+    static POPS: Cell<usize> = Cell::new(0);
+}
+
+/// The scheduling classes, from the highest to the lowest.
+const CLASSES: [ProcPriority; 3] = [ProcPriority::High, ProcPriority::Normal, ProcPriority::Low];
+
+/// How many pops out of this amount start with the normal class.
+const NORMAL_INTERVAL: usize = 4;
+/// How many pops out of this amount start with the low class.
+const LOW_INTERVAL: usize = 16;
+
+/// Returns the index of the queue of the given scheduling class.
+#[inline]
+fn class(priority: ProcPriority) -> usize {
+    match priority {
+        ProcPriority::High => 0,
+        ProcPriority::Normal => 1,
+        ProcPriority::Low => 2,
+    }
+}
+
+impl Pool {
+    fn new() -> Self {
+        let (senders, receivers) = CLASSES.iter().map(|_| unbounded()).unzip();
+        Pool { senders, receivers }
+    }
+
+    /// Pops the next process to run, from the queue of the highest
+    /// scheduling class that isn't empty, except for the pops where
+    /// a lower class gets its turn first.
+    fn pop(&self) -> Option<LightProc> {
+        let pop = POPS.with(|pops| {
+            let pop = pops.get();
+            pops.set(pop.wrapping_add(1));
+            pop
+        });
+
+        self.pop_nth(pop)
+    }
+
+    /// Pops the next process to run as the `pop`th pop of the
+    /// current thread.
+    fn pop_nth(&self, pop: usize) -> Option<LightProc> {
+        let first = if pop % LOW_INTERVAL == LOW_INTERVAL - 1 {
+            class(ProcPriority::Low)
+        } else if pop % NORMAL_INTERVAL == NORMAL_INTERVAL - 1 {
+            class(ProcPriority::Normal)
+        } else {
+            class(ProcPriority::High)
+        };
+
+        std::iter::once(first)
+            .chain((0..CLASSES.len()).filter(|class| *class != first))
+            .find_map(|class| self.receivers[class].try_recv().ok())
+    }
+
+    /// Waits until one of the queues isn't empty, or the timeout
+    /// elapsed.
+    fn wait(&self, timeout: Duration) {
+        let mut select = Select::new();
+        for receiver in &self.receivers {
+            select.recv(receiver);
+        }
+
+        let _ = select.ready_timeout(timeout);
+    }
 }
 
 struct AsyncRunner {
@@ -158,18 +307,18 @@ struct AsyncRunner {
 impl DynamicRunner for AsyncRunner {
     fn run_static(&self, park_timeout: Duration) -> ! {
         loop {
-            for task in &POOL.receiver {
+            while let Some(task) = POOL.pop() {
                 trace!("static: running task");
                 self.run(task);
             }
 
-            trace!("static: empty queue, parking with timeout");
-            thread::park_timeout(park_timeout);
+            trace!("static: empty queues, waiting with timeout");
+            POOL.wait(park_timeout);
         }
     }
     fn run_dynamic(&self, parker: &dyn Fn()) -> ! {
         loop {
-            while let Ok(task) = POOL.receiver.try_recv() {
+            while let Some(task) = POOL.pop() {
                 trace!("dynamic thread: running task");
                 self.run(task);
             }
@@ -181,7 +330,7 @@ impl DynamicRunner for AsyncRunner {
         }
     }
     fn run_standalone(&self) {
-        while let Ok(task) = POOL.receiver.try_recv() {
+        while let Some(task) = POOL.pop() {
             self.run(task);
         }
         trace!("standalone thread: quitting.");
@@ -203,7 +352,10 @@ impl AsyncRunner {
 
 static DYNAMIC_POOL_MANAGER: OnceCell<DynamicPoolManager> = OnceCell::new();
 
+static POOL_CONFIG: OnceCell<PoolConfig> = OnceCell::new();
+
 static POOL: Lazy<Pool> = Lazy::new(|| {
+    let config = POOL_CONFIG.get_or_init(PoolConfig::default);
     #[cfg(feature = "tokio-runtime")]
     {
         let runner = Arc::new(AsyncRunner {
@@ -214,7 +366,10 @@ static POOL: Lazy<Pool> = Lazy::new(|| {
         });
 
         DYNAMIC_POOL_MANAGER
-            .set(DynamicPoolManager::new(*low_watermark() as usize, runner))
+            .set(
+                DynamicPoolManager::new(config.threads(), runner)
+                    .with_affinity(config.affinity().clone()),
+            )
             .expect("couldn't create dynamic pool manager");
     }
     #[cfg(not(feature = "tokio-runtime"))]
@@ -222,7 +377,10 @@ static POOL: Lazy<Pool> = Lazy::new(|| {
         let runner = Arc::new(AsyncRunner {});
 
         DYNAMIC_POOL_MANAGER
-            .set(DynamicPoolManager::new(*low_watermark() as usize, runner))
+            .set(
+                DynamicPoolManager::new(config.threads(), runner)
+                    .with_affinity(config.affinity().clone()),
+            )
             .expect("couldn't create dynamic pool manager");
     }
 
@@ -231,6 +389,51 @@ static POOL: Lazy<Pool> = Lazy::new(|| {
         .expect("couldn't get static pool manager")
         .initialize();
 
-    let (sender, receiver) = unbounded();
-    Pool { sender, receiver }
+    Pool::new()
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pops_the_highest_class_first() {
+        let pool = Pool::new();
+        for priority in &[ProcPriority::Low, ProcPriority::High, ProcPriority::Normal] {
+            let stack = ProcStack::default().with_priority(*priority);
+            let (proc, _) = LightProc::build(async {}, |_| {}, stack);
+            pool.senders[class(*priority)].send(proc).unwrap();
+        }
+
+        let popped: Vec<_> = (0..3)
+            .map(|pop| pool.pop_nth(pop).unwrap().stack().get_priority())
+            .collect();
+        assert_eq!(popped, CLASSES);
+        assert!(pool.pop().is_none());
+    }
+
+    #[test]
+    fn lower_classes_make_progress() {
+        let pool = Pool::new();
+        for priority in &[ProcPriority::High, ProcPriority::Normal, ProcPriority::Low] {
+            for _ in 0..LOW_INTERVAL * 2 {
+                let stack = ProcStack::default().with_priority(*priority);
+                let (proc, _) = LightProc::build(async {}, |_| {}, stack);
+                pool.senders[class(*priority)].send(proc).unwrap();
+            }
+        }
+
+        // The high class keeps getting most of the pops, even though
+        // its queue never empties...
+        let popped: Vec<_> = (0..LOW_INTERVAL * 2)
+            .map(|pop| pool.pop_nth(pop).unwrap().stack().get_priority())
+            .collect();
+        let count = |priority| popped.iter().filter(|popped| **popped == priority).count();
+        assert_eq!(count(ProcPriority::Low), 2);
+        assert_eq!(count(ProcPriority::Normal), 6);
+        assert_eq!(count(ProcPriority::High), 24);
+        // ...while the lower ones get their turn.
+        assert_eq!(popped[NORMAL_INTERVAL - 1], ProcPriority::Normal);
+        assert_eq!(popped[LOW_INTERVAL - 1], ProcPriority::Low);
+    }
+}
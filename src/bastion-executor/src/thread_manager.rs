@@ -51,12 +51,12 @@ use crossbeam_queue::ArrayQueue;
 use fmt::{Debug, Formatter};
 use lazy_static::lazy_static;
 use lever::prelude::TTas;
-use placement::CoreId;
+use placement::{Affinity, CoreId};
 use std::collections::VecDeque;
 use std::time::Duration;
 use std::{
     sync::{
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicU64, AtomicUsize, Ordering},
         Arc, Mutex,
     },
     thread::{self, Thread},
@@ -157,6 +157,8 @@ pub struct DynamicPoolManager {
     runner: Arc<dyn DynamicRunner + Send + Sync>,
     last_frequency: AtomicU64,
     frequencies: TTas<VecDeque<u64>>,
+    affinity: Affinity,
+    next_core: AtomicUsize,
 }
 
 impl Debug for DynamicPoolManager {
@@ -168,6 +170,7 @@ impl Debug for DynamicPoolManager {
             .field("parked_threads", &self.parked_threads.len())
             .field("last_frequency", &self.last_frequency)
             .field("frequencies", &self.frequencies.try_lock())
+            .field("affinity", &self.affinity)
             .finish()
     }
 }
@@ -184,9 +187,17 @@ impl DynamicPoolManager {
             frequencies: TTas::new(VecDeque::with_capacity(
                 FREQUENCY_QUEUE_SIZE.saturating_add(1),
             )),
+            affinity: Affinity::default(),
+            next_core: AtomicUsize::new(0),
         }
     }
 
+    /// Sets how the threads of the pool are pinned onto the cores
+    pub fn with_affinity(mut self, affinity: Affinity) -> Self {
+        self.affinity = affinity;
+        self
+    }
+
     pub fn increment_frequency(&self) {
         self.last_frequency.fetch_add(1, Ordering::Acquire);
     }
@@ -201,7 +212,7 @@ impl DynamicPoolManager {
             thread::Builder::new()
                 .name("bastion-driver-static".to_string())
                 .spawn(move || {
-                    Self::affinity_pinner();
+                    self.affinity_pinner();
                     clone.run_static(THREAD_PARK_TIMEOUT);
                 })
                 .expect("couldn't spawn static thread");
@@ -215,7 +226,7 @@ impl DynamicPoolManager {
             thread::Builder::new()
                 .name("bastion-driver-dynamic".to_string())
                 .spawn(move || {
-                    Self::affinity_pinner();
+                    self.affinity_pinner();
                     clone.run_dynamic(&|| self.park_thread());
                 })
                 .expect("cannot start dynamic thread");
@@ -257,7 +268,7 @@ impl DynamicPoolManager {
             thread::Builder::new()
                 .name("bastion-blocking-driver-standalone".to_string())
                 .spawn(move || {
-                    Self::affinity_pinner();
+                    self.affinity_pinner();
                     clone.run_standalone();
                 })
                 .unwrap();
@@ -293,13 +304,24 @@ impl DynamicPoolManager {
 
     ///
     /// Affinity pinner for blocking pool
-    /// Pinning isn't going to be enabled for single core systems.
+    /// Round-robin pinning isn't going to be enabled for single core systems.
     #[inline]
-    fn affinity_pinner() {
-        if 1 != *load_balancer::core_count() {
-            let mut core = ROUND_ROBIN_PIN.lock().unwrap();
-            placement::set_for_current(*core);
-            core.id = (core.id + 1) % *load_balancer::core_count();
+    fn affinity_pinner(&self) {
+        match &self.affinity {
+            Affinity::Unpinned => (),
+            Affinity::RoundRobin => {
+                if 1 != *load_balancer::core_count() {
+                    let mut core = ROUND_ROBIN_PIN.lock().unwrap();
+                    placement::set_for_current(*core);
+                    core.id = (core.id + 1) % *load_balancer::core_count();
+                }
+            }
+            Affinity::Cores(cores) if !cores.is_empty() => {
+                let next = self.next_core.fetch_add(1, Ordering::Relaxed);
+                let id = cores[next % cores.len()];
+                placement::set_for_current(CoreId { id });
+            }
+            Affinity::Cores(_) => (),
         }
     }
 
@@ -0,0 +1,348 @@
+//!
+//! System timer wheel
+//!
+//! The timer wheel allows to schedule callbacks to be run once after a delay,
+//! or periodically, without spawning a task (or a thread) per timer.
+//!
+//! All the timers are stored in a single hashed wheel, which is advanced by
+//! a dedicated thread every [TICK]. This means that timers have a resolution
+//! of [TICK], and that the callbacks are run by the timer thread: they
+//! should be cheap (e.g. sending a message) and must not block.
+//!
+//! Scheduling a timer returns a [TimerHandle] which can be used to cancel it.
+use once_cell::sync::Lazy;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::trace;
+
+/// The resolution of the timer wheel.
+pub const TICK: Duration = Duration::from_millis(10);
+
+/// Number of slots of the wheel. Timers that are due more than
+/// `SLOTS` ticks in the future will stay in their slot for several
+/// rotations of the wheel.
+const SLOTS: usize = 512;
+
+static WHEEL: Lazy<Arc<Mutex<Wheel>>> = Lazy::new(|| {
+    let wheel = Arc::new(Mutex::new(Wheel::new()));
+    let driver = wheel.clone();
+
+    thread::Builder::new()
+        .name("bastion-timer".to_string())
+        .spawn(move || drive(driver))
+        .expect("cannot start the timer thread");
+
+    wheel
+});
+
+///
+/// Schedules `callback` to be run once, after `delay`.
+///
+/// The callback will be run by the timer thread, so it should be cheap and mustn't block.
+///
+/// # Example
+/// ```rust
+/// use bastion_executor::timer::schedule_once;
+/// use std::time::Duration;
+///
+/// let handle = schedule_once(Duration::from_millis(50), || {
+///     println!("50ms later.");
+/// });
+///
+/// // Changed our mind...
+/// handle.cancel();
+/// ```
+pub fn schedule_once<F>(delay: Duration, callback: F) -> TimerHandle
+where
+    F: FnOnce() + Send + 'static,
+{
+    let entry = Arc::new(Entry::new(Callback::Once(Some(Box::new(callback)))));
+    insert(delay, entry.clone());
+
+    TimerHandle { entry }
+}
+
+///
+/// Schedules `callback` to be run every `interval`, until it returns `false`
+/// or the returned [TimerHandle] gets cancelled.
+///
+/// The callback will be run by the timer thread, so it should be cheap and mustn't block.
+///
+/// # Example
+/// ```rust
+/// use bastion_executor::timer::schedule_interval;
+/// use std::time::Duration;
+///
+/// let mut count = 0;
+/// let _handle = schedule_interval(Duration::from_millis(50), move || {
+///     count += 1;
+///     // Stop after the 10th time...
+///     count < 10
+/// });
+/// ```
+pub fn schedule_interval<F>(interval: Duration, callback: F) -> TimerHandle
+where
+    F: FnMut() -> bool + Send + 'static,
+{
+    let entry = Arc::new(Entry::new(Callback::Interval(interval, Box::new(callback))));
+    insert(interval, entry.clone());
+
+    TimerHandle { entry }
+}
+
+///
+/// Creates a timer running `callback` once, which isn't driven by the timer
+/// wheel but by the caller of [TimerHandle::fire] (like another runtime's timer).
+pub fn detached_once<F>(callback: F) -> TimerHandle
+where
+    F: FnOnce() + Send + 'static,
+{
+    let entry = Arc::new(Entry::new(Callback::Once(Some(Box::new(callback)))));
+
+    TimerHandle { entry }
+}
+
+///
+/// Creates a timer running `callback` every `interval`, until it returns
+/// `false` or gets cancelled, which isn't driven by the timer wheel but by
+/// the caller of [TimerHandle::fire] (like another runtime's timer).
+pub fn detached_interval<F>(interval: Duration, callback: F) -> TimerHandle
+where
+    F: FnMut() -> bool + Send + 'static,
+{
+    let entry = Arc::new(Entry::new(Callback::Interval(interval, Box::new(callback))));
+
+    TimerHandle { entry }
+}
+
+///
+/// A handle to a timer scheduled with [schedule_once] or [schedule_interval].
+///
+/// Dropping the handle doesn't cancel the timer.
+#[derive(Clone)]
+pub struct TimerHandle {
+    entry: Arc<Entry>,
+}
+
+impl TimerHandle {
+    ///
+    /// Cancels the timer. Its callback won't be run anymore.
+    pub fn cancel(&self) {
+        self.entry.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    ///
+    /// Returns whether the timer was cancelled, or is a one-off
+    /// timer that already fired.
+    pub fn is_cancelled(&self) -> bool {
+        self.entry.cancelled.load(Ordering::SeqCst)
+    }
+
+    ///
+    /// Runs the callback of a detached timer (see [detached_once] and
+    /// [detached_interval]), returning how long to wait before firing it
+    /// again, if it needs to be.
+    pub fn fire(&self) -> Option<Duration> {
+        self.entry.fire()
+    }
+}
+
+impl Debug for TimerHandle {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("TimerHandle")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+enum Callback {
+    Once(Option<Box<dyn FnOnce() + Send>>),
+    Interval(Duration, Box<dyn FnMut() -> bool + Send>),
+}
+
+struct Entry {
+    cancelled: AtomicBool,
+    callback: Mutex<Callback>,
+}
+
+impl Entry {
+    fn new(callback: Callback) -> Self {
+        Entry {
+            cancelled: AtomicBool::new(false),
+            callback: Mutex::new(callback),
+        }
+    }
+
+    /// Runs the callback, returning its interval if it needs
+    /// to be scheduled again.
+    fn fire(&self) -> Option<Duration> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let mut callback = self.callback.lock().unwrap();
+        match &mut *callback {
+            Callback::Once(callback) => {
+                self.cancelled.store(true, Ordering::SeqCst);
+                if let Some(callback) = callback.take() {
+                    callback();
+                }
+
+                None
+            }
+            Callback::Interval(interval, callback) => {
+                if callback() {
+                    Some(*interval)
+                } else {
+                    self.cancelled.store(true, Ordering::SeqCst);
+                    None
+                }
+            }
+        }
+    }
+}
+
+struct Scheduled {
+    // The tick at which the entry is due.
+    deadline: u64,
+    entry: Arc<Entry>,
+}
+
+struct Wheel {
+    slots: Vec<Vec<Scheduled>>,
+    // The amount of ticks elapsed since the wheel was created.
+    current: u64,
+}
+
+impl Wheel {
+    fn new() -> Self {
+        let slots = (0..SLOTS).map(|_| Vec::new()).collect();
+        Wheel { slots, current: 0 }
+    }
+
+    fn insert(&mut self, delay: Duration, entry: Arc<Entry>) {
+        // Round up, and make sure that the entry is due at the
+        // next tick at the earliest.
+        let ticks = ((delay.as_nanos() + TICK.as_nanos() - 1) / TICK.as_nanos()).max(1) as u64;
+        let deadline = self.current + ticks;
+        let slot = (deadline % SLOTS as u64) as usize;
+
+        self.slots[slot].push(Scheduled { deadline, entry });
+    }
+
+    /// Advances the wheel by one tick, returning the entries that
+    /// are due.
+    fn advance(&mut self) -> Vec<Arc<Entry>> {
+        self.current += 1;
+        let current = self.current;
+        let slot = &mut self.slots[(current % SLOTS as u64) as usize];
+
+        let mut due = Vec::new();
+        slot.retain(|scheduled| {
+            if scheduled.entry.cancelled.load(Ordering::SeqCst) {
+                false
+            } else if scheduled.deadline <= current {
+                due.push(scheduled.entry.clone());
+                false
+            } else {
+                true
+            }
+        });
+
+        due
+    }
+}
+
+fn insert(delay: Duration, entry: Arc<Entry>) {
+    WHEEL.lock().unwrap().insert(delay, entry);
+}
+
+fn drive(wheel: Arc<Mutex<Wheel>>) {
+    let start = Instant::now();
+    loop {
+        let current = wheel.lock().unwrap().current;
+        let next = start + TICK * (current + 1) as u32;
+        // If the thread is late, the wheel is advanced without
+        // sleeping until it catches up.
+        let now = Instant::now();
+        if next > now {
+            thread::sleep(next - now);
+        }
+
+        let due = wheel.lock().unwrap().advance();
+        for entry in due {
+            if let Some(interval) = entry.fire() {
+                wheel.lock().unwrap().insert(interval, entry);
+            }
+        }
+
+        trace!("timer: advanced to tick {}", current + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn rounds_delays_up_to_the_next_tick() {
+        let mut wheel = Wheel::new();
+        let entry = Arc::new(Entry::new(Callback::Once(None)));
+
+        wheel.insert(Duration::from_millis(1), entry.clone());
+        wheel.insert(TICK * 3, entry);
+
+        assert_eq!(wheel.advance().len(), 1);
+        assert_eq!(wheel.advance().len(), 0);
+        assert_eq!(wheel.advance().len(), 1);
+    }
+
+    #[test]
+    fn keeps_entries_for_later_rotations() {
+        let mut wheel = Wheel::new();
+        let entry = Arc::new(Entry::new(Callback::Once(None)));
+
+        wheel.insert(TICK * (SLOTS as u32 + 1), entry);
+
+        for _ in 0..SLOTS {
+            assert!(wheel.advance().is_empty());
+        }
+        assert_eq!(wheel.advance().len(), 1);
+    }
+
+    #[test]
+    fn interval_stops_when_cancelled() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let entry = Entry::new(Callback::Interval(
+            TICK,
+            Box::new(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                true
+            }),
+        ));
+
+        assert_eq!(entry.fire(), Some(TICK));
+        entry.cancelled.store(true, Ordering::SeqCst);
+        assert_eq!(entry.fire(), None);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn detached_once_fires_a_single_time() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let handle = detached_once(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        assert_eq!(handle.fire(), None);
+        assert!(handle.is_cancelled());
+        assert_eq!(handle.fire(), None);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,338 @@
+//!
+//! io_uring reactor for file and socket operations
+//!
+//! Available on Linux with the `io-uring` feature, this reactor submits the operations
+//! to a single ring shared by the whole process. A dedicated thread waits for their
+//! completions and wakes up the futures waiting for them, so that heavy I/O doesn't
+//! need to consume the threads of the blocking pool.
+//!
+//! The operations take ownership of their buffer, which is given back with their
+//! result: the kernel might write to it until the operation completes, even if its
+//! future was dropped (in which case the buffer is dropped once it completed).
+//!
+//! # Example
+//! ```rust,no_run
+//! use bastion_executor::prelude::*;
+//! use bastion_executor::uring;
+//! use lightproc::proc_stack::ProcStack;
+//! use std::fs::File;
+//! use std::os::unix::io::AsRawFd;
+//!
+//! let file = File::open("Cargo.toml").unwrap();
+//! let (res, buf) = run(
+//!     uring::read_at(file.as_raw_fd(), vec![0; 1024], 0),
+//!     ProcStack::default(),
+//! );
+//! let read = res.unwrap();
+//! println!("{}", String::from_utf8_lossy(&buf[..read]));
+//! ```
+
+use io_uring::{opcode, squeue, types, IoUring};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::io;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use tracing::{error, trace};
+
+/// Amount of entries of the submission queue of the ring.
+const RING_ENTRIES: u32 = 256;
+
+static REACTOR: Lazy<io::Result<Arc<Reactor>>> = Lazy::new(Reactor::start);
+
+/// The ring shared by all operations, and the operations in flight.
+struct Reactor {
+    ring: IoUring,
+    // Serializes the pushes onto the submission queue.
+    submissions: Mutex<()>,
+    ops: Mutex<HashMap<u64, Slot>>,
+    next_id: AtomicU64,
+}
+
+/// The state of an operation in flight.
+struct Slot {
+    // The buffer the kernel reads from or writes to.
+    buf: Vec<u8>,
+    result: Option<i32>,
+    waker: Option<Waker>,
+    // Whether the future of the operation was dropped.
+    detached: bool,
+}
+
+/// Future of an operation submitted to the ring, resolving to its result and its
+/// buffer.
+pub struct Op {
+    // The reactor and id of the operation, or `None` if it couldn't be
+    // submitted.
+    submitted: Option<(Arc<Reactor>, u64)>,
+    // The error and buffer of an operation that couldn't be submitted.
+    failed: Option<(io::Error, Vec<u8>)>,
+    completed: bool,
+}
+
+impl Reactor {
+    fn start() -> io::Result<Arc<Self>> {
+        let reactor = Arc::new(Reactor {
+            ring: IoUring::new(RING_ENTRIES)?,
+            submissions: Mutex::new(()),
+            ops: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        });
+
+        let completer = reactor.clone();
+        thread::Builder::new()
+            .name("bastion-uring".to_string())
+            .spawn(move || completer.complete())?;
+
+        Ok(reactor)
+    }
+
+    /// Waits for the completions of the ring, forever.
+    fn complete(&self) {
+        loop {
+            if let Err(err) = self.ring.submitter().submit_and_wait(1) {
+                if err.raw_os_error() != Some(libc::EINTR) {
+                    error!("uring: couldn't wait for completions: {}", err);
+                }
+
+                continue;
+            }
+
+            // Only this thread consumes the completion queue.
+            let completions = unsafe { self.ring.completion_shared() };
+            let mut ops = self.ops.lock().unwrap();
+            for cqe in completions {
+                let id = cqe.user_data();
+                trace!("uring: operation {} completed: {}", id, cqe.result());
+                let detached = match ops.get_mut(&id) {
+                    Some(slot) => {
+                        slot.result = Some(cqe.result());
+                        if let Some(waker) = slot.waker.take() {
+                            waker.wake();
+                        }
+
+                        slot.detached
+                    }
+                    None => false,
+                };
+
+                if detached {
+                    ops.remove(&id);
+                }
+            }
+        }
+    }
+
+    /// Submits the entry built with the pointer and length of `buf`.
+    fn submit<B>(self: &Arc<Self>, mut buf: Vec<u8>, build: B) -> Op
+    where
+        B: FnOnce(*mut u8, u32) -> squeue::Entry,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let entry = build(buf.as_mut_ptr(), buf.len() as u32).user_data(id);
+        self.ops.lock().unwrap().insert(
+            id,
+            Slot {
+                buf,
+                result: None,
+                waker: None,
+                detached: false,
+            },
+        );
+
+        let pushed = {
+            let _guard = self.submissions.lock().unwrap();
+            self.push(&entry)
+        };
+        if let Err(err) = pushed {
+            let slot = self.ops.lock().unwrap().remove(&id).unwrap();
+            return Op::failed(err, slot.buf);
+        }
+
+        // Once pushed, the entry is submitted by the next call to `io_uring_enter`,
+        // which might be the one of the completion thread.
+        if let Err(err) = self.ring.submitter().submit() {
+            error!("uring: couldn't submit operation {}: {}", id, err);
+        }
+
+        Op {
+            submitted: Some((self.clone(), id)),
+            failed: None,
+            completed: false,
+        }
+    }
+
+    /// Pushes the entry onto the submission queue, submitting the queued entries
+    /// while it is full.
+    fn push(&self, entry: &squeue::Entry) -> io::Result<()> {
+        loop {
+            // The pushes are serialized by the caller, and the buffer of the entry
+            // lives in its slot until the operation completes.
+            let mut submissions = unsafe { self.ring.submission_shared() };
+            if unsafe { submissions.push(entry) }.is_ok() {
+                return Ok(());
+            }
+
+            drop(submissions);
+            self.ring.submitter().submit()?;
+        }
+    }
+}
+
+impl Op {
+    fn failed(err: io::Error, buf: Vec<u8>) -> Self {
+        Op {
+            submitted: None,
+            failed: Some((err, buf)),
+            completed: false,
+        }
+    }
+}
+
+impl Future for Op {
+    type Output = (io::Result<usize>, Vec<u8>);
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let (reactor, id) = match &self.submitted {
+            Some((reactor, id)) => (reactor.clone(), *id),
+            None => {
+                self.completed = true;
+                let (err, buf) = self
+                    .failed
+                    .take()
+                    .expect("uring: operation polled after completion");
+                return Poll::Ready((Err(err), buf));
+            }
+        };
+
+        let mut ops = reactor.ops.lock().unwrap();
+        let result = match ops.get_mut(&id) {
+            Some(slot) => match slot.result {
+                Some(result) => result,
+                None => {
+                    slot.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            },
+            None => return Poll::Pending,
+        };
+
+        let slot = ops.remove(&id).unwrap();
+        drop(ops);
+        self.completed = true;
+
+        let res = if result < 0 {
+            Err(io::Error::from_raw_os_error(-result))
+        } else {
+            Ok(result as usize)
+        };
+        Poll::Ready((res, slot.buf))
+    }
+}
+
+impl Drop for Op {
+    fn drop(&mut self) {
+        if let (Some((reactor, id)), false) = (&self.submitted, self.completed) {
+            // The buffer is kept until the operation completes.
+            let mut ops = reactor.ops.lock().unwrap();
+            let completed = match ops.get_mut(id) {
+                Some(slot) => {
+                    slot.detached = true;
+                    slot.result.is_some()
+                }
+                None => false,
+            };
+
+            if completed {
+                ops.remove(id);
+            }
+        }
+    }
+}
+
+impl Debug for Op {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Op")
+            .field("id", &self.submitted.as_ref().map(|(_, id)| *id))
+            .field("completed", &self.completed)
+            .finish()
+    }
+}
+
+fn reactor() -> io::Result<Arc<Reactor>> {
+    match &*REACTOR {
+        Ok(reactor) => Ok(reactor.clone()),
+        Err(err) => Err(io::Error::new(err.kind(), err.to_string())),
+    }
+}
+
+fn submit<B>(buf: Vec<u8>, build: B) -> Op
+where
+    B: FnOnce(*mut u8, u32) -> squeue::Entry,
+{
+    match reactor() {
+        Ok(reactor) => reactor.submit(buf, build),
+        // The ring couldn't be set up, e.g. because the kernel doesn't support
+        // io_uring.
+        Err(err) => Op::failed(err, buf),
+    }
+}
+
+///
+/// Reads from the file descriptor at `offset` into `buf`, returning the amount of
+/// bytes read and the buffer.
+pub fn read_at(fd: RawFd, buf: Vec<u8>, offset: u64) -> Op {
+    submit(buf, |ptr, len| {
+        opcode::Read::new(types::Fd(fd), ptr, len)
+            .offset(offset)
+            .build()
+    })
+}
+
+///
+/// Writes `buf` to the file descriptor at `offset`, returning the amount of bytes
+/// written and the buffer.
+pub fn write_at(fd: RawFd, buf: Vec<u8>, offset: u64) -> Op {
+    submit(buf, |ptr, len| {
+        opcode::Write::new(types::Fd(fd), ptr, len)
+            .offset(offset)
+            .build()
+    })
+}
+
+///
+/// Receives data from the socket into `buf`, returning the amount of bytes received
+/// and the buffer.
+pub fn recv(fd: RawFd, buf: Vec<u8>) -> Op {
+    submit(buf, |ptr, len| {
+        opcode::Recv::new(types::Fd(fd), ptr, len).build()
+    })
+}
+
+///
+/// Sends `buf` through the socket, returning the amount of bytes sent and the
+/// buffer.
+pub fn send(fd: RawFd, buf: Vec<u8>) -> Op {
+    submit(buf, |ptr, len| {
+        opcode::Send::new(types::Fd(fd), ptr, len).build()
+    })
+}
+
+///
+/// Accepts a connection on the listening socket, returning the file descriptor of
+/// the accepted socket.
+pub async fn accept(fd: RawFd) -> io::Result<RawFd> {
+    let op = submit(Vec::new(), |_, _| {
+        opcode::Accept::new(types::Fd(fd), std::ptr::null_mut(), std::ptr::null_mut())
+            .flags(libc::SOCK_CLOEXEC)
+            .build()
+    });
+
+    op.await.0.map(|fd| fd as RawFd)
+}
@@ -0,0 +1,28 @@
+// With `tokio-runtime`, the processes run on the threads of the
+// runtime instead of the (pinned) threads of the pool.
+#![cfg(all(target_os = "linux", not(feature = "tokio-runtime")))]
+use bastion_executor::placement::{self, Affinity};
+use bastion_executor::pool::{self, PoolConfig};
+use bastion_executor::run::run;
+use lightproc::proc_stack::ProcStack;
+
+#[test]
+fn pinned_threads() {
+    let core = placement::get_core_ids().unwrap()[0].id;
+    let config = PoolConfig::new()
+        .with_threads(1)
+        .with_affinity(Affinity::Cores(vec![core]));
+    pool::configure(config.clone()).unwrap();
+
+    // The process runs on a thread pinned onto the core...
+    let handle = pool::spawn(
+        async { placement::get_core_ids().unwrap() },
+        ProcStack::default(),
+    );
+    let cores = run(handle, ProcStack::default()).unwrap();
+    assert_eq!(cores.len(), 1);
+    assert_eq!(cores[0].id, core);
+
+    // ...and the pool can't be configured again.
+    assert_eq!(pool::configure(config.clone()), Err(config));
+}
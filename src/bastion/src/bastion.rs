@@ -1,21 +1,54 @@
+use crate::blocking_pool;
 use crate::broadcast::{Broadcast, Parent};
+use crate::child_ref::ChildRef;
 use crate::children::Children;
 use crate::children_ref::ChildrenRef;
+#[cfg(feature = "cluster")]
+use crate::cluster::Cluster;
 use crate::config::Config;
 use crate::context::{BastionContext, BastionId};
+use crate::dead_letters::DeadLetters;
 use crate::envelope::Envelope;
+#[cfg(feature = "remote")]
+use crate::errors::RemoteError;
+use crate::events::SupervisionEvents;
+use crate::executor::{self, Executor};
+#[cfg(feature = "management")]
+use crate::management::{self, ManagementConfig};
 use crate::message::{BastionMessage, Message};
+use crate::middleware::{self, Middleware};
 use crate::path::BastionPathElement;
+use crate::reload::{self, ReloadReport};
+#[cfg(feature = "remote")]
+use crate::remote::{self, RemoteNode, RemotingConfig};
+use crate::selection::Selection;
+#[cfg(all(feature = "signals", not(target_os = "windows")))]
+use crate::signals::{self, SignalConfig};
+use crate::state::StateRegistry;
 use crate::supervisor::{Supervisor, SupervisorRef};
-use crate::system::SYSTEM;
+use crate::system::{self, SYSTEM};
+use crate::testing::DeterministicExecutor;
+use crate::tree::TreeSnapshot;
+use crate::watch::StopReason;
 
 use core::future::Future;
-use tracing::{debug, trace};
+#[cfg(feature = "remote")]
+use tracing::error;
+use tracing::{debug, info, trace};
 
+use std::env;
 use std::fmt::{self, Debug, Formatter};
+#[cfg(any(
+    feature = "management",
+    all(feature = "signals", not(target_os = "windows"))
+))]
+use std::io;
+#[cfg(any(feature = "management", feature = "remote"))]
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
 
 distributed_api! {
-    use std::sync::Arc;
     use crate::distributed::*;
     use artillery_core::cluster::ap::*;
 }
@@ -264,7 +297,168 @@ impl Bastion {
             std::panic::set_hook(Box::new(|_| ()));
         }
 
+        // The executor must be set (and configured) before the
+        // system is spawned.
+        executor::set_executor(config.executor().clone());
+        executor::configure_pool(config.executor_threads(), config.thread_affinity());
+        blocking_pool::set_pool(config.blocking_pool().cloned());
+        reload::init(&config);
         lazy_static::initialize(&SYSTEM);
+
+        // The node can only join a cluster once it is bound.
+        #[cfg(feature = "remote")]
+        if let Some((addr, remoting)) = config.remoting() {
+            if let Err(err) = Bastion::bind_with(*addr, remoting.clone()) {
+                error!("Bastion: Couldn't bind to {}: {:?}", addr, err);
+            }
+        }
+        #[cfg(feature = "cluster")]
+        if let Some(cluster) = config.cluster() {
+            if let Err(err) = Cluster::join(cluster.clone()) {
+                error!("Bastion: Couldn't join the cluster: {:?}", err);
+            }
+        }
+    }
+
+    /// Applies the settings of `config` which can be changed while
+    /// the system is running, comparing it with the configuration
+    /// the system was initialized (or last reloaded) with, and
+    /// returns a [`ReloadReport`] listing the settings that changed,
+    /// depending on whether they were applied or only take effect
+    /// once the process is restarted.
+    ///
+    /// The settings applied are:
+    /// - The capacities of the bounded mailboxes, the rate limits
+    ///   and the bounds of the resizers of the children groups (see
+    ///   [`Config::with_mailbox_capacity`], [`Config::with_rate_limit`]
+    ///   and [`Config::with_resizer_bounds`]), which the running
+    ///   groups apply as soon as they are told to. The rate limit
+    ///   of a group which wasn't rate limited when it was launched
+    ///   only applies to its elements launched afterwards.
+    /// - The log level (see [`Config::with_log_level`]), the budget
+    ///   of the children (see [`Config::with_budget`]) and whether
+    ///   the metrics are collected.
+    /// - The default mailbox configuration and the restart strategies
+    ///   of the children groups, which apply to the groups launched
+    ///   afterwards.
+    ///
+    /// The backtraces, executor, blocking pool, remoting and cluster
+    /// settings require a restart.
+    ///
+    /// # Argument
+    ///
+    /// * `config` - The new configuration of the system.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init_with(Config::new().with_rate_limit("api_clients", RateLimit::per_second(50)));
+    /// Bastion::start();
+    ///
+    /// // The downstream API raised its quota...
+    /// let config = Config::new().with_rate_limit("api_clients", RateLimit::per_second(200));
+    /// let report = Bastion::reload_config(config);
+    /// if !report.is_fully_applied() {
+    ///     println!("Restart required for: {:?}", report.requires_restart());
+    /// }
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`ReloadReport`]: reload/struct.ReloadReport.html
+    /// [`Config::with_mailbox_capacity`]: struct.Config.html#method.with_mailbox_capacity
+    /// [`Config::with_rate_limit`]: struct.Config.html#method.with_rate_limit
+    /// [`Config::with_resizer_bounds`]: struct.Config.html#method.with_resizer_bounds
+    /// [`Config::with_log_level`]: struct.Config.html#method.with_log_level
+    /// [`Config::with_budget`]: struct.Config.html#method.with_budget
+    pub fn reload_config(config: Config) -> ReloadReport {
+        debug!("Bastion: Reloading config: {:?}", config);
+        let report = reload::reload(config);
+
+        let msg = BastionMessage::reconfigure();
+        let envelope = Envelope::from_dead_letters(msg);
+        trace!("Bastion: Sending envelope: {:?}", envelope);
+        // FIXME: Err(Error)
+        SYSTEM.sender().unbounded_send(envelope).ok();
+
+        report
+    }
+
+    /// Initializes the system to run on a [`DeterministicExecutor`],
+    /// which runs it on a single thread and interleaves the
+    /// messages of its children in an order picked using a seed.
+    ///
+    /// The seed is read from the `BASTION_TEST_SEED` environment
+    /// variable if it is set, or picked randomly otherwise (and
+    /// logged), so that a failing test can be replayed by setting
+    /// the variable.
+    ///
+    /// This method returns the executor, to control it and get its
+    /// seed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let executor = Bastion::init_test();
+    /// Bastion::start();
+    ///
+    /// // Tests children deterministically...
+    /// executor.wait_until_idle(Duration::from_secs(1));
+    /// println!("Seed: {}", executor.seed());
+    ///
+    /// Bastion::stop();
+    /// Bastion::block_until_stopped();
+    /// executor.shutdown();
+    /// ```
+    ///
+    /// [`DeterministicExecutor`]: testing/struct.DeterministicExecutor.html
+    pub fn init_test() -> DeterministicExecutor {
+        let seed = env::var("BASTION_TEST_SEED")
+            .ok()
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or_else(rand::random);
+        Bastion::init_test_with_seed(seed)
+    }
+
+    /// Initializes the system to run on a [`DeterministicExecutor`]
+    /// with the given seed (see [`Bastion::init_test`]).
+    ///
+    /// # Argument
+    ///
+    /// * `seed` - The seed of the order the processes of the system
+    ///     are polled in.
+    ///
+    /// [`DeterministicExecutor`]: testing/struct.DeterministicExecutor.html
+    /// [`Bastion::init_test`]: #method.init_test
+    pub fn init_test_with_seed(seed: u64) -> DeterministicExecutor {
+        info!(
+            "Bastion: Initializing deterministically with seed: {}",
+            seed
+        );
+        let executor = DeterministicExecutor::new(seed);
+        let config = Config::new().with_executor(Executor::custom(executor.clone()));
+        Bastion::init_with(config);
+
+        executor
     }
 
     /// Creates a new [`Supervisor`], passes it through the specified
@@ -530,6 +724,10 @@ impl Bastion {
         debug!("Bastion: Broadcasting message: {:?}", msg);
         let msg = BastionMessage::broadcast(msg);
         let envelope = Envelope::from_dead_letters(msg);
+        if system::refuses(&envelope) {
+            return Err(envelope.into_msg().unwrap());
+        }
+
         trace!("Bastion: Sending envelope: {:?}", envelope);
         // FIXME: panics?
         SYSTEM
@@ -538,6 +736,475 @@ impl Bastion {
             .map_err(|err| err.into_inner().into_msg().unwrap())
     }
 
+    /// Returns a [`DeadLetters`] handle to the system-level dead
+    /// letters actor, collecting the messages that couldn't be
+    /// delivered to their recipient (e.g. because it was stopped
+    /// or because it doesn't exist).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    /// use futures::prelude::*;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    ///
+    /// let dead_letters = Bastion::dead_letters();
+    /// let mut letters = dead_letters.subscribe();
+    ///
+    /// Bastion::children(|children| {
+    ///     children.with_exec(move |ctx: BastionContext| {
+    ///         async move {
+    ///             // ...
+    ///             # Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    /// Bastion::start();
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    ///
+    /// // Every message that couldn't be delivered since the
+    /// // subscription will be yielded by `letters`...
+    /// # if false {
+    /// while let Some(letter) = run!(letters.next()) {
+    ///     println!("Dead letter sent to {}: {:?}", letter.target(), letter.message());
+    /// }
+    /// # }
+    /// println!("{} dead letters so far.", dead_letters.total());
+    /// # }
+    /// ```
+    ///
+    /// [`DeadLetters`]: dead_letters/struct.DeadLetters.html
+    pub fn dead_letters() -> DeadLetters {
+        DeadLetters::new(SYSTEM.dead_letters_state().clone())
+    }
+
+    /// Returns a reference to the running child with the given name,
+    /// as named by its children group using
+    /// [`Children::with_child_name`], or `None` if there is no such
+    /// child (e.g. because it is being restarted).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the child.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    ///
+    /// Bastion::children(|children| {
+    ///     children.with_child_name(|index| format!("ingest-worker-{}", index))
+    /// }).expect("Couldn't create the children group.");
+    ///
+    /// Bastion::start();
+    ///
+    /// let worker: Option<ChildRef> = Bastion::child_by_name("ingest-worker-0");
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Children::with_child_name`]: children/struct.Children.html#method.with_child_name
+    pub fn child_by_name(name: &str) -> Option<ChildRef> {
+        SYSTEM.child_by_name(name)
+    }
+
+    /// Returns a [`Selection`] of the running children whose path
+    /// matches the pattern (see the [`selection`] module), allowing
+    /// to send messages to a dynamic subtree of the system.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern the paths of the selected children
+    ///     match, like `/**/workers/*`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    ///
+    /// Bastion::supervisor(|sp| {
+    ///     sp.children(|children| children.with_name("workers"))
+    /// }).expect("Couldn't create the supervisor.");
+    ///
+    /// Bastion::start();
+    ///
+    /// let workers = Bastion::select("/**/workers/*");
+    /// workers.tell_everyone("reload").ok();
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Selection`]: selection/struct.Selection.html
+    /// [`selection`]: selection/index.html
+    pub fn select(pattern: &str) -> Selection {
+        Selection::new(pattern)
+    }
+
+    /// Returns a [`TreeSnapshot`] of the supervision tree, describing
+    /// the supervisors, the children groups they supervise and, for
+    /// every element of those groups, whether it is running,
+    /// restarting or stopped, how many times it was restarted and
+    /// how many messages are waiting in its mailbox.
+    ///
+    /// The snapshot can be serialized (e.g. to expose the live tree
+    /// from a health endpoint).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    ///
+    /// Bastion::children(|children| children.with_name("workers"))
+    ///     .expect("Couldn't create the children group.");
+    ///
+    /// Bastion::start();
+    ///
+    /// let tree = Bastion::tree();
+    /// let json = serde_json::to_string_pretty(&tree).unwrap();
+    /// println!("{}", json);
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`TreeSnapshot`]: tree/struct.TreeSnapshot.html
+    pub fn tree() -> TreeSnapshot {
+        TreeSnapshot::new()
+    }
+
+    /// Returns a [`SupervisionEvents`] stream yielding every
+    /// [`SupervisionEvent`] emitted after this call (e.g. when a
+    /// child starts, stops, panics or gets restarted, or when a
+    /// supervisor escalates a failure).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    /// use futures::prelude::*;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    ///
+    /// let mut events = Bastion::events();
+    ///
+    /// Bastion::children(|children| {
+    ///     children.with_exec(move |ctx: BastionContext| {
+    ///         async move {
+    ///             // ...
+    ///             # Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    /// Bastion::start();
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    ///
+    /// # if false {
+    /// while let Some(event) = run!(events.next()) {
+    ///     if let SupervisionEvent::ChildPanicked { path, error } = event {
+    ///         println!("{} panicked: {:?}", path, error);
+    ///     }
+    /// }
+    /// # }
+    /// # }
+    /// ```
+    ///
+    /// [`SupervisionEvents`]: events/struct.SupervisionEvents.html
+    /// [`SupervisionEvent`]: events/enum.SupervisionEvent.html
+    pub fn events() -> SupervisionEvents {
+        SupervisionEvents::subscribe()
+    }
+
+    /// Returns the [`StateRegistry`] holding the values shared with
+    /// every child of the system, which they can then retrieve using
+    /// [`BastionContext::state`] instead of capturing clones of them
+    /// in their closures.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::sync::Arc;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    ///
+    /// struct Client {
+    ///     endpoint: String,
+    /// }
+    ///
+    /// Bastion::state().insert(Client {
+    ///     endpoint: "http://localhost:8080".to_string(),
+    /// });
+    ///
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| async move {
+    ///         let client: Arc<Client> = ctx.state();
+    ///         // Use the client...
+    ///
+    ///         Ok(())
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    /// Bastion::start();
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`StateRegistry`]: state/struct.StateRegistry.html
+    /// [`BastionContext::state`]: context/struct.BastionContext.html#method.state
+    pub fn state() -> StateRegistry {
+        StateRegistry::global()
+    }
+
+    /// Adds a [`Middleware`] run around the delivery of every
+    /// message to every child of the system, before the ones
+    /// added to their children groups using
+    /// [`Children::with_middleware`].
+    ///
+    /// # Arguments
+    ///
+    /// * `middleware` - The middleware to add.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    ///
+    /// // Log every message delivered to a child...
+    /// Bastion::add_middleware(|msg: SignedMessage, next: Next| {
+    ///     println!("{}: Received {:?}", next.path(), msg.msg());
+    ///     next.run(msg);
+    /// });
+    ///
+    /// Bastion::start();
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Middleware`]: middleware/trait.Middleware.html
+    /// [`Children::with_middleware`]: children/struct.Children.html#method.with_middleware
+    pub fn add_middleware(middleware: impl Middleware) {
+        debug!("Bastion: Adding middleware.");
+        middleware::add(Arc::new(middleware));
+    }
+
+    #[cfg(feature = "remote")]
+    /// Binds this node to the given address, allowing other nodes
+    /// to connect to it using [`Bastion::connect`] and to send
+    /// messages to its children, using the default
+    /// [`RemotingConfig`].
+    ///
+    /// This method returns the address the node was bound to
+    /// (which is useful when binding to port `0`) if it succeeded,
+    /// or `Err(error)` otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to listen on.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    ///
+    /// # let addr = "127.0.0.1:0";
+    /// # /*
+    /// let addr = "0.0.0.0:4222";
+    /// # */
+    /// Bastion::bind(addr).expect("Couldn't bind the node.");
+    ///
+    /// Bastion::start();
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Bastion::connect`]: #method.connect
+    /// [`RemotingConfig`]: remote/struct.RemotingConfig.html
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<SocketAddr, RemoteError> {
+        Bastion::bind_with(addr, RemotingConfig::default())
+    }
+
+    #[cfg(feature = "remote")]
+    /// Binds this node to the given address, like [`Bastion::bind`]
+    /// but using the given [`RemotingConfig`].
+    ///
+    /// This method returns the address the node was bound to if it
+    /// succeeded, or `Err(error)` otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to listen on.
+    /// * `config` - The configuration of the remoting subsystem.
+    ///
+    /// [`Bastion::bind`]: #method.bind
+    /// [`RemotingConfig`]: remote/struct.RemotingConfig.html
+    pub fn bind_with<A: ToSocketAddrs>(
+        addr: A,
+        config: RemotingConfig,
+    ) -> Result<SocketAddr, RemoteError> {
+        debug!("Bastion: Binding with config: {:?}", config);
+        remote::bind(addr, config)
+    }
+
+    #[cfg(feature = "remote")]
+    /// Connects this node to the node bound to the given address
+    /// (see [`Bastion::bind`]), exchanging a handshake with it.
+    ///
+    /// This method returns a [`RemoteNode`] allowing to send
+    /// messages to the other node's children if it succeeded,
+    /// or `Err(error)` otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address the other node is bound to.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use bastion::prelude::*;
+    ///
+    /// # fn run() -> Result<(), RemoteError> {
+    /// bastion::remote::register_message::<String>();
+    ///
+    /// let node = Bastion::connect("10.0.0.2:4222")?;
+    /// let child_ref = node.child_ref("/0000/0001/0002");
+    /// child_ref
+    ///     .tell_anonymously("Hello from another node!".to_string())
+    ///     .expect("Couldn't send the message.");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Bastion::bind`]: #method.bind
+    /// [`RemoteNode`]: remote/struct.RemoteNode.html
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<RemoteNode, RemoteError> {
+        debug!("Bastion: Connecting to another node.");
+        remote::connect(addr)
+    }
+
     /// Sends a message to the system to tell it to start
     /// handling messages and running children.
     ///
@@ -616,6 +1283,10 @@ impl Bastion {
     /// ```
     pub fn stop() {
         debug!("Bastion: Stopping.");
+        // Other nodes can't connect to this one anymore.
+        #[cfg(feature = "remote")]
+        remote::unbind();
+
         let msg = BastionMessage::stop();
         let envelope = Envelope::from_dead_letters(msg);
         trace!("Bastion: Sending envelope: {:?}", envelope);
@@ -657,7 +1328,18 @@ impl Bastion {
     /// ```
     pub fn kill() {
         debug!("Bastion: Killing.");
-        let msg = BastionMessage::kill();
+        // Killing while shutting down gracefully means that the
+        // children didn't finish in time.
+        let reason = if SYSTEM.is_draining() {
+            StopReason::ShutdownTimeout
+        } else {
+            StopReason::Killed
+        };
+
+        #[cfg(feature = "remote")]
+        remote::unbind();
+
+        let msg = BastionMessage::kill_with(reason);
         let envelope = Envelope::from_dead_letters(msg);
         trace!("Bastion: Sending envelope: {:?}", envelope);
         // FIXME: Err(Error)
@@ -673,6 +1355,191 @@ impl Bastion {
         SYSTEM.notify_stopped();
     }
 
+    /// Stops the system gracefully, blocking the current thread
+    /// until it is stopped.
+    ///
+    /// New messages are refused (returning them as errors) while the
+    /// children finish handling the messages that are already in
+    /// their mailbox. A child is stopped, calling its `after_stop`
+    /// callback, once it waits for a message with an empty mailbox
+    /// or once it called [`BastionContext::done`]. The children that
+    /// are still running once `timeout` elapsed are killed.
+    ///
+    /// This method returns `true` if every child stopped before
+    /// the deadline, or `false` otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long the children are given to stop
+    ///     before being killed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    ///
+    /// // Use bastion, spawn children and supervisors...
+    ///
+    /// Bastion::start();
+    /// // Send messages to children and/or do some
+    /// // work until you decide to stop the system...
+    ///
+    /// if !Bastion::shutdown_gracefully(Duration::from_secs(5)) {
+    ///     // Some children were killed before they were done...
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [`BastionContext::done`]: context/struct.BastionContext.html#method.done
+    pub fn shutdown_gracefully(timeout: Duration) -> bool {
+        debug!("Bastion: Shutting down gracefully.");
+        SYSTEM.start_draining();
+
+        let msg = BastionMessage::drain();
+        let envelope = Envelope::from_dead_letters(msg);
+        trace!("Bastion: Sending envelope: {:?}", envelope);
+        // FIXME: Err(Error)
+        SYSTEM.sender().unbounded_send(envelope).ok();
+
+        let drained = SYSTEM.wait_until_drained(timeout);
+        if drained {
+            Bastion::stop();
+        } else {
+            debug!("Bastion: Timed out while shutting down gracefully.");
+            Bastion::kill();
+        }
+
+        SYSTEM.wait_until_stopped();
+        drained
+    }
+
+    #[cfg(all(feature = "signals", not(target_os = "windows")))]
+    /// Installs the handlers of the signals of the process (see the
+    /// [`signals`] module): `SIGTERM` and `SIGINT` shut the system
+    /// down gracefully (see [`Bastion::shutdown_gracefully`]), while
+    /// `SIGHUP` reloads its configuration and notifies the children
+    /// subscribed with [`Signals::subscribe`].
+    ///
+    /// The system being stopped, a thread blocked on
+    /// [`Bastion::block_until_stopped`] (e.g. the main thread) can
+    /// then return.
+    ///
+    /// This method returns an error if the handlers couldn't be
+    /// installed.
+    ///
+    /// # Argument
+    ///
+    /// * `config` - The configuration of the handling of the signals.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    ///
+    /// let signals = SignalConfig::new().with_drain_timeout(Duration::from_secs(10));
+    /// Bastion::handle_signals(signals).expect("Couldn't handle the signals.");
+    ///
+    /// Bastion::start();
+    /// // Stops once the process receives SIGTERM or SIGINT...
+    /// # Bastion::stop();
+    /// Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`signals`]: signals/index.html
+    /// [`Bastion::shutdown_gracefully`]: #method.shutdown_gracefully
+    /// [`Signals::subscribe`]: signals/struct.Signals.html#method.subscribe
+    /// [`Bastion::block_until_stopped`]: #method.block_until_stopped
+    pub fn handle_signals(config: SignalConfig) -> io::Result<()> {
+        debug!("Bastion: Handling signals: {:?}", config);
+        signals::handle(config)
+    }
+
+    #[cfg(feature = "management")]
+    /// Starts the management server (see the [`management`] module)
+    /// on the given address, exposing the health, the supervision
+    /// tree and the metrics of the system, and allowing to pause,
+    /// resume or restart its children groups.
+    ///
+    /// This method returns the address the server is bound to (e.g.
+    /// when binding to port `0`), or an error if it couldn't be
+    /// bound.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to bind the server to.
+    /// * `config` - The configuration of the server, with the bearer
+    ///     token authenticating the requests.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    ///
+    /// let config = ManagementConfig::new("s3cr3t").expect("The bearer token is empty.");
+    /// let addr = Bastion::serve_management("127.0.0.1:0", config)
+    ///     .expect("Couldn't start the management server.");
+    /// println!("curl -H 'Authorization: Bearer s3cr3t' http://{}/tree", addr);
+    ///
+    /// Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`management`]: management/index.html
+    pub fn serve_management<A: ToSocketAddrs>(
+        addr: A,
+        config: ManagementConfig,
+    ) -> io::Result<SocketAddr> {
+        debug!("Bastion: Serving management with config: {:?}", config);
+        management::serve(addr, config)
+    }
+
     /// Blocks the current thread until the system is stopped
     /// (either by calling [`Bastion::stop()`] or
     /// [`Bastion::kill`]).
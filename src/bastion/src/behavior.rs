@@ -0,0 +1,84 @@
+//!
+//! Behaviors allow a child to implement a state machine by
+//! swapping the handler its messages are given to, instead of
+//! matching on a state in a single `msg!` block.
+//!
+//! Each child has a stack of behaviors: [`BastionContext::become_with`]
+//! pushes a new behavior that becomes the active one, while
+//! [`BastionContext::unbecome`] pops it to go back to the previous
+//! one. Messages are given to the active behavior by
+//! [`BastionContext::handle`] and [`BastionContext::run_behaviors`].
+//!
+//! [`BastionContext::become_with`]: ../context/struct.BastionContext.html#method.become_with
+//! [`BastionContext::unbecome`]: ../context/struct.BastionContext.html#method.unbecome
+//! [`BastionContext::handle`]: ../context/struct.BastionContext.html#method.handle
+//! [`BastionContext::run_behaviors`]: ../context/struct.BastionContext.html#method.run_behaviors
+use crate::context::BastionContext;
+use crate::envelope::SignedMessage;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+/// A handler of the messages received by a child, as pushed on its
+/// behaviors stack by [`BastionContext::become_with`].
+///
+/// [`BastionContext::become_with`]: ../context/struct.BastionContext.html#method.become_with
+pub struct Behavior {
+    handler: Arc<dyn Fn(&BastionContext, SignedMessage) + Send + Sync>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Behaviors {
+    stack: Mutex<Vec<Behavior>>,
+}
+
+impl Behavior {
+    /// Creates a new behavior giving the messages to `handler`.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - The closure handling the messages, which can
+    ///     use the context to answer them or to switch behavior.
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&BastionContext, SignedMessage) + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+        Behavior { handler }
+    }
+
+    pub(crate) fn handle(&self, ctx: &BastionContext, msg: SignedMessage) {
+        (self.handler)(ctx, msg)
+    }
+}
+
+impl Behaviors {
+    pub(crate) fn push(&self, behavior: Behavior) {
+        self.stack.lock().unwrap().push(behavior);
+    }
+
+    pub(crate) fn pop(&self) -> Option<Behavior> {
+        self.stack.lock().unwrap().pop()
+    }
+
+    /// Returns the active behavior. It is cloned so that the
+    /// stack isn't locked while the behavior handles a message,
+    /// which can make it switch behavior.
+    pub(crate) fn active(&self) -> Option<Behavior> {
+        self.stack.lock().unwrap().last().cloned()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.stack.lock().unwrap().len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.stack.lock().unwrap().is_empty()
+    }
+}
+
+impl Debug for Behavior {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Behavior").finish()
+    }
+}
@@ -0,0 +1,298 @@
+//!
+//! A dedicated pool running the blocking tasks of the system (those
+//! spawned using `blocking!`), configured using
+//! [`Config::with_blocking_pool`].
+//!
+//! The pool keeps at least its minimum amount of threads running,
+//! and starts new ones (up to its maximum) when all of them are
+//! busy. The threads above the minimum stop once they stayed idle
+//! for the pool's keep-alive duration. When a queue limit is set,
+//! the tasks spawned while the queue is full are rejected: their
+//! handle then resolves to `None`, as if they were cancelled.
+//!
+//! With the `metrics` feature, the amount of active blocking tasks
+//! and the time they spent queued are available in the metrics of
+//! the system (see the [`metrics`] module).
+//!
+//! [`Config::with_blocking_pool`]: ../struct.Config.html#method.with_blocking_pool
+//! [`metrics`]: ../metrics/index.html
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use lazy_static::lazy_static;
+use lightproc::lightproc::LightProc;
+use lightproc::proc_stack::ProcStack;
+use lightproc::recoverable_handle::RecoverableHandle;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, trace, warn};
+
+lazy_static! {
+    // The pool configured using `Config::with_blocking_pool`, if
+    // any.
+    static ref POOL: RwLock<Option<Arc<BlockingPool>>> = RwLock::new(None);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The configuration of the dedicated pool running the blocking
+/// tasks of the system, set using [`Config::with_blocking_pool`].
+///
+/// By default, the pool keeps 2 threads running, starts up to 512
+/// threads which stop after staying idle for 10 seconds, and
+/// doesn't limit the amount of queued tasks.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::time::Duration;
+/// #
+/// // Sized for heavy file I/O...
+/// let blocking_pool = BlockingPoolConfig::new()
+///     .with_min_threads(4)
+///     .with_max_threads(64)
+///     .with_keep_alive(Duration::from_secs(30))
+///     .with_queue_limit(1024);
+///
+/// let config = Config::new().with_blocking_pool(blocking_pool);
+/// ```
+///
+/// [`Config::with_blocking_pool`]: ../struct.Config.html#method.with_blocking_pool
+pub struct BlockingPoolConfig {
+    min_threads: usize,
+    max_threads: usize,
+    keep_alive: Duration,
+    queue_limit: Option<usize>,
+}
+
+#[derive(Debug)]
+// The threads of the pool and the tasks waiting for one of them.
+pub(crate) struct BlockingPool {
+    config: BlockingPoolConfig,
+    state: Mutex<State>,
+    available: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    queue: VecDeque<LightProc>,
+    threads: usize,
+    idle: usize,
+}
+
+impl BlockingPoolConfig {
+    /// Creates a new configuration with the default sizes.
+    pub fn new() -> Self {
+        BlockingPoolConfig::default()
+    }
+
+    /// Sets the amount of threads the pool keeps running, even
+    /// when they are idle.
+    ///
+    /// # Argument
+    ///
+    /// * `min_threads` - The minimum amount of threads of the pool.
+    pub fn with_min_threads(mut self, min_threads: usize) -> Self {
+        self.min_threads = min_threads;
+        self
+    }
+
+    /// Sets the maximum amount of threads running the tasks of the
+    /// pool at once (which is at least 1, and at least the minimum
+    /// amount of threads).
+    ///
+    /// # Argument
+    ///
+    /// * `max_threads` - The maximum amount of threads of the pool.
+    pub fn with_max_threads(mut self, max_threads: usize) -> Self {
+        self.max_threads = max_threads;
+        self
+    }
+
+    /// Sets how long the threads above the minimum amount can stay
+    /// idle before stopping.
+    ///
+    /// # Argument
+    ///
+    /// * `keep_alive` - How long idle threads are kept.
+    pub fn with_keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Sets the maximum amount of tasks waiting for a thread, above
+    /// which new tasks are rejected.
+    ///
+    /// # Argument
+    ///
+    /// * `queue_limit` - The maximum amount of queued tasks.
+    pub fn with_queue_limit(mut self, queue_limit: usize) -> Self {
+        self.queue_limit = Some(queue_limit);
+        self
+    }
+
+    /// Returns the amount of threads the pool keeps running.
+    pub fn min_threads(&self) -> usize {
+        self.min_threads
+    }
+
+    /// Returns the maximum amount of threads of the pool.
+    pub fn max_threads(&self) -> usize {
+        self.max_threads.max(self.min_threads).max(1)
+    }
+
+    /// Returns how long the threads above the minimum amount can
+    /// stay idle.
+    pub fn keep_alive(&self) -> Duration {
+        self.keep_alive
+    }
+
+    /// Returns the maximum amount of queued tasks, if any.
+    pub fn queue_limit(&self) -> Option<usize> {
+        self.queue_limit
+    }
+}
+
+impl Default for BlockingPoolConfig {
+    fn default() -> Self {
+        BlockingPoolConfig {
+            min_threads: 2,
+            max_threads: 512,
+            keep_alive: Duration::from_secs(10),
+            queue_limit: None,
+        }
+    }
+}
+
+impl BlockingPool {
+    fn new(config: BlockingPoolConfig) -> Arc<Self> {
+        let pool = Arc::new(BlockingPool {
+            config,
+            state: Mutex::new(State::default()),
+            available: Condvar::new(),
+        });
+
+        // FIXME: panics
+        let mut state = pool.state.lock().unwrap();
+        for _ in 0..pool.config.min_threads() {
+            pool.start_thread(&mut state);
+        }
+        drop(state);
+
+        pool
+    }
+
+    /// Spawns a blocking task onto the pool, rejecting it if the
+    /// queue is full.
+    pub(crate) fn spawn<F, R>(self: &Arc<Self>, future: F, stack: ProcStack) -> RecoverableHandle<R>
+    where
+        F: Future<Output = R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let pool = self.clone();
+        let schedule = move |proc: LightProc| pool.schedule(proc);
+        let (proc, recoverable) = LightProc::recoverable(future, schedule, stack);
+
+        // Only the spawned tasks are limited, not the ones woken up
+        // after having been polled.
+        if self.is_full() {
+            warn!("BlockingPool: Queue full, rejecting task.");
+            #[cfg(feature = "metrics")]
+            metrics::record_blocking_rejected();
+            // Dropping the process cancels it.
+            drop(proc);
+        } else {
+            proc.schedule();
+        }
+
+        recoverable
+    }
+
+    fn is_full(&self) -> bool {
+        match self.config.queue_limit() {
+            // FIXME: panics
+            Some(limit) => self.state.lock().unwrap().queue.len() >= limit,
+            None => false,
+        }
+    }
+
+    fn schedule(self: &Arc<Self>, proc: LightProc) {
+        // FIXME: panics
+        let mut state = self.state.lock().unwrap();
+        state.queue.push_back(proc);
+        if state.queue.len() > state.idle && state.threads < self.config.max_threads() {
+            self.start_thread(&mut state);
+        } else {
+            self.available.notify_one();
+        }
+    }
+
+    fn start_thread(self: &Arc<Self>, state: &mut State) {
+        state.threads += 1;
+        #[cfg(feature = "metrics")]
+        metrics::set_blocking_threads(state.threads);
+        trace!("BlockingPool: Starting thread #{}.", state.threads);
+
+        let pool = self.clone();
+        thread::Builder::new()
+            .name("bastion-blocking".to_string())
+            .spawn(move || pool.work())
+            .expect("cannot start a blocking thread");
+    }
+
+    fn work(&self) {
+        // FIXME: panics
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(proc) = state.queue.pop_front() {
+                drop(state);
+                proc.run();
+                // FIXME: panics
+                state = self.state.lock().unwrap();
+                continue;
+            }
+
+            state.idle += 1;
+            if state.threads > self.config.min_threads() {
+                let (waited, timeout) = self
+                    .available
+                    .wait_timeout(state, self.config.keep_alive())
+                    // FIXME: panics
+                    .unwrap();
+                state = waited;
+                state.idle -= 1;
+
+                if timeout.timed_out()
+                    && state.queue.is_empty()
+                    && state.threads > self.config.min_threads()
+                {
+                    state.threads -= 1;
+                    #[cfg(feature = "metrics")]
+                    metrics::set_blocking_threads(state.threads);
+                    trace!("BlockingPool: Stopping idle thread.");
+                    return;
+                }
+            } else {
+                // FIXME: panics
+                state = self.available.wait(state).unwrap();
+                state.idle -= 1;
+            }
+        }
+    }
+}
+
+pub(crate) fn set_pool(config: Option<BlockingPoolConfig>) {
+    if let Some(config) = &config {
+        debug!("BlockingPool: Starting with config: {:?}", config);
+    }
+
+    // FIXME: panics
+    *POOL.write().unwrap() = config.map(BlockingPool::new);
+}
+
+// FIXME: panics
+pub(crate) fn pool() -> Option<Arc<BlockingPool>> {
+    POOL.read().unwrap().clone()
+}
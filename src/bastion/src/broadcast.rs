@@ -1,27 +1,47 @@
 use crate::children_ref::ChildrenRef;
 use crate::context::BastionId;
+use crate::dead_letters;
 use crate::envelope::Envelope;
+use crate::mailbox::{Mailbox, OverflowStrategy};
 use crate::message::BastionMessage;
 use crate::path::{BastionPath, BastionPathElement};
 use crate::supervisor::SupervisorRef;
 use crate::system::SYSTEM;
+use crate::watch::StopReason;
 use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use futures::prelude::*;
 use fxhash::FxHashMap;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use tracing::trace;
 
-pub(crate) type Sender = UnboundedSender<Envelope>;
 pub(crate) type Receiver = UnboundedReceiver<Envelope>;
 
+#[derive(Debug, Clone)]
+pub(crate) struct Sender {
+    inner: UnboundedSender<Envelope>,
+    // The mailbox of the element the messages are sent to, if
+    // it is bounded and needs to be checked before sending user
+    // messages.
+    mailbox: Option<Arc<Mailbox>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct SendError {
+    env: Envelope,
+    full: bool,
+}
+
 #[derive(Debug)]
 pub(crate) struct Broadcast {
     sender: Sender,
     recver: Receiver,
     path: Arc<BastionPath>, // Arc is needed because we put path to Envelope
     parent: Parent,
-    children: FxHashMap<BastionId, Sender>,
+    // The senders of the registered children, along with their
+    // path (used when a message couldn't be delivered to them).
+    children: FxHashMap<BastionId, (Sender, Arc<BastionPath>)>,
 }
 
 #[derive(Debug, Clone)]
@@ -42,9 +62,122 @@ impl Parent {
     }
 }
 
+impl Sender {
+    pub(crate) fn unbounded_send(&self, env: Envelope) -> Result<(), SendError> {
+        let mailbox = match &self.mailbox {
+            Some(mailbox) if matches!(env.msg, BastionMessage::Message(_)) => mailbox,
+            _ => {
+                return self
+                    .inner
+                    .unbounded_send(env)
+                    .map_err(|err| SendError::disconnected(err.into_inner()))
+            }
+        };
+
+        if !mailbox.try_reserve() {
+            if let OverflowStrategy::DropNewest = mailbox.config().overflow() {
+                match mailbox.path() {
+                    Some(path) => dead_letters::publish(path, env),
+                    None => trace!("Sender: Mailbox is full, dropping message: {:?}", env),
+                }
+
+                return Ok(());
+            }
+
+            return Err(SendError::full(env));
+        }
+
+        self.inner.unbounded_send(env).map_err(|err| {
+            mailbox.release();
+            SendError::disconnected(err.into_inner())
+        })
+    }
+
+    /// Sends the envelope, waiting for the mailbox it is sent to
+    /// to have room for it if its overflow strategy is
+    /// [`OverflowStrategy::Backpressure`].
+    pub(crate) async fn send(&self, mut env: Envelope) -> Result<(), SendError> {
+        loop {
+            future::poll_fn(|ctx| self.poll_ready(ctx)).await;
+
+            match self.unbounded_send(env) {
+                // Another sender took the room we were waiting for.
+                Err(err) if err.is_full() && self.applies_backpressure() => env = err.into_inner(),
+                res => return res,
+            }
+        }
+    }
+
+    /// Sends the envelope once [`poll_ready`] returned, even if
+    /// another sender took the room it was waiting for (which can
+    /// only make the mailbox exceed its capacity by the amount of
+    /// concurrent senders).
+    ///
+    /// [`poll_ready`]: #method.poll_ready
+    pub(crate) fn start_send(&self, env: Envelope) -> Result<(), SendError> {
+        let (mailbox, env) = match self.unbounded_send(env) {
+            // Only bounded mailboxes apply backpressure.
+            Err(err) if err.is_full() && self.applies_backpressure() => {
+                (self.mailbox.as_ref().unwrap(), err.into_inner())
+            }
+            res => return res,
+        };
+
+        mailbox.force_reserve();
+        self.inner.unbounded_send(env).map_err(|err| {
+            mailbox.release();
+            SendError::disconnected(err.into_inner())
+        })
+    }
+
+    pub(crate) fn poll_ready(&self, ctx: &mut Context) -> Poll<()> {
+        match &self.mailbox {
+            Some(mailbox) => mailbox.poll_ready(ctx),
+            None => Poll::Ready(()),
+        }
+    }
+
+    fn applies_backpressure(&self) -> bool {
+        self.mailbox
+            .as_ref()
+            .map(|mailbox| mailbox.applies_backpressure())
+            .unwrap_or(false)
+    }
+}
+
+impl From<UnboundedSender<Envelope>> for Sender {
+    fn from(inner: UnboundedSender<Envelope>) -> Self {
+        Sender {
+            inner,
+            mailbox: None,
+        }
+    }
+}
+
+impl SendError {
+    fn full(env: Envelope) -> Self {
+        SendError { env, full: true }
+    }
+
+    fn disconnected(env: Envelope) -> Self {
+        SendError { env, full: false }
+    }
+
+    /// Returns whether the envelope couldn't be sent because the
+    /// recipient's mailbox was full.
+    pub(crate) fn is_full(&self) -> bool {
+        self.full
+    }
+
+    pub(crate) fn into_inner(self) -> Envelope {
+        self.env
+    }
+}
+
 impl Broadcast {
     pub(crate) fn new(parent: Parent, element: BastionPathElement) -> Self {
         let (sender, recver) = mpsc::unbounded();
+        let sender = sender.into();
         let children = FxHashMap::default();
 
         let parent_path: BastionPath = match &parent {
@@ -73,6 +206,7 @@ impl Broadcast {
         assert!(parent.is_none() || parent.is_system());
 
         let (sender, recver) = mpsc::unbounded();
+        let sender = sender.into();
         let children = FxHashMap::default();
         let path = BastionPath::root();
         let path = Arc::new(path);
@@ -94,6 +228,16 @@ impl Broadcast {
         &self.sender
     }
 
+    /// Makes the sender of this broadcast check the given mailbox
+    /// before sending user messages. This needs to be called
+    /// before the sender gets cloned.
+    pub(crate) fn attach_mailbox(&mut self, mailbox: Arc<Mailbox>) {
+        mailbox.attach(self.path.clone());
+        if mailbox.is_bounded() {
+            self.sender.mailbox = Some(mailbox);
+        }
+    }
+
     pub(crate) fn path(&self) -> &Arc<BastionPath> {
         &self.path
     }
@@ -103,8 +247,17 @@ impl Broadcast {
     }
 
     pub(crate) fn register(&mut self, child: &Self) {
+        self.children.insert(
+            child.id().clone(),
+            (child.sender.clone(), child.path.clone()),
+        );
+    }
+
+    /// Returns the bounded mailboxes of the registered children.
+    pub(crate) fn children_mailboxes(&self) -> impl Iterator<Item = &Arc<Mailbox>> {
         self.children
-            .insert(child.id().clone(), child.sender.clone());
+            .values()
+            .filter_map(|(sender, _)| sender.mailbox.as_ref())
     }
 
     pub(crate) fn unregister(&mut self, id: &BastionId) {
@@ -132,7 +285,11 @@ impl Broadcast {
     }
 
     pub(crate) fn kill_child(&mut self, id: &BastionId) {
-        let msg = BastionMessage::kill();
+        self.kill_child_with(id, StopReason::Killed);
+    }
+
+    pub(crate) fn kill_child_with(&mut self, id: &BastionId, reason: StopReason) {
+        let msg = BastionMessage::kill_with(reason);
         let env = Envelope::new(msg, self.path.clone(), self.sender.clone());
         self.send_child(id, env);
 
@@ -140,7 +297,11 @@ impl Broadcast {
     }
 
     pub(crate) fn kill_children(&mut self) {
-        let msg = BastionMessage::kill();
+        self.kill_children_with(StopReason::Killed);
+    }
+
+    pub(crate) fn kill_children_with(&mut self, reason: StopReason) {
+        let msg = BastionMessage::kill_with(reason);
         let env = Envelope::new(msg, self.path.clone(), self.sender.clone());
         self.send_children(env);
 
@@ -171,18 +332,27 @@ impl Broadcast {
 
     pub(crate) fn send_child(&self, id: &BastionId, envelope: Envelope) {
         // FIXME: Err if None?
-        if let Some(child) = self.children.get(id) {
-            // FIXME: handle errors
-            child.unbounded_send(envelope).ok();
+        match self.children.get(id) {
+            Some((child, path)) => {
+                if let Err(err) = child.unbounded_send(envelope) {
+                    dead_letters::publish(path.clone(), err.into_inner());
+                }
+            }
+            None => dead_letters::publish(self.path.clone(), envelope),
         }
     }
 
     pub(crate) fn send_children(&self, env: Envelope) {
-        for child in self.children.values() {
+        if self.children.is_empty() {
+            return dead_letters::publish(self.path.clone(), env);
+        }
+
+        for (child, path) in self.children.values() {
             // FIXME: Err(Error) if None
             if let Some(env) = env.try_clone() {
-                // FIXME: handle errors
-                child.unbounded_send(env).ok();
+                if let Err(err) = child.unbounded_send(env) {
+                    dead_letters::publish(path.clone(), err.into_inner());
+                }
             }
         }
     }
@@ -252,7 +422,8 @@ impl Stream for Broadcast {
 mod tests {
     use super::{BastionMessage, Broadcast, Parent};
     use crate::context::{BastionId, NIL_ID};
-    use crate::envelope::Envelope;
+    use crate::envelope::{Envelope, SignedMessage};
+    use crate::mailbox::{Mailbox, MailboxConfig, OverflowStrategy};
     use crate::path::{BastionPath, BastionPathElement};
     use futures::channel::mpsc;
     use futures::executor;
@@ -288,7 +459,7 @@ mod tests {
                     .append(BastionPathElement::Children(NIL_ID))
                     .unwrap(),
             ),
-            sender,
+            sender.into(),
         );
 
         parent.send_children(env.try_clone().unwrap());
@@ -328,4 +499,45 @@ mod tests {
             }
         });
     }
+
+    #[test]
+    fn drop_oldest_with_a_full_channel() {
+        let mut child = Broadcast::new(
+            Parent::System,
+            BastionPathElement::Supervisor(BastionId::new()),
+        );
+        let config = MailboxConfig::bounded(2).with_overflow(OverflowStrategy::DropOldest);
+        let mailbox = Arc::new(Mailbox::new(config));
+        child.attach_mailbox(mailbox.clone());
+
+        // The messages are all still in the channel when the
+        // mailbox overflows...
+        let sender = child.sender().clone();
+        for msg in &["first", "second", "third"] {
+            let env = Envelope::new(
+                BastionMessage::tell(*msg),
+                child.path().clone(),
+                sender.clone(),
+            );
+            sender.unbounded_send(env).unwrap();
+        }
+
+        // ...so the oldest one is dropped once received.
+        executor::block_on(async {
+            while let Poll::Ready(Some(env)) = poll!(child.next()) {
+                let msg = match env.msg {
+                    BastionMessage::Message(msg) => msg,
+                    _ => panic!(),
+                };
+                mailbox.push(SignedMessage::new(msg, env.sign), env.priority);
+            }
+        });
+
+        assert_eq!(mailbox.len(), 2);
+        assert!(!mailbox.has_room());
+        for expected in &["second", "third"] {
+            let (msg, _) = mailbox.pop().unwrap().extract();
+            assert_eq!(msg.downcast::<&'static str>().unwrap(), *expected);
+        }
+    }
 }
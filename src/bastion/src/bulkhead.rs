@@ -0,0 +1,260 @@
+//!
+//! A bulkhead bounds the amount of "asked" messages the elements
+//! of a children group handle at once, so that a single caller
+//! flooding the group can't exhaust it for all the others.
+//!
+//! An asked message is in flight from the moment it is delivered
+//! until it is answered (or until its answer can't be sent anymore).
+//! The asked messages delivered once the bulkhead is full are
+//! queued, up to the bulkhead's queue capacity, and delivered as
+//! soon as others are answered. The ones delivered once the queue
+//! is full too are rejected: their answers resolve to an error and
+//! they are forwarded to the dead letters.
+//!
+//! Told and broadcasted messages aren't bounded by bulkheads.
+//!
+//! Bulkheads are attached to a children group using
+//! [`Children::with_bulkhead`].
+//!
+//! [`Children::with_bulkhead`]: ../children/struct.Children.html#method.with_bulkhead
+use crate::child_ref::ChildRef;
+use crate::dead_letters;
+use crate::envelope::{Envelope, SignedMessage};
+use crate::middleware::{Middleware, Next};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tracing::trace;
+
+#[derive(Debug, Clone)]
+/// A bulkhead run around the delivery of the messages sent to
+/// the elements of a children group.
+///
+/// Clones of a bulkhead share its state.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// // Handles at most 8 asked messages at once, and queues 32 more...
+/// let bulkhead = Bulkhead::new(8).with_max_queued(32);
+///
+/// Bastion::children(|children| {
+///     children
+///         .with_redundancy(4)
+///         .with_bulkhead(bulkhead.clone())
+///         .with_exec(|ctx| async move {
+///             loop {
+///                 msg! { ctx.recv().await?,
+///                     query: &'static str =!> {
+///                         // Query the database...
+///                         answer!(ctx, "rows").ok();
+///                     };
+///                     _: _ => ();
+///                 }
+///             }
+///         })
+/// }).expect("Couldn't create the children group.");
+///
+/// assert_eq!(bulkhead.in_flight(), 0);
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub struct Bulkhead {
+    max_in_flight: usize,
+    max_queued: usize,
+    shared: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+// Held by the sender of the answer of an in-flight message,
+// releasing its slot once it is dropped.
+pub(crate) struct Permit(Bulkhead);
+
+#[derive(Debug, Default)]
+struct State {
+    in_flight: usize,
+    // The messages waiting for a slot, with the child they were
+    // delivered to.
+    queue: VecDeque<(SignedMessage, ChildRef)>,
+}
+
+impl Bulkhead {
+    /// Creates a new bulkhead which doesn't queue any message.
+    ///
+    /// # Argument
+    ///
+    /// * `max_in_flight` - The maximum amount of asked messages
+    ///     the group's elements handle at once (at least `1`).
+    pub fn new(max_in_flight: usize) -> Self {
+        Bulkhead {
+            max_in_flight: max_in_flight.max(1),
+            max_queued: 0,
+            shared: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// Sets the maximum amount of asked messages waiting for
+    /// others to be answered, above which they are rejected.
+    ///
+    /// # Argument
+    ///
+    /// * `max_queued` - The capacity of the bulkhead's queue.
+    pub fn with_max_queued(mut self, max_queued: usize) -> Self {
+        self.max_queued = max_queued;
+        self
+    }
+
+    /// Returns the maximum amount of asked messages handled at
+    /// once.
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    /// Returns the capacity of the bulkhead's queue.
+    pub fn max_queued(&self) -> usize {
+        self.max_queued
+    }
+
+    /// Returns the amount of asked messages currently in flight.
+    pub fn in_flight(&self) -> usize {
+        // FIXME: panics
+        self.shared.lock().unwrap().in_flight
+    }
+
+    /// Returns the amount of asked messages currently waiting for
+    /// others to be answered.
+    pub fn queued(&self) -> usize {
+        // FIXME: panics
+        self.shared.lock().unwrap().queue.len()
+    }
+
+    // Frees the slot of an answered message, giving it to the
+    // first queued message if there is one.
+    fn release(&self) {
+        let queued = {
+            // FIXME: panics
+            let mut state = self.shared.lock().unwrap();
+            match state.queue.pop_front() {
+                Some(queued) => queued,
+                None => {
+                    state.in_flight -= 1;
+                    return;
+                }
+            }
+        };
+
+        let (mut msg, child) = queued;
+        trace!("Bulkhead: Delivering queued message to: {}", child.path());
+        msg.msg.set_permit(Permit(self.clone()));
+        if let Err(env) = child.send(Envelope::from_signed(msg)) {
+            dead_letters::publish(child.path().clone(), env);
+        }
+    }
+
+    fn reject(&self, mut msg: SignedMessage, next: Next) {
+        trace!("Bulkhead: Rejecting message.");
+        // The answer of an asked message resolves to an error once
+        // its sender is dropped.
+        msg.msg.take_sender();
+        dead_letters::publish_message(next.path().clone(), msg.msg, msg.sign);
+    }
+}
+
+impl Middleware for Bulkhead {
+    fn around(&self, mut msg: SignedMessage, next: Next) {
+        // Queued messages already hold the slot they were given.
+        if !msg.msg.is_ask() || msg.msg.has_permit() {
+            return next.run(msg);
+        }
+
+        // FIXME: panics
+        let mut state = self.shared.lock().unwrap();
+        if state.in_flight < self.max_in_flight {
+            state.in_flight += 1;
+            drop(state);
+
+            msg.msg.set_permit(Permit(self.clone()));
+            next.run(msg);
+        } else if state.queue.len() < self.max_queued {
+            trace!("Bulkhead: Queuing message.");
+            state.queue.push_back((msg, next.child().clone()));
+        } else {
+            drop(state);
+            self.reject(msg, next);
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::BastionId;
+    use crate::envelope::RefAddr;
+    use crate::message::Msg;
+    use crate::middleware::{self, Chain};
+    use crate::path::BastionPath;
+    use futures::channel::mpsc;
+
+    fn asked() -> SignedMessage {
+        let (sender, _) = mpsc::unbounded();
+        let sign = RefAddr::new(Arc::new(BastionPath::root()), sender.into());
+        let (msg, _) = Msg::ask("query");
+
+        SignedMessage::new(msg, sign)
+    }
+
+    #[test]
+    fn bounds_the_in_flight_asks() {
+        let bulkhead = Bulkhead::new(2).with_max_queued(1);
+        let mut chain = Chain::default();
+        chain.push(Arc::new(bulkhead.clone()));
+
+        let (sender, _recver) = mpsc::unbounded();
+        let path = Arc::new(BastionPath::root());
+        let child = ChildRef::new(BastionId::new(), sender.into(), "child".to_string(), path);
+
+        let mut delivered = Vec::new();
+        for _ in 0..4 {
+            middleware::deliver(&child, &chain, asked(), |msg| delivered.push(msg));
+        }
+
+        // Two messages are in flight, one is queued and the last
+        // one was rejected.
+        assert_eq!(delivered.len(), 2);
+        assert_eq!(bulkhead.in_flight(), 2);
+        assert_eq!(bulkhead.queued(), 1);
+
+        // Answering a message delivers the queued one...
+        delivered.pop();
+        assert_eq!(bulkhead.in_flight(), 2);
+        assert_eq!(bulkhead.queued(), 0);
+
+        // ...and answering the others frees their slots.
+        delivered.pop();
+        assert_eq!(bulkhead.in_flight(), 1);
+    }
+}
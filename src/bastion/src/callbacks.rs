@@ -1,5 +1,12 @@
+use crate::errors::ChildError;
+use crate::path::BastionPath;
+use crate::watch::StopReason;
+use fxhash::FxHashMap;
+use std::any::{Any, TypeId};
+use std::error::Error;
 use std::fmt::{self, Debug, Formatter};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -60,9 +67,85 @@ pub(crate) enum CallbackType {
 /// [`Children`]: children/struct.Children.html
 pub struct Callbacks {
     before_start: Option<Arc<dyn Fn() + Send + Sync>>,
-    before_restart: Option<Arc<dyn Fn() + Send + Sync>>,
-    after_restart: Option<Arc<dyn Fn() + Send + Sync>>,
-    after_stop: Option<Arc<dyn Fn() + Send + Sync>>,
+    before_restart: Option<Arc<dyn Fn(&StateBag, &ChildFailure) + Send + Sync>>,
+    after_restart: Option<Arc<dyn Fn(&StateBag, &ChildFailure) + Send + Sync>>,
+    after_stop: Option<Arc<dyn Fn(&StopReason) + Send + Sync>>,
+}
+
+#[derive(Default, Clone)]
+/// A set of values, indexed by their type, that an element of a
+/// children group hands off to its next incarnation when it is
+/// restarted.
+///
+/// The same `StateBag` is available to every incarnation of an
+/// element using [`BastionContext::state_bag`], and is given to
+/// the callbacks defined using [`Callbacks::with_before_restart_state`]
+/// and [`Callbacks::with_after_restart_state`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();    
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();    
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// struct Cursor(u64);
+///
+/// Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| {
+///         async move {
+///             // Resumes where the previous incarnation stopped...
+///             let mut cursor = ctx.state_bag().take::<Cursor>().unwrap_or(Cursor(0));
+///
+///             loop {
+///                 let _: SignedMessage = ctx.recv().await?;
+///                 cursor.0 += 1;
+///                 // ...and saves where this one stopped.
+///                 ctx.state_bag().insert(Cursor(cursor.0));
+///             }
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`BastionContext::state_bag`]: context/struct.BastionContext.html#method.state_bag
+/// [`Callbacks::with_before_restart_state`]: struct.Callbacks.html#method.with_before_restart_state
+/// [`Callbacks::with_after_restart_state`]: struct.Callbacks.html#method.with_after_restart_state
+pub struct StateBag {
+    values: Arc<Mutex<FxHashMap<TypeId, Box<dyn Any + Send>>>>,
+}
+
+#[derive(Debug, Clone)]
+/// Describes why an element of a children group is restarted, given
+/// to the callbacks defined using [`Callbacks::with_before_restart_failure`]
+/// and [`Callbacks::with_after_restart_failure`].
+///
+/// [`Callbacks::with_before_restart_failure`]: struct.Callbacks.html#method.with_before_restart_failure
+/// [`Callbacks::with_after_restart_failure`]: struct.Callbacks.html#method.with_after_restart_failure
+pub struct ChildFailure {
+    path: Arc<BastionPath>,
+    reason: StopReason,
+    error: Option<ChildError>,
+    restarts: usize,
+    failed_at: Instant,
+    downtime: Option<Duration>,
 }
 
 impl Callbacks {
@@ -256,11 +339,80 @@ impl Callbacks {
     /// [`Supervisor`]: supervisor/struct.Supervisor.html
     /// [`Children`]: children/struct.Children.html
     /// [`with_after_stop`]: #method.with_after_stop
-    pub fn with_before_restart<C>(mut self, before_restart: C) -> Self
+    pub fn with_before_restart<C>(self, before_restart: C) -> Self
     where
         C: Fn() + Send + Sync + 'static,
     {
-        let before_restart = Arc::new(before_restart);
+        self.with_before_restart_state(move |_: &StateBag| before_restart())
+    }
+
+    /// Sets the method that will get called before the [`Supervisor`]
+    /// or [`Children`] is reset, like [`with_before_restart`], given
+    /// the [`StateBag`] its next incarnation is going to receive.
+    ///
+    /// Note that this replaces the callback defined using
+    /// [`with_before_restart`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// struct Restarts(u32);
+    ///
+    /// let callbacks = Callbacks::new()
+    ///     .with_before_restart_state(|state: &StateBag| {
+    ///         let restarts = state.take::<Restarts>().map(|r| r.0).unwrap_or(0);
+    ///         state.insert(Restarts(restarts + 1));
+    ///     });
+    /// ```
+    ///
+    /// [`Supervisor`]: supervisor/struct.Supervisor.html
+    /// [`Children`]: children/struct.Children.html
+    /// [`StateBag`]: struct.StateBag.html
+    /// [`with_before_restart`]: #method.with_before_restart
+    pub fn with_before_restart_state<C>(mut self, before_restart: C) -> Self
+    where
+        C: Fn(&StateBag) + Send + Sync + 'static,
+    {
+        let before_restart =
+            Arc::new(move |state: &StateBag, _: &ChildFailure| before_restart(state));
+        self.before_restart = Some(before_restart);
+        self
+    }
+
+    /// Sets the method that will get called before the [`Supervisor`]
+    /// or [`Children`] is reset, like [`with_before_restart`], given
+    /// the [`ChildFailure`] describing why it is restarted (e.g.
+    /// the message it panicked with).
+    ///
+    /// Note that this replaces the callback defined using
+    /// [`with_before_restart`] or [`with_before_restart_state`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let callbacks = Callbacks::new()
+    ///     .with_before_restart_failure(|failure: &ChildFailure| {
+    ///         if let Some(message) = failure.panic_message() {
+    ///             eprintln!("{} panicked: {}", failure.path(), message);
+    ///         }
+    ///     });
+    /// ```
+    ///
+    /// [`Supervisor`]: supervisor/struct.Supervisor.html
+    /// [`Children`]: children/struct.Children.html
+    /// [`ChildFailure`]: struct.ChildFailure.html
+    /// [`with_before_restart`]: #method.with_before_restart
+    /// [`with_before_restart_state`]: #method.with_before_restart_state
+    pub fn with_before_restart_failure<C>(mut self, before_restart: C) -> Self
+    where
+        C: Fn(&ChildFailure) + Send + Sync + 'static,
+    {
+        let before_restart =
+            Arc::new(move |_: &StateBag, failure: &ChildFailure| before_restart(failure));
         self.before_restart = Some(before_restart);
         self
     }
@@ -330,11 +482,90 @@ impl Callbacks {
     /// [`Supervisor`]: supervisor/struct.Supervisor.html
     /// [`Children`]: children/struct.Children.html
     /// [`with_before_start`]: #method.with_before_start
-    pub fn with_after_restart<C>(mut self, after_restart: C) -> Self
+    pub fn with_after_restart<C>(self, after_restart: C) -> Self
     where
         C: Fn() + Send + Sync + 'static,
     {
-        let after_restart = Arc::new(after_restart);
+        self.with_after_restart_state(move |_: &StateBag| after_restart())
+    }
+
+    /// Sets the method that will get called before the [`Supervisor`]
+    /// or [`Children`] is launched after being restarted, like
+    /// [`with_after_restart`], given the [`StateBag`] its previous
+    /// incarnation left.
+    ///
+    /// Note that this replaces the callback defined using
+    /// [`with_after_restart`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// #[derive(Clone)]
+    /// struct Restarts(u32);
+    ///
+    /// let callbacks = Callbacks::new()
+    ///     .with_after_restart_state(|state: &StateBag| {
+    ///         if let Some(Restarts(restarts)) = state.get::<Restarts>() {
+    ///             println!("Restarted {} times.", restarts);
+    ///         }
+    ///     });
+    /// ```
+    ///
+    /// [`Supervisor`]: supervisor/struct.Supervisor.html
+    /// [`Children`]: children/struct.Children.html
+    /// [`StateBag`]: struct.StateBag.html
+    /// [`with_after_restart`]: #method.with_after_restart
+    pub fn with_after_restart_state<C>(mut self, after_restart: C) -> Self
+    where
+        C: Fn(&StateBag) + Send + Sync + 'static,
+    {
+        let after_restart =
+            Arc::new(move |state: &StateBag, _: &ChildFailure| after_restart(state));
+        self.after_restart = Some(after_restart);
+        self
+    }
+
+    /// Sets the method that will get called before the [`Supervisor`]
+    /// or [`Children`] is launched after being restarted, like
+    /// [`with_after_restart`], given the [`ChildFailure`] it recovered
+    /// from, which also contains how many times it was restarted and
+    /// for how long it was down.
+    ///
+    /// This is called before the restarted element handles any
+    /// message, allowing to warm up the resources it needs.
+    ///
+    /// Note that this replaces the callback defined using
+    /// [`with_after_restart`] or [`with_after_restart_state`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let callbacks = Callbacks::new()
+    ///     .with_after_restart_failure(|failure: &ChildFailure| {
+    ///         println!(
+    ///             "{} restarted {} times, down for {:?}.",
+    ///             failure.path(),
+    ///             failure.restarts(),
+    ///             failure.downtime(),
+    ///         );
+    ///     });
+    /// ```
+    ///
+    /// [`Supervisor`]: supervisor/struct.Supervisor.html
+    /// [`Children`]: children/struct.Children.html
+    /// [`ChildFailure`]: struct.ChildFailure.html
+    /// [`with_after_restart`]: #method.with_after_restart
+    /// [`with_after_restart_state`]: #method.with_after_restart_state
+    pub fn with_after_restart_failure<C>(mut self, after_restart: C) -> Self
+    where
+        C: Fn(&ChildFailure) + Send + Sync + 'static,
+    {
+        let after_restart =
+            Arc::new(move |_: &StateBag, failure: &ChildFailure| after_restart(failure));
         self.after_restart = Some(after_restart);
         self
     }
@@ -406,9 +637,40 @@ impl Callbacks {
     /// [`Supervisor`]: supervisor/struct.Supervisor.html
     /// [`Children`]: children/struct.Children.html
     /// [`with_before_restart`]: #method.with_before_restart
-    pub fn with_after_stop<C>(mut self, after_stop: C) -> Self
+    pub fn with_after_stop<C>(self, after_stop: C) -> Self
     where
         C: Fn() + Send + Sync + 'static,
+    {
+        self.with_after_stop_reason(move |_: &StopReason| after_stop())
+    }
+
+    /// Sets the method that will get called after the [`Supervisor`]
+    /// or [`Children`] is stopped or killed, like [`with_after_stop`],
+    /// given the [`StopReason`] why it terminated.
+    ///
+    /// Note that this replaces the callback defined using
+    /// [`with_after_stop`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let callbacks = Callbacks::new()
+    ///     .with_after_stop_reason(|reason: &StopReason| {
+    ///         if *reason == StopReason::ShutdownTimeout {
+    ///             eprintln!("Children group killed before being drained.");
+    ///         }
+    ///     });
+    /// ```
+    ///
+    /// [`Supervisor`]: supervisor/struct.Supervisor.html
+    /// [`Children`]: children/struct.Children.html
+    /// [`StopReason`]: watch/enum.StopReason.html
+    /// [`with_after_stop`]: #method.with_after_stop
+    pub fn with_after_stop_reason<C>(mut self, after_stop: C) -> Self
+    where
+        C: Fn(&StopReason) + Send + Sync + 'static,
     {
         let after_stop = Arc::new(after_stop);
         self.after_stop = Some(after_stop);
@@ -493,25 +755,29 @@ impl Callbacks {
         }
     }
 
-    pub(crate) fn before_restart(&self) {
+    pub(crate) fn before_restart(&self, state: &StateBag, failure: &ChildFailure) {
         if let Some(before_restart) = &self.before_restart {
-            before_restart()
+            before_restart(state, failure)
         } else {
-            self.after_stop()
+            self.after_stop_with(failure.reason())
         }
     }
 
-    pub(crate) fn after_restart(&self) {
+    pub(crate) fn after_restart(&self, state: &StateBag, failure: &ChildFailure) {
         if let Some(after_restart) = &self.after_restart {
-            after_restart()
+            after_restart(state, failure)
         } else {
             self.before_start()
         }
     }
 
     pub(crate) fn after_stop(&self) {
+        self.after_stop_with(&StopReason::Normal)
+    }
+
+    pub(crate) fn after_stop_with(&self, reason: &StopReason) {
         if let Some(after_stop) = &self.after_stop {
-            after_stop()
+            after_stop(reason)
         }
     }
 }
@@ -526,3 +792,208 @@ impl Debug for Callbacks {
             .finish()
     }
 }
+
+impl StateBag {
+    /// Creates a new empty `StateBag`.
+    pub fn new() -> Self {
+        StateBag::default()
+    }
+
+    /// Stores a value in the bag, returning the value of the same
+    /// type that it replaced, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to hand off to the next incarnation.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let state = StateBag::new();
+    ///
+    /// assert_eq!(state.insert(1u64), None);
+    /// assert_eq!(state.insert(2u64), Some(1));
+    /// ```
+    pub fn insert<T: Send + 'static>(&self, value: T) -> Option<T> {
+        // FIXME: panics?
+        self.values
+            .lock()
+            .unwrap()
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Returns a clone of the value of type `T` stored in the bag,
+    /// if any.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let state = StateBag::new();
+    /// state.insert("cursor".to_string());
+    ///
+    /// assert_eq!(state.get::<String>(), Some("cursor".to_string()));
+    /// assert_eq!(state.get::<u64>(), None);
+    /// ```
+    pub fn get<T: Clone + Send + 'static>(&self) -> Option<T> {
+        // FIXME: panics?
+        self.values
+            .lock()
+            .unwrap()
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+            .cloned()
+    }
+
+    /// Removes the value of type `T` from the bag and returns it,
+    /// if any.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let state = StateBag::new();
+    /// state.insert(1u64);
+    ///
+    /// assert_eq!(state.take::<u64>(), Some(1));
+    /// assert_eq!(state.take::<u64>(), None);
+    /// ```
+    pub fn take<T: Send + 'static>(&self) -> Option<T> {
+        // FIXME: panics?
+        self.values
+            .lock()
+            .unwrap()
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Returns whether a value of type `T` is stored in the bag.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let state = StateBag::new();
+    /// state.insert(1u64);
+    ///
+    /// assert!(state.contains::<u64>());
+    /// assert!(!state.contains::<u32>());
+    /// ```
+    pub fn contains<T: Send + 'static>(&self) -> bool {
+        // FIXME: panics?
+        self.values.lock().unwrap().contains_key(&TypeId::of::<T>())
+    }
+}
+
+impl ChildFailure {
+    pub(crate) fn new(path: Arc<BastionPath>, reason: StopReason, restarts: usize) -> Self {
+        ChildFailure {
+            path,
+            reason,
+            error: None,
+            restarts,
+            failed_at: Instant::now(),
+            downtime: None,
+        }
+    }
+
+    pub(crate) fn with_error(mut self, error: ChildError) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// Returns a copy of this failure as seen once the element was
+    /// restarted.
+    pub(crate) fn restarted(&self) -> Self {
+        let mut failure = self.clone();
+        failure.restarts += 1;
+        failure.downtime = Some(self.failed_at.elapsed());
+        failure
+    }
+
+    /// Returns the path of the element that failed.
+    pub fn path(&self) -> &Arc<BastionPath> {
+        &self.path
+    }
+
+    /// Returns why the element terminated.
+    pub fn reason(&self) -> &StopReason {
+        &self.reason
+    }
+
+    /// Returns the error of the element's future, with the message
+    /// and backtrace of its panic if it panicked, or `None` if it
+    /// was killed.
+    pub fn error(&self) -> Option<&ChildError> {
+        self.error.as_ref()
+    }
+
+    /// Returns the typed error the element's future returned, if it
+    /// was defined using [`Children::with_typed_exec`] and returned
+    /// an error of type `E`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::io;
+    /// #
+    /// let callbacks = Callbacks::new()
+    ///     .with_before_restart_failure(|failure: &ChildFailure| {
+    ///         if let Some(err) = failure.downcast_error::<io::Error>() {
+    ///             eprintln!("{} failed with an I/O error: {}", failure.path(), err);
+    ///         }
+    ///     });
+    /// ```
+    ///
+    /// [`Children::with_typed_exec`]: children/struct.Children.html#method.with_typed_exec
+    pub fn downcast_error<E: Error + 'static>(&self) -> Option<&E> {
+        match &self.error {
+            Some(ChildError::Error(err)) => err.downcast_ref::<E>(),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the element panicked, rather than returning
+    /// an error or being killed.
+    pub fn is_panic(&self) -> bool {
+        matches!(self.reason, StopReason::Panic(_))
+    }
+
+    /// Returns the message the element panicked with, if it panicked
+    /// with a string payload.
+    pub fn panic_message(&self) -> Option<&str> {
+        match &self.reason {
+            StopReason::Panic(message) => message.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns how many times the element was restarted, including
+    /// the restart this failure causes once it happened.
+    pub fn restarts(&self) -> usize {
+        self.restarts
+    }
+
+    /// Returns for how long the element was down, or `None` if it
+    /// wasn't restarted yet.
+    pub fn downtime(&self) -> Option<Duration> {
+        self.downtime
+    }
+}
+
+impl Debug for StateBag {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("StateBag")
+            .field("len", &self.values.lock().unwrap().len())
+            .finish()
+    }
+}
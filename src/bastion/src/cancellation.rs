@@ -0,0 +1,200 @@
+//!
+//! Cancellation tokens tied to the lifecycle of the children.
+//!
+//! Every incarnation of a child gets a [`CancellationToken`]
+//! (see [`BastionContext::cancellation_token`]), which is cancelled
+//! as soon as the child is asked to stop or is about to be
+//! restarted. Long-running futures (HTTP requests, database
+//! queries...) can race against it to be aborted cleanly, instead
+//! of being dropped at some arbitrary point of their poll.
+//!
+//! [`CancellationToken`]: struct.CancellationToken.html
+//! [`BastionContext::cancellation_token`]: ../context/struct.BastionContext.html#method.cancellation_token
+use futures::future::{self, Either};
+use futures::pin_mut;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Default, Clone)]
+/// A token which can be cancelled once, waking up all the futures
+/// waiting for it.
+///
+/// The token given by [`BastionContext::cancellation_token`] is
+/// cancelled by the system when its child is stopped or restarted,
+/// but tokens can also be created and cancelled manually.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// let token = CancellationToken::new();
+/// assert!(!token.is_cancelled());
+///
+/// token.cancel();
+/// assert!(token.is_cancelled());
+///
+/// let output = run!(token.run_until_cancelled(async { 42 }));
+/// assert_eq!(output, None);
+/// # }
+/// ```
+///
+/// [`BastionContext::cancellation_token`]: ../context/struct.BastionContext.html#method.cancellation_token
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    // The tasks waiting for the token to be cancelled.
+    wakers: Mutex<Vec<Waker>>,
+}
+
+/// A future resolving once its [`CancellationToken`] is
+/// cancelled, returned by [`CancellationToken::cancelled`].
+///
+/// [`CancellationToken`]: struct.CancellationToken.html
+/// [`CancellationToken::cancelled`]: struct.CancellationToken.html#method.cancelled
+pub struct Cancelled {
+    token: CancellationToken,
+}
+
+impl CancellationToken {
+    /// Creates a new token, which isn't cancelled.
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Cancels the token, waking up all the futures waiting for
+    /// it. Cancelling a token more than once has no effect.
+    pub fn cancel(&self) {
+        if self.inner.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        // FIXME: panics
+        let wakers = std::mem::take(&mut *self.inner.wakers.lock().unwrap());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Returns whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns a future resolving once the token is cancelled
+    /// (right away if it already was).
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled {
+            token: self.clone(),
+        }
+    }
+
+    /// Runs the future until it completes or the token is
+    /// cancelled, whichever comes first.
+    ///
+    /// This method returns the output of the future, or `None` if
+    /// the token was cancelled before it completed (in which case
+    /// the future is dropped).
+    ///
+    /// # Argument
+    ///
+    /// * `future` - The future to run.
+    pub async fn run_until_cancelled<F>(&self, future: F) -> Option<F::Output>
+    where
+        F: Future,
+    {
+        if self.is_cancelled() {
+            return None;
+        }
+
+        pin_mut!(future);
+        match future::select(future, self.cancelled()).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+}
+
+impl Debug for CancellationToken {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl Future for Cancelled {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let inner = &self.token.inner;
+        if inner.cancelled.load(Ordering::SeqCst) {
+            return Poll::Ready(());
+        }
+
+        // FIXME: panics
+        let mut wakers = inner.wakers.lock().unwrap();
+        // The token might have been cancelled while locking.
+        if inner.cancelled.load(Ordering::SeqCst) {
+            return Poll::Ready(());
+        }
+
+        if !wakers.iter().any(|waker| waker.will_wake(ctx.waker())) {
+            wakers.push(ctx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Debug for Cancelled {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Cancelled")
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::task::noop_waker;
+
+    #[test]
+    fn wakes_up_once_cancelled() {
+        let waker = noop_waker();
+        let mut ctx = Context::from_waker(&waker);
+
+        let token = CancellationToken::new();
+        let mut cancelled = token.cancelled();
+        assert!(Pin::new(&mut cancelled).poll(&mut ctx).is_pending());
+        assert!(Pin::new(&mut cancelled).poll(&mut ctx).is_pending());
+        assert_eq!(token.inner.wakers.lock().unwrap().len(), 1);
+
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        assert!(token.inner.wakers.lock().unwrap().is_empty());
+        assert!(Pin::new(&mut cancelled).poll(&mut ctx).is_ready());
+        assert!(Pin::new(&mut token.cancelled()).poll(&mut ctx).is_ready());
+    }
+}
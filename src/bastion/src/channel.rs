@@ -0,0 +1,100 @@
+//!
+//! Channel bridges deliver the items of `tokio` channels to the
+//! mailboxes of the system (see `ChildrenRef::attach_receiver` and
+//! `ChildRef::subscribe_to_broadcast`).
+//!
+//! Every bridge runs as a forwarder child under its own
+//! supervisor, which restarts it if it fails without losing the
+//! channel. A forwarder stops once its channel is closed, or once
+//! the child it delivers to stopped.
+use crate::child_ref::ChildRef;
+use crate::children_ref::ChildrenRef;
+use crate::context::BastionContext;
+use crate::message::Message;
+use crate::source::Feeder;
+use crate::supervisor::SupervisorRef;
+use crate::Bastion;
+use futures::lock::Mutex;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use tracing::{debug, trace, warn};
+
+pub(crate) fn attach_receiver<M: Message>(
+    group: &ChildrenRef,
+    receiver: mpsc::Receiver<M>,
+) -> Result<SupervisorRef, ()> {
+    let receiver = Arc::new(Mutex::new(receiver));
+    let group = group.id().clone();
+
+    let supervisor = Bastion::supervisor(|sp| sp)?;
+    supervisor.children(|children| {
+        children
+            .with_name("channel-forwarder")
+            .with_exec(move |_: BastionContext| {
+                let receiver = receiver.clone();
+                let mut feeder = Feeder::new(group.clone());
+                let group = group.clone();
+                async move {
+                    // The receiver is shared by the incarnations of
+                    // the forwarder.
+                    let mut receiver = receiver.lock().await;
+                    while let Some(msg) = receiver.recv().await {
+                        trace!("ChannelForwarder({}): Forwarding: {:?}", group, msg);
+                        feeder.feed(msg).await;
+                    }
+
+                    debug!("ChannelForwarder({}): Channel closed.", group);
+                    Ok(())
+                }
+            })
+    })?;
+
+    Ok(supervisor)
+}
+
+pub(crate) fn subscribe_to_broadcast<M: Message + Clone>(
+    child: &ChildRef,
+    sender: &broadcast::Sender<M>,
+) -> Result<SupervisorRef, ()> {
+    let receiver = Arc::new(Mutex::new(sender.subscribe()));
+    let child = child.clone();
+
+    let supervisor = Bastion::supervisor(|sp| sp)?;
+    supervisor.children(|children| {
+        children
+            .with_name("broadcast-forwarder")
+            .with_exec(move |_: BastionContext| {
+                let receiver = receiver.clone();
+                let child = child.clone();
+                async move {
+                    let mut receiver = receiver.lock().await;
+                    loop {
+                        let msg = match receiver.recv().await {
+                            Ok(msg) => msg,
+                            Err(RecvError::Lagged(skipped)) => {
+                                warn!(
+                                    "BroadcastForwarder({}): Skipped {} messages.",
+                                    child.id(),
+                                    skipped
+                                );
+                                continue;
+                            }
+                            Err(RecvError::Closed) => {
+                                debug!("BroadcastForwarder({}): Channel closed.", child.id());
+                                return Ok(());
+                            }
+                        };
+
+                        trace!("BroadcastForwarder({}): Forwarding: {:?}", child.id(), msg);
+                        if child.tell_anonymously_async(msg).await.is_err() {
+                            debug!("BroadcastForwarder({}): Child stopped.", child.id());
+                            return Ok(());
+                        }
+                    }
+                }
+            })
+    })?;
+
+    Ok(supervisor)
+}
@@ -0,0 +1,313 @@
+//!
+//! Fault injection, to verify that a supervision tree recovers from
+//! failures.
+//!
+//! Once [`enable`]d with a [`ChaosConfig`], every message sent to a
+//! child can randomly make it panic, be dropped (and published to the
+//! dead letters) or be delivered late, and (with the `remote` feature)
+//! the connections to the partitioned nodes stop sending and receiving
+//! frames. The faults are picked using a random number generator
+//! seeded by the configuration, and [`stats`] tells how many of them
+//! were injected.
+//!
+//! [`enable`]: fn.enable.html
+//! [`ChaosConfig`]: struct.ChaosConfig.html
+//! [`stats`]: fn.stats.html
+#[cfg(feature = "remote")]
+use crate::remote::NodeId;
+use lazy_static::lazy_static;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tracing::info;
+
+#[derive(Debug, Clone, PartialEq)]
+/// The faults to inject, and how often.
+///
+/// By default, no fault is injected.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::time::Duration;
+/// #
+/// let config = ChaosConfig::new(42)
+///     .with_panic_probability(0.01)
+///     .with_drop_probability(0.05)
+///     .with_delay(0.1, Duration::from_millis(200));
+///
+/// bastion::chaos::enable(config);
+/// # bastion::chaos::disable();
+/// ```
+pub struct ChaosConfig {
+    seed: u64,
+    panic_probability: f64,
+    drop_probability: f64,
+    delay_probability: f64,
+    max_delay: Duration,
+    #[cfg(feature = "remote")]
+    partitions: Vec<NodeId>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// How many faults were injected since chaos was last enabled.
+pub struct ChaosStats {
+    /// The amount of children made to panic.
+    pub panics: u64,
+    /// The amount of messages dropped.
+    pub drops: u64,
+    /// The amount of messages delivered late.
+    pub delays: u64,
+}
+
+/// A fault to inject into the delivery of a message.
+pub(crate) enum Fault {
+    Panic,
+    Drop,
+    Delay(Duration),
+}
+
+struct Chaos {
+    config: ChaosConfig,
+    rng: Mutex<StdRng>,
+    panics: AtomicU64,
+    drops: AtomicU64,
+    delays: AtomicU64,
+}
+
+impl Chaos {
+    fn new(config: ChaosConfig) -> Self {
+        Chaos {
+            rng: Mutex::new(StdRng::seed_from_u64(config.seed)),
+            config,
+            panics: AtomicU64::new(0),
+            drops: AtomicU64::new(0),
+            delays: AtomicU64::new(0),
+        }
+    }
+
+    fn pick(&self) -> Option<Fault> {
+        let config = &self.config;
+        // FIXME: panics
+        let mut rng = self.rng.lock().unwrap();
+        if rng.gen_bool(config.panic_probability) {
+            self.panics.fetch_add(1, Ordering::SeqCst);
+            Some(Fault::Panic)
+        } else if rng.gen_bool(config.drop_probability) {
+            self.drops.fetch_add(1, Ordering::SeqCst);
+            Some(Fault::Drop)
+        } else if rng.gen_bool(config.delay_probability) {
+            self.delays.fetch_add(1, Ordering::SeqCst);
+            let max = config.max_delay.as_millis() as u64;
+            Some(Fault::Delay(Duration::from_millis(rng.gen_range(0..=max))))
+        } else {
+            None
+        }
+    }
+
+    fn stats(&self) -> ChaosStats {
+        ChaosStats {
+            panics: self.panics.load(Ordering::SeqCst),
+            drops: self.drops.load(Ordering::SeqCst),
+            delays: self.delays.load(Ordering::SeqCst),
+        }
+    }
+}
+
+lazy_static! {
+    // The faults to inject, if chaos is enabled.
+    static ref CHAOS: RwLock<Option<Chaos>> = RwLock::new(None);
+}
+
+impl ChaosConfig {
+    /// Creates a configuration injecting no fault, whose faults
+    /// (once added) are picked using the given seed.
+    ///
+    /// # Argument
+    ///
+    /// * `seed` - The seed of the random number generator picking
+    ///     the faults, making a failing run reproducible.
+    pub fn new(seed: u64) -> Self {
+        ChaosConfig {
+            seed,
+            panic_probability: 0.0,
+            drop_probability: 0.0,
+            delay_probability: 0.0,
+            max_delay: Duration::from_secs(0),
+            #[cfg(feature = "remote")]
+            partitions: Vec::new(),
+        }
+    }
+
+    /// Sets the probability that a message makes the child it is
+    /// sent to panic (and thus fault) before handling it.
+    ///
+    /// # Argument
+    ///
+    /// * `probability` - The probability, between 0 and 1.
+    pub fn with_panic_probability(mut self, probability: f64) -> Self {
+        self.panic_probability = probability.max(0.0).min(1.0);
+        self
+    }
+
+    /// Sets the probability that a message is dropped instead of
+    /// being delivered (it is then published to the dead letters).
+    ///
+    /// # Argument
+    ///
+    /// * `probability` - The probability, between 0 and 1.
+    pub fn with_drop_probability(mut self, probability: f64) -> Self {
+        self.drop_probability = probability.max(0.0).min(1.0);
+        self
+    }
+
+    /// Sets the probability that a message is delivered late, and
+    /// how late it can be.
+    ///
+    /// # Arguments
+    ///
+    /// * `probability` - The probability, between 0 and 1.
+    /// * `max_delay` - The maximum delay, the actual one being
+    ///     random.
+    pub fn with_delay(mut self, probability: f64, max_delay: Duration) -> Self {
+        self.delay_probability = probability.max(0.0).min(1.0);
+        self.max_delay = max_delay;
+        self
+    }
+
+    #[cfg(feature = "remote")]
+    /// Partitions this node from another one: no frame is sent to
+    /// or received from it anymore, until chaos is disabled.
+    ///
+    /// # Argument
+    ///
+    /// * `node` - The identifier of the node to partition from.
+    pub fn with_partition(mut self, node: NodeId) -> Self {
+        if !self.partitions.contains(&node) {
+            self.partitions.push(node);
+        }
+
+        self
+    }
+
+    /// Returns the seed of the random number generator picking the
+    /// faults.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the probability that a message makes its child
+    /// panic.
+    pub fn panic_probability(&self) -> f64 {
+        self.panic_probability
+    }
+
+    /// Returns the probability that a message is dropped.
+    pub fn drop_probability(&self) -> f64 {
+        self.drop_probability
+    }
+
+    /// Returns the probability that a message is delivered late.
+    pub fn delay_probability(&self) -> f64 {
+        self.delay_probability
+    }
+
+    /// Returns the maximum delay of the messages delivered late.
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    #[cfg(feature = "remote")]
+    /// Returns the nodes this node is partitioned from.
+    pub fn partitions(&self) -> &[NodeId] {
+        &self.partitions
+    }
+}
+
+/// Starts injecting the faults of the configuration (replacing the
+/// one enabled before, if any) and resets the [`stats`].
+///
+/// # Argument
+///
+/// * `config` - The faults to inject, and how often.
+///
+/// [`stats`]: fn.stats.html
+pub fn enable(config: ChaosConfig) {
+    info!("Chaos: Enabling with seed {}.", config.seed);
+    // FIXME: panics
+    *CHAOS.write().unwrap() = Some(Chaos::new(config));
+}
+
+/// Stops injecting faults, healing the partitions.
+pub fn disable() {
+    info!("Chaos: Disabling.");
+    // FIXME: panics
+    CHAOS.write().unwrap().take();
+}
+
+/// Returns whether faults are being injected.
+pub fn is_enabled() -> bool {
+    // FIXME: panics
+    CHAOS.read().unwrap().is_some()
+}
+
+/// Returns how many faults were injected since chaos was last
+/// enabled (or nothing if it isn't).
+pub fn stats() -> Option<ChaosStats> {
+    // FIXME: panics
+    CHAOS.read().unwrap().as_ref().map(Chaos::stats)
+}
+
+/// Picks the fault to inject into the delivery of a message, if
+/// any.
+pub(crate) fn inject() -> Option<Fault> {
+    // FIXME: panics
+    CHAOS.read().unwrap().as_ref()?.pick()
+}
+
+#[cfg(feature = "remote")]
+/// Returns whether this node is partitioned from another one.
+pub(crate) fn is_partitioned(node: &NodeId) -> bool {
+    // FIXME: panics
+    match &*CHAOS.read().unwrap() {
+        Some(chaos) => chaos.config.partitions.contains(node),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn faults(chaos: &Chaos) -> Vec<u8> {
+        (0..100)
+            .map(|_| match chaos.pick() {
+                Some(Fault::Panic) => 1,
+                Some(Fault::Drop) => 2,
+                Some(Fault::Delay(_)) => 3,
+                None => 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replays_the_faults_of_a_seed() {
+        let config = ChaosConfig::new(7)
+            .with_panic_probability(0.2)
+            .with_drop_probability(0.2)
+            .with_delay(0.2, Duration::from_millis(10));
+
+        let chaos = Chaos::new(config.clone());
+        let first = faults(&chaos);
+        let stats = chaos.stats();
+        assert_eq!(
+            stats.panics + stats.drops + stats.delays,
+            first.iter().filter(|fault| **fault != 0).count() as u64
+        );
+
+        assert_eq!(faults(&Chaos::new(config)), first);
+    }
+}
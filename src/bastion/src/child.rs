@@ -1,22 +1,41 @@
 //!
 //! Child is a element of Children group executing user-defined computation
 use crate::broadcast::Broadcast;
-use crate::callbacks::{CallbackType, Callbacks};
+use crate::callbacks::{CallbackType, Callbacks, ChildFailure};
+use crate::cancellation::CancellationToken;
+use crate::chaos::{self, Fault};
 use crate::child_ref::ChildRef;
-use crate::context::{BastionContext, BastionId, ContextState};
-use crate::envelope::Envelope;
-use crate::message::BastionMessage;
+use crate::context::{BastionContext, BastionId, ContextState, NIL_ID};
+use crate::dead_letters;
+use crate::deadlock;
+use crate::envelope::{Envelope, SignedMessage};
+use crate::errors::ChildError;
+use crate::events::{self, SupervisionEvent};
+use crate::executor;
+use crate::local::LocalThread;
+use crate::mailbox::Priority;
+use crate::message::{BastionMessage, Msg};
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use crate::middleware::{self, Chain};
+use crate::panics;
+use crate::realtime::RealtimeThreads;
+#[cfg(feature = "remote")]
+use crate::remote;
 #[cfg(feature = "scaling")]
 use crate::resizer::ActorGroupStats;
+use crate::rt;
 use crate::system::SYSTEM;
+use crate::tree::{self, ChildStatus};
+use crate::watch::{ExitSignal, StopReason, Terminated};
 use anyhow::Result as AnyResult;
 
-use bastion_executor::pool;
 use futures::pending;
 use futures::poll;
 use futures::prelude::*;
 use lightproc::prelude::*;
 use lightproc::proc_state::EmptyProcState;
+use std::error::Error;
 use std::fmt::{self, Debug, Formatter};
 use std::future::Future;
 use std::pin::Pin;
@@ -25,7 +44,7 @@ use std::task::{Context, Poll};
 use tracing::{debug, error, trace, warn};
 
 pub(crate) struct Init(pub(crate) Box<dyn Fn(BastionContext) -> Exec + Send>);
-pub(crate) struct Exec(pub(crate) Pin<Box<dyn Future<Output = Result<(), ()>> + Send>>);
+pub(crate) struct Exec(pub(crate) Pin<Box<dyn Future<Output = Result<(), ChildError>> + Send>>);
 
 #[derive(Debug)]
 pub(crate) struct Child {
@@ -46,6 +65,35 @@ pub(crate) struct Child {
     pre_start_msgs: Vec<Envelope>,
     // A shortcut for accessing to this actor by others.
     child_ref: ChildRef,
+    // The children watching this child, which will be sent a
+    // `Terminated` message once it terminates.
+    watchers: Vec<ChildRef>,
+    // The children linked to this child, which will be stopped
+    // (or sent an `ExitSignal` message) once it terminates.
+    links: Vec<ChildRef>,
+    // Whether to receive an `ExitSignal` message instead of
+    // being stopped when a linked child terminates.
+    trap_exits: bool,
+    // The middleware run around the delivery of every message
+    // to this child.
+    middleware: Chain,
+    // Whether the system is shutting down gracefully, in which
+    // case the child stops once its mailbox is drained.
+    draining: bool,
+    // The name the child can be found with, from
+    // `Bastion::child_by_name`.
+    name: Option<String>,
+    // The pinned threads this child runs on, instead of the
+    // executor.
+    realtime: Option<RealtimeThreads>,
+    // The scheduling class of this child.
+    priority: ProcPriority,
+    // The token of this incarnation of the child, cancelled once
+    // it stops or faults (or is dropped).
+    cancellation: CancellationToken,
+    // Whether the chaos configuration picked this child to panic
+    // the next time its future is polled.
+    chaos_panic: bool,
     started: bool,
 }
 
@@ -56,7 +104,7 @@ impl Init {
         F: Future<Output = Result<(), ()>> + Send + 'static,
     {
         let init = Box::new(move |ctx: BastionContext| {
-            let fut = init(ctx);
+            let fut = init(ctx).map_err(|()| ChildError::Failed);
             let exec = Box::pin(fut);
 
             Exec(exec)
@@ -64,6 +112,41 @@ impl Init {
 
         Init(init)
     }
+
+    pub(crate) fn with_typed<C, F, E>(init: C) -> Self
+    where
+        C: Fn(BastionContext) -> F + Send + 'static,
+        F: Future<Output = Result<(), E>> + Send + 'static,
+        E: Error + Send + Sync + 'static,
+    {
+        let init = Box::new(move |ctx: BastionContext| {
+            let fut = init(ctx).map_err(|err| ChildError::Error(Arc::new(err)));
+            let exec = Box::pin(fut);
+
+            Exec(exec)
+        });
+
+        Init(init)
+    }
+
+    /// Creates the closure of a group whose futures don't need to be
+    /// `Send`, which are created and polled on the given thread.
+    pub(crate) fn local<C, F>(thread: LocalThread, init: C) -> Self
+    where
+        C: Fn(BastionContext) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<(), ()>> + 'static,
+    {
+        let init = Arc::new(init);
+        let init = Box::new(move |ctx: BastionContext| {
+            let init = init.clone();
+            let fut = thread.run(move || init(ctx));
+            let exec = Box::pin(async move { fut.await?.map_err(|()| ChildError::Failed) });
+
+            Exec(exec)
+        });
+
+        Init(init)
+    }
 }
 
 impl Child {
@@ -76,6 +159,16 @@ impl Child {
     ) -> Self {
         debug!("Child({}): Initializing.", bcast.id());
         let pre_start_msgs = Vec::new();
+        let watchers = Vec::new();
+        let links = Vec::new();
+        let trap_exits = false;
+        let middleware = Chain::default();
+        let draining = false;
+        let name = None;
+        let realtime = None;
+        let priority = ProcPriority::default();
+        let cancellation = CancellationToken::new();
+        let chaos_panic = false;
         let started = false;
 
         Child {
@@ -85,10 +178,50 @@ impl Child {
             state,
             pre_start_msgs,
             child_ref,
+            watchers,
+            links,
+            trap_exits,
+            middleware,
+            draining,
+            name,
+            realtime,
+            priority,
+            cancellation,
+            chaos_panic,
             started,
         }
     }
 
+    pub(crate) fn with_trap_exits(mut self, trap_exits: bool) -> Self {
+        self.trap_exits = trap_exits;
+        self
+    }
+
+    pub(crate) fn with_middleware(mut self, middleware: Chain) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    pub(crate) fn with_name(mut self, name: Option<String>) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub(crate) fn with_realtime(mut self, realtime: Option<RealtimeThreads>) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    pub(crate) fn with_priority(mut self, priority: ProcPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub(crate) fn with_cancellation_token(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
     fn stack(&self) -> ProcStack {
         trace!("Child({}): Creating ProcStack.", self.id());
         let id = self.bcast.id().clone();
@@ -101,7 +234,8 @@ impl Child {
         let child_ref_inner = self.child_ref.clone();
 
         // FIXME: with_pid
-        ProcStack::default().with_after_panic(move |_state: &mut EmptyProcState| {
+        let stack = ProcStack::default().with_priority(self.priority);
+        stack.with_after_panic(move |_state: &mut EmptyProcState| {
             warn!("Child({}): Panicked.", id);
 
             if let Some(parent) = &parent_inner {
@@ -122,15 +256,105 @@ impl Child {
         self.bcast.id()
     }
 
-    fn stopped(&mut self) {
+    fn stopped(&mut self, reason: StopReason) {
         debug!("Child({}): Stopped.", self.id());
+        self.cancellation.cancel();
+        let path = self.bcast.path().clone();
+        #[cfg(feature = "remote")]
+        remote::unregister_child(&path);
+        self.unregister(ChildStatus::Stopped);
+        events::emit(SupervisionEvent::ChildStopped {
+            path,
+            reason: reason.clone(),
+        });
+        self.notify_watchers(reason);
         self.remove_from_dispatchers();
+        self.forward_to_dead_letters();
         self.bcast.stopped();
     }
 
-    fn faulted(&mut self) {
+    async fn stop(&mut self) {
+        self.stopped(StopReason::Normal);
+
+        #[cfg(feature = "scaling")]
+        self.cleanup_actors_stats().await;
+
+        self.callbacks.after_stop();
+    }
+
+    /// Stops making the child findable by name or selectable, and
+    /// marks it with the status in the supervision tree.
+    fn unregister(&self, status: ChildStatus) {
+        tree::set_child_status(self.id(), status);
+        if let Some(name) = &self.name {
+            SYSTEM.unregister_name(name, self.id());
+        }
+    }
+
+    /// Returns whether this child is the dead letters actor, which
+    /// keeps running until the system is stopped.
+    fn is_dead_letters(&self) -> bool {
+        match self.bcast.parent().clone().into_children() {
+            Some(parent) => parent.id() == &NIL_ID,
+            None => false,
+        }
+    }
+
+    /// Forwards the messages that are still in the child's mailbox
+    /// or stash (and that thus will never be received) to the dead
+    /// letters.
+    fn forward_to_dead_letters(&mut self) {
+        let path = self.bcast.path().clone();
+        self.state.mailbox().unstash_all();
+        while let Some(msg) = self.state.pop_message() {
+            let (msg, sign) = msg.extract();
+            dead_letters::publish_message(path.clone(), msg, sign);
+        }
+
+        for env in self.pre_start_msgs.drain(..) {
+            dead_letters::publish(path.clone(), env);
+        }
+    }
+
+    /// Sends a `Terminated` message to all the children watching
+    /// this child.
+    fn notify_watchers(&mut self, reason: StopReason) {
+        for watcher in self.watchers.drain(..) {
+            let terminated = Terminated(self.bcast.path().clone(), reason.clone());
+            let msg = BastionMessage::tell(terminated);
+            let env = Envelope::new(msg, self.bcast.path().clone(), self.bcast.sender().clone());
+            // The watcher might already be terminated.
+            watcher.send(env).ok();
+        }
+
+        for link in self.links.drain(..) {
+            let msg = BastionMessage::exit(self.child_ref.clone(), reason.clone());
+            let env = Envelope::new(msg, self.bcast.path().clone(), self.bcast.sender().clone());
+            // The linked child might already be terminated.
+            link.send(env).ok();
+        }
+    }
+
+    /// Describes a failure of the child, that happened now.
+    fn failure(&self, reason: StopReason) -> ChildFailure {
+        ChildFailure::new(self.bcast.path().clone(), reason, self.state.restarts())
+    }
+
+    fn before_restart(&self, failure: ChildFailure) {
+        self.callbacks
+            .before_restart(self.state.state_bag(), &failure);
+        self.state.set_failure(failure);
+    }
+
+    fn faulted(&mut self, failure: ChildFailure) {
         debug!("Child({}): Faulted.", self.id());
+        self.cancellation.cancel();
+        #[cfg(feature = "remote")]
+        remote::unregister_child(self.bcast.path());
+        self.unregister(ChildStatus::Restarting);
+        self.notify_watchers(failure.reason().clone());
         self.remove_from_dispatchers();
+        self.before_restart(failure);
 
         let parent = self.bcast.parent().clone().into_children().unwrap();
         let path = self.bcast.path().clone();
@@ -142,7 +366,50 @@ impl Child {
         parent.send(env).ok();
     }
 
+    /// Injects the fault picked by the chaos configuration (if
+    /// any) into the delivery of a message, returning the envelope
+    /// if it still needs to be handled now.
+    fn inject_fault(&mut self, env: Envelope) -> Option<Envelope> {
+        let is_message = matches!(env.msg, BastionMessage::Message(_));
+        if !is_message || self.is_dead_letters() {
+            return Some(env);
+        }
+
+        match chaos::inject() {
+            Some(Fault::Panic) => {
+                debug!("Child({}): Chaos picked the child to panic.", self.id());
+                self.chaos_panic = true;
+                Some(env)
+            }
+            Some(Fault::Drop) => {
+                debug!("Child({}): Chaos dropped a message.", self.id());
+                dead_letters::publish(self.bcast.path().clone(), env);
+                None
+            }
+            Some(Fault::Delay(delay)) => {
+                debug!(
+                    "Child({}): Chaos delayed a message by {:?}.",
+                    self.id(),
+                    delay
+                );
+                let child_ref = self.child_ref.clone();
+                executor::schedule_once(delay, move || {
+                    // The child might already be terminated.
+                    child_ref.send(env).ok();
+                });
+
+                None
+            }
+            None => Some(env),
+        }
+    }
+
     async fn handle(&mut self, env: Envelope) -> Result<(), ()> {
+        let env = match self.inject_fault(env) {
+            Some(env) => env,
+            None => return Ok(()),
+        };
+
         match env {
             Envelope {
                 msg: BastionMessage::Start,
@@ -152,24 +419,45 @@ impl Child {
                 msg: BastionMessage::Stop,
                 ..
             } => {
-                self.stopped();
-
-                #[cfg(feature = "scaling")]
-                self.cleanup_actors_stats().await;
-
-                self.callbacks.after_stop();
+                self.stop().await;
                 return Err(());
             }
             Envelope {
-                msg: BastionMessage::Kill,
+                msg: BastionMessage::Drain,
+                ..
+            } => self.draining = !self.is_dead_letters(),
+            Envelope {
+                msg: BastionMessage::Pause,
+                ..
+            } => {
+                debug!("Child({}): Pausing.", self.id());
+                self.state.set_paused(true);
+            }
+            Envelope {
+                msg: BastionMessage::Resume,
                 ..
             } => {
-                self.stopped();
+                debug!("Child({}): Resuming.", self.id());
+                self.state.set_paused(false);
+            }
+            Envelope {
+                msg: BastionMessage::Restart,
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Reconfigure,
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Kill { reason },
+                ..
+            } => {
+                self.stopped(reason.clone());
 
                 #[cfg(feature = "scaling")]
                 self.cleanup_actors_stats().await;
 
-                self.callbacks.before_restart();
+                self.before_restart(self.failure(reason));
                 return Err(());
             }
             // FIXME
@@ -198,9 +486,22 @@ impl Child {
             Envelope {
                 msg: BastionMessage::Message(msg),
                 sign,
+                priority,
+                headers,
+                #[cfg(feature = "otel")]
+                span,
             } => {
                 debug!("Child({}): Received a message: {:?}", self.id(), msg);
-                self.state.push_message(msg, sign);
+                let msg = SignedMessage::new(msg, sign).with_headers(headers);
+                #[cfg(feature = "otel")]
+                let msg = msg.with_span(span);
+                let state = &self.state;
+                middleware::deliver(&self.child_ref, &self.middleware, msg, |msg| {
+                    state.push_message(msg, priority)
+                });
+
+                #[cfg(feature = "metrics")]
+                metrics::record_received(self.bcast.path(), self.state.mailbox().len());
             }
             Envelope {
                 msg: BastionMessage::RestartRequired { .. },
@@ -242,7 +543,64 @@ impl Child {
             Envelope {
                 msg: BastionMessage::Heartbeat,
                 ..
-            } => unreachable!(),
+            } => self.state.record_heartbeat(),
+            Envelope {
+                msg: BastionMessage::Watch { watcher },
+                ..
+            } => {
+                debug!("Child({}): Watched by: {:?}", self.id(), watcher.path());
+                if !self.watchers.contains(&watcher) {
+                    self.watchers.push(watcher);
+                }
+            }
+            Envelope {
+                msg: BastionMessage::Unwatch { id },
+                ..
+            } => {
+                debug!("Child({}): Unwatched by: {}", self.id(), id);
+                self.watchers.retain(|watcher| watcher.id() != &id);
+            }
+            Envelope {
+                msg: BastionMessage::Link { peer },
+                ..
+            } => {
+                debug!("Child({}): Linked to: {:?}", self.id(), peer.path());
+                if !self.links.contains(&peer) {
+                    self.links.push(peer);
+                }
+            }
+            Envelope {
+                msg: BastionMessage::Exit { from, reason },
+                sign,
+                ..
+            } => {
+                // The link might have been dropped by a restart.
+                if !self.links.contains(&from) {
+                    return Ok(());
+                }
+
+                self.links.retain(|link| link != &from);
+                if self.trap_exits {
+                    debug!("Child({}): Trapped exit of: {:?}", self.id(), from.path());
+                    let msg = Msg::tell(ExitSignal(from.path().clone(), reason));
+                    self.state
+                        .push_message(SignedMessage::new(msg, sign), Priority::Normal);
+                    return Ok(());
+                }
+
+                debug!(
+                    "Child({}): Linked child exited: {:?}",
+                    self.id(),
+                    from.path()
+                );
+                self.stopped(StopReason::Linked);
+
+                #[cfg(feature = "scaling")]
+                self.cleanup_actors_stats().await;
+
+                self.callbacks.after_stop_with(&StopReason::Linked);
+                return Err(());
+            }
         }
 
         Ok(())
@@ -257,6 +615,15 @@ impl Child {
         debug!("Child({}): Starting.", self.id());
         self.callbacks.before_start();
         self.started = true;
+        self.state.record_heartbeat();
+        let path = self.bcast.path().clone();
+        events::emit(SupervisionEvent::ChildStarted { path });
+        #[cfg(feature = "remote")]
+        remote::register_child(&self.child_ref);
+        tree::register_child(&self.child_ref, self.name.clone(), self.state.clone());
+        if let Some(name) = &self.name {
+            SYSTEM.register_name(name.clone(), self.child_ref.clone());
+        }
 
         let msgs = self.pre_start_msgs.drain(..).collect::<Vec<_>>();
         self.pre_start_msgs.shrink_to_fit();
@@ -278,8 +645,15 @@ impl Child {
     fn apply_callback(&mut self, callback_type: CallbackType) {
         match callback_type {
             CallbackType::BeforeStart => self.callbacks.before_start(),
-            CallbackType::BeforeRestart => self.callbacks.before_restart(),
-            CallbackType::AfterRestart => self.callbacks.after_restart(),
+            CallbackType::BeforeRestart => self.before_restart(self.failure(StopReason::Killed)),
+            CallbackType::AfterRestart => {
+                let failure = self
+                    .state
+                    .restarted()
+                    .unwrap_or_else(|| self.failure(StopReason::Killed));
+                self.callbacks
+                    .after_restart(self.state.state_bag(), &failure);
+            }
             CallbackType::AfterStop => self.callbacks.after_stop(),
         }
     }
@@ -306,6 +680,12 @@ impl Child {
             return;
         };
 
+        let _running = if self.is_dead_letters() {
+            None
+        } else {
+            Some(SYSTEM.running_child())
+        };
+
         loop {
             #[cfg(feature = "scaling")]
             self.update_stats().await;
@@ -362,28 +742,104 @@ impl Child {
                 continue;
             }
 
-            match poll!(&mut self.exec) {
-                Poll::Ready(Ok(())) => {
+            let polled = {
+                // The future is polled in the span of the message
+                // it is processing.
+                #[cfg(feature = "otel")]
+                let state = self.state.clone();
+                let exec = &mut self.exec;
+                let chaos_panic = &mut self.chaos_panic;
+                let path = self.bcast.path().clone();
+                poll!(future::poll_fn(move |ctx| {
+                    #[cfg(feature = "otel")]
+                    let _entered = state.enter_span();
+                    let chaos_panic = std::mem::take(&mut *chaos_panic);
+                    // Panics are caught to report their message and
+                    // backtrace, and every poll gets a new budget.
+                    match rt::budgeted(|| {
+                        panics::catch_unwind(|| {
+                            if chaos_panic {
+                                panic!("Chaos: Injected panic.");
+                            }
+
+                            // The messages asked while polling the
+                            // future are asked by this child.
+                            deadlock::polling(&path, || Pin::new(&mut *exec).poll(ctx))
+                        })
+                    }) {
+                        Ok(Poll::Ready(res)) => Poll::Ready(Ok(res)),
+                        Ok(Poll::Pending) => Poll::Pending,
+                        Err(error) => Poll::Ready(Err(error)),
+                    }
+                }))
+            };
+
+            // A poll blocking for too long is reported as soon as
+            // it returns.
+            if let Some((message_type, elapsed)) = self.state.check_slow_handler() {
+                let path = self.bcast.path().clone();
+                warn!(
+                    "Child({}): Handling a message of type {} took {:?}.",
+                    self.id(),
+                    message_type,
+                    elapsed
+                );
+                events::emit(SupervisionEvent::SlowHandler {
+                    path,
+                    message_type,
+                    elapsed,
+                });
+            }
+
+            match polled {
+                Poll::Ready(Ok(Ok(()))) => {
                     debug!(
                         "Child({}): The future finished executing successfully.",
                         self.id()
                     );
-                    return self.stopped();
+                    let reason = self.state.take_stop_reason();
+                    return self.stopped(reason.unwrap_or(StopReason::Normal));
                 }
-                Poll::Ready(Err(())) => {
+                Poll::Ready(Ok(Err(error))) => {
                     warn!("Child({}): The future returned an error.", self.id());
-                    return self.faulted();
+                    let path = self.bcast.path().clone();
+                    let failure = self.failure(StopReason::Failed).with_error(error.clone());
+                    self.state.record_failed();
+                    events::emit(SupervisionEvent::ChildFailed { path, error });
+                    return self.faulted(failure);
+                }
+                Poll::Ready(Err(error)) => {
+                    warn!("Child({}): Panicked.", self.id());
+                    let path = self.bcast.path().clone();
+                    let message = match &error {
+                        ChildError::Panic { message, .. } => message.clone(),
+                        _ => None,
+                    };
+                    let failure = self
+                        .failure(StopReason::Panic(message))
+                        .with_error(error.clone());
+                    self.state.record_failed();
+                    events::emit(SupervisionEvent::ChildPanicked { path, error });
+                    return self.faulted(failure);
                 }
                 Poll::Pending => (),
             }
 
+            if self.draining && self.state.is_drained() {
+                debug!("Child({}): Drained.", self.id());
+                return self.stop().await;
+            }
+
             pending!();
         }
     }
 
     pub(crate) fn launch(self) -> RecoverableHandle<()> {
         let stack = self.stack();
-        pool::spawn(self.run(), stack)
+        match self.realtime.clone() {
+            Some(realtime) => realtime.spawn(self.run(), stack),
+            None => executor::spawn_proc(self.run(), stack),
+        }
     }
 
     /// Adds the actor into each registry declared in the parent node.
@@ -417,11 +873,23 @@ impl Child {
             .actor_stats()
             .remove(&self.bcast.id().clone())
             .ok();
+        self.state
+            .actor_latencies()
+            .remove(&self.bcast.id().clone())
+            .ok();
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        // The child might be dropped without stopping or faulting
+        // (e.g. if its group was killed, or if it panicked).
+        self.cancellation.cancel();
     }
 }
 
 impl Future for Exec {
-    type Output = Result<(), ()>;
+    type Output = Result<(), ChildError>;
 
     fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
         Pin::new(&mut self.get_mut().0).poll(ctx)
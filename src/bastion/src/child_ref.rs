@@ -1,14 +1,29 @@
 //!
 //! Allows users to communicate with Child through the mailboxes.
 use crate::broadcast::Sender;
+#[cfg(feature = "tokio-channels")]
+use crate::channel;
 use crate::context::BastionId;
-use crate::envelope::{Envelope, RefAddr};
-use crate::message::{Answer, BastionMessage, Message};
+use crate::delivery::{self, DeliveryConfig, DeliveryId};
+use crate::envelope::{Envelope, Headers, RefAddr};
+use crate::errors::AskError;
+use crate::executor;
+use crate::mailbox::Priority;
+use crate::message::{Answer, AnswerStream, BastionMessage, Message, Request};
 use crate::path::BastionPath;
+#[cfg(feature = "tokio-channels")]
+use crate::supervisor::SupervisorRef;
+use crate::system;
+use crate::watch::StopReason;
+use futures::future::{self, Either};
+use futures::Sink;
 use std::cmp::{Eq, PartialEq};
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
 use tracing::{debug, trace};
 
 #[derive(Debug, Clone)]
@@ -215,6 +230,357 @@ impl ChildRef {
         self.send(env).map_err(|env| env.into_msg().unwrap())
     }
 
+    /// Sends a message to the child this `ChildRef` is referencing,
+    /// pushing it in the lane of its mailbox matching the given
+    /// [`Priority`].
+    ///
+    /// Messages with a higher priority will be received by
+    /// [`BastionContext::recv`] before the ones with a lower
+    /// priority, even if they were sent after them.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)`
+    /// otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send.
+    /// * `priority` - The [`Priority`] of the message.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    ///     # Bastion::init();
+    ///     # let children_ref =
+    /// // Create a new child...
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             loop {
+    ///                 msg! { ctx.recv().await?,
+    ///                     // ...which will receive "flush" before
+    ///                     // the work items that are still queued...
+    ///                     msg: &'static str => {
+    ///                         // Handle the message...
+    ///                     };
+    ///                     _: _ => ();
+    ///                 }
+    ///             }
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    ///     # let child_ref = &children_ref.elems()[0];
+    /// for _ in 0..100 {
+    ///     child_ref.tell_anonymously("work").expect("Couldn't send the message.");
+    /// }
+    /// child_ref
+    ///     .tell_anonymously_with_priority("flush", Priority::High)
+    ///     .expect("Couldn't send the message.");
+    ///     #
+    ///     # Bastion::start();
+    ///     # Bastion::stop();
+    ///     # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Priority`]: mailbox/enum.Priority.html
+    /// [`BastionContext::recv`]: context/struct.BastionContext.html#method.recv
+    pub fn tell_anonymously_with_priority<M: Message>(
+        &self,
+        msg: M,
+        priority: Priority,
+    ) -> Result<(), M> {
+        debug!(
+            "ChildRef({}): Telling message with priority {:?}: {:?}",
+            self.id(),
+            priority,
+            msg
+        );
+        let msg = BastionMessage::tell(msg);
+        let env = Envelope::from_dead_letters(msg).with_priority(priority);
+        // FIXME: panics?
+        self.send(env).map_err(|env| env.into_msg().unwrap())
+    }
+
+    /// Sends a message to the child this `ChildRef` is referencing,
+    /// along with the given [`Headers`], which the child can read
+    /// using [`SignedMessage::headers`].
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)`
+    /// otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send.
+    /// * `headers` - The headers to send along with the message.
+    ///
+    /// [`Headers`]: envelope/struct.Headers.html
+    /// [`SignedMessage::headers`]: envelope/struct.SignedMessage.html#method.headers
+    pub fn tell_anonymously_with_headers<M: Message>(
+        &self,
+        msg: M,
+        headers: Headers,
+    ) -> Result<(), M> {
+        debug!(
+            "ChildRef({}): Telling message with headers {:?}: {:?}",
+            self.id(),
+            headers,
+            msg
+        );
+        let msg = BastionMessage::tell(msg);
+        let env = Envelope::from_dead_letters(msg).with_headers(headers);
+        // FIXME: panics?
+        self.send(env).map_err(|env| env.into_msg().unwrap())
+    }
+
+    /// Sends a message to the child this `ChildRef` is referencing,
+    /// waiting for its mailbox to have room for it if it is bounded
+    /// and uses [`OverflowStrategy::Backpressure`].
+    ///
+    /// For other mailboxes, this behaves like [`tell_anonymously`].
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)`
+    /// otherwise.
+    ///
+    /// # Argument
+    ///
+    /// * `msg` - The message to send.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    ///     # Bastion::init();
+    ///     # let children_ref =
+    /// // Create a new child with a small mailbox...
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_mailbox(MailboxConfig::bounded(1).with_overflow(OverflowStrategy::Backpressure))
+    ///         .with_exec(|ctx: BastionContext| {
+    ///             async move {
+    ///                 loop {
+    ///                     let _ = ctx.recv().await?;
+    ///                 }
+    ///             }
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    ///     # Bastion::children(|children| {
+    ///         # children.with_exec(move |ctx: BastionContext| {
+    ///             # let child_ref = children_ref.elems()[0].clone();
+    ///             # async move {
+    /// // Later, the messages are "told" to the child, waiting for
+    /// // it to receive the previous ones...
+    /// for i in 0..8 {
+    ///     child_ref.tell_anonymously_async(i).await.expect("Couldn't send the message.");
+    /// }
+    ///                 #
+    ///                 # Ok(())
+    ///             # }
+    ///         # })
+    ///     # }).unwrap();
+    ///     #
+    ///     # Bastion::start();
+    ///     # Bastion::stop();
+    ///     # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`OverflowStrategy::Backpressure`]: mailbox/enum.OverflowStrategy.html#variant.Backpressure
+    /// [`tell_anonymously`]: #method.tell_anonymously
+    pub async fn tell_anonymously_async<M: Message>(&self, msg: M) -> Result<(), M> {
+        debug!(
+            "ChildRef({}): Telling message (async): {:?}",
+            self.id(),
+            msg
+        );
+        let msg = BastionMessage::tell(msg);
+        let env = Envelope::from_dead_letters(msg);
+        if system::refuses(&env) {
+            return Err(env.into_msg().unwrap());
+        }
+
+        // FIXME: panics?
+        self.sender
+            .send(env)
+            .await
+            .map_err(|err| err.into_inner().into_msg().unwrap())
+    }
+
+    /// Sends a message to the child this `ChildRef` is referencing,
+    /// redelivering it until the child acknowledges it with
+    /// [`BastionContext::ack`], using the default [`DeliveryConfig`].
+    ///
+    /// The child receives the message wrapped in a [`Delivery`],
+    /// containing the id it must be acknowledged with. Messages that
+    /// weren't acknowledged after being redelivered too many times
+    /// are forwarded to the dead letters.
+    ///
+    /// This method returns the [`DeliveryId`] of the message if it
+    /// succeeded, or `Err(msg)` otherwise.
+    ///
+    /// # Argument
+    ///
+    /// * `msg` - The message to send.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    ///     # Bastion::init();
+    ///     # let children_ref =
+    /// // Create a new child...
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             loop {
+    ///                 msg! { ctx.recv().await?,
+    ///                     // ...which will acknowledge the messages
+    ///                     // once they are handled...
+    ///                     delivery: Delivery<&'static str> => {
+    ///                         ctx.ack(delivery.id());
+    ///                     };
+    ///                     _: _ => ();
+    ///                 }
+    ///             }
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    ///     # let child_ref = &children_ref.elems()[0];
+    /// let id: DeliveryId = child_ref
+    ///     .tell_reliable("important")
+    ///     .expect("Couldn't send the message.");
+    ///     #
+    ///     # Bastion::start();
+    ///     # Bastion::stop();
+    ///     # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`BastionContext::ack`]: ../context/struct.BastionContext.html#method.ack
+    /// [`DeliveryConfig`]: ../delivery/struct.DeliveryConfig.html
+    /// [`Delivery`]: ../delivery/struct.Delivery.html
+    /// [`DeliveryId`]: ../delivery/struct.DeliveryId.html
+    pub fn tell_reliable<M: Message + Clone>(&self, msg: M) -> Result<DeliveryId, M> {
+        self.tell_reliable_with_config(msg, DeliveryConfig::default())
+    }
+
+    /// Sends a message to the child this `ChildRef` is referencing,
+    /// redelivering it until the child acknowledges it, like
+    /// [`tell_reliable`] but using the given [`DeliveryConfig`].
+    ///
+    /// This method returns the [`DeliveryId`] of the message if it
+    /// succeeded, or `Err(msg)` otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send.
+    /// * `config` - The configuration of the redeliveries.
+    ///
+    /// [`tell_reliable`]: #method.tell_reliable
+    /// [`DeliveryConfig`]: ../delivery/struct.DeliveryConfig.html
+    /// [`DeliveryId`]: ../delivery/struct.DeliveryId.html
+    pub fn tell_reliable_with_config<M: Message + Clone>(
+        &self,
+        msg: M,
+        config: DeliveryConfig,
+    ) -> Result<DeliveryId, M> {
+        debug!(
+            "ChildRef({}): Telling message reliably: {:?}",
+            self.id(),
+            msg
+        );
+        delivery::send(self, msg, config)
+    }
+
+    /// Subscribes the child this `ChildRef` is referencing to a
+    /// `tokio` [`broadcast`] channel, delivering every message sent
+    /// on the channel to its mailbox.
+    ///
+    /// The messages are delivered by a forwarder child, created
+    /// under a new supervisor, which stops once the channel is
+    /// closed or once the child stopped. Messages the child lagged
+    /// behind are skipped.
+    ///
+    /// This method returns the supervisor of the forwarder if it
+    /// succeeded, or `Err(())` otherwise.
+    ///
+    /// # Argument
+    ///
+    /// * `sender` - The sender of the channel to subscribe to.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use bastion::prelude::*;
+    /// # use tokio::sync::broadcast;
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// # Bastion::start();
+    /// #
+    /// # let children_ref = Bastion::children(|children| children).unwrap();
+    /// # let child_ref = &children_ref.elems()[0];
+    /// let (sender, _) = broadcast::channel::<&'static str>(16);
+    /// child_ref
+    ///     .subscribe_to_broadcast(&sender)
+    ///     .expect("Couldn't subscribe.");
+    ///
+    /// sender.send("Hello!").ok();
+    /// # }
+    /// ```
+    ///
+    /// [`broadcast`]: https://docs.rs/tokio/1/tokio/sync/broadcast/index.html
+    #[cfg(feature = "tokio-channels")]
+    pub fn subscribe_to_broadcast<M: Message + Clone>(
+        &self,
+        sender: &tokio::sync::broadcast::Sender<M>,
+    ) -> Result<SupervisorRef, ()> {
+        debug!("ChildRef({}): Subscribing to broadcast channel.", self.id());
+        channel::subscribe_to_broadcast(self, sender)
+    }
+
     /// Sends a message to the child this `ChildRef` is referencing,
     /// allowing it to answer.
     /// This message is intended to be used outside of Bastion context when
@@ -307,6 +673,12 @@ impl ChildRef {
     pub fn ask_anonymously<M: Message>(&self, msg: M) -> Result<Answer, M> {
         debug!("ChildRef({}): Asking message: {:?}", self.id(), msg);
         let (msg, answer) = BastionMessage::ask(msg);
+        // An ask that would deadlock isn't sent.
+        let answer = answer.track(self.path());
+        if answer.deadlock().is_some() {
+            return Ok(answer);
+        }
+
         let env = Envelope::from_dead_letters(msg);
         // FIXME: panics?
         self.send(env).map_err(|env| env.into_msg().unwrap())?;
@@ -314,6 +686,156 @@ impl ChildRef {
         Ok(answer)
     }
 
+    /// Sends a message to the child this `ChildRef` is referencing,
+    /// allowing it to answer it with many chunks using the
+    /// `answer_stream!` macro generated by [`msg!`].
+    ///
+    /// This method returns an [`AnswerStream`] yielding the chunks
+    /// if it succeeded, or `Err(msg)` otherwise.
+    ///
+    /// # Argument
+    ///
+    /// * `msg` - The message to send.
+    ///
+    /// [`msg!`]: ../macro.msg.html
+    /// [`AnswerStream`]: message/struct.AnswerStream.html
+    pub fn ask_stream_anonymously<M: Message>(&self, msg: M) -> Result<AnswerStream, M> {
+        debug!(
+            "ChildRef({}): Asking message as a stream: {:?}",
+            self.id(),
+            msg
+        );
+        let (msg, answer) = BastionMessage::ask_stream(msg);
+        let env = Envelope::from_dead_letters(msg);
+        // FIXME: panics?
+        self.send(env).map_err(|env| env.into_msg().unwrap())?;
+
+        Ok(answer)
+    }
+
+    /// Sends a typed [`Request`] to the child this `ChildRef` is
+    /// referencing and waits for its [`Request::Response`].
+    ///
+    /// Unlike [`ask_anonymously`], the answer is downcasted to the
+    /// request's response type, so that the caller doesn't need to
+    /// match on it.
+    ///
+    /// This method returns the response if it succeeded, or an
+    /// [`AskError`] otherwise.
+    ///
+    /// # Argument
+    ///
+    /// * `req` - The request to send.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    ///     # Bastion::init();
+    /// #[derive(Debug)]
+    /// struct Ping(u32);
+    /// #[derive(Debug, PartialEq)]
+    /// struct Pong(u32);
+    ///
+    /// impl Request for Ping {
+    ///     type Response = Pong;
+    /// }
+    ///
+    ///     # let children_ref =
+    /// // Create a new child...
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             // ...which will receive the request...
+    ///             msg! { ctx.recv().await?,
+    ///                 ping: Ping =!> {
+    ///                     // ...and reply to it with a `Pong`...
+    ///                     reply!(ctx, Pong(ping.0)).expect("Couldn't reply.");
+    ///                 };
+    ///                 _: _ => ();
+    ///             }
+    ///
+    ///             Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    ///     # Bastion::children(|children| {
+    ///         # children.with_exec(move |ctx: BastionContext| {
+    ///             # let child_ref = children_ref.elems()[0].clone();
+    ///             # async move {
+    /// // Later, the request is sent to the child and its response
+    /// // is received, already downcasted to a `Pong`...
+    /// let pong: Pong = child_ref.ask_typed(Ping(42)).await.expect("Couldn't ask.");
+    /// assert_eq!(pong, Pong(42));
+    ///                 #
+    ///                 # Ok(())
+    ///             # }
+    ///         # })
+    ///     # }).unwrap();
+    ///     #
+    ///     # Bastion::start();
+    ///     # Bastion::stop();
+    ///     # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Request`]: message/trait.Request.html
+    /// [`Request::Response`]: message/trait.Request.html#associatedtype.Response
+    /// [`ask_anonymously`]: #method.ask_anonymously
+    /// [`AskError`]: errors/enum.AskError.html
+    pub async fn ask_typed<R: Request>(&self, req: R) -> Result<R::Response, AskError> {
+        debug!("ChildRef({}): Asking typed request: {:?}", self.id(), req);
+        let answer = self
+            .ask_anonymously(req)
+            .map_err(|_| AskError::SendFailed)?;
+        if let Some(cycle) = answer.deadlock() {
+            return Err(AskError::DeadlockDetected(cycle.to_vec()));
+        }
+
+        let (msg, _) = answer.await.map_err(|_| AskError::NoAnswer)?.extract();
+
+        msg.downcast::<R::Response>()
+            .map_err(|_| AskError::UnexpectedResponse)
+    }
+
+    // Sends `msg` and waits at most `timeout` for an answer of
+    // type `R`.
+    pub(crate) async fn ask_timeout<R: Message, M: Message>(
+        &self,
+        msg: M,
+        timeout: Duration,
+    ) -> Result<R, AskError> {
+        let answer = self
+            .ask_anonymously(msg)
+            .map_err(|_| AskError::SendFailed)?;
+        if let Some(cycle) = answer.deadlock() {
+            return Err(AskError::DeadlockDetected(cycle.to_vec()));
+        }
+
+        let answer = match future::select(answer, executor::sleep(timeout)).await {
+            Either::Left((answer, _)) => answer.map_err(|_| AskError::NoAnswer)?,
+            Either::Right(_) => return Err(AskError::Timeout(timeout)),
+        };
+        let (msg, _) = answer.extract();
+
+        msg.downcast::<R>()
+            .map_err(|_| AskError::UnexpectedResponse)
+    }
+
     /// Sends a message to the child this `ChildRef` is referencing
     /// to tell it to stop its execution.
     ///
@@ -414,6 +936,72 @@ impl ChildRef {
         self.send(env).map_err(|_| ())
     }
 
+    /// Links the child this `ChildRef` is referencing to the one
+    /// `other` is referencing, so that once one of them terminates,
+    /// the other one is stopped too (or receives an [`ExitSignal`]
+    /// message if it traps exits, see [`Children::with_trap_exits`]).
+    ///
+    /// If `other` is already terminated, the child this `ChildRef`
+    /// is referencing is notified right away, with
+    /// [`StopReason::Unreachable`].
+    ///
+    /// This method returns `()` if it succeeded, or `Err(())`
+    /// otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The child to link to.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// # let children_ref = Bastion::children(|children| children.with_redundancy(2)).unwrap();
+    /// # let child_ref = &children_ref.elems()[0];
+    /// # let other = &children_ref.elems()[1];
+    /// child_ref.link(other).expect("Couldn't link the children.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`ExitSignal`]: ../watch/struct.ExitSignal.html
+    /// [`Children::with_trap_exits`]: ../children/struct.Children.html#method.with_trap_exits
+    /// [`StopReason::Unreachable`]: ../watch/enum.StopReason.html#variant.Unreachable
+    pub fn link(&self, other: &ChildRef) -> Result<(), ()> {
+        debug!("ChildRef({}): Linking to: {}", self.id(), other.id());
+        let msg = BastionMessage::link(other.clone());
+        let env = Envelope::new(msg, other.path.clone(), other.sender.clone());
+        self.send(env).map_err(|_| ())?;
+
+        let msg = BastionMessage::link(self.clone());
+        let env = Envelope::new(msg, self.path.clone(), self.sender.clone());
+        if other.send(env).is_err() {
+            let msg = BastionMessage::exit(other.clone(), StopReason::Unreachable);
+            let env = Envelope::new(msg, other.path.clone(), other.sender.clone());
+            self.send(env).map_err(|_| ())?;
+        }
+
+        Ok(())
+    }
+
     /// Returns [`RefAddr`] for the child
     pub fn addr(&self) -> RefAddr {
         RefAddr::new(self.path.clone(), self.sender.clone())
@@ -421,6 +1009,10 @@ impl ChildRef {
 
     pub(crate) fn send(&self, env: Envelope) -> Result<(), Envelope> {
         trace!("ChildRef({}): Sending message: {:?}", self.id(), env);
+        if system::refuses(&env) {
+            return Err(env);
+        }
+
         self.sender
             .unbounded_send(env)
             .map_err(|err| err.into_inner())
@@ -441,6 +1033,88 @@ impl ChildRef {
     }
 }
 
+/// Allows to pipe a [`Stream`] of messages to the child this
+/// `ChildRef` is referencing (e.g. using [`StreamExt::forward`]),
+/// "telling" them anonymously.
+///
+/// The sink is only ready once the child's mailbox has room for a
+/// new message if it is bounded and uses
+/// [`OverflowStrategy::Backpressure`]. Otherwise, it is always
+/// ready and messages are handled like with [`tell_anonymously`].
+///
+/// Sending a message fails with the message itself if it couldn't
+/// be sent.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use futures::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// # let children_ref = Bastion::children(|children| children).unwrap();
+/// let child_ref = children_ref.elems()[0].clone();
+/// Bastion::children(move |children| {
+///     children.with_exec(move |_: BastionContext| {
+///         let child_ref = child_ref.clone();
+///         async move {
+///             stream::iter(0..8u32).map(Ok).forward(child_ref).await
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`Stream`]: https://docs.rs/futures/0.3/futures/stream/trait.Stream.html
+/// [`StreamExt::forward`]: https://docs.rs/futures/0.3/futures/stream/trait.StreamExt.html#method.forward
+/// [`OverflowStrategy::Backpressure`]: mailbox/enum.OverflowStrategy.html#variant.Backpressure
+/// [`tell_anonymously`]: #method.tell_anonymously
+impl<M: Message> Sink<M> for ChildRef {
+    type Error = M;
+
+    fn poll_ready(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Result<(), M>> {
+        self.sender.poll_ready(ctx).map(Ok)
+    }
+
+    fn start_send(self: Pin<&mut Self>, msg: M) -> Result<(), M> {
+        debug!("ChildRef({}): Sending message: {:?}", self.id(), msg);
+        let msg = BastionMessage::tell(msg);
+        let env = Envelope::from_dead_letters(msg);
+        if system::refuses(&env) {
+            return Err(env.into_msg().unwrap());
+        }
+
+        // FIXME: panics?
+        self.sender
+            .start_send(env)
+            .map_err(|err| err.into_inner().into_msg().unwrap())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), M>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), M>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
 impl PartialEq for ChildRef {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
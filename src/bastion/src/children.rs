@@ -1,21 +1,36 @@
 //!
 //! Children are a group of child supervised under a supervisor
 use crate::broadcast::{Broadcast, Parent, Sender};
-use crate::callbacks::{CallbackType, Callbacks};
+use crate::bulkhead::Bulkhead;
+use crate::callbacks::{CallbackType, Callbacks, ChildFailure};
 use crate::child::{Child, Init};
 use crate::child_ref::ChildRef;
 use crate::children_ref::ChildrenRef;
+use crate::circuit_breaker::CircuitBreaker;
 use crate::context::{BastionContext, BastionId, ContextState};
 use crate::dispatcher::Dispatcher;
 use crate::envelope::Envelope;
-use crate::message::BastionMessage;
-use crate::path::BastionPathElement;
+use crate::events::{self, SupervisionEvent};
+use crate::executor;
+use crate::local::LocalThread;
+use crate::mailbox::{self, Mailbox, MailboxConfig};
+use crate::message::{BastionMessage, Deployment, Message};
+use crate::middleware::{Chain, Middleware};
+use crate::path::{BastionPath, BastionPathElement};
+#[cfg(feature = "persistence")]
+use crate::persistence::Journal;
+use crate::rate_limit::{RateLimit, RateLimiter};
+use crate::realtime::RealtimeThreads;
+use crate::reload;
 #[cfg(feature = "scaling")]
 use crate::resizer::{ActorGroupStats, OptimalSizeExploringResizer, ScalingRule};
+use crate::source;
+use crate::supervisor::{group_restart_strategy, RestartStrategy};
 use crate::system::SYSTEM;
+use crate::tree::{self, ChildStatus};
+use crate::watch::StopReason;
 use anyhow::Result as AnyResult;
 
-use bastion_executor::pool;
 use futures::pending;
 use futures::poll;
 use futures::prelude::*;
@@ -23,12 +38,15 @@ use futures::stream::FuturesOrdered;
 use futures_timer::Delay;
 use fxhash::FxHashMap;
 use lightproc::prelude::*;
-use std::fmt::Debug;
+use std::any::TypeId;
+use std::error::Error;
+use std::fmt::{self, Debug, Formatter};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::Poll;
 use std::time::Duration;
+use std::time::Instant;
 use tracing::{debug, trace, warn};
 
 #[derive(Debug)]
@@ -95,6 +113,9 @@ pub struct Children {
     // The closure returning the future that will be used by
     // every element of the group.
     init: Init,
+    // The closures used instead of `init` by the elements that
+    // were added using `ChildrenRef::add_child`.
+    added_inits: FxHashMap<BastionId, Init>,
     redundancy: usize,
     // The callbacks called at the group's different lifecycle
     // events.
@@ -106,58 +127,170 @@ pub struct Children {
     started: bool,
     // List of dispatchers attached to each actor in the group.
     dispatchers: Vec<Arc<Box<Dispatcher>>>,
+    // The types of the messages the group's elements declared to
+    // handle, checked by `ChildrenRef::typed`.
+    handled: Vec<TypeId>,
     // The name of children
     name: Option<String>,
+    // The closure naming the group's elements from their index,
+    // the names of the launched elements and the index of the
+    // next launched element.
+    child_name: Option<ChildName>,
+    child_names: FxHashMap<BastionId, String>,
+    next_child_index: usize,
     #[cfg(feature = "scaling")]
     // Resizer for dynamic actor group scaling up/down.
     resizer: Box<OptimalSizeExploringResizer>,
+    #[cfg(feature = "scaling")]
+    // The states of the launched elements and since when all of
+    // them are idle, used to scale the group down to zero elements.
+    elem_states: FxHashMap<BastionId, Arc<Pin<Box<ContextState>>>>,
+    #[cfg(feature = "scaling")]
+    idle_since: Option<Instant>,
     // Defines how often do heartbeat checks. By default checks will
     // be done each 60 seconds.
     hearbeat_tick: Duration,
+    // How long the group's elements can go without answering a
+    // heartbeat before being restarted, if their liveness is
+    // checked, and the paths and states of the elements.
+    heartbeat_timeout: Option<Duration>,
+    heartbeats: FxHashMap<BastionId, (Arc<BastionPath>, Arc<Pin<Box<ContextState>>>)>,
     // Special kind for actors that not going to be visible for others
     // parts of the cluster, but required for extra behaviour for the
     // Children instance. For example for heartsbeat checks, collecting
     // stats, etc.
     helper_actors: FxHashMap<BastionId, (Sender, RecoverableHandle<()>)>,
+    // The closures of the helper actors feeding the items of
+    // streams to the group's elements.
+    sources: Vec<Init>,
+    // The configuration of the mailboxes of the group's elements.
+    mailbox: MailboxConfig,
+    // Whether the group's elements receive an `ExitSignal` message
+    // instead of being stopped when a child they are linked to
+    // terminates.
+    trap_exits: bool,
+    // The middleware run around the delivery of every message
+    // to the group's elements.
+    middleware: Chain,
+    // The circuit breaker the group's elements report their
+    // successes and failures to.
+    circuit_breaker: Option<CircuitBreaker>,
+    // The token bucket limiting the rate at which the group's
+    // elements process their messages.
+    rate_limiter: Option<RateLimiter>,
+    // How long the group's elements can take to handle a message
+    // before it is reported as slow, if it is.
+    slow_handler_threshold: Option<Duration>,
+    // The pinned threads the group's elements run on, if they
+    // don't run on the executor.
+    realtime: Option<RealtimeThreads>,
+    // The scheduling class of the group's elements.
+    priority: ProcPriority,
+    // Whether the group's elements stopped dequeuing their
+    // messages until the group is resumed.
+    paused: bool,
+    #[cfg(feature = "persistence")]
+    // The journal the group's elements persist their events to.
+    journal: Option<Arc<dyn Journal>>,
+    #[cfg(feature = "persistence")]
+    // The amount of snapshots kept by the group's elements, or
+    // `None` to keep all of them.
+    snapshot_retention: Option<usize>,
 }
 
+// The closure naming the elements of a children group from their
+// index.
+struct ChildName(Arc<dyn Fn(usize) -> String + Send + Sync>);
+
 impl Children {
     pub(crate) fn new(bcast: Broadcast) -> Self {
         debug!("Children({}): Initializing.", bcast.id());
         let launched = FxHashMap::default();
         let init = Init::default();
+        let added_inits = FxHashMap::default();
         let redundancy = 1;
         let callbacks = Callbacks::new();
         let pre_start_msgs = Vec::new();
         let started = false;
         let dispatchers = Vec::new();
+        let handled = Vec::new();
         let name = None;
+        let child_name = None;
+        let child_names = FxHashMap::default();
+        let next_child_index = 0;
         #[cfg(feature = "scaling")]
         let resizer = Box::new(OptimalSizeExploringResizer::default());
+        #[cfg(feature = "scaling")]
+        let elem_states = FxHashMap::default();
+        #[cfg(feature = "scaling")]
+        let idle_since = None;
         let hearbeat_tick = Duration::from_secs(60);
+        let heartbeat_timeout = None;
+        let heartbeats = FxHashMap::default();
         let helper_actors = FxHashMap::default();
+        let sources = Vec::new();
+        let mailbox = mailbox::default_config();
+        let trap_exits = false;
+        let middleware = Chain::default();
+        let circuit_breaker = None;
+        let rate_limiter = None;
+        let slow_handler_threshold = None;
+        let realtime = None;
+        let priority = ProcPriority::default();
+        let paused = false;
+        #[cfg(feature = "persistence")]
+        let journal = None;
+        #[cfg(feature = "persistence")]
+        let snapshot_retention = None;
 
         Children {
             bcast,
             launched,
             init,
+            added_inits,
             redundancy,
             callbacks,
             pre_start_msgs,
             started,
             dispatchers,
+            handled,
             name,
+            child_name,
+            child_names,
+            next_child_index,
             #[cfg(feature = "scaling")]
             resizer,
+            #[cfg(feature = "scaling")]
+            elem_states,
+            #[cfg(feature = "scaling")]
+            idle_since,
             hearbeat_tick,
+            heartbeat_timeout,
+            heartbeats,
             helper_actors,
+            sources,
+            mailbox,
+            trap_exits,
+            middleware,
+            circuit_breaker,
+            rate_limiter,
+            slow_handler_threshold,
+            realtime,
+            priority,
+            paused,
+            #[cfg(feature = "persistence")]
+            journal,
+            #[cfg(feature = "persistence")]
+            snapshot_retention,
         }
     }
 
     fn stack(&self) -> ProcStack {
         trace!("Children({}): Creating ProcStack.", self.id());
         // FIXME: with_pid
-        ProcStack::default()
+        // The group handles the supervision messages of its
+        // elements, which run with their own priority.
+        ProcStack::default().with_priority(ProcPriority::High)
     }
 
     /// Returns this children group's identifier.
@@ -240,7 +373,20 @@ impl Children {
             .map(|dispatcher| dispatcher.dispatcher_type())
             .collect();
 
-        ChildrenRef::new(id, sender, path, children, dispatchers)
+        let name = self.name();
+        let mailbox = self.mailbox.clone();
+        let handled = self.handled.clone();
+
+        ChildrenRef::new(
+            id,
+            sender,
+            path,
+            name,
+            mailbox,
+            children,
+            dispatchers,
+            handled,
+        )
     }
 
     /// Sets the name of this children group.
@@ -249,6 +395,65 @@ impl Children {
         self
     }
 
+    /// Sets the closure naming the elements of this children group,
+    /// allowing to find them using [`Bastion::child_by_name`].
+    ///
+    /// The closure is given the index of the element in the group,
+    /// counting from `0` and increasing each time a new element is
+    /// launched. Restarted elements keep their name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The closure returning the name of an element from
+    ///     its index.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_redundancy(4)
+    ///         .with_child_name(|index| format!("ingest-worker-{}", index))
+    /// }).expect("Couldn't create the children group.");
+    ///
+    /// Bastion::start();
+    ///
+    /// // Once the element started...
+    /// if let Some(worker) = Bastion::child_by_name("ingest-worker-3") {
+    ///     worker.tell_anonymously("ingest").ok();
+    /// }
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Bastion::child_by_name`]: ../struct.Bastion.html#method.child_by_name
+    pub fn with_child_name<N>(mut self, name: N) -> Self
+    where
+        N: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        trace!("Children({}): Setting child name closure.", self.id());
+        self.child_name = Some(ChildName(Arc::new(name)));
+        self
+    }
+
     /// Sets the closure taking a [`BastionContext`] and returning a
     /// [`Future`] that will be used by every element of this children
     /// group.
@@ -313,6 +518,300 @@ impl Children {
         self
     }
 
+    /// Sets the closure taking a [`BastionContext`] and returning a
+    /// [`Future`] that will be used by every element of this
+    /// children group, like [`with_exec`], except that the future
+    /// can fail with a typed error instead of `Err(())`.
+    ///
+    /// The error is given to the supervision events and to the
+    /// restart callbacks (see [`ChildFailure::downcast_error`]),
+    /// allowing them to tell apart, say, transient I/O errors
+    /// from fatal configuration errors.
+    ///
+    /// Note that this replaces the closure defined using
+    /// [`with_exec`].
+    ///
+    /// # Arguments
+    ///
+    /// * `init` - The closure taking a [`BastionContext`] and returning
+    ///     a [`Future`] that will be used by every element of this
+    ///     children group.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::io;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_callbacks(Callbacks::new().with_before_restart_failure(
+    ///             |failure: &ChildFailure| {
+    ///                 if let Some(err) = failure.downcast_error::<io::Error>() {
+    ///                     eprintln!("Restarting after an I/O error: {}", err);
+    ///                 }
+    ///             },
+    ///         ))
+    ///         .with_typed_exec(|ctx| async move {
+    ///             if ctx.recv().await.is_err() {
+    ///                 return Err(io::Error::new(io::ErrorKind::Other, "closed"));
+    ///             }
+    ///
+    ///             Ok(())
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`BastionContext`]: ../context/struct.BastionContext.html
+    /// [`Future`]: https://doc.rust-lang.org/std/future/trait.Future.html
+    /// [`with_exec`]: #method.with_exec
+    /// [`ChildFailure::downcast_error`]: ../struct.ChildFailure.html#method.downcast_error
+    pub fn with_typed_exec<I, F, E>(mut self, init: I) -> Self
+    where
+        I: Fn(BastionContext) -> F + Send + 'static,
+        F: Future<Output = Result<(), E>> + Send + 'static,
+        E: Error + Send + Sync + 'static,
+    {
+        trace!("Children({}): Setting typed exec closure.", self.id());
+        self.init = Init::with_typed(init);
+        self
+    }
+
+    /// Sets the closure taking a [`BastionContext`] and returning a
+    /// [`Future`] that will be used by every element of this
+    /// children group, like [`with_exec`], except that the future
+    /// doesn't need to be `Send` (e.g. because it holds an `Rc`).
+    ///
+    /// The futures of the group's elements are created and polled
+    /// on a dedicated thread, started for the group, where they are
+    /// never moved across threads. The elements are still
+    /// supervised, and receive their messages, like the elements of
+    /// any other group.
+    ///
+    /// Note that this replaces the closure defined using
+    /// [`with_exec`].
+    ///
+    /// # Arguments
+    ///
+    /// * `init` - The closure taking a [`BastionContext`] and returning
+    ///     a [`Future`] that will be used by every element of this
+    ///     children group.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::rc::Rc;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec_local(|ctx| async move {
+    ///         // The handle stays on the thread of the group...
+    ///         let handle = Rc::new("not Send");
+    ///         loop {
+    ///             msg! { ctx.recv().await?,
+    ///                 msg: &'static str => {
+    ///                     println!("{}: {}", handle, msg);
+    ///                 };
+    ///                 _: _ => ();
+    ///             }
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`BastionContext`]: ../context/struct.BastionContext.html
+    /// [`Future`]: https://doc.rust-lang.org/std/future/trait.Future.html
+    /// [`with_exec`]: #method.with_exec
+    pub fn with_exec_local<I, F>(mut self, init: I) -> Self
+    where
+        I: Fn(BastionContext) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<(), ()>> + 'static,
+    {
+        trace!("Children({}): Setting local exec closure.", self.id());
+        let thread = LocalThread::new(format!("bastion-local-{}", self.name()));
+        self.init = Init::local(thread, init);
+        self
+    }
+
+    /// Makes the elements of this children group run on a set of
+    /// threads dedicated to the group, each of them pinned onto
+    /// one of the given cores, instead of running on the executor.
+    ///
+    /// Every element stays on the thread (and thus the core) it
+    /// was assigned to when it was launched, which prevents the
+    /// work-stealing scheduler from migrating latency-sensitive
+    /// children across cores.
+    ///
+    /// # Argument
+    ///
+    /// * `cores` - The ids of the cores the threads are pinned
+    ///     onto, one thread being started per core.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_redundancy(2)
+    ///         // Each element gets its own core...
+    ///         .with_realtime_threads(vec![0, 1])
+    ///         .with_exec(|ctx| async move {
+    ///             // Send and receive messages...
+    ///             let _ = ctx.recv().await?;
+    ///             Ok(())
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    pub fn with_realtime_threads(mut self, cores: Vec<usize>) -> Self {
+        if cores.is_empty() {
+            warn!(
+                "Children({}): No cores given for the realtime threads.",
+                self.id()
+            );
+            return self;
+        }
+
+        trace!(
+            "Children({}): Setting realtime threads on cores: {:?}",
+            self.id(),
+            cores
+        );
+        let name = format!("bastion-realtime-{}", self.name());
+        self.realtime = Some(RealtimeThreads::new(name, &cores));
+        self
+    }
+
+    /// Sets the scheduling class of the elements of this children
+    /// group (which is [`ProcPriority::Normal`] by default).
+    ///
+    /// When the executor is busy, the elements of a class run
+    /// before the ones of the lower classes, which allows
+    /// latency-critical children to preempt bulk background work
+    /// (the lower classes still get a share of the executor, not
+    /// to be starved). Note that supervisors and the system always run in the
+    /// [`ProcPriority::High`] class.
+    ///
+    /// This only applies when the system runs on `bastion-executor`.
+    ///
+    /// # Argument
+    ///
+    /// * `priority` - The scheduling class of the elements.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         // Runs only when nothing else is ready...
+    ///         .with_priority(ProcPriority::Low)
+    ///         .with_exec(|ctx| async move {
+    ///             // Send and receive messages...
+    ///             let _ = ctx.recv().await?;
+    ///             Ok(())
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`ProcPriority::Normal`]: ../executor/enum.ProcPriority.html#variant.Normal
+    /// [`ProcPriority::High`]: ../executor/enum.ProcPriority.html#variant.High
+    pub fn with_priority(mut self, priority: ProcPriority) -> Self {
+        trace!("Children({}): Setting priority: {:?}", self.id(), priority);
+        self.priority = priority;
+        self
+    }
+
+    // Wraps the closure used by the group's elements, e.g. to give
+    // them a resource or to react to their completion.
+    #[cfg(not(target_os = "windows"))]
+    pub(crate) fn map_init<M>(mut self, map: M) -> Self
+    where
+        M: FnOnce(Init) -> Init,
+    {
+        let init = std::mem::take(&mut self.init);
+        self.init = map(init);
+        self
+    }
+
     /// Sets the number of elements this children group will
     /// contain. Each element will call the closure passed in
     /// [`with_exec`] and run the returned future until it stops,
@@ -359,29 +858,755 @@ impl Children {
         trace!(
             "Children({}): Setting redundancy: {}",
             self.id(),
-            redundancy
+            redundancy
+        );
+        if redundancy == std::usize::MIN {
+            self.redundancy = redundancy.saturating_add(1);
+        } else {
+            self.redundancy = redundancy;
+        }
+        #[cfg(feature = "scaling")]
+        {
+            self.resizer.set_lower_bound(self.redundancy as u64);
+        }
+
+        self
+    }
+
+    /// Appends each supervised element to the declared dispatcher.
+    ///
+    /// By default supervised elements aren't added to any of dispatcher.
+    ///
+    /// # Arguments
+    ///
+    /// * `redundancy` - An instance of struct that implements the
+    /// [`DispatcherHandler`] trait.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_dispatcher(
+    ///             Dispatcher::with_type(DispatcherType::Named("CustomGroup".to_string()))
+    ///         )
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    /// [`DispatcherHandler`]: ../dispatcher/trait.DispatcherHandler.html
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatchers.push(Arc::new(Box::new(dispatcher)));
+        self
+    }
+
+    /// Declares that this children group's elements handle the
+    /// messages of type `M`, allowing to get a
+    /// [`TypedChildrenRef<M>`] for it using [`ChildrenRef::typed`].
+    ///
+    /// This method can be called once for each type of message the
+    /// elements handle, and returns the children group's `Children`
+    /// itself.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// let children_ref = Bastion::children(|children| {
+    ///     children
+    ///         .with_message_type::<u64>()
+    ///         .with_exec(|ctx: BastionContext| async move {
+    ///             loop {
+    ///                 msg! { ctx.recv().await?,
+    ///                     n: u64 => {
+    ///                         // Handle the number...
+    ///                     };
+    ///                     _: _ => ();
+    ///                 }
+    ///             }
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    /// assert!(children_ref.typed::<u64>().is_ok());
+    /// assert!(children_ref.typed::<String>().is_err());
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`TypedChildrenRef<M>`]: ../children_ref/struct.TypedChildrenRef.html
+    /// [`ChildrenRef::typed`]: ../children_ref/struct.ChildrenRef.html#method.typed
+    pub fn with_message_type<M: Message>(mut self) -> Self {
+        trace!(
+            "Children({}): Handling messages of type: {}",
+            self.id(),
+            std::any::type_name::<M>()
+        );
+        let handled = TypeId::of::<M>();
+        if !self.handled.contains(&handled) {
+            self.handled.push(handled);
+        }
+
+        self
+    }
+
+    #[cfg(feature = "scaling")]
+    /// Sets a custom resizer for the Children.
+    ///
+    /// This method is available only with the `scaling` feature flag.
+    ///
+    /// # Arguments
+    ///
+    /// * `resizer` - An instance of the [`Resizer`] struct.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    ///     # Bastion::init();
+    ///     #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_resizer(
+    ///             OptimalSizeExploringResizer::default()
+    ///                 .with_lower_bound(10)
+    ///                 .with_upper_bound(UpperBound::Limit(100))
+    ///         )
+    /// }).expect("Couldn't create the children group.");
+    ///     #
+    ///     # Bastion::start();
+    ///     # Bastion::stop();
+    ///     # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    /// [`Resizer`]: ../resizer/struct.Resizer.html
+    pub fn with_resizer(mut self, mut resizer: OptimalSizeExploringResizer) -> Self {
+        self.redundancy = resizer.lower_bound() as usize;
+        self.resizer = Box::new(resizer);
+        self
+    }
+
+    /// Sets the callbacks that will get called at this children group's
+    /// different lifecycle events.
+    ///
+    /// See [`Callbacks`]'s documentation for more information about the
+    /// different callbacks available.
+    ///
+    /// # Arguments
+    ///
+    /// * `callbacks` - The callbacks that will get called for this
+    ///     children group.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     let callbacks = Callbacks::new()
+    ///         .with_before_start(|| println!("Children group started."))
+    ///         .with_after_stop(|| println!("Children group stopped."));
+    ///
+    ///     children
+    ///         .with_callbacks(callbacks)
+    ///         .with_exec(|ctx| {
+    ///             // -- Children group started.
+    ///             async move {
+    ///                 // ...
+    ///                 # Ok(())
+    ///             }
+    ///             // -- Children group stopped.
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Callbacks`]: struct.Callbacks.html
+    pub fn with_callbacks(mut self, callbacks: Callbacks) -> Self {
+        trace!(
+            "Children({}): Setting callbacks: {:?}",
+            self.id(),
+            callbacks
+        );
+        self.callbacks = callbacks;
+        self
+    }
+
+    /// Overrides the default time interval for heartbeat onto
+    /// the user defined.
+    ///
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The value of the [`std::time::Duration`] type
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    /// children
+    ///     .with_heartbeat_tick(Duration::from_secs(5))
+    ///     .with_exec(|ctx| {
+    ///         // -- Children group started.
+    ///         async move {
+    ///             // ...
+    ///             # Ok(())
+    ///         }
+    ///         // -- Children group stopped.
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    /// [`std::time::Duration`]: https://doc.rust-lang.org/nightly/core/time/struct.Duration.html
+    pub fn with_heartbeat_tick(mut self, interval: Duration) -> Self {
+        trace!(
+            "Children({}): Set heartbeat tick to {:?}",
+            self.id(),
+            interval
+        );
+        self.hearbeat_tick = interval;
+        self
+    }
+
+    /// Checks the liveness of this children group's elements: every
+    /// `interval`, the group pings its elements, and restarts the
+    /// ones that didn't answer for longer than `timeout` (because
+    /// they are stuck in a blocking call or a deadlock, never
+    /// yielding back to the runtime), as if they faulted.
+    ///
+    /// The stalled elements are killed with
+    /// [`StopReason::Stalled`], and a
+    /// [`SupervisionEvent::ChildStalled`] is emitted before their
+    /// supervisor restarts them.
+    ///
+    /// This replaces the interval set with
+    /// [`with_heartbeat_tick`].
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - How often the elements are pinged.
+    /// * `timeout` - How long an element can go without answering
+    ///     before being restarted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_heartbeat(Duration::from_secs(1), Duration::from_secs(5))
+    ///         .with_exec(|ctx| async move {
+    ///             // ...
+    ///             # Ok(())
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`StopReason::Stalled`]: ../watch/enum.StopReason.html#variant.Stalled
+    /// [`SupervisionEvent::ChildStalled`]: ../events/enum.SupervisionEvent.html#variant.ChildStalled
+    /// [`with_heartbeat_tick`]: #method.with_heartbeat_tick
+    pub fn with_heartbeat(mut self, interval: Duration, timeout: Duration) -> Self {
+        trace!(
+            "Children({}): Set heartbeat to {:?} (timeout={:?})",
+            self.id(),
+            interval,
+            timeout
+        );
+        self.hearbeat_tick = interval;
+        self.heartbeat_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the configuration of the mailboxes of this children
+    /// group's elements.
+    ///
+    /// By default, mailboxes are unbounded (unless the system was
+    /// configured using [`Config::with_default_mailbox`]). A bounded
+    /// mailbox will use its [`OverflowStrategy`] when a message is
+    /// sent to it while it is full.
+    ///
+    /// This method returns the children group's `Children` itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The [`MailboxConfig`] used by the mailbox of
+    ///     each element of the group.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    /// children
+    ///     .with_mailbox(MailboxConfig::bounded(128).with_overflow(OverflowStrategy::DropOldest))
+    ///     .with_exec(|ctx| {
+    ///         // -- Children group started.
+    ///         async move {
+    ///             // ...
+    ///             # Ok(())
+    ///         }
+    ///         // -- Children group stopped.
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Config::with_default_mailbox`]: ../struct.Config.html#method.with_default_mailbox
+    /// [`OverflowStrategy`]: mailbox/enum.OverflowStrategy.html
+    /// [`MailboxConfig`]: mailbox/struct.MailboxConfig.html
+    pub fn with_mailbox(mut self, config: MailboxConfig) -> Self {
+        trace!(
+            "Children({}): Setting mailbox config: {:?}",
+            self.id(),
+            config
+        );
+        self.mailbox = config;
+        self
+    }
+
+    /// Sets whether this children group's elements trap exits, i.e.
+    /// whether they receive an [`ExitSignal`] message instead of
+    /// being stopped when a child they are linked to (see
+    /// [`ChildRef::link`]) terminates.
+    ///
+    /// By default, exits aren't trapped.
+    ///
+    /// This method returns the children group's `Children` itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `trap_exits` - Whether the group's elements trap exits.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_trap_exits(true)
+    ///         .with_exec(|ctx| async move {
+    ///             msg! { ctx.recv().await?,
+    ///                 msg: ExitSignal => {
+    ///                     let ExitSignal(path, reason) = msg;
+    ///                     // A linked child terminated...
+    ///                 };
+    ///                 _: _ => ();
+    ///             }
+    ///
+    ///             Ok(())
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`ExitSignal`]: ../watch/struct.ExitSignal.html
+    /// [`ChildRef::link`]: ../child_ref/struct.ChildRef.html#method.link
+    pub fn with_trap_exits(mut self, trap_exits: bool) -> Self {
+        trace!(
+            "Children({}): Setting trap exits: {}",
+            self.id(),
+            trap_exits
+        );
+        self.trap_exits = trap_exits;
+        self
+    }
+
+    /// Adds a [`Middleware`] run around the delivery of every
+    /// message to this children group's elements, after the
+    /// middleware added using [`Bastion::add_middleware`] and
+    /// the ones previously added to the group.
+    ///
+    /// This method returns the children group's `Children` itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `middleware` - The middleware to add.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         // Only deliver the messages sent with a tenant...
+    ///         .with_middleware(|msg: SignedMessage, next: Next| {
+    ///             if msg.headers().get("tenant").is_some() {
+    ///                 next.run(msg);
+    ///             }
+    ///         })
+    ///         .with_exec(|ctx| async move {
+    ///             // ...
+    ///             # Ok(())
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Middleware`]: ../middleware/trait.Middleware.html
+    /// [`Bastion::add_middleware`]: ../struct.Bastion.html#method.add_middleware
+    pub fn with_middleware(mut self, middleware: impl Middleware) -> Self {
+        trace!("Children({}): Adding middleware.", self.id());
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Sets the [`CircuitBreaker`] of this children group, which
+    /// stops delivering messages to the group's elements once they
+    /// failed too many times in a row.
+    ///
+    /// The breaker runs after the middleware previously added to
+    /// the group (see [`with_middleware`]).
+    ///
+    /// This method returns the children group's `Children` itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit_breaker` - The circuit breaker of the group.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_circuit_breaker(CircuitBreaker::new(5, Duration::from_secs(30)))
+    ///         .with_exec(|ctx| async move {
+    ///             // ...
+    ///             # Ok(())
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`CircuitBreaker`]: ../circuit_breaker/struct.CircuitBreaker.html
+    /// [`with_middleware`]: #method.with_middleware
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        trace!("Children({}): Setting circuit breaker.", self.id());
+        circuit_breaker.attach(self.bcast.path().clone());
+        self.middleware.push(Arc::new(circuit_breaker.clone()));
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Sets the [`RateLimit`] of this children group, capping the
+    /// amount of messages its elements process (all together) per
+    /// period of time.
+    ///
+    /// The messages received above the limit stay in the elements'
+    /// mailboxes until they can be processed.
+    ///
+    /// This method returns the children group's `Children` itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate_limit` - The rate limit of the group.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_redundancy(4)
+    ///         // The downstream API accepts at most 10 requests per second...
+    ///         .with_rate_limit(RateLimit::per_second(10))
+    ///         .with_exec(|ctx| async move {
+    ///             loop {
+    ///                 let msg = ctx.recv().await?;
+    ///                 // Call the downstream API...
+    ///             }
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`RateLimit`]: ../rate_limit/struct.RateLimit.html
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        trace!(
+            "Children({}): Setting rate limit: {:?}",
+            self.id(),
+            rate_limit
+        );
+        self.rate_limiter = Some(RateLimiter::new(rate_limit));
+        self
+    }
+
+    /// Reports the messages this children group's elements take
+    /// longer than `threshold` to handle (from the moment they are
+    /// received to the moment the element waits for a new one), by
+    /// emitting a [`SupervisionEvent::SlowHandler`] with the type
+    /// of the message and how long it took.
+    ///
+    /// A message is reported once, as soon as the element yields
+    /// back to the runtime after the threshold was exceeded (which
+    /// also catches the handlers blocking in a single poll).
+    ///
+    /// # Argument
+    ///
+    /// * `threshold` - How long handling a message can take before
+    ///     being reported.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_slow_handler_threshold(Duration::from_millis(100))
+    ///         .with_exec(|ctx| async move {
+    ///             // ...
+    ///             # Ok(())
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`SupervisionEvent::SlowHandler`]: ../events/enum.SupervisionEvent.html#variant.SlowHandler
+    pub fn with_slow_handler_threshold(mut self, threshold: Duration) -> Self {
+        trace!(
+            "Children({}): Setting slow handler threshold: {:?}",
+            self.id(),
+            threshold
         );
-        if redundancy == std::usize::MIN {
-            self.redundancy = redundancy.saturating_add(1);
-        } else {
-            self.redundancy = redundancy;
-        }
-        #[cfg(feature = "scaling")]
-        {
-            self.resizer.set_lower_bound(self.redundancy as u64);
-        }
-
+        self.slow_handler_threshold = Some(threshold);
         self
     }
 
-    /// Appends each supervised element to the declared dispatcher.
+    /// Sets the [`Bulkhead`] of this children group, bounding the
+    /// amount of "asked" messages its elements handle (all together)
+    /// at once. It is run as one of the group's middleware (see
+    /// [`with_middleware`]).
     ///
-    /// By default supervised elements aren't added to any of dispatcher.
+    /// This method returns the children group's `Children` itself.
     ///
     /// # Arguments
     ///
-    /// * `redundancy` - An instance of struct that implements the
-    /// [`DispatcherHandler`] trait.
+    /// * `bulkhead` - The bulkhead of the group.
     ///
     /// # Example
     ///
@@ -391,12 +1616,12 @@ impl Children {
     /// # #[cfg(feature = "tokio-runtime")]
     /// # #[tokio::main]
     /// # async fn main() {
-    /// #    run();    
+    /// #    run();
     /// # }
     /// #
     /// # #[cfg(not(feature = "tokio-runtime"))]
     /// # fn main() {
-    /// #    run();    
+    /// #    run();
     /// # }
     /// #
     /// # fn run() {
@@ -404,9 +1629,13 @@ impl Children {
     /// #
     /// Bastion::children(|children| {
     ///     children
-    ///         .with_dispatcher(
-    ///             Dispatcher::with_type(DispatcherType::Named("CustomGroup".to_string()))
-    ///         )
+    ///         .with_redundancy(4)
+    ///         // Rejects the asked messages once 16 are in flight and 64 are queued...
+    ///         .with_bulkhead(Bulkhead::new(16).with_max_queued(64))
+    ///         .with_exec(|ctx| async move {
+    ///             // ...
+    ///             # Ok(())
+    ///         })
     /// }).expect("Couldn't create the children group.");
     /// #
     /// # Bastion::start();
@@ -414,71 +1643,120 @@ impl Children {
     /// # Bastion::block_until_stopped();
     /// # }
     /// ```
-    /// [`DispatcherHandler`]: ../dispatcher/trait.DispatcherHandler.html
-    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
-        self.dispatchers.push(Arc::new(Box::new(dispatcher)));
+    ///
+    /// [`Bulkhead`]: ../bulkhead/struct.Bulkhead.html
+    /// [`with_middleware`]: #method.with_middleware
+    pub fn with_bulkhead(mut self, bulkhead: Bulkhead) -> Self {
+        trace!("Children({}): Setting bulkhead: {:?}", self.id(), bulkhead);
+        self.middleware.push(Arc::new(bulkhead));
         self
     }
 
-    #[cfg(feature = "scaling")]
-    /// Sets a custom resizer for the Children.
+    /// Feeds the items of a [`Stream`] to this children group's
+    /// elements, in turn, waiting for their mailboxes to have room
+    /// for them if they apply backpressure (see
+    /// [`OverflowStrategy::Backpressure`]).
     ///
-    /// This method is available only with the `scaling` feature flag.
+    /// The stream is created by calling `source` with the resume
+    /// point of the last item that was delivered (or `None` the
+    /// first time). Every item of the stream is a resume point
+    /// (like the offset of a record) along with the message to
+    /// deliver, or an error, in which case the stream is created
+    /// again following `restart_strategy`. The restarts counter is
+    /// reset every time an item is delivered. The source stops once
+    /// its stream ends.
+    ///
+    /// This method returns the children group's `Children` itself.
     ///
     /// # Arguments
     ///
-    /// * `resizer` - An instance of the [`Resizer`] struct.
+    /// * `source` - The closure taking the resume point of the last
+    ///     delivered item and returning the stream to consume.
+    /// * `restart_strategy` - How the stream is created again after
+    ///     it failed.
     ///
     /// # Example
     ///
     /// ```rust
     /// # use bastion::prelude::*;
+    /// # use futures::prelude::*;
     /// #
     /// # #[cfg(feature = "tokio-runtime")]
     /// # #[tokio::main]
     /// # async fn main() {
-    /// #    run();    
+    /// #    run();
     /// # }
     /// #
     /// # #[cfg(not(feature = "tokio-runtime"))]
     /// # fn main() {
-    /// #    run();    
+    /// #    run();
     /// # }
     /// #
     /// # fn run() {
-    ///     # Bastion::init();
-    ///     #
+    /// # Bastion::init();
+    /// #
     /// Bastion::children(|children| {
     ///     children
-    ///         .with_resizer(
-    ///             OptimalSizeExploringResizer::default()
-    ///                 .with_lower_bound(10)
-    ///                 .with_upper_bound(UpperBound::Limit(100))
+    ///         .with_redundancy(4)
+    ///         .with_stream_source(
+    ///             |offset: Option<u64>| {
+    ///                 // Consume the records following the last delivered one...
+    ///                 let from = offset.map(|offset| offset + 1).unwrap_or(0);
+    ///                 stream::iter(from..).map(|offset| Ok::<_, ()>((offset, offset * 2)))
+    ///             },
+    ///             RestartStrategy::default(),
     ///         )
+    ///         .with_exec(|ctx| async move {
+    ///             loop {
+    ///                 msg! { ctx.recv().await?,
+    ///                     record: u64 => {
+    ///                         // Handle the record...
+    ///                     };
+    ///                     _: _ => ();
+    ///                 }
+    ///             }
+    ///         })
     /// }).expect("Couldn't create the children group.");
-    ///     #
-    ///     # Bastion::start();
-    ///     # Bastion::stop();
-    ///     # Bastion::block_until_stopped();
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
     /// # }
     /// ```
-    /// [`Resizer`]: ../resizer/struct.Resizer.html
-    pub fn with_resizer(mut self, mut resizer: OptimalSizeExploringResizer) -> Self {
-        self.redundancy = resizer.lower_bound() as usize;
-        self.resizer = Box::new(resizer);
+    ///
+    /// [`Stream`]: https://docs.rs/futures/0.3/futures/stream/trait.Stream.html
+    /// [`OverflowStrategy::Backpressure`]: ../mailbox/enum.OverflowStrategy.html#variant.Backpressure
+    pub fn with_stream_source<F, S, R, M, E>(
+        mut self,
+        source: F,
+        restart_strategy: RestartStrategy,
+    ) -> Self
+    where
+        F: Fn(Option<R>) -> S + Send + Sync + 'static,
+        S: Stream<Item = Result<(R, M), E>> + Send + 'static,
+        R: Clone + Debug + Send + 'static,
+        M: Message,
+        E: Debug + Send + 'static,
+    {
+        trace!(
+            "Children({}): Adding stream source: {:?}",
+            self.id(),
+            restart_strategy
+        );
+        self.sources.push(source::init(source, restart_strategy));
         self
     }
 
-    /// Sets the callbacks that will get called at this children group's
-    /// different lifecycle events.
+    #[cfg(feature = "persistence")]
+    /// Sets the journal this children group's elements persist
+    /// their events to, and recover their state from using
+    /// [`BastionContext::persistent`].
     ///
-    /// See [`Callbacks`]'s documentation for more information about the
-    /// different callbacks available.
+    /// This method returns the children group's `Children` itself.
     ///
     /// # Arguments
     ///
-    /// * `callbacks` - The callbacks that will get called for this
-    ///     children group.
+    /// * `journal` - The [`Journal`] used by the group's elements.
     ///
     /// # Example
     ///
@@ -499,20 +1777,18 @@ impl Children {
     /// # fn run() {
     /// # Bastion::init();
     /// #
-    /// Bastion::children(|children| {
-    ///     let callbacks = Callbacks::new()
-    ///         .with_before_start(|| println!("Children group started."))
-    ///         .with_after_stop(|| println!("Children group stopped."));
+    /// // Created outside of the closure to survive restarts
+    /// // of the group...
+    /// let journal = MemoryJournal::new();
     ///
+    /// Bastion::children(|children| {
     ///     children
-    ///         .with_callbacks(callbacks)
+    ///         .with_journal(journal.clone())
     ///         .with_exec(|ctx| {
-    ///             // -- Children group started.
     ///             async move {
     ///                 // ...
     ///                 # Ok(())
     ///             }
-    ///             // -- Children group stopped.
     ///         })
     /// }).expect("Couldn't create the children group.");
     /// #
@@ -522,30 +1798,31 @@ impl Children {
     /// # }
     /// ```
     ///
-    /// [`Callbacks`]: struct.Callbacks.html
-    pub fn with_callbacks(mut self, callbacks: Callbacks) -> Self {
-        trace!(
-            "Children({}): Setting callbacks: {:?}",
-            self.id(),
-            callbacks
-        );
-        self.callbacks = callbacks;
+    /// [`BastionContext::persistent`]: ../context/struct.BastionContext.html#method.persistent
+    /// [`Journal`]: ../persistence/trait.Journal.html
+    pub fn with_journal<J: Journal>(mut self, journal: J) -> Self {
+        trace!("Children({}): Setting journal: {:?}", self.id(), journal);
+        self.journal = Some(Arc::new(journal));
         self
     }
 
-    /// Overrides the default time interval for heartbeat onto
-    /// the user defined.
+    #[cfg(feature = "persistence")]
+    /// Sets the amount of snapshots this children group's elements
+    /// keep in the journal when saving a new one using
+    /// [`BastionContext::save_snapshot`]. The oldest snapshots are
+    /// deleted. By default, all of them are kept.
     ///
+    /// This method returns the children group's `Children` itself.
     ///
     /// # Arguments
     ///
-    /// * `interval` - The value of the [`std::time::Duration`] type
+    /// * `keep` - The amount of snapshots to keep for each
+    ///     persistence id.
     ///
     /// # Example
     ///
     /// ```rust
     /// # use bastion::prelude::*;
-    /// # use std::time::Duration;
     /// #
     /// # #[cfg(feature = "tokio-runtime")]
     /// # #[tokio::main]
@@ -562,16 +1839,15 @@ impl Children {
     /// # Bastion::init();
     /// #
     /// Bastion::children(|children| {
-    /// children
-    ///     .with_heartbeat_tick(Duration::from_secs(5))
-    ///     .with_exec(|ctx| {
-    ///         // -- Children group started.
-    ///         async move {
-    ///             // ...
-    ///             # Ok(())
-    ///         }
-    ///         // -- Children group stopped.
-    ///     })
+    ///     children
+    ///         .with_journal(MemoryJournal::new())
+    ///         .with_snapshot_retention(2)
+    ///         .with_exec(|ctx| {
+    ///             async move {
+    ///                 // ...
+    ///                 # Ok(())
+    ///             }
+    ///         })
     /// }).expect("Couldn't create the children group.");
     /// #
     /// # Bastion::start();
@@ -579,14 +1855,15 @@ impl Children {
     /// # Bastion::block_until_stopped();
     /// # }
     /// ```
-    /// [`std::time::Duration`]: https://doc.rust-lang.org/nightly/core/time/struct.Duration.html
-    pub fn with_heartbeat_tick(mut self, interval: Duration) -> Self {
+    ///
+    /// [`BastionContext::save_snapshot`]: ../context/struct.BastionContext.html#method.save_snapshot
+    pub fn with_snapshot_retention(mut self, keep: usize) -> Self {
         trace!(
-            "Children({}): Set heartbeat tick to {:?}",
+            "Children({}): Setting snapshot retention: {}",
             self.id(),
-            interval
+            keep
         );
-        self.hearbeat_tick = interval;
+        self.snapshot_retention = Some(keep);
         self
     }
 
@@ -626,12 +1903,20 @@ impl Children {
             .await;
     }
 
-    async fn kill(&mut self) {
+    async fn kill(&mut self, reason: StopReason) {
         debug!("Children({}): Killing.", self.id());
-        self.bcast.kill_children();
+        self.bcast.kill_children_with(reason);
+        // The killed elements might not get the time to do it.
+        for (id, name) in self.child_names.drain() {
+            SYSTEM.unregister_name(&name, &id);
+        }
+        self.added_inits.clear();
+        #[cfg(feature = "scaling")]
+        self.elem_states.clear();
 
         let mut children = FuturesOrdered::new();
-        for (_, (_, launched)) in self.launched.drain() {
+        for (id, (_, launched)) in self.launched.drain() {
+            tree::unregister_child(&id);
             launched.cancel();
 
             children.push(launched);
@@ -647,6 +1932,7 @@ impl Children {
 
     fn stopped(&mut self) {
         debug!("Children({}): Stopped.", self.id());
+        tree::unregister_group(self.id());
         if let Err(e) = self.remove_dispatchers() {
             warn!("couldn't remove all dispatchers from the registry: {}", e);
         };
@@ -655,22 +1941,23 @@ impl Children {
 
     fn faulted(&mut self) {
         debug!("Children({}): Faulted.", self.id());
+        tree::unregister_group(self.id());
         if let Err(e) = self.remove_dispatchers() {
             warn!("couldn't remove all dispatchers from the registry: {}", e);
         };
         self.bcast.faulted();
     }
 
-    async fn kill_children(&mut self) -> Result<(), ()> {
+    async fn kill_children(&mut self, reason: StopReason) -> Result<(), ()> {
         self.disable_helper_actors().await;
-        self.kill().await;
+        self.kill(reason).await;
         self.stopped();
         Err(())
     }
 
     async fn stop_children(&mut self) -> Result<(), ()> {
         self.disable_helper_actors().await;
-        self.kill().await;
+        self.kill(StopReason::Killed).await;
         self.stopped();
         Err(())
     }
@@ -693,7 +1980,7 @@ impl Children {
         // FIXME: Err if false?
         if self.launched.contains_key(id) {
             warn!("Children({}): Child({}) faulted.", self.id(), id);
-            self.kill().await;
+            self.kill(StopReason::Killed).await;
             self.faulted();
 
             return Err(());
@@ -713,7 +2000,18 @@ impl Children {
 
     fn restart_child(&mut self, old_id: &BastionId, old_state: Arc<Pin<Box<ContextState>>>) {
         let parent = Parent::children(self.as_ref());
-        let bcast = Broadcast::new(parent, BastionPathElement::Child(old_id.clone()));
+        let mut bcast = Broadcast::new(parent, BastionPathElement::Child(old_id.clone()));
+
+        let path = bcast.path().clone();
+        events::emit(SupervisionEvent::ChildRestarted { path });
+
+        // The restarted element keeps its mailbox, but the messages
+        // that were still in the old channel are lost.
+        let mailbox = old_state.mailbox().clone();
+        mailbox.resync();
+        bcast.attach_mailbox(mailbox);
+        old_state.record_heartbeat();
+        old_state.local_storage().clear_transient();
 
         let id = bcast.id().clone();
         let sender = bcast.sender().clone();
@@ -730,7 +2028,13 @@ impl Children {
             supervisor,
             old_state.clone(),
         );
-        let exec = (self.init.0)(ctx);
+        #[cfg(feature = "persistence")]
+        let ctx = ctx
+            .with_journal(self.journal.clone())
+            .with_snapshot_retention(self.snapshot_retention);
+        let init = self.added_inits.get(old_id).unwrap_or(&self.init);
+        let cancellation = ctx.cancellation_token();
+        let exec = (init.0)(ctx);
 
         self.bcast.register(&bcast);
 
@@ -749,7 +2053,14 @@ impl Children {
         debug!("Children({}): Restarting Child({}).", self.id(), bcast.id());
         let callbacks = self.callbacks.clone();
         let state = Arc::new(Box::pin(ContextState::new()));
-        let child = Child::new(exec, callbacks, bcast, state, child_ref);
+        let name = self.child_names.get(old_id).cloned();
+        let child = Child::new(exec, callbacks, bcast, state, child_ref)
+            .with_cancellation_token(cancellation)
+            .with_trap_exits(self.trap_exits)
+            .with_middleware(self.middleware.clone())
+            .with_name(name)
+            .with_realtime(self.realtime.clone())
+            .with_priority(self.priority);
         debug!(
             "Children({}): Launching faulted Child({}).",
             self.id(),
@@ -760,6 +2071,134 @@ impl Children {
         self.launched.insert(id, (sender, launched));
     }
 
+    /// Applies the settings of the system's configuration for the
+    /// groups with this group's name (see `Bastion::reload_config`)
+    /// to the group and its launched elements.
+    fn apply_group_settings(&mut self) {
+        let settings = match reload::group_settings(&self.name()) {
+            Some(settings) => settings,
+            None => return,
+        };
+
+        debug!(
+            "Children({}): Applying group settings: {:?}",
+            self.id(),
+            settings
+        );
+        if let Some(capacity) = settings.mailbox_capacity {
+            self.mailbox.set_capacity(capacity);
+            for mailbox in self.bcast.children_mailboxes() {
+                mailbox.set_capacity(capacity);
+            }
+        }
+
+        // The elements share the group's token bucket, if it has one.
+        if let Some(rate_limit) = settings.rate_limit {
+            match &self.rate_limiter {
+                Some(rate_limiter) => rate_limiter.set_limit(rate_limit),
+                None => self.rate_limiter = Some(RateLimiter::new(rate_limit)),
+            }
+        }
+
+        #[cfg(feature = "scaling")]
+        if let Some((lower_bound, upper_bound)) = settings.resizer_bounds {
+            self.redundancy = lower_bound as usize;
+            self.resizer.set_lower_bound(lower_bound);
+            self.resizer.set_upper_bound(upper_bound);
+        }
+    }
+
+    /// Restarts the elements which didn't answer a heartbeat
+    /// before the timeout, and pings the other ones.
+    fn check_liveness(&mut self) {
+        let timeout = match self.heartbeat_timeout {
+            Some(timeout) => timeout,
+            None => return,
+        };
+
+        let stalled = self
+            .heartbeats
+            .iter()
+            // The elements waiting to be restarted can't answer.
+            .filter(|(_, (_, state))| state.failure().is_none())
+            .filter_map(|(id, (_, state))| {
+                let elapsed = state.last_heartbeat().elapsed();
+                if elapsed > timeout && self.launched.contains_key(id) {
+                    Some((id.clone(), elapsed))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for (id, stalled_for) in stalled {
+            self.stalled(&id, stalled_for);
+        }
+
+        for id in self.heartbeats.keys() {
+            let msg = BastionMessage::heartbeat();
+            let env = Envelope::new(msg, self.bcast.path().clone(), self.bcast.sender().clone());
+            self.bcast.send_child(id, env);
+        }
+    }
+
+    /// Kills every element and asks the supervisor to restart them,
+    /// following its restart strategy as if they had faulted.
+    fn restart_elems(&mut self) {
+        debug!("Children({}): Restarting elements.", self.id());
+        let parent_id = self.bcast.id().clone();
+        let ids = self.launched.keys().cloned().collect::<Vec<_>>();
+        for id in ids {
+            if let Some((_, launched)) = self.launched.get(&id) {
+                launched.cancel();
+            }
+
+            self.bcast.unregister(&id);
+            tree::set_child_status(&id, ChildStatus::Restarting);
+            if let Some(name) = self.child_names.get(&id) {
+                SYSTEM.unregister_name(name, &id);
+            }
+
+            self.request_restarting_child(&id, &parent_id);
+        }
+    }
+
+    /// Kills an element which didn't answer a heartbeat before the
+    /// timeout and asks its supervisor to restart it, as it can't
+    /// fault by itself.
+    fn stalled(&mut self, id: &BastionId, stalled_for: Duration) {
+        warn!(
+            "Children({}): Child({}) stalled for {:?}.",
+            self.id(),
+            id,
+            stalled_for
+        );
+        let (path, state) = match self.heartbeats.get(id) {
+            Some((path, state)) => (path.clone(), state.clone()),
+            None => return,
+        };
+
+        // The child's future gets dropped once it yields back, if
+        // it ever does.
+        if let Some((_, launched)) = self.launched.get(id) {
+            launched.cancel();
+        }
+
+        self.bcast.unregister(id);
+        tree::set_child_status(id, ChildStatus::Restarting);
+        if let Some(name) = self.child_names.get(id) {
+            SYSTEM.unregister_name(name, id);
+        }
+
+        let failure = ChildFailure::new(path.clone(), StopReason::Stalled, state.restarts());
+        self.callbacks.before_restart(state.state_bag(), &failure);
+        state.set_failure(failure);
+
+        events::emit(SupervisionEvent::ChildStalled { path, stalled_for });
+        let parent_id = self.bcast.id().clone();
+        self.request_restarting_child(id, &parent_id);
+    }
+
     fn drop_child(&mut self, id: &BastionId) {
         debug!(
             "Children({}): Dropping Child({:?}): reached restart limits.",
@@ -767,6 +2206,12 @@ impl Children {
             id,
         );
         self.launched.remove_entry(id);
+        self.child_names.remove(id);
+        self.added_inits.remove(id);
+        self.heartbeats.remove(id);
+        #[cfg(feature = "scaling")]
+        self.elem_states.remove(id);
+        tree::unregister_child(id);
 
         #[cfg(feature = "scaling")]
         self.update_actors_count_stats();
@@ -783,14 +2228,55 @@ impl Children {
                 ..
             } => self.stop_children().await?,
             Envelope {
-                msg: BastionMessage::Kill,
+                msg: BastionMessage::Drain,
                 ..
-            } => self.kill_children().await?,
-            // FIXME
+            } => {
+                debug!("Children({}): Draining.", self.id());
+                // The heartbeat would otherwise keep running
+                // until the deadline.
+                self.disable_helper_actors().await;
+                self.bcast.send_children(envelope);
+            }
             Envelope {
-                msg: BastionMessage::Deploy(_),
+                msg: BastionMessage::Kill { reason },
                 ..
-            } => unimplemented!(),
+            } => self.kill_children(reason).await?,
+            Envelope {
+                msg: BastionMessage::Pause,
+                ..
+            } => {
+                debug!("Children({}): Pausing.", self.id());
+                self.paused = true;
+                self.bcast.send_children(envelope);
+            }
+            Envelope {
+                msg: BastionMessage::Resume,
+                ..
+            } => {
+                debug!("Children({}): Resuming.", self.id());
+                self.paused = false;
+                self.bcast.send_children(envelope);
+            }
+            Envelope {
+                msg: BastionMessage::Restart,
+                ..
+            } => self.restart_elems(),
+            Envelope {
+                msg: BastionMessage::Reconfigure,
+                ..
+            } => self.apply_group_settings(),
+            Envelope {
+                msg: BastionMessage::Deploy(deployment),
+                ..
+            } => match *deployment {
+                Deployment::Child {
+                    bcast,
+                    mailbox,
+                    init,
+                } => self.add_child(bcast, mailbox, init),
+                // FIXME
+                _ => unimplemented!(),
+            },
             // FIXME
             Envelope {
                 msg: BastionMessage::Prune { .. },
@@ -818,6 +2304,8 @@ impl Children {
                     self.id(),
                     message
                 );
+                #[cfg(feature = "scaling")]
+                self.wake_up();
                 self.bcast.send_children(envelope);
             }
             Envelope {
@@ -855,7 +2343,23 @@ impl Children {
             Envelope {
                 msg: BastionMessage::Heartbeat,
                 ..
-            } => {}
+            } => self.check_liveness(),
+            Envelope {
+                msg: BastionMessage::Watch { .. },
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Unwatch { .. },
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Link { .. },
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Exit { .. },
+                ..
+            } => unreachable!(),
         }
 
         Ok(())
@@ -900,6 +2404,14 @@ impl Children {
 
     #[cfg(feature = "scaling")]
     async fn autoresize_group(&mut self) {
+        if let Some(idle_timeout) = self.resizer.idle_timeout() {
+            // The group stays without elements until it receives
+            // a message.
+            if self.launched.is_empty() || self.scale_to_zero(idle_timeout).await {
+                return;
+            }
+        }
+
         match self.resizer.scale(&self.launched).await {
             ScalingRule::Upscale(count) => {
                 for _ in 0..count {
@@ -911,16 +2423,70 @@ impl Children {
                     self.drop_child(&id);
                 }
             }
+            ScalingRule::Shutdown(actors_to_stop) => {
+                for id in actors_to_stop {
+                    self.bcast.stop_child(&id);
+                    self.handle_stopped_child(&id).await.ok();
+                }
+            }
             ScalingRule::DoNothing => {}
         }
 
         self.update_actors_count_stats();
     }
 
+    #[cfg(feature = "scaling")]
+    /// Stops all the elements if all of them were idle for
+    /// `idle_timeout`, returning whether they were stopped.
+    async fn scale_to_zero(&mut self, idle_timeout: Duration) -> bool {
+        if !self.elem_states.values().all(|state| state.is_drained()) {
+            self.idle_since = None;
+            return false;
+        }
+
+        let idle_since = *self.idle_since.get_or_insert_with(Instant::now);
+        if idle_since.elapsed() < idle_timeout {
+            return false;
+        }
+
+        debug!("Children({}): Idle, scaling down to zero.", self.id());
+        self.idle_since = None;
+        let ids = self.launched.keys().cloned().collect::<Vec<_>>();
+        for id in ids {
+            self.bcast.stop_child(&id);
+            self.handle_stopped_child(&id).await.ok();
+        }
+
+        self.update_actors_count_stats();
+        true
+    }
+
+    #[cfg(feature = "scaling")]
+    /// Launches an element if the group scaled down to zero
+    /// elements, to receive the messages it is sent.
+    fn wake_up(&mut self) {
+        if self.resizer.idle_timeout().is_some() && self.launched.is_empty() {
+            debug!("Children({}): Waking up.", self.id());
+            self.launch_child();
+            self.update_actors_count_stats();
+        }
+    }
+
+    #[cfg(feature = "scaling")]
+    fn scales_to_zero(&self) -> bool {
+        self.resizer.idle_timeout().is_some()
+    }
+
+    #[cfg(not(feature = "scaling"))]
+    fn scales_to_zero(&self) -> bool {
+        false
+    }
+
     #[cfg(feature = "scaling")]
     fn init_data_for_scaling(&self, state: &mut ContextState) {
         state.set_stats(self.resizer.stats());
         state.set_actor_stats(self.resizer.actor_stats());
+        state.set_actor_latencies(self.resizer.actor_latencies());
     }
 
     async fn run(mut self) -> Self {
@@ -978,9 +2544,32 @@ impl Children {
     }
 
     pub(crate) fn launch_child(&mut self) {
-        let name = self.name();
         let parent = Parent::children(self.as_ref());
-        let bcast = Broadcast::new(parent, BastionPathElement::Child(BastionId::new()));
+        let mut bcast = Broadcast::new(parent, BastionPathElement::Child(BastionId::new()));
+
+        let mailbox = Arc::new(Mailbox::new(self.mailbox.clone()));
+        bcast.attach_mailbox(mailbox.clone());
+
+        self.launch_elem(bcast, mailbox, None);
+    }
+
+    fn add_child(&mut self, bcast: Broadcast, mailbox: Arc<Mailbox>, init: Init) {
+        debug!("Children({}): Adding Child({}).", self.id(), bcast.id());
+        // The mailbox was configured when the child was added, and
+        // the group might have been reconfigured since.
+        if let Some(capacity) = self.mailbox.capacity() {
+            mailbox.set_capacity(capacity);
+        }
+        self.launch_elem(bcast, mailbox, Some(init));
+
+        #[cfg(feature = "scaling")]
+        self.update_actors_count_stats();
+    }
+
+    /// Launches an element of the group, using `init` instead of
+    /// the group's closure if it is given.
+    fn launch_elem(&mut self, bcast: Broadcast, mailbox: Arc<Mailbox>, init: Option<Init>) {
+        let name = self.name();
 
         // TODO: clone or ref?
         let id = bcast.id().clone();
@@ -991,12 +2580,23 @@ impl Children {
         let children = self.as_ref();
         let supervisor = self.bcast.parent().clone().into_supervisor();
 
-        #[allow(unused_mut)]
         let mut state = ContextState::new();
+        state.set_mailbox(mailbox);
+        state.set_paused(self.paused);
+        state.set_circuit_breaker(self.circuit_breaker.clone());
+        state.set_rate_limiter(self.rate_limiter.clone());
+        state.set_slow_handler_threshold(self.slow_handler_threshold);
+        state.set_restart_strategy(group_restart_strategy(&self.name()));
         #[cfg(feature = "scaling")]
         self.init_data_for_scaling(&mut state);
 
         let state = Arc::new(Box::pin(state));
+        #[cfg(feature = "scaling")]
+        self.elem_states.insert(id.clone(), state.clone());
+        if self.heartbeat_timeout.is_some() {
+            let path = bcast.path().clone();
+            self.heartbeats.insert(id.clone(), (path, state.clone()));
+        }
 
         let ctx = BastionContext::new(
             id.clone(),
@@ -1005,7 +2605,15 @@ impl Children {
             supervisor,
             state.clone(),
         );
-        let exec = (self.init.0)(ctx);
+        #[cfg(feature = "persistence")]
+        let ctx = ctx
+            .with_journal(self.journal.clone())
+            .with_snapshot_retention(self.snapshot_retention);
+        let cancellation = ctx.cancellation_token();
+        let exec = (init.as_ref().unwrap_or(&self.init).0)(ctx);
+        if let Some(init) = init {
+            self.added_inits.insert(id.clone(), init);
+        }
 
         let parent_id = self.bcast.id().clone();
         let msg = BastionMessage::instantiated_child(parent_id, id.clone(), state.clone());
@@ -1024,7 +2632,14 @@ impl Children {
             bcast.id()
         );
         let callbacks = self.callbacks.clone();
-        let child = Child::new(exec, callbacks, bcast, state, child_ref);
+        let name = self.name_child(&id);
+        let child = Child::new(exec, callbacks, bcast, state, child_ref)
+            .with_cancellation_token(cancellation)
+            .with_trap_exits(self.trap_exits)
+            .with_middleware(self.middleware.clone())
+            .with_name(name)
+            .with_realtime(self.realtime.clone())
+            .with_priority(self.priority);
         debug!("Children({}): Launching Child({}).", self.id(), child.id());
         let id = child.id().clone();
         let launched = child.launch();
@@ -1032,6 +2647,22 @@ impl Children {
     }
 
     pub(crate) fn launch_heartbeat(&mut self) {
+        let init = self.get_heartbeat_fut();
+        self.launch_helper(&init, "HeartbeatChild");
+    }
+
+    fn launch_sources(&mut self) {
+        let sources = std::mem::take(&mut self.sources);
+        for source in &sources {
+            self.launch_helper(source, "StreamSource");
+        }
+
+        self.sources = sources;
+    }
+
+    /// Launches a helper actor of the group, which isn't visible
+    /// to the other parts of the system.
+    fn launch_helper(&mut self, init: &Init, kind: &str) {
         let name = self.name();
         let parent = Parent::children(self.as_ref());
         let bcast = Broadcast::new(parent, BastionPathElement::Child(BastionId::new()));
@@ -1048,20 +2679,23 @@ impl Children {
         let state = Arc::new(Box::pin(ContextState::new()));
 
         let ctx = BastionContext::new(id, child_ref.clone(), children, supervisor, state.clone());
-        let init = self.get_heartbeat_fut();
+        let cancellation = ctx.cancellation_token();
         let exec = (init.0)(ctx);
         self.bcast.register(&bcast);
 
         debug!(
-            "Children({}): Initializing HeartbeatChild({}).",
+            "Children({}): Initializing {}({}).",
             self.id(),
+            kind,
             bcast.id()
         );
         let callbacks = self.callbacks.clone();
-        let child = Child::new(exec, callbacks, bcast, state, child_ref);
+        let child = Child::new(exec, callbacks, bcast, state, child_ref)
+            .with_cancellation_token(cancellation);
         debug!(
-            "Children({}): Launching HeartbeatChild({}).",
+            "Children({}): Launching {}({}).",
             self.id(),
+            kind,
             child.id()
         );
         let id = child.id().clone();
@@ -1069,19 +2703,35 @@ impl Children {
         self.helper_actors.insert(id, (sender, launched));
     }
 
+    /// Returns the name of a new element, if the elements are
+    /// named.
+    fn name_child(&mut self, id: &BastionId) -> Option<String> {
+        let name = (self.child_name.as_ref()?.0)(self.next_child_index);
+        self.next_child_index += 1;
+        self.child_names.insert(id.clone(), name.clone());
+        Some(name)
+    }
+
     pub(crate) fn launch_elems(&mut self) {
         debug!("Children({}): Launching elements.", self.id());
-        for _ in 0..self.redundancy {
-            self.launch_child();
+        tree::register_group(self.as_ref(), self.name());
+        self.apply_group_settings();
+        // A group that can scale down to zero elements starts
+        // without any.
+        if !self.scales_to_zero() {
+            for _ in 0..self.redundancy {
+                self.launch_child();
+            }
         }
 
         self.launch_heartbeat();
+        self.launch_sources();
     }
 
     pub(crate) fn launch(self) -> RecoverableHandle<Self> {
         debug!("Children({}): Launching.", self.id());
         let stack = self.stack();
-        pool::spawn(self.run(), stack)
+        executor::spawn_proc(self.run(), stack)
     }
 
     /// Registers all declared local dispatchers in the global dispatcher.
@@ -1104,3 +2754,9 @@ impl Children {
         Ok(())
     }
 }
+
+impl Debug for ChildName {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("ChildName").finish()
+    }
+}
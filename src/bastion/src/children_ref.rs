@@ -1,16 +1,32 @@
 //!
 //! Allows users to communicate with children through the mailboxes.
-use crate::broadcast::Sender;
+use crate::broadcast::{Broadcast, Parent, Sender};
+#[cfg(feature = "tokio-channels")]
+use crate::channel;
+use crate::child::Init;
 use crate::child_ref::ChildRef;
-use crate::context::BastionId;
+use crate::context::{BastionContext, BastionId};
+use crate::dead_letters;
 use crate::dispatcher::DispatcherType;
 use crate::envelope::Envelope;
-use crate::message::{BastionMessage, Message};
-use crate::path::BastionPath;
-use crate::system::SYSTEM;
+use crate::errors::AskError;
+use crate::executor;
+use crate::mailbox::{Mailbox, MailboxConfig};
+use crate::message::{Answer, BastionMessage, Message};
+use crate::path::{BastionPath, BastionPathElement};
+#[cfg(feature = "tower")]
+use crate::service::GroupService;
+#[cfg(feature = "tokio-channels")]
+use crate::supervisor::SupervisorRef;
+use crate::system;
+use futures::future;
+use std::any::TypeId;
 use std::cmp::{Eq, PartialEq};
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::marker::PhantomData;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{debug, trace};
 
 #[derive(Debug, Clone)]
@@ -20,8 +36,59 @@ pub struct ChildrenRef {
     id: BastionId,
     sender: Sender,
     path: Arc<BastionPath>,
+    // The name of the group and the configuration of its
+    // elements' mailboxes, used by the elements added using
+    // `add_child`.
+    name: String,
+    mailbox: MailboxConfig,
     children: Vec<ChildRef>,
     dispatchers: Vec<DispatcherType>,
+    // The types of the messages the group's elements declared to
+    // handle (see `Children::with_message_type`).
+    handled: Vec<TypeId>,
+}
+
+/// A [`ChildrenRef`] which only sends messages of type `M` to its
+/// children group, as returned by [`ChildrenRef::typed`].
+///
+/// Sending another type of message to the group this way fails to
+/// compile instead of only being noticed at runtime, when no element
+/// matches it.
+///
+/// [`ChildrenRef`]: struct.ChildrenRef.html
+/// [`ChildrenRef::typed`]: struct.ChildrenRef.html#method.typed
+pub struct TypedChildrenRef<M> {
+    children_ref: ChildrenRef,
+    _msg: PhantomData<fn(M)>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// The options used by [`ChildrenRef::ask_one_with`] to decide
+/// how long to wait for an answer and how to retry against other
+/// elements of the children group.
+///
+/// The default options wait up to 5 seconds for an answer, without
+/// retrying.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::time::Duration;
+/// #
+/// let options = AskOptions::default()
+///     .with_timeout(Duration::from_millis(500))
+///     .with_retries(2)
+///     .with_backoff(Duration::from_millis(100));
+///
+/// assert_eq!(options.retries(), 2);
+/// ```
+///
+/// [`ChildrenRef::ask_one_with`]: struct.ChildrenRef.html#method.ask_one_with
+pub struct AskOptions {
+    timeout: Duration,
+    retries: usize,
+    backoff: Duration,
 }
 
 impl ChildrenRef {
@@ -29,15 +96,21 @@ impl ChildrenRef {
         id: BastionId,
         sender: Sender,
         path: Arc<BastionPath>,
+        name: String,
+        mailbox: MailboxConfig,
         children: Vec<ChildRef>,
         dispatchers: Vec<DispatcherType>,
+        handled: Vec<TypeId>,
     ) -> Self {
         ChildrenRef {
             id,
             sender,
             path,
+            name,
+            mailbox,
             children,
             dispatchers,
+            handled,
         }
     }
 
@@ -154,6 +227,154 @@ impl ChildrenRef {
         &self.children
     }
 
+    /// Adds an element to the running children group this
+    /// `ChildrenRef` is referencing, which will use the given
+    /// closure instead of the group's one (see
+    /// [`Children::with_exec`]).
+    ///
+    /// The element is registered in the group's dispatchers, is
+    /// supervised and restarted like the other elements of the
+    /// group and is taken into account by its resizer, but it
+    /// isn't added again when the whole group is restarted.
+    ///
+    /// This method returns a [`ChildRef`] referencing the added
+    /// element. Note that the list returned by [`elems`] isn't
+    /// updated.
+    ///
+    /// # Arguments
+    ///
+    /// * `init` - The closure taking a [`BastionContext`] and
+    ///     returning a [`Future`] that will be used by the added
+    ///     element.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// let devices = Bastion::children(|children| children.with_name("devices"))
+    ///     .expect("Couldn't create the children group.");
+    ///
+    /// // Once a device is discovered...
+    /// let device = devices.add_child(|ctx: BastionContext| async move {
+    ///     // Communicate with the device...
+    ///     # let _ = ctx;
+    ///     Ok(())
+    /// });
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Children::with_exec`]: children/struct.Children.html#method.with_exec
+    /// [`ChildRef`]: children/struct.ChildRef.html
+    /// [`elems`]: #method.elems
+    /// [`BastionContext`]: context/struct.BastionContext.html
+    /// [`Future`]: https://doc.rust-lang.org/std/future/trait.Future.html
+    pub fn add_child<I, F>(&self, init: I) -> ChildRef
+    where
+        I: Fn(BastionContext) -> F + Send + 'static,
+        F: Future<Output = Result<(), ()>> + Send + 'static,
+    {
+        let parent = Parent::children(self.clone());
+        let mut bcast = Broadcast::new(parent, BastionPathElement::Child(BastionId::new()));
+        debug!("ChildrenRef({}): Adding Child({}).", self.id(), bcast.id());
+
+        let mailbox = Arc::new(Mailbox::new(self.mailbox.clone()));
+        bcast.attach_mailbox(mailbox.clone());
+
+        let id = bcast.id().clone();
+        let sender = bcast.sender().clone();
+        let path = bcast.path().clone();
+        let child_ref = ChildRef::new(id, sender, self.name.clone(), path);
+
+        let msg = BastionMessage::deploy_child(bcast, mailbox, Init::new(init));
+        let env = Envelope::from_dead_letters(msg);
+        // The element is stopped with the group if it is gone.
+        self.send(env).ok();
+
+        child_ref
+    }
+
+    /// Stops an element of the children group this `ChildrenRef`
+    /// is referencing and removes it from the group, which won't
+    /// restart it.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(())` if
+    /// the element isn't part of the group or if the message
+    /// couldn't be sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `child` - The element to remove, which might have been
+    ///     added using [`add_child`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// # let devices = Bastion::children(|children| children).unwrap();
+    /// let device = devices.add_child(|ctx: BastionContext| async move {
+    ///     loop {
+    ///         let _ = ctx.recv().await?;
+    ///     }
+    /// });
+    ///
+    /// // Once the device is disconnected...
+    /// devices.remove_child(&device).expect("Couldn't remove the child.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`add_child`]: #method.add_child
+    pub fn remove_child(&self, child: &ChildRef) -> Result<(), ()> {
+        if child.path().iter().rev().nth(1) != Some(self.id()) {
+            return Err(());
+        }
+
+        debug!(
+            "ChildrenRef({}): Removing Child({}).",
+            self.id(),
+            child.id()
+        );
+        // The group drops the element once it stopped.
+        child.stop()
+    }
+
     /// Sends a message to the children group this `ChildrenRef`
     /// is referencing which will then send it to all of its
     /// elements.
@@ -229,6 +450,357 @@ impl ChildrenRef {
         self.send(env).map_err(|err| err.into_msg().unwrap())
     }
 
+    /// "Asks" a message to all the elements of the children group
+    /// this `ChildrenRef` is referencing, waits for all of their
+    /// answers concurrently and collects them, downcasted to `R`,
+    /// in the same order as [`elems`].
+    ///
+    /// Each element has up to `timeout` to answer, after which its
+    /// result is an [`AskError::Timeout`]. The result of an element
+    /// that answered with another type than `R` is an
+    /// [`AskError::UnexpectedResponse`].
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send to every element.
+    /// * `timeout` - How long each element has to answer.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    ///     # Bastion::init();
+    /// let children_ref = Bastion::children(|children| {
+    ///     children.with_redundancy(3).with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             msg! { ctx.recv().await?,
+    ///                 msg: &'static str =!> {
+    ///                     answer!(ctx, msg.len()).expect("Couldn't answer.");
+    ///                 };
+    ///                 _: _ => ();
+    ///             }
+    ///
+    ///             Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    ///     # Bastion::children(|children| {
+    ///         # children.with_exec(move |ctx: BastionContext| {
+    ///             # let children_ref = children_ref.clone();
+    ///             # async move {
+    /// let lengths: Vec<Result<usize, AskError>> = children_ref
+    ///     .ask_all_collect("Hello!", Duration::from_secs(1))
+    ///     .await;
+    /// assert_eq!(lengths.len(), 3);
+    ///                 #
+    ///                 # Ok(())
+    ///             # }
+    ///         # })
+    ///     # }).unwrap();
+    ///     #
+    ///     # Bastion::start();
+    ///     # Bastion::stop();
+    ///     # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`elems`]: #method.elems
+    /// [`AskError::Timeout`]: ../errors/enum.AskError.html#variant.Timeout
+    /// [`AskError::UnexpectedResponse`]: ../errors/enum.AskError.html#variant.UnexpectedResponse
+    pub async fn ask_all_collect<R, M>(&self, msg: M, timeout: Duration) -> Vec<Result<R, AskError>>
+    where
+        R: Message,
+        M: Message + Clone,
+    {
+        debug!(
+            "ChildrenRef({}): Asking {} elements: {:?}",
+            self.id(),
+            self.children.len(),
+            msg
+        );
+        let asks = self
+            .children
+            .iter()
+            .map(|child| child.ask_timeout::<R, M>(msg.clone(), timeout));
+
+        future::join_all(asks).await
+    }
+
+    /// "Asks" a message to one of the elements of the children
+    /// group this `ChildrenRef` is referencing and waits for its
+    /// answer, downcasted to `R`.
+    ///
+    /// The element is picked randomly. If it can't be reached, if
+    /// it dies before answering or if it doesn't answer within the
+    /// [`AskOptions`]'s timeout, the message is asked again to the
+    /// next element of the group, up to the amount of retries of
+    /// the options.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send.
+    /// * `options` - How long to wait for an answer and how to retry.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    ///     # Bastion::init();
+    /// let children_ref = Bastion::children(|children| {
+    ///     children.with_redundancy(3).with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             msg! { ctx.recv().await?,
+    ///                 msg: &'static str =!> {
+    ///                     answer!(ctx, msg.len()).expect("Couldn't answer.");
+    ///                 };
+    ///                 _: _ => ();
+    ///             }
+    ///
+    ///             Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    ///     # Bastion::children(|children| {
+    ///         # children.with_exec(move |ctx: BastionContext| {
+    ///             # let children_ref = children_ref.clone();
+    ///             # async move {
+    /// let options = AskOptions::default()
+    ///     .with_timeout(Duration::from_millis(500))
+    ///     .with_retries(2);
+    /// let length: usize = children_ref
+    ///     .ask_one_with("Hello!", options)
+    ///     .await
+    ///     .expect("Couldn't ask.");
+    /// assert_eq!(length, 6);
+    ///                 #
+    ///                 # Ok(())
+    ///             # }
+    ///         # })
+    ///     # }).unwrap();
+    ///     #
+    ///     # Bastion::start();
+    ///     # Bastion::stop();
+    ///     # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`AskOptions`]: struct.AskOptions.html
+    pub async fn ask_one_with<R, M>(&self, msg: M, options: AskOptions) -> Result<R, AskError>
+    where
+        R: Message,
+        M: Message + Clone,
+    {
+        if self.children.is_empty() {
+            return Err(AskError::SendFailed);
+        }
+
+        let start = rand::random::<usize>() % self.children.len();
+        let mut attempt = 0;
+        loop {
+            let child = &self.children[(start + attempt) % self.children.len()];
+            debug!(
+                "ChildrenRef({}): Asking Child({}) (attempt {}): {:?}",
+                self.id(),
+                child.id(),
+                attempt + 1,
+                msg
+            );
+            match child.ask_timeout(msg.clone(), options.timeout).await {
+                // Only failures of the recipient are retried, another
+                // element would answer with the same type.
+                Err(AskError::UnexpectedResponse) => return Err(AskError::UnexpectedResponse),
+                Err(err) if attempt < options.retries => {
+                    trace!(
+                        "ChildrenRef({}): Child({}) couldn't answer: {:?}",
+                        self.id(),
+                        child.id(),
+                        err
+                    );
+                    attempt += 1;
+                    executor::sleep(options.backoff).await;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Returns a [`TypedChildrenRef<M>`] only sending messages of
+    /// type `M` to the children group this `ChildrenRef` is
+    /// referencing.
+    ///
+    /// This method returns `Err(())` if the group's elements didn't
+    /// declare that they handle `M` (see
+    /// [`Children::with_message_type`]).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// let children_ref = Bastion::children(|children| {
+    ///     children
+    ///         .with_message_type::<&'static str>()
+    ///         .with_exec(|ctx: BastionContext| async move {
+    ///             loop {
+    ///                 msg! { ctx.recv().await?,
+    ///                     msg: &'static str => {
+    ///                         // Handle the message...
+    ///                     };
+    ///                     _: _ => ();
+    ///                 }
+    ///             }
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    /// let typed = children_ref
+    ///     .typed::<&'static str>()
+    ///     .expect("The group doesn't handle &'static str.");
+    /// typed.broadcast("Hello!").expect("Couldn't broadcast.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`TypedChildrenRef<M>`]: struct.TypedChildrenRef.html
+    /// [`Children::with_message_type`]: ../children/struct.Children.html#method.with_message_type
+    pub fn typed<M: Message>(&self) -> Result<TypedChildrenRef<M>, ()> {
+        if !self.handled.contains(&TypeId::of::<M>()) {
+            debug!(
+                "ChildrenRef({}): Doesn't handle messages of type: {}",
+                self.id(),
+                std::any::type_name::<M>()
+            );
+            return Err(());
+        }
+
+        Ok(TypedChildrenRef {
+            children_ref: self.clone(),
+            _msg: PhantomData,
+        })
+    }
+
+    /// Returns a [`GroupService`] asking its requests to the
+    /// elements of the children group this `ChildrenRef` is
+    /// referencing, and resolving to their answers downcasted to
+    /// `Res`.
+    ///
+    /// The elements must answer within 5 seconds by default (see
+    /// [`GroupService::with_timeout`]).
+    ///
+    /// [`GroupService`]: ../service/struct.GroupService.html
+    /// [`GroupService::with_timeout`]: ../service/struct.GroupService.html#method.with_timeout
+    #[cfg(feature = "tower")]
+    pub fn into_service<Req, Res>(self) -> GroupService<Req, Res>
+    where
+        Req: Message,
+        Res: Message,
+    {
+        GroupService::new(self.id.clone())
+    }
+
+    /// Attaches a `tokio` [`mpsc::Receiver`] to the children group
+    /// this `ChildrenRef` is referencing, delivering the items it
+    /// receives to the running elements of the group in turn.
+    ///
+    /// The items are delivered by a forwarder child, created under
+    /// a new supervisor, which stops once every sender of the
+    /// channel was dropped.
+    ///
+    /// This method returns the supervisor of the forwarder if it
+    /// succeeded, or `Err(())` otherwise.
+    ///
+    /// # Argument
+    ///
+    /// * `receiver` - The receiver of the channel.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use bastion::prelude::*;
+    /// # use tokio::sync::mpsc;
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// # Bastion::start();
+    /// #
+    /// let children_ref = Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| async move {
+    ///         loop {
+    ///             msg! { ctx.recv().await?,
+    ///                 event: u64 => {
+    ///                     // Handle the event...
+    ///                 };
+    ///                 _: _ => ();
+    ///             }
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    /// let (sender, receiver) = mpsc::channel::<u64>(16);
+    /// children_ref
+    ///     .attach_receiver(receiver)
+    ///     .expect("Couldn't attach the receiver.");
+    ///
+    /// sender.try_send(42).ok();
+    /// # }
+    /// ```
+    ///
+    /// [`mpsc::Receiver`]: https://docs.rs/tokio/1/tokio/sync/mpsc/struct.Receiver.html
+    #[cfg(feature = "tokio-channels")]
+    pub fn attach_receiver<M: Message>(
+        &self,
+        receiver: tokio::sync::mpsc::Receiver<M>,
+    ) -> Result<SupervisorRef, ()> {
+        debug!("ChildrenRef({}): Attaching channel receiver.", self.id());
+        channel::attach_receiver(self, receiver)
+    }
+
     /// Sends a message to the children group this `ChildrenRef`
     /// is referencing to tell it to stop all of its running
     /// elements.
@@ -311,14 +883,155 @@ impl ChildrenRef {
         self.send(env).map_err(|_| ())
     }
 
+    /// Sends a message to the children group this `ChildrenRef`
+    /// is referencing to tell all of its elements to stop
+    /// dequeuing messages until [`resume`] is called.
+    ///
+    /// The messages received in the meantime are kept in the
+    /// elements' mailbox, and the elements can still be stopped
+    /// or killed while they are paused.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(())`
+    /// otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// # let children_ref = Bastion::children(|children| children).unwrap();
+    /// children_ref.pause().expect("Couldn't send the message.");
+    ///
+    /// // Migrate the workers, the queued messages being kept...
+    ///
+    /// children_ref.resume().expect("Couldn't send the message.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`resume`]: #method.resume
+    pub fn pause(&self) -> Result<(), ()> {
+        debug!("ChildrenRef({}): Pausing.", self.id());
+        let msg = BastionMessage::pause();
+        let env = Envelope::from_dead_letters(msg);
+        self.send(env).map_err(|_| ())
+    }
+
+    /// Sends a message to the children group this `ChildrenRef`
+    /// is referencing to tell all of its elements, paused with
+    /// [`pause`], to dequeue their messages again.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(())`
+    /// otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// # let children_ref = Bastion::children(|children| children).unwrap();
+    /// # children_ref.pause().unwrap();
+    /// children_ref.resume().expect("Couldn't send the message.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`pause`]: #method.pause
+    pub fn resume(&self) -> Result<(), ()> {
+        debug!("ChildrenRef({}): Resuming.", self.id());
+        let msg = BastionMessage::resume();
+        let env = Envelope::from_dead_letters(msg);
+        self.send(env).map_err(|_| ())
+    }
+
+    /// Sends a message to the children group this `ChildrenRef`
+    /// is referencing to tell it to kill all of its elements and
+    /// have them restarted by its supervisor, following the restart
+    /// strategy as if they had faulted.
+    ///
+    /// The restarted elements keep their mailboxes, but the messages
+    /// they were handling are lost.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(())`
+    /// otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// # let children_ref = Bastion::children(|children| children).unwrap();
+    /// children_ref.restart().expect("Couldn't send the message.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    pub fn restart(&self) -> Result<(), ()> {
+        debug!("ChildrenRef({}): Restarting.", self.id());
+        let msg = BastionMessage::restart();
+        let env = Envelope::from_dead_letters(msg);
+        self.send(env).map_err(|_| ())
+    }
+
     pub(crate) fn send(&self, env: Envelope) -> Result<(), Envelope> {
         trace!("ChildrenRef({}): Sending message: {:?}", self.id(), env);
+        if system::refuses(&env) {
+            return Err(env);
+        }
+
         self.sender.unbounded_send(env).or_else(|err| {
-            SYSTEM
-                .dead_letters()
-                .sender
-                .unbounded_send(err.into_inner())
-                .map_err(|err| err.into_inner())
+            dead_letters::publish(self.path.clone(), err.into_inner());
+            Ok(())
         })
     }
 
@@ -332,6 +1045,185 @@ impl ChildrenRef {
     }
 }
 
+impl<M: Message> TypedChildrenRef<M> {
+    /// Returns the [`ChildrenRef`] this `TypedChildrenRef` sends
+    /// its messages with.
+    ///
+    /// [`ChildrenRef`]: struct.ChildrenRef.html
+    pub fn children_ref(&self) -> &ChildrenRef {
+        &self.children_ref
+    }
+
+    /// Sends a message to one of the elements of the children
+    /// group, picked randomly.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)`
+    /// otherwise (e.g. when the group doesn't have any element).
+    ///
+    /// # Argument
+    ///
+    /// * `msg` - The message to send.
+    pub fn tell_one(&self, msg: M) -> Result<(), M> {
+        let children = self.children_ref.elems();
+        if children.is_empty() {
+            return Err(msg);
+        }
+
+        let child = &children[rand::random::<usize>() % children.len()];
+        child.tell_anonymously(msg)
+    }
+
+    /// "Asks" a message to one of the elements of the children
+    /// group, picked randomly, returning the [`Answer`] it will
+    /// send back.
+    ///
+    /// This method returns the [`Answer`] if it succeeded, or
+    /// `Err(msg)` otherwise (e.g. when the group doesn't have any
+    /// element).
+    ///
+    /// # Argument
+    ///
+    /// * `msg` - The message to send.
+    ///
+    /// [`Answer`]: ../message/struct.Answer.html
+    pub fn ask_one(&self, msg: M) -> Result<Answer, M> {
+        let children = self.children_ref.elems();
+        if children.is_empty() {
+            return Err(msg);
+        }
+
+        let child = &children[rand::random::<usize>() % children.len()];
+        child.ask_anonymously(msg)
+    }
+
+    /// Sends a message to all the elements of the children group,
+    /// as [`ChildrenRef::broadcast`] does.
+    ///
+    /// # Argument
+    ///
+    /// * `msg` - The message to send.
+    ///
+    /// [`ChildrenRef::broadcast`]: struct.ChildrenRef.html#method.broadcast
+    pub fn broadcast(&self, msg: M) -> Result<(), M> {
+        self.children_ref.broadcast(msg)
+    }
+
+    /// "Asks" a message to all the elements of the children group
+    /// and collects their answers, as [`ChildrenRef::ask_all_collect`]
+    /// does.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send to every element.
+    /// * `timeout` - How long each element has to answer.
+    ///
+    /// [`ChildrenRef::ask_all_collect`]: struct.ChildrenRef.html#method.ask_all_collect
+    pub async fn ask_all_collect<R: Message>(
+        &self,
+        msg: M,
+        timeout: Duration,
+    ) -> Vec<Result<R, AskError>>
+    where
+        M: Clone,
+    {
+        self.children_ref.ask_all_collect(msg, timeout).await
+    }
+
+    /// "Asks" a message to one of the elements of the children
+    /// group, retrying against the other ones, as
+    /// [`ChildrenRef::ask_one_with`] does.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send.
+    /// * `options` - How long to wait for an answer and how to retry.
+    ///
+    /// [`ChildrenRef::ask_one_with`]: struct.ChildrenRef.html#method.ask_one_with
+    pub async fn ask_one_with<R: Message>(&self, msg: M, options: AskOptions) -> Result<R, AskError>
+    where
+        M: Clone,
+    {
+        self.children_ref.ask_one_with(msg, options).await
+    }
+}
+
+impl<M> Clone for TypedChildrenRef<M> {
+    fn clone(&self) -> Self {
+        TypedChildrenRef {
+            children_ref: self.children_ref.clone(),
+            _msg: PhantomData,
+        }
+    }
+}
+
+impl<M> Debug for TypedChildrenRef<M> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("TypedChildrenRef")
+            .field("children_ref", &self.children_ref)
+            .field("msg", &std::any::type_name::<M>())
+            .finish()
+    }
+}
+
+impl AskOptions {
+    /// Sets how long a recipient has to answer before it is
+    /// considered as failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait for an answer.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many times the message is asked again to another
+    /// element when the recipient failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `retries` - The maximum amount of retries.
+    pub fn with_retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets how long to wait before retrying.
+    ///
+    /// # Arguments
+    ///
+    /// * `backoff` - The delay between two attempts.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Returns how long a recipient has to answer.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the maximum amount of retries.
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+
+    /// Returns the delay between two attempts.
+    pub fn backoff(&self) -> Duration {
+        self.backoff
+    }
+}
+
+impl Default for AskOptions {
+    fn default() -> Self {
+        AskOptions {
+            timeout: Duration::from_secs(5),
+            retries: 0,
+            backoff: Duration::from_millis(0),
+        }
+    }
+}
+
 impl PartialEq for ChildrenRef {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
@@ -0,0 +1,310 @@
+//!
+//! A circuit breaker stops delivering messages to the elements of
+//! a children group once they failed too many times in a row,
+//! giving them (or whatever they depend on) time to recover.
+//!
+//! While the breaker is open, the messages sent to the group are
+//! fast-failed (the answers of asked messages resolve to an error
+//! and the messages are forwarded to the dead letters) or rerouted
+//! to a fallback child. Once the cool-down elapsed, the breaker is
+//! half-open: a single probe message is delivered, closing the
+//! breaker if it was handled successfully, or opening it again
+//! otherwise.
+//!
+//! Breakers are attached to a children group using
+//! [`Children::with_circuit_breaker`].
+//!
+//! [`Children::with_circuit_breaker`]: ../children/struct.Children.html#method.with_circuit_breaker
+use crate::child_ref::ChildRef;
+use crate::dead_letters;
+use crate::envelope::{Envelope, SignedMessage};
+use crate::events::{self, SupervisionEvent};
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use crate::middleware::{Middleware, Next};
+use crate::path::BastionPath;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, trace};
+
+#[derive(Debug, Clone)]
+/// A circuit breaker run around the delivery of the messages sent
+/// to the elements of a children group.
+///
+/// Clones of a breaker share its state.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::time::Duration;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// // Stops delivering messages for 30 seconds after 5 failures in a row...
+/// let breaker = CircuitBreaker::new(5, Duration::from_secs(30));
+///
+/// Bastion::children(|children| {
+///     children
+///         .with_circuit_breaker(breaker.clone())
+///         .with_exec(|ctx| async move {
+///             loop {
+///                 let msg = ctx.recv().await?;
+///                 // Call a flaky downstream API...
+///             }
+///         })
+/// }).expect("Couldn't create the children group.");
+///
+/// assert_eq!(breaker.state(), CircuitState::Closed);
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub struct CircuitBreaker {
+    max_failures: usize,
+    cooldown: Duration,
+    fallback: Option<ChildRef>,
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The state of a [`CircuitBreaker`].
+///
+/// [`CircuitBreaker`]: struct.CircuitBreaker.html
+pub enum CircuitState {
+    /// The messages are delivered.
+    Closed,
+    /// The messages are fast-failed (or rerouted to the fallback
+    /// child) until the cool-down elapsed.
+    Open,
+    /// A probe message was delivered, deciding whether the breaker
+    /// closes or opens again.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Shared {
+    // The path of the children group the breaker is attached to.
+    path: Mutex<Option<Arc<BastionPath>>>,
+    breaker: Mutex<Breaker>,
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: CircuitState,
+    // The amount of failures since the last success.
+    failures: usize,
+    // When the breaker opened, or when the probe was delivered
+    // if it is half-open.
+    since: Instant,
+}
+
+impl CircuitBreaker {
+    /// Creates a new circuit breaker.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_failures` - The amount of failures in a row of the
+    ///     group's elements after which the breaker opens.
+    /// * `cooldown` - How long the breaker stays open before a
+    ///     probe message is delivered.
+    pub fn new(max_failures: usize, cooldown: Duration) -> Self {
+        let shared = Shared {
+            path: Mutex::new(None),
+            breaker: Mutex::new(Breaker {
+                state: CircuitState::Closed,
+                failures: 0,
+                since: Instant::now(),
+            }),
+        };
+
+        CircuitBreaker {
+            max_failures: max_failures.max(1),
+            cooldown,
+            fallback: None,
+            shared: Arc::new(shared),
+        }
+    }
+
+    /// Sets the child the messages are rerouted to while the
+    /// breaker is open, instead of being fast-failed. The child's
+    /// answers are sent to the messages' original senders.
+    ///
+    /// # Argument
+    ///
+    /// * `fallback` - The child the messages are rerouted to.
+    pub fn with_fallback(mut self, fallback: ChildRef) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Returns the current state of the breaker.
+    pub fn state(&self) -> CircuitState {
+        // FIXME: panics
+        self.shared.breaker.lock().unwrap().state
+    }
+
+    pub(crate) fn attach(&self, path: Arc<BastionPath>) {
+        // FIXME: panics
+        *self.shared.path.lock().unwrap() = Some(path);
+    }
+
+    /// Records that an element of the group handled a message.
+    pub(crate) fn record_success(&self) {
+        // FIXME: panics
+        let mut breaker = self.shared.breaker.lock().unwrap();
+        breaker.failures = 0;
+        if breaker.state != CircuitState::Closed {
+            self.transition(&mut breaker, CircuitState::Closed);
+        }
+    }
+
+    /// Records that an element of the group failed.
+    pub(crate) fn record_failure(&self) {
+        // FIXME: panics
+        let mut breaker = self.shared.breaker.lock().unwrap();
+        breaker.failures += 1;
+        match breaker.state {
+            CircuitState::Closed if breaker.failures >= self.max_failures => {
+                self.transition(&mut breaker, CircuitState::Open);
+            }
+            CircuitState::HalfOpen => self.transition(&mut breaker, CircuitState::Open),
+            _ => (),
+        }
+    }
+
+    // Returns whether a message can be delivered, opening the
+    // breaker half-way if the cool-down elapsed.
+    fn allows_delivery(&self) -> bool {
+        // FIXME: panics
+        let mut breaker = self.shared.breaker.lock().unwrap();
+        match breaker.state {
+            CircuitState::Closed => true,
+            // A new probe is delivered if the previous one was
+            // lost (e.g. because the child was stopped).
+            CircuitState::Open | CircuitState::HalfOpen
+                if breaker.since.elapsed() >= self.cooldown =>
+            {
+                self.transition(&mut breaker, CircuitState::HalfOpen);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn transition(&self, breaker: &mut Breaker, state: CircuitState) {
+        breaker.state = state;
+        breaker.since = Instant::now();
+
+        // FIXME: panics
+        let path = match self.shared.path.lock().unwrap().clone() {
+            Some(path) => path,
+            None => return,
+        };
+
+        debug!("CircuitBreaker({}): Transitioning to {:?}.", path, state);
+        #[cfg(feature = "metrics")]
+        metrics::set_circuit_state(&path, state);
+        events::emit(SupervisionEvent::CircuitStateChanged { path, state });
+    }
+
+    fn fast_fail(&self, mut msg: SignedMessage, next: Next) {
+        if let Some(fallback) = &self.fallback {
+            trace!("CircuitBreaker: Rerouting message to the fallback child.");
+            if let Err(env) = fallback.send(Envelope::from_signed(msg)) {
+                dead_letters::publish(fallback.path().clone(), env);
+            }
+
+            return;
+        }
+
+        trace!("CircuitBreaker: Fast-failing message.");
+        // The answer of an asked message resolves to an error once
+        // its sender is dropped.
+        msg.msg.take_sender();
+        dead_letters::publish_message(next.path().clone(), msg.msg, msg.sign);
+    }
+}
+
+impl Middleware for CircuitBreaker {
+    fn around(&self, msg: SignedMessage, next: Next) {
+        if self.allows_delivery() {
+            next.run(msg);
+        } else {
+            self.fast_fail(msg, next);
+        }
+    }
+}
+
+impl CircuitState {
+    #[cfg(feature = "metrics")]
+    pub(crate) fn as_gauge(self) -> f64 {
+        match self {
+            CircuitState::Closed => 0.0,
+            CircuitState::HalfOpen => 1.0,
+            CircuitState::Open => 2.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn opens_after_max_failures_in_a_row() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        breaker.record_failure();
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.allows_delivery());
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.allows_delivery());
+    }
+
+    #[test]
+    fn probes_once_the_cooldown_elapsed() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(0));
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        assert!(breaker.allows_delivery());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        assert!(breaker.allows_delivery());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn half_open_delivers_a_single_probe() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(10));
+        breaker.record_failure();
+        thread::sleep(Duration::from_millis(10));
+
+        assert!(breaker.allows_delivery());
+        assert!(!breaker.allows_delivery());
+    }
+}
@@ -0,0 +1,1753 @@
+//!
+//! Cluster membership allows nodes to know which other nodes are
+//! part of the cluster and whether they can be reached, and is
+//! available with the `cluster` feature.
+//!
+//! A node that was bound to an address (see [`Bastion::bind`]) joins
+//! a cluster with [`Cluster::join`], by contacting the seed nodes of
+//! its [`ClusterConfig`]. Nodes then periodically gossip their view
+//! of the membership to each other, SWIM-style: changes spread to the
+//! whole cluster, and a node that was wrongly suspected to be
+//! unreachable refutes it by gossiping itself with a higher
+//! incarnation number.
+//!
+//! The gossip messages double as heartbeats, which are fed to a
+//! phi-accrual failure detector: once the suspicion level (phi) of a
+//! node goes above the configured threshold, it is marked as
+//! unreachable. When the cluster is partitioned, the configured
+//! [`SplitBrainStrategy`] decides which side keeps running, and the
+//! members of the other side are downed.
+//!
+//! Supervisors subscribed with [`Cluster::subscribe`] get each
+//! [`MemberEvent`] broadcasted to their children, while children
+//! registered with [`LeaderElection::register`] are notified when
+//! this node becomes (or stops being) the leader of the cluster.
+//!
+//! Every member also gossips the names of the named dispatchers (see
+//! [`DispatcherType::Named`]) registered on it, so that
+//! [`Cluster::broadcast_message`] and [`Cluster::tell_one`] reach the
+//! children groups of a dispatcher on every node.
+//!
+//! [`Bastion::bind`]: ../struct.Bastion.html#method.bind
+//! [`Cluster::join`]: struct.Cluster.html#method.join
+//! [`ClusterConfig`]: struct.ClusterConfig.html
+//! [`Cluster::subscribe`]: struct.Cluster.html#method.subscribe
+//! [`MemberEvent`]: enum.MemberEvent.html
+//! [`LeaderElection::register`]: struct.LeaderElection.html#method.register
+//! [`SplitBrainStrategy`]: enum.SplitBrainStrategy.html
+//! [`DispatcherType::Named`]: ../dispatcher/enum.DispatcherType.html#variant.Named
+//! [`Cluster::broadcast_message`]: struct.Cluster.html#method.broadcast_message
+//! [`Cluster::tell_one`]: struct.Cluster.html#method.tell_one
+use crate::child_ref::ChildRef;
+use crate::codec::RemoteMessage;
+use crate::discovery::Discovery;
+use crate::dispatcher::BroadcastTarget;
+use crate::envelope::{RefAddr, SignedMessage};
+use crate::errors::ClusterError;
+use crate::message::Msg;
+use crate::remote::{self, NodeId, RemoteNode};
+use crate::supervisor::SupervisorRef;
+use crate::system::SYSTEM;
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, trace, warn};
+
+lazy_static! {
+    static ref STATE: Mutex<Option<ClusterState>> = Mutex::new(None);
+    // The functions called before this node leaves the cluster.
+    static ref LEAVE_HOOKS: Mutex<Vec<Box<dyn Fn() + Send>>> = Mutex::new(Vec::new());
+    static ref LEADERSHIP: Mutex<Leadership> = Mutex::new(Leadership::default());
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A `struct` allowing to join or leave a cluster, and to get its
+/// membership.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use bastion::prelude::*;
+///
+/// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// Bastion::init();
+/// Bastion::bind("10.0.0.1:4222").expect("Couldn't bind the node.");
+///
+/// let config = ClusterConfig::default().with_seed("10.0.0.2:4222".parse()?);
+/// Cluster::join(config).expect("Couldn't join the cluster.");
+///
+/// Bastion::start();
+///
+/// for member in Cluster::members() {
+///     println!("{} ({}): {:?}", member.id(), member.addr(), member.status());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Cluster {
+    _private: (),
+}
+
+#[derive(Debug, Clone)]
+/// The configuration of the cluster membership of a node, as
+/// given to [`Cluster::join`].
+///
+/// [`Cluster::join`]: struct.Cluster.html#method.join
+pub struct ClusterConfig {
+    seeds: Vec<SocketAddr>,
+    advertised_addr: Option<SocketAddr>,
+    gossip_interval: Duration,
+    phi_threshold: f64,
+    max_samples: usize,
+    min_std_deviation: Duration,
+    acceptable_pause: Duration,
+    split_brain_strategy: Option<SplitBrainStrategy>,
+    stable_after: Duration,
+    discovery: Option<Arc<dyn Discovery>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The strategy used to resolve a partition of the cluster (a
+/// "split brain"), where each side would otherwise keep running
+/// as if the members of the other side crashed.
+///
+/// Once the set of unreachable members didn't change for the
+/// configured duration (see [`ClusterConfig::with_stable_after`]),
+/// each side applies the strategy: the side that wins downs the
+/// unreachable members, while the nodes of the side that loses down
+/// themselves.
+///
+/// [`ClusterConfig::with_stable_after`]: struct.ClusterConfig.html#method.with_stable_after
+pub enum SplitBrainStrategy {
+    /// Keeps the side with the most members. If both sides have the
+    /// same number of members, the side with the oldest member is
+    /// kept.
+    KeepMajority,
+    /// Keeps the side with the oldest member.
+    KeepOldest,
+    /// Keeps the side with at least the given number of members.
+    /// With a quorum of more than half of the size of the cluster,
+    /// at most one side is kept (but none might be).
+    StaticQuorum(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// The status of a member of the cluster.
+pub enum MemberStatus {
+    /// The member is part of the cluster and can be reached.
+    Up,
+    /// The member is part of the cluster but was detected to be
+    /// unreachable.
+    Unreachable,
+    /// The member was downed by the split-brain resolver (see
+    /// [`SplitBrainStrategy`]).
+    ///
+    /// [`SplitBrainStrategy`]: enum.SplitBrainStrategy.html
+    Down,
+    /// The member left the cluster.
+    Left,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A member of the cluster, as returned by [`Cluster::members`].
+///
+/// [`Cluster::members`]: struct.Cluster.html#method.members
+pub struct Member {
+    id: NodeId,
+    addr: SocketAddr,
+    status: MemberStatus,
+    joined_at: u64,
+    dispatchers: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+/// A change of the membership of the cluster, broadcasted to
+/// the children of the supervisors subscribed with
+/// [`Cluster::subscribe`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// let supervisor = Bastion::supervisor(|sp| {
+///     sp.children(|children| {
+///         children.with_exec(|ctx: BastionContext| async move {
+///             loop {
+///                 msg! { ctx.recv().await?,
+///                     ref event: MemberEvent => {
+///                         println!("{:?}", event);
+///                         // Rebalance the work...
+///                     };
+///                     _: _ => ();
+///                 }
+///             }
+///         })
+///     })
+/// }).expect("Couldn't create the supervisor.");
+///
+/// Cluster::subscribe(&supervisor);
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`Cluster::subscribe`]: struct.Cluster.html#method.subscribe
+pub enum MemberEvent {
+    /// A node joined the cluster.
+    MemberUp(Member),
+    /// A member was detected to be unreachable.
+    MemberUnreachable(Member),
+    /// A member that was unreachable can be reached again.
+    MemberReachable(Member),
+    /// A member left the cluster.
+    MemberLeft(Member),
+    /// A member was downed by the split-brain resolver. When this
+    /// node is the one being downed, it isn't part of the cluster
+    /// anymore and should stop the work that the other side of the
+    /// partition is taking over.
+    MemberDowned(Member),
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A `struct` allowing children to be notified when this node
+/// gains or loses the leadership of the cluster, to coordinate
+/// the work that must only be done by one node at a time (e.g.
+/// scheduling jobs).
+///
+/// The leader is the oldest member of the cluster that is up, so
+/// that the members elect it without exchanging any additional
+/// message and that it only changes when it leaves the cluster or
+/// becomes unreachable. A node that didn't join a cluster isn't the
+/// leader of any, but a node joining a cluster without any seed is
+/// the leader of the new cluster.
+///
+/// A leader is only elected by a node that can reach a majority of
+/// the members that weren't removed from the cluster, so that the
+/// sides of a partition don't each elect their own: while members
+/// are unreachable, the smaller side of the partition (or both sides
+/// if they have the same size) has no leader, until the split-brain
+/// resolver (see [`ClusterConfig::with_split_brain_strategy`]) downs
+/// the members of the other side.
+///
+/// [`ClusterConfig::with_split_brain_strategy`]: struct.ClusterConfig.html#method.with_split_brain_strategy
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// let children = Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| async move {
+///         LeaderElection::register(ctx.current());
+///         loop {
+///             msg! { ctx.recv().await?,
+///                 event: LeadershipEvent => match event {
+///                     LeadershipEvent::Elected => println!("Scheduling the jobs..."),
+///                     LeadershipEvent::Revoked => println!("Unscheduling the jobs..."),
+///                 };
+///                 _: _ => ();
+///             }
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub struct LeaderElection {
+    _private: (),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A change of the leadership of this node, sent to the children
+/// registered with [`LeaderElection::register`].
+///
+/// [`LeaderElection::register`]: struct.LeaderElection.html#method.register
+pub enum LeadershipEvent {
+    /// This node became the leader of the cluster.
+    Elected,
+    /// This node isn't the leader of the cluster anymore.
+    Revoked,
+}
+
+#[derive(Default)]
+struct Leadership {
+    // Whether this node was the leader the last time it changed.
+    leading: bool,
+    children: Vec<ChildRef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+// A member as gossiped between nodes.
+struct MemberState {
+    id: NodeId,
+    addr: SocketAddr,
+    status: MemberStatus,
+    // Incremented by the member itself to refute that it
+    // is unreachable or (once) to leave the cluster.
+    incarnation: u64,
+    // When the member joined, in milliseconds since the UNIX
+    // epoch.
+    joined_at: u64,
+    // The names of the named dispatchers registered on the member,
+    // which increments its incarnation when they change.
+    #[serde(default)]
+    dispatchers: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum ClusterMessage {
+    // Sent by a joining node to the seeds, which answer with
+    // their view of the membership.
+    Join(MemberState),
+    Gossip(Vec<MemberState>),
+}
+
+struct ClusterState {
+    config: ClusterConfig,
+    me: MemberState,
+    members: FxHashMap<NodeId, MemberEntry>,
+    // The seeds that couldn't be contacted yet.
+    seeds: Vec<SocketAddr>,
+    subscribers: Vec<SupervisorRef>,
+    running: Arc<AtomicBool>,
+    // When the membership changed for the last time.
+    last_change: Instant,
+    // Whether the other members downed this node.
+    downed: bool,
+}
+
+struct MemberEntry {
+    state: MemberState,
+    node: Option<RemoteNode>,
+    detector: PhiAccrualDetector,
+}
+
+// A phi-accrual failure detector (as described by Hayashibara et
+// al.), computing how likely it is that a node is unreachable from
+// the intervals between the heartbeats received from it.
+#[derive(Debug)]
+struct PhiAccrualDetector {
+    // The last intervals, in milliseconds.
+    intervals: VecDeque<f64>,
+    max_samples: usize,
+    min_std_deviation: f64,
+    acceptable_pause: f64,
+    // The interval expected before the first one was measured.
+    first_interval: f64,
+    last: Option<Instant>,
+}
+
+impl Cluster {
+    /// Makes this node join a cluster, by contacting the seeds of
+    /// the configuration until one of them answers. A node without
+    /// any seed starts a new cluster that other nodes can join.
+    ///
+    /// The node must have been bound to an address with
+    /// [`Bastion::bind`] beforehand.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(error)` if
+    /// the node wasn't bound or already joined a cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration of the cluster membership.
+    ///
+    /// [`Bastion::bind`]: ../struct.Bastion.html#method.bind
+    pub fn join(config: ClusterConfig) -> Result<(), ClusterError> {
+        let mut state = STATE.lock().unwrap();
+        if state.is_some() {
+            return Err(ClusterError::AlreadyJoined);
+        }
+
+        let addr = config
+            .advertised_addr
+            .or_else(remote::bound_addr)
+            .filter(|addr| !addr.ip().is_unspecified())
+            .ok_or(ClusterError::NotBound)?;
+
+        let me = MemberState {
+            id: remote::local_node_id(),
+            addr,
+            status: MemberStatus::Up,
+            incarnation: 0,
+            joined_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis() as u64),
+            dispatchers: SYSTEM.dispatcher().named(),
+        };
+        debug!("Cluster: Joining as {} ({}).", me.id, me.addr);
+
+        let discovery = config.discovery.clone();
+        let new = ClusterState::new(config, me);
+        let running = new.running.clone();
+        let interval = new.config.gossip_interval;
+        *state = Some(new);
+
+        thread::Builder::new()
+            .name("bastion-cluster".to_string())
+            .spawn(move || {
+                while running.load(Ordering::SeqCst) {
+                    thread::sleep(interval);
+                    tick();
+                }
+            })
+            .expect("cannot start the cluster thread");
+
+        drop(state);
+        if let Some(discovery) = discovery {
+            if let Err(err) = discovery.register(addr) {
+                warn!("Cluster: Couldn't register to the discovery: {}", err);
+            }
+        }
+
+        elect();
+        Ok(())
+    }
+
+    /// Makes this node leave the cluster it joined, letting the
+    /// other members know about it.
+    pub fn leave() {
+        remove(MemberStatus::Left);
+    }
+
+    /// Returns the members of the cluster this node joined
+    /// (including itself), or an empty list if it didn't join any.
+    pub fn members() -> Vec<Member> {
+        let state = STATE.lock().unwrap();
+        let state = match state.as_ref() {
+            Some(state) => state,
+            None => return Vec::new(),
+        };
+
+        let others = state.members.values().map(|entry| entry.state.member());
+        Some(state.me.member()).into_iter().chain(others).collect()
+    }
+
+    /// Returns this node as a member of the cluster, if it joined
+    /// one.
+    pub fn local_member() -> Option<Member> {
+        let state = STATE.lock().unwrap();
+        state.as_ref().map(|state| state.me.member())
+    }
+
+    /// Returns the leader of the cluster, which is its oldest member
+    /// that is up, or `None` if this node didn't join a cluster or
+    /// can't reach a majority of its members.
+    ///
+    /// Every member elects the same leader once the membership
+    /// changes were gossiped to the whole cluster.
+    pub fn leader() -> Option<Member> {
+        elect_leader(Cluster::members())
+    }
+
+    /// Returns whether this node is the leader of the cluster.
+    pub fn is_leader() -> bool {
+        let local = remote::local_node_id();
+        Cluster::leader().map_or(false, |leader| leader.id == local)
+    }
+
+    /// Returns the members of the cluster that are up and have a
+    /// named dispatcher with the given name registered (including
+    /// this node).
+    ///
+    /// # Argument
+    ///
+    /// * `name` - The name of the dispatcher.
+    pub fn dispatcher_members(name: &str) -> Vec<Member> {
+        Cluster::members()
+            .into_iter()
+            .filter(|member| member.status == MemberStatus::Up)
+            .filter(|member| {
+                member
+                    .dispatchers
+                    .iter()
+                    .any(|registered| registered == name)
+            })
+            .collect()
+    }
+
+    /// Broadcasts a message to the children groups of the targeted
+    /// named dispatchers, on this node and on every member of the
+    /// cluster they are registered on (see
+    /// [`Cluster::dispatcher_members`]).
+    ///
+    /// The message is serialized to be sent to the other members,
+    /// so its type must be registered on every node (see
+    /// [`register_message`]). The children receive it as an
+    /// `Arc<SignedMessage>`, as with [`BastionContext::broadcast_message`].
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The dispatchers the message is broadcasted to.
+    /// * `msg` - The broadcasted message.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use bastion::prelude::*;
+    /// #
+    /// # fn run() {
+    /// bastion::remote::register_message::<String>();
+    /// Cluster::broadcast_message(
+    ///     BroadcastTarget::Group("orders".to_string()),
+    ///     "order #1".to_string(),
+    /// );
+    /// # }
+    /// ```
+    ///
+    /// [`Cluster::dispatcher_members`]: #method.dispatcher_members
+    /// [`register_message`]: ../remote/fn.register_message.html
+    /// [`BastionContext::broadcast_message`]: ../context/struct.BastionContext.html#method.broadcast_message
+    pub fn broadcast_message<M: RemoteMessage + Clone>(target: BroadcastTarget, msg: M) {
+        let local = remote::local_node_id();
+        let remotes = Cluster::members()
+            .into_iter()
+            .filter(|member| member.id != local && member.status == MemberStatus::Up);
+        for member in remotes {
+            let names = member.dispatchers.iter().filter(|name| match &target {
+                BroadcastTarget::All => true,
+                BroadcastTarget::Group(group) => *name == group,
+            });
+
+            for name in names {
+                trace!("Cluster: Broadcasting to {}@{}: {:?}", name, member.id, msg);
+                match remote::node(member.id) {
+                    Some(node) => {
+                        let child_ref = node.child_ref(remote::dispatcher_path(name));
+                        child_ref.tell_anonymously(msg.clone()).ok();
+                    }
+                    None => debug!("Cluster: Not connected to {}.", member.id),
+                }
+            }
+        }
+
+        // The message isn't dead if the group is only registered on
+        // other members.
+        let dispatcher = SYSTEM.dispatcher();
+        if let BroadcastTarget::Group(group) = &target {
+            if !dispatcher.has_named(group) {
+                return;
+            }
+        }
+
+        let msg = Arc::new(SignedMessage::new(
+            Msg::broadcast(msg),
+            RefAddr::dead_letters(),
+        ));
+        dispatcher.broadcast_message(target, &msg);
+    }
+
+    /// Sends a message to the children groups of the named
+    /// dispatcher on one of the nodes it is registered on (this
+    /// node or another member of the cluster), picked randomly. The
+    /// dispatcher's handler then picks the child receiving it on
+    /// that node.
+    ///
+    /// As with [`Cluster::broadcast_message`], the type of the
+    /// message must be registered on every node.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)` if
+    /// the dispatcher isn't registered on any node that this node
+    /// can reach.
+    ///
+    /// # Arguments
+    ///
+    /// * `dispatcher` - The name of the dispatcher.
+    /// * `msg` - The message to send.
+    ///
+    /// [`Cluster::broadcast_message`]: #method.broadcast_message
+    pub fn tell_one<M: RemoteMessage>(dispatcher: &str, msg: M) -> Result<(), M> {
+        let local = remote::local_node_id();
+        let remotes: Vec<_> = Cluster::dispatcher_members(dispatcher)
+            .into_iter()
+            .filter(|member| member.id != local)
+            .collect();
+        let registered = SYSTEM.dispatcher().has_named(dispatcher);
+
+        let candidates = remotes.len() + registered as usize;
+        if candidates == 0 {
+            return Err(msg);
+        }
+
+        // The last candidate is this node, if it is one.
+        let picked = rand::random::<usize>() % candidates;
+        let member = match remotes.get(picked) {
+            Some(member) => member,
+            None => {
+                remote::dispatch(dispatcher, Msg::broadcast(msg));
+                return Ok(());
+            }
+        };
+
+        trace!("Cluster: Telling {}@{}: {:?}", dispatcher, member.id, msg);
+        match remote::node(member.id) {
+            Some(node) => node
+                .child_ref(remote::dispatcher_path(dispatcher))
+                .tell_anonymously(msg),
+            None => Err(msg),
+        }
+    }
+
+    /// Subscribes a supervisor to the membership changes of the
+    /// cluster, which will be broadcasted to its children as
+    /// [`MemberEvent`] messages.
+    ///
+    /// The supervisor needs to be subscribed again if this node
+    /// leaves a cluster and joins another one.
+    ///
+    /// # Arguments
+    ///
+    /// * `supervisor` - The supervisor to subscribe.
+    ///
+    /// [`MemberEvent`]: enum.MemberEvent.html
+    pub fn subscribe(supervisor: &SupervisorRef) {
+        let mut state = STATE.lock().unwrap();
+        match state.as_mut() {
+            Some(state) => state.subscribers.push(supervisor.clone()),
+            None => warn!("Cluster: Can't subscribe before joining a cluster."),
+        }
+    }
+}
+
+impl LeaderElection {
+    /// Registers a child to be sent a [`LeadershipEvent`] every
+    /// time this node gains or loses the leadership of the cluster,
+    /// starting with [`LeadershipEvent::Elected`] right away if it
+    /// is already the leader.
+    ///
+    /// # Arguments
+    ///
+    /// * `child` - The child to register.
+    ///
+    /// [`LeadershipEvent`]: enum.LeadershipEvent.html
+    /// [`LeadershipEvent::Elected`]: enum.LeadershipEvent.html#variant.Elected
+    pub fn register(child: &ChildRef) {
+        let mut leadership = LEADERSHIP.lock().unwrap();
+        if leadership.leading {
+            child.tell_anonymously(LeadershipEvent::Elected).ok();
+        }
+
+        leadership.children.push(child.clone());
+    }
+
+    /// Unregisters a child, which won't be sent any
+    /// [`LeadershipEvent`] anymore.
+    ///
+    /// # Arguments
+    ///
+    /// * `child` - The child to unregister.
+    ///
+    /// [`LeadershipEvent`]: enum.LeadershipEvent.html
+    pub fn unregister(child: &ChildRef) {
+        let mut leadership = LEADERSHIP.lock().unwrap();
+        leadership
+            .children
+            .retain(|registered| registered.id() != child.id());
+    }
+
+    /// Returns whether this node is the leader of the cluster.
+    pub fn is_leader() -> bool {
+        Cluster::is_leader()
+    }
+}
+
+impl ClusterConfig {
+    /// Adds the address of a seed node, which is contacted to join
+    /// the cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The address the seed node is bound to.
+    pub fn with_seed(mut self, seed: SocketAddr) -> Self {
+        self.seeds.push(seed);
+        self
+    }
+
+    /// Sets the way the nodes to contact to join the cluster are
+    /// found, on top of the seeds. The discovery is used until this
+    /// node knows another member, and this node is registered to it
+    /// once it joined.
+    ///
+    /// # Arguments
+    ///
+    /// * `discovery` - The discovery finding the nodes.
+    pub fn with_discovery<D: Discovery>(mut self, discovery: D) -> Self {
+        self.discovery = Some(Arc::new(discovery));
+        self
+    }
+
+    /// Sets the address the other nodes use to connect to this
+    /// node, which is needed when it was bound to an unspecified
+    /// address (e.g. `0.0.0.0`). By default, the address it was
+    /// bound to is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of this node.
+    pub fn with_advertised_addr(mut self, addr: SocketAddr) -> Self {
+        self.advertised_addr = Some(addr);
+        self
+    }
+
+    /// Sets the interval between two rounds of gossip, which are
+    /// also used as heartbeats. By default, it is one second.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The gossip interval.
+    pub fn with_gossip_interval(mut self, interval: Duration) -> Self {
+        self.gossip_interval = interval;
+        self
+    }
+
+    /// Sets the suspicion level (phi) above which a member is
+    /// marked as unreachable. A threshold of `8.0` (the default)
+    /// roughly means a one in a hundred million chance of a false
+    /// positive.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - The phi threshold.
+    pub fn with_phi_threshold(mut self, threshold: f64) -> Self {
+        self.phi_threshold = threshold;
+        self
+    }
+
+    /// Sets the additional time tolerated between two heartbeats
+    /// before suspecting a member (e.g. to survive garbage
+    /// collection pauses or network hiccups). By default, it is
+    /// three seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `pause` - The acceptable pause.
+    pub fn with_acceptable_pause(mut self, pause: Duration) -> Self {
+        self.acceptable_pause = pause;
+        self
+    }
+
+    /// Sets the strategy used to resolve the partitions of the
+    /// cluster. By default, partitions aren't resolved and the
+    /// unreachable members are never downed.
+    ///
+    /// # Arguments
+    ///
+    /// * `strategy` - The split-brain resolution strategy.
+    pub fn with_split_brain_strategy(mut self, strategy: SplitBrainStrategy) -> Self {
+        self.split_brain_strategy = Some(strategy);
+        self
+    }
+
+    /// Sets how long the membership must stay unchanged before a
+    /// partition gets resolved, to avoid downing members that are
+    /// only unreachable for a short time. By default, it is twenty
+    /// seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `stable_after` - The duration without membership changes.
+    pub fn with_stable_after(mut self, stable_after: Duration) -> Self {
+        self.stable_after = stable_after;
+        self
+    }
+
+    /// Returns the addresses of the seed nodes.
+    pub fn seeds(&self) -> &[SocketAddr] {
+        &self.seeds
+    }
+
+    /// Returns the interval between two rounds of gossip.
+    pub fn gossip_interval(&self) -> Duration {
+        self.gossip_interval
+    }
+
+    /// Returns the suspicion level above which a member is marked
+    /// as unreachable.
+    pub fn phi_threshold(&self) -> f64 {
+        self.phi_threshold
+    }
+
+    /// Returns the strategy used to resolve the partitions of the
+    /// cluster, if any.
+    pub fn split_brain_strategy(&self) -> Option<&SplitBrainStrategy> {
+        self.split_brain_strategy.as_ref()
+    }
+}
+
+impl SplitBrainStrategy {
+    /// Returns whether the side of the partition with the
+    /// reachable members is kept.
+    fn keeps(&self, reachable: &[&MemberState], unreachable: &[&MemberState]) -> bool {
+        let oldest = reachable
+            .iter()
+            .chain(unreachable.iter())
+            .min_by_key(|member| (member.joined_at, member.id));
+        let has_oldest = oldest.map_or(false, |oldest| {
+            reachable.iter().any(|member| member.id == oldest.id)
+        });
+
+        match self {
+            SplitBrainStrategy::KeepMajority => {
+                reachable.len() > unreachable.len()
+                    || (reachable.len() == unreachable.len() && has_oldest)
+            }
+            SplitBrainStrategy::KeepOldest => has_oldest,
+            SplitBrainStrategy::StaticQuorum(quorum) => reachable.len() >= *quorum,
+        }
+    }
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        ClusterConfig {
+            seeds: Vec::new(),
+            advertised_addr: None,
+            gossip_interval: Duration::from_secs(1),
+            phi_threshold: 8.0,
+            max_samples: 1000,
+            min_std_deviation: Duration::from_millis(100),
+            acceptable_pause: Duration::from_secs(3),
+            split_brain_strategy: None,
+            stable_after: Duration::from_secs(20),
+            discovery: None,
+        }
+    }
+}
+
+impl Member {
+    /// Returns the id of the member's node.
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Returns the address of the member's node.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the status of the member.
+    pub fn status(&self) -> MemberStatus {
+        self.status
+    }
+
+    /// Returns when the member joined the cluster, according to its
+    /// own clock.
+    pub fn joined_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.joined_at)
+    }
+
+    /// Returns the names of the named dispatchers registered on the
+    /// member, as it last gossiped them.
+    pub fn dispatchers(&self) -> &[String] {
+        &self.dispatchers
+    }
+}
+
+impl MemberEvent {
+    /// Returns the member the event is about.
+    pub fn member(&self) -> &Member {
+        match self {
+            MemberEvent::MemberUp(member)
+            | MemberEvent::MemberUnreachable(member)
+            | MemberEvent::MemberReachable(member)
+            | MemberEvent::MemberLeft(member)
+            | MemberEvent::MemberDowned(member) => member,
+        }
+    }
+}
+
+impl MemberStatus {
+    // The precedence of the status over the other ones, for
+    // the same incarnation.
+    fn rank(self) -> u8 {
+        match self {
+            MemberStatus::Up => 0,
+            MemberStatus::Unreachable => 1,
+            MemberStatus::Down => 2,
+            MemberStatus::Left => 3,
+        }
+    }
+
+    // Whether the member isn't part of the cluster anymore.
+    fn is_removed(self) -> bool {
+        self == MemberStatus::Down || self == MemberStatus::Left
+    }
+}
+
+impl MemberState {
+    fn member(&self) -> Member {
+        Member {
+            id: self.id,
+            addr: self.addr,
+            status: self.status,
+            joined_at: self.joined_at,
+            dispatchers: self.dispatchers.clone(),
+        }
+    }
+
+    /// Returns whether this state is more recent than the other one.
+    fn supersedes(&self, other: &MemberState) -> bool {
+        if other.status.is_removed() {
+            return false;
+        }
+
+        self.incarnation > other.incarnation
+            || (self.incarnation == other.incarnation && self.status.rank() > other.status.rank())
+    }
+}
+
+impl ClusterState {
+    fn new(config: ClusterConfig, me: MemberState) -> Self {
+        let seeds = config
+            .seeds
+            .iter()
+            .copied()
+            .filter(|seed| *seed != me.addr)
+            .collect();
+
+        ClusterState {
+            config,
+            me,
+            members: FxHashMap::default(),
+            seeds,
+            subscribers: Vec::new(),
+            running: Arc::new(AtomicBool::new(true)),
+            last_change: Instant::now(),
+            downed: false,
+        }
+    }
+
+    fn gossip(&self) -> Vec<MemberState> {
+        let others = self.members.values().map(|entry| entry.state.clone());
+        Some(self.me.clone()).into_iter().chain(others).collect()
+    }
+
+    /// Updates the named dispatchers registered on this node, which
+    /// makes the other members take the new ones.
+    fn update_dispatchers(&mut self, dispatchers: Vec<String>) {
+        if dispatchers != self.me.dispatchers {
+            debug!("Cluster: Registered dispatchers: {:?}", dispatchers);
+            self.me.dispatchers = dispatchers;
+            self.me.incarnation += 1;
+        }
+    }
+
+    /// Returns the connected nodes of the members that didn't leave.
+    fn targets(&self) -> Vec<RemoteNode> {
+        self.members
+            .values()
+            .filter(|entry| !entry.state.status.is_removed())
+            .filter_map(|entry| entry.node.clone())
+            .filter(|node| node.is_connected())
+            .collect()
+    }
+
+    /// Returns the addresses of the members that didn't leave and
+    /// that this node isn't connected to.
+    fn unconnected(&self) -> Vec<SocketAddr> {
+        self.members
+            .values()
+            .filter(|entry| !entry.state.status.is_removed())
+            .filter(|entry| !entry.node.as_ref().map_or(false, RemoteNode::is_connected))
+            .map(|entry| entry.state.addr)
+            .collect()
+    }
+
+    /// Records that a message was received from the node, which
+    /// is used as a heartbeat and to reply to it.
+    fn heard_from(&mut self, node: &RemoteNode, now: Instant) {
+        if let Some(entry) = self.members.get_mut(&node.id()) {
+            entry.detector.heartbeat(now);
+            if !entry.node.as_ref().map_or(false, RemoteNode::is_connected) {
+                entry.node = Some(node.clone());
+            }
+        }
+    }
+
+    fn merge(&mut self, states: Vec<MemberState>, events: &mut Vec<MemberEvent>) {
+        for state in states {
+            if state.id == self.me.id {
+                if state.status == MemberStatus::Down {
+                    debug!("Cluster: Downed by the other members.");
+                    self.downed = true;
+                }
+
+                // Another node suspects this node: refuting it.
+                if state.status == MemberStatus::Unreachable
+                    && state.incarnation >= self.me.incarnation
+                    && self.me.status == MemberStatus::Up
+                {
+                    debug!("Cluster: Refuting status: {:?}", state.status);
+                    self.me.incarnation = state.incarnation + 1;
+                }
+
+                continue;
+            }
+
+            let entry = match self.members.get_mut(&state.id) {
+                Some(entry) => entry,
+                None => {
+                    let member = state.member();
+                    match state.status {
+                        // The member was removed before this node heard of it.
+                        MemberStatus::Down | MemberStatus::Left => (),
+                        MemberStatus::Up => events.push(MemberEvent::MemberUp(member)),
+                        MemberStatus::Unreachable => {
+                            events.push(MemberEvent::MemberUp(member.clone()));
+                            events.push(MemberEvent::MemberUnreachable(member));
+                        }
+                    }
+
+                    let detector = PhiAccrualDetector::new(&self.config);
+                    let entry = MemberEntry {
+                        state,
+                        node: None,
+                        detector,
+                    };
+                    self.members.insert(entry.state.id, entry);
+                    continue;
+                }
+            };
+
+            if !state.supersedes(&entry.state) {
+                continue;
+            }
+
+            let previous = entry.state.status;
+            entry.state = state;
+            let member = entry.state.member();
+            match (previous, entry.state.status) {
+                (MemberStatus::Up, MemberStatus::Unreachable) => {
+                    events.push(MemberEvent::MemberUnreachable(member))
+                }
+                (MemberStatus::Unreachable, MemberStatus::Up) => {
+                    // The detector's samples are stale.
+                    entry.detector = PhiAccrualDetector::new(&self.config);
+                    events.push(MemberEvent::MemberReachable(member))
+                }
+                (_, MemberStatus::Down) => events.push(MemberEvent::MemberDowned(member)),
+                (_, MemberStatus::Left) => events.push(MemberEvent::MemberLeft(member)),
+                _ => (),
+            }
+        }
+    }
+
+    /// Records when the membership changed.
+    fn track(&mut self, now: Instant, events: &[MemberEvent]) {
+        if !events.is_empty() {
+            self.last_change = now;
+        }
+    }
+
+    /// Applies the split-brain resolution strategy if members are
+    /// unreachable and the membership is stable, downing them if
+    /// this side of the partition is kept.
+    ///
+    /// This method returns whether this node must down itself.
+    fn resolve(&mut self, now: Instant, events: &mut Vec<MemberEvent>) -> bool {
+        let strategy = match &self.config.split_brain_strategy {
+            Some(strategy) => strategy,
+            None => return false,
+        };
+
+        if now.saturating_duration_since(self.last_change) < self.config.stable_after {
+            return false;
+        }
+
+        let (unreachable, up): (Vec<_>, Vec<_>) = self
+            .members
+            .values()
+            .map(|entry| &entry.state)
+            .filter(|state| !state.status.is_removed())
+            .partition(|state| state.status == MemberStatus::Unreachable);
+        if unreachable.is_empty() {
+            return false;
+        }
+
+        let reachable: Vec<_> = Some(&self.me).into_iter().chain(up).collect();
+        if !strategy.keeps(&reachable, &unreachable) {
+            debug!("Cluster: This side of the partition lost ({:?}).", strategy);
+            return true;
+        }
+
+        for entry in self.members.values_mut() {
+            if entry.state.status == MemberStatus::Unreachable {
+                debug!("Cluster: Downing {}.", entry.state.id);
+                entry.state.status = MemberStatus::Down;
+                events.push(MemberEvent::MemberDowned(entry.state.member()));
+            }
+        }
+
+        false
+    }
+
+    /// Marks the members whose suspicion level is above the
+    /// threshold as unreachable.
+    fn detect(&mut self, now: Instant, events: &mut Vec<MemberEvent>) {
+        let threshold = self.config.phi_threshold;
+        for entry in self.members.values_mut() {
+            if entry.state.status != MemberStatus::Up {
+                continue;
+            }
+
+            let phi = entry.detector.phi(now);
+            if phi > threshold {
+                debug!("Cluster: {} is unreachable (phi={}).", entry.state.id, phi);
+                entry.state.status = MemberStatus::Unreachable;
+                events.push(MemberEvent::MemberUnreachable(entry.state.member()));
+            }
+        }
+    }
+}
+
+impl PhiAccrualDetector {
+    fn new(config: &ClusterConfig) -> Self {
+        PhiAccrualDetector {
+            intervals: VecDeque::new(),
+            max_samples: config.max_samples,
+            min_std_deviation: millis(config.min_std_deviation),
+            acceptable_pause: millis(config.acceptable_pause),
+            first_interval: millis(config.gossip_interval),
+            last: None,
+        }
+    }
+
+    fn heartbeat(&mut self, now: Instant) {
+        let interval = match self.last {
+            Some(last) => millis(now.saturating_duration_since(last)),
+            None => self.first_interval,
+        };
+
+        self.intervals.push_back(interval);
+        if self.intervals.len() > self.max_samples {
+            self.intervals.pop_front();
+        }
+
+        self.last = Some(now);
+    }
+
+    /// Returns the suspicion level of the node, which is `0.0` until
+    /// a heartbeat was received.
+    fn phi(&self, now: Instant) -> f64 {
+        let last = match self.last {
+            Some(last) => last,
+            None => return 0.0,
+        };
+
+        let elapsed = millis(now.saturating_duration_since(last));
+        let len = self.intervals.len() as f64;
+        let mean = self.intervals.iter().sum::<f64>() / len;
+        let variance = self
+            .intervals
+            .iter()
+            .map(|interval| (interval - mean).powi(2))
+            .sum::<f64>()
+            / len;
+
+        let mean = mean + self.acceptable_pause;
+        let std_deviation = variance.sqrt().max(self.min_std_deviation);
+
+        // A logistic approximation of the cumulative distribution
+        // function of the normal distribution.
+        let y = (elapsed - mean) / std_deviation;
+        let e = (-y * (1.5976 + 0.070566 * y * y)).exp();
+        if elapsed > mean {
+            -(e / (1.0 + e)).log10()
+        } else {
+            -(1.0 - 1.0 / (1.0 + e)).log10()
+        }
+    }
+}
+
+/// Handles a message of the membership protocol sent by a node.
+pub(crate) fn handle(node: RemoteNode, payload: &[u8]) {
+    let msg: ClusterMessage = match serde_json::from_slice(payload) {
+        Ok(msg) => msg,
+        Err(err) => {
+            warn!("Cluster: Couldn't deserialize message: {}", err);
+            return;
+        }
+    };
+
+    trace!("Cluster: Received from {}: {:?}", node.id(), msg);
+    let mut events = Vec::new();
+    let (reply, subscribers, downed) = {
+        let mut state = STATE.lock().unwrap();
+        let state = match state.as_mut() {
+            Some(state) => state,
+            None => return,
+        };
+
+        let reply = match msg {
+            ClusterMessage::Join(member) => {
+                state.merge(vec![member], &mut events);
+                Some(ClusterMessage::Gossip(state.gossip()))
+            }
+            ClusterMessage::Gossip(members) => {
+                state.merge(members, &mut events);
+                None
+            }
+        };
+
+        let now = Instant::now();
+        state.heard_from(&node, now);
+        state.track(now, &events);
+        (reply, state.subscribers.clone(), state.downed)
+    };
+
+    if let Some(reply) = reply {
+        node.send_cluster(encode(&reply)).ok();
+    }
+
+    publish(&subscribers, events);
+    if downed {
+        remove(MemberStatus::Down);
+    }
+}
+
+/// Runs a round of gossip and failure detection, connecting to
+/// the members and seeds this node isn't connected to yet.
+fn tick() {
+    let mut events = Vec::new();
+    let (me, targets, unconnected, mut seeds, payload, subscribers, discovery) = {
+        let mut guard = STATE.lock().unwrap();
+        let state = match guard.as_mut() {
+            Some(state) => state,
+            None => return,
+        };
+
+        let now = Instant::now();
+        state.detect(now, &mut events);
+        // The partitions are only resolved once the membership is
+        // stable.
+        state.track(now, &events);
+        if state.resolve(now, &mut events) {
+            state.downed = true;
+        }
+
+        if state.downed {
+            let subscribers = state.subscribers.clone();
+            drop(guard);
+            publish(&subscribers, events);
+            return remove(MemberStatus::Down);
+        }
+
+        state.track(now, &events);
+        state.update_dispatchers(SYSTEM.dispatcher().named());
+        (
+            state.me.clone(),
+            state.targets(),
+            state.unconnected(),
+            state.seeds.clone(),
+            encode(&ClusterMessage::Gossip(state.gossip())),
+            state.subscribers.clone(),
+            // The other members are learned through gossip once
+            // this node knows one of them.
+            state
+                .config
+                .discovery
+                .clone()
+                .filter(|_| state.members.is_empty()),
+        )
+    };
+
+    publish(&subscribers, events);
+    for node in targets {
+        node.send_cluster(payload.clone()).ok();
+    }
+
+    if let Some(discovery) = discovery {
+        match discovery.discover() {
+            Ok(discovered) => {
+                let discovered: Vec<_> = discovered
+                    .into_iter()
+                    .filter(|addr| *addr != me.addr && !seeds.contains(addr))
+                    .collect();
+                trace!("Cluster: Discovered nodes: {:?}", discovered);
+                seeds.extend(discovered);
+            }
+            Err(err) => debug!("Cluster: Couldn't discover nodes: {}", err),
+        }
+    }
+
+    for addr in unconnected {
+        match remote::connect(addr) {
+            Ok(node) => {
+                node.send_cluster(payload.clone()).ok();
+                let mut state = STATE.lock().unwrap();
+                if let Some(state) = state.as_mut() {
+                    if let Some(entry) = state.members.get_mut(&node.id()) {
+                        entry.node = Some(node);
+                    }
+                }
+            }
+            Err(err) => trace!("Cluster: Couldn't connect to {}: {:?}", addr, err),
+        }
+    }
+
+    for seed in seeds {
+        match remote::connect(seed) {
+            Ok(node) => {
+                debug!("Cluster: Contacted seed {} ({}).", node.id(), seed);
+                node.send_cluster(encode(&ClusterMessage::Join(me.clone())))
+                    .ok();
+                let mut state = STATE.lock().unwrap();
+                if let Some(state) = state.as_mut() {
+                    state.seeds.retain(|addr| *addr != seed);
+                }
+            }
+            Err(err) => debug!("Cluster: Couldn't contact seed {}: {:?}", seed, err),
+        }
+    }
+}
+
+/// Removes this node from the cluster because it left or was
+/// downed, letting the other members know about it.
+fn remove(status: MemberStatus) {
+    for hook in LEAVE_HOOKS.lock().unwrap().iter() {
+        hook();
+    }
+
+    let (targets, payload, subscribers, me, discovery) = {
+        let mut state = STATE.lock().unwrap();
+        let mut state = match state.take() {
+            Some(state) => state,
+            None => return,
+        };
+
+        debug!("Cluster: Removing this node: {:?}", status);
+        state.running.store(false, Ordering::SeqCst);
+        state.me.status = status;
+        state.me.incarnation += 1;
+        (
+            state.targets(),
+            encode(&ClusterMessage::Gossip(state.gossip())),
+            state.subscribers.clone(),
+            state.me.member(),
+            state.config.discovery.clone(),
+        )
+    };
+
+    if let Some(discovery) = discovery {
+        discovery.deregister().ok();
+    }
+
+    for node in targets {
+        node.send_cluster(payload.clone()).ok();
+    }
+
+    let events = match status {
+        MemberStatus::Down => vec![MemberEvent::MemberDowned(me)],
+        _ => Vec::new(),
+    };
+    publish(&subscribers, events);
+}
+
+/// Registers a function that is called before this node leaves
+/// the cluster, while it is still a member.
+pub(crate) fn on_leave<F: Fn() + Send + 'static>(hook: F) {
+    LEAVE_HOOKS.lock().unwrap().push(Box::new(hook));
+}
+
+fn publish(subscribers: &[SupervisorRef], events: Vec<MemberEvent>) {
+    for event in events {
+        debug!("Cluster: {:?}", event);
+        for subscriber in subscribers {
+            // The supervisor might be stopped.
+            subscriber.broadcast(event.clone()).ok();
+        }
+    }
+
+    elect();
+}
+
+/// Returns the oldest member that is up, if the members that are
+/// up are a majority of the ones that weren't removed.
+fn elect_leader(members: Vec<Member>) -> Option<Member> {
+    let counted = members
+        .iter()
+        .filter(|member| !member.status.is_removed())
+        .count();
+    let up: Vec<_> = members
+        .into_iter()
+        .filter(|member| member.status == MemberStatus::Up)
+        .collect();
+    if up.len() * 2 <= counted {
+        debug!(
+            "Cluster: No leader without a majority ({}/{}).",
+            up.len(),
+            counted
+        );
+        return None;
+    }
+
+    up.into_iter()
+        .min_by_key(|member| (member.joined_at, member.id))
+}
+
+/// Notifies the registered children if this node gained or lost
+/// the leadership of the cluster.
+fn elect() {
+    let mut leadership = LEADERSHIP.lock().unwrap();
+    let leading = Cluster::is_leader();
+    if leading == leadership.leading {
+        return;
+    }
+
+    debug!("Cluster: Leadership changed: leading={}", leading);
+    leadership.leading = leading;
+    let event = if leading {
+        LeadershipEvent::Elected
+    } else {
+        LeadershipEvent::Revoked
+    };
+
+    // The children that were stopped are unregistered.
+    leadership
+        .children
+        .retain(|child| child.tell_anonymously(event).is_ok());
+}
+
+fn encode(msg: &ClusterMessage) -> Vec<u8> {
+    // Cluster messages only contain serializable fields.
+    serde_json::to_vec(msg).unwrap()
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::BastionId;
+    use crate::envelope::Envelope;
+    use crate::path::BastionPath;
+    use futures::channel::mpsc;
+
+    fn member(status: MemberStatus, incarnation: u64) -> MemberState {
+        MemberState {
+            id: NodeId::new(),
+            addr: "127.0.0.1:4222".parse().unwrap(),
+            status,
+            incarnation,
+            joined_at: 0,
+            dispatchers: Vec::new(),
+        }
+    }
+
+    fn state() -> ClusterState {
+        ClusterState::new(ClusterConfig::default(), member(MemberStatus::Up, 0))
+    }
+
+    fn child() -> (ChildRef, mpsc::UnboundedReceiver<Envelope>) {
+        let (sender, recver) = mpsc::unbounded();
+        let path = Arc::new(BastionPath::root());
+        let child = ChildRef::new(BastionId::new(), sender.into(), "child".to_string(), path);
+        (child, recver)
+    }
+
+    fn events(recver: &mut mpsc::UnboundedReceiver<Envelope>) -> Vec<LeadershipEvent> {
+        let mut events = Vec::new();
+        while let Ok(Some(env)) = recver.try_next() {
+            events.extend(env.into_msg::<LeadershipEvent>());
+        }
+
+        events
+    }
+
+    #[test]
+    fn detects_missing_heartbeats() {
+        let mut detector = PhiAccrualDetector::new(&ClusterConfig::default());
+        let start = Instant::now();
+        for i in 0..10 {
+            detector.heartbeat(start + Duration::from_secs(i));
+        }
+
+        let last = start + Duration::from_secs(9);
+        assert!(detector.phi(last + Duration::from_secs(1)) < 1.0);
+        assert!(detector.phi(last + Duration::from_secs(30)) > 8.0);
+    }
+
+    #[test]
+    fn merges_newer_states() {
+        let mut state = state();
+        let mut events = Vec::new();
+
+        let other = member(MemberStatus::Up, 0);
+        state.merge(vec![other.clone()], &mut events);
+        assert!(matches!(events.as_slice(), [MemberEvent::MemberUp(_)]));
+
+        // The same incarnation being unreachable supersedes it...
+        events.clear();
+        let unreachable = MemberState {
+            status: MemberStatus::Unreachable,
+            ..other.clone()
+        };
+        state.merge(vec![unreachable], &mut events);
+        assert!(matches!(
+            events.as_slice(),
+            [MemberEvent::MemberUnreachable(_)]
+        ));
+
+        // ...but the older one doesn't.
+        events.clear();
+        state.merge(vec![other.clone()], &mut events);
+        assert!(events.is_empty());
+
+        let refuted = MemberState {
+            incarnation: 1,
+            ..other.clone()
+        };
+        state.merge(vec![refuted], &mut events);
+        assert!(matches!(
+            events.as_slice(),
+            [MemberEvent::MemberReachable(_)]
+        ));
+
+        // Nothing supersedes a member leaving.
+        events.clear();
+        let left = MemberState {
+            status: MemberStatus::Left,
+            incarnation: 2,
+            ..other.clone()
+        };
+        state.merge(vec![left], &mut events);
+        let rejoined = MemberState {
+            incarnation: 3,
+            ..other
+        };
+        state.merge(vec![rejoined], &mut events);
+        assert!(matches!(events.as_slice(), [MemberEvent::MemberLeft(_)]));
+    }
+
+    #[test]
+    fn refutes_suspicions() {
+        let mut state = state();
+        let suspected = MemberState {
+            status: MemberStatus::Unreachable,
+            ..state.me.clone()
+        };
+
+        state.merge(vec![suspected], &mut Vec::new());
+        assert_eq!(state.me.status, MemberStatus::Up);
+        assert_eq!(state.me.incarnation, 1);
+        assert!(!state.downed);
+
+        let downed = MemberState {
+            status: MemberStatus::Down,
+            ..state.me.clone()
+        };
+        state.merge(vec![downed], &mut Vec::new());
+        assert!(state.downed);
+    }
+
+    #[test]
+    fn resolves_partitions() {
+        let config = ClusterConfig::default()
+            .with_split_brain_strategy(SplitBrainStrategy::KeepMajority)
+            .with_stable_after(Duration::from_secs(0));
+        let mut state = ClusterState::new(config, member(MemberStatus::Up, 0));
+        let mut events = Vec::new();
+
+        let up = member(MemberStatus::Up, 0);
+        let unreachable = member(MemberStatus::Unreachable, 0);
+        state.merge(vec![up, unreachable.clone()], &mut events);
+
+        // This side has the majority...
+        events.clear();
+        assert!(!state.resolve(Instant::now(), &mut events));
+        assert!(matches!(events.as_slice(), [MemberEvent::MemberDowned(_)]));
+        assert_eq!(
+            state.members[&unreachable.id].state.status,
+            MemberStatus::Down
+        );
+
+        // ...until the other members are unreachable too.
+        let others = vec![
+            member(MemberStatus::Unreachable, 0),
+            member(MemberStatus::Unreachable, 0),
+        ];
+        state.merge(others, &mut events);
+        assert!(state.resolve(Instant::now(), &mut Vec::new()));
+    }
+
+    #[test]
+    fn gossips_dispatchers() {
+        let mut other = state();
+        let mut node = state();
+        let mut events = Vec::new();
+        other.merge(node.gossip(), &mut events);
+
+        // Registering a dispatcher makes the member's state supersede
+        // the one the other members know...
+        node.update_dispatchers(vec!["orders".to_string()]);
+        assert_eq!(node.me.incarnation, 1);
+        node.update_dispatchers(vec!["orders".to_string()]);
+        assert_eq!(node.me.incarnation, 1);
+
+        events.clear();
+        other.merge(node.gossip(), &mut events);
+        assert!(events.is_empty());
+
+        // ...so that they know where the dispatcher is registered.
+        let member = other.members[&node.me.id].state.member();
+        assert_eq!(member.dispatchers(), ["orders".to_string()]);
+    }
+
+    #[test]
+    fn elects_the_oldest_member_of_a_majority() {
+        let joined = |joined_at, status| {
+            MemberState {
+                joined_at,
+                ..member(status, 0)
+            }
+            .member()
+        };
+        let oldest = joined(1, MemberStatus::Up);
+        let younger = joined(2, MemberStatus::Up);
+
+        let members = vec![younger.clone(), oldest.clone()];
+        assert_eq!(elect_leader(members), Some(oldest.clone()));
+
+        // The removed members aren't counted...
+        let members = vec![
+            younger.clone(),
+            joined(0, MemberStatus::Down),
+            joined(0, MemberStatus::Left),
+        ];
+        assert_eq!(elect_leader(members), Some(younger.clone()));
+
+        // ...but the unreachable ones are, so that only one side of
+        // a partition has a leader.
+        let members = vec![
+            younger.clone(),
+            oldest.clone(),
+            joined(0, MemberStatus::Unreachable),
+        ];
+        assert_eq!(elect_leader(members), Some(oldest.clone()));
+
+        let members = vec![oldest, joined(0, MemberStatus::Unreachable)];
+        assert_eq!(elect_leader(members), None);
+    }
+
+    #[test]
+    fn notifies_the_leadership_changes() {
+        let me = MemberState {
+            id: remote::local_node_id(),
+            joined_at: 2,
+            ..member(MemberStatus::Up, 0)
+        };
+        let oldest = MemberState {
+            joined_at: 1,
+            ..member(MemberStatus::Up, 0)
+        };
+        let mut state = ClusterState::new(ClusterConfig::default(), me);
+        state.merge(vec![oldest.clone()], &mut Vec::new());
+        *STATE.lock().unwrap() = Some(state);
+        elect();
+
+        // The oldest member leads...
+        let (child, mut recver) = child();
+        LeaderElection::register(&child);
+        assert!(!LeaderElection::is_leader());
+        assert!(events(&mut recver).is_empty());
+
+        // ...until it is downed.
+        let downed = MemberState {
+            status: MemberStatus::Down,
+            ..oldest
+        };
+        STATE
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .merge(vec![downed], &mut Vec::new());
+        elect();
+        assert!(LeaderElection::is_leader());
+        assert_eq!(events(&mut recver), [LeadershipEvent::Elected]);
+
+        // The children registered afterwards are notified right away.
+        let (other, mut other_recver) = child();
+        LeaderElection::register(&other);
+        assert_eq!(events(&mut other_recver), [LeadershipEvent::Elected]);
+
+        // This node being downed revokes its leadership.
+        remove(MemberStatus::Down);
+        assert!(!LeaderElection::is_leader());
+        assert_eq!(events(&mut recver), [LeadershipEvent::Revoked]);
+        assert_eq!(events(&mut other_recver), [LeadershipEvent::Revoked]);
+
+        LeaderElection::unregister(&child);
+        LeaderElection::unregister(&other);
+    }
+
+    #[test]
+    fn applies_split_brain_strategies() {
+        let oldest = MemberState {
+            joined_at: 1,
+            ..member(MemberStatus::Up, 0)
+        };
+        let younger: Vec<_> = (0..2)
+            .map(|_| MemberState {
+                joined_at: 2,
+                ..member(MemberStatus::Up, 0)
+            })
+            .collect();
+        let younger: Vec<_> = younger.iter().collect();
+
+        let majority = SplitBrainStrategy::KeepMajority;
+        assert!(majority.keeps(&younger, &[&oldest]));
+        assert!(!majority.keeps(&[&oldest], &younger));
+        assert!(majority.keeps(&[&oldest], &younger[..1]));
+
+        let oldest_side = SplitBrainStrategy::KeepOldest;
+        assert!(oldest_side.keeps(&[&oldest], &younger));
+        assert!(!oldest_side.keeps(&younger, &[&oldest]));
+
+        let quorum = SplitBrainStrategy::StaticQuorum(2);
+        assert!(quorum.keeps(&younger, &[&oldest]));
+        assert!(!quorum.keeps(&[&oldest], &younger[..1]));
+    }
+}
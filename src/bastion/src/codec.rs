@@ -0,0 +1,240 @@
+//!
+//! Codecs serialize and deserialize the messages sent between
+//! nodes, and are available with the `remote` feature.
+//!
+//! Any type implementing [`Serialize`] and [`DeserializeOwned`]
+//! (e.g. by deriving them) is a [`RemoteMessage`]. The codec used
+//! by a node is set using [`RemotingConfig::with_codec`], and must
+//! be the same on both ends of a connection (which is checked during
+//! the handshake). [`JsonCodec`] is the default one, while
+//! [`BincodeCodec`] is a more compact binary alternative. Other
+//! formats can be plugged by implementing [`MessageCodec`].
+//!
+//! [`Serialize`]: https://docs.rs/serde/1/serde/trait.Serialize.html
+//! [`DeserializeOwned`]: https://docs.rs/serde/1/serde/de/trait.DeserializeOwned.html
+//! [`RemoteMessage`]: trait.RemoteMessage.html
+//! [`RemotingConfig::with_codec`]: ../remote/struct.RemotingConfig.html#method.with_codec
+//! [`JsonCodec`]: struct.JsonCodec.html
+//! [`BincodeCodec`]: struct.BincodeCodec.html
+//! [`MessageCodec`]: trait.MessageCodec.html
+use crate::message::Message;
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+pub use erased_serde;
+
+/// A message that can be sent to other nodes. It is automatically
+/// implemented for every message implementing [`Serialize`] and
+/// [`DeserializeOwned`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct Ping(u64);
+///
+/// fn assert_remote<M: RemoteMessage>() {}
+/// assert_remote::<Ping>();
+/// ```
+///
+/// [`Serialize`]: https://docs.rs/serde/1/serde/trait.Serialize.html
+/// [`DeserializeOwned`]: https://docs.rs/serde/1/serde/de/trait.DeserializeOwned.html
+pub trait RemoteMessage: Message + Serialize + DeserializeOwned {}
+impl<T> RemoteMessage for T where T: Message + Serialize + DeserializeOwned {}
+
+/// A format used to serialize the messages sent between nodes.
+///
+/// Messages are given to codecs as type-erased [`erased_serde`]
+/// values, which allows to select the codec of a node at runtime.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// use bastion::codec::erased_serde;
+///
+/// #[derive(Debug)]
+/// // A codec using JSON in a more readable way...
+/// struct PrettyJsonCodec;
+///
+/// impl MessageCodec for PrettyJsonCodec {
+///     fn name(&self) -> &str {
+///         // ...whose payloads can be read by the JSON codec.
+///         "json"
+///     }
+///
+///     fn encode(&self, msg: &dyn erased_serde::Serialize) -> Result<Vec<u8>, String> {
+///         serde_json::to_vec_pretty(msg).map_err(|err| err.to_string())
+///     }
+///
+///     fn decode(
+///         &self,
+///         payload: &[u8],
+///         visitor: &mut dyn FnMut(
+///             &mut dyn erased_serde::Deserializer,
+///         ) -> Result<(), erased_serde::Error>,
+///     ) -> Result<(), String> {
+///         JsonCodec.decode(payload, visitor)
+///     }
+/// }
+///
+/// let config = RemotingConfig::default().with_codec(PrettyJsonCodec);
+/// # drop(config);
+/// ```
+///
+/// [`erased_serde`]: https://docs.rs/erased-serde/0.3/erased_serde/
+pub trait MessageCodec: Debug + Send + Sync + 'static {
+    /// Returns the name of the codec, which is exchanged during
+    /// handshakes to make sure that both nodes use the same format.
+    fn name(&self) -> &str;
+
+    /// Serializes a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to serialize.
+    fn encode(&self, msg: &dyn erased_serde::Serialize) -> Result<Vec<u8>, String>;
+
+    /// Deserializes a message, by giving a deserializer reading the
+    /// payload to `visitor`.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The serialized message.
+    /// * `visitor` - The function deserializing the message from
+    ///     the deserializer.
+    fn decode(
+        &self,
+        payload: &[u8],
+        visitor: &mut dyn FnMut(
+            &mut dyn erased_serde::Deserializer,
+        ) -> Result<(), erased_serde::Error>,
+    ) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// A [`MessageCodec`] serializing messages to JSON, using
+/// [`serde_json`]. This is the default codec.
+///
+/// [`MessageCodec`]: trait.MessageCodec.html
+/// [`serde_json`]: https://docs.rs/serde_json/1/serde_json/
+pub struct JsonCodec;
+
+#[derive(Debug, Clone, Copy, Default)]
+/// A [`MessageCodec`] serializing messages to a compact binary
+/// format, using [`bincode`].
+///
+/// [`MessageCodec`]: trait.MessageCodec.html
+/// [`bincode`]: https://docs.rs/bincode/1/bincode/
+pub struct BincodeCodec;
+
+impl MessageCodec for JsonCodec {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn encode(&self, msg: &dyn erased_serde::Serialize) -> Result<Vec<u8>, String> {
+        serde_json::to_vec(msg).map_err(|err| err.to_string())
+    }
+
+    fn decode(
+        &self,
+        payload: &[u8],
+        visitor: &mut dyn FnMut(
+            &mut dyn erased_serde::Deserializer,
+        ) -> Result<(), erased_serde::Error>,
+    ) -> Result<(), String> {
+        let mut deserializer = serde_json::Deserializer::from_slice(payload);
+        visitor(&mut <dyn erased_serde::Deserializer>::erase(
+            &mut deserializer,
+        ))
+        .map_err(|err| err.to_string())?;
+        deserializer.end().map_err(|err| err.to_string())
+    }
+}
+
+impl MessageCodec for BincodeCodec {
+    fn name(&self) -> &str {
+        "bincode"
+    }
+
+    fn encode(&self, msg: &dyn erased_serde::Serialize) -> Result<Vec<u8>, String> {
+        bincode::DefaultOptions::new()
+            .serialize(msg)
+            .map_err(|err| err.to_string())
+    }
+
+    fn decode(
+        &self,
+        payload: &[u8],
+        visitor: &mut dyn FnMut(
+            &mut dyn erased_serde::Deserializer,
+        ) -> Result<(), erased_serde::Error>,
+    ) -> Result<(), String> {
+        let mut deserializer =
+            bincode::Deserializer::from_slice(payload, bincode::DefaultOptions::new());
+        visitor(&mut <dyn erased_serde::Deserializer>::erase(
+            &mut deserializer,
+        ))
+        .map_err(|err| err.to_string())
+    }
+}
+
+/// Serializes a message using the codec.
+pub(crate) fn encode<M: RemoteMessage>(
+    codec: &dyn MessageCodec,
+    msg: &M,
+) -> Result<Vec<u8>, String> {
+    codec.encode(msg)
+}
+
+/// Deserializes a message using the codec.
+pub(crate) fn decode<M: RemoteMessage>(
+    codec: &dyn MessageCodec,
+    payload: &[u8],
+) -> Result<M, String> {
+    let mut decoded = None;
+    codec.decode(payload, &mut |deserializer| {
+        decoded = Some(erased_serde::deserialize(deserializer)?);
+        Ok(())
+    })?;
+
+    decoded.ok_or_else(|| "the codec didn't deserialize the message".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Ping {
+        id: u64,
+        label: String,
+    }
+
+    #[test]
+    fn roundtrips_messages() {
+        let ping = Ping {
+            id: 42,
+            label: "ping".to_string(),
+        };
+
+        for codec in &[&JsonCodec as &dyn MessageCodec, &BincodeCodec] {
+            let payload = encode(*codec, &ping).unwrap();
+            let decoded: Ping = decode(*codec, &payload).unwrap();
+            assert_eq!(decoded, ping);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_payloads() {
+        assert!(decode::<Ping>(&JsonCodec, b"{\"id\": 42}").is_err());
+        assert!(decode::<Ping>(&BincodeCodec, &[1, 2]).is_err());
+    }
+}
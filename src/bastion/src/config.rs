@@ -1,9 +1,53 @@
+use crate::blocking_pool::BlockingPoolConfig;
+#[cfg(feature = "cluster")]
+use crate::cluster::ClusterConfig;
+use crate::errors::ConfigError;
+use crate::executor::{Affinity, Executor};
+use crate::mailbox::MailboxConfig;
+use crate::rate_limit::RateLimit;
+#[cfg(feature = "remote")]
+use crate::remote::RemotingConfig;
+#[cfg(feature = "scaling")]
+use crate::resizer::UpperBound;
+use crate::rt::Budget;
+use crate::settings::Settings;
+use crate::supervisor::RestartStrategy;
+use fxhash::FxHashMap;
+use std::env;
+#[cfg(feature = "config-file")]
+use std::fs;
+#[cfg(feature = "remote")]
+use std::net::SocketAddr;
+#[cfg(feature = "config-file")]
+use std::path::Path;
+use tracing::Level;
+
 #[derive(Default, Debug, Clone)]
 /// The configuration that should be used to initialize the
 /// system using [`Bastion::init_with`].
 ///
 /// The default behaviors are the following:
 /// - All backtraces are shown (see [`Config::show_backtraces`]).
+/// - The system runs on the thread pools of `bastion-executor`
+///   (see [`Config::with_executor`]).
+/// - The blocking tasks run on the blocking pool of the executor
+///   (see [`Config::with_blocking_pool`]).
+/// - The threads of `bastion-executor` are pinned onto the cores in
+///   a round-robin fashion (see [`Config::with_executor_threads`]
+///   and [`Config::with_thread_affinity`]).
+/// - The children yield to the executor after 128 operations per
+///   poll (see [`Config::with_budget`]).
+/// - The mailboxes of the children are unbounded (see
+///   [`Config::with_default_mailbox`]).
+/// - Every log is let through by the [`LogLevelLayer`] (see
+///   [`Config::with_log_level`]).
+///
+/// A configuration can also be loaded from a file (see
+/// [`Config::from_file`]) or from the environment (see
+/// [`Config::from_env`]), so that a deployment can be tuned
+/// without being recompiled, and some of its settings can be
+/// changed while the system is running (see
+/// [`Bastion::reload_config`]).
 ///
 /// # Example
 ///
@@ -35,8 +79,41 @@
 /// ```
 ///
 /// [`Bastion::init_with`]: struct.Bastion.html#method.init_with
+/// [`Config::show_backtraces`]: #method.show_backtraces
+/// [`Config::with_executor`]: #method.with_executor
+/// [`Config::with_blocking_pool`]: #method.with_blocking_pool
+/// [`Config::with_executor_threads`]: #method.with_executor_threads
+/// [`Config::with_thread_affinity`]: #method.with_thread_affinity
+/// [`Config::with_budget`]: #method.with_budget
+/// [`Config::with_default_mailbox`]: #method.with_default_mailbox
+/// [`Config::from_file`]: #method.from_file
+/// [`Config::from_env`]: #method.from_env
+/// [`LogLevelLayer`]: reload/struct.LogLevelLayer.html
+/// [`Config::with_log_level`]: #method.with_log_level
+/// [`Bastion::reload_config`]: struct.Bastion.html#method.reload_config
 pub struct Config {
     backtraces: Backtraces,
+    executor: Executor,
+    blocking_pool: Option<BlockingPoolConfig>,
+    executor_threads: Option<usize>,
+    thread_affinity: Option<Affinity>,
+    budget: Budget,
+    default_mailbox: Option<MailboxConfig>,
+    // The restart strategies of the children groups, by name.
+    restart_strategies: FxHashMap<String, RestartStrategy>,
+    // The capacities of the bounded mailboxes, the rate limits and
+    // the bounds of the resizers of the children groups, by name.
+    mailbox_capacities: FxHashMap<String, usize>,
+    rate_limits: FxHashMap<String, RateLimit>,
+    #[cfg(feature = "scaling")]
+    resizer_bounds: FxHashMap<String, (u64, UpperBound)>,
+    log_level: Option<Level>,
+    #[cfg(feature = "remote")]
+    remoting: Option<(SocketAddr, RemotingConfig)>,
+    #[cfg(feature = "cluster")]
+    cluster: Option<ClusterConfig>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<bool>,
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -59,6 +136,128 @@ impl Config {
         Config::default()
     }
 
+    /// Loads a configuration from a TOML file (usually named
+    /// `bastion.toml`), whose settings can then be overridden by
+    /// environment variables (see [`Config::from_env`]). This
+    /// method is available with the `config-file` feature.
+    ///
+    /// The file can set the executor threads and budget, the
+    /// dedicated blocking pool, the default mailboxes (see
+    /// [`Config::with_default_mailbox`]), the restart strategies of
+    /// the children groups by name (see
+    /// [`Config::with_restart_strategy`]), the address the node is
+    /// bound to and the cluster it joins, and whether the metrics
+    /// are collected:
+    ///
+    /// ```toml
+    /// [executor]
+    /// threads = 8
+    ///
+    /// [blocking_pool]
+    /// max_threads = 16
+    /// keep_alive_ms = 30000
+    ///
+    /// [mailbox]
+    /// capacity = 1024
+    /// overflow = "drop_oldest"
+    ///
+    /// [restart_strategies.workers]
+    /// max_restarts = 5
+    /// backoff = "exponential"
+    /// base_ms = 100
+    /// max_ms = 10000
+    ///
+    /// [remote]
+    /// bind = "0.0.0.0:4222"
+    ///
+    /// [cluster]
+    /// seeds = ["10.0.0.2:4222"]
+    ///
+    /// [metrics]
+    /// enabled = true
+    /// ```
+    ///
+    /// This method returns the configuration if it succeeded, or
+    /// `Err(error)` if the file couldn't be read or a setting is
+    /// invalid.
+    ///
+    /// # Argument
+    ///
+    /// * `path` - The path of the file.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use bastion::prelude::*;
+    ///
+    /// # fn run() -> Result<(), ConfigError> {
+    /// let config = Config::from_file("bastion.toml")?;
+    ///
+    /// Bastion::init_with(config);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Config::from_env`]: #method.from_env
+    /// [`Config::with_default_mailbox`]: #method.with_default_mailbox
+    /// [`Config::with_restart_strategy`]: #method.with_restart_strategy
+    #[cfg(feature = "config-file")]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let toml = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        let mut settings = Settings::from_toml(&toml)?;
+        settings.override_with(|key| env::var(key).ok())?;
+
+        settings.apply(Config::new())
+    }
+
+    /// Loads a configuration from the environment variables named
+    /// after the settings of [`Config::from_file`], prefixed with
+    /// `BASTION_` (e.g. `BASTION_EXECUTOR_THREADS`,
+    /// `BASTION_BLOCKING_POOL_MAX_THREADS`, `BASTION_MAILBOX_CAPACITY`,
+    /// `BASTION_REMOTE_BIND`, `BASTION_CLUSTER_SEEDS` with addresses
+    /// separated by commas, or `BASTION_METRICS_ENABLED`).
+    ///
+    /// The restart strategies of the children groups can only be
+    /// set in a file.
+    ///
+    /// This method returns the configuration if it succeeded, or
+    /// `Err(error)` if a variable is invalid.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// let config = Config::from_env().expect("Invalid configuration.");
+    ///
+    /// Bastion::init_with(config);
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Config::from_file`]: #method.from_file
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let mut settings = Settings::default();
+        settings.override_with(|key| env::var(key).ok())?;
+
+        settings.apply(Config::new())
+    }
+
     /// Makes Bastion show all backtraces, like an application
     /// without it would. This can be useful when trying to
     /// debug children panicking.
@@ -140,9 +339,439 @@ impl Config {
         self
     }
 
+    /// Sets the executor the system spawns its supervisors,
+    /// children, timers and blocking tasks on.
+    ///
+    /// By default, the system runs on the thread pools of
+    /// `bastion-executor`, but it can run on a `tokio` runtime or
+    /// on any implementation of [`AgnosticExecutor`].
+    ///
+    /// # Argument
+    ///
+    /// * `executor` - The executor the system runs on.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use bastion::prelude::*;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # fn run() {
+    /// let runtime = tokio::runtime::Runtime::new().unwrap();
+    /// let config = Config::new().with_executor(Executor::Tokio(runtime.handle().clone()));
+    ///
+    /// Bastion::init_with(config);
+    ///
+    /// // The children now run on the runtime...
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`AgnosticExecutor`]: executor/trait.AgnosticExecutor.html
+    pub fn with_executor(mut self, executor: Executor) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Sets the amount of threads of `bastion-executor` that are
+    /// always available to run the supervisors and children of the
+    /// system (which defaults to the `BASTION_BLOCKING_THREADS`
+    /// environment variable, or 2).
+    ///
+    /// This only applies when the system runs on `bastion-executor`
+    /// (see [`Config::with_executor`]), and before its threads are
+    /// started.
+    ///
+    /// # Argument
+    ///
+    /// * `threads` - The amount of threads.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    ///
+    /// let config = Config::new().with_executor_threads(8);
+    /// ```
+    ///
+    /// [`Config::with_executor`]: #method.with_executor
+    pub fn with_executor_threads(mut self, threads: usize) -> Self {
+        self.executor_threads = Some(threads);
+        self
+    }
+
+    /// Sets how the threads of `bastion-executor` are pinned onto
+    /// the cores (by default, every new thread is pinned onto the
+    /// next core).
+    ///
+    /// Like [`Config::with_executor_threads`], this only applies
+    /// when the system runs on `bastion-executor`. To pin the
+    /// elements of a specific children group, see
+    /// [`Children::with_realtime_threads`].
+    ///
+    /// # Argument
+    ///
+    /// * `affinity` - How the threads are pinned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    ///
+    /// // Keeps the first core for something else...
+    /// let cores = (1..num_cpus::get()).collect();
+    /// let config = Config::new().with_thread_affinity(Affinity::Cores(cores));
+    /// ```
+    ///
+    /// [`Config::with_executor_threads`]: #method.with_executor_threads
+    /// [`Children::with_realtime_threads`]: children/struct.Children.html#method.with_realtime_threads
+    pub fn with_thread_affinity(mut self, affinity: Affinity) -> Self {
+        self.thread_affinity = Some(affinity);
+        self
+    }
+
+    /// Sets the budget of the children, after which they are
+    /// forced to yield to the executor (see the [`rt`] module).
+    ///
+    /// # Argument
+    ///
+    /// * `budget` - The budget of every poll of a child, or
+    ///     [`Budget::unlimited`] to disable cooperative scheduling.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    ///
+    /// let config = Config::new().with_budget(Budget::new().with_operations(32));
+    /// ```
+    ///
+    /// [`rt`]: rt/index.html
+    /// [`Budget::unlimited`]: rt/struct.Budget.html#method.unlimited
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Makes the blocking tasks of the system (those spawned using
+    /// `blocking!`) run on a dedicated pool, whose amount of
+    /// threads and queued tasks is bounded by `blocking_pool`.
+    ///
+    /// By default, they run on the blocking pool of the executor
+    /// (see [`Config::with_executor`]).
+    ///
+    /// # Argument
+    ///
+    /// * `blocking_pool` - The configuration of the pool.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// let blocking_pool = BlockingPoolConfig::new()
+    ///     .with_max_threads(16)
+    ///     .with_keep_alive(Duration::from_secs(30))
+    ///     .with_queue_limit(256);
+    /// let config = Config::new().with_blocking_pool(blocking_pool);
+    ///
+    /// Bastion::init_with(config);
+    ///
+    /// // The blocking tasks now run on the dedicated pool...
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Config::with_executor`]: #method.with_executor
+    pub fn with_blocking_pool(mut self, blocking_pool: BlockingPoolConfig) -> Self {
+        self.blocking_pool = Some(blocking_pool);
+        self
+    }
+
+    /// Sets the mailbox of the elements of the children groups that
+    /// don't set their own (see [`Children::with_mailbox`]), which
+    /// are unbounded by default.
+    ///
+    /// # Argument
+    ///
+    /// * `mailbox` - The configuration of the mailboxes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    ///
+    /// let mailbox = MailboxConfig::bounded(1024).with_overflow(OverflowStrategy::DropOldest);
+    /// let config = Config::new().with_default_mailbox(mailbox);
+    /// ```
+    ///
+    /// [`Children::with_mailbox`]: children/struct.Children.html#method.with_mailbox
+    pub fn with_default_mailbox(mut self, mailbox: MailboxConfig) -> Self {
+        self.default_mailbox = Some(mailbox);
+        self
+    }
+
+    /// Sets the restart strategy applied to the elements of the
+    /// children groups with the given name (see
+    /// [`Children::with_name`]), instead of the one of their
+    /// supervisor (see [`Supervisor::with_restart_strategy`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the children groups.
+    /// * `restart_strategy` - The restart strategy of their elements.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let restart_strategy = RestartStrategy::default()
+    ///     .with_restart_policy(RestartPolicy::Tries(5))
+    ///     .with_actor_restart_strategy(ActorRestartStrategy::LinearBackOff {
+    ///         timeout: Duration::from_millis(100),
+    ///     });
+    /// let config = Config::new().with_restart_strategy("workers", restart_strategy);
+    /// ```
+    ///
+    /// [`Children::with_name`]: children/struct.Children.html#method.with_name
+    /// [`Supervisor::with_restart_strategy`]: supervisor/struct.Supervisor.html#method.with_restart_strategy
+    pub fn with_restart_strategy(
+        mut self,
+        name: impl Into<String>,
+        restart_strategy: RestartStrategy,
+    ) -> Self {
+        self.restart_strategies
+            .insert(name.into(), restart_strategy);
+        self
+    }
+
+    /// Sets the capacity of the mailboxes of the elements of the
+    /// children groups with the given name (see
+    /// [`Children::with_name`]), if they are bounded (see
+    /// [`Children::with_mailbox`]).
+    ///
+    /// Unlike the rest of their mailboxes' configuration, this
+    /// capacity can be changed while the groups are running (see
+    /// [`Bastion::reload_config`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the children groups.
+    /// * `capacity` - The maximum amount of messages each mailbox
+    ///     can hold.
+    ///
+    /// [`Children::with_name`]: children/struct.Children.html#method.with_name
+    /// [`Children::with_mailbox`]: children/struct.Children.html#method.with_mailbox
+    /// [`Bastion::reload_config`]: struct.Bastion.html#method.reload_config
+    pub fn with_mailbox_capacity(mut self, name: impl Into<String>, capacity: usize) -> Self {
+        self.mailbox_capacities.insert(name.into(), capacity);
+        self
+    }
+
+    /// Sets the rate limit of the children groups with the given
+    /// name (see [`Children::with_name`] and
+    /// [`Children::with_rate_limit`]), which can be changed while
+    /// the groups are running (see [`Bastion::reload_config`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the children groups.
+    /// * `rate_limit` - The rate limit of each group.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    ///
+    /// let config = Config::new().with_rate_limit("api_clients", RateLimit::per_second(50));
+    /// ```
+    ///
+    /// [`Children::with_name`]: children/struct.Children.html#method.with_name
+    /// [`Children::with_rate_limit`]: children/struct.Children.html#method.with_rate_limit
+    /// [`Bastion::reload_config`]: struct.Bastion.html#method.reload_config
+    pub fn with_rate_limit(mut self, name: impl Into<String>, rate_limit: RateLimit) -> Self {
+        self.rate_limits.insert(name.into(), rate_limit);
+        self
+    }
+
+    #[cfg(feature = "scaling")]
+    /// Sets the bounds of the resizer of the children groups with
+    /// the given name (see [`Children::with_name`] and
+    /// [`Children::with_resizer`]), which can be changed while
+    /// the groups are running (see [`Bastion::reload_config`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the children groups.
+    /// * `lower_bound` - The minimal amount of elements of each group.
+    /// * `upper_bound` - The maximal amount of elements of each group.
+    ///
+    /// [`Children::with_name`]: children/struct.Children.html#method.with_name
+    /// [`Children::with_resizer`]: children/struct.Children.html#method.with_resizer
+    /// [`Bastion::reload_config`]: struct.Bastion.html#method.reload_config
+    pub fn with_resizer_bounds(
+        mut self,
+        name: impl Into<String>,
+        lower_bound: u64,
+        upper_bound: UpperBound,
+    ) -> Self {
+        self.resizer_bounds
+            .insert(name.into(), (lower_bound.max(1), upper_bound));
+        self
+    }
+
+    /// Sets the most verbose level of the logs let through by the
+    /// [`LogLevelLayer`], which can be changed while the system is
+    /// running (see [`Bastion::reload_config`]).
+    ///
+    /// # Argument
+    ///
+    /// * `level` - The most verbose level of the logs let through.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bastion::prelude::*;
+    ///
+    /// let config = Config::new().with_log_level(tracing::Level::WARN);
+    /// ```
+    ///
+    /// [`LogLevelLayer`]: reload/struct.LogLevelLayer.html
+    /// [`Bastion::reload_config`]: struct.Bastion.html#method.reload_config
+    pub fn with_log_level(mut self, level: Level) -> Self {
+        self.log_level = Some(level);
+        self
+    }
+
+    #[cfg(feature = "remote")]
+    /// Makes the node bind to the given address with the given
+    /// configuration once the system is initialized (see
+    /// [`Bastion::bind_with`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to listen on.
+    /// * `remoting` - The configuration of the remoting subsystem.
+    ///
+    /// [`Bastion::bind_with`]: struct.Bastion.html#method.bind_with
+    pub fn with_remoting(mut self, addr: SocketAddr, remoting: RemotingConfig) -> Self {
+        self.remoting = Some((addr, remoting));
+        self
+    }
+
+    #[cfg(feature = "cluster")]
+    /// Makes the node join a cluster once it is bound (see
+    /// [`Config::with_remoting`] and [`Cluster::join`]).
+    ///
+    /// # Argument
+    ///
+    /// * `cluster` - The configuration of the cluster membership.
+    ///
+    /// [`Config::with_remoting`]: #method.with_remoting
+    /// [`Cluster::join`]: cluster/struct.Cluster.html#method.join
+    pub fn with_cluster(mut self, cluster: ClusterConfig) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    #[cfg(feature = "metrics")]
+    /// Sets whether the metrics of the system are collected (see
+    /// the [`metrics`] module), which they are by default.
+    ///
+    /// # Argument
+    ///
+    /// * `enabled` - Whether the metrics are collected.
+    ///
+    /// [`metrics`]: metrics/index.html
+    pub fn with_metrics(mut self, enabled: bool) -> Self {
+        self.metrics = Some(enabled);
+        self
+    }
+
     pub(crate) fn backtraces(&self) -> &Backtraces {
         &self.backtraces
     }
+
+    pub(crate) fn executor(&self) -> &Executor {
+        &self.executor
+    }
+
+    pub(crate) fn blocking_pool(&self) -> Option<&BlockingPoolConfig> {
+        self.blocking_pool.as_ref()
+    }
+
+    pub(crate) fn executor_threads(&self) -> Option<usize> {
+        self.executor_threads
+    }
+
+    pub(crate) fn thread_affinity(&self) -> Option<&Affinity> {
+        self.thread_affinity.as_ref()
+    }
+
+    pub(crate) fn budget(&self) -> Budget {
+        self.budget
+    }
+
+    pub(crate) fn default_mailbox(&self) -> Option<&MailboxConfig> {
+        self.default_mailbox.as_ref()
+    }
+
+    pub(crate) fn restart_strategies(&self) -> &FxHashMap<String, RestartStrategy> {
+        &self.restart_strategies
+    }
+
+    pub(crate) fn mailbox_capacities(&self) -> &FxHashMap<String, usize> {
+        &self.mailbox_capacities
+    }
+
+    pub(crate) fn rate_limits(&self) -> &FxHashMap<String, RateLimit> {
+        &self.rate_limits
+    }
+
+    #[cfg(feature = "scaling")]
+    pub(crate) fn resizer_bounds(&self) -> &FxHashMap<String, (u64, UpperBound)> {
+        &self.resizer_bounds
+    }
+
+    pub(crate) fn log_level(&self) -> Option<Level> {
+        self.log_level
+    }
+
+    #[cfg(feature = "remote")]
+    pub(crate) fn remoting(&self) -> Option<&(SocketAddr, RemotingConfig)> {
+        self.remoting.as_ref()
+    }
+
+    #[cfg(feature = "cluster")]
+    pub(crate) fn cluster(&self) -> Option<&ClusterConfig> {
+        self.cluster.as_ref()
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn metrics(&self) -> Option<bool> {
+        self.metrics
+    }
 }
 
 impl Backtraces {
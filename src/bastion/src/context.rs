@@ -2,31 +2,77 @@
 //! A context allows a child's future to access its received
 //! messages, parent and supervisor.
 
+use crate::behavior::{Behavior, Behaviors};
+use crate::callbacks::{ChildFailure, StateBag};
+use crate::cancellation::CancellationToken;
 use crate::child_ref::ChildRef;
 use crate::children_ref::ChildrenRef;
+use crate::circuit_breaker::CircuitBreaker;
+use crate::delivery::{self, DeliveryId};
 use crate::dispatcher::{BroadcastTarget, DispatcherType, NotificationType};
-use crate::envelope::{Envelope, RefAddr, SignedMessage};
-use crate::message::{Answer, BastionMessage, Message, Msg};
-use crate::supervisor::SupervisorRef;
-use crate::{prelude::ReceiveError, system::SYSTEM};
+use crate::envelope::{Envelope, Headers, RefAddr, SignedMessage};
+#[cfg(feature = "persistence")]
+use crate::errors::PersistenceError;
+use crate::events::{self, SupervisionEvent};
+use crate::executor;
+use crate::local_storage::LocalStorage;
+use crate::mailbox::{Mailbox, MailboxConfig, Priority};
+use crate::message::{Answer, AnswerStream, BastionMessage, Message, Msg};
+#[cfg(feature = "metrics")]
+use crate::metrics;
+#[cfg(not(target_os = "windows"))]
+use crate::net::{self, TcpConnection};
+#[cfg(feature = "persistence")]
+use crate::persistence::{Journal, Persistent, PersistentChild};
+use crate::rate_limit::RateLimiter;
+use crate::rt;
+use crate::scope::{self, Nursery};
+use crate::state::StateRegistry;
+use crate::supervisor::{RestartStrategy, SupervisorRef};
+use crate::watch::{StopReason, Terminated};
+#[cfg(all(feature = "websocket", not(target_os = "windows")))]
+use crate::websocket::WebSocket;
+use crate::{
+    prelude::ReceiveError,
+    system::{self, SYSTEM},
+};
 
-use crossbeam_queue::SegQueue;
+use bastion_executor::timer::TimerHandle;
 use futures::pending;
+use futures::stream::{self, Stream};
 use futures::FutureExt;
 use futures_timer::Delay;
 #[cfg(feature = "scaling")]
 use lever::table::lotable::LOTable;
+#[cfg(feature = "persistence")]
+use serde::{de::DeserializeOwned, Serialize};
 use std::fmt::{self, Display, Formatter};
+use std::future::Future;
+#[cfg(not(target_os = "windows"))]
+use std::io;
+#[cfg(not(target_os = "windows"))]
+use std::net::SocketAddr;
 use std::pin::Pin;
 #[cfg(feature = "scaling")]
 use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+#[cfg(feature = "otel")]
+use std::thread::{self, ThreadId};
+use std::time::Instant;
 use std::{sync::Arc, time::Duration};
-use tracing::{debug, trace};
+#[cfg(feature = "otel")]
+use tracing::Span;
+use tracing::{debug, trace, warn};
 use uuid::Uuid;
 
 /// Identifier for a root supervisor and dead-letters children.
 pub const NIL_ID: BastionId = BastionId(Uuid::nil());
 
+// How long a throttled child sleeps at most before checking whether
+// it can pop its next message again.
+const THROTTLE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(Hash, Eq, PartialEq, Debug, Clone)]
 /// An identifier used by supervisors, children groups and
 /// their elements to identify themselves, using a v4 UUID.
@@ -136,17 +182,89 @@ pub struct BastionContext {
     children: ChildrenRef,
     supervisor: Option<SupervisorRef>,
     state: Arc<Pin<Box<ContextState>>>,
+    // The behaviors stack of the child (which isn't kept when
+    // it gets restarted).
+    behaviors: Behaviors,
+    // The token cancelled when this incarnation of the child is
+    // stopped or restarted.
+    cancellation: CancellationToken,
+    // The journal of the children group, if it has one.
+    #[cfg(feature = "persistence")]
+    journal: Option<Arc<dyn Journal>>,
+    // The amount of snapshots kept by the children group, if
+    // it has a snapshot retention.
+    #[cfg(feature = "persistence")]
+    snapshot_retention: Option<usize>,
 }
 
 #[derive(Debug)]
 pub(crate) struct ContextState {
-    messages: SegQueue<SignedMessage>,
+    mailbox: Arc<Mailbox>,
     #[cfg(feature = "scaling")]
     stats: Arc<AtomicU64>,
     #[cfg(feature = "scaling")]
     actor_stats: Arc<LOTable<BastionId, u32>>,
+    // How long the group's elements took to process their last
+    // message (in microseconds), and when this child received its
+    // last message if it wasn't processed yet.
+    #[cfg(feature = "scaling")]
+    actor_latencies: Arc<LOTable<BastionId, u64>>,
+    #[cfg(feature = "scaling")]
+    processing_since: Mutex<Option<Instant>>,
+    // When the last message was received, if it wasn't
+    // processed yet.
+    #[cfg(feature = "metrics")]
+    last_recv: Mutex<Option<Instant>>,
+    // The span of the message being processed, and the thread it
+    // is entered on while the child is polled.
+    #[cfg(feature = "otel")]
+    span: Mutex<(Span, Option<ThreadId>)>,
+    // Whether the child is waiting for a message.
+    waiting: AtomicBool,
+    // Whether the child called `BastionContext::done`.
+    done: AtomicBool,
+    // Whether the child stopped dequeuing its messages until it
+    // is resumed.
+    paused: AtomicBool,
+    // The values handed off to the child's next incarnation.
+    state_bag: StateBag,
+    // The values only available to the child, mostly cleared
+    // when it is restarted.
+    local_storage: LocalStorage,
+    // How many times the child was restarted.
+    restarts: AtomicUsize,
+    // Why the child is being restarted, if it is.
+    failure: Mutex<Option<ChildFailure>>,
+    // Why the child stopped, if its future finished successfully
+    // for another reason than being done.
+    stop_reason: Mutex<Option<StopReason>>,
+    // The circuit breaker of the child's group, if it has one,
+    // and whether the child is handling a message it popped.
+    circuit_breaker: Option<CircuitBreaker>,
+    handling: AtomicBool,
+    // The token bucket shared by the child's group, if it has a
+    // rate limit.
+    rate_limiter: Option<RateLimiter>,
+    // When the child last answered a heartbeat of its group (or
+    // started).
+    heartbeat: Mutex<Instant>,
+    // How long the child can take to handle a message before it
+    // is reported as slow, if it is, and the message being handled
+    // (its type and since when, and whether it was reported).
+    slow_handler_threshold: Option<Duration>,
+    handled_msg: Mutex<Option<(&'static str, Instant, bool)>>,
+    // How the supervisor restarts the child instead of using its
+    // own restart strategy, if it should.
+    restart_strategy: Option<RestartStrategy>,
 }
 
+/// Marks a child as waiting for a message until it is dropped.
+struct Waiting<'a>(&'a ContextState);
+
+// Exits the span of the message being processed once dropped.
+#[cfg(feature = "otel")]
+pub(crate) struct EnteredSpan<'a>(&'a ContextState);
+
 impl BastionId {
     pub(crate) fn new() -> Self {
         let uuid = Uuid::new_v4();
@@ -164,15 +282,39 @@ impl BastionContext {
         state: Arc<Pin<Box<ContextState>>>,
     ) -> Self {
         debug!("BastionContext({}): Creating.", id);
+        let behaviors = Behaviors::default();
+        let cancellation = CancellationToken::new();
         BastionContext {
             id,
             child,
             children,
             supervisor,
             state,
+            behaviors,
+            cancellation,
+            #[cfg(feature = "persistence")]
+            journal: None,
+            #[cfg(feature = "persistence")]
+            snapshot_retention: None,
         }
     }
 
+    pub(crate) fn context_state(&self) -> &Arc<Pin<Box<ContextState>>> {
+        &self.state
+    }
+
+    #[cfg(feature = "persistence")]
+    pub(crate) fn with_journal(mut self, journal: Option<Arc<dyn Journal>>) -> Self {
+        self.journal = journal;
+        self
+    }
+
+    #[cfg(feature = "persistence")]
+    pub(crate) fn with_snapshot_retention(mut self, snapshot_retention: Option<usize>) -> Self {
+        self.snapshot_retention = snapshot_retention;
+        self
+    }
+
     /// Returns a [`ChildRef`] referencing the children group's
     /// element that is linked to this `BastionContext`.
     ///
@@ -390,7 +532,7 @@ impl BastionContext {
 
         trace!("BastionContext({}): Trying to receive message.", self.id);
 
-        if let Some(msg) = self.state.pop_message() {
+        if let Some(msg) = self.pop_message() {
             trace!("BastionContext({}): Received message: {:?}", self.id, msg);
             Some(msg)
         } else {
@@ -453,12 +595,25 @@ impl BastionContext {
     /// [`SignedMessage`]: ../prelude/struct.SignedMessage.html
     pub async fn recv(&self) -> Result<SignedMessage, ()> {
         debug!("BastionContext({}): Waiting to receive message.", self.id);
+        // A child waiting for a message with an empty mailbox can
+        // be stopped by a graceful shutdown.
+        let _waiting = Waiting::new(&**self.state);
+        // A child always receiving messages yields once its budget
+        // is exhausted.
+        rt::consume_budget().await;
         loop {
-            if let Some(msg) = self.state.pop_message() {
+            if let Some(msg) = self.pop_message() {
                 trace!("BastionContext({}): Received message: {:?}", self.id, msg);
                 return Ok(msg);
             }
-            pending!();
+
+            // A throttled child is woken up once it can pop its
+            // next message, or to check whether its group's rate
+            // limit was changed (see `Bastion::reload_config`).
+            match self.state.throttled_for() {
+                Some(delay) => executor::sleep(delay.min(THROTTLE_CHECK_INTERVAL)).await,
+                None => self.wait_message().await,
+            }
         }
     }
 
@@ -530,64 +685,157 @@ impl BastionContext {
             message = self.recv().fuse() => {
                 message.map_err(|_| ReceiveError::Other)
             },
-            _duration = Delay::new(timeout).fuse() => {
+            _duration = executor::sleep(timeout).fuse() => {
                 Err(ReceiveError::Timeout(timeout))
             }
         }
     }
 
-    /// Returns [`RefAddr`] of the current `BastionContext`
+    /// Retrieves asynchronously a batch of messages received by the
+    /// element this `BastionContext` is linked to, waiting (always
+    /// asynchronously) for at least one of them.
+    ///
+    /// Once the first message was received, the batch is returned
+    /// as soon as it contains `max_items` messages or `max_wait`
+    /// elapsed, allowing to amortize the cost of handling the
+    /// messages (e.g. by writing them to a database at once).
+    ///
+    /// This method returns the messages (in the order they were
+    /// received) if it succeeded, or `Err(())` otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_items` - The maximum amount of messages in the batch
+    ///     (at least `1`).
+    /// * `max_wait` - How long to wait for more messages once the
+    ///     first one was received.
     ///
     /// # Example
     ///
     /// ```rust
     /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
     /// #
     /// # #[cfg(feature = "tokio-runtime")]
     /// # #[tokio::main]
     /// # async fn main() {
-    /// #    run();    
+    /// #    run();
     /// # }
     /// #
     /// # #[cfg(not(feature = "tokio-runtime"))]
     /// # fn main() {
-    /// #    run();    
+    /// #    run();
     /// # }
     /// #
     /// # fn run() {
     /// # Bastion::init();
     /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             loop {
+    ///                 // At most 100 rows at once, and at most 50ms after the first one...
+    ///                 let rows = ctx.recv_batch(100, Duration::from_millis(50)).await?;
+    ///                 // Insert the rows...
+    ///             }
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    pub async fn recv_batch(
+        &self,
+        max_items: usize,
+        max_wait: Duration,
+    ) -> Result<Vec<SignedMessage>, ()> {
+        debug!(
+            "BastionContext({}): Waiting to receive a batch of at most {} messages.",
+            self.id, max_items
+        );
+        let max_items = max_items.max(1);
+        let mut batch = vec![self.recv().await?];
+        let mut deadline = executor::sleep(max_wait).fuse();
+        while batch.len() < max_items {
+            futures::select! {
+                msg = self.recv().fuse() => batch.push(msg?),
+                _ = deadline => break,
+            }
+        }
+
+        trace!(
+            "BastionContext({}): Received a batch of {} messages.",
+            self.id,
+            batch.len()
+        );
+        Ok(batch)
+    }
+
+    /// Returns a [`Stream`] of the messages received by the element
+    /// this `BastionContext` is linked to, which waits (always
+    /// asynchronously) for the next one like [`recv`] does.
     ///
+    /// This allows to handle the messages using the combinators of
+    /// streams (like `filter`, `chunks` or `select`), instead of
+    /// calling [`recv`] in a loop.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use futures::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
     /// Bastion::children(|children| {
     ///     children.with_exec(|ctx: BastionContext| {
     ///         async move {
-    ///             ctx.tell(&ctx.signature(), "Hello to myself");
+    ///             ctx.stream()
+    ///                 .filter(|msg| future::ready(msg.msg().is_ask()))
+    ///                 .for_each(|msg| async move {
+    ///                     // Handle the asked messages...
+    ///                 })
+    ///                 .await;
     ///
-    ///             # Bastion::stop();
     ///             Ok(())
     ///         }
     ///     })
     /// }).expect("Couldn't create the children group.");
     /// #
     /// # Bastion::start();
+    /// # Bastion::stop();
     /// # Bastion::block_until_stopped();
     /// # }
     /// ```
     ///
-    /// [`RefAddr`]: /prelude/struct.Answer.html
-    pub fn signature(&self) -> RefAddr {
-        RefAddr::new(
-            self.current().path().clone(),
-            self.current().sender().clone(),
-        )
+    /// [`Stream`]: https://docs.rs/futures/0.3/futures/stream/trait.Stream.html
+    /// [`recv`]: #method.recv
+    pub fn stream(&self) -> impl Stream<Item = SignedMessage> + '_ {
+        trace!("BastionContext({}): Streaming messages.", self.id);
+        stream::unfold(self, |ctx| async move {
+            let msg = ctx.recv().await.ok()?;
+            Some((msg, ctx))
+        })
     }
 
-    /// Sends a message to the specified [`RefAddr`]
-    ///
-    /// # Arguments
-    ///
-    /// * `to` – the [`RefAddr`] to send the message to
-    /// * `msg` – The actual message to send
+    /// Returns the [`StateBag`] that this element shares with its
+    /// previous and next incarnations, allowing it to recover its
+    /// state (e.g. caches or sequence numbers) once restarted.
     ///
     /// # Example
     ///
@@ -611,13 +859,9 @@ impl BastionContext {
     /// Bastion::children(|children| {
     ///     children.with_exec(|ctx: BastionContext| {
     ///         async move {
-    ///             // Wait for a message to be received...
-    ///             let smsg: SignedMessage = ctx.recv().await?;
-    ///             // Obtain address of this message sender...
-    ///             let sender_addr = smsg.signature();
-    ///             // And send something back
-    ///             ctx.tell(&sender_addr, "Ack").expect("Unable to acknowledge");
-    ///             Ok(())
+    ///             let seq: u64 = ctx.state_bag().get().unwrap_or(0);
+    ///             ctx.state_bag().insert(seq + 1);
+    ///             # Ok(())
     ///         }
     ///     })
     /// }).expect("Couldn't create the children group.");
@@ -628,184 +872,1723 @@ impl BastionContext {
     /// # }
     /// ```
     ///
-    /// [`RefAddr`]: ../prelude/struct.RefAddr.html
-    pub fn tell<M: Message>(&self, to: &RefAddr, msg: M) -> Result<(), M> {
-        debug!(
-            "{:?}: Telling message: {:?} to: {:?}",
-            self.current().path(),
-            msg,
-            to.path()
-        );
-        let msg = BastionMessage::tell(msg);
-        let env = Envelope::new_with_sign(msg, self.signature());
-        // FIXME: panics?
-        to.sender()
-            .unbounded_send(env)
-            .map_err(|err| err.into_inner().into_msg().unwrap())
+    /// [`StateBag`]: ../struct.StateBag.html
+    pub fn state_bag(&self) -> &StateBag {
+        self.state.state_bag()
     }
 
-    /// Sends a message from behalf of current context to the addr,
-    /// allowing to addr owner answer.
-    ///
-    /// This method returns [`Answer`] if it succeeded, or `Err(msg)`
-    /// otherwise.
-    ///
-    /// # Argument
+    /// Returns the [`LocalStorage`] of this element, allowing the
+    /// helpers its future uses to store their own state for it
+    /// without it being passed to its closure.
     ///
-    /// * `msg` - The message to send.
+    /// Its values are cleared when the element is restarted, except
+    /// for the ones inserted using [`LocalStorage::insert_persistent`].
     ///
     /// # Example
     ///
-    /// ```
+    /// ```rust
     /// # use bastion::prelude::*;
     /// #
     /// # #[cfg(feature = "tokio-runtime")]
     /// # #[tokio::main]
     /// # async fn main() {
-    /// #    run();    
+    /// #    run();
     /// # }
     /// #
     /// # #[cfg(not(feature = "tokio-runtime"))]
     /// # fn main() {
-    /// #    run();    
+    /// #    run();
     /// # }
     /// #
     /// # fn run() {
-    ///     # Bastion::init();
-    /// // The message that will be "asked"...
-    /// const ASK_MSG: &'static str = "A message containing data (ask).";
-    /// // The message the will be "answered"...
-    /// const ANSWER_MSG: &'static str = "A message containing data (answer).";
+    /// # Bastion::init();
+    /// #
+    /// struct Session(String);
     ///
-    ///     # let children_ref =
-    /// // Create a new child...
     /// Bastion::children(|children| {
     ///     children.with_exec(|ctx: BastionContext| {
     ///         async move {
-    ///             // ...which will receive the message asked...
-    ///             msg! { ctx.recv().await?,
-    ///                 msg: &'static str =!> {
-    ///                     assert_eq!(msg, ASK_MSG);
-    ///                     // Handle the message...
-    ///
-    ///                     // ...and eventually answer to it...
-    ///                     answer!(ctx, ANSWER_MSG);
-    ///                 };
-    ///                 // This won't happen because this example
-    ///                 // only "asks" a `&'static str`...
-    ///                 _: _ => ();
-    ///             }
-    ///
-    ///             Ok(())
+    ///             ctx.local_storage().insert(Session("token".to_string()));
+    ///             let session = ctx.local_storage().take::<Session>();
+    ///             # Ok(())
     ///         }
     ///     })
     /// }).expect("Couldn't create the children group.");
-    ///
-    ///     # Bastion::children(|children| {
-    ///         # children.with_exec(move |ctx: BastionContext| {
-    ///             # let child_ref = children_ref.elems()[0].clone();
-    ///             # async move {
-    /// // Later, the message is "asked" to the child...
-    /// let answer: Answer = ctx.ask(&child_ref.addr(), ASK_MSG).expect("Couldn't send the message.");
-    ///
-    /// // ...and the child's answer is received...
-    /// msg! { answer.await.expect("Couldn't receive the answer."),
-    ///     msg: &'static str => {
-    ///         assert_eq!(msg, ANSWER_MSG);
-    ///         // Handle the answer...
-    ///     };
-    ///     // This won't happen because this example
-    ///     // only answers a `&'static str`...
-    ///     _: _ => ();
-    /// }
-    ///                 #
-    ///                 # Ok(())
-    ///             # }
-    ///         # })
-    ///     # }).unwrap();
-    ///     #
-    ///     # Bastion::start();
-    ///     # Bastion::stop();
-    ///     # Bastion::block_until_stopped();
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
     /// # }
     /// ```
     ///
-    /// [`Answer`]: /message/struct.Answer.html
-    pub fn ask<M: Message>(&self, to: &RefAddr, msg: M) -> Result<Answer, M> {
-        debug!(
-            "{:?}: Asking message: {:?} to: {:?}",
-            self.current().path(),
-            msg,
-            to
-        );
-        let (msg, answer) = BastionMessage::ask(msg);
-        let env = Envelope::new_with_sign(msg, self.signature());
-        // FIXME: panics?
-        to.sender()
-            .unbounded_send(env)
-            .map_err(|err| err.into_inner().into_msg().unwrap())?;
-
-        Ok(answer)
+    /// [`LocalStorage`]: ../local_storage/struct.LocalStorage.html
+    /// [`LocalStorage::insert_persistent`]: ../local_storage/struct.LocalStorage.html#method.insert_persistent
+    pub fn local_storage(&self) -> &LocalStorage {
+        self.state.local_storage()
     }
 
-    /// Sends the notification to each declared dispatcher of the actor.
+    /// Returns the value of the given type shared with this child,
+    /// looked up in the [`StateRegistry`] of its supervisor (and of
+    /// the supervisor's parents) before the one of the system
+    /// (see [`Bastion::state`]).
     ///
-    /// # Argument
+    /// # Panics
     ///
-    /// * `dispatchers` - Vector of dispatcher names to which need to
-    /// deliver a notification.
-    /// * `notification_type` - The type of the notification to send.
+    /// This method panics (and thus faults the child) if no value
+    /// of this type is shared with the child, see [`try_state`]
+    /// otherwise.
     ///
-    pub fn notify(&self, dispatchers: &[DispatcherType], notification_type: NotificationType) {
-        let global_dispatcher = SYSTEM.dispatcher();
-        let from_actor = self.current();
-        global_dispatcher.notify(from_actor, dispatchers, notification_type);
-    }
-
-    /// Sends the broadcasted message to the target group(s).
+    /// # Example
     ///
-    /// # Argument
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::sync::Arc;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// struct Config {
+    ///     url: String,
+    /// }
     ///
-    /// * `target` - Defines the message receivers in according with
-    /// the [`BroadcastTarget`] value.
-    /// * `message` - The broadcasted message.
+    /// Bastion::state().insert(Config {
+    ///     url: "redis://localhost".to_string(),
+    /// });
     ///
-    /// [`BroadcastTarget`]: ../dispatcher/enum.DispatcherType.html
-    pub fn broadcast_message<M: Message>(&self, target: BroadcastTarget, message: M) {
-        let msg = Arc::new(SignedMessage {
-            msg: Msg::broadcast(message),
-            sign: self.signature(),
-        });
-
-        let global_dispatcher = SYSTEM.dispatcher();
-        global_dispatcher.broadcast_message(target, &msg);
-    }
-}
-
-impl ContextState {
-    pub(crate) fn new() -> Self {
-        ContextState {
-            messages: SegQueue::new(),
-            #[cfg(feature = "scaling")]
-            stats: Arc::new(AtomicU64::new(0)),
-            #[cfg(feature = "scaling")]
-            actor_stats: Arc::new(LOTable::new()),
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| async move {
+    ///         let config: Arc<Config> = ctx.state();
+    ///         // Connect to `config.url`...
+    ///
+    ///         Ok(())
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`StateRegistry`]: ../state/struct.StateRegistry.html
+    /// [`Bastion::state`]: ../struct.Bastion.html#method.state
+    /// [`try_state`]: #method.try_state
+    pub fn state<T: Send + Sync + 'static>(&self) -> Arc<T> {
+        match self.try_state() {
+            Some(value) => value,
+            None => panic!(
+                "No state of type {} was shared.",
+                std::any::type_name::<T>()
+            ),
         }
     }
 
-    #[cfg(feature = "scaling")]
-    pub(crate) fn set_stats(&mut self, stats: Arc<AtomicU64>) {
-        self.stats = stats;
+    /// Returns the value of the given type shared with this child
+    /// (see [`state`]), or `None` if there is none.
+    ///
+    /// [`state`]: #method.state
+    pub fn try_state<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        match &self.supervisor {
+            Some(supervisor) => supervisor.state().get(),
+            None => StateRegistry::global().get(),
+        }
     }
 
-    #[cfg(feature = "scaling")]
-    pub(crate) fn set_actor_stats(&mut self, actor_stats: Arc<LOTable<BastionId, u32>>) {
-        self.actor_stats = actor_stats;
+    /// Returns the connection served by the child, if its children
+    /// group was created by a [`TcpServer`].
+    ///
+    /// [`TcpServer`]: ../net/struct.TcpServer.html
+    #[cfg(not(target_os = "windows"))]
+    pub fn tcp_connection(&self) -> Option<TcpConnection> {
+        self.state.state_bag().get()
     }
 
-    #[cfg(feature = "scaling")]
-    pub(crate) fn stats(&self) -> Arc<AtomicU64> {
-        self.stats.clone()
+    /// Returns the WebSocket connection run by the child, if its
+    /// children group was created by a [`WebSocketServer`] or a
+    /// [`WebSocketClient`].
+    ///
+    /// [`WebSocketServer`]: ../websocket/struct.WebSocketServer.html
+    /// [`WebSocketClient`]: ../websocket/struct.WebSocketClient.html
+    #[cfg(all(feature = "websocket", not(target_os = "windows")))]
+    pub fn websocket(&self) -> Option<WebSocket> {
+        self.state.state_bag().get()
+    }
+
+    /// Sends a datagram to a peer, from the socket of the
+    /// [`UdpEndpoint`] delivering datagrams to the child's
+    /// children group.
+    ///
+    /// This method returns the amount of bytes that were sent if
+    /// it succeeded, or an error if no endpoint delivers datagrams
+    /// to the group or if the datagram couldn't be sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `peer` - The address of the peer (e.g. the one of a
+    ///     received [`Datagram`]).
+    /// * `data` - The data of the datagram.
+    ///
+    /// [`UdpEndpoint`]: ../net/struct.UdpEndpoint.html
+    /// [`Datagram`]: ../net/struct.Datagram.html
+    #[cfg(not(target_os = "windows"))]
+    pub async fn reply_datagram(
+        &self,
+        peer: SocketAddr,
+        data: impl AsRef<[u8]>,
+    ) -> io::Result<usize> {
+        net::send_datagram(self.parent().id(), peer, data.as_ref()).await
+    }
+
+    /// Marks the child as done, allowing [`Bastion::shutdown_gracefully`]
+    /// to stop it without waiting for its mailbox to be drained.
+    ///
+    /// Children waiting for a message with [`recv`] while their
+    /// mailbox is empty are stopped anyway, so this is only needed
+    /// by children waiting for something else.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             loop {
+    ///                 // Work that isn't driven by messages...
+    ///                 # futures_timer::Delay::new(Duration::from_millis(10)).await;
+    ///
+    ///                 if ctx.is_shutting_down() {
+    ///                     ctx.done();
+    ///                 }
+    ///             }
+    ///             # Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::shutdown_gracefully(Duration::from_secs(1));
+    /// # }
+    /// ```
+    ///
+    /// [`Bastion::shutdown_gracefully`]: ../struct.Bastion.html#method.shutdown_gracefully
+    /// [`recv`]: #method.recv
+    pub fn done(&self) {
+        debug!("BastionContext({}): Done.", self.id);
+        self.state.done.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether the system is shutting down gracefully, in
+    /// which case no new messages are accepted.
+    ///
+    /// See [`done`] for an example.
+    ///
+    /// [`done`]: #method.done
+    pub fn is_shutting_down(&self) -> bool {
+        SYSTEM.is_draining()
+    }
+
+    /// Stashes a message that was received but that can't be
+    /// handled yet (e.g. while the child is still initializing),
+    /// to receive it again after calling [`unstash_all`].
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)` if
+    /// the stash already contains as many messages as the
+    /// [`MailboxConfig::with_stash_capacity`] of the children
+    /// group allows.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to stash.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             let msg: SignedMessage = ctx.recv().await?;
+    ///             // The child isn't ready to handle it yet...
+    ///             ctx.stash(msg).expect("The stash is full.");
+    ///
+    ///             // ...
+    ///
+    ///             // Once it is, the stashed message is received
+    ///             // again before any other.
+    ///             ctx.unstash_all();
+    ///             let msg: SignedMessage = ctx.recv().await?;
+    ///             # Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`unstash_all`]: #method.unstash_all
+    /// [`MailboxConfig::with_stash_capacity`]: ../mailbox/struct.MailboxConfig.html#method.with_stash_capacity
+    pub fn stash(&self, msg: SignedMessage) -> Result<(), SignedMessage> {
+        debug!("{:?}: Stashing message: {:?}", self.current().path(), msg);
+        self.state.mailbox().stash(msg)
+    }
+
+    /// Puts all the messages stashed using [`stash`] back at the
+    /// front of the mailbox, in the order they were stashed in,
+    /// so that they are received before any other message.
+    ///
+    /// [`stash`]: #method.stash
+    pub fn unstash_all(&self) {
+        let count = self.state.mailbox().unstash_all();
+        debug!("{:?}: Unstashed {} messages.", self.current().path(), count);
+    }
+
+    /// Pushes a new behavior on the child's behaviors stack, making
+    /// it the one handling the messages given to [`handle`] (or
+    /// received by [`run_behaviors`]) until [`unbecome`] is called
+    /// or another behavior is pushed.
+    ///
+    /// # Arguments
+    ///
+    /// * `behavior` - The [`Behavior`] that becomes the active one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// fn off() -> Behavior {
+    ///     Behavior::new(|ctx, msg| {
+    ///         msg! { msg,
+    ///             msg: &'static str => {
+    ///                 if msg == "switch" {
+    ///                     ctx.become_with(on());
+    ///                 }
+    ///             };
+    ///             _: _ => ();
+    ///         }
+    ///     })
+    /// }
+    ///
+    /// fn on() -> Behavior {
+    ///     Behavior::new(|ctx, msg| {
+    ///         msg! { msg,
+    ///             msg: &'static str => {
+    ///                 if msg == "switch" {
+    ///                     // Goes back to being off...
+    ///                     ctx.unbecome();
+    ///                 }
+    ///             };
+    ///             _: _ => ();
+    ///         }
+    ///     })
+    /// }
+    ///
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| async move {
+    ///         ctx.become_with(off());
+    ///         // Runs until the behaviors stack is empty...
+    ///         ctx.run_behaviors().await
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`handle`]: #method.handle
+    /// [`run_behaviors`]: #method.run_behaviors
+    /// [`unbecome`]: #method.unbecome
+    /// [`Behavior`]: ../behavior/struct.Behavior.html
+    pub fn become_with(&self, behavior: Behavior) {
+        debug!(
+            "{:?}: Becoming: {:?} (stack size: {})",
+            self.current().path(),
+            behavior,
+            self.behaviors.len() + 1
+        );
+        self.behaviors.push(behavior);
+    }
+
+    /// Pops the active behavior from the child's behaviors stack,
+    /// making the previous one active again.
+    ///
+    /// This method returns `false` if the stack was already empty,
+    /// and `true` otherwise.
+    pub fn unbecome(&self) -> bool {
+        debug!("{:?}: Unbecoming.", self.current().path());
+        self.behaviors.pop().is_some()
+    }
+
+    /// Gives the message to the active behavior of the child's
+    /// behaviors stack.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)` if
+    /// the stack is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to handle.
+    pub fn handle(&self, msg: SignedMessage) -> Result<(), SignedMessage> {
+        match self.behaviors.active() {
+            Some(behavior) => {
+                behavior.handle(self, msg);
+                Ok(())
+            }
+            None => Err(msg),
+        }
+    }
+
+    /// Receives messages and gives them to the active behavior of
+    /// the child's behaviors stack, until the stack is empty.
+    ///
+    /// This method returns `()` once the stack is empty, or
+    /// `Err(())` if a message couldn't be received (like [`recv`]).
+    ///
+    /// [`recv`]: #method.recv
+    pub async fn run_behaviors(&self) -> Result<(), ()> {
+        while !self.behaviors.is_empty() {
+            let msg = self.recv().await?;
+            self.handle(msg).ok();
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "persistence")]
+    /// Recovers the state of a persistent child by replaying the
+    /// events persisted with the given persistence id to the
+    /// journal of the children group (see [`Children::with_journal`]).
+    ///
+    /// Calling this method at the start of the child's future makes
+    /// its state survive restarts.
+    ///
+    /// This method returns the recovered [`Persistent`] state if it
+    /// succeeded, or `Err(error)` if the group doesn't have a
+    /// journal or if the events couldn't be replayed.
+    ///
+    /// # Arguments
+    ///
+    /// * `persistence_id` - The id the events are persisted with.
+    /// * `initial` - The state before any event was applied.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// #[derive(Default)]
+    /// struct Counter(i64);
+    ///
+    /// impl PersistentChild for Counter {
+    ///     type Event = i64;
+    ///
+    ///     fn apply(&mut self, event: &i64) {
+    ///         self.0 += event;
+    ///     }
+    /// }
+    ///
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// let journal = MemoryJournal::new();
+    ///
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_journal(journal.clone())
+    ///         .with_exec(|ctx: BastionContext| async move {
+    ///             let mut counter = ctx
+    ///                 .persistent("counter", Counter::default())
+    ///                 .expect("Couldn't recover the counter.");
+    ///
+    ///             loop {
+    ///                 msg! { ctx.recv().await?,
+    ///                     value: i64 => {
+    ///                         // The counter is only changed once the
+    ///                         // event was persisted...
+    ///                         counter.persist(value).expect("Couldn't persist the event.");
+    ///                     };
+    ///                     _: _ => ();
+    ///                 }
+    ///             }
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Children::with_journal`]: ../children/struct.Children.html#method.with_journal
+    /// [`Persistent`]: ../persistence/struct.Persistent.html
+    pub fn persistent<S, P>(
+        &self,
+        persistence_id: P,
+        initial: S,
+    ) -> Result<Persistent<S>, PersistenceError>
+    where
+        S: PersistentChild,
+        P: Into<String>,
+    {
+        let journal = self.journal.clone().ok_or(PersistenceError::NoJournal)?;
+        let persistent = Persistent::recover(persistence_id, journal, initial)?;
+        Ok(self.retain_snapshots(persistent))
+    }
+
+    #[cfg(feature = "persistence")]
+    /// Recovers the state of a persistent child like [`persistent`],
+    /// but starting from the latest snapshot saved with
+    /// [`save_snapshot`]: only the events persisted after it are
+    /// replayed.
+    ///
+    /// This method returns the recovered [`Persistent`] state if it
+    /// succeeded, or `Err(error)` if the group doesn't have a
+    /// journal or if the snapshot or the events couldn't be
+    /// replayed.
+    ///
+    /// # Arguments
+    ///
+    /// * `persistence_id` - The id the events are persisted with.
+    /// * `initial` - The state before any event was applied, used
+    ///     if there isn't any snapshot yet.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use serde::{Deserialize, Serialize};
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// #[derive(Default, Serialize, Deserialize)]
+    /// struct Counter(i64);
+    ///
+    /// impl PersistentChild for Counter {
+    ///     type Event = i64;
+    ///
+    ///     fn apply(&mut self, event: &i64) {
+    ///         self.0 += event;
+    ///     }
+    /// }
+    ///
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// let journal = MemoryJournal::new();
+    ///
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_journal(journal.clone())
+    ///         .with_snapshot_retention(1)
+    ///         .with_exec(|ctx: BastionContext| async move {
+    ///             let mut counter = ctx
+    ///                 .persistent_with_snapshots("counter", Counter::default())
+    ///                 .expect("Couldn't recover the counter.");
+    ///
+    ///             loop {
+    ///                 msg! { ctx.recv().await?,
+    ///                     value: i64 => {
+    ///                         counter.persist(value).expect("Couldn't persist the event.");
+    ///                         // Snapshotting every 100 events...
+    ///                         if counter.seq_nr() % 100 == 0 {
+    ///                             ctx.save_snapshot(&counter).expect("Couldn't save the snapshot.");
+    ///                         }
+    ///                     };
+    ///                     _: _ => ();
+    ///                 }
+    ///             }
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`persistent`]: #method.persistent
+    /// [`save_snapshot`]: #method.save_snapshot
+    /// [`Persistent`]: ../persistence/struct.Persistent.html
+    pub fn persistent_with_snapshots<S, P>(
+        &self,
+        persistence_id: P,
+        initial: S,
+    ) -> Result<Persistent<S>, PersistenceError>
+    where
+        S: PersistentChild + DeserializeOwned,
+        P: Into<String>,
+    {
+        let journal = self.journal.clone().ok_or(PersistenceError::NoJournal)?;
+        let persistent = Persistent::recover_from_snapshot(persistence_id, journal, initial)?;
+        Ok(self.retain_snapshots(persistent))
+    }
+
+    #[cfg(feature = "persistence")]
+    /// Saves a snapshot of a persistent child's state to the
+    /// journal of the children group, allowing
+    /// [`persistent_with_snapshots`] to only replay the events
+    /// persisted after it. The oldest snapshots are then deleted,
+    /// depending on the snapshot retention of the group (see
+    /// [`Children::with_snapshot_retention`]).
+    ///
+    /// This method returns `()` if it succeeded, or `Err(error)`
+    /// if the snapshot couldn't be saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `persistent` - The state to save a snapshot of.
+    ///
+    /// [`persistent_with_snapshots`]: #method.persistent_with_snapshots
+    /// [`Children::with_snapshot_retention`]: ../children/struct.Children.html#method.with_snapshot_retention
+    pub fn save_snapshot<S>(&self, persistent: &Persistent<S>) -> Result<(), PersistenceError>
+    where
+        S: PersistentChild + Serialize,
+    {
+        persistent.save_snapshot()
+    }
+
+    #[cfg(feature = "persistence")]
+    fn retain_snapshots<S: PersistentChild>(&self, persistent: Persistent<S>) -> Persistent<S> {
+        match self.snapshot_retention {
+            Some(keep) => persistent.with_snapshot_retention(keep),
+            None => persistent,
+        }
+    }
+
+    /// Returns [`RefAddr`] of the current `BastionContext`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    ///
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             ctx.tell(&ctx.signature(), "Hello to myself");
+    ///
+    ///             # Bastion::stop();
+    ///             Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`RefAddr`]: /prelude/struct.Answer.html
+    pub fn signature(&self) -> RefAddr {
+        RefAddr::new(
+            self.current().path().clone(),
+            self.current().sender().clone(),
+        )
+    }
+
+    /// Sends a message to the specified [`RefAddr`]
+    ///
+    /// # Arguments
+    ///
+    /// * `to` – the [`RefAddr`] to send the message to
+    /// * `msg` – The actual message to send
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             // Wait for a message to be received...
+    ///             let smsg: SignedMessage = ctx.recv().await?;
+    ///             // Obtain address of this message sender...
+    ///             let sender_addr = smsg.signature();
+    ///             // And send something back
+    ///             ctx.tell(&sender_addr, "Ack").expect("Unable to acknowledge");
+    ///             Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`RefAddr`]: ../prelude/struct.RefAddr.html
+    pub fn tell<M: Message>(&self, to: &RefAddr, msg: M) -> Result<(), M> {
+        debug!(
+            "{:?}: Telling message: {:?} to: {:?}",
+            self.current().path(),
+            msg,
+            to.path()
+        );
+        let msg = BastionMessage::tell(msg);
+        let env = Envelope::new_with_sign(msg, self.signature());
+        if system::refuses(&env) {
+            return Err(env.into_msg().unwrap());
+        }
+
+        // FIXME: panics?
+        to.sender()
+            .unbounded_send(env)
+            .map_err(|err| err.into_inner().into_msg().unwrap())
+    }
+
+    /// Sends a message to the specified [`RefAddr`], along with the
+    /// given [`Headers`], which its recipient can read using
+    /// [`SignedMessage::headers`].
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)`
+    /// otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `to` - The address of the recipient of the message.
+    /// * `msg` - The message to send.
+    /// * `headers` - The headers to send along with the message.
+    ///
+    /// [`RefAddr`]: ../prelude/struct.RefAddr.html
+    /// [`Headers`]: ../envelope/struct.Headers.html
+    /// [`SignedMessage::headers`]: ../envelope/struct.SignedMessage.html#method.headers
+    pub fn tell_with_headers<M: Message>(
+        &self,
+        to: &RefAddr,
+        msg: M,
+        headers: Headers,
+    ) -> Result<(), M> {
+        debug!(
+            "{:?}: Telling message with headers {:?}: {:?} to: {:?}",
+            self.current().path(),
+            headers,
+            msg,
+            to.path()
+        );
+        let msg = BastionMessage::tell(msg);
+        let env = Envelope::new_with_sign(msg, self.signature()).with_headers(headers);
+        if system::refuses(&env) {
+            return Err(env.into_msg().unwrap());
+        }
+
+        // FIXME: panics?
+        to.sender()
+            .unbounded_send(env)
+            .map_err(|err| err.into_inner().into_msg().unwrap())
+    }
+
+    /// Forwards a received message to the given child, keeping its
+    /// original signature so that the child's answers (using
+    /// `answer!` if it was "asked", or [`tell`] to its signature)
+    /// are sent to the message's original sender instead of to
+    /// this child.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)`
+    /// otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The received message to forward.
+    /// * `to` - The child to forward the message to.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// # let workers = Bastion::children(|children| children).unwrap();
+    /// let worker = workers.elems()[0].clone();
+    /// Bastion::children(|children| {
+    ///     children.with_exec(move |ctx: BastionContext| {
+    ///         let worker = worker.clone();
+    ///         async move {
+    ///             loop {
+    ///                 let msg: SignedMessage = ctx.recv().await?;
+    ///                 // The worker will answer the original sender...
+    ///                 ctx.forward(msg, &worker).expect("Couldn't forward the message.");
+    ///             }
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`tell`]: #method.tell
+    pub fn forward(&self, msg: SignedMessage, to: &ChildRef) -> Result<(), SignedMessage> {
+        debug!(
+            "{:?}: Forwarding message: {:?} to: {:?}",
+            self.current().path(),
+            msg,
+            to.path()
+        );
+        let env = Envelope::from_signed(msg);
+        // FIXME: panics?
+        to.send(env).map_err(|env| env.into_signed().unwrap())
+    }
+
+    /// Sends a message to the specified [`RefAddr`], waiting for
+    /// its mailbox to have room for it if it is bounded and uses
+    /// [`OverflowStrategy::Backpressure`].
+    ///
+    /// For other mailboxes, this behaves like [`tell`].
+    ///
+    /// # Arguments
+    ///
+    /// * `to` – the [`RefAddr`] to send the message to
+    /// * `msg` – The actual message to send
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             let smsg: SignedMessage = ctx.recv().await?;
+    ///             let sender_addr = smsg.signature();
+    ///             // Wait for the sender to have room for the answer...
+    ///             ctx.tell_async(&sender_addr, "Ack").await.expect("Unable to acknowledge");
+    ///             Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`RefAddr`]: ../prelude/struct.RefAddr.html
+    /// [`OverflowStrategy::Backpressure`]: ../mailbox/enum.OverflowStrategy.html#variant.Backpressure
+    /// [`tell`]: #method.tell
+    pub async fn tell_async<M: Message>(&self, to: &RefAddr, msg: M) -> Result<(), M> {
+        debug!(
+            "{:?}: Telling message (async): {:?} to: {:?}",
+            self.current().path(),
+            msg,
+            to.path()
+        );
+        let msg = BastionMessage::tell(msg);
+        let env = Envelope::new_with_sign(msg, self.signature());
+        if system::refuses(&env) {
+            return Err(env.into_msg().unwrap());
+        }
+
+        // FIXME: panics?
+        to.sender()
+            .send(env)
+            .await
+            .map_err(|err| err.into_inner().into_msg().unwrap())
+    }
+
+    /// Schedules a message to be sent to the current child once,
+    /// after the given delay.
+    ///
+    /// The timer is handled by the system timer wheel, so no task
+    /// is spawned for it. Its resolution is of 10 milliseconds.
+    ///
+    /// This method returns a [`TimerHandle`] that can be used to
+    /// cancel the timer before the message is sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `delay` - The time to wait before sending the message.
+    /// * `msg` - The message to send.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             let timer: TimerHandle = ctx.schedule_once(Duration::from_millis(100), "timeout");
+    ///
+    ///             msg! { ctx.recv().await?,
+    ///                 msg: &'static str => {
+    ///                     assert_eq!(msg, "timeout");
+    ///                 };
+    ///                 // A message was received before the timer fired...
+    ///                 _: _ => timer.cancel();
+    ///             }
+    ///
+    ///             Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`TimerHandle`]: ../prelude/struct.TimerHandle.html
+    pub fn schedule_once<M: Message>(&self, delay: Duration, msg: M) -> TimerHandle {
+        debug!(
+            "{:?}: Scheduling message: {:?} in {:?}",
+            self.current().path(),
+            msg,
+            delay
+        );
+        let sign = self.signature();
+        executor::schedule_once(delay, move || {
+            let msg = BastionMessage::tell(msg);
+            let env = Envelope::new_with_sign(msg, sign.clone());
+            // FIXME: handle errors
+            sign.sender().unbounded_send(env).ok();
+        })
+    }
+
+    /// Schedules a message to be sent to the current child every
+    /// `interval`, until the returned [`TimerHandle`] gets cancelled
+    /// or the child is stopped.
+    ///
+    /// The timer is handled by the system timer wheel, so no task
+    /// is spawned for it. Its resolution is of 10 milliseconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The time to wait between each message.
+    /// * `msg` - The message to send, which is cloned each time.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             let timer = ctx.schedule_interval(Duration::from_secs(1), "tick");
+    ///
+    ///             for _ in 0..10 {
+    ///                 msg! { ctx.recv().await?,
+    ///                     msg: &'static str => {
+    ///                         // Handle the tick...
+    ///                     };
+    ///                     _: _ => ();
+    ///                 }
+    ///             }
+    ///
+    ///             timer.cancel();
+    ///             Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`TimerHandle`]: ../prelude/struct.TimerHandle.html
+    pub fn schedule_interval<M: Message + Clone>(&self, interval: Duration, msg: M) -> TimerHandle {
+        debug!(
+            "{:?}: Scheduling message: {:?} every {:?}",
+            self.current().path(),
+            msg,
+            interval
+        );
+        let sign = self.signature();
+        executor::schedule_interval(interval, move || {
+            let msg = BastionMessage::tell(msg.clone());
+            let env = Envelope::new_with_sign(msg, sign.clone());
+            // The timer is stopped once the child can't receive
+            // messages anymore.
+            match sign.sender().unbounded_send(env) {
+                Ok(()) => true,
+                Err(err) => err.is_full(),
+            }
+        })
+    }
+
+    /// Watches the given child, so that a [`Terminated`] message
+    /// is sent to the current child once the watched child stops or
+    /// crashes.
+    ///
+    /// If the watched child is already terminated, the message is
+    /// sent right away with [`StopReason::Unreachable`].
+    ///
+    /// # Arguments
+    ///
+    /// * `child` - The child to watch.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// # let peers = Bastion::children(|children| children).unwrap();
+    /// Bastion::children(|children| {
+    ///     children.with_exec(move |ctx: BastionContext| {
+    ///         let peer: ChildRef = peers.elems()[0].clone();
+    ///         async move {
+    ///             ctx.watch(&peer);
+    ///
+    ///             msg! { ctx.recv().await?,
+    ///                 msg: Terminated => {
+    ///                     // The peer terminated...
+    ///                 };
+    ///                 _: _ => ();
+    ///             }
+    ///
+    ///             Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Terminated`]: ../watch/struct.Terminated.html
+    /// [`StopReason::Unreachable`]: ../watch/enum.StopReason.html#variant.Unreachable
+    pub fn watch(&self, child: &ChildRef) {
+        debug!("{:?}: Watching: {:?}", self.current().path(), child.path());
+        let msg = BastionMessage::watch(self.current().clone());
+        let env = Envelope::new_with_sign(msg, self.signature());
+        if child.send(env).is_err() {
+            let msg =
+                BastionMessage::tell(Terminated(child.path().clone(), StopReason::Unreachable));
+            let env = Envelope::new(msg, child.path().clone(), child.sender().clone());
+            self.current().send(env).ok();
+        }
+    }
+
+    /// Stops watching the given child, which was watched using
+    /// [`watch`].
+    ///
+    /// # Arguments
+    ///
+    /// * `child` - The child to stop watching.
+    ///
+    /// [`watch`]: #method.watch
+    pub fn unwatch(&self, child: &ChildRef) {
+        debug!(
+            "{:?}: Unwatching: {:?}",
+            self.current().path(),
+            child.path()
+        );
+        let msg = BastionMessage::unwatch(self.current().id().clone());
+        let env = Envelope::new_with_sign(msg, self.signature());
+        child.send(env).ok();
+    }
+
+    /// Runs `f` with a [`Nursery`] allowing to spawn concurrent
+    /// sub-tasks which can't outlive the scope.
+    ///
+    /// Once the future returned by `f` completes, this method waits
+    /// for all the sub-tasks spawned in the scope to complete too
+    /// before returning its output. If the child is stopped or
+    /// restarted before, all of them are cancelled.
+    ///
+    /// # Argument
+    ///
+    /// * `f` - The closure given the [`Nursery`] of the scope and
+    ///     returning its future.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| async move {
+    ///         let sum = ctx
+    ///             .scope(|nursery| async move {
+    ///                 let left = nursery.spawn(async { 1 + 2 });
+    ///                 let right = nursery.spawn(async { 3 + 4 });
+    ///
+    ///                 left.await.unwrap_or(0) + right.await.unwrap_or(0)
+    ///             })
+    ///             .await;
+    ///
+    ///         assert_eq!(sum, 10);
+    ///         Ok(())
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Nursery`]: ../scope/struct.Nursery.html
+    pub async fn scope<F, Fut, T>(&self, f: F) -> T
+    where
+        F: FnOnce(Nursery) -> Fut,
+        Fut: Future<Output = T>,
+    {
+        trace!("{:?}: Entering a scope.", self.current().path());
+        scope::scope(f).await
+    }
+
+    /// Returns the [`CancellationToken`] of the child, which is
+    /// cancelled as soon as the child is asked to stop or is about
+    /// to be restarted (each incarnation of the child getting a new
+    /// one).
+    ///
+    /// Long-running futures can be raced against it (see
+    /// [`CancellationToken::run_until_cancelled`]) to be aborted
+    /// cleanly, even when they run in tasks spawned by the child.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| async move {
+    ///         let token = ctx.cancellation_token();
+    ///         spawn! {
+    ///             async move {
+    ///                 let query = futures_timer::Delay::new(Duration::from_secs(60));
+    ///                 match token.run_until_cancelled(query).await {
+    ///                     Some(()) => { /* The query completed... */ }
+    ///                     None => { /* The child is stopping... */ }
+    ///                 }
+    ///             }
+    ///         };
+    ///
+    ///         Ok(())
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`CancellationToken`]: ../cancellation/struct.CancellationToken.html
+    /// [`CancellationToken::run_until_cancelled`]: ../cancellation/struct.CancellationToken.html#method.run_until_cancelled
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Acknowledges a message sent with [`ChildRef::tell_reliable`],
+    /// so that it stops being redelivered.
+    ///
+    /// This method returns `true` if the message was waiting to be
+    /// acknowledged, or `false` if it was already acknowledged or
+    /// given up on.
+    ///
+    /// # Arguments
+    ///
+    /// * `delivery_id` - The id of the [`Delivery`] the message was
+    ///     received in.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| async move {
+    ///         msg! { ctx.recv().await?,
+    ///             delivery: Delivery<u64> => {
+    ///                 // Handle the message, then...
+    ///                 ctx.ack(delivery.id());
+    ///             };
+    ///             _: _ => ();
+    ///         }
+    ///
+    ///         Ok(())
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`ChildRef::tell_reliable`]: ../child_ref/struct.ChildRef.html#method.tell_reliable
+    /// [`Delivery`]: ../delivery/struct.Delivery.html
+    pub fn ack(&self, delivery_id: DeliveryId) -> bool {
+        debug!(
+            "{:?}: Acknowledging delivery: {}",
+            self.current().path(),
+            delivery_id
+        );
+        delivery::ack(delivery_id)
+    }
+
+    /// Sends a message from behalf of current context to the addr,
+    /// allowing to addr owner answer.
+    ///
+    /// This method returns [`Answer`] if it succeeded, or `Err(msg)`
+    /// otherwise.
+    ///
+    /// # Argument
+    ///
+    /// * `msg` - The message to send.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    ///     # Bastion::init();
+    /// // The message that will be "asked"...
+    /// const ASK_MSG: &'static str = "A message containing data (ask).";
+    /// // The message the will be "answered"...
+    /// const ANSWER_MSG: &'static str = "A message containing data (answer).";
+    ///
+    ///     # let children_ref =
+    /// // Create a new child...
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| {
+    ///         async move {
+    ///             // ...which will receive the message asked...
+    ///             msg! { ctx.recv().await?,
+    ///                 msg: &'static str =!> {
+    ///                     assert_eq!(msg, ASK_MSG);
+    ///                     // Handle the message...
+    ///
+    ///                     // ...and eventually answer to it...
+    ///                     answer!(ctx, ANSWER_MSG);
+    ///                 };
+    ///                 // This won't happen because this example
+    ///                 // only "asks" a `&'static str`...
+    ///                 _: _ => ();
+    ///             }
+    ///
+    ///             Ok(())
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    ///     # Bastion::children(|children| {
+    ///         # children.with_exec(move |ctx: BastionContext| {
+    ///             # let child_ref = children_ref.elems()[0].clone();
+    ///             # async move {
+    /// // Later, the message is "asked" to the child...
+    /// let answer: Answer = ctx.ask(&child_ref.addr(), ASK_MSG).expect("Couldn't send the message.");
+    ///
+    /// // ...and the child's answer is received...
+    /// msg! { answer.await.expect("Couldn't receive the answer."),
+    ///     msg: &'static str => {
+    ///         assert_eq!(msg, ANSWER_MSG);
+    ///         // Handle the answer...
+    ///     };
+    ///     // This won't happen because this example
+    ///     // only answers a `&'static str`...
+    ///     _: _ => ();
+    /// }
+    ///                 #
+    ///                 # Ok(())
+    ///             # }
+    ///         # })
+    ///     # }).unwrap();
+    ///     #
+    ///     # Bastion::start();
+    ///     # Bastion::stop();
+    ///     # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Answer`]: /message/struct.Answer.html
+    pub fn ask<M: Message>(&self, to: &RefAddr, msg: M) -> Result<Answer, M> {
+        debug!(
+            "{:?}: Asking message: {:?} to: {:?}",
+            self.current().path(),
+            msg,
+            to
+        );
+        let (msg, answer) = BastionMessage::ask(msg);
+        let env = Envelope::new_with_sign(msg, self.signature());
+        if system::refuses(&env) {
+            return Err(env.into_msg().unwrap());
+        }
+
+        // An ask that would deadlock isn't sent.
+        let answer = answer.track(to.path());
+        if answer.deadlock().is_some() {
+            return Ok(answer);
+        }
+
+        // FIXME: panics?
+        to.sender()
+            .unbounded_send(env)
+            .map_err(|err| err.into_inner().into_msg().unwrap())?;
+
+        Ok(answer)
+    }
+
+    /// Sends a message to the given [`RefAddr`], allowing its
+    /// recipient to answer it with many chunks using the
+    /// `answer_stream!` macro generated by [`msg!`].
+    ///
+    /// This method returns an [`AnswerStream`] yielding the chunks
+    /// if it succeeded, or `Err(msg)` otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `to` - The address of the recipient of the message.
+    /// * `msg` - The message to send.
+    ///
+    /// [`RefAddr`]: ../prelude/struct.RefAddr.html
+    /// [`msg!`]: ../macro.msg.html
+    /// [`AnswerStream`]: ../message/struct.AnswerStream.html
+    pub fn ask_stream<M: Message>(&self, to: &RefAddr, msg: M) -> Result<AnswerStream, M> {
+        debug!(
+            "{:?}: Asking message as a stream: {:?} to: {:?}",
+            self.current().path(),
+            msg,
+            to
+        );
+        let (msg, answer) = BastionMessage::ask_stream(msg);
+        let env = Envelope::new_with_sign(msg, self.signature());
+        if system::refuses(&env) {
+            return Err(env.into_msg().unwrap());
+        }
+
+        // FIXME: panics?
+        to.sender()
+            .unbounded_send(env)
+            .map_err(|err| err.into_inner().into_msg().unwrap())?;
+
+        Ok(answer)
+    }
+
+    /// Sends the notification to each declared dispatcher of the actor.
+    ///
+    /// # Argument
+    ///
+    /// * `dispatchers` - Vector of dispatcher names to which need to
+    /// deliver a notification.
+    /// * `notification_type` - The type of the notification to send.
+    ///
+    pub fn notify(&self, dispatchers: &[DispatcherType], notification_type: NotificationType) {
+        let global_dispatcher = SYSTEM.dispatcher();
+        let from_actor = self.current();
+        global_dispatcher.notify(from_actor, dispatchers, notification_type);
+    }
+
+    /// Sends the broadcasted message to the target group(s).
+    ///
+    /// # Argument
+    ///
+    /// * `target` - Defines the message receivers in according with
+    /// the [`BroadcastTarget`] value.
+    /// * `message` - The broadcasted message.
+    ///
+    /// [`BroadcastTarget`]: ../dispatcher/enum.DispatcherType.html
+    pub fn broadcast_message<M: Message>(&self, target: BroadcastTarget, message: M) {
+        let msg = Arc::new(SignedMessage::new(
+            Msg::broadcast(message),
+            self.signature(),
+        ));
+
+        let global_dispatcher = SYSTEM.dispatcher();
+        global_dispatcher.broadcast_message(target, &msg);
+    }
+
+    // Waits for the child to be woken up by a new message, or
+    // until its mailbox should be looked into again if its
+    // messages aren't all pushed to it.
+    async fn wait_message(&self) {
+        #[cfg(feature = "redis-mailbox")]
+        if let Some(interval) = self.state.mailbox().poll_interval() {
+            return Delay::new(interval).await;
+        }
+
+        pending!()
+    }
+
+    fn pop_message(&self) -> Option<SignedMessage> {
+        // The messages of a paused child are kept in its mailbox
+        // until it is resumed.
+        if self.state.is_paused() {
+            return None;
+        }
+
+        let msg = self.state.pop_limited_message();
+        self.state.record_handled(msg.is_some());
+        // The previous message was handled once the child pops a
+        // new one or waits for it.
+        if let Some((message_type, elapsed)) = self.state.handle_msg(msg.as_ref().map(|m| &m.msg)) {
+            let path = self.current().path().clone();
+            warn!(
+                "{:?}: Handling a message of type {} took {:?}.",
+                path, message_type, elapsed
+            );
+            events::emit(SupervisionEvent::SlowHandler {
+                path,
+                message_type,
+                elapsed,
+            });
+        }
+
+        #[cfg(feature = "metrics")]
+        self.record_metrics(msg.is_some());
+
+        #[cfg(feature = "scaling")]
+        self.record_latency(msg.is_some());
+
+        #[cfg(feature = "otel")]
+        self.update_span(msg.as_ref());
+
+        msg
+    }
+
+    // The span of the popped message is the one its processing
+    // is polled in (starting right away, within the current poll),
+    // while a child waiting for a message isn't in any span.
+    #[cfg(feature = "otel")]
+    fn update_span(&self, msg: Option<&SignedMessage>) {
+        let span = match msg {
+            Some(msg) => tracing::info_span!(
+                parent: msg.span(),
+                "bastion::message",
+                path = %self.current().path()
+            ),
+            None => Span::none(),
+        };
+
+        self.state.set_span(span);
+    }
+
+    #[cfg(feature = "metrics")]
+    fn record_metrics(&self, received: bool) {
+        let path = self.current().path();
+        let mut last_recv = self.state.last_recv.lock().unwrap();
+        // The previous message was processed once the
+        // child waits for a new one.
+        if let Some(last_recv) = last_recv.take() {
+            metrics::record_processing(path, last_recv.elapsed());
+        }
+
+        if received {
+            *last_recv = Some(Instant::now());
+            metrics::record_popped(path, self.state.mailbox().len());
+        }
+    }
+
+    #[cfg(feature = "scaling")]
+    fn record_latency(&self, received: bool) {
+        let mut processing_since = self.state.processing_since.lock().unwrap();
+        // The previous message was processed once the
+        // child waits for a new one.
+        if let Some(processing_since) = processing_since.take() {
+            let latency = processing_since.elapsed().as_micros() as u64;
+            self.state
+                .actor_latencies
+                .insert(self.current().id().clone(), latency)
+                .ok();
+        }
+
+        if received {
+            *processing_since = Some(Instant::now());
+        }
+    }
+}
+
+impl ContextState {
+    pub(crate) fn new() -> Self {
+        ContextState {
+            mailbox: Arc::new(Mailbox::new(MailboxConfig::default())),
+            #[cfg(feature = "scaling")]
+            stats: Arc::new(AtomicU64::new(0)),
+            #[cfg(feature = "scaling")]
+            actor_stats: Arc::new(LOTable::new()),
+            #[cfg(feature = "scaling")]
+            actor_latencies: Arc::new(LOTable::new()),
+            #[cfg(feature = "scaling")]
+            processing_since: Mutex::new(None),
+            #[cfg(feature = "metrics")]
+            last_recv: Mutex::new(None),
+            #[cfg(feature = "otel")]
+            span: Mutex::new((Span::none(), None)),
+            waiting: AtomicBool::new(false),
+            done: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            state_bag: StateBag::new(),
+            local_storage: LocalStorage::new(),
+            restarts: AtomicUsize::new(0),
+            failure: Mutex::new(None),
+            stop_reason: Mutex::new(None),
+            circuit_breaker: None,
+            handling: AtomicBool::new(false),
+            rate_limiter: None,
+            heartbeat: Mutex::new(Instant::now()),
+            slow_handler_threshold: None,
+            handled_msg: Mutex::new(None),
+            restart_strategy: None,
+        }
+    }
+
+    #[cfg(feature = "scaling")]
+    pub(crate) fn set_stats(&mut self, stats: Arc<AtomicU64>) {
+        self.stats = stats;
+    }
+
+    #[cfg(feature = "scaling")]
+    pub(crate) fn set_actor_stats(&mut self, actor_stats: Arc<LOTable<BastionId, u32>>) {
+        self.actor_stats = actor_stats;
+    }
+
+    #[cfg(feature = "scaling")]
+    pub(crate) fn set_actor_latencies(&mut self, actor_latencies: Arc<LOTable<BastionId, u64>>) {
+        self.actor_latencies = actor_latencies;
+    }
+
+    #[cfg(feature = "scaling")]
+    pub(crate) fn stats(&self) -> Arc<AtomicU64> {
+        self.stats.clone()
     }
 
     #[cfg(feature = "scaling")]
@@ -813,17 +2596,235 @@ impl ContextState {
         self.actor_stats.clone()
     }
 
-    pub(crate) fn push_message(&self, msg: Msg, sign: RefAddr) {
-        self.messages.push(SignedMessage::new(msg, sign))
+    #[cfg(feature = "scaling")]
+    pub(crate) fn actor_latencies(&self) -> Arc<LOTable<BastionId, u64>> {
+        self.actor_latencies.clone()
+    }
+
+    pub(crate) fn set_rate_limiter(&mut self, rate_limiter: Option<RateLimiter>) {
+        self.rate_limiter = rate_limiter;
+    }
+
+    pub(crate) fn set_slow_handler_threshold(&mut self, threshold: Option<Duration>) {
+        self.slow_handler_threshold = threshold;
+    }
+
+    pub(crate) fn set_restart_strategy(&mut self, restart_strategy: Option<RestartStrategy>) {
+        self.restart_strategy = restart_strategy;
+    }
+
+    pub(crate) fn restart_strategy(&self) -> Option<&RestartStrategy> {
+        self.restart_strategy.as_ref()
+    }
+
+    // The child started handling a new message (or is waiting
+    // for one), returning the type of the previous one and how
+    // long it took if it was slow and wasn't reported yet.
+    // FIXME: panics
+    pub(crate) fn handle_msg(&self, msg: Option<&Msg>) -> Option<(&'static str, Duration)> {
+        let threshold = self.slow_handler_threshold?;
+        let mut handled_msg = self.handled_msg.lock().unwrap();
+        let slow = match handled_msg.take() {
+            Some((type_name, since, false)) if since.elapsed() > threshold => {
+                Some((type_name, since.elapsed()))
+            }
+            _ => None,
+        };
+
+        *handled_msg = msg.map(|msg| (msg.type_name(), Instant::now(), false));
+        slow
+    }
+
+    // Returns the type of the message being handled and since when
+    // if it is slow and wasn't reported yet, marking it reported.
+    // FIXME: panics
+    pub(crate) fn check_slow_handler(&self) -> Option<(&'static str, Duration)> {
+        let threshold = self.slow_handler_threshold?;
+        let mut handled_msg = self.handled_msg.lock().unwrap();
+        let (type_name, since, reported) = handled_msg.as_mut()?;
+        let elapsed = since.elapsed();
+        if *reported || elapsed <= threshold {
+            return None;
+        }
+
+        *reported = true;
+        Some((*type_name, elapsed))
+    }
+
+    // Returns how long the child has to wait before popping its
+    // next message, if its group's rate limit was reached.
+    pub(crate) fn throttled_for(&self) -> Option<Duration> {
+        self.rate_limiter.as_ref()?.throttled_for()
+    }
+
+    pub(crate) fn set_circuit_breaker(&mut self, circuit_breaker: Option<CircuitBreaker>) {
+        self.circuit_breaker = circuit_breaker;
+    }
+
+    // A child popping (or waiting for) a message handled the
+    // one it popped before successfully.
+    pub(crate) fn record_handled(&self, popped: bool) {
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            if self.handling.swap(popped, Ordering::SeqCst) {
+                circuit_breaker.record_success();
+            }
+        }
+    }
+
+    // The message the child was handling (if any) wasn't handled
+    // successfully.
+    pub(crate) fn record_failed(&self) {
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            self.handling.store(false, Ordering::SeqCst);
+            circuit_breaker.record_failure();
+        }
+    }
+
+    pub(crate) fn set_mailbox(&mut self, mailbox: Arc<Mailbox>) {
+        self.mailbox = mailbox;
+    }
+
+    pub(crate) fn mailbox(&self) -> &Arc<Mailbox> {
+        &self.mailbox
+    }
+
+    pub(crate) fn push_message(&self, msg: SignedMessage, priority: Priority) {
+        self.mailbox.push(msg, priority)
+    }
+
+    /// Enters the span of the message being processed on the
+    /// current thread, until the returned guard is dropped.
+    #[cfg(feature = "otel")]
+    pub(crate) fn enter_span(&self) -> EnteredSpan<'_> {
+        // FIXME: panics
+        let mut span = self.span.lock().unwrap();
+        span.0.with_subscriber(|(id, dispatch)| dispatch.enter(id));
+        span.1 = Some(thread::current().id());
+
+        EnteredSpan(self)
+    }
+
+    /// Sets the span of the message being processed, switching to
+    /// it right away if the previous one is entered on the current
+    /// thread.
+    #[cfg(feature = "otel")]
+    pub(crate) fn set_span(&self, new: Span) {
+        // FIXME: panics
+        let mut span = self.span.lock().unwrap();
+        if span.1 == Some(thread::current().id()) {
+            span.0.with_subscriber(|(id, dispatch)| dispatch.exit(id));
+            new.with_subscriber(|(id, dispatch)| dispatch.enter(id));
+        }
+
+        span.0 = new;
     }
 
     pub(crate) fn pop_message(&self) -> Option<SignedMessage> {
-        self.messages.pop()
+        self.mailbox.pop()
+    }
+
+    // Pops a message if the rate limit of the child's group wasn't
+    // reached.
+    pub(crate) fn pop_limited_message(&self) -> Option<SignedMessage> {
+        match &self.rate_limiter {
+            Some(rate_limiter) => rate_limiter.acquire(|| self.mailbox.pop()),
+            None => self.mailbox.pop(),
+        }
+    }
+
+    pub(crate) fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn state_bag(&self) -> &StateBag {
+        &self.state_bag
+    }
+
+    pub(crate) fn local_storage(&self) -> &LocalStorage {
+        &self.local_storage
+    }
+
+    pub(crate) fn restarts(&self) -> usize {
+        self.restarts.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_failure(&self, failure: ChildFailure) {
+        *self.failure.lock().unwrap() = Some(failure);
+    }
+
+    /// Returns the failure that will cause the child's restart, if
+    /// it faulted.
+    pub(crate) fn failure(&self) -> Option<ChildFailure> {
+        self.failure.lock().unwrap().clone()
+    }
+
+    /// Counts a restart, returning the failure that caused it as
+    /// seen once the child was restarted.
+    pub(crate) fn restarted(&self) -> Option<ChildFailure> {
+        let failure = self.failure.lock().unwrap().take()?;
+        self.restarts.fetch_add(1, Ordering::SeqCst);
+        Some(failure.restarted())
+    }
+
+    // FIXME: panics
+    pub(crate) fn record_heartbeat(&self) {
+        *self.heartbeat.lock().unwrap() = Instant::now();
+    }
+
+    // FIXME: panics
+    pub(crate) fn last_heartbeat(&self) -> Instant {
+        *self.heartbeat.lock().unwrap()
+    }
+
+    // FIXME: panics
+    pub(crate) fn set_stop_reason(&self, reason: StopReason) {
+        *self.stop_reason.lock().unwrap() = Some(reason);
+    }
+
+    // FIXME: panics
+    pub(crate) fn take_stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason.lock().unwrap().take()
     }
 
     #[cfg(feature = "scaling")]
     pub(crate) fn mailbox_size(&self) -> u32 {
-        self.messages.len() as _
+        self.mailbox.len() as _
+    }
+
+    /// Returns whether the child can be stopped by a graceful
+    /// shutdown, because it called `BastionContext::done` or is
+    /// waiting for a message that isn't coming.
+    pub(crate) fn is_drained(&self) -> bool {
+        self.done.load(Ordering::SeqCst)
+            || (self.waiting.load(Ordering::SeqCst) && self.mailbox.len() == 0)
+    }
+}
+
+impl<'a> Waiting<'a> {
+    fn new(state: &'a ContextState) -> Self {
+        state.waiting.store(true, Ordering::SeqCst);
+        Waiting(state)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.waiting.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "otel")]
+impl Drop for EnteredSpan<'_> {
+    fn drop(&mut self) {
+        // FIXME: panics
+        let mut span = self.0.span.lock().unwrap();
+        if span.1.take().is_some() {
+            span.0.with_subscriber(|(id, dispatch)| dispatch.exit(id));
+        }
     }
 }
 
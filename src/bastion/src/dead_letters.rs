@@ -0,0 +1,331 @@
+//!
+//! Dead letters are the messages that couldn't be delivered
+//! to their recipient, either because it was stopped, because
+//! it never existed or because its mailbox was full.
+//!
+//! Instead of silently dropping them, Bastion forwards them
+//! to a system-level dead letters actor, which keeps count of
+//! them per target path (for the last `1024` paths dead letters
+//! were sent to) and forwards them to all the streams returned
+//! by [`DeadLetters::subscribe`].
+//!
+//! [`DeadLetters::subscribe`]: struct.DeadLetters.html#method.subscribe
+use crate::envelope::{Envelope, RefAddr, SignedMessage};
+use crate::message::{BastionMessage, Msg};
+use crate::path::BastionPath;
+use crate::system::SYSTEM;
+use futures::channel::mpsc::{self, Receiver, Sender};
+use futures::prelude::*;
+use fxhash::FxHashMap;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tracing::trace;
+
+/// The maximum number of paths the dead letters are counted for.
+/// Once reached, the counter of the path that received a dead
+/// letter the longest time ago is forgotten.
+const MAX_COUNTERS: usize = 1024;
+
+/// The number of dead letters a stream returned by
+/// [`DeadLetters::subscribe`] can buffer. The dead letters
+/// recorded while it is full are dropped for this stream.
+///
+/// [`DeadLetters::subscribe`]: struct.DeadLetters.html#method.subscribe
+const SUBSCRIBER_CAPACITY: usize = 1024;
+
+#[derive(Debug)]
+/// A message that couldn't be delivered to its recipient.
+pub struct DeadLetter {
+    target: Arc<BastionPath>,
+    message: SignedMessage,
+}
+
+#[derive(Debug, Clone)]
+/// A handle to the system-level dead letters actor, as returned
+/// by [`Bastion::dead_letters`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// Bastion::init();
+///
+/// let dead_letters: DeadLetters = Bastion::dead_letters();
+/// let _letters = dead_letters.subscribe();
+///
+/// Bastion::children(|children| {
+///     children.with_exec(move |ctx: BastionContext| {
+///         async move {
+///             // ...
+///             # Ok(())
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+///
+/// Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+///
+/// println!("{} dead letters so far.", dead_letters.total());
+/// # }
+/// ```
+///
+/// [`Bastion::dead_letters`]: ../struct.Bastion.html#method.dead_letters
+pub struct DeadLetters {
+    state: Arc<DeadLettersState>,
+}
+
+#[derive(Debug)]
+/// A [`Stream`] of the dead letters received by the dead
+/// letters actor after it was created with
+/// [`DeadLetters::subscribe`].
+///
+/// [`Stream`]: https://docs.rs/futures/0.3/futures/stream/trait.Stream.html
+/// [`DeadLetters::subscribe`]: struct.DeadLetters.html#method.subscribe
+pub struct DeadLettersStream {
+    recver: Receiver<Arc<DeadLetter>>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct DeadLettersState {
+    // The amount of dead letters per target path, along with the
+    // number of the last one (used to forget the counter not
+    // updated for the longest time once there are `MAX_COUNTERS`).
+    counters: Mutex<FxHashMap<String, (u64, u64)>>,
+    total: AtomicU64,
+    subscribers: Mutex<Vec<Sender<Arc<DeadLetter>>>>,
+}
+
+impl DeadLetter {
+    pub(crate) fn new(target: Arc<BastionPath>, message: SignedMessage) -> Self {
+        DeadLetter { target, message }
+    }
+
+    /// Returns the path of the element the message was sent to,
+    /// or of its parent if the element couldn't be found.
+    pub fn target(&self) -> &Arc<BastionPath> {
+        &self.target
+    }
+
+    /// Returns the message that couldn't be delivered, along
+    /// with its sender's signature.
+    pub fn message(&self) -> &SignedMessage {
+        &self.message
+    }
+
+    /// Returns the message that couldn't be delivered, along
+    /// with its sender's signature.
+    pub fn into_message(self) -> SignedMessage {
+        self.message
+    }
+}
+
+impl DeadLetters {
+    pub(crate) fn new(state: Arc<DeadLettersState>) -> Self {
+        DeadLetters { state }
+    }
+
+    /// Returns a new [`DeadLettersStream`] that will yield every
+    /// dead letter received after this call.
+    ///
+    /// Dead letters are shared between all the streams, which is
+    /// why they are wrapped in an `Arc`. Each stream buffers up to
+    /// `1024` dead letters, the ones received once it is full
+    /// being dropped for it.
+    ///
+    /// [`DeadLettersStream`]: struct.DeadLettersStream.html
+    pub fn subscribe(&self) -> DeadLettersStream {
+        let (sender, recver) = mpsc::channel(SUBSCRIBER_CAPACITY);
+        self.state.subscribers.lock().unwrap().push(sender);
+
+        DeadLettersStream { recver }
+    }
+
+    /// Returns the amount of dead letters that were sent to
+    /// the element with the given path, which is `0` if its
+    /// counter was forgotten.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the element.
+    pub fn count(&self, path: &BastionPath) -> u64 {
+        let counters = self.state.counters.lock().unwrap();
+        counters
+            .get(&path.to_string())
+            .map_or(0, |(count, _)| *count)
+    }
+
+    /// Returns the amount of dead letters per target path,
+    /// formatted as strings, for the last `1024` paths dead
+    /// letters were sent to.
+    pub fn counters(&self) -> HashMap<String, u64> {
+        let counters = self.state.counters.lock().unwrap();
+        counters
+            .iter()
+            .map(|(path, (count, _))| (path.clone(), *count))
+            .collect()
+    }
+
+    /// Returns the total amount of dead letters.
+    pub fn total(&self) -> u64 {
+        self.state.total.load(Ordering::SeqCst)
+    }
+}
+
+impl Stream for DeadLettersStream {
+    type Item = Arc<DeadLetter>;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().recver).poll_next(ctx)
+    }
+}
+
+impl DeadLettersState {
+    pub(crate) fn record(&self, letter: Arc<DeadLetter>) {
+        trace!("DeadLetters: Recording dead letter: {:?}", letter);
+        let number = self.total.fetch_add(1, Ordering::SeqCst) + 1;
+        let target = letter.target.to_string();
+        let mut counters = self.counters.lock().unwrap();
+        if counters.len() >= MAX_COUNTERS && !counters.contains_key(&target) {
+            let oldest = counters
+                .iter()
+                .min_by_key(|(_, (_, last))| *last)
+                .map(|(path, _)| path.clone());
+            if let Some(oldest) = oldest {
+                counters.remove(&oldest);
+            }
+        }
+
+        let counter = counters.entry(target).or_insert((0, number));
+        *counter = (counter.0 + 1, number);
+        drop(counters);
+
+        // Subscribers whose stream was dropped are removed, while
+        // the ones whose stream is full miss the dead letter.
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let mut i = 0;
+        while i < subscribers.len() {
+            match subscribers[i].try_send(letter.clone()) {
+                Err(err) if err.is_disconnected() => {
+                    subscribers.swap_remove(i);
+                }
+                _ => i += 1,
+            }
+        }
+    }
+}
+
+/// Forwards the envelope to the dead letters actor if it contains
+/// a user message. Other messages are dropped.
+pub(crate) fn publish(target: Arc<BastionPath>, env: Envelope) {
+    match env.msg {
+        BastionMessage::Message(msg) => publish_message(target, msg, env.sign),
+        msg => trace!(
+            "DeadLetters: Dropping message sent to {}: {:?}",
+            target,
+            msg
+        ),
+    }
+}
+
+pub(crate) fn publish_message(target: Arc<BastionPath>, msg: Msg, sign: RefAddr) {
+    // This is a dead letter that couldn't be delivered to the
+    // dead letters actor itself, there isn't anything left to do.
+    if msg.downcast_ref::<DeadLetter>().is_some() {
+        trace!("DeadLetters: Dropping undeliverable dead letter: {:?}", msg);
+        return;
+    }
+
+    let letter = DeadLetter::new(target, SignedMessage::new(msg, sign));
+    let msg = BastionMessage::broadcast(letter);
+    let env = Envelope::from_dead_letters(msg);
+    // FIXME: handle errors
+    SYSTEM.dead_letters().sender().unbounded_send(env).ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::BastionId;
+    use crate::path::BastionPathElement;
+
+    fn letter(target: Arc<BastionPath>) -> Arc<DeadLetter> {
+        let (sender, _) = futures::channel::mpsc::unbounded();
+        let sign = RefAddr::new(target.clone(), sender.into());
+        let message = SignedMessage::new(Msg::tell("lost"), sign);
+        Arc::new(DeadLetter::new(target, message))
+    }
+
+    fn child_path() -> Arc<BastionPath> {
+        let path = BastionPath::root()
+            .append(BastionPathElement::Supervisor(BastionId::new()))
+            .unwrap();
+        Arc::new(path)
+    }
+
+    #[test]
+    fn forgets_oldest_counters() {
+        let state = Arc::new(DeadLettersState::default());
+        let dead_letters = DeadLetters::new(state.clone());
+
+        let first = child_path();
+        let second = child_path();
+        state.record(letter(first.clone()));
+        state.record(letter(second.clone()));
+        state.record(letter(first.clone()));
+        for _ in 2..MAX_COUNTERS {
+            state.record(letter(child_path()));
+        }
+
+        // `second` is the path whose last dead letter is the oldest.
+        state.record(letter(child_path()));
+        assert_eq!(dead_letters.counters().len(), MAX_COUNTERS);
+        assert_eq!(dead_letters.count(&first), 2);
+        assert_eq!(dead_letters.count(&second), 0);
+        assert_eq!(dead_letters.total(), MAX_COUNTERS as u64 + 2);
+    }
+
+    #[test]
+    fn bounds_subscribers() {
+        let state = Arc::new(DeadLettersState::default());
+        let dead_letters = DeadLetters::new(state.clone());
+        let mut stream = dead_letters.subscribe();
+        let dropped = dead_letters.subscribe();
+        drop(dropped);
+
+        let target = child_path();
+        for _ in 0..SUBSCRIBER_CAPACITY * 2 {
+            state.record(letter(target.clone()));
+        }
+
+        // The stream that was dropped isn't sent dead letters anymore.
+        assert_eq!(state.subscribers.lock().unwrap().len(), 1);
+
+        // The channel also has room for one message per sender.
+        let mut received = 0;
+        while let Some(Some(_)) = stream.next().now_or_never() {
+            received += 1;
+        }
+        assert_eq!(received, SUBSCRIBER_CAPACITY + 1);
+
+        // The stream still receives the dead letters once emptied.
+        state.record(letter(target));
+        assert!(stream.next().now_or_never().flatten().is_some());
+    }
+}
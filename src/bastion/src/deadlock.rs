@@ -0,0 +1,201 @@
+//!
+//! Detection of the asks waiting on each other.
+//!
+//! In debug builds, every message "asked" while a child's future
+//! is polled adds an edge from the child to the recipient of the
+//! message to a graph of the pending asks, until it is answered
+//! (or its sender is dropped) or its answer is received or dropped.
+//! An ask closing a cycle in this graph (the recipient is, directly
+//! or not, waiting on the child asking it) would never be answered,
+//! and fails instead.
+use crate::context::BastionId;
+use crate::path::BastionPath;
+use fxhash::{FxHashMap, FxHashSet};
+use lazy_static::lazy_static;
+use std::cell::RefCell;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+thread_local! {
+    // The path of the child whose future is being polled on
+    // this thread, if any.
+    static CURRENT: RefCell<Option<Arc<BastionPath>>> = RefCell::new(None);
+}
+
+lazy_static! {
+    // The pending asks of every child waiting on an answer.
+    static ref PENDING: Mutex<FxHashMap<BastionId, Node>> = Mutex::new(FxHashMap::default());
+}
+
+static NEXT_ASK_ID: AtomicU64 = AtomicU64::new(0);
+
+// A child waiting on answers, and the recipients of its asks.
+struct Node {
+    path: Arc<BastionPath>,
+    asks: Vec<(u64, Arc<BastionPath>)>,
+}
+
+/// An ask added to the graph, removed from it once dropped.
+pub(crate) struct PendingAsk {
+    asker: BastionId,
+    id: u64,
+}
+
+/// The ask of an `Answer`, shared with its `AnswerSender`, which
+/// is removed from the graph once either of them releases it or is
+/// dropped.
+#[derive(Default)]
+pub(crate) struct AskGuard(Option<Arc<Mutex<Option<PendingAsk>>>>);
+
+/// Calls the closure (polling the future of a child) with the
+/// child being the one asking the messages asked by it.
+pub(crate) fn polling<F, R>(path: &Arc<BastionPath>, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    if !cfg!(debug_assertions) {
+        return f();
+    }
+
+    let previous = CURRENT.with(|current| current.replace(Some(path.clone())));
+    let res = f();
+    CURRENT.with(|current| *current.borrow_mut() = previous);
+
+    res
+}
+
+/// Adds an ask of the child being polled (if any) to the graph,
+/// returning the cycle of paths it would close (starting and ending
+/// with the child) if it deadlocks instead.
+pub(crate) fn ask(target: &Arc<BastionPath>) -> Result<Option<PendingAsk>, Vec<Arc<BastionPath>>> {
+    if !cfg!(debug_assertions) {
+        return Ok(None);
+    }
+
+    let asker = match CURRENT.with(|current| current.borrow().clone()) {
+        Some(asker) => asker,
+        None => return Ok(None),
+    };
+
+    // FIXME: panics
+    let mut pending = PENDING.lock().unwrap();
+    let mut visited = FxHashSet::default();
+    if let Some(mut cycle) = path_to(&pending, target, asker.id(), &mut visited) {
+        cycle.insert(0, asker);
+        warn!("Deadlock: Detected a cycle of asks: {:?}", cycle);
+        return Err(cycle);
+    }
+
+    let id = NEXT_ASK_ID.fetch_add(1, Ordering::Relaxed);
+    let node = pending.entry(asker.id().clone()).or_insert_with(|| Node {
+        path: asker.clone(),
+        asks: Vec::new(),
+    });
+    node.asks.push((id, target.clone()));
+
+    let asker = asker.id().clone();
+    Ok(Some(PendingAsk { asker, id }))
+}
+
+// Returns the paths leading from `from` to `to` following the
+// pending asks, if any.
+fn path_to(
+    pending: &FxHashMap<BastionId, Node>,
+    from: &Arc<BastionPath>,
+    to: &BastionId,
+    visited: &mut FxHashSet<BastionId>,
+) -> Option<Vec<Arc<BastionPath>>> {
+    if from.id() == to {
+        return Some(vec![from.clone()]);
+    }
+
+    if !visited.insert(from.id().clone()) {
+        return None;
+    }
+
+    let node = pending.get(from.id())?;
+    for (_, target) in &node.asks {
+        if let Some(mut path) = path_to(pending, target, to, visited) {
+            path.insert(0, node.path.clone());
+            return Some(path);
+        }
+    }
+
+    None
+}
+
+impl Drop for PendingAsk {
+    fn drop(&mut self) {
+        // FIXME: panics
+        let mut pending = PENDING.lock().unwrap();
+        if let Some(node) = pending.get_mut(&self.asker) {
+            node.asks.retain(|(id, _)| *id != self.id);
+            if node.asks.is_empty() {
+                pending.remove(&self.asker);
+            }
+        }
+    }
+}
+
+impl AskGuard {
+    /// Creates a new guard, which doesn't hold any ask until one is
+    /// tracked with it.
+    pub(crate) fn new() -> Self {
+        if !cfg!(debug_assertions) {
+            return AskGuard(None);
+        }
+
+        AskGuard(Some(Arc::new(Mutex::new(None))))
+    }
+
+    /// Returns a guard sharing the ask of this one.
+    pub(crate) fn share(&self) -> Self {
+        AskGuard(self.0.clone())
+    }
+
+    pub(crate) fn track(&self, pending: Option<PendingAsk>) {
+        if let Some(slot) = &self.0 {
+            // FIXME: panics
+            *slot.lock().unwrap() = pending;
+        }
+    }
+
+    /// Removes the ask from the graph, if it wasn't already.
+    pub(crate) fn release(&self) {
+        if let Some(slot) = &self.0 {
+            // The ask is dropped once the slot is unlocked.
+            // FIXME: panics
+            let pending = slot.lock().unwrap().take();
+            drop(pending);
+        }
+    }
+}
+
+impl Drop for AskGuard {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+impl Debug for AskGuard {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let pending = self
+            .0
+            .as_ref()
+            .and_then(|slot| slot.try_lock().ok().map(|pending| pending.is_some()));
+        fmt.debug_struct("AskGuard")
+            .field("pending", &pending.unwrap_or(false))
+            .finish()
+    }
+}
+
+impl Debug for PendingAsk {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("PendingAsk")
+            .field("asker", &self.asker)
+            .field("id", &self.id)
+            .finish()
+    }
+}
@@ -0,0 +1,307 @@
+//!
+//! Reliable delivery allows to send messages to children with an
+//! at-least-once guarantee, instead of the at-most-once guarantee
+//! of the other ways of sending messages.
+//!
+//! A message sent with [`ChildRef::tell_reliable`] is kept in an
+//! outbox until its recipient acknowledges it by calling
+//! [`BastionContext::ack`] with the id of the [`Delivery`] it
+//! received. Until then, it is redelivered every time the redelivery
+//! interval elapses, and forwarded to the dead letters once it was
+//! redelivered too many times (see [`DeliveryConfig`]).
+//!
+//! Because a message can be redelivered after it was handled but
+//! before it was acknowledged, its recipient might receive it more
+//! than once and should handle it idempotently.
+//!
+//! [`ChildRef::tell_reliable`]: ../child_ref/struct.ChildRef.html#method.tell_reliable
+//! [`BastionContext::ack`]: ../context/struct.BastionContext.html#method.ack
+//! [`Delivery`]: struct.Delivery.html
+//! [`DeliveryConfig`]: struct.DeliveryConfig.html
+use crate::child_ref::ChildRef;
+use crate::dead_letters;
+use crate::envelope::{Envelope, RefAddr};
+use crate::executor;
+use crate::message::{BastionMessage, Message, Msg};
+use bastion_executor::timer::TimerHandle;
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+use std::fmt::{self, Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, trace};
+
+lazy_static! {
+    // The timers redelivering the messages that weren't
+    // acknowledged yet.
+    static ref OUTBOX: Mutex<FxHashMap<DeliveryId, TimerHandle>> = Mutex::new(FxHashMap::default());
+}
+
+static NEXT_DELIVERY_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The identifier of a message sent with [`ChildRef::tell_reliable`],
+/// used by its recipient to acknowledge it.
+///
+/// [`ChildRef::tell_reliable`]: ../child_ref/struct.ChildRef.html#method.tell_reliable
+pub struct DeliveryId(u64);
+
+#[derive(Debug, Clone)]
+/// The message received by a child when a message was sent to it
+/// with [`ChildRef::tell_reliable`], containing the message and the
+/// id it must be acknowledged with.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// let children_ref = Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| async move {
+///         loop {
+///             msg! { ctx.recv().await?,
+///                 delivery: Delivery<&'static str> => {
+///                     // Handle the message...
+///                     println!("Received: {}", delivery.msg());
+///                     // ...and acknowledge it so that it isn't
+///                     // redelivered.
+///                     ctx.ack(delivery.id());
+///                 };
+///                 _: _ => ();
+///             }
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+///
+/// let child_ref = &children_ref.elems()[0];
+/// child_ref.tell_reliable("important").expect("Couldn't send the message.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub struct Delivery<M> {
+    id: DeliveryId,
+    attempt: usize,
+    msg: M,
+}
+
+#[derive(Debug, Clone)]
+/// The configuration of the redeliveries of a message sent with
+/// [`ChildRef::tell_reliable_with_config`].
+///
+/// By default, messages are redelivered every second, up to five
+/// times.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::time::Duration;
+/// #
+/// let config = DeliveryConfig::default()
+///     .with_redelivery_interval(Duration::from_millis(200))
+///     .with_max_redeliveries(10);
+/// # drop(config);
+/// ```
+///
+/// [`ChildRef::tell_reliable_with_config`]: ../child_ref/struct.ChildRef.html#method.tell_reliable_with_config
+pub struct DeliveryConfig {
+    redelivery_interval: Duration,
+    max_redeliveries: usize,
+}
+
+impl DeliveryId {
+    fn new() -> Self {
+        DeliveryId(NEXT_DELIVERY_ID.fetch_add(1, Ordering::SeqCst))
+    }
+}
+
+impl Display for DeliveryId {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.0)
+    }
+}
+
+impl<M> Delivery<M> {
+    /// Returns the id the message must be acknowledged with.
+    pub fn id(&self) -> DeliveryId {
+        self.id
+    }
+
+    /// Returns how many times the message was delivered, including
+    /// this time (`1` for the first delivery).
+    pub fn attempt(&self) -> usize {
+        self.attempt
+    }
+
+    /// Returns whether the message was already delivered before,
+    /// which means that it might have already been handled.
+    pub fn is_redelivery(&self) -> bool {
+        self.attempt > 1
+    }
+
+    /// Returns the message.
+    pub fn msg(&self) -> &M {
+        &self.msg
+    }
+
+    /// Returns the message, consuming the delivery.
+    pub fn into_msg(self) -> M {
+        self.msg
+    }
+}
+
+impl DeliveryConfig {
+    /// Sets the time to wait for an acknowledgment before
+    /// redelivering a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The time between two deliveries.
+    pub fn with_redelivery_interval(mut self, interval: Duration) -> Self {
+        self.redelivery_interval = interval;
+        self
+    }
+
+    /// Sets the amount of times a message is redelivered before
+    /// it is forwarded to the dead letters.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_redeliveries` - The maximum amount of redeliveries.
+    pub fn with_max_redeliveries(mut self, max_redeliveries: usize) -> Self {
+        self.max_redeliveries = max_redeliveries;
+        self
+    }
+
+    /// Returns the time to wait for an acknowledgment before
+    /// redelivering a message.
+    pub fn redelivery_interval(&self) -> Duration {
+        self.redelivery_interval
+    }
+
+    /// Returns the amount of times a message is redelivered before
+    /// it is forwarded to the dead letters.
+    pub fn max_redeliveries(&self) -> usize {
+        self.max_redeliveries
+    }
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        DeliveryConfig {
+            redelivery_interval: Duration::from_secs(1),
+            max_redeliveries: 5,
+        }
+    }
+}
+
+/// Sends the message to the child and keeps it in the outbox until
+/// it gets acknowledged.
+pub(crate) fn send<M>(child: &ChildRef, msg: M, config: DeliveryConfig) -> Result<DeliveryId, M>
+where
+    M: Message + Clone,
+{
+    let id = DeliveryId::new();
+    trace!("Delivery({}): Sending to: {}", id, child.id());
+
+    // The timer is scheduled before the first delivery is sent, so
+    // that an acknowledgment can't be received before the message
+    // is in the outbox.
+    let target = child.clone();
+    let redelivered = msg.clone();
+    let mut attempt = 1;
+    let handle = executor::schedule_interval(config.redelivery_interval, move || {
+        if !OUTBOX.lock().unwrap().contains_key(&id) {
+            return false;
+        }
+
+        attempt += 1;
+        let delivery = Delivery {
+            id,
+            attempt,
+            msg: redelivered.clone(),
+        };
+
+        if attempt > config.max_redeliveries + 1 {
+            debug!(
+                "Delivery({}): Giving up after {} deliveries.",
+                id,
+                attempt - 1
+            );
+            OUTBOX.lock().unwrap().remove(&id);
+            let sign = RefAddr::dead_letters();
+            dead_letters::publish_message(target.path().clone(), Msg::tell(delivery), sign);
+            return false;
+        }
+
+        trace!("Delivery({}): Redelivering (attempt {}).", id, attempt);
+        // A failed redelivery counts as an attempt.
+        deliver(&target, delivery).ok();
+        true
+    });
+    OUTBOX.lock().unwrap().insert(id, handle);
+
+    let delivery = Delivery {
+        id,
+        attempt: 1,
+        msg,
+    };
+    match deliver(child, delivery) {
+        Ok(()) => Ok(id),
+        Err(delivery) => {
+            if let Some(handle) = OUTBOX.lock().unwrap().remove(&id) {
+                handle.cancel();
+            }
+
+            Err(delivery.msg)
+        }
+    }
+}
+
+/// Removes the message from the outbox, returning whether it was
+/// still waiting to be acknowledged.
+pub(crate) fn ack(id: DeliveryId) -> bool {
+    match OUTBOX.lock().unwrap().remove(&id) {
+        Some(handle) => {
+            trace!("Delivery({}): Acknowledged.", id);
+            handle.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+fn deliver<M: Message>(child: &ChildRef, delivery: Delivery<M>) -> Result<(), Delivery<M>> {
+    let msg = BastionMessage::tell(delivery);
+    let env = Envelope::from_dead_letters(msg);
+    // FIXME: panics?
+    child.send(env).map_err(|env| env.into_msg().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_unknown_acknowledgments() {
+        assert!(!ack(DeliveryId::new()));
+    }
+}
@@ -0,0 +1,642 @@
+//!
+//! Discovery finds the nodes a node can contact to join a cluster,
+//! instead of (or on top of) the seeds of its [`ClusterConfig`], and
+//! is available with the `cluster` feature.
+//!
+//! A [`Discovery`] is set using [`ClusterConfig::with_discovery`],
+//! and is used until the node knows at least one other member (the
+//! other members are then learned through gossip). The available
+//! implementations are:
+//! - [`DnsDiscovery::host`], which resolves the addresses of a host
+//!   name (e.g. a headless Kubernetes service).
+//! - [`DnsDiscovery::srv`] (with the `discovery-dns` feature), which
+//!   looks up the `SRV` records of a service.
+//! - [`MdnsDiscovery`] (with the `discovery-mdns` feature), which
+//!   announces and browses the nodes of the local network using
+//!   multicast DNS.
+//! - [`KubernetesDiscovery`] (with the `discovery-kubernetes` feature),
+//!   which watches the pods matching a label selector using the
+//!   Kubernetes API.
+//!
+//! [`ClusterConfig`]: ../cluster/struct.ClusterConfig.html
+//! [`Discovery`]: trait.Discovery.html
+//! [`ClusterConfig::with_discovery`]: ../cluster/struct.ClusterConfig.html#method.with_discovery
+//! [`DnsDiscovery::host`]: struct.DnsDiscovery.html#method.host
+//! [`DnsDiscovery::srv`]: struct.DnsDiscovery.html#method.srv
+//! [`MdnsDiscovery`]: struct.MdnsDiscovery.html
+//! [`KubernetesDiscovery`]: struct.KubernetesDiscovery.html
+#[cfg(any(feature = "discovery-mdns", feature = "discovery-kubernetes"))]
+use fxhash::FxHashMap;
+#[cfg(feature = "discovery-kubernetes")]
+use serde_json::Value;
+use std::fmt::Debug;
+#[cfg(any(feature = "discovery-mdns", feature = "discovery-kubernetes"))]
+use std::fmt::{self, Formatter};
+use std::io;
+#[cfg(feature = "discovery-kubernetes")]
+use std::io::BufRead;
+#[cfg(any(feature = "discovery-mdns", feature = "discovery-kubernetes"))]
+use std::net::IpAddr;
+use std::net::{SocketAddr, ToSocketAddrs};
+#[cfg(any(feature = "discovery-mdns", feature = "discovery-kubernetes"))]
+use std::sync::{Arc, Mutex};
+#[cfg(any(feature = "discovery-mdns", feature = "discovery-kubernetes"))]
+use std::thread;
+#[cfg(feature = "discovery-kubernetes")]
+use std::time::Duration;
+#[cfg(feature = "discovery-kubernetes")]
+use tracing::warn;
+#[cfg(any(feature = "discovery-mdns", feature = "discovery-kubernetes"))]
+use tracing::{debug, trace};
+
+/// A way to find the addresses of the nodes of a cluster.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::io;
+/// # use std::net::SocketAddr;
+/// #
+/// #[derive(Debug)]
+/// // Reads the addresses from an environment variable.
+/// struct EnvDiscovery;
+///
+/// impl Discovery for EnvDiscovery {
+///     fn discover(&self) -> io::Result<Vec<SocketAddr>> {
+///         let nodes = std::env::var("BASTION_NODES").unwrap_or_default();
+///         nodes
+///             .split(',')
+///             .filter(|node| !node.is_empty())
+///             .map(|node| {
+///                 node.parse()
+///                     .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+///             })
+///             .collect()
+///     }
+/// }
+///
+/// let config = ClusterConfig::default().with_discovery(EnvDiscovery);
+/// # drop(config);
+/// ```
+pub trait Discovery: Debug + Send + Sync + 'static {
+    /// Returns the addresses of the nodes that were discovered, which
+    /// may include this node.
+    fn discover(&self) -> io::Result<Vec<SocketAddr>>;
+
+    /// Makes this node discoverable by the other nodes, once it
+    /// joined a cluster. By default, it does nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address the other nodes use to connect to
+    ///     this node.
+    fn register(&self, _addr: SocketAddr) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Stops making this node discoverable, once it isn't part of
+    /// the cluster anymore. By default, it does nothing.
+    fn deregister(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A [`Discovery`] using DNS to find the nodes.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// // Every pod of the service is a node.
+/// let discovery = DnsDiscovery::host("bastion.default.svc.cluster.local", 4222);
+/// let config = ClusterConfig::default().with_discovery(discovery);
+/// # drop(config);
+/// ```
+///
+/// [`Discovery`]: trait.Discovery.html
+pub struct DnsDiscovery {
+    query: DnsQuery,
+}
+
+#[derive(Debug, Clone)]
+enum DnsQuery {
+    Host(String, u16),
+    #[cfg(feature = "discovery-dns")]
+    Srv(String),
+}
+
+impl DnsDiscovery {
+    /// Creates a discovery resolving the addresses of a host name
+    /// (its `A` and `AAAA` records) using the resolver of the
+    /// system, every node being bound to the same port.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - The host name to resolve.
+    /// * `port` - The port the nodes are bound to.
+    pub fn host<H: Into<String>>(host: H, port: u16) -> Self {
+        DnsDiscovery {
+            query: DnsQuery::Host(host.into(), port),
+        }
+    }
+
+    #[cfg(feature = "discovery-dns")]
+    /// Creates a discovery looking up the `SRV` records of a
+    /// service (e.g. `_bastion._tcp.example.com`), which give the
+    /// host name and port of each node.
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - The name of the service.
+    pub fn srv<S: Into<String>>(service: S) -> Self {
+        DnsDiscovery {
+            query: DnsQuery::Srv(service.into()),
+        }
+    }
+}
+
+impl Discovery for DnsDiscovery {
+    fn discover(&self) -> io::Result<Vec<SocketAddr>> {
+        match &self.query {
+            DnsQuery::Host(host, port) => Ok((host.as_str(), *port).to_socket_addrs()?.collect()),
+            #[cfg(feature = "discovery-dns")]
+            DnsQuery::Srv(service) => {
+                let resolver = trust_dns_resolver::Resolver::from_system_conf()?;
+                let records = resolver
+                    .srv_lookup(service.as_str())
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+                let mut addrs = Vec::new();
+                for record in records.iter() {
+                    let target = record.target().to_utf8();
+                    let target = target.trim_end_matches('.');
+                    addrs.extend((target, record.port()).to_socket_addrs()?);
+                }
+
+                Ok(addrs)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "discovery-mdns")]
+/// A [`Discovery`] announcing and browsing the nodes of the local
+/// network using multicast DNS (mDNS), which is available with the
+/// `discovery-mdns` feature.
+///
+/// Nodes are announced as instances of a service type (
+/// `_bastion._tcp.local.` by default), so that nodes of different
+/// clusters can share a network by using different service types.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use bastion::prelude::*;
+/// #
+/// let discovery = MdnsDiscovery::new().expect("Couldn't start mDNS.");
+/// let config = ClusterConfig::default().with_discovery(discovery);
+/// # drop(config);
+/// ```
+///
+/// [`Discovery`]: trait.Discovery.html
+pub struct MdnsDiscovery {
+    daemon: mdns_sd::ServiceDaemon,
+    service_type: String,
+    // The addresses of the nodes, by instance name.
+    discovered: Arc<Mutex<FxHashMap<String, Vec<SocketAddr>>>>,
+    // The full name of this node's instance, once registered.
+    registered: Mutex<Option<String>>,
+}
+
+#[cfg(feature = "discovery-mdns")]
+impl MdnsDiscovery {
+    /// The service type the nodes are announced as by default.
+    pub const DEFAULT_SERVICE_TYPE: &'static str = "_bastion._tcp.local.";
+
+    /// Starts announcing and browsing the nodes of the default
+    /// service type.
+    ///
+    /// This method returns the discovery if it succeeded, or
+    /// `Err(error)` if the multicast socket couldn't be created.
+    pub fn new() -> io::Result<Self> {
+        MdnsDiscovery::with_service_type(Self::DEFAULT_SERVICE_TYPE)
+    }
+
+    /// Starts announcing and browsing the nodes of the given
+    /// service type, which must end with `.local.`.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_type` - The service type of the nodes.
+    pub fn with_service_type<S: Into<String>>(service_type: S) -> io::Result<Self> {
+        let service_type = service_type.into();
+        let daemon = mdns_sd::ServiceDaemon::new().map_err(mdns_error)?;
+        let events = daemon.browse(&service_type).map_err(mdns_error)?;
+
+        let discovered = Arc::new(Mutex::new(FxHashMap::default()));
+        let browsed = discovered.clone();
+        thread::Builder::new()
+            .name("bastion-mdns".to_string())
+            .spawn(move || {
+                // The channel is closed once the daemon shut down.
+                while let Ok(event) = events.recv() {
+                    trace!("MdnsDiscovery: Received event: {:?}", event);
+                    let mut discovered = browsed.lock().unwrap();
+                    match event {
+                        mdns_sd::ServiceEvent::ServiceResolved(info) => {
+                            let port = info.get_port();
+                            let addrs = info
+                                .get_addresses()
+                                .iter()
+                                .map(|ip| SocketAddr::new(IpAddr::V4(*ip), port))
+                                .collect();
+                            debug!("MdnsDiscovery: Resolved node: {:?}", addrs);
+                            discovered.insert(info.get_fullname().to_string(), addrs);
+                        }
+                        mdns_sd::ServiceEvent::ServiceRemoved(_, fullname) => {
+                            discovered.remove(&fullname);
+                        }
+                        _ => (),
+                    }
+                }
+            })?;
+
+        Ok(MdnsDiscovery {
+            daemon,
+            service_type,
+            discovered,
+            registered: Mutex::new(None),
+        })
+    }
+
+    /// Returns the service type the nodes are announced as.
+    pub fn service_type(&self) -> &str {
+        &self.service_type
+    }
+}
+
+#[cfg(feature = "discovery-mdns")]
+impl Debug for MdnsDiscovery {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("MdnsDiscovery")
+            .field("service_type", &self.service_type)
+            .field("registered", &self.registered)
+            .finish()
+    }
+}
+
+#[cfg(feature = "discovery-mdns")]
+impl Discovery for MdnsDiscovery {
+    fn discover(&self) -> io::Result<Vec<SocketAddr>> {
+        let discovered = self.discovered.lock().unwrap();
+        Ok(discovered.values().flatten().copied().collect())
+    }
+
+    fn register(&self, addr: SocketAddr) -> io::Result<()> {
+        let ip = match addr.ip() {
+            IpAddr::V4(ip) => ip,
+            IpAddr::V6(_) => {
+                let err = "mDNS discovery only supports IPv4 addresses";
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, err));
+            }
+        };
+
+        let instance = crate::remote::local_node_id().to_string();
+        let host = format!("{}.local.", instance);
+        let info = mdns_sd::ServiceInfo::new(
+            &self.service_type,
+            &instance,
+            &host,
+            ip,
+            addr.port(),
+            None::<std::collections::HashMap<String, String>>,
+        )
+        .map_err(mdns_error)?;
+
+        let fullname = info.get_fullname().to_string();
+        self.daemon.register(info).map_err(mdns_error)?;
+        *self.registered.lock().unwrap() = Some(fullname);
+        Ok(())
+    }
+
+    fn deregister(&self) -> io::Result<()> {
+        if let Some(fullname) = self.registered.lock().unwrap().take() {
+            self.daemon.unregister(&fullname).map_err(mdns_error)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "discovery-mdns")]
+fn mdns_error(err: mdns_sd::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err.to_string())
+}
+
+#[cfg(feature = "discovery-kubernetes")]
+/// A [`Discovery`] watching the pods of a Kubernetes namespace
+/// matching a label selector (e.g. the pods of a `StatefulSet`),
+/// which is available with the `discovery-kubernetes` feature.
+///
+/// The discovery uses the service account of the pod it runs in,
+/// which must be allowed to `list` and `watch` pods. Once created,
+/// it keeps watching the pods, so that the discovered nodes follow
+/// the pods that are scaled up or down. Only the running pods that
+/// aren't being deleted are discovered.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use bastion::prelude::*;
+/// #
+/// let discovery = KubernetesDiscovery::in_cluster("app=bastion", 4222)
+///     .expect("Couldn't reach the Kubernetes API.");
+/// let config = ClusterConfig::default().with_discovery(discovery);
+/// # drop(config);
+/// ```
+///
+/// [`Discovery`]: trait.Discovery.html
+pub struct KubernetesDiscovery {
+    namespace: String,
+    label_selector: String,
+    // The addresses of the nodes, by pod name.
+    discovered: Arc<Mutex<FxHashMap<String, SocketAddr>>>,
+}
+
+#[cfg(feature = "discovery-kubernetes")]
+struct KubernetesClient {
+    agent: ureq::Agent,
+    pods_url: String,
+    token: String,
+    label_selector: String,
+    port: u16,
+}
+
+#[cfg(feature = "discovery-kubernetes")]
+impl KubernetesDiscovery {
+    const SERVICE_ACCOUNT: &'static str = "/var/run/secrets/kubernetes.io/serviceaccount";
+    // How long to wait before listing the pods again after an error.
+    const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+    /// Starts watching the pods of the namespace this pod runs in,
+    /// matching the given label selector.
+    ///
+    /// This method returns the discovery if it succeeded, or
+    /// `Err(error)` if it isn't running in a Kubernetes pod.
+    ///
+    /// # Arguments
+    ///
+    /// * `label_selector` - The label selector of the pods (e.g.
+    ///     `app=bastion`).
+    /// * `port` - The port the nodes are bound to.
+    pub fn in_cluster<S: Into<String>>(label_selector: S, port: u16) -> io::Result<Self> {
+        let namespace = std::fs::read_to_string(format!("{}/namespace", Self::SERVICE_ACCOUNT))?;
+        KubernetesDiscovery::in_namespace(namespace.trim(), label_selector, port)
+    }
+
+    /// Starts watching the pods of the given namespace, matching the
+    /// given label selector.
+    ///
+    /// This method returns the discovery if it succeeded, or
+    /// `Err(error)` if it isn't running in a Kubernetes pod.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The namespace of the pods.
+    /// * `label_selector` - The label selector of the pods (e.g.
+    ///     `app=bastion`).
+    /// * `port` - The port the nodes are bound to.
+    pub fn in_namespace<N, S>(namespace: N, label_selector: S, port: u16) -> io::Result<Self>
+    where
+        N: Into<String>,
+        S: Into<String>,
+    {
+        let namespace = namespace.into();
+        let label_selector = label_selector.into();
+
+        let var =
+            |name| std::env::var(name).map_err(|err| io::Error::new(io::ErrorKind::NotFound, err));
+        let host = var("KUBERNETES_SERVICE_HOST")?;
+        let api_port = var("KUBERNETES_SERVICE_PORT")?;
+        // The host is an IPv6 address in IPv6 clusters.
+        let host = if host.contains(':') {
+            format!("[{}]", host)
+        } else {
+            host
+        };
+
+        let token = std::fs::read_to_string(format!("{}/token", Self::SERVICE_ACCOUNT))?;
+        let ca = std::fs::read(format!("{}/ca.crt", Self::SERVICE_ACCOUNT))?;
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut ca.as_slice())? {
+            roots
+                .add(&rustls::Certificate(cert))
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        }
+
+        let tls = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let client = KubernetesClient {
+            agent: ureq::AgentBuilder::new().tls_config(Arc::new(tls)).build(),
+            pods_url: format!(
+                "https://{}:{}/api/v1/namespaces/{}/pods",
+                host, api_port, namespace
+            ),
+            token: token.trim().to_string(),
+            label_selector: label_selector.clone(),
+            port,
+        };
+
+        let discovered = Arc::new(Mutex::new(FxHashMap::default()));
+        let watched = discovered.clone();
+        thread::Builder::new()
+            .name("bastion-kubernetes".to_string())
+            .spawn(move || loop {
+                // The API server ends watches after a while, or when
+                // the version they started from is too old, in which
+                // case the pods are listed again.
+                let res = client
+                    .list(&watched)
+                    .and_then(|version| client.watch(&version, &watched));
+                if let Err(err) = res {
+                    warn!("KubernetesDiscovery: Couldn't watch the pods: {}", err);
+                    thread::sleep(Self::RETRY_DELAY);
+                }
+            })?;
+
+        Ok(KubernetesDiscovery {
+            namespace,
+            label_selector,
+            discovered,
+        })
+    }
+
+    /// Returns the namespace of the watched pods.
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// Returns the label selector of the watched pods.
+    pub fn label_selector(&self) -> &str {
+        &self.label_selector
+    }
+}
+
+#[cfg(feature = "discovery-kubernetes")]
+impl KubernetesClient {
+    fn get(&self) -> ureq::Request {
+        self.agent
+            .get(&self.pods_url)
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .query("labelSelector", &self.label_selector)
+    }
+
+    // Replaces the discovered pods with the listed ones, returning
+    // the version of the list to start watching from.
+    fn list(&self, discovered: &Mutex<FxHashMap<String, SocketAddr>>) -> io::Result<String> {
+        let list: Value = self.get().call().map_err(kubernetes_error)?.into_json()?;
+
+        let mut pods = FxHashMap::default();
+        if let Some(items) = list["items"].as_array() {
+            for pod in items {
+                if let (Some(name), Some(addr)) = (pod_name(pod), pod_addr(pod, self.port)) {
+                    pods.insert(name.to_string(), addr);
+                }
+            }
+        }
+
+        debug!("KubernetesDiscovery: Listed pods: {:?}", pods);
+        *discovered.lock().unwrap() = pods;
+
+        match list["metadata"]["resourceVersion"].as_str() {
+            Some(version) => Ok(version.to_string()),
+            None => {
+                let err = "the list of pods has no resource version";
+                Err(io::Error::new(io::ErrorKind::InvalidData, err))
+            }
+        }
+    }
+
+    // Applies the changes to the pods as they happen, until the
+    // API server ends the watch.
+    fn watch(
+        &self,
+        version: &str,
+        discovered: &Mutex<FxHashMap<String, SocketAddr>>,
+    ) -> io::Result<()> {
+        let res = self
+            .get()
+            .query("watch", "true")
+            .query("resourceVersion", version)
+            .call()
+            .map_err(kubernetes_error)?;
+
+        for line in io::BufReader::new(res.into_reader()).lines() {
+            let event: Value = serde_json::from_str(&line?)?;
+            trace!("KubernetesDiscovery: Received event: {:?}", event);
+
+            let pod = &event["object"];
+            if event["type"] == "ERROR" {
+                let err = pod["message"].as_str().unwrap_or("the watch failed");
+                return Err(io::Error::new(io::ErrorKind::Other, err.to_string()));
+            }
+
+            let name = match pod_name(pod) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            let mut discovered = discovered.lock().unwrap();
+            match (event["type"].as_str(), pod_addr(pod, self.port)) {
+                (Some("ADDED"), Some(addr)) | (Some("MODIFIED"), Some(addr)) => {
+                    debug!("KubernetesDiscovery: Discovered pod: {} ({})", name, addr);
+                    discovered.insert(name, addr);
+                }
+                _ => {
+                    discovered.remove(&name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "discovery-kubernetes")]
+impl Debug for KubernetesDiscovery {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("KubernetesDiscovery")
+            .field("namespace", &self.namespace)
+            .field("label_selector", &self.label_selector)
+            .finish()
+    }
+}
+
+#[cfg(feature = "discovery-kubernetes")]
+impl Discovery for KubernetesDiscovery {
+    fn discover(&self) -> io::Result<Vec<SocketAddr>> {
+        let discovered = self.discovered.lock().unwrap();
+        Ok(discovered.values().copied().collect())
+    }
+}
+
+#[cfg(feature = "discovery-kubernetes")]
+fn pod_name(pod: &Value) -> Option<&str> {
+    pod["metadata"]["name"].as_str()
+}
+
+#[cfg(feature = "discovery-kubernetes")]
+// Returns the address of the node running in a pod, if the pod is
+// running and isn't being deleted.
+fn pod_addr(pod: &Value, port: u16) -> Option<SocketAddr> {
+    if pod["status"]["phase"] != "Running" || !pod["metadata"]["deletionTimestamp"].is_null() {
+        return None;
+    }
+
+    let ip: IpAddr = pod["status"]["podIP"].as_str()?.parse().ok()?;
+    Some(SocketAddr::new(ip, port))
+}
+
+#[cfg(feature = "discovery-kubernetes")]
+fn kubernetes_error(err: ureq::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_hosts() {
+        let discovery = DnsDiscovery::host("127.0.0.1", 4222);
+        let addrs = discovery.discover().unwrap();
+        assert_eq!(addrs, vec!["127.0.0.1:4222".parse().unwrap()]);
+    }
+
+    #[cfg(feature = "discovery-kubernetes")]
+    #[test]
+    fn discovers_running_pods() {
+        let pod = |phase: &str, deleting: bool| {
+            let mut pod = serde_json::json!({
+                "metadata": { "name": "bastion-0" },
+                "status": { "phase": phase, "podIP": "10.0.0.1" },
+            });
+            if deleting {
+                pod["metadata"]["deletionTimestamp"] = "2020-01-01T00:00:00Z".into();
+            }
+
+            pod
+        };
+
+        let addr = "10.0.0.1:4222".parse().unwrap();
+        assert_eq!(pod_addr(&pod("Running", false), 4222), Some(addr));
+        assert_eq!(pod_addr(&pod("Pending", false), 4222), None);
+        assert_eq!(pod_addr(&pod("Running", true), 4222), None);
+        assert_eq!(pod_name(&pod("Running", false)), Some("bastion-0"));
+    }
+}
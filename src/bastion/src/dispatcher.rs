@@ -3,14 +3,19 @@
 //! group of actors through the dispatchers that holds information about
 //! actors grouped together.
 use crate::child_ref::ChildRef;
+use crate::context::BastionId;
 use crate::envelope::SignedMessage;
+use crate::path::BastionPath;
 use anyhow::Result as AnyResult;
+use fxhash::FxHashMap;
 use lever::prelude::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
 use std::fmt::{self, Debug};
 use std::hash::{Hash, Hasher};
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
-    Arc,
+    Arc, Mutex,
 };
 use tracing::{debug, trace, warn};
 
@@ -28,6 +33,13 @@ pub enum NotificationType {
     /// Represents a notification when the existing actor
     /// was stopped, killed, suspended or finished an execution.
     Remove,
+    /// Represents a notification sent by an actor once it
+    /// processed a message it received from the dispatcher,
+    /// which is used by handlers keeping track of the messages
+    /// in-flight (like [`LeastLoadedHandler`]).
+    ///
+    /// [`LeastLoadedHandler`]: struct.LeastLoadedHandler.html
+    Processed,
 }
 
 #[derive(Debug, Clone)]
@@ -103,6 +115,459 @@ impl DispatcherHandler for RoundRobinHandler {
         };
     }
 }
+
+/// Dispatcher that sends all the messages with the same key to the
+/// same child, which is useful when children cache data related to
+/// the keys they handle.
+///
+/// The children are placed on a hash ring (multiple times, see
+/// [`with_virtual_nodes`]) which is rebuilt every time a child is
+/// registered or removed, and each message is sent to the first
+/// child following its key on the ring. This means that when the
+/// group changes, only the keys of the children next to the ones
+/// that were added or removed are moved to another child.
+///
+/// Messages for which no key can be extracted are dispatched as if
+/// their key was the path of their sender.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use bastion::dispatcher::ConsistentHashHandler;
+/// #
+/// #[derive(Debug)]
+/// struct Job {
+///     user_id: u64,
+/// }
+///
+/// let handler = ConsistentHashHandler::new(|msg: &SignedMessage| {
+///     msg.msg().downcast_ref::<Job>().map(|job| job.user_id)
+/// });
+/// let dispatcher = Dispatcher::with_type(DispatcherType::Named("jobs".to_string()))
+///     .with_handler(Box::new(handler));
+/// ```
+///
+/// [`with_virtual_nodes`]: #method.with_virtual_nodes
+pub struct ConsistentHashHandler {
+    key: Box<dyn Fn(&SignedMessage) -> Option<u64> + Send + Sync>,
+    virtual_nodes: usize,
+    // The hashes of the children's virtual nodes.
+    ring: Mutex<BTreeMap<u64, ChildRef>>,
+}
+
+impl ConsistentHashHandler {
+    /// Creates a new handler which uses `key` to extract the key of
+    /// the messages it dispatches.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The closure returning the key of a message, if any.
+    pub fn new<F, K>(key: F) -> Self
+    where
+        F: Fn(&SignedMessage) -> Option<K> + Send + Sync + 'static,
+        K: Hash,
+    {
+        ConsistentHashHandler {
+            key: Box::new(move |message| key(message).map(|key| hash(&key))),
+            virtual_nodes: 16,
+            ring: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Sets how many times each child is placed on the hash ring.
+    /// More virtual nodes spread the keys more evenly between the
+    /// children, at the cost of a bigger ring.
+    ///
+    /// The default amount of virtual nodes is `16`.
+    ///
+    /// # Arguments
+    ///
+    /// * `virtual_nodes` - The amount of virtual nodes per child.
+    pub fn with_virtual_nodes(mut self, virtual_nodes: usize) -> Self {
+        self.virtual_nodes = virtual_nodes.max(1);
+        self
+    }
+
+    fn rebuild(&self, entries: &DispatcherMap) {
+        let mut ring = self.ring.lock().unwrap();
+        ring.clear();
+
+        for (child, _) in entries.iter().filter(|entry| entry.0.is_public()) {
+            for node in 0..self.virtual_nodes {
+                ring.insert(hash(&(child.id(), node)), child.clone());
+            }
+        }
+    }
+
+    fn lookup(&self, key: u64) -> Option<ChildRef> {
+        let ring = self.ring.lock().unwrap();
+        ring.range(key..)
+            .next()
+            .or_else(|| ring.iter().next())
+            .map(|(_, child)| child.clone())
+    }
+}
+
+impl DispatcherHandler for ConsistentHashHandler {
+    // The ring is rebuilt when the group changes.
+    fn notify(
+        &self,
+        _from_child: &ChildRef,
+        entries: &DispatcherMap,
+        _notification_type: NotificationType,
+    ) {
+        self.rebuild(entries);
+    }
+    // The child following the message's key on the ring receives it.
+    fn broadcast_message(&self, _entries: &DispatcherMap, message: &Arc<SignedMessage>) {
+        let key = match (self.key)(message) {
+            Some(key) => key,
+            None => hash(&message.signature().path().to_string()),
+        };
+
+        match self.lookup(key) {
+            Some(child) => {
+                trace!("sending message with key {} to child {}", key, child.path());
+                child.tell_anonymously(message.clone()).unwrap();
+            }
+            None => debug!("no public children to broadcast message to"),
+        }
+    }
+}
+
+impl Debug for ConsistentHashHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ConsistentHashHandler(virtual_nodes: {:?}, ring: {:?})",
+            self.virtual_nodes,
+            self.ring.lock().unwrap().len()
+        )
+    }
+}
+
+/// Dispatcher that does weighted round-robin distribution: over
+/// time, each child receives a share of the messages proportional
+/// to its weight, and messages are interleaved between the children
+/// instead of being sent in bursts.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use bastion::dispatcher::WeightedRoundRobinHandler;
+/// #
+/// // Children of the "fast" group receive three times
+/// // more messages than the other ones.
+/// let handler = WeightedRoundRobinHandler::new(|child: &ChildRef| {
+///     if child.name() == "fast" {
+///         3
+///     } else {
+///         1
+///     }
+/// });
+/// let dispatcher = Dispatcher::with_type(DispatcherType::Named("workers".to_string()))
+///     .with_handler(Box::new(handler));
+/// ```
+pub struct WeightedRoundRobinHandler {
+    weight: Box<dyn Fn(&ChildRef) -> usize + Send + Sync>,
+    // The current weight of each child.
+    current: Mutex<FxHashMap<BastionId, i64>>,
+}
+
+impl WeightedRoundRobinHandler {
+    /// Creates a new handler which uses `weight` to get the weight
+    /// of each child. Children with a weight of `0` don't receive
+    /// any message.
+    ///
+    /// # Arguments
+    ///
+    /// * `weight` - The closure returning the weight of a child.
+    pub fn new<F>(weight: F) -> Self
+    where
+        F: Fn(&ChildRef) -> usize + Send + Sync + 'static,
+    {
+        WeightedRoundRobinHandler {
+            weight: Box::new(weight),
+            current: Mutex::new(FxHashMap::default()),
+        }
+    }
+}
+
+impl DispatcherHandler for WeightedRoundRobinHandler {
+    // Removed children are forgotten.
+    fn notify(
+        &self,
+        from_child: &ChildRef,
+        _entries: &DispatcherMap,
+        notification_type: NotificationType,
+    ) {
+        if let NotificationType::Remove = notification_type {
+            self.current.lock().unwrap().remove(from_child.id());
+        }
+    }
+    // The child with the highest current weight receives the message,
+    // like the "smooth weighted round-robin" of nginx.
+    fn broadcast_message(&self, entries: &DispatcherMap, message: &Arc<SignedMessage>) {
+        let mut current = self.current.lock().unwrap();
+        let mut total = 0;
+        let mut selected: Option<(ChildRef, i64)> = None;
+
+        for (child, _) in entries.iter().filter(|entry| entry.0.is_public()) {
+            let weight = (self.weight)(&child) as i64;
+            if weight == 0 {
+                continue;
+            }
+
+            let current_weight = current.entry(child.id().clone()).or_insert(0);
+            *current_weight += weight;
+            total += weight;
+
+            match &selected {
+                Some((_, max)) if *max >= *current_weight => (),
+                _ => selected = Some((child.clone(), *current_weight)),
+            }
+        }
+
+        match selected {
+            Some((child, _)) => {
+                if let Some(current_weight) = current.get_mut(child.id()) {
+                    *current_weight -= total;
+                }
+                trace!("sending message to child {}", child.path());
+                child.tell_anonymously(message.clone()).unwrap();
+            }
+            None => debug!("no public children to broadcast message to"),
+        }
+    }
+}
+
+impl Debug for WeightedRoundRobinHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "WeightedRoundRobinHandler(children: {:?})",
+            self.current.lock().unwrap().len()
+        )
+    }
+}
+
+/// Dispatcher that sends each message to the child with the least
+/// messages in-flight, which is useful when some children are slower
+/// than others.
+///
+/// A message is in-flight from the moment it is sent to a child and
+/// until this child notifies the dispatcher that it processed it
+/// (using [`BastionContext::notify`] with
+/// [`NotificationType::Processed`]).
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use bastion::dispatcher::LeastLoadedHandler;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// let workers = DispatcherType::Named("workers".to_string());
+/// let dispatcher = Dispatcher::with_type(workers.clone())
+///     .with_handler(Box::new(LeastLoadedHandler::default()));
+///
+/// Bastion::children(|children| {
+///     children
+///         .with_dispatcher(dispatcher)
+///         .with_exec(move |ctx: BastionContext| {
+///             let workers = workers.clone();
+///             async move {
+///                 loop {
+///                     let msg = ctx.recv().await?;
+///                     // Processing the message...
+///                     # drop(msg);
+///                     // ...and then letting the dispatcher know.
+///                     ctx.notify(&[workers.clone()], NotificationType::Processed);
+///                 }
+///             }
+///         })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`BastionContext::notify`]: ../context/struct.BastionContext.html#method.notify
+/// [`NotificationType::Processed`]: enum.NotificationType.html#variant.Processed
+#[derive(Default, Debug)]
+pub struct LeastLoadedHandler {
+    // The amount of messages in-flight for each child.
+    in_flight: Mutex<FxHashMap<BastionId, usize>>,
+}
+
+impl LeastLoadedHandler {
+    /// Returns the amount of messages sent to the given child that
+    /// it didn't notify as processed yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `child` - The child to get the amount of messages in-flight of.
+    pub fn in_flight(&self, child: &ChildRef) -> usize {
+        let in_flight = self.in_flight.lock().unwrap();
+        in_flight.get(child.id()).copied().unwrap_or(0)
+    }
+}
+
+impl DispatcherHandler for LeastLoadedHandler {
+    // Keeps track of the messages processed by the children.
+    fn notify(
+        &self,
+        from_child: &ChildRef,
+        _entries: &DispatcherMap,
+        notification_type: NotificationType,
+    ) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        match notification_type {
+            NotificationType::Register => {
+                in_flight.insert(from_child.id().clone(), 0);
+            }
+            NotificationType::Remove => {
+                in_flight.remove(from_child.id());
+            }
+            NotificationType::Processed => {
+                if let Some(count) = in_flight.get_mut(from_child.id()) {
+                    *count = count.saturating_sub(1);
+                }
+            }
+        }
+    }
+    // The child with the least messages in-flight receives the message.
+    fn broadcast_message(&self, entries: &DispatcherMap, message: &Arc<SignedMessage>) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let selected = entries
+            .iter()
+            .filter(|entry| entry.0.is_public())
+            .min_by_key(|entry| in_flight.get(entry.0.id()).copied().unwrap_or(0));
+
+        match selected {
+            Some((child, _)) => {
+                *in_flight.entry(child.id().clone()).or_insert(0) += 1;
+                trace!("sending message to child {}", child.path());
+                child.tell_anonymously(message.clone()).unwrap();
+            }
+            None => debug!("no public children to broadcast message to"),
+        }
+    }
+}
+
+/// Dispatcher that pins each sender to a child: all the messages
+/// sent by the same sender (identified by its path) are sent to the
+/// same child for as long as this child is registered, which is
+/// needed when children hold some state for each sender (like
+/// connection-oriented protocols).
+///
+/// New senders are pinned to the child with the least senders
+/// pinned to it. When a child is removed, its senders are pinned
+/// again to another child once they send a new message, while the
+/// other senders aren't moved.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use bastion::dispatcher::StickySessionHandler;
+/// #
+/// let dispatcher = Dispatcher::with_type(DispatcherType::Named("sessions".to_string()))
+///     .with_handler(Box::new(StickySessionHandler::default()));
+/// ```
+#[derive(Default, Debug)]
+pub struct StickySessionHandler {
+    // The child each sender is pinned to.
+    sessions: Mutex<FxHashMap<String, ChildRef>>,
+}
+
+impl StickySessionHandler {
+    /// Returns the child the sender with the given path is
+    /// pinned to, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - The path of the sender.
+    pub fn pinned_to(&self, sender: &BastionPath) -> Option<ChildRef> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.get(&sender.to_string()).cloned()
+    }
+}
+
+impl DispatcherHandler for StickySessionHandler {
+    // The senders pinned to removed children are forgotten.
+    fn notify(
+        &self,
+        from_child: &ChildRef,
+        _entries: &DispatcherMap,
+        notification_type: NotificationType,
+    ) {
+        if let NotificationType::Remove = notification_type {
+            let mut sessions = self.sessions.lock().unwrap();
+            sessions.retain(|_, child| child != from_child);
+        }
+    }
+    // The message is sent to the child its sender is pinned to.
+    fn broadcast_message(&self, entries: &DispatcherMap, message: &Arc<SignedMessage>) {
+        let sender = message.signature().path().to_string();
+        let mut sessions = self.sessions.lock().unwrap();
+
+        let pinned = sessions
+            .get(&sender)
+            .filter(|child| entries.contains_key(child))
+            .cloned();
+        let child = match pinned {
+            Some(child) => child,
+            None => {
+                let selected = entries
+                    .iter()
+                    .filter(|entry| entry.0.is_public())
+                    .min_by_key(|entry| {
+                        sessions.values().filter(|child| *child == &entry.0).count()
+                    });
+
+                match selected {
+                    Some((child, _)) => {
+                        trace!("pinning sender {} to child {}", sender, child.path());
+                        sessions.insert(sender.clone(), child.clone());
+                        child
+                    }
+                    None => {
+                        debug!("no public children to broadcast message to");
+                        return;
+                    }
+                }
+            }
+        };
+
+        trace!("sending message from {} to child {}", sender, child.path());
+        child.tell_anonymously(message.clone()).unwrap();
+    }
+}
+
+fn hash<K: Hash>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
 /// Generic trait which any custom dispatcher handler must implement for
 /// the further usage by the `Dispatcher` instances.
 pub trait DispatcherHandler {
@@ -386,6 +851,28 @@ impl GlobalDispatcher {
         self.dispatchers.remove(&dispatcher.dispatcher_type())?;
         Ok(())
     }
+
+    /// Returns whether a dispatcher with the given name is registered.
+    pub(crate) fn has_named(&self, name: &str) -> bool {
+        self.dispatchers
+            .contains_key(&DispatcherType::Named(name.to_string()))
+    }
+
+    /// Returns the sorted names of the registered named dispatchers,
+    /// as gossiped to the other members of the cluster.
+    pub(crate) fn named(&self) -> Vec<String> {
+        let mut names: Vec<_> = self
+            .dispatchers
+            .iter()
+            .filter_map(|pair| match pair.0 {
+                DispatcherType::Named(name) => Some(name),
+                DispatcherType::Anonymous => None,
+            })
+            .collect();
+
+        names.sort();
+        names
+    }
 }
 
 #[cfg(test)]
@@ -439,6 +926,24 @@ mod tests {
         }
     }
 
+    // Allows to inspect a handler after giving it to a dispatcher.
+    struct SharedHandler<H>(Arc<H>);
+
+    impl<H: DispatcherHandler> DispatcherHandler for SharedHandler<H> {
+        fn notify(
+            &self,
+            from_child: &ChildRef,
+            entries: &DispatcherMap,
+            notification_type: NotificationType,
+        ) {
+            self.0.notify(from_child, entries, notification_type)
+        }
+
+        fn broadcast_message(&self, entries: &DispatcherMap, message: &Arc<SignedMessage>) {
+            self.0.broadcast_message(entries, message)
+        }
+    }
+
     #[test]
     fn test_get_dispatcher_type_as_anonymous() {
         let instance = Dispatcher::default();
@@ -462,7 +967,7 @@ mod tests {
         let (sender, _) = mpsc::unbounded();
         let path = Arc::new(BastionPath::root());
         let name = "test_name".to_string();
-        let child_ref = ChildRef::new(bastion_id, sender, name, path);
+        let child_ref = ChildRef::new(bastion_id, sender.into(), name, path);
 
         assert_eq!(instance.actors.contains_key(&child_ref), false);
 
@@ -479,7 +984,7 @@ mod tests {
         let (sender, _) = mpsc::unbounded();
         let path = Arc::new(BastionPath::root());
         let name = "test_name".to_string();
-        let child_ref = ChildRef::new(bastion_id, sender, name, path);
+        let child_ref = ChildRef::new(bastion_id, sender.into(), name, path);
 
         instance
             .register(&child_ref, "my::test::module".to_string())
@@ -498,7 +1003,7 @@ mod tests {
         let (sender, _) = mpsc::unbounded();
         let path = Arc::new(BastionPath::root());
         let name = "test_name".to_string();
-        let child_ref = ChildRef::new(bastion_id, sender, name, path);
+        let child_ref = ChildRef::new(bastion_id, sender.into(), name, path);
 
         instance.notify(&child_ref, NotificationType::Register);
         let handler_was_called = handler.was_called();
@@ -515,7 +1020,7 @@ mod tests {
         const DATA: &str = "A message containing data (ask).";
         let message = Arc::new(SignedMessage::new(
             Msg::broadcast(DATA),
-            RefAddr::new(path, sender),
+            RefAddr::new(path, sender.into()),
         ));
 
         instance.broadcast_message(&message);
@@ -523,6 +1028,177 @@ mod tests {
         assert_eq!(handler_was_called, true);
     }
 
+    #[test]
+    fn test_consistent_hash_handler_keeps_keys_on_the_same_child() {
+        let handler = ConsistentHashHandler::new(|message: &SignedMessage| {
+            message.msg().downcast_ref::<u64>().map(|key| *key)
+        });
+        let instance = Dispatcher::default().with_handler(Box::new(handler));
+
+        let mut children = Vec::new();
+        for _ in 0..3 {
+            let (sender, recver) = mpsc::unbounded();
+            let path = Arc::new(BastionPath::root());
+            let name = "test_name".to_string();
+            let child_ref = ChildRef::new(BastionId::new(), sender.into(), name, path);
+            instance
+                .register(&child_ref, "my::test::module".to_string())
+                .unwrap();
+            children.push((child_ref, recver));
+        }
+
+        let (sender, _) = mpsc::unbounded();
+        let path = Arc::new(BastionPath::root());
+        let message = Arc::new(SignedMessage::new(
+            Msg::broadcast(42u64),
+            RefAddr::new(path, sender.into()),
+        ));
+        for _ in 0..3 {
+            instance.broadcast_message(&message);
+        }
+
+        let received = children
+            .iter_mut()
+            .map(|(_, recver)| {
+                let mut count = 0;
+                while let Ok(Some(_)) = recver.try_next() {
+                    count += 1;
+                }
+                count
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(received.iter().sum::<usize>(), 3);
+        assert!(received.contains(&3));
+
+        // Removing another child doesn't move the key.
+        let owner = received.iter().position(|count| *count == 3).unwrap();
+        let other = (owner + 1) % children.len();
+        instance.remove(&children[other].0);
+        instance.broadcast_message(&message);
+        assert!(matches!(children[owner].1.try_next(), Ok(Some(_))));
+    }
+
+    #[test]
+    fn test_weighted_round_robin_handler_follows_weights() {
+        let handler = WeightedRoundRobinHandler::new(
+            |child: &ChildRef| {
+                if child.name() == "heavy" {
+                    3
+                } else {
+                    1
+                }
+            },
+        );
+        let instance = Dispatcher::default().with_handler(Box::new(handler));
+
+        let mut children = Vec::new();
+        for name in &["heavy", "light"] {
+            let (sender, recver) = mpsc::unbounded();
+            let path = Arc::new(BastionPath::root());
+            let child_ref = ChildRef::new(BastionId::new(), sender.into(), name.to_string(), path);
+            instance
+                .register(&child_ref, "my::test::module".to_string())
+                .unwrap();
+            children.push(recver);
+        }
+
+        let (sender, _) = mpsc::unbounded();
+        let path = Arc::new(BastionPath::root());
+        let message = Arc::new(SignedMessage::new(
+            Msg::broadcast("A message containing data."),
+            RefAddr::new(path, sender.into()),
+        ));
+        for _ in 0..8 {
+            instance.broadcast_message(&message);
+        }
+
+        let received = children
+            .iter_mut()
+            .map(|recver| {
+                let mut count = 0;
+                while let Ok(Some(_)) = recver.try_next() {
+                    count += 1;
+                }
+                count
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(received, vec![6, 2]);
+    }
+
+    #[test]
+    fn test_least_loaded_handler_tracks_in_flight_messages() {
+        let handler = Arc::new(LeastLoadedHandler::default());
+        let instance = Dispatcher::default().with_handler(Box::new(SharedHandler(handler.clone())));
+
+        let mut children = Vec::new();
+        for _ in 0..2 {
+            let (sender, _) = mpsc::unbounded();
+            let path = Arc::new(BastionPath::root());
+            let name = "test_name".to_string();
+            let child_ref = ChildRef::new(BastionId::new(), sender.into(), name, path);
+            instance
+                .register(&child_ref, "my::test::module".to_string())
+                .unwrap();
+            children.push(child_ref);
+        }
+
+        let (sender, _) = mpsc::unbounded();
+        let path = Arc::new(BastionPath::root());
+        let message = Arc::new(SignedMessage::new(
+            Msg::broadcast("A message containing data."),
+            RefAddr::new(path, sender.into()),
+        ));
+        for _ in 0..4 {
+            instance.broadcast_message(&message);
+        }
+        assert_eq!(handler.in_flight(&children[0]), 2);
+        assert_eq!(handler.in_flight(&children[1]), 2);
+
+        // The next message goes to the child which processed one.
+        instance.notify(&children[1], NotificationType::Processed);
+        assert_eq!(handler.in_flight(&children[1]), 1);
+        instance.broadcast_message(&message);
+        assert_eq!(handler.in_flight(&children[0]), 2);
+        assert_eq!(handler.in_flight(&children[1]), 2);
+    }
+
+    #[test]
+    fn test_sticky_session_handler_pins_senders() {
+        let handler = Arc::new(StickySessionHandler::default());
+        let instance = Dispatcher::default().with_handler(Box::new(SharedHandler(handler.clone())));
+
+        let mut children = Vec::new();
+        for _ in 0..2 {
+            let (sender, _) = mpsc::unbounded();
+            let path = Arc::new(BastionPath::root());
+            let name = "test_name".to_string();
+            let child_ref = ChildRef::new(BastionId::new(), sender.into(), name, path);
+            instance
+                .register(&child_ref, "my::test::module".to_string())
+                .unwrap();
+            children.push(child_ref);
+        }
+
+        let (sender, _) = mpsc::unbounded();
+        let path = Arc::new(BastionPath::root());
+        let message = Arc::new(SignedMessage::new(
+            Msg::broadcast("A message containing data."),
+            RefAddr::new(path.clone(), sender.into()),
+        ));
+
+        instance.broadcast_message(&message);
+        let pinned = handler.pinned_to(&path).expect("The sender wasn't pinned.");
+        instance.broadcast_message(&message);
+        assert_eq!(handler.pinned_to(&path), Some(pinned.clone()));
+
+        // The sender is pinned to another child once its child is removed.
+        instance.remove(&pinned);
+        assert_eq!(handler.pinned_to(&path), None);
+        instance.broadcast_message(&message);
+        let repinned = handler.pinned_to(&path).expect("The sender wasn't pinned.");
+        assert_ne!(repinned, pinned);
+    }
+
     #[test]
     fn test_global_dispatcher_add_local_dispatcher() {
         let dispatcher_type = DispatcherType::Named("test".to_string());
@@ -572,7 +1248,7 @@ mod tests {
         let (sender, _) = mpsc::unbounded();
         let path = Arc::new(BastionPath::root());
         let name = "test_name".to_string();
-        let child_ref = ChildRef::new(bastion_id, sender, name, path);
+        let child_ref = ChildRef::new(bastion_id, sender.into(), name, path);
 
         let dispatcher_type = DispatcherType::Named("test".to_string());
         let local_dispatcher = Arc::new(Box::new(Dispatcher::with_type(dispatcher_type.clone())));
@@ -598,7 +1274,7 @@ mod tests {
         let (sender, _) = mpsc::unbounded();
         let path = Arc::new(BastionPath::root());
         let name = "test_name".to_string();
-        let child_ref = ChildRef::new(bastion_id, sender, name, path);
+        let child_ref = ChildRef::new(bastion_id, sender.into(), name, path);
 
         let dispatcher_type = DispatcherType::Named("test".to_string());
         let local_dispatcher = Arc::new(Box::new(Dispatcher::with_type(dispatcher_type.clone())));
@@ -625,7 +1301,7 @@ mod tests {
         let (sender, _) = mpsc::unbounded();
         let path = Arc::new(BastionPath::root());
         let name = "test_name".to_string();
-        let child_ref = ChildRef::new(bastion_id, sender, name, path);
+        let child_ref = ChildRef::new(bastion_id, sender.into(), name, path);
 
         let dispatcher_type = DispatcherType::Named("test".to_string());
         let handler = Box::new(CustomHandler::new(false));
@@ -654,7 +1330,7 @@ mod tests {
         let (sender, _) = mpsc::unbounded();
         let path = Arc::new(BastionPath::root());
         let name = "test_name".to_string();
-        let child_ref = ChildRef::new(bastion_id, sender, name, path);
+        let child_ref = ChildRef::new(bastion_id, sender.into(), name, path);
 
         let dispatcher_type = DispatcherType::Named("test".to_string());
         let handler = Box::new(CustomHandler::new(false));
@@ -677,11 +1353,31 @@ mod tests {
         const DATA: &str = "A message containing data (ask).";
         let message = Arc::new(SignedMessage::new(
             Msg::broadcast(DATA),
-            RefAddr::new(path, sender),
+            RefAddr::new(path, sender.into()),
         ));
 
         global_dispatcher.broadcast_message(BroadcastTarget::Group("".to_string()), &message);
         let handler_was_called = handler.was_called();
         assert_eq!(handler_was_called, true);
     }
+
+    #[test]
+    fn test_global_dispatcher_lists_named_dispatchers() {
+        let global_dispatcher = GlobalDispatcher::new();
+        for dispatcher_type in vec![
+            DispatcherType::Named("orders".to_string()),
+            DispatcherType::Anonymous,
+            DispatcherType::Named("audits".to_string()),
+        ] {
+            let dispatcher = Arc::new(Box::new(Dispatcher::with_type(dispatcher_type)));
+            global_dispatcher.register_dispatcher(&dispatcher).unwrap();
+        }
+
+        assert_eq!(
+            global_dispatcher.named(),
+            vec!["audits".to_string(), "orders".to_string()]
+        );
+        assert!(global_dispatcher.has_named("orders"));
+        assert!(!global_dispatcher.has_named("payments"));
+    }
 }
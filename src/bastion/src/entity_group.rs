@@ -0,0 +1,277 @@
+//!
+//! Entity groups spawn a child for every key (like a user or a
+//! device) the first time a message is sent to it, route the next
+//! messages sent to the key to this child, and stop it once it
+//! didn't receive any message for the group's passivation timeout.
+//!
+//! Entity groups are local to the node; to distribute entities
+//! across a cluster, see the `sharding` feature.
+use crate::child_ref::ChildRef;
+use crate::children_ref::ChildrenRef;
+use crate::context::BastionContext;
+use crate::executor;
+use crate::message::{Answer, Message};
+use crate::supervisor::SupervisorRef;
+use crate::Bastion;
+use fxhash::FxHashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// A group of children spawned on demand, one for every key they
+/// are sent messages for.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::time::Duration;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// Bastion::init();
+/// Bastion::start();
+///
+/// let devices = EntityGroup::new(Duration::from_secs(60), |id: u64, ctx: BastionContext| {
+///     async move {
+///         // Connect to the device...
+///         loop {
+///             msg! { ctx.recv().await?,
+///                 command: &'static str => {
+///                     println!("Device {}: {}", id, command);
+///                 };
+///                 _: _ => ();
+///             }
+///         }
+///     }
+/// })
+/// .expect("Couldn't create the entity group.");
+///
+/// // Spawns the child of the device 42...
+/// devices.tell(42, "reboot").expect("Couldn't send the message.");
+/// // ...and sends it this message too.
+/// devices.tell(42, "ping").expect("Couldn't send the message.");
+/// #
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub struct EntityGroup<K, F> {
+    group: Arc<Group<K>>,
+    init: Arc<F>,
+}
+
+struct Group<K> {
+    passivation_timeout: Duration,
+    // The supervisor of the entities' children groups.
+    supervisor: SupervisorRef,
+    entities: Mutex<FxHashMap<K, Entry>>,
+}
+
+struct Entry {
+    children: ChildrenRef,
+    last_used: Instant,
+}
+
+impl<K, F, R> EntityGroup<K, F>
+where
+    K: Hash + Eq + Clone + Debug + Send + Sync + 'static,
+    F: Fn(K, BastionContext) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<(), ()>> + Send + 'static,
+{
+    /// Creates an entity group whose children are supervised by a
+    /// new supervisor.
+    ///
+    /// This method returns the group if it succeeded, or `Err(())`
+    /// if the supervisor couldn't be created.
+    ///
+    /// # Arguments
+    ///
+    /// * `passivation_timeout` - The time after which a child that
+    ///     didn't receive any message is stopped.
+    /// * `init` - The closure taking the key of a child and a
+    ///     [`BastionContext`], and returning the [`Future`] the
+    ///     child will run.
+    ///
+    /// [`BastionContext`]: ../context/struct.BastionContext.html
+    /// [`Future`]: https://doc.rust-lang.org/std/future/trait.Future.html
+    pub fn new(passivation_timeout: Duration, init: F) -> Result<Self, ()> {
+        let supervisor = Bastion::supervisor(|sp| sp)?;
+        debug!("EntityGroup({}): Initializing.", supervisor.id());
+        let group = Arc::new(Group {
+            passivation_timeout,
+            supervisor,
+            entities: Mutex::new(FxHashMap::default()),
+        });
+
+        // The passivation stops once the group is dropped.
+        let interval = (passivation_timeout / 2).max(Duration::from_millis(10));
+        let passivated = Arc::downgrade(&group);
+        executor::schedule_interval(interval, move || match passivated.upgrade() {
+            Some(group) => {
+                group.passivate();
+                true
+            }
+            None => false,
+        });
+
+        Ok(EntityGroup {
+            group,
+            init: Arc::new(init),
+        })
+    }
+
+    /// Sends a message to the child of the key, spawning it if it
+    /// isn't running, without waiting for its answer.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)` if
+    /// the child couldn't be spawned or the message couldn't be
+    /// sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the child the message is sent to.
+    /// * `msg` - The message to send.
+    pub fn tell<M: Message>(&self, key: K, msg: M) -> Result<(), M> {
+        match self.entity(key) {
+            Some(child) => child.tell_anonymously(msg),
+            None => Err(msg),
+        }
+    }
+
+    /// Sends a message to the child of the key, spawning it if it
+    /// isn't running, and returns an [`Answer`] for its answer.
+    ///
+    /// This method returns the answer if it succeeded, or
+    /// `Err(msg)` if the child couldn't be spawned or the message
+    /// couldn't be sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the child the message is sent to.
+    /// * `msg` - The message to send.
+    ///
+    /// [`Answer`]: ../message/struct.Answer.html
+    pub fn ask<M: Message>(&self, key: K, msg: M) -> Result<Answer, M> {
+        match self.entity(key) {
+            Some(child) => child.ask_anonymously(msg),
+            None => Err(msg),
+        }
+    }
+
+    /// Returns the child of the key, spawning it if it isn't
+    /// running, or `None` if it couldn't be spawned.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the child.
+    pub fn entity(&self, key: K) -> Option<ChildRef> {
+        // FIXME: panics
+        let mut entities = self.group.entities.lock().unwrap();
+        if !entities.contains_key(&key) {
+            let children = self.spawn(key.clone()).ok()?;
+            let entry = Entry {
+                children,
+                last_used: Instant::now(),
+            };
+            entities.insert(key.clone(), entry);
+        }
+
+        let entry = entities.get_mut(&key)?;
+        entry.last_used = Instant::now();
+        entry.children.elems().first().cloned()
+    }
+
+    /// Returns the keys of the running children.
+    pub fn keys(&self) -> Vec<K> {
+        // FIXME: panics
+        let entities = self.group.entities.lock().unwrap();
+        entities.keys().cloned().collect()
+    }
+
+    /// Returns the number of running children.
+    pub fn len(&self) -> usize {
+        // FIXME: panics
+        self.group.entities.lock().unwrap().len()
+    }
+
+    /// Returns whether no child is running.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the supervisor of the children groups of the
+    /// entities.
+    pub fn supervisor(&self) -> &SupervisorRef {
+        &self.group.supervisor
+    }
+
+    fn spawn(&self, key: K) -> Result<ChildrenRef, ()> {
+        debug!(
+            "EntityGroup({}): Spawning entity: {:?}",
+            self.group.supervisor.id(),
+            key
+        );
+        let name = format!("entity/{:?}", key);
+        let init = self.init.clone();
+        self.group.supervisor.children(|children| {
+            children
+                .with_name(name)
+                .with_exec(move |ctx: BastionContext| init(key.clone(), ctx))
+        })
+    }
+}
+
+impl<K: Debug> Group<K> {
+    /// Stops the children that didn't receive any message for the
+    /// passivation timeout.
+    fn passivate(&self) {
+        let now = Instant::now();
+        // FIXME: panics
+        let mut entities = self.entities.lock().unwrap();
+        entities.retain(|key, entry| {
+            if now.duration_since(entry.last_used) < self.passivation_timeout {
+                return true;
+            }
+
+            debug!(
+                "EntityGroup({}): Passivating entity: {:?}",
+                self.supervisor.id(),
+                key
+            );
+            entry.children.stop().ok();
+            false
+        });
+    }
+}
+
+impl<K, F> Clone for EntityGroup<K, F> {
+    fn clone(&self) -> Self {
+        EntityGroup {
+            group: self.group.clone(),
+            init: self.init.clone(),
+        }
+    }
+}
+
+impl<K, F> Debug for EntityGroup<K, F> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("EntityGroup")
+            .field("supervisor", &self.group.supervisor)
+            .field("passivation_timeout", &self.group.passivation_timeout)
+            .finish()
+    }
+}
@@ -3,15 +3,29 @@
 //! and instruct Bastion how to send messages back to them
 
 use crate::broadcast::Sender;
+use crate::mailbox::Priority;
 use crate::message::{BastionMessage, Message, Msg};
 use crate::path::BastionPath;
 use crate::system::SYSTEM;
+use fxhash::FxHashMap;
 use std::sync::Arc;
+#[cfg(feature = "otel")]
+use tracing::Span;
 
 #[derive(Debug)]
 pub(crate) struct Envelope {
     pub(crate) msg: BastionMessage,
     pub(crate) sign: RefAddr,
+    // The mailbox lane the message will be pushed to, if it
+    // is a user message.
+    pub(crate) priority: Priority,
+    // The metadata the message was sent with, if it is a
+    // user message.
+    pub(crate) headers: Headers,
+    // The span the message was sent in, which its recipient
+    // will process it in.
+    #[cfg(feature = "otel")]
+    pub(crate) span: Span,
 }
 
 #[derive(Debug)]
@@ -54,11 +68,40 @@ pub(crate) struct Envelope {
 pub struct SignedMessage {
     pub(crate) msg: Msg,
     pub(crate) sign: RefAddr,
+    pub(crate) headers: Headers,
+    #[cfg(feature = "otel")]
+    pub(crate) span: Span,
 }
 
 impl SignedMessage {
     pub(crate) fn new(msg: Msg, sign: RefAddr) -> Self {
-        SignedMessage { msg, sign }
+        SignedMessage {
+            msg,
+            sign,
+            headers: Headers::default(),
+            #[cfg(feature = "otel")]
+            span: Span::current(),
+        }
+    }
+
+    pub(crate) fn with_headers(mut self, headers: Headers) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    #[cfg(feature = "otel")]
+    pub(crate) fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    /// Returns the span the message was sent in, which the
+    /// span its recipient processes it in is a child of.
+    ///
+    /// Only available with the `otel` feature.
+    #[cfg(feature = "otel")]
+    pub fn span(&self) -> &Span {
+        &self.span
     }
 
     #[doc(hidden)]
@@ -66,6 +109,26 @@ impl SignedMessage {
         (self.msg, self.sign)
     }
 
+    /// Returns the message itself, without its signature.
+    pub fn msg(&self) -> &Msg {
+        &self.msg
+    }
+
+    /// Returns the [`Headers`] the message was sent with.
+    ///
+    /// [`Headers`]: struct.Headers.html
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Returns the correlation ID the message was sent with, if
+    /// any (see [`Headers::with_correlation_id`]).
+    ///
+    /// [`Headers::with_correlation_id`]: struct.Headers.html#method.with_correlation_id
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.headers.correlation_id()
+    }
+
     /// Returns a message signature to identify the message sender
     ///
     /// # Example
@@ -274,29 +337,215 @@ impl RefAddr {
     }
 }
 
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Metadata sent along with a message, like the correlation ID
+/// of the request it is a part of, which middleware and tracing
+/// or idempotency layers can use without wrapping the message.
+///
+/// Headers are set using [`ChildRef::tell_anonymously_with_headers`]
+/// or [`BastionContext::tell_with_headers`], and read using
+/// [`SignedMessage::headers`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// let children_ref = Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| {
+///         async move {
+///             let msg: SignedMessage = ctx.recv().await?;
+///             assert_eq!(msg.correlation_id(), Some("order-42"));
+///             assert_eq!(msg.headers().get("tenant"), Some("acme"));
+///
+///             Ok(())
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+///
+/// let headers = Headers::new()
+///     .with_correlation_id("order-42")
+///     .with_header("tenant", "acme");
+/// children_ref.elems()[0]
+///     .tell_anonymously_with_headers("ship", headers)
+///     .expect("Couldn't send the message.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`ChildRef::tell_anonymously_with_headers`]: ../child_ref/struct.ChildRef.html#method.tell_anonymously_with_headers
+/// [`BastionContext::tell_with_headers`]: ../context/struct.BastionContext.html#method.tell_with_headers
+/// [`SignedMessage::headers`]: struct.SignedMessage.html#method.headers
+pub struct Headers {
+    correlation_id: Option<String>,
+    entries: FxHashMap<String, String>,
+}
+
+impl Headers {
+    /// Creates empty headers.
+    pub fn new() -> Self {
+        Headers::default()
+    }
+
+    /// Sets the correlation ID of the message, which identifies
+    /// the request (or workflow) it is a part of.
+    ///
+    /// # Argument
+    ///
+    /// * `id` - The correlation ID of the message.
+    pub fn with_correlation_id(mut self, id: impl Into<String>) -> Self {
+        self.correlation_id = Some(id.into());
+        self
+    }
+
+    /// Sets the value of a header, replacing its previous value if
+    /// it was already set.
+    ///
+    /// Header values are meant to be small, like IDs or flags.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the header.
+    /// * `value` - The value of the header.
+    pub fn with_header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    /// Sets the value of a header, returning its previous value if
+    /// it was already set.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the header.
+    /// * `value` - The value of the header.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.entries.insert(key.into(), value.into())
+    }
+
+    /// Returns the correlation ID of the message, if any.
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    /// Returns the value of a header, if it is set.
+    ///
+    /// # Argument
+    ///
+    /// * `key` - The name of the header.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    /// Returns an iterator over the names and values of the
+    /// headers (not including the correlation ID).
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Returns whether neither a correlation ID nor any header is
+    /// set.
+    pub fn is_empty(&self) -> bool {
+        self.correlation_id.is_none() && self.entries.is_empty()
+    }
+}
+
 impl Envelope {
     pub(crate) fn new(msg: BastionMessage, path: Arc<BastionPath>, sender: Sender) -> Self {
         Envelope {
             msg,
             sign: RefAddr::new(path, sender),
+            priority: Priority::default(),
+            headers: Headers::default(),
+            #[cfg(feature = "otel")]
+            span: Span::current(),
         }
     }
 
     pub(crate) fn new_with_sign(msg: BastionMessage, sign: RefAddr) -> Self {
-        Envelope { msg, sign }
+        Envelope {
+            msg,
+            sign,
+            priority: Priority::default(),
+            headers: Headers::default(),
+            #[cfg(feature = "otel")]
+            span: Span::current(),
+        }
     }
 
     pub(crate) fn from_dead_letters(msg: BastionMessage) -> Self {
         Envelope {
             msg,
             sign: RefAddr::dead_letters(),
+            priority: Priority::default(),
+            headers: Headers::default(),
+            #[cfg(feature = "otel")]
+            span: Span::current(),
         }
     }
 
+    // Wraps a received message back into an envelope, keeping
+    // its signature, headers (and span) so that it can be
+    // forwarded.
+    pub(crate) fn from_signed(smsg: SignedMessage) -> Self {
+        Envelope {
+            msg: BastionMessage::Message(smsg.msg),
+            sign: smsg.sign,
+            priority: Priority::default(),
+            headers: smsg.headers,
+            #[cfg(feature = "otel")]
+            span: smsg.span,
+        }
+    }
+
+    pub(crate) fn into_signed(self) -> Option<SignedMessage> {
+        if let BastionMessage::Message(msg) = self.msg {
+            let smsg = SignedMessage::new(msg, self.sign).with_headers(self.headers);
+            #[cfg(feature = "otel")]
+            let smsg = smsg.with_span(self.span);
+            Some(smsg)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub(crate) fn with_headers(mut self, headers: Headers) -> Self {
+        self.headers = headers;
+        self
+    }
+
     pub(crate) fn try_clone(&self) -> Option<Self> {
         self.msg.try_clone().map(|msg| Envelope {
             msg,
             sign: self.sign.clone(),
+            priority: self.priority,
+            headers: self.headers.clone(),
+            #[cfg(feature = "otel")]
+            span: self.span.clone(),
         })
     }
 
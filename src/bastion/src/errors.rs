@@ -2,8 +2,25 @@
 //! Describes the error types that may happen within bastion.
 //! Given Bastion has a let it crash strategy, most error aren't noticeable.
 //! A ReceiveError may however be raised when calling try_recv() or try_recv_timeout()
+//! and an AskError when a typed request couldn't be answered.
+//! A PersistenceError may be raised when the events of a persistent child
+//! couldn't be persisted or replayed.
+//! A RemoteError may be raised when a node couldn't be bound to an address
+//! or connected to another node.
+//! A ClusterError may be raised when a node couldn't join a cluster,
+//! a ShardingError when a shard region couldn't be initialized and a
+//! SingletonError when a cluster singleton couldn't be spawned.
+//! A ChildError describes how a child failed, given with the supervision
+//! events and to the restart callbacks.
+//! A ConfigError may be raised when a configuration couldn't be loaded
+//! from a file or from the environment, and a ManagementError when the
+//! configuration of the management server is invalid.
 //! More errors may happen in the future.
 
+use crate::path::BastionPath;
+use std::error::Error;
+use std::io;
+use std::sync::Arc;
 use std::time::Duration;
 
 #[derive(Debug)]
@@ -15,3 +32,142 @@ pub enum ReceiveError {
     /// Generic error. Not used yet
     Other,
 }
+
+#[derive(Debug)]
+/// These errors happen
+/// when a typed request is sent using `ask_typed()`
+pub enum AskError {
+    /// The request couldn't be sent to the recipient
+    SendFailed,
+    /// The recipient dropped the request without answering it
+    NoAnswer,
+    /// The recipient answered with a message of another type
+    /// than the request's response type
+    UnexpectedResponse,
+    /// The recipient didn't answer on time
+    Timeout(Duration),
+    /// The request wasn't sent because the recipient is (directly
+    /// or not) waiting on an answer of the child sending it, so the
+    /// request would never be answered. Only detected in debug
+    /// builds, with the cycle of children waiting on each other
+    /// (starting and ending with the child sending the request).
+    DeadlockDetected(Vec<Arc<BastionPath>>),
+}
+
+#[derive(Debug)]
+/// These errors happen
+/// when the events of a persistent child are persisted or replayed
+pub enum PersistenceError {
+    /// The children group doesn't have a journal
+    NoJournal,
+    /// An event couldn't be serialized or deserialized
+    Serialization(String),
+    /// The sequence number of an appended event isn't the one
+    /// following the highest sequence number of the journal
+    SequenceMismatch {
+        /// The sequence number the journal expected
+        expected: u64,
+        /// The sequence number of the appended event
+        found: u64,
+    },
+    /// The event with this sequence number is missing from the
+    /// journal or was corrupted
+    Corrupted {
+        /// The sequence number of the event
+        seq_nr: u64,
+    },
+    /// The journal's backend failed
+    Journal(String),
+}
+
+#[derive(Debug)]
+/// These errors happen
+/// when a node is bound to an address or connected to another node
+pub enum RemoteError {
+    /// The connection couldn't be established or was closed
+    Io(io::Error),
+    /// The other node didn't complete the handshake or speaks
+    /// another version of the protocol
+    Handshake(String),
+}
+
+#[derive(Debug)]
+/// These errors happen
+/// when a node joins a cluster using `Cluster::join()`
+pub enum ClusterError {
+    /// The node wasn't bound to an address, or was bound to an
+    /// unspecified one without advertising another address
+    NotBound,
+    /// The node already joined a cluster
+    AlreadyJoined,
+}
+
+#[derive(Debug)]
+/// These errors happen
+/// when a shard region is initialized using `Sharding::init()`
+pub enum ShardingError {
+    /// The shard region of the entity type was already initialized
+    AlreadyInitialized,
+    /// The supervisors of the shard region couldn't be created
+    SpawnFailed,
+}
+
+#[derive(Debug, Clone)]
+/// These errors happen
+/// when the future of a child fails
+pub enum ChildError {
+    /// The future returned an error
+    Failed,
+    /// The future returned a typed error, when it was defined using
+    /// `Children::with_typed_exec()`
+    Error(Arc<dyn Error + Send + Sync>),
+    /// The future panicked
+    Panic {
+        /// The panic's message, if it was a string
+        message: Option<String>,
+        /// The panic's backtrace, if backtraces are enabled using
+        /// the `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment
+        /// variables
+        backtrace: Option<String>,
+    },
+}
+
+#[derive(Debug)]
+/// These errors happen
+/// when a configuration is loaded using `Config::from_file()`
+/// or `Config::from_env()`
+pub enum ConfigError {
+    /// The file couldn't be read
+    Io(io::Error),
+    /// The file isn't valid TOML or contains unknown settings
+    Parse(String),
+    /// A setting has a value that couldn't be parsed
+    InvalidValue {
+        /// The name of the setting (or of the environment variable)
+        key: String,
+        /// The value of the setting
+        value: String,
+    },
+    /// A setting required by another one is missing
+    Missing(String),
+}
+
+#[derive(Debug)]
+/// These errors happen
+/// when a cluster singleton is spawned using `ClusterSingleton::spawn()`
+pub enum SingletonError {
+    /// A singleton with the same name was already spawned
+    AlreadySpawned,
+    /// The supervisor of the singleton couldn't be created
+    SpawnFailed,
+}
+
+#[derive(Debug)]
+/// These errors happen
+/// when the configuration of the management server is created using
+/// `ManagementConfig::new()`
+pub enum ManagementError {
+    /// The bearer token is empty (or only made of whitespaces), so
+    /// any request would be authorized
+    EmptyToken,
+}
@@ -0,0 +1,160 @@
+//!
+//! Supervision events are emitted when children start, stop,
+//! fail or get restarted and when supervisors escalate
+//! failures, allowing to monitor the system (e.g. to feed
+//! alerting) without parsing its logs.
+//!
+//! Events are yielded by the streams returned by
+//! [`Bastion::events`].
+//!
+//! [`Bastion::events`]: ../struct.Bastion.html#method.events
+use crate::circuit_breaker::CircuitState;
+use crate::errors::ChildError;
+use crate::path::BastionPath;
+use crate::watch::StopReason;
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::prelude::*;
+use lazy_static::lazy_static;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tracing::trace;
+
+lazy_static! {
+    static ref SUBSCRIBERS: Mutex<Vec<UnboundedSender<SupervisionEvent>>> = Mutex::new(Vec::new());
+}
+
+#[derive(Debug, Clone)]
+/// An event that happened to an element of the system.
+pub enum SupervisionEvent {
+    /// A child started (or restarted) executing its future.
+    ChildStarted {
+        /// The path of the child.
+        path: Arc<BastionPath>,
+    },
+    /// A child stopped, either because its future finished
+    /// executing successfully or because it was stopped or
+    /// killed.
+    ChildStopped {
+        /// The path of the child.
+        path: Arc<BastionPath>,
+        /// Why the child stopped.
+        reason: StopReason,
+    },
+    /// The future of a child returned an error.
+    ChildFailed {
+        /// The path of the child.
+        path: Arc<BastionPath>,
+        /// The error the future returned.
+        error: ChildError,
+    },
+    /// The future of a child panicked.
+    ChildPanicked {
+        /// The path of the child.
+        path: Arc<BastionPath>,
+        /// The panic's message and backtrace.
+        error: ChildError,
+    },
+    /// A child was restarted by its children group, as asked
+    /// by its supervisor.
+    ChildRestarted {
+        /// The path of the child.
+        path: Arc<BastionPath>,
+    },
+    /// A child didn't answer the heartbeats of its group before
+    /// their timeout (see [`Children::with_heartbeat`]), and is
+    /// going to be restarted.
+    ///
+    /// [`Children::with_heartbeat`]: ../children/struct.Children.html#method.with_heartbeat
+    ChildStalled {
+        /// The path of the child.
+        path: Arc<BastionPath>,
+        /// How long the child went without answering.
+        stalled_for: Duration,
+    },
+    /// A child took longer than the threshold of its group (see
+    /// [`Children::with_slow_handler_threshold`]) to handle a
+    /// message.
+    ///
+    /// [`Children::with_slow_handler_threshold`]: ../children/struct.Children.html#method.with_slow_handler_threshold
+    SlowHandler {
+        /// The path of the child.
+        path: Arc<BastionPath>,
+        /// The name of the type of the message.
+        message_type: &'static str,
+        /// How long the child took to handle the message (so far,
+        /// if it is still handling it).
+        elapsed: Duration,
+    },
+    /// A supervisor gave up recovering from a failure and
+    /// escalated it to its parent.
+    SupervisorEscalated {
+        /// The path of the supervisor.
+        path: Arc<BastionPath>,
+    },
+    /// The circuit breaker of a children group opened, closed,
+    /// or started probing.
+    CircuitStateChanged {
+        /// The path of the children group.
+        path: Arc<BastionPath>,
+        /// The new state of the breaker.
+        state: CircuitState,
+    },
+}
+
+#[derive(Debug)]
+/// A [`Stream`] of the supervision events emitted after it was
+/// created with [`Bastion::events`].
+///
+/// [`Stream`]: https://docs.rs/futures/0.3/futures/stream/trait.Stream.html
+/// [`Bastion::events`]: ../struct.Bastion.html#method.events
+pub struct SupervisionEvents {
+    recver: UnboundedReceiver<SupervisionEvent>,
+}
+
+impl SupervisionEvent {
+    /// Returns the path of the element the event happened to.
+    pub fn path(&self) -> &Arc<BastionPath> {
+        match self {
+            SupervisionEvent::ChildStarted { path }
+            | SupervisionEvent::ChildStopped { path, .. }
+            | SupervisionEvent::ChildFailed { path, .. }
+            | SupervisionEvent::ChildPanicked { path, .. }
+            | SupervisionEvent::ChildRestarted { path }
+            | SupervisionEvent::ChildStalled { path, .. }
+            | SupervisionEvent::SlowHandler { path, .. }
+            | SupervisionEvent::SupervisorEscalated { path }
+            | SupervisionEvent::CircuitStateChanged { path, .. } => path,
+        }
+    }
+}
+
+impl SupervisionEvents {
+    pub(crate) fn subscribe() -> Self {
+        let (sender, recver) = mpsc::unbounded();
+        SUBSCRIBERS.lock().unwrap().push(sender);
+
+        SupervisionEvents { recver }
+    }
+}
+
+impl Stream for SupervisionEvents {
+    type Item = SupervisionEvent;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().recver).poll_next(ctx)
+    }
+}
+
+/// Sends the event to all the subscribers.
+pub(crate) fn emit(event: SupervisionEvent) {
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    if subscribers.is_empty() {
+        return;
+    }
+
+    trace!("Events: Emitting event: {:?}", event);
+    // Subscribers whose stream was dropped are removed.
+    subscribers.retain(|subscriber| subscriber.unbounded_send(event.clone()).is_ok());
+}
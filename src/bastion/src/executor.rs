@@ -1,8 +1,355 @@
 //! A module that exposes the functions used under the hoods from `bastion`s macros: `spawn!`, `run!`
-//! and `blocking!`.
-pub use lightproc::proc_stack::ProcStack;
+//! and `blocking!`, and the [`Executor`] the system runs on.
+//!
+//! [`Executor`]: enum.Executor.html
+use crate::blocking_pool;
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use crate::testing;
+pub use bastion_executor::placement::Affinity;
+use bastion_executor::pool::{self, PoolConfig};
+use bastion_executor::timer::{self, TimerHandle};
+use futures::future::BoxFuture;
+use futures_timer::Delay;
+use lazy_static::lazy_static;
+use lightproc::lightproc::LightProc;
+pub use lightproc::proc_stack::{ProcPriority, ProcStack};
 use lightproc::recoverable_handle::RecoverableHandle;
+use std::fmt::{self, Debug, Formatter};
 use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::warn;
+
+lazy_static! {
+    // The executor the system runs on, if it isn't
+    // `bastion-executor`.
+    static ref EXECUTOR: RwLock<Option<Arc<dyn AgnosticExecutor>>> = RwLock::new(None);
+}
+
+/// The operations the system needs from the executor it runs on,
+/// allowing it to run on any async runtime (or custom
+/// scheduler).
+///
+/// The processes of the system (its supervisors, children and
+/// blocking tasks) are still scheduled by `lightproc`, which only
+/// asks the executor to poll them once woken up, and its timers
+/// are driven using [`sleep`].
+///
+/// # Example
+///
+/// ```rust
+/// use bastion::prelude::*;
+/// use futures::future::BoxFuture;
+/// use futures::FutureExt;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// // Runs every task on its own thread...
+/// struct ThreadPerTask;
+///
+/// impl AgnosticExecutor for ThreadPerTask {
+///     fn spawn(&self, task: BoxFuture<'static, ()>) {
+///         thread::spawn(move || futures::executor::block_on(task));
+///     }
+///
+///     fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+///         thread::spawn(task);
+///     }
+///
+///     fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+///         async move { thread::sleep(duration) }.boxed()
+///     }
+/// }
+///
+/// let config = Config::new().with_executor(Executor::custom(ThreadPerTask));
+/// ```
+///
+/// [`sleep`]: #tymethod.sleep
+pub trait AgnosticExecutor: Send + Sync + 'static {
+    /// Spawns a task, which must be polled until it finished.
+    ///
+    /// # Argument
+    ///
+    /// * `task` - The task to spawn.
+    fn spawn(&self, task: BoxFuture<'static, ()>);
+
+    /// Spawns a blocking task, which must be run on a thread
+    /// where blocking doesn't prevent other tasks from running.
+    ///
+    /// # Argument
+    ///
+    /// * `task` - The task to spawn.
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>);
+
+    /// Returns a future resolving once `duration` elapsed.
+    ///
+    /// # Argument
+    ///
+    /// * `duration` - How long to sleep.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// The [`AgnosticExecutor`] implementation of the thread pools of
+/// `bastion-executor`, which the system runs on by default.
+///
+/// [`AgnosticExecutor`]: trait.AgnosticExecutor.html
+pub struct BastionExecutor;
+
+#[derive(Clone)]
+/// The executor the system spawns its supervisors, children,
+/// timers and blocking tasks on, set using
+/// [`Config::with_executor`].
+///
+/// [`Config::with_executor`]: ../struct.Config.html#method.with_executor
+pub enum Executor {
+    /// The thread pools of `bastion-executor` (the default).
+    Bastion,
+    /// An existing `tokio` runtime, referenced by one of its
+    /// handles (available with the `tokio-runtime` feature).
+    ///
+    /// The children then run in the context of the runtime, and
+    /// can use its timers and I/O.
+    #[cfg(feature = "tokio-runtime")]
+    Tokio(tokio::runtime::Handle),
+    /// Any other executor (see [`Executor::custom`]).
+    ///
+    /// [`Executor::custom`]: #method.custom
+    Custom(Arc<dyn AgnosticExecutor>),
+}
+
+impl Executor {
+    /// Returns an executor running the system on `executor`.
+    ///
+    /// # Argument
+    ///
+    /// * `executor` - The implementation of [`AgnosticExecutor`]
+    ///     the system runs on.
+    ///
+    /// [`AgnosticExecutor`]: trait.AgnosticExecutor.html
+    pub fn custom<E: AgnosticExecutor>(executor: E) -> Self {
+        Executor::Custom(Arc::new(executor))
+    }
+
+    /// Returns whether both executors are known to be the same one
+    /// (the handles of `tokio` runtimes can't be compared, so they
+    /// never are).
+    pub(crate) fn is_same(&self, other: &Executor) -> bool {
+        match (self, other) {
+            (Executor::Bastion, Executor::Bastion) => true,
+            (Executor::Custom(executor), Executor::Custom(other)) => {
+                Arc::as_ptr(executor) as *const () == Arc::as_ptr(other) as *const ()
+            }
+            _ => false,
+        }
+    }
+
+    fn agnostic(self) -> Option<Arc<dyn AgnosticExecutor>> {
+        match self {
+            Executor::Bastion => None,
+            #[cfg(feature = "tokio-runtime")]
+            Executor::Tokio(handle) => Some(Arc::new(handle)),
+            Executor::Custom(executor) => Some(executor),
+        }
+    }
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Executor::Bastion
+    }
+}
+
+impl Debug for Executor {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            Executor::Bastion => fmt.write_str("Bastion"),
+            #[cfg(feature = "tokio-runtime")]
+            Executor::Tokio(handle) => fmt.debug_tuple("Tokio").field(handle).finish(),
+            Executor::Custom(_) => fmt.write_str("Custom"),
+        }
+    }
+}
+
+impl AgnosticExecutor for BastionExecutor {
+    fn spawn(&self, task: BoxFuture<'static, ()>) {
+        pool::spawn(task, ProcStack::default());
+    }
+
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+        bastion_executor::blocking::spawn_blocking(async move { task() }, ProcStack::default());
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(Delay::new(duration))
+    }
+}
+
+#[cfg(feature = "tokio-runtime")]
+impl AgnosticExecutor for tokio::runtime::Handle {
+    fn spawn(&self, task: BoxFuture<'static, ()>) {
+        tokio::runtime::Handle::spawn(self, task);
+    }
+
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+        tokio::runtime::Handle::spawn_blocking(self, task);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        // The timer is registered with the runtime of the handle,
+        // instead of the one of the calling thread (if there is one).
+        let _guard = self.enter();
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+pub(crate) fn set_executor(executor: Executor) {
+    // FIXME: panics
+    *EXECUTOR.write().unwrap() = executor.agnostic();
+}
+
+// Configures the threads of `bastion-executor`, if the system runs
+// on it.
+pub(crate) fn configure_pool(threads: Option<usize>, affinity: Option<&Affinity>) {
+    if threads.is_none() && affinity.is_none() {
+        return;
+    }
+
+    if agnostic().is_some() {
+        warn!("Executor: Not running on bastion-executor, ignoring its threads configuration.");
+        return;
+    }
+
+    let mut config = PoolConfig::new();
+    if let Some(threads) = threads {
+        config = config.with_threads(threads);
+    }
+    if let Some(affinity) = affinity {
+        config = config.with_affinity(affinity.clone());
+    }
+
+    if pool::configure(config).is_err() {
+        warn!("Executor: The threads of bastion-executor were already configured or started.");
+    }
+}
+
+// FIXME: panics
+fn agnostic() -> Option<Arc<dyn AgnosticExecutor>> {
+    EXECUTOR.read().unwrap().clone()
+}
+
+/// Spawns a process of the system onto the configured executor.
+pub(crate) fn spawn_proc<F, T>(future: F, stack: ProcStack) -> RecoverableHandle<T>
+where
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    match agnostic() {
+        None => pool::spawn(future, stack),
+        Some(executor) => {
+            // Every time the process is woken up, it is polled by
+            // a task of the executor.
+            let schedule = move |proc: LightProc| {
+                executor.spawn(Box::pin(async move { proc.run() }));
+            };
+            let (proc, recoverable) = LightProc::recoverable(future, schedule, stack);
+            proc.schedule();
+            recoverable
+        }
+    }
+}
+
+/// Spawns a blocking task onto the configured blocking pool, or
+/// onto the blocking pool of the configured executor.
+pub(crate) fn spawn_blocking_proc<F, R>(future: F, stack: ProcStack) -> RecoverableHandle<R>
+where
+    F: Future<Output = R> + Send + 'static,
+    R: Send + 'static,
+{
+    #[cfg(feature = "metrics")]
+    let future = metrics::instrument_blocking(future);
+
+    if let Some(pool) = blocking_pool::pool() {
+        return pool.spawn(future, stack);
+    }
+
+    match agnostic() {
+        None => bastion_executor::blocking::spawn_blocking(future, stack),
+        Some(executor) => {
+            let schedule = move |proc: LightProc| {
+                executor.spawn_blocking(Box::new(move || proc.run()));
+            };
+            let (proc, recoverable) = LightProc::recoverable(future, schedule, stack);
+            proc.schedule();
+            recoverable
+        }
+    }
+}
+
+/// Returns a future resolving once `duration` elapsed, on the
+/// installed [`TestClock`] if there is one, or using the timers of
+/// the configured executor otherwise.
+///
+/// [`TestClock`]: ../testing/struct.TestClock.html
+pub(crate) fn sleep(duration: Duration) -> BoxFuture<'static, ()> {
+    if let Some(clock) = testing::clock() {
+        return Box::pin(clock.sleep(duration));
+    }
+
+    match agnostic() {
+        None => Box::pin(Delay::new(duration)),
+        Some(executor) => executor.sleep(duration),
+    }
+}
+
+/// Schedules `callback` to be run once after `delay`, by the
+/// timers of the configured executor.
+pub(crate) fn schedule_once<F>(delay: Duration, callback: F) -> TimerHandle
+where
+    F: FnOnce() + Send + 'static,
+{
+    if testing::clock().is_none() && agnostic().is_none() {
+        return timer::schedule_once(delay, callback);
+    }
+
+    drive(delay, timer::detached_once(callback))
+}
+
+/// Schedules `callback` to be run every `interval` until it
+/// returns `false`, by the timers of the configured executor.
+pub(crate) fn schedule_interval<F>(interval: Duration, callback: F) -> TimerHandle
+where
+    F: FnMut() -> bool + Send + 'static,
+{
+    if testing::clock().is_none() && agnostic().is_none() {
+        return timer::schedule_interval(interval, callback);
+    }
+
+    drive(interval, timer::detached_interval(interval, callback))
+}
+
+// Fires a detached timer from a process sleeping on the test clock
+// or the timers of the executor, until it doesn't need to be fired
+// again.
+fn drive(delay: Duration, timer: TimerHandle) -> TimerHandle {
+    let driven = timer.clone();
+    spawn_proc(
+        async move {
+            let mut delay = delay;
+            loop {
+                sleep(delay).await;
+                match driven.fire() {
+                    Some(next) => delay = next,
+                    None => return,
+                }
+            }
+        },
+        ProcStack::default(),
+    );
+
+    timer
+}
 
 /// Spawns a blocking task, which will run on the blocking thread pool,
 /// and returns the handle.
@@ -33,7 +380,7 @@ where
     F: Future<Output = R> + Send + 'static,
     R: Send + 'static,
 {
-    bastion_executor::blocking::spawn_blocking(future, lightproc::proc_stack::ProcStack::default())
+    spawn_blocking_proc(future, ProcStack::default())
 }
 
 /// Block the current thread until passed
@@ -108,5 +455,5 @@ where
     F: Future<Output = T> + Send + 'static,
     T: Send + 'static,
 {
-    bastion_executor::pool::spawn(future, lightproc::proc_stack::ProcStack::default())
+    spawn_proc(future, ProcStack::default())
 }
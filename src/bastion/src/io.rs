@@ -1,5 +1,138 @@
 //!
 //! IO subsystem for Bastion
+//!
+//! On Linux, the `io-uring` feature adds file and socket operations
+//! backed by the io_uring reactor of `bastion-executor` ([`read_file`],
+//! [`write_file`], [`accept`], [`read`] and [`write`]), which don't
+//! consume the threads of the blocking pool.
+//!
+//! [`read_file`]: fn.read_file.html
+//! [`write_file`]: fn.write_file.html
+//! [`accept`]: fn.accept.html
+//! [`read`]: fn.read.html
+//! [`write`]: fn.write.html
 
 pub use nuclei::join_handle::*;
 pub use nuclei::*;
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub use self::uring::{accept, read, read_file, write, write_file};
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring {
+    use bastion_executor::uring;
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::net::{TcpListener, TcpStream};
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::path::Path;
+
+    // The size of the chunks read by `read_file`, when the size of
+    // the file isn't known.
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Reads the whole content of a file using io_uring.
+    ///
+    /// # Argument
+    ///
+    /// * `path` - The path of the file to read.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use bastion::prelude::*;
+    /// #
+    /// # fn run() {
+    /// Bastion::children(|children| {
+    ///     children.with_exec(|ctx: BastionContext| async move {
+    ///         let config = bastion::io::read_file("config.toml").await.map_err(|_| ())?;
+    ///         // Processes the file...
+    ///         Ok(())
+    ///     })
+    /// }).expect("Couldn't create the children group.");
+    /// # }
+    /// ```
+    pub async fn read_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+        let file = File::open(path)?;
+        let len = file.metadata().map(|meta| meta.len() as usize).unwrap_or(0);
+
+        let mut content = Vec::with_capacity(len);
+        loop {
+            // Reads at least the rest of the file, or a chunk if
+            // it grew (or if its size isn't known).
+            let size = len.saturating_sub(content.len()).max(CHUNK_SIZE);
+            let offset = content.len() as u64;
+            let (res, buf) = uring::read_at(file.as_raw_fd(), vec![0; size], offset).await;
+            match res? {
+                0 => return Ok(content),
+                read => content.extend_from_slice(&buf[..read]),
+            }
+        }
+    }
+
+    /// Writes `content` to a file using io_uring, creating it if it
+    /// doesn't exist and truncating it otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to write.
+    /// * `content` - The content of the file.
+    pub async fn write_file<P: AsRef<Path>, C: Into<Vec<u8>>>(
+        path: P,
+        content: C,
+    ) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        let content = content.into();
+        let mut written = 0;
+        while written < content.len() {
+            let chunk = content[written..].to_vec();
+            let (res, _) = uring::write_at(file.as_raw_fd(), chunk, written as u64).await;
+            match res? {
+                0 => return Err(io::ErrorKind::WriteZero.into()),
+                count => written += count,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Accepts a new connection on `listener` using io_uring.
+    ///
+    /// # Argument
+    ///
+    /// * `listener` - The listener to accept a connection on.
+    pub async fn accept(listener: &TcpListener) -> io::Result<TcpStream> {
+        let fd = uring::accept(listener.as_raw_fd()).await?;
+        // The file descriptor was just returned by `accept`.
+        Ok(unsafe { TcpStream::from_raw_fd(fd) })
+    }
+
+    /// Reads data from `stream` into `buf` using io_uring, returning
+    /// the amount of bytes read along with the buffer (which the
+    /// kernel owns until the read completes).
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - The stream to read from.
+    /// * `buf` - The buffer to read into.
+    pub async fn read(stream: &TcpStream, buf: Vec<u8>) -> (io::Result<usize>, Vec<u8>) {
+        uring::recv(stream.as_raw_fd(), buf).await
+    }
+
+    /// Writes `buf` to `stream` using io_uring, returning the amount
+    /// of bytes written along with the buffer (which the kernel owns
+    /// until the write completes).
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - The stream to write to.
+    /// * `buf` - The data to write.
+    pub async fn write(stream: &TcpStream, buf: Vec<u8>) -> (io::Result<usize>, Vec<u8>) {
+        uring::send(stream.as_raw_fd(), buf).await
+    }
+}
@@ -0,0 +1,578 @@
+//!
+//! The Kafka connector delivers the records of a topic to a children
+//! group, and is available with the `kafka` feature.
+//!
+//! A [`KafkaConsumer`] spawns a consumer child for every partition of
+//! the topic, under a new supervisor. Every record is delivered to
+//! one of the handlers' elements as a [`KafkaRecord`], and its offset
+//! is only committed once it (and all the records preceding it) was
+//! acknowledged by calling [`KafkaRecord::ack`].
+//!
+//! When a consumer child fails, it is restarted by its supervisor and
+//! gets its partition assigned again, resuming from the last committed
+//! offset (the records that weren't acknowledged are thus delivered
+//! again). The callbacks set using [`KafkaConsumer::with_callbacks`]
+//! are called when this happens, allowing to react to the rebalance.
+//!
+//! Replies are sent using a [`KafkaProducer`].
+//!
+//! [`KafkaConsumer`]: struct.KafkaConsumer.html
+//! [`KafkaRecord`]: struct.KafkaRecord.html
+//! [`KafkaRecord::ack`]: struct.KafkaRecord.html#method.ack
+//! [`KafkaConsumer::with_callbacks`]: struct.KafkaConsumer.html#method.with_callbacks
+//! [`KafkaProducer`]: struct.KafkaProducer.html
+use crate::callbacks::Callbacks;
+use crate::child_ref::ChildRef;
+use crate::children_ref::ChildrenRef;
+use crate::context::BastionContext;
+use crate::envelope::SignedMessage;
+use crate::executor;
+use crate::source::Feeder;
+use crate::supervisor::SupervisorRef;
+use crate::Bastion;
+use futures::prelude::*;
+use futures_timer::Delay;
+use rdkafka::client::DefaultClientContext;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{
+    BaseConsumer, CommitMode, Consumer, DefaultConsumerContext, StreamConsumer,
+};
+use rdkafka::error::KafkaError;
+use rdkafka::message::{BorrowedMessage, Message as _};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::{AsyncRuntime, Timeout};
+use rdkafka::{Offset, TopicPartitionList};
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::time::Duration;
+use tracing::{debug, error, trace, warn};
+
+// How long to wait for the metadata of a topic.
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+/// A consumer of the records of a Kafka topic, delivering them to a
+/// children group.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use bastion::prelude::*;
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// # Bastion::start();
+/// #
+/// let handlers = Bastion::children(|children| {
+///     children
+///         .with_redundancy(4)
+///         .with_exec(|ctx| async move {
+///             loop {
+///                 msg! { ctx.recv().await?,
+///                     record: KafkaRecord => {
+///                         // Handle the record...
+///                         record.ack().ok();
+///                     };
+///                     _: _ => ();
+///                 }
+///             }
+///         })
+/// }).expect("Couldn't create the children group.");
+///
+/// KafkaConsumer::new("localhost:9092", "orders-handlers", "orders")
+///     .with_max_in_flight(64)
+///     .deliver_to(&handlers)
+///     .expect("Couldn't consume the topic.");
+/// # }
+/// ```
+pub struct KafkaConsumer {
+    config: ClientConfig,
+    topic: String,
+    partitions: Option<Vec<i32>>,
+    max_in_flight: usize,
+    callbacks: Callbacks,
+}
+
+#[derive(Debug)]
+/// A record of a Kafka topic, delivered by a [`KafkaConsumer`].
+///
+/// [`KafkaConsumer`]: struct.KafkaConsumer.html
+pub struct KafkaRecord {
+    topic: String,
+    partition: i32,
+    offset: i64,
+    key: Option<Vec<u8>>,
+    payload: Option<Vec<u8>>,
+    // The consumer child the record must be acknowledged to.
+    consumer: ChildRef,
+}
+
+#[derive(Clone)]
+/// A handle to a Kafka producer, allowing to publish records (e.g.
+/// the replies to consumed records).
+///
+/// Clones of a producer share its connections.
+pub struct KafkaProducer {
+    producer: FutureProducer<DefaultClientContext, BastionRuntime>,
+    timeout: Duration,
+}
+
+/// The runtime used by the Kafka clients to spawn their tasks and
+/// wait, using Bastion's executor.
+pub struct BastionRuntime;
+
+#[derive(Debug)]
+// Sent by a handler to the consumer child of the record it
+// acknowledged.
+struct Ack {
+    offset: i64,
+}
+
+#[derive(Debug, Default)]
+// The offsets of the records of a partition that were delivered,
+// and of the ones that were acknowledged but can't be committed
+// yet because records preceding them weren't.
+struct Offsets {
+    pending: BTreeSet<i64>,
+    acked: BTreeSet<i64>,
+}
+
+// What a consumer child woke up for.
+enum Event {
+    Record(KafkaRecord),
+    Msg(SignedMessage),
+}
+
+impl KafkaConsumer {
+    /// Creates a new consumer of the topic, whose offsets are
+    /// committed for the given consumer group.
+    ///
+    /// # Arguments
+    ///
+    /// * `brokers` - The comma-separated addresses of the brokers.
+    /// * `group_id` - The id of the consumer group.
+    /// * `topic` - The topic to consume.
+    pub fn new(brokers: &str, group_id: &str, topic: &str) -> Self {
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", brokers)
+            .set("group.id", group_id)
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest");
+
+        KafkaConsumer {
+            config,
+            topic: topic.to_string(),
+            partitions: None,
+            max_in_flight: 100,
+            callbacks: Callbacks::new(),
+        }
+    }
+
+    /// Sets a property of the configuration of the consumers (see
+    /// the documentation of `librdkafka` for the available ones).
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the property.
+    /// * `value` - The value of the property.
+    pub fn with_config(mut self, key: &str, value: &str) -> Self {
+        self.config.set(key, value);
+        self
+    }
+
+    /// Sets the partitions of the topic to consume, instead of all
+    /// of them.
+    ///
+    /// # Argument
+    ///
+    /// * `partitions` - The partitions to consume.
+    pub fn with_partitions(mut self, partitions: impl IntoIterator<Item = i32>) -> Self {
+        self.partitions = Some(partitions.into_iter().collect());
+        self
+    }
+
+    /// Sets the maximum amount of records of a partition that can be
+    /// delivered without being acknowledged, above which the
+    /// partition's consumer waits for acknowledgements.
+    ///
+    /// By default, `100` records can be in flight.
+    ///
+    /// # Argument
+    ///
+    /// * `max_in_flight` - The maximum amount of unacknowledged
+    ///     records per partition (at least `1`).
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    /// Sets the callbacks of the children groups of the consumer
+    /// children, which are called when a consumer child fails and
+    /// is restarted (see the module-level documentation).
+    ///
+    /// # Argument
+    ///
+    /// * `callbacks` - The callbacks of the consumers' groups.
+    pub fn with_callbacks(mut self, callbacks: Callbacks) -> Self {
+        self.callbacks = callbacks;
+        self
+    }
+
+    /// Starts consuming the topic, spawning a consumer child for
+    /// every partition under a new supervisor, and delivering the
+    /// records to the elements of `handlers` in turn.
+    ///
+    /// This method returns the supervisor of the consumers if it
+    /// succeeded, or an error if the partitions of the topic couldn't
+    /// be fetched.
+    ///
+    /// # Argument
+    ///
+    /// * `handlers` - The children group the records are delivered to.
+    pub fn deliver_to(self, handlers: &ChildrenRef) -> Result<SupervisorRef, KafkaError> {
+        let partitions = match &self.partitions {
+            Some(partitions) => partitions.clone(),
+            None => self.fetch_partitions()?,
+        };
+
+        debug!(
+            "KafkaConsumer({}): Consuming partitions: {:?}",
+            self.topic, partitions
+        );
+        let supervisor = Bastion::supervisor(|sp| sp).map_err(|()| KafkaError::Canceled)?;
+        for partition in partitions {
+            let consumer = self.clone();
+            let handlers = handlers.clone();
+            supervisor
+                .children(|children| {
+                    children
+                        .with_name(format!("kafka/{}/{}", self.topic, partition))
+                        .with_callbacks(self.callbacks.clone())
+                        .with_exec(move |ctx: BastionContext| {
+                            let consumer = consumer.clone();
+                            let handlers = handlers.clone();
+                            async move { consumer.consume(ctx, partition, handlers).await }
+                        })
+                })
+                .map_err(|()| KafkaError::Canceled)?;
+        }
+
+        Ok(supervisor)
+    }
+
+    fn fetch_partitions(&self) -> Result<Vec<i32>, KafkaError> {
+        let consumer: BaseConsumer = self.config.create()?;
+        let metadata = consumer.fetch_metadata(Some(&self.topic), METADATA_TIMEOUT)?;
+        let partitions = metadata
+            .topics()
+            .iter()
+            .flat_map(|topic| topic.partitions())
+            .map(|partition| partition.id())
+            .collect();
+
+        Ok(partitions)
+    }
+
+    async fn consume(
+        self,
+        ctx: BastionContext,
+        partition: i32,
+        handlers: ChildrenRef,
+    ) -> Result<(), ()> {
+        let consumer: StreamConsumer<DefaultConsumerContext, BastionRuntime> =
+            self.config.create().map_err(|err| {
+                error!("KafkaConsumer({}): Couldn't connect: {}", self.topic, err);
+            })?;
+
+        // The partition is consumed from the last committed offset.
+        let mut assignment = TopicPartitionList::new();
+        assignment.add_partition(&self.topic, partition);
+        consumer.assign(&assignment).map_err(|err| {
+            error!(
+                "KafkaConsumer({}): Couldn't assign partition {}: {}",
+                self.topic, partition, err
+            );
+        })?;
+
+        debug!(
+            "KafkaConsumer({}): Consuming partition {}.",
+            self.topic, partition
+        );
+        let mut feeder = Feeder::new(handlers.id().clone());
+        let mut offsets = Offsets::default();
+        let mut records = consumer.stream();
+        loop {
+            let event = if offsets.in_flight() >= self.max_in_flight {
+                Event::Msg(ctx.recv().await?)
+            } else {
+                // The borrowed records must not be held across the
+                // other awaits.
+                futures::select! {
+                    record = records.next().fuse() => match record {
+                        Some(Ok(record)) => Event::Record(self.record(&ctx, &record)),
+                        Some(Err(err)) => {
+                            warn!("KafkaConsumer({}): Failed: {}", self.topic, err);
+                            return Err(());
+                        }
+                        None => return Ok(()),
+                    },
+                    msg = ctx.recv().fuse() => Event::Msg(msg?),
+                }
+            };
+
+            let msg = match event {
+                Event::Record(record) => {
+                    trace!(
+                        "KafkaConsumer({}): Delivering record {} of partition {}.",
+                        self.topic,
+                        record.offset,
+                        partition
+                    );
+                    offsets.deliver(record.offset);
+                    feeder.feed(record).await;
+                    continue;
+                }
+                Event::Msg(msg) => msg,
+            };
+
+            let ack = match msg.extract().0.downcast::<Ack>() {
+                Ok(ack) => ack,
+                Err(_) => continue,
+            };
+
+            if let Some(offset) = offsets.ack(ack.offset) {
+                // The committed offset is the one of the next record
+                // to consume.
+                let mut commit = TopicPartitionList::new();
+                commit
+                    .add_partition_offset(&self.topic, partition, Offset::Offset(offset + 1))
+                    .and_then(|()| consumer.commit(&commit, CommitMode::Async))
+                    .map_err(|err| {
+                        warn!("KafkaConsumer({}): Couldn't commit: {}", self.topic, err);
+                    })
+                    .ok();
+            }
+        }
+    }
+}
+
+impl KafkaConsumer {
+    fn record(&self, ctx: &BastionContext, record: &BorrowedMessage) -> KafkaRecord {
+        KafkaRecord {
+            topic: self.topic.clone(),
+            partition: record.partition(),
+            offset: record.offset(),
+            key: record.key().map(<[u8]>::to_vec),
+            payload: record.payload().map(<[u8]>::to_vec),
+            consumer: ctx.current().clone(),
+        }
+    }
+}
+
+impl KafkaRecord {
+    /// Returns the topic of the record.
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Returns the partition of the record.
+    pub fn partition(&self) -> i32 {
+        self.partition
+    }
+
+    /// Returns the offset of the record.
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// Returns the key of the record, if it has one.
+    pub fn key(&self) -> Option<&[u8]> {
+        self.key.as_deref()
+    }
+
+    /// Returns the payload of the record, if it has one.
+    pub fn payload(&self) -> Option<&[u8]> {
+        self.payload.as_deref()
+    }
+
+    /// Acknowledges that the record was handled, allowing its offset
+    /// to be committed once all the records preceding it were
+    /// acknowledged too.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(())` if the
+    /// consumer child of the record stopped (in which case the
+    /// record will be delivered again).
+    pub fn ack(&self) -> Result<(), ()> {
+        let ack = Ack {
+            offset: self.offset,
+        };
+        self.consumer.tell_anonymously(ack).map_err(|_| ())
+    }
+}
+
+impl KafkaProducer {
+    /// Creates a new producer connected to the given brokers.
+    ///
+    /// # Argument
+    ///
+    /// * `brokers` - The comma-separated addresses of the brokers.
+    pub fn new(brokers: &str) -> Result<Self, KafkaError> {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", brokers);
+        KafkaProducer::from_config(&config)
+    }
+
+    /// Creates a new producer using the given configuration.
+    ///
+    /// # Argument
+    ///
+    /// * `config` - The configuration of the producer.
+    pub fn from_config(config: &ClientConfig) -> Result<Self, KafkaError> {
+        let producer = config.create()?;
+        Ok(KafkaProducer {
+            producer,
+            timeout: Duration::from_secs(30),
+        })
+    }
+
+    /// Sets how long to wait for the producer's queue to have room
+    /// for a record before failing to send it.
+    ///
+    /// # Argument
+    ///
+    /// * `timeout` - The maximum time to wait.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Publishes a record to the given topic.
+    ///
+    /// This method returns the partition and offset of the record
+    /// if it succeeded, or an error otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic to publish the record to.
+    /// * `key` - The key of the record, if it has one.
+    /// * `payload` - The payload of the record.
+    pub async fn send(
+        &self,
+        topic: &str,
+        key: Option<&[u8]>,
+        payload: &[u8],
+    ) -> Result<(i32, i64), KafkaError> {
+        trace!("KafkaProducer: Sending record to: {}", topic);
+        let mut record = FutureRecord::<[u8], [u8]>::to(topic).payload(payload);
+        if let Some(key) = key {
+            record = record.key(key);
+        }
+
+        self.producer
+            .send(record, Timeout::After(self.timeout))
+            .await
+            .map_err(|(err, _)| err)
+    }
+}
+
+impl AsyncRuntime for BastionRuntime {
+    type Delay = Delay;
+
+    fn spawn<T>(task: T)
+    where
+        T: Future<Output = ()> + Send + 'static,
+    {
+        executor::spawn(task);
+    }
+
+    fn delay_for(duration: Duration) -> Self::Delay {
+        Delay::new(duration)
+    }
+}
+
+impl Offsets {
+    fn in_flight(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn deliver(&mut self, offset: i64) {
+        self.pending.insert(offset);
+    }
+
+    /// Acknowledges the offset, returning the highest offset that
+    /// can be committed if it changed.
+    fn ack(&mut self, offset: i64) -> Option<i64> {
+        if !self.pending.contains(&offset) {
+            return None;
+        }
+
+        self.acked.insert(offset);
+        let mut committed = None;
+        while let Some(&lowest) = self.pending.iter().next() {
+            if !self.acked.remove(&lowest) {
+                break;
+            }
+
+            self.pending.remove(&lowest);
+            committed = Some(lowest);
+        }
+
+        committed
+    }
+}
+
+impl Debug for KafkaConsumer {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("KafkaConsumer")
+            .field("topic", &self.topic)
+            .field("partitions", &self.partitions)
+            .field("max_in_flight", &self.max_in_flight)
+            .finish()
+    }
+}
+
+impl Debug for KafkaProducer {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("KafkaProducer")
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl Debug for BastionRuntime {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("BastionRuntime").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_acknowledged_offsets_in_order() {
+        let mut offsets = Offsets::default();
+        for offset in 10..14 {
+            offsets.deliver(offset);
+        }
+
+        assert_eq!(offsets.ack(11), None);
+        assert_eq!(offsets.ack(10), Some(11));
+        assert_eq!(offsets.in_flight(), 2);
+
+        assert_eq!(offsets.ack(13), None);
+        assert_eq!(offsets.ack(12), Some(13));
+        assert_eq!(offsets.in_flight(), 0);
+    }
+
+    #[test]
+    fn ignores_unknown_offsets() {
+        let mut offsets = Offsets::default();
+        offsets.deliver(1);
+        assert_eq!(offsets.ack(0), None);
+        assert_eq!(offsets.ack(1), Some(1));
+        assert_eq!(offsets.ack(1), None);
+    }
+}
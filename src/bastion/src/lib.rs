@@ -59,8 +59,9 @@
 #![cfg_attr(feature = "docs", feature(doc_cfg))]
 
 pub use self::bastion::Bastion;
-pub use self::callbacks::Callbacks;
+pub use self::callbacks::{Callbacks, ChildFailure, StateBag};
 pub use self::config::Config;
+pub use self::retry::retry;
 
 #[macro_use]
 mod macros;
@@ -68,24 +69,91 @@ mod macros;
 mod bastion;
 mod broadcast;
 mod callbacks;
+#[cfg(feature = "tokio-channels")]
+mod channel;
 mod child;
 mod config;
+mod deadlock;
+mod local;
+mod panics;
+#[cfg(feature = "quic")]
+mod quic;
+mod realtime;
+mod settings;
+mod source;
 mod system;
+#[cfg(feature = "tls")]
+mod tls;
 
+pub mod behavior;
+pub mod blocking_pool;
+pub mod bulkhead;
+pub mod cancellation;
+pub mod chaos;
 pub mod child_ref;
 pub mod children;
 pub mod children_ref;
+pub mod circuit_breaker;
+#[cfg(feature = "cluster")]
+pub mod cluster;
+#[cfg(feature = "remote")]
+pub mod codec;
 pub mod context;
+pub mod dead_letters;
+pub mod delivery;
+#[cfg(feature = "cluster")]
+pub mod discovery;
 pub mod dispatcher;
+pub mod entity_group;
 pub mod envelope;
+pub mod events;
 pub mod executor;
 #[cfg(not(target_os = "windows"))]
 pub mod io;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod local_storage;
+pub mod mailbox;
+#[cfg(feature = "management")]
+pub mod management;
 pub mod message;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod middleware;
+#[cfg(feature = "nats")]
+pub mod nats;
+#[cfg(not(target_os = "windows"))]
+pub mod net;
 pub mod path;
+#[cfg(feature = "persistence")]
+pub mod persistence;
+pub mod rate_limit;
+#[cfg(feature = "redis-mailbox")]
+pub mod redis_mailbox;
+pub mod reload;
+#[cfg(feature = "remote")]
+pub mod remote;
 #[cfg(feature = "scaling")]
 pub mod resizer;
+pub mod retry;
+pub mod rt;
+pub mod scope;
+pub mod selection;
+#[cfg(feature = "tower")]
+pub mod service;
+#[cfg(feature = "sharding")]
+pub mod sharding;
+#[cfg(all(feature = "signals", not(target_os = "windows")))]
+pub mod signals;
+#[cfg(feature = "cluster")]
+pub mod singleton;
+pub mod state;
 pub mod supervisor;
+pub mod testing;
+pub mod tree;
+pub mod watch;
+#[cfg(all(feature = "websocket", not(target_os = "windows")))]
+pub mod websocket;
 
 pub mod errors;
 
@@ -98,30 +166,106 @@ distributed_api! {
 /// Prelude of Bastion
 pub mod prelude {
     pub use crate::bastion::Bastion;
-    pub use crate::callbacks::Callbacks;
+    pub use crate::behavior::Behavior;
+    pub use crate::blocking_pool::BlockingPoolConfig;
+    pub use crate::bulkhead::Bulkhead;
+    pub use crate::callbacks::{Callbacks, ChildFailure, StateBag};
+    pub use crate::cancellation::{CancellationToken, Cancelled};
+    pub use crate::chaos::{ChaosConfig, ChaosStats};
     pub use crate::child_ref::ChildRef;
     pub use crate::children::Children;
-    pub use crate::children_ref::ChildrenRef;
+    pub use crate::children_ref::{AskOptions, ChildrenRef, TypedChildrenRef};
+    pub use crate::circuit_breaker::{CircuitBreaker, CircuitState};
+    #[cfg(feature = "cluster")]
+    pub use crate::cluster::{
+        Cluster, ClusterConfig, LeaderElection, LeadershipEvent, Member, MemberEvent, MemberStatus,
+        SplitBrainStrategy,
+    };
+    #[cfg(feature = "remote")]
+    pub use crate::codec::{BincodeCodec, JsonCodec, MessageCodec, RemoteMessage};
     pub use crate::config::Config;
     pub use crate::context::{BastionContext, BastionId, NIL_ID};
+    pub use crate::dead_letters::{DeadLetter, DeadLetters, DeadLettersStream};
+    pub use crate::delivery::{Delivery, DeliveryConfig, DeliveryId};
+    #[cfg(feature = "discovery-kubernetes")]
+    pub use crate::discovery::KubernetesDiscovery;
+    #[cfg(feature = "discovery-mdns")]
+    pub use crate::discovery::MdnsDiscovery;
+    #[cfg(feature = "cluster")]
+    pub use crate::discovery::{Discovery, DnsDiscovery};
     pub use crate::dispatcher::{
         BroadcastTarget, DefaultDispatcherHandler, Dispatcher, DispatcherHandler, DispatcherMap,
         DispatcherType, NotificationType,
     };
-    pub use crate::envelope::{RefAddr, SignedMessage};
+    pub use crate::entity_group::EntityGroup;
+    pub use crate::envelope::{Headers, RefAddr, SignedMessage};
     pub use crate::errors::*;
+    pub use crate::events::{SupervisionEvent, SupervisionEvents};
+    pub use crate::executor::{
+        Affinity, AgnosticExecutor, BastionExecutor, Executor, ProcPriority,
+    };
     #[cfg(not(target_os = "windows"))]
     pub use crate::io::*;
-    pub use crate::message::{Answer, AnswerSender, Message, Msg};
+    #[cfg(feature = "kafka")]
+    pub use crate::kafka::{KafkaConsumer, KafkaProducer, KafkaRecord};
+    pub use crate::local_storage::LocalStorage;
+    pub use crate::mailbox::{MailboxConfig, OverflowStrategy, Priority};
+    #[cfg(feature = "management")]
+    pub use crate::management::ManagementConfig;
+    pub use crate::message::{
+        Answer, AnswerSender, AnswerStream, Message, Msg, Request, Responder,
+    };
+    pub use crate::middleware::{Middleware, Next};
     pub use crate::msg;
+    #[cfg(feature = "nats")]
+    pub use crate::nats::{Nats, NatsMessage};
+    #[cfg(not(target_os = "windows"))]
+    pub use crate::net::{Datagram, TcpConnection, TcpServer, UdpEndpoint};
     pub use crate::path::{BastionPath, BastionPathElement};
+    #[cfg(feature = "persistence-sled")]
+    pub use crate::persistence::SledJournal;
+    #[cfg(feature = "persistence")]
+    pub use crate::persistence::{Journal, MemoryJournal, Persistent, PersistentChild};
+    pub use crate::rate_limit::RateLimit;
+    #[cfg(feature = "redis-mailbox")]
+    pub use crate::redis_mailbox::RedisMailbox;
+    pub use crate::reload::{LogLevelLayer, ReloadReport};
+    #[cfg(feature = "compression")]
+    pub use crate::remote::Compression;
+    #[cfg(feature = "quic")]
+    pub use crate::remote::Transport;
+    #[cfg(feature = "remote")]
+    pub use crate::remote::{NodeId, RemoteChildRef, RemoteNode, RemotingConfig};
     #[cfg(feature = "scaling")]
-    pub use crate::resizer::{OptimalSizeExploringResizer, UpperBound, UpscaleStrategy};
+    pub use crate::resizer::{
+        LatencyTargetPolicy, OptimalSizeExploringResizer, QueueLengthPolicy, ScalingPolicy,
+        ScalingStats, UpperBound, UpscaleStrategy,
+    };
+    pub use crate::retry::RetryPolicy;
+    pub use crate::rt::Budget;
+    pub use crate::scope::{Nursery, TaskHandle};
+    pub use crate::selection::Selection;
+    #[cfg(feature = "tower")]
+    pub use crate::service::GroupService;
+    #[cfg(feature = "sharding")]
+    pub use crate::sharding::{Entity, ShardRegion, Sharding, ShardingConfig};
+    #[cfg(all(feature = "signals", not(target_os = "windows")))]
+    pub use crate::signals::{ReloadSignal, SignalConfig, Signals};
+    #[cfg(feature = "cluster")]
+    pub use crate::singleton::{ClusterSingleton, SingletonConfig};
+    pub use crate::state::StateRegistry;
     pub use crate::supervisor::{
-        ActorRestartStrategy, RestartPolicy, RestartStrategy, SupervisionStrategy, Supervisor,
-        SupervisorRef,
+        ActorRestartStrategy, Directive, IntensityDecision, RestartPolicy, RestartStrategy,
+        SupervisedHandle, SupervisionStrategy, Supervisor, SupervisorRef,
+    };
+    pub use crate::tree::{
+        ChildSnapshot, ChildStatus, ChildrenSnapshot, SupervisorSnapshot, TreeSnapshot,
     };
+    pub use crate::watch::{ExitSignal, StopReason, Terminated};
+    #[cfg(all(feature = "websocket", not(target_os = "windows")))]
+    pub use crate::websocket::{WebSocket, WebSocketClient, WebSocketServer, WsFrame};
     pub use crate::{answer, blocking, children, run, spawn, supervisor};
+    pub use bastion_executor::timer::TimerHandle;
 
     distributed_api! {
         // pub use crate::dist_messages::*;
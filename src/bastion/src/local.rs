@@ -0,0 +1,103 @@
+//!
+//! Runs the futures of the children created using
+//! `Children::with_exec_local`, which don't need to be `Send`.
+//!
+//! Every children group using a local exec closure gets its own
+//! thread, running a single-threaded executor. The future of each
+//! element is created and polled on this thread, while the element
+//! polls a proxy future waiting for its result, so that it is
+//! supervised (and receives its messages) like any other child.
+use crate::errors::ChildError;
+use crate::panics;
+use futures::channel::{mpsc, oneshot};
+use futures::executor::LocalPool;
+use futures::future::{self, Either, LocalBoxFuture};
+use futures::prelude::*;
+use futures::task::LocalSpawnExt;
+use std::pin::Pin;
+use std::task::Poll;
+use std::thread;
+use tracing::{debug, trace};
+
+type Spawn = Box<dyn FnOnce() -> LocalBoxFuture<'static, ()> + Send>;
+
+#[derive(Debug, Clone)]
+// A thread running the local futures of a children group, until
+// the group is dropped.
+pub(crate) struct LocalThread {
+    sender: mpsc::UnboundedSender<Spawn>,
+}
+
+impl LocalThread {
+    pub(crate) fn new(name: String) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded::<Spawn>();
+        let thread_name = name.clone();
+        thread::Builder::new()
+            .name(thread_name)
+            .spawn(move || {
+                debug!("LocalThread({}): Started.", name);
+                let mut pool = LocalPool::new();
+                let spawner = pool.spawner();
+                pool.run_until(async move {
+                    while let Some(spawn) = receiver.next().await {
+                        trace!("LocalThread({}): Spawning local future.", name);
+                        spawner.spawn_local(spawn()).ok();
+                    }
+                });
+                debug!("LocalThread({}): Stopped.", name);
+            })
+            .expect("cannot start the local thread");
+
+        LocalThread { sender }
+    }
+
+    /// Creates a future using `make` and polls it on the thread,
+    /// returning a future resolving to its output (or to the panic
+    /// of `make` or of the future). Dropping the returned future
+    /// drops the local one.
+    pub(crate) fn run<M, F, R>(&self, make: M) -> impl Future<Output = Result<R, ChildError>> + Send
+    where
+        M: FnOnce() -> F + Send + 'static,
+        F: Future<Output = R> + 'static,
+        R: Send + 'static,
+    {
+        let (mut tx, rx) = oneshot::channel();
+        let spawn: Spawn = Box::new(move || {
+            Box::pin(async move {
+                // The panics of the future are caught the same way
+                // the element would catch them.
+                let mut fut = match panics::catch_unwind(make) {
+                    Ok(fut) => Box::pin(fut),
+                    Err(err) => {
+                        tx.send(Err(err)).ok();
+                        return;
+                    }
+                };
+                let run = future::poll_fn(move |ctx| {
+                    match panics::catch_unwind(|| Pin::new(&mut fut).poll(ctx)) {
+                        Ok(Poll::Ready(res)) => Poll::Ready(Ok(res)),
+                        Ok(Poll::Pending) => Poll::Pending,
+                        Err(err) => Poll::Ready(Err(err)),
+                    }
+                });
+
+                // The future is dropped once the element stopped
+                // waiting for it.
+                let res = match future::select(Box::pin(run), tx.cancellation()).await {
+                    Either::Left((res, _)) => res,
+                    Either::Right(_) => return,
+                };
+                tx.send(res).ok();
+            })
+        });
+
+        let spawned = self.sender.unbounded_send(spawn).is_ok();
+        async move {
+            if !spawned {
+                return Err(ChildError::Failed);
+            }
+
+            rx.await.unwrap_or(Err(ChildError::Failed))
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//!
+//! Values stored by a child for its own use.
+//!
+//! The [`LocalStorage`] of a child (see
+//! [`BastionContext::local_storage`]) allows the helpers its future
+//! uses to store the state they need for this child (e.g. a cache
+//! or a counter) without it being passed to the child's closure.
+//!
+//! Unlike its [`StateBag`], the local storage of a child is cleared
+//! when it is restarted, except for the values inserted using
+//! [`LocalStorage::insert_persistent`].
+//!
+//! [`LocalStorage`]: struct.LocalStorage.html
+//! [`BastionContext::local_storage`]: ../context/struct.BastionContext.html#method.local_storage
+//! [`StateBag`]: ../struct.StateBag.html
+//! [`LocalStorage::insert_persistent`]: struct.LocalStorage.html#method.insert_persistent
+use fxhash::FxHashMap;
+use std::any::{Any, TypeId};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default, Clone)]
+/// A set of values, indexed by their type, only available to an
+/// element of a children group.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// #[derive(Default)]
+/// struct Handled(u64);
+///
+/// // A helper counting the messages handled by the child using it.
+/// fn count_handled(ctx: &BastionContext) -> u64 {
+///     ctx.local_storage()
+///         .with_default(|handled: &mut Handled| {
+///             handled.0 += 1;
+///             handled.0
+///         })
+/// }
+///
+/// Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| async move {
+///         loop {
+///             let _: SignedMessage = ctx.recv().await?;
+///             println!("Handled {} messages.", count_handled(&ctx));
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub struct LocalStorage {
+    values: Arc<Mutex<FxHashMap<TypeId, Entry>>>,
+}
+
+struct Entry {
+    value: Box<dyn Any + Send>,
+    // Whether the value is kept when the child is restarted.
+    persistent: bool,
+}
+
+impl LocalStorage {
+    /// Creates a new empty `LocalStorage`.
+    pub fn new() -> Self {
+        LocalStorage::default()
+    }
+
+    fn insert_entry<T: Send + 'static>(&self, value: T, persistent: bool) -> Option<T> {
+        let entry = Entry {
+            value: Box::new(value),
+            persistent,
+        };
+
+        // FIXME: panics
+        self.values
+            .lock()
+            .unwrap()
+            .insert(TypeId::of::<T>(), entry)
+            .and_then(|entry| entry.value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Stores a value, which is cleared when the child is restarted,
+    /// returning the value of the same type that it replaced, if any.
+    ///
+    /// # Argument
+    ///
+    /// * `value` - The value to store.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let storage = LocalStorage::new();
+    ///
+    /// assert_eq!(storage.insert(1u64), None);
+    /// assert_eq!(storage.insert(2u64), Some(1));
+    /// ```
+    pub fn insert<T: Send + 'static>(&self, value: T) -> Option<T> {
+        self.insert_entry(value, false)
+    }
+
+    /// Stores a value, which is kept when the child is restarted,
+    /// returning the value of the same type that it replaced, if any.
+    ///
+    /// # Argument
+    ///
+    /// * `value` - The value to store.
+    pub fn insert_persistent<T: Send + 'static>(&self, value: T) -> Option<T> {
+        self.insert_entry(value, true)
+    }
+
+    /// Returns a clone of the stored value of type `T`, if any.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let storage = LocalStorage::new();
+    /// storage.insert("cache".to_string());
+    ///
+    /// assert_eq!(storage.get::<String>(), Some("cache".to_string()));
+    /// assert_eq!(storage.get::<u64>(), None);
+    /// ```
+    pub fn get<T: Clone + Send + 'static>(&self) -> Option<T> {
+        // FIXME: panics
+        self.values
+            .lock()
+            .unwrap()
+            .get(&TypeId::of::<T>())
+            .and_then(|entry| entry.value.downcast_ref::<T>())
+            .cloned()
+    }
+
+    /// Calls the closure with the stored value of type `T`, returning
+    /// its result, or `None` if no such value is stored.
+    ///
+    /// Note that the storage is locked while the closure runs, which
+    /// thus shouldn't use it.
+    ///
+    /// # Argument
+    ///
+    /// * `f` - The closure, given the stored value.
+    pub fn with<T, F, R>(&self, f: F) -> Option<R>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut T) -> R,
+    {
+        // FIXME: panics
+        let mut values = self.values.lock().unwrap();
+        let value = values
+            .get_mut(&TypeId::of::<T>())?
+            .value
+            .downcast_mut::<T>()?;
+
+        Some(f(value))
+    }
+
+    /// Calls the closure with the stored value of type `T` (storing
+    /// its default value first, if none is stored), returning its
+    /// result.
+    ///
+    /// Note that the storage is locked while the closure runs, which
+    /// thus shouldn't use it.
+    ///
+    /// # Argument
+    ///
+    /// * `f` - The closure, given the stored value.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let storage = LocalStorage::new();
+    ///
+    /// assert_eq!(storage.with_default(|count: &mut u64| { *count += 1; *count }), 1);
+    /// assert_eq!(storage.with_default(|count: &mut u64| { *count += 1; *count }), 2);
+    /// ```
+    pub fn with_default<T, F, R>(&self, f: F) -> R
+    where
+        T: Default + Send + 'static,
+        F: FnOnce(&mut T) -> R,
+    {
+        // FIXME: panics
+        let mut values = self.values.lock().unwrap();
+        let entry = values.entry(TypeId::of::<T>()).or_insert_with(|| Entry {
+            value: Box::new(T::default()),
+            persistent: false,
+        });
+
+        // The entry is indexed by the type of its value.
+        f(entry.value.downcast_mut::<T>().unwrap())
+    }
+
+    /// Removes the stored value of type `T` and returns it, if any.
+    pub fn take<T: Send + 'static>(&self) -> Option<T> {
+        // FIXME: panics
+        self.values
+            .lock()
+            .unwrap()
+            .remove(&TypeId::of::<T>())
+            .and_then(|entry| entry.value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Returns whether a value of type `T` is stored.
+    pub fn contains<T: Send + 'static>(&self) -> bool {
+        // FIXME: panics
+        self.values.lock().unwrap().contains_key(&TypeId::of::<T>())
+    }
+
+    /// Removes the values which weren't inserted using
+    /// [`insert_persistent`], before the child is restarted.
+    ///
+    /// [`insert_persistent`]: #method.insert_persistent
+    pub(crate) fn clear_transient(&self) {
+        // FIXME: panics
+        self.values
+            .lock()
+            .unwrap()
+            .retain(|_, entry| entry.persistent);
+    }
+}
+
+impl Debug for LocalStorage {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("LocalStorage")
+            .field("len", &self.values.lock().unwrap().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_persistent_values_only() {
+        let storage = LocalStorage::new();
+        storage.insert(1u32);
+        storage.insert_persistent("persistent");
+        assert_eq!(storage.with(|value: &mut u32| *value += 1), Some(()));
+        assert_eq!(storage.with(|value: &mut u64| *value += 1), None);
+
+        storage.clear_transient();
+        assert!(!storage.contains::<u32>());
+        assert_eq!(storage.get::<&str>(), Some("persistent"));
+
+        // Replacing a value keeps it persistent only if inserted as such.
+        storage.insert("transient");
+        storage.clear_transient();
+        assert!(!storage.contains::<&str>());
+    }
+}
@@ -0,0 +1,754 @@
+//!
+//! Mailboxes are the queues in which the messages sent to an
+//! element of a children group are stored until they get
+//! received by the element's future.
+//!
+//! Mailboxes are unbounded by default, but can be configured
+//! with a capacity and an [`OverflowStrategy`] for each children
+//! group using [`Children::with_mailbox`].
+//!
+//! Each mailbox is split into three lanes, one for each
+//! [`Priority`], which are consumed in priority order.
+//!
+//! Messages can also be stashed by the element (e.g. while it is
+//! initializing) using [`BastionContext::stash`], to be put back at
+//! the front of the mailbox later on using
+//! [`BastionContext::unstash_all`].
+//!
+//! With the `redis-mailbox` feature, the messages can also be kept
+//! in a Redis stream, so that they survive the restarts of the
+//! process (see [`MailboxConfig::redis`]).
+//!
+//! [`OverflowStrategy`]: mailbox/enum.OverflowStrategy.html
+//! [`Children::with_mailbox`]: children/struct.Children.html#method.with_mailbox
+//! [`Priority`]: mailbox/enum.Priority.html
+//! [`BastionContext::stash`]: context/struct.BastionContext.html#method.stash
+//! [`BastionContext::unstash_all`]: context/struct.BastionContext.html#method.unstash_all
+//! [`MailboxConfig::redis`]: mailbox/struct.MailboxConfig.html#method.redis
+use crate::dead_letters;
+use crate::envelope::SignedMessage;
+use crate::path::BastionPath;
+#[cfg(feature = "redis-mailbox")]
+use crate::redis_mailbox::{RedisConsumer, RedisMailbox};
+use crossbeam_queue::SegQueue;
+use lazy_static::lazy_static;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll, Waker};
+#[cfg(feature = "redis-mailbox")]
+use std::time::Duration;
+use tracing::trace;
+
+lazy_static! {
+    // The mailbox configured using `Config::with_default_mailbox`,
+    // if any.
+    static ref DEFAULT: RwLock<Option<MailboxConfig>> = RwLock::new(None);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The strategy used by a mailbox when a message is sent to it
+/// while it already contains as many messages as its capacity
+/// allows.
+///
+/// The default strategy is [`OverflowStrategy::DropNewest`].
+///
+/// [`OverflowStrategy::DropNewest`]: #variant.DropNewest
+pub enum OverflowStrategy {
+    /// The message that was sent is silently dropped.
+    DropNewest,
+    /// The oldest message of the mailbox is dropped to
+    /// make room for the message that was sent.
+    ///
+    /// The messages that were sent but not yet received by an
+    /// element busy polling (e.g. blocking in a handler) can't be
+    /// dropped until it receives them: it then keeps the newest
+    /// ones, but its mailbox exceeds its capacity in the meantime.
+    DropOldest,
+    /// The sender is asked to wait until the mailbox has room
+    /// for the message. Non-async senders (like
+    /// [`ChildRef::tell_anonymously`]) will get their message
+    /// back, while async senders (like
+    /// [`ChildRef::tell_anonymously_async`]) will wait.
+    ///
+    /// [`ChildRef::tell_anonymously`]: ../child_ref/struct.ChildRef.html#method.tell_anonymously
+    /// [`ChildRef::tell_anonymously_async`]: ../child_ref/struct.ChildRef.html#method.tell_anonymously_async
+    Backpressure,
+    /// The sender gets its message back.
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// The priority of a message, defining in which lane of its
+/// recipient's mailbox it will be pushed.
+///
+/// Messages with a higher priority are received before the ones
+/// with a lower priority, whatever the order they were sent in.
+/// Messages with the same priority are received in the order
+/// they were sent in.
+///
+/// The default priority is [`Priority::Normal`], which is
+/// the one used by all the methods that don't allow to specify
+/// a priority.
+///
+/// [`Priority::Normal`]: #variant.Normal
+pub enum Priority {
+    /// The lane used for control messages that need to be
+    /// handled before any other.
+    High,
+    /// The lane used by default.
+    Normal,
+    /// The lane used for messages that can wait until all
+    /// the other ones were handled.
+    Low,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The configuration of the mailboxes of a children group's
+/// elements.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// let config = MailboxConfig::bounded(64)
+///     .with_overflow(OverflowStrategy::Backpressure);
+///
+/// Bastion::children(|children| {
+///     children
+///         .with_mailbox(config)
+///         .with_exec(|ctx| {
+///             async move {
+///                 // ...
+///                 # Ok(())
+///             }
+///         })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub struct MailboxConfig {
+    capacity: Option<usize>,
+    overflow: OverflowStrategy,
+    stash_capacity: Option<usize>,
+    #[cfg(feature = "redis-mailbox")]
+    redis: Option<RedisMailbox>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Mailbox {
+    config: MailboxConfig,
+    // The capacity of the mailbox if it is bounded, which can be
+    // changed while its element is running.
+    capacity: AtomicUsize,
+    // The lanes of the mailbox, ordered by priority (see
+    // `Priority::lane`).
+    lanes: [SegQueue<SignedMessage>; 3],
+    // The messages that were unstashed, which are received
+    // before the ones of the lanes.
+    unstashed: Mutex<VecDeque<SignedMessage>>,
+    // The number of messages in `unstashed`, checked before locking
+    // it (which is then only needed after the messages were
+    // unstashed).
+    unstashed_len: AtomicUsize,
+    // The messages that were stashed, in the order they were
+    // stashed in.
+    stash: Mutex<Vec<SignedMessage>>,
+    // The number of user messages that were accepted by the
+    // mailbox's senders and that haven't been received yet
+    // (including the ones that are still in the channel).
+    reserved: AtomicUsize,
+    // The wakers of the senders waiting for the mailbox
+    // to have room for their message.
+    waiters: SegQueue<Waker>,
+    // The path of the element the mailbox belongs to, which the
+    // messages dropped because it was full are sent to as dead
+    // letters (once it was attached to the element's broadcast).
+    path: RwLock<Option<Arc<BastionPath>>>,
+    // The consumer of the Redis stream the messages are kept in,
+    // if any.
+    #[cfg(feature = "redis-mailbox")]
+    redis: Option<RedisConsumer>,
+}
+
+impl MailboxConfig {
+    /// Creates a new configuration for mailboxes without
+    /// any capacity limit (this is the default).
+    pub fn unbounded() -> Self {
+        MailboxConfig {
+            capacity: None,
+            overflow: OverflowStrategy::DropNewest,
+            stash_capacity: None,
+            #[cfg(feature = "redis-mailbox")]
+            redis: None,
+        }
+    }
+
+    /// Creates a new configuration for unbounded mailboxes whose
+    /// messages are kept in a Redis stream, so that they survive
+    /// the restarts of the process (see the documentation of the
+    /// [`redis_mailbox`] module for the guarantees it offers).
+    ///
+    /// # Argument
+    ///
+    /// * `mailbox` - The [`RedisMailbox`] the messages are kept in.
+    ///
+    /// [`redis_mailbox`]: ../redis_mailbox/index.html
+    /// [`RedisMailbox`]: ../redis_mailbox/struct.RedisMailbox.html
+    #[cfg(feature = "redis-mailbox")]
+    pub fn redis(mailbox: RedisMailbox) -> Self {
+        MailboxConfig {
+            redis: Some(mailbox),
+            ..MailboxConfig::unbounded()
+        }
+    }
+
+    /// Creates a new configuration for mailboxes that can hold
+    /// up to `capacity` messages, using [`OverflowStrategy::DropNewest`]
+    /// when they are full.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum amount of messages a mailbox
+    ///     can hold.
+    ///
+    /// [`OverflowStrategy::DropNewest`]: enum.OverflowStrategy.html#variant.DropNewest
+    pub fn bounded(capacity: usize) -> Self {
+        MailboxConfig {
+            capacity: Some(capacity),
+            overflow: OverflowStrategy::DropNewest,
+            stash_capacity: None,
+            #[cfg(feature = "redis-mailbox")]
+            redis: None,
+        }
+    }
+
+    /// Sets the strategy used when a message is sent to a full
+    /// mailbox.
+    ///
+    /// # Arguments
+    ///
+    /// * `overflow` - The [`OverflowStrategy`] to use.
+    ///
+    /// [`OverflowStrategy`]: enum.OverflowStrategy.html
+    pub fn with_overflow(mut self, overflow: OverflowStrategy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Sets the maximum amount of messages that can be stashed
+    /// at once using [`BastionContext::stash`]. By default, the
+    /// stash is unbounded.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum amount of stashed messages.
+    ///
+    /// [`BastionContext::stash`]: ../context/struct.BastionContext.html#method.stash
+    pub fn with_stash_capacity(mut self, capacity: usize) -> Self {
+        self.stash_capacity = Some(capacity);
+        self
+    }
+
+    /// Returns the maximum amount of messages a mailbox can
+    /// hold, or `None` if it is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Changes the capacity of a bounded configuration, leaving an
+    /// unbounded one unchanged.
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        if self.capacity.is_some() {
+            self.capacity = Some(capacity);
+        }
+    }
+
+    /// Returns the strategy used when a message is sent to
+    /// a full mailbox.
+    pub fn overflow(&self) -> &OverflowStrategy {
+        &self.overflow
+    }
+
+    /// Returns the maximum amount of messages that can be
+    /// stashed at once, or `None` if the stash is unbounded.
+    pub fn stash_capacity(&self) -> Option<usize> {
+        self.stash_capacity
+    }
+
+    /// Returns the Redis stream the messages are kept in, if any.
+    #[cfg(feature = "redis-mailbox")]
+    pub fn redis_mailbox(&self) -> Option<&RedisMailbox> {
+        self.redis.as_ref()
+    }
+}
+
+impl Priority {
+    const LANES: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+    fn lane(self) -> usize {
+        match self {
+            Priority::High => 0,
+            Priority::Normal => 1,
+            Priority::Low => 2,
+        }
+    }
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Priority::Normal
+    }
+}
+
+impl Default for MailboxConfig {
+    fn default() -> Self {
+        MailboxConfig::unbounded()
+    }
+}
+
+pub(crate) fn set_default_config(config: Option<MailboxConfig>) {
+    // FIXME: panics
+    *DEFAULT.write().unwrap() = config;
+}
+
+/// Returns the configuration of the mailboxes of the children groups
+/// which don't set their own.
+pub(crate) fn default_config() -> MailboxConfig {
+    // FIXME: panics
+    DEFAULT.read().unwrap().clone().unwrap_or_default()
+}
+
+impl Mailbox {
+    pub(crate) fn new(config: MailboxConfig) -> Self {
+        #[cfg(feature = "redis-mailbox")]
+        let redis = config.redis.clone().map(RedisConsumer::new);
+        Mailbox {
+            capacity: AtomicUsize::new(config.capacity.unwrap_or(usize::MAX)),
+            config,
+            lanes: [SegQueue::new(), SegQueue::new(), SegQueue::new()],
+            unstashed: Mutex::new(VecDeque::new()),
+            unstashed_len: AtomicUsize::new(0),
+            stash: Mutex::new(Vec::new()),
+            reserved: AtomicUsize::new(0),
+            waiters: SegQueue::new(),
+            path: RwLock::new(None),
+            #[cfg(feature = "redis-mailbox")]
+            redis,
+        }
+    }
+
+    /// Sets the path of the element the mailbox belongs to.
+    pub(crate) fn attach(&self, path: Arc<BastionPath>) {
+        *self.path.write().unwrap() = Some(path);
+    }
+
+    /// Returns the path of the element the mailbox belongs to, if
+    /// it was attached to its broadcast.
+    pub(crate) fn path(&self) -> Option<Arc<BastionPath>> {
+        self.path.read().unwrap().clone()
+    }
+
+    pub(crate) fn config(&self) -> &MailboxConfig {
+        &self.config
+    }
+
+    pub(crate) fn is_bounded(&self) -> bool {
+        self.config.capacity.is_some()
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        if self.is_bounded() {
+            Some(self.capacity.load(Ordering::SeqCst))
+        } else {
+            None
+        }
+    }
+
+    /// Changes the capacity of the mailbox if it is bounded (an
+    /// unbounded mailbox stays unbounded). The messages it already
+    /// holds are kept even if there are more of them than the new
+    /// capacity.
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        if !self.is_bounded() {
+            return;
+        }
+
+        trace!("Mailbox: Setting capacity: {}", capacity);
+        self.capacity.store(capacity, Ordering::SeqCst);
+        // The senders waiting for room might now have some.
+        while let Some(waker) = self.waiters.pop() {
+            waker.wake();
+        }
+    }
+
+    /// Returns whether a sender should wait for the mailbox
+    /// to have room before sending its message.
+    pub(crate) fn applies_backpressure(&self) -> bool {
+        self.is_bounded() && self.config.overflow == OverflowStrategy::Backpressure
+    }
+
+    /// Returns whether the mailbox can accept a new message
+    /// without overflowing its capacity.
+    pub(crate) fn has_room(&self) -> bool {
+        match self.capacity() {
+            Some(capacity) => self.reserved.load(Ordering::SeqCst) < capacity,
+            None => true,
+        }
+    }
+
+    /// Tries to reserve room in the mailbox for a message that
+    /// is about to be sent, returning `false` if the message
+    /// should be rejected or dropped.
+    pub(crate) fn try_reserve(&self) -> bool {
+        let capacity = match self.capacity() {
+            Some(capacity) => capacity,
+            None => return true,
+        };
+
+        // The oldest messages are dropped to make room for the new
+        // ones, which are always accepted. The ones in the channel
+        // are dropped once pushed, if there still isn't any room.
+        if self.config.overflow == OverflowStrategy::DropOldest {
+            if self.reserved.load(Ordering::SeqCst) >= capacity {
+                self.drop_oldest();
+            }
+
+            self.reserved.fetch_add(1, Ordering::SeqCst);
+            return true;
+        }
+
+        let mut reserved = self.reserved.load(Ordering::SeqCst);
+        loop {
+            if reserved >= capacity {
+                return false;
+            }
+
+            match self.reserved.compare_exchange_weak(
+                reserved,
+                reserved + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return true,
+                Err(actual) => reserved = actual,
+            }
+        }
+    }
+
+    /// Reserves room in the mailbox for a message regardless of
+    /// its capacity.
+    pub(crate) fn force_reserve(&self) {
+        if self.is_bounded() {
+            self.reserved.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Releases the room reserved for a message, either because
+    /// it was received, dropped or because it couldn't be sent.
+    pub(crate) fn release(&self) {
+        if !self.is_bounded() {
+            return;
+        }
+
+        self.reserved
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |reserved| {
+                Some(reserved.saturating_sub(1))
+            })
+            .ok();
+
+        while let Some(waker) = self.waiters.pop() {
+            waker.wake();
+        }
+    }
+
+    /// Resets the amount of reserved room to the amount of
+    /// messages currently in the mailbox, forgetting about
+    /// the messages that were lost in a channel that isn't
+    /// used anymore (e.g. after the element was restarted).
+    pub(crate) fn resync(&self) {
+        self.reserved.store(self.len(), Ordering::SeqCst);
+
+        // The restarted element receives the messages it was
+        // handling again.
+        #[cfg(feature = "redis-mailbox")]
+        if let Some(redis) = &self.redis {
+            redis.recover();
+        }
+    }
+
+    /// Returns how often an element waiting for a message should
+    /// look for one, if its messages aren't all pushed to it.
+    #[cfg(feature = "redis-mailbox")]
+    pub(crate) fn poll_interval(&self) -> Option<Duration> {
+        self.redis.as_ref().map(RedisConsumer::poll_interval)
+    }
+
+    pub(crate) fn poll_ready(&self, ctx: &mut Context) -> Poll<()> {
+        if !self.applies_backpressure() || self.has_room() {
+            return Poll::Ready(());
+        }
+
+        self.waiters.push(ctx.waker().clone());
+        // The mailbox might have been emptied between the first
+        // check and the waker's registration.
+        if self.has_room() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    pub(crate) fn push(&self, msg: SignedMessage, priority: Priority) {
+        #[cfg(feature = "redis-mailbox")]
+        if let Some(redis) = &self.redis {
+            if redis.append(&msg) {
+                self.release();
+                return;
+            }
+        }
+
+        if let (Some(capacity), OverflowStrategy::DropOldest) =
+            (self.capacity(), &self.config.overflow)
+        {
+            while self.len() >= capacity {
+                if !self.drop_oldest() {
+                    break;
+                }
+            }
+        }
+
+        self.lanes[priority.lane()].push(msg)
+    }
+
+    /// Drops the oldest message of the lowest priority lane that
+    /// isn't empty, sending it to the dead letters and returning
+    /// whether there was one.
+    fn drop_oldest(&self) -> bool {
+        let dropped = Priority::LANES
+            .iter()
+            .rev()
+            .find_map(|priority| self.lanes[priority.lane()].pop());
+        let dropped = match dropped {
+            Some(dropped) => dropped,
+            None => return false,
+        };
+
+        self.release();
+        match self.path() {
+            Some(path) => {
+                let (msg, sign) = dropped.extract();
+                dead_letters::publish_message(path, msg, sign);
+            }
+            None => trace!("Mailbox: Dropping oldest message: {:?}", dropped),
+        }
+
+        true
+    }
+
+    pub(crate) fn pop(&self) -> Option<SignedMessage> {
+        // The message popped before was handled.
+        #[cfg(feature = "redis-mailbox")]
+        if let Some(redis) = &self.redis {
+            redis.ack_handled();
+        }
+
+        // The room of unstashed messages was already released
+        // when they were first received.
+        if self.unstashed_len.load(Ordering::SeqCst) > 0 {
+            let mut unstashed = self.unstashed.lock().unwrap();
+            if let Some(msg) = unstashed.pop_front() {
+                self.unstashed_len.fetch_sub(1, Ordering::SeqCst);
+                return Some(msg);
+            }
+        }
+
+        let msg = Priority::LANES
+            .iter()
+            .find_map(|priority| self.lanes[priority.lane()].pop());
+        match msg {
+            Some(msg) => {
+                self.release();
+                Some(msg)
+            }
+            #[cfg(feature = "redis-mailbox")]
+            None => self.redis.as_ref()?.pop(),
+            #[cfg(not(feature = "redis-mailbox"))]
+            None => None,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        let unstashed = self.unstashed_len.load(Ordering::SeqCst);
+        unstashed + self.lanes.iter().map(SegQueue::len).sum::<usize>()
+    }
+
+    /// Stashes the message, returning it back if the stash is
+    /// full.
+    pub(crate) fn stash(&self, msg: SignedMessage) -> Result<(), SignedMessage> {
+        let mut stash = self.stash.lock().unwrap();
+        match self.config.stash_capacity {
+            Some(capacity) if stash.len() >= capacity => Err(msg),
+            _ => {
+                stash.push(msg);
+                Ok(())
+            }
+        }
+    }
+
+    /// Puts all the stashed messages back at the front of the
+    /// mailbox, in the order they were stashed in, returning how
+    /// many of them there were.
+    pub(crate) fn unstash_all(&self) -> usize {
+        let stashed = self.stash.lock().unwrap().drain(..).collect::<Vec<_>>();
+        let count = stashed.len();
+
+        let mut unstashed = self.unstashed.lock().unwrap();
+        for msg in stashed.into_iter().rev() {
+            unstashed.push_front(msg);
+        }
+
+        self.unstashed_len.fetch_add(count, Ordering::SeqCst);
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::RefAddr;
+    use crate::message::Msg;
+    use futures::channel::mpsc;
+
+    fn signed(msg: &'static str) -> SignedMessage {
+        let (sender, _) = mpsc::unbounded();
+        let path = Arc::new(BastionPath::root());
+        let sign = RefAddr::new(path, sender.into());
+
+        SignedMessage::new(Msg::tell(msg), sign)
+    }
+
+    #[test]
+    fn unbounded_always_reserves() {
+        let mailbox = Mailbox::new(MailboxConfig::unbounded());
+        for _ in 0..1024 {
+            assert!(mailbox.try_reserve());
+        }
+        assert!(mailbox.has_room());
+    }
+
+    #[test]
+    fn fail_rejects_when_full() {
+        let config = MailboxConfig::bounded(2).with_overflow(OverflowStrategy::Fail);
+        let mailbox = Mailbox::new(config);
+
+        assert!(mailbox.try_reserve());
+        assert!(mailbox.try_reserve());
+        assert!(!mailbox.try_reserve());
+
+        mailbox.push(signed("first"), Priority::Normal);
+        assert!(mailbox.pop().is_some());
+        assert!(mailbox.try_reserve());
+    }
+
+    #[test]
+    fn applies_a_new_capacity() {
+        let config = MailboxConfig::bounded(1).with_overflow(OverflowStrategy::Fail);
+        let mailbox = Mailbox::new(config);
+        assert!(mailbox.try_reserve());
+        assert!(!mailbox.try_reserve());
+
+        mailbox.set_capacity(2);
+        assert!(mailbox.try_reserve());
+        assert!(!mailbox.has_room());
+
+        let mailbox = Mailbox::new(MailboxConfig::unbounded());
+        mailbox.set_capacity(1);
+        assert!(!mailbox.is_bounded());
+    }
+
+    #[test]
+    fn drop_oldest_keeps_newest() {
+        let config = MailboxConfig::bounded(2).with_overflow(OverflowStrategy::DropOldest);
+        let mailbox = Mailbox::new(config);
+
+        for msg in &["first", "second", "third"] {
+            assert!(mailbox.try_reserve());
+            mailbox.push(signed(*msg), Priority::Normal);
+        }
+
+        assert_eq!(mailbox.len(), 2);
+        let (msg, _) = mailbox.pop().unwrap().extract();
+        assert_eq!(msg.downcast::<&'static str>().unwrap(), "second");
+    }
+
+    #[test]
+    fn drop_oldest_makes_room_when_reserving() {
+        let config = MailboxConfig::bounded(2).with_overflow(OverflowStrategy::DropOldest);
+        let mailbox = Mailbox::new(config);
+
+        for msg in &["first", "second"] {
+            assert!(mailbox.try_reserve());
+            mailbox.push(signed(*msg), Priority::Normal);
+        }
+
+        // The room is made before the message is even sent.
+        assert!(mailbox.try_reserve());
+        assert_eq!(mailbox.len(), 1);
+        assert!(!mailbox.has_room());
+    }
+
+    #[test]
+    fn pops_in_priority_order() {
+        let mailbox = Mailbox::new(MailboxConfig::unbounded());
+
+        mailbox.push(signed("low"), Priority::Low);
+        mailbox.push(signed("normal"), Priority::Normal);
+        mailbox.push(signed("high"), Priority::High);
+        mailbox.push(signed("high again"), Priority::High);
+
+        for expected in &["high", "high again", "normal", "low"] {
+            let (msg, _) = mailbox.pop().unwrap().extract();
+            assert_eq!(msg.downcast::<&'static str>().unwrap(), *expected);
+        }
+        assert!(mailbox.pop().is_none());
+    }
+
+    #[test]
+    fn unstashes_at_the_front() {
+        let config = MailboxConfig::unbounded().with_stash_capacity(2);
+        let mailbox = Mailbox::new(config);
+
+        assert!(mailbox.stash(signed("first")).is_ok());
+        assert!(mailbox.stash(signed("second")).is_ok());
+        assert!(mailbox.stash(signed("overflow")).is_err());
+        mailbox.push(signed("high"), Priority::High);
+
+        assert_eq!(mailbox.unstash_all(), 2);
+        assert_eq!(mailbox.len(), 3);
+        for expected in &["first", "second", "high"] {
+            let (msg, _) = mailbox.pop().unwrap().extract();
+            assert_eq!(msg.downcast::<&'static str>().unwrap(), *expected);
+        }
+        assert!(mailbox.pop().is_none());
+        assert_eq!(mailbox.len(), 0);
+    }
+}
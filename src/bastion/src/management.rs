@@ -0,0 +1,375 @@
+//!
+//! An embedded HTTP server allowing to operate a running system, and
+//! available with the `management` feature.
+//!
+//! Once started with [`Bastion::serve_management`], it answers:
+//! - `GET /health` with `200 OK`, or `503 Service Unavailable` once
+//!   the system started to drain (see [`Bastion::shutdown_gracefully`]).
+//! - `GET /tree` with the snapshot of the supervision tree (see
+//!   [`Bastion::tree`]), serialized as JSON.
+//! - `GET /metrics` with the metrics of the system in the Prometheus
+//!   text format, if the `metrics` feature is enabled.
+//! - `POST /groups/<path>/pause`, `POST /groups/<path>/resume` and
+//!   `POST /groups/<path>/restart` by pausing, resuming or restarting
+//!   the children group with the given path (e.g.
+//!   `/groups/<supervisor id>/<group id>/pause`), as the `ChildrenRef`
+//!   methods with the same names do.
+//!
+//! Every request but the health checks needs to be authenticated with
+//! the bearer token of the [`ManagementConfig`] (i.e. to have an
+//! `Authorization: Bearer <token>` header). The health checks are made
+//! by load balancers and orchestrators which usually can't send one,
+//! and only tell whether the system is draining.
+//!
+//! [`Bastion::serve_management`]: ../struct.Bastion.html#method.serve_management
+//! [`Bastion::shutdown_gracefully`]: ../struct.Bastion.html#method.shutdown_gracefully
+//! [`Bastion::tree`]: ../struct.Bastion.html#method.tree
+//! [`ManagementConfig`]: struct.ManagementConfig.html
+use crate::bastion::Bastion;
+use crate::errors::ManagementError;
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use crate::system::SYSTEM;
+use crate::tree;
+use std::fmt::{self, Debug, Formatter};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, trace, warn};
+
+// The maximum size of the request line and headers of a request.
+const MAX_HEAD_LEN: usize = 8 * 1024;
+// The maximum number of connections handled at the same time, each
+// on its own thread.
+const MAX_CONNECTIONS: usize = 16;
+
+#[derive(Clone)]
+/// The configuration of the management server, as given to
+/// [`Bastion::serve_management`].
+///
+/// By default, a connection which doesn't send the head of its
+/// request (its request line and headers) within 5 seconds is
+/// closed.
+///
+/// # Example
+///
+/// ```rust
+/// use bastion::prelude::*;
+/// use std::time::Duration;
+///
+/// let config = ManagementConfig::new("s3cr3t")
+///     .expect("The bearer token is empty.")
+///     .with_read_timeout(Duration::from_secs(1));
+/// ```
+///
+/// [`Bastion::serve_management`]: ../struct.Bastion.html#method.serve_management
+pub struct ManagementConfig {
+    bearer_token: String,
+    read_timeout: Duration,
+}
+
+// Reads from a connection until the deadline of the head of its
+// request, however the bytes are spread over time.
+struct DeadlineReader {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl ManagementConfig {
+    /// Creates a new configuration requiring the requests to be
+    /// authenticated with the given bearer token, without its
+    /// leading and trailing whitespaces (which are also trimmed
+    /// from the tokens the requests send).
+    ///
+    /// This returns [`ManagementError::EmptyToken`] if the token is
+    /// empty (or only made of whitespaces), as any request would
+    /// then be authorized.
+    ///
+    /// # Argument
+    ///
+    /// * `bearer_token` - The token the requests need to send in
+    ///     their `Authorization` header.
+    ///
+    /// [`ManagementError::EmptyToken`]: ../errors/enum.ManagementError.html#variant.EmptyToken
+    pub fn new<T: Into<String>>(bearer_token: T) -> Result<Self, ManagementError> {
+        let bearer_token = bearer_token.into().trim().to_string();
+        if bearer_token.is_empty() {
+            return Err(ManagementError::EmptyToken);
+        }
+
+        Ok(ManagementConfig {
+            bearer_token,
+            read_timeout: Duration::from_secs(5),
+        })
+    }
+
+    /// Sets how long a connection has to send the head of its
+    /// request (its request line and headers) before being closed.
+    ///
+    /// # Argument
+    ///
+    /// * `timeout` - The read timeout of the connections.
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Returns how long a connection has to send the head of its
+    /// request.
+    pub fn read_timeout(&self) -> Duration {
+        self.read_timeout
+    }
+
+    fn is_authorized(&self, authorization: Option<&str>) -> bool {
+        let token = match authorization.and_then(|value| value.strip_prefix("Bearer ")) {
+            Some(token) => token.trim().as_bytes(),
+            None => return false,
+        };
+
+        // The tokens are compared in constant time, not to leak how
+        // much of the expected one was guessed.
+        let expected = self.bearer_token.as_bytes();
+        token.len() == expected.len()
+            && token
+                .iter()
+                .zip(expected)
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+impl Debug for ManagementConfig {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        // The bearer token is a secret.
+        fmt.debug_struct("ManagementConfig")
+            .field("read_timeout", &self.read_timeout)
+            .finish()
+    }
+}
+
+impl Read for DeadlineReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::from_secs(0) {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Response {
+    fn new(status: &'static str, body: &str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", body),
+        }
+    }
+
+    fn write_to(&self, stream: &mut TcpStream) -> io::Result<()> {
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            self.content_type,
+            self.body.len(),
+            self.body
+        )?;
+        stream.flush()
+    }
+}
+
+/// Binds the management server to the given address and starts the
+/// thread serving it, returning the address it is bound to.
+pub(crate) fn serve<A: ToSocketAddrs>(addr: A, config: ManagementConfig) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let addr = listener.local_addr()?;
+
+    thread::Builder::new()
+        .name("bastion-management".to_string())
+        .spawn(move || {
+            let connections = Arc::new(AtomicUsize::new(0));
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        warn!("Management: Couldn't accept a connection: {}", err);
+                        continue;
+                    }
+                };
+
+                if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    warn!("Management: Too many connections, refusing one.");
+                    let response = Response::new("503 Service Unavailable", "Too many connections");
+                    response.write_to(&mut stream).ok();
+                    continue;
+                }
+
+                // A slow client mustn't delay the other ones (e.g.
+                // the health checks).
+                let config = config.clone();
+                let active = connections.clone();
+                let spawned = thread::Builder::new()
+                    .name("bastion-management-conn".to_string())
+                    .spawn(move || {
+                        if let Err(err) = handle_connection(stream, &config) {
+                            debug!("Management: Couldn't handle a connection: {}", err);
+                        }
+
+                        active.fetch_sub(1, Ordering::SeqCst);
+                    });
+                if let Err(err) = spawned {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    warn!("Management: Couldn't spawn a connection thread: {}", err);
+                }
+            }
+        })?;
+
+    debug!("Management: Serving on {}.", addr);
+    Ok(addr)
+}
+
+fn handle_connection(mut stream: TcpStream, config: &ManagementConfig) -> io::Result<()> {
+    stream.set_write_timeout(Some(config.read_timeout))?;
+    let deadline = DeadlineReader {
+        stream: stream.try_clone()?,
+        deadline: Instant::now() + config.read_timeout,
+    };
+    // The body of the requests, if any, is ignored.
+    let mut reader = BufReader::new(deadline.take(MAX_HEAD_LEN as u64));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut authorization = None;
+    loop {
+        let mut header = String::new();
+        // The connection was closed or the head is too large.
+        if reader.read_line(&mut header)? == 0 {
+            return Response::new("400 Bad Request", "Bad request").write_to(&mut stream);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method, path),
+        _ => return Response::new("400 Bad Request", "Bad request").write_to(&mut stream),
+    };
+
+    trace!("Management: Received request: {} {}", method, path);
+    let path = path.split('?').next().unwrap_or(path);
+    // The health checks don't need the token.
+    let authorized =
+        (method, path) == ("GET", "/health") || config.is_authorized(authorization.as_deref());
+    let response = if authorized {
+        route(method, path)
+    } else {
+        warn!("Management: Unauthorized request: {} {}", method, path);
+        Response::new("401 Unauthorized", "Unauthorized")
+    };
+
+    response.write_to(&mut stream)
+}
+
+fn route(method: &str, path: &str) -> Response {
+    match (method, path) {
+        ("GET", "/health") if SYSTEM.is_draining() => {
+            Response::new("503 Service Unavailable", "Draining")
+        }
+        ("GET", "/health") => Response::new("200 OK", "OK"),
+        ("GET", "/tree") => match serde_json::to_string(&Bastion::tree()) {
+            Ok(body) => Response {
+                status: "200 OK",
+                content_type: "application/json",
+                body,
+            },
+            Err(err) => Response::new("500 Internal Server Error", &err.to_string()),
+        },
+        #[cfg(feature = "metrics")]
+        ("GET", "/metrics") => Response {
+            status: "200 OK",
+            content_type: "text/plain; version=0.0.4",
+            body: metrics::prometheus_handle().render(),
+        },
+        ("POST", _) if path.starts_with("/groups/") => control_group(&path["/groups".len()..]),
+        _ => Response::new("404 Not Found", "Not found"),
+    }
+}
+
+/// Pauses, resumes or restarts the children group whose path is
+/// followed by the action in `path`.
+fn control_group(path: &str) -> Response {
+    let (group_path, action) = match path.rsplit_once('/') {
+        Some(split) => split,
+        None => return Response::new("404 Not Found", "Not found"),
+    };
+
+    let children_ref = match tree::group_by_path(group_path) {
+        Some(children_ref) => children_ref,
+        None => return Response::new("404 Not Found", "Unknown children group"),
+    };
+
+    let sent = match action {
+        "pause" => children_ref.pause(),
+        "resume" => children_ref.resume(),
+        "restart" => children_ref.restart(),
+        _ => return Response::new("404 Not Found", "Unknown action"),
+    };
+
+    match sent {
+        Ok(()) => {
+            debug!("Management: Sent {} to Children({}).", action, group_path);
+            Response::new("202 Accepted", "Accepted")
+        }
+        Err(()) => Response::new("503 Service Unavailable", "Couldn't send the message"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authorizes_the_bearer_token() {
+        let config = ManagementConfig::new("s3cr3t").unwrap();
+
+        assert!(config.is_authorized(Some("Bearer s3cr3t")));
+        assert!(!config.is_authorized(Some("Bearer s3cr3")));
+        assert!(!config.is_authorized(Some("Basic s3cr3t")));
+        assert!(!config.is_authorized(None));
+
+        // Both tokens are trimmed.
+        let config = ManagementConfig::new(" s3cr3t\n").unwrap();
+        assert!(config.is_authorized(Some("Bearer s3cr3t ")));
+    }
+
+    #[test]
+    fn refuses_an_empty_token() {
+        assert!(matches!(
+            ManagementConfig::new(" "),
+            Err(ManagementError::EmptyToken)
+        ));
+    }
+}
@@ -6,18 +6,28 @@
 //! * All message communication relies on at-most-once delivery guarantee.
 //! * Messages are not guaranteed to be ordered, all message's order is causal.
 //!
+use crate::broadcast::Broadcast;
+use crate::bulkhead::Permit;
 use crate::callbacks::CallbackType;
+use crate::child::Init;
+use crate::child_ref::ChildRef;
 use crate::children::Children;
-use crate::context::{BastionId, ContextState};
+use crate::context::{BastionContext, BastionId, ContextState};
+use crate::deadlock::{self, AskGuard};
 use crate::envelope::{RefAddr, SignedMessage};
+use crate::mailbox::Mailbox;
+use crate::path::BastionPath;
 use crate::supervisor::{SupervisionStrategy, Supervisor};
+use crate::watch::StopReason;
 
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use futures::channel::oneshot::{self, Receiver};
+use futures::Stream;
 use std::any::{type_name, Any};
 use std::fmt::Debug;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 use tracing::{debug, trace};
 
@@ -33,9 +43,47 @@ use tracing::{debug, trace};
 pub trait Message: Any + Send + Sync + Debug {}
 impl<T> Message for T where T: Any + Send + Sync + Debug {}
 
+/// A trait that a message which expects an answer of a specific
+/// type can implement to be "asked" using [`ChildRef::ask_typed`].
+///
+/// The child handling the request can then answer to it using
+/// the `reply!` macro generated by [`msg!`] for the `=!>` cases,
+/// which only compiles if the answer is of type [`Request::Response`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// #[derive(Debug)]
+/// struct Ping(u32);
+///
+/// #[derive(Debug)]
+/// struct Pong(u32);
+///
+/// impl Request for Ping {
+///     type Response = Pong;
+/// }
+/// ```
+///
+/// [`ChildRef::ask_typed`]: ../child_ref/struct.ChildRef.html#method.ask_typed
+/// [`msg!`]: ../macro.msg.html
+pub trait Request: Message {
+    /// The type of the message that should be answered to this
+    /// request.
+    type Response: Message;
+}
+
 #[derive(Debug)]
 #[doc(hidden)]
-pub struct AnswerSender(oneshot::Sender<SignedMessage>);
+pub struct AnswerSender(AnswerChannel, Option<Permit>, AskGuard);
+
+#[derive(Debug)]
+enum AnswerChannel {
+    // The sender is taken by the first answer.
+    Once(Mutex<Option<oneshot::Sender<SignedMessage>>>),
+    Stream(UnboundedSender<SignedMessage>),
+}
 
 #[derive(Debug)]
 /// A [`Future`] returned when successfully "asking" a
@@ -124,7 +172,177 @@ pub struct AnswerSender(oneshot::Sender<SignedMessage>);
 /// [`ChildRef::ask`]: ../children/struct.ChildRef.html#method.ask
 /// [`Msg`]: message/struct.Msg.html
 /// [`msg!`]: macro.msg.html
-pub struct Answer(Receiver<SignedMessage>);
+pub struct Answer {
+    recver: Receiver<SignedMessage>,
+    // The ask added to the graph of pending asks until it is
+    // answered (or either side is dropped), or the cycle of asks it
+    // would have closed.
+    pending: AskGuard,
+    deadlock: Option<Vec<Arc<BastionPath>>>,
+}
+
+#[derive(Debug)]
+/// A [`Stream`] returned when successfully "asking" a
+/// message using [`ChildRef::ask_stream_anonymously`] or
+/// [`BastionContext::ask_stream`], and which yields every
+/// chunk answered by the child using the `answer_stream!`
+/// macro generated by [`msg!`] for the `=!>` cases.
+///
+/// The stream ends once the child stopped handling the
+/// message.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use futures::StreamExt;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+///     # Bastion::init();
+///     # let children_ref =
+/// // Create a new child...
+/// Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| {
+///         async move {
+///             // ...which will receive the message asked...
+///             msg! { ctx.recv().await?,
+///                 count: usize =!> {
+///                     // ...and answer it with many chunks...
+///                     for chunk in 0..count {
+///                         answer_stream!(ctx, chunk).expect("Couldn't answer.");
+///                     }
+///                 };
+///                 _: _ => ();
+///             }
+///
+///             Ok(())
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+///
+///     # Bastion::children(|children| {
+///         # children.with_exec(move |ctx: BastionContext| {
+///             # let child_ref = children_ref.elems()[0].clone();
+///             # async move {
+/// // Later, the message is "asked" to the child...
+/// let mut chunks: AnswerStream = child_ref
+///     .ask_stream_anonymously(3usize)
+///     .expect("Couldn't send the message.");
+///
+/// // ...and its chunks are received as they are answered...
+/// while let Some(chunk) = chunks.next().await {
+///     msg! { chunk,
+///         chunk: usize => {
+///             // Handle the chunk...
+///         };
+///         _: _ => ();
+///     }
+/// }
+///                 #
+///                 # Ok(())
+///             # }
+///         # })
+///     # }).unwrap();
+///     #
+///     # Bastion::start();
+///     # Bastion::stop();
+///     # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`Stream`]: https://docs.rs/futures/0.3/futures/stream/trait.Stream.html
+/// [`ChildRef::ask_stream_anonymously`]: ../child_ref/struct.ChildRef.html#method.ask_stream_anonymously
+/// [`BastionContext::ask_stream`]: ../context/struct.BastionContext.html#method.ask_stream
+/// [`msg!`]: ../macro.msg.html
+pub struct AnswerStream(UnboundedReceiver<SignedMessage>);
+
+#[derive(Debug)]
+/// The answering end of an "asked" message, taken out of it
+/// using [`Msg::take_responder`] or the `responder!` macro
+/// generated by [`msg!`] for the `=!>` cases.
+///
+/// Unlike the `answer!` macro, a `Responder` isn't tied to the
+/// scope of the message: it can be moved into another task or
+/// sent to another child (it implements [`Message`]), which
+/// will then answer the original caller directly.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+///     # Bastion::init();
+/// // Create a worker...
+/// let workers = Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| {
+///         async move {
+///             loop {
+///                 msg! { ctx.recv().await?,
+///                     delegated: (&'static str, Responder) => {
+///                         let (job, responder) = delegated;
+///                         // ...which does the work and answers the
+///                         // original caller...
+///                         responder.respond("done", &ctx).expect("Couldn't answer.");
+///                     };
+///                     _: _ => ();
+///                 }
+///             }
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+/// let worker = workers.elems()[0].clone();
+///
+/// // Create a front-door child...
+/// Bastion::children(|children| {
+///     children.with_exec(move |ctx: BastionContext| {
+///         let worker = worker.clone();
+///         async move {
+///             loop {
+///                 msg! { ctx.recv().await?,
+///                     job: &'static str =!> {
+///                         // ...which delegates the work to the worker.
+///                         worker.tell_anonymously((job, responder!())).ok();
+///                     };
+///                     _: _ => ();
+///                 }
+///             }
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+///     #
+///     # Bastion::start();
+///     # Bastion::stop();
+///     # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`Msg::take_responder`]: message/struct.Msg.html#method.take_responder
+/// [`msg!`]: macro.msg.html
+/// [`Message`]: message/trait.Message.html
+pub struct Responder(AnswerSender);
 
 #[derive(Debug)]
 /// A message returned by [`BastionContext::recv`] or
@@ -196,7 +414,7 @@ pub struct Answer(Receiver<SignedMessage>);
 /// [`BastionContext::recv`]: context/struct.BastionContext.html#method.recv
 /// [`BastionContext::try_recv`]: context/struct.BastionContext.html#method.try_recv
 /// [`msg!`]: macro.msg.html
-pub struct Msg(MsgInner);
+pub struct Msg(MsgInner, &'static str);
 
 #[derive(Debug)]
 enum MsgInner {
@@ -212,7 +430,14 @@ enum MsgInner {
 pub(crate) enum BastionMessage {
     Start,
     Stop,
-    Kill,
+    Drain,
+    Kill {
+        reason: StopReason,
+    },
+    Pause,
+    Resume,
+    Restart,
+    Reconfigure,
     Deploy(Box<Deployment>),
     Prune {
         id: BastionId,
@@ -251,12 +476,30 @@ pub(crate) enum BastionMessage {
         id: BastionId,
     },
     Heartbeat,
+    Watch {
+        watcher: ChildRef,
+    },
+    Unwatch {
+        id: BastionId,
+    },
+    Link {
+        peer: ChildRef,
+    },
+    Exit {
+        from: ChildRef,
+        reason: StopReason,
+    },
 }
 
 #[derive(Debug)]
 pub(crate) enum Deployment {
     Supervisor(Supervisor),
     Children(Children),
+    Child {
+        bcast: Broadcast,
+        mailbox: Arc<Mailbox>,
+        init: Init,
+    },
 }
 
 impl AnswerSender {
@@ -267,33 +510,152 @@ impl AnswerSender {
         debug!("{:?}: Sending answer: {:?}", self, msg);
         let msg = Msg::tell(msg);
         trace!("{:?}: Sending message: {:?}", self, msg);
-        self.0
-            .send(SignedMessage::new(msg, sign))
+        self.send_signed(SignedMessage::new(msg, sign))
             .map_err(|smsg| smsg.msg.try_unwrap().unwrap())
     }
+
+    // Only called by the `answer_stream!` macro, which can be
+    // called many times for the same message. If the message
+    // wasn't asked using `ask_stream`, only its first chunk is
+    // sent.
+    #[doc(hidden)]
+    pub fn send_chunk<M: Message>(&self, msg: M, sign: RefAddr) -> Result<(), M> {
+        debug!("{:?}: Sending answer chunk: {:?}", self, msg);
+        let msg = Msg::tell(msg);
+        let smsg = SignedMessage::new(msg, sign);
+        let sent = match &self.0 {
+            // FIXME: panics
+            AnswerChannel::Once(sender) => match sender.lock().unwrap().take() {
+                Some(sender) => {
+                    self.2.release();
+                    sender.send(smsg)
+                }
+                None => Err(smsg),
+            },
+            AnswerChannel::Stream(sender) => {
+                sender.unbounded_send(smsg).map_err(|err| err.into_inner())
+            }
+        };
+
+        sent.map_err(|smsg| smsg.msg.try_unwrap().unwrap())
+    }
+
+    // Only called by the `reply!` macro, which makes sure that
+    // `R` is the type of the request that is being answered.
+    #[doc(hidden)]
+    pub fn reply<R: Request>(
+        self,
+        response: R::Response,
+        sign: RefAddr,
+    ) -> Result<(), R::Response> {
+        self.send(response, sign)
+    }
+
+    pub(crate) fn send_msg(self, msg: Msg, sign: RefAddr) -> Result<(), ()> {
+        debug!("{:?}: Sending answer: {:?}", self, msg);
+        self.send_signed(SignedMessage::new(msg, sign))
+            .map_err(|_| ())
+    }
+
+    fn send_signed(self, smsg: SignedMessage) -> Result<(), SignedMessage> {
+        match self.0 {
+            // FIXME: panics
+            AnswerChannel::Once(sender) => match sender.into_inner().unwrap() {
+                Some(sender) => sender.send(smsg),
+                None => Err(smsg),
+            },
+            AnswerChannel::Stream(sender) => {
+                sender.unbounded_send(smsg).map_err(|err| err.into_inner())
+            }
+        }
+    }
+}
+
+impl Responder {
+    /// Answers the message this `Responder` was taken out of,
+    /// signing the answer with the given context.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(answer)`
+    /// if the caller doesn't wait for the answer anymore.
+    ///
+    /// # Arguments
+    ///
+    /// * `answer` - The answer to send.
+    /// * `ctx` - The context of the child sending the answer.
+    pub fn respond<M: Message>(self, answer: M, ctx: &BastionContext) -> Result<(), M> {
+        self.0.send(answer, ctx.signature())
+    }
+
+    /// Answers the message this `Responder` was taken out of,
+    /// without signing the answer.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(answer)`
+    /// if the caller doesn't wait for the answer anymore.
+    ///
+    /// # Argument
+    ///
+    /// * `answer` - The answer to send.
+    pub fn respond_anonymously<M: Message>(self, answer: M) -> Result<(), M> {
+        self.0.send(answer, RefAddr::dead_letters())
+    }
+
+    /// Sends a chunk of the answer to the message this `Responder`
+    /// was taken out of, which can be called many times if the
+    /// message was asked as a stream (see [`AnswerStream`]).
+    ///
+    /// This method returns `()` if it succeeded, or `Err(chunk)`
+    /// otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The chunk of the answer to send.
+    /// * `ctx` - The context of the child sending the chunk.
+    ///
+    /// [`AnswerStream`]: message/struct.AnswerStream.html
+    pub fn respond_chunk<M: Message>(&self, chunk: M, ctx: &BastionContext) -> Result<(), M> {
+        self.0.send_chunk(chunk, ctx.signature())
+    }
+
+    #[doc(hidden)]
+    pub fn new(sender: AnswerSender) -> Self {
+        Responder(sender)
+    }
 }
 
 impl Msg {
     pub(crate) fn broadcast<M: Message>(msg: M) -> Self {
         let inner = MsgInner::Broadcast(Arc::new(msg));
-        Msg(inner)
+        Msg(inner, type_name::<M>())
     }
 
     pub(crate) fn tell<M: Message>(msg: M) -> Self {
         let inner = MsgInner::Tell(Box::new(msg));
-        Msg(inner)
+        Msg(inner, type_name::<M>())
     }
 
     pub(crate) fn ask<M: Message>(msg: M) -> (Self, Answer) {
         let msg = Box::new(msg);
-        let (sender, recver) = oneshot::channel();
-        let sender = AnswerSender(sender);
-        let answer = Answer(recver);
+        let (sender, answer) = Answer::channel();
+
+        let sender = Some(sender);
+        let inner = MsgInner::Ask { msg, sender };
+
+        (Msg(inner, type_name::<M>()), answer)
+    }
+
+    pub(crate) fn ask_stream<M: Message>(msg: M) -> (Self, AnswerStream) {
+        let msg = Box::new(msg);
+        let (sender, answer) = AnswerStream::channel();
 
         let sender = Some(sender);
         let inner = MsgInner::Ask { msg, sender };
 
-        (Msg(inner), answer)
+        (Msg(inner, type_name::<M>()), answer)
+    }
+
+    /// Returns the name of the type of the message.
+    pub(crate) fn type_name(&self) -> &'static str {
+        self.1
     }
 
     #[doc(hidden)]
@@ -321,6 +683,54 @@ impl Msg {
         }
     }
 
+    /// Takes the [`Responder`] of this message out of it, if it
+    /// was "asked" and wasn't answered yet, so that it can be
+    /// answered later, outside of the scope of this message.
+    ///
+    /// [`Responder`]: message/struct.Responder.html
+    pub fn take_responder(&mut self) -> Option<Responder> {
+        self.take_sender().map(Responder)
+    }
+
+    // Attaches the permit of a bulkhead to the sender of this
+    // message's answer, if it was "asked", so that it is released
+    // once the message is answered.
+    pub(crate) fn set_permit(&mut self, permit: Permit) {
+        if let MsgInner::Ask {
+            sender: Some(sender),
+            ..
+        } = &mut self.0
+        {
+            sender.1 = Some(permit);
+        }
+    }
+
+    pub(crate) fn tell_or_ask_ref<M: Message>(&self) -> Option<&M> {
+        match &self.0 {
+            MsgInner::Tell(msg) => msg.downcast_ref(),
+            MsgInner::Ask { msg, .. } => msg.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "redis-mailbox")]
+    pub(crate) fn tell_ref<M: Message>(&self) -> Option<&M> {
+        match &self.0 {
+            MsgInner::Tell(msg) => msg.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn has_permit(&self) -> bool {
+        matches!(
+            &self.0,
+            MsgInner::Ask {
+                sender: Some(AnswerSender(_, Some(_), _)),
+                ..
+            }
+        )
+    }
+
     #[doc(hidden)]
     pub fn is<M: Message>(&self) -> bool {
         match &self.0 {
@@ -333,6 +743,7 @@ impl Msg {
     #[doc(hidden)]
     pub fn downcast<M: Message>(self) -> Result<M, Self> {
         trace!("{:?}: Downcasting to {}.", self, type_name::<M>());
+        let name = self.1;
         match self.0 {
             MsgInner::Tell(msg) => {
                 if msg.is::<M>() {
@@ -340,7 +751,7 @@ impl Msg {
                     Ok(*msg.downcast().unwrap())
                 } else {
                     let inner = MsgInner::Tell(msg);
-                    Err(Msg(inner))
+                    Err(Msg(inner, name))
                 }
             }
             MsgInner::Ask { msg, sender } => {
@@ -349,7 +760,7 @@ impl Msg {
                     Ok(*msg.downcast().unwrap())
                 } else {
                     let inner = MsgInner::Ask { msg, sender };
-                    Err(Msg(inner))
+                    Err(Msg(inner, name))
                 }
             }
             _ => Err(self),
@@ -372,7 +783,7 @@ impl Msg {
         trace!("{:?}: Trying to clone.", self);
         if let MsgInner::Broadcast(msg) = &self.0 {
             let inner = MsgInner::Broadcast(msg.clone());
-            Some(Msg(inner))
+            Some(Msg(inner, self.1))
         } else {
             None
         }
@@ -380,18 +791,19 @@ impl Msg {
 
     pub(crate) fn try_unwrap<M: Message>(self) -> Result<M, Self> {
         debug!("{:?}: Trying to unwrap.", self);
+        let name = self.1;
         if let MsgInner::Broadcast(msg) = self.0 {
             match msg.downcast() {
                 Ok(msg) => match Arc::try_unwrap(msg) {
                     Ok(msg) => Ok(msg),
                     Err(msg) => {
                         let inner = MsgInner::Broadcast(msg);
-                        Err(Msg(inner))
+                        Err(Msg(inner, name))
                     }
                 },
                 Err(msg) => {
                     let inner = MsgInner::Broadcast(msg);
-                    Err(Msg(inner))
+                    Err(Msg(inner, name))
                 }
             }
         } else {
@@ -409,8 +821,32 @@ impl BastionMessage {
         BastionMessage::Stop
     }
 
+    pub(crate) fn drain() -> Self {
+        BastionMessage::Drain
+    }
+
     pub(crate) fn kill() -> Self {
-        BastionMessage::Kill
+        BastionMessage::kill_with(StopReason::Killed)
+    }
+
+    pub(crate) fn kill_with(reason: StopReason) -> Self {
+        BastionMessage::Kill { reason }
+    }
+
+    pub(crate) fn pause() -> Self {
+        BastionMessage::Pause
+    }
+
+    pub(crate) fn resume() -> Self {
+        BastionMessage::Resume
+    }
+
+    pub(crate) fn restart() -> Self {
+        BastionMessage::Restart
+    }
+
+    pub(crate) fn reconfigure() -> Self {
+        BastionMessage::Reconfigure
     }
 
     pub(crate) fn deploy_supervisor(supervisor: Supervisor) -> Self {
@@ -425,6 +861,16 @@ impl BastionMessage {
         BastionMessage::Deploy(deployment.into())
     }
 
+    pub(crate) fn deploy_child(bcast: Broadcast, mailbox: Arc<Mailbox>, init: Init) -> Self {
+        let deployment = Deployment::Child {
+            bcast,
+            mailbox,
+            init,
+        };
+
+        BastionMessage::Deploy(deployment.into())
+    }
+
     pub(crate) fn prune(id: BastionId) -> Self {
         BastionMessage::Prune { id }
     }
@@ -464,6 +910,11 @@ impl BastionMessage {
         (BastionMessage::Message(msg), answer)
     }
 
+    pub(crate) fn ask_stream<M: Message>(msg: M) -> (Self, AnswerStream) {
+        let (msg, answer) = Msg::ask_stream(msg);
+        (BastionMessage::Message(msg), answer)
+    }
+
     pub(crate) fn restart_required(id: BastionId, parent_id: BastionId) -> Self {
         BastionMessage::RestartRequired { id, parent_id }
     }
@@ -500,12 +951,33 @@ impl BastionMessage {
         BastionMessage::Heartbeat
     }
 
+    pub(crate) fn watch(watcher: ChildRef) -> Self {
+        BastionMessage::Watch { watcher }
+    }
+
+    pub(crate) fn unwatch(id: BastionId) -> Self {
+        BastionMessage::Unwatch { id }
+    }
+
+    pub(crate) fn link(peer: ChildRef) -> Self {
+        BastionMessage::Link { peer }
+    }
+
+    pub(crate) fn exit(from: ChildRef, reason: StopReason) -> Self {
+        BastionMessage::Exit { from, reason }
+    }
+
     pub(crate) fn try_clone(&self) -> Option<Self> {
         trace!("{:?}: Trying to clone.", self);
         let clone = match self {
             BastionMessage::Start => BastionMessage::start(),
             BastionMessage::Stop => BastionMessage::stop(),
-            BastionMessage::Kill => BastionMessage::kill(),
+            BastionMessage::Drain => BastionMessage::drain(),
+            BastionMessage::Kill { reason } => BastionMessage::kill_with(reason.clone()),
+            BastionMessage::Pause => BastionMessage::pause(),
+            BastionMessage::Resume => BastionMessage::resume(),
+            BastionMessage::Restart => BastionMessage::restart(),
+            BastionMessage::Reconfigure => BastionMessage::reconfigure(),
             // FIXME
             BastionMessage::Deploy(_) => unimplemented!(),
             BastionMessage::Prune { id } => BastionMessage::prune(id.clone()),
@@ -540,6 +1012,12 @@ impl BastionMessage {
             BastionMessage::Stopped { id } => BastionMessage::stopped(id.clone()),
             BastionMessage::Faulted { id } => BastionMessage::faulted(id.clone()),
             BastionMessage::Heartbeat => BastionMessage::heartbeat(),
+            BastionMessage::Watch { watcher } => BastionMessage::watch(watcher.clone()),
+            BastionMessage::Unwatch { id } => BastionMessage::unwatch(id.clone()),
+            BastionMessage::Link { peer } => BastionMessage::link(peer.clone()),
+            BastionMessage::Exit { from, reason } => {
+                BastionMessage::exit(from.clone(), reason.clone())
+            }
         };
 
         Some(clone)
@@ -554,12 +1032,77 @@ impl BastionMessage {
     }
 }
 
+impl Answer {
+    pub(crate) fn channel() -> (AnswerSender, Self) {
+        let (sender, recver) = oneshot::channel();
+        let sender = AnswerChannel::Once(Mutex::new(Some(sender)));
+        let pending = AskGuard::new();
+        let guard = pending.share();
+        let answer = Answer {
+            recver,
+            pending,
+            deadlock: None,
+        };
+
+        (AnswerSender(sender, None, guard), answer)
+    }
+
+    // Adds the ask to the graph of pending asks, failing it if it
+    // would deadlock.
+    pub(crate) fn track(mut self, target: &Arc<BastionPath>) -> Self {
+        match deadlock::ask(target) {
+            Ok(pending) => self.pending.track(pending),
+            Err(cycle) => self.deadlock = Some(cycle),
+        }
+
+        self
+    }
+
+    // Returns the cycle of asks this ask would have closed, if it
+    // was failed because it would deadlock.
+    pub(crate) fn deadlock(&self) -> Option<&[Arc<BastionPath>]> {
+        self.deadlock.as_deref()
+    }
+}
+
 impl Future for Answer {
     type Output = Result<SignedMessage, ()>;
 
     fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
         debug!("{:?}: Polling.", self);
-        Pin::new(&mut self.get_mut().0).poll(ctx).map_err(|_| ())
+        if self.deadlock.is_some() {
+            return Poll::Ready(Err(()));
+        }
+
+        let answer = self.get_mut();
+        let polled = Pin::new(&mut answer.recver).poll(ctx).map_err(|_| ());
+        // The asker isn't waiting on the answer anymore, even if it
+        // is kept.
+        if polled.is_ready() {
+            answer.pending.release();
+        }
+
+        polled
+    }
+}
+
+impl AnswerStream {
+    pub(crate) fn channel() -> (AnswerSender, Self) {
+        let (sender, recver) = mpsc::unbounded();
+        let sender = AnswerChannel::Stream(sender);
+        (
+            AnswerSender(sender, None, AskGuard::default()),
+            AnswerStream(recver),
+        )
+    }
+}
+
+impl Stream for AnswerStream {
+    type Item = SignedMessage;
+
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        debug!("{:?}: Polling.", self);
+        Pin::new(&mut self.get_mut().0).poll_next(ctx)
     }
 }
 
@@ -583,7 +1126,13 @@ impl Future for Answer {
 /// If the message can be answered (when using `=!>` instead
 /// of `=>` as said above), an answer can be sent by passing
 /// it to the `answer!` macro that will be generated for this
-/// use.
+/// use. If the matched type implements [`Request`], the
+/// `reply!` macro can be used instead, which will only accept
+/// an answer of type [`Request::Response`]. If the message was
+/// asked as a stream, the `answer_stream!` macro can be called
+/// many times to send the answer in chunks (see [`AnswerStream`]).
+/// The `responder!` macro takes the answering end of the message
+/// out of its scope instead (see [`Responder`]).
 ///
 /// A default case is required, which is defined in the same
 /// way as any other case but with its type set as `_` (note
@@ -659,6 +1208,10 @@ impl Future for Answer {
 /// ```
 ///
 /// [`Msg`]: children/struct.Msg.html
+/// [`Request`]: message/trait.Request.html
+/// [`Request::Response`]: message/trait.Request.html#associatedtype.Response
+/// [`AnswerStream`]: message/struct.AnswerStream.html
+/// [`Responder`]: message/struct.Responder.html
 /// [`BastionContext::recv`]: context/struct.BastionContext.html#method.recv
 /// [`BastionContext::try_recv`]: context/struct.BastionContext.html#method.try_recv
 macro_rules! msg {
@@ -772,12 +1325,37 @@ macro_rules! msg {
                 };
             }
 
+            macro_rules! responder {
+                () => {
+                    $crate::message::Responder::new(sender)
+                };
+            }
+
+            macro_rules! answer_stream {
+                ($ctx:expr, $answer:expr) => {
+                    {
+                        let sign = $ctx.signature();
+                        sender.send_chunk($answer, sign)
+                    }
+                };
+            }
+
             if false {
                 unreachable!();
             }
             $(
                 else if $var.is::<$aty>() {
                     let $avar = $var.downcast::<$aty>().unwrap();
+
+                    macro_rules! reply {
+                        ($ctx:expr, $answer:expr) => {
+                            {
+                                let sign = $ctx.signature();
+                                sender.reply::<$aty>($answer, sign)
+                            }
+                        };
+                    }
+
                     { $ahandle }
                 }
             )*
@@ -0,0 +1,368 @@
+//!
+//! Metrics about the children and supervisors of the system,
+//! available with the `metrics` feature.
+//!
+//! The following metrics are collected, labelled with the path of
+//! the element they are about:
+//! - `bastion_messages_received_total`: the amount of messages
+//!   received by each child.
+//! - `bastion_mailbox_depth`: the amount of messages waiting in the
+//!   mailbox of each child.
+//! - `bastion_message_processing_seconds`: the time spent by each
+//!   child between receiving a message and waiting for the next one.
+//! - `bastion_restarts_total`: the amount of children restarted by
+//!   each supervisor.
+//! - `bastion_active_children`: the amount of children currently
+//!   supervised by each supervisor.
+//!
+//! And the following ones, about the blocking tasks of the system:
+//! - `bastion_blocking_active_tasks`: the amount of blocking tasks
+//!   currently running.
+//! - `bastion_blocking_queue_wait_seconds`: the time spent by the
+//!   blocking tasks between being spawned and starting to run.
+//! - `bastion_blocking_threads`: the amount of threads of the
+//!   dedicated blocking pool (see `Config::with_blocking_pool`).
+//! - `bastion_blocking_rejected_total`: the amount of blocking tasks
+//!   rejected because the queue of the dedicated pool was full.
+//!
+//! Their collection can be disabled using `Config::with_metrics`.
+//!
+//! They can be rendered in the Prometheus text format using the
+//! handle returned by [`prometheus_handle`], which allows to expose
+//! them with any HTTP server.
+//!
+//! [`prometheus_handle`]: fn.prometheus_handle.html
+use crate::circuit_breaker::CircuitState;
+use crate::path::BastionPath;
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    static ref MESSAGES_RECEIVED: Family = Family::new(
+        "bastion_messages_received_total",
+        "The amount of messages received by a child.",
+        "counter",
+    );
+    static ref MAILBOX_DEPTH: Family = Family::new(
+        "bastion_mailbox_depth",
+        "The amount of messages waiting in the mailbox of a child.",
+        "gauge",
+    );
+    static ref PROCESSING: Family = Family::new(
+        "bastion_message_processing_seconds",
+        "The time spent by a child processing a message.",
+        "summary",
+    );
+    static ref RESTARTS: Family = Family::new(
+        "bastion_restarts_total",
+        "The amount of children restarted by a supervisor.",
+        "counter",
+    );
+    static ref ACTIVE_CHILDREN: Family = Family::new(
+        "bastion_active_children",
+        "The amount of children supervised by a supervisor.",
+        "gauge",
+    );
+    static ref CIRCUIT_STATE: Family = Family::new(
+        "bastion_circuit_breaker_state",
+        "The state of the circuit breaker of a children group (0: closed, 1: half-open, 2: open).",
+        "gauge",
+    );
+    static ref BLOCKING_ACTIVE: Family = Family::new(
+        "bastion_blocking_active_tasks",
+        "The amount of blocking tasks currently running.",
+        "gauge",
+    );
+    static ref BLOCKING_QUEUE_WAIT: Family = Family::new(
+        "bastion_blocking_queue_wait_seconds",
+        "The time spent by a blocking task waiting to run.",
+        "summary",
+    );
+    static ref BLOCKING_THREADS: Family = Family::new(
+        "bastion_blocking_threads",
+        "The amount of threads of the dedicated blocking pool.",
+        "gauge",
+    );
+    static ref BLOCKING_REJECTED: Family = Family::new(
+        "bastion_blocking_rejected_total",
+        "The amount of blocking tasks rejected by the dedicated blocking pool.",
+        "counter",
+    );
+}
+
+// Whether the metrics are collected, as configured using
+// `Config::with_metrics`.
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+#[derive(Debug, Clone, Copy, Default)]
+/// A handle allowing to render the metrics collected by the
+/// system, as returned by [`prometheus_handle`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// Bastion::init();
+/// Bastion::start();
+///
+/// let handle = bastion::metrics::prometheus_handle();
+/// // This would usually be the body of the response of
+/// // a `GET /metrics` request...
+/// let body: String = handle.render();
+/// # drop(body);
+/// #
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`prometheus_handle`]: fn.prometheus_handle.html
+pub struct PrometheusHandle {
+    _private: (),
+}
+
+// A metric and its values by label.
+struct Family {
+    name: &'static str,
+    help: &'static str,
+    kind: &'static str,
+    // The values are stored by path (which is empty for the
+    // metrics that aren't about an element) and suffix (which is
+    // empty except for the `_sum` and `_count` of summaries).
+    values: Mutex<BTreeMap<(String, &'static str), f64>>,
+}
+
+/// Returns a handle allowing to render the metrics collected by
+/// the system in the Prometheus text format.
+pub fn prometheus_handle() -> PrometheusHandle {
+    PrometheusHandle::default()
+}
+
+impl PrometheusHandle {
+    /// Renders all the metrics collected so far in the Prometheus
+    /// text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for family in &[
+            &*MESSAGES_RECEIVED,
+            &*MAILBOX_DEPTH,
+            &*PROCESSING,
+            &*RESTARTS,
+            &*ACTIVE_CHILDREN,
+            &*CIRCUIT_STATE,
+            &*BLOCKING_ACTIVE,
+            &*BLOCKING_QUEUE_WAIT,
+            &*BLOCKING_THREADS,
+            &*BLOCKING_REJECTED,
+        ] {
+            family.render(&mut out);
+        }
+
+        out
+    }
+}
+
+impl Family {
+    fn new(name: &'static str, help: &'static str, kind: &'static str) -> Self {
+        Family {
+            name,
+            help,
+            kind,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn add(&self, path: &BastionPath, suffix: &'static str, value: f64) {
+        self.add_labelled(path.to_string(), suffix, value);
+    }
+
+    fn add_unlabelled(&self, suffix: &'static str, value: f64) {
+        self.add_labelled(String::new(), suffix, value);
+    }
+
+    fn add_labelled(&self, path: String, suffix: &'static str, value: f64) {
+        if !ENABLED.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut values = self.values.lock().unwrap();
+        *values.entry((path, suffix)).or_insert(0.0) += value;
+    }
+
+    fn set(&self, path: &BastionPath, value: f64) {
+        if !ENABLED.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut values = self.values.lock().unwrap();
+        values.insert((path.to_string(), ""), value);
+    }
+
+    fn set_unlabelled(&self, value: f64) {
+        if !ENABLED.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut values = self.values.lock().unwrap();
+        values.insert((String::new(), ""), value);
+    }
+
+    fn render(&self, out: &mut String) {
+        let values = self.values.lock().unwrap();
+        writeln!(out, "# HELP {} {}", self.name, self.help).unwrap();
+        writeln!(out, "# TYPE {} {}", self.name, self.kind).unwrap();
+        for ((path, suffix), value) in values.iter() {
+            if path.is_empty() {
+                writeln!(out, "{}{} {}", self.name, suffix, value).unwrap();
+                continue;
+            }
+
+            writeln!(
+                out,
+                "{}{}{{path=\"{}\"}} {}",
+                self.name,
+                suffix,
+                escape(path),
+                value
+            )
+            .unwrap();
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+pub(crate) fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub(crate) fn record_received(path: &BastionPath, depth: usize) {
+    MESSAGES_RECEIVED.add(path, "", 1.0);
+    MAILBOX_DEPTH.set(path, depth as f64);
+}
+
+pub(crate) fn record_popped(path: &BastionPath, depth: usize) {
+    MAILBOX_DEPTH.set(path, depth as f64);
+}
+
+pub(crate) fn record_processing(path: &BastionPath, elapsed: Duration) {
+    PROCESSING.add(path, "_sum", elapsed.as_secs_f64());
+    PROCESSING.add(path, "_count", 1.0);
+}
+
+pub(crate) fn record_restart(supervisor: &BastionPath) {
+    RESTARTS.add(supervisor, "", 1.0);
+}
+
+pub(crate) fn set_active_children(supervisor: &BastionPath, count: usize) {
+    ACTIVE_CHILDREN.set(supervisor, count as f64);
+}
+
+pub(crate) fn set_circuit_state(children: &BastionPath, state: CircuitState) {
+    CIRCUIT_STATE.set(children, state.as_gauge());
+}
+
+pub(crate) fn set_blocking_threads(count: usize) {
+    BLOCKING_THREADS.set_unlabelled(count as f64);
+}
+
+pub(crate) fn record_blocking_rejected() {
+    BLOCKING_REJECTED.add_unlabelled("", 1.0);
+}
+
+/// Records the time `future` (a blocking task) spends waiting to be
+/// polled for the first time, and counts it as active until it
+/// finished (or was dropped).
+pub(crate) fn instrument_blocking<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let spawned_at = Instant::now();
+    async move {
+        BLOCKING_QUEUE_WAIT.add_unlabelled("_sum", spawned_at.elapsed().as_secs_f64());
+        BLOCKING_QUEUE_WAIT.add_unlabelled("_count", 1.0);
+
+        let _active = ActiveBlocking::start();
+        future.await
+    }
+}
+
+// Counts a blocking task as active while it is alive.
+struct ActiveBlocking;
+
+impl ActiveBlocking {
+    fn start() -> Self {
+        BLOCKING_ACTIVE.add_unlabelled("", 1.0);
+        ActiveBlocking
+    }
+}
+
+impl Drop for ActiveBlocking {
+    fn drop(&mut self) {
+        BLOCKING_ACTIVE.add_unlabelled("", -1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_families_in_the_text_format() {
+        let family = Family::new("test_total", "A test counter.", "counter");
+        let path = BastionPath::root();
+        family.add(&path, "", 1.0);
+        family.add(&path, "", 2.0);
+
+        let mut out = String::new();
+        family.render(&mut out);
+
+        assert_eq!(
+            out,
+            format!(
+                "# HELP test_total A test counter.\n# TYPE test_total counter\ntest_total{{path=\"{}\"}} 3\n",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn renders_unlabelled_values_without_labels() {
+        let family = Family::new("test_seconds", "A test summary.", "summary");
+        family.add_unlabelled("_sum", 0.5);
+        family.add_unlabelled("_count", 1.0);
+
+        let mut out = String::new();
+        family.render(&mut out);
+
+        assert_eq!(
+            out,
+            "# HELP test_seconds A test summary.\n# TYPE test_seconds summary\ntest_seconds_count 1\ntest_seconds_sum 0.5\n"
+        );
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
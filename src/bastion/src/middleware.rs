@@ -0,0 +1,222 @@
+//!
+//! Middleware are run around the delivery of every message to a
+//! child, allowing to implement logging, metrics, authorization
+//! or filtering once instead of inside every child's future.
+//!
+//! Middleware are registered for every child of the system using
+//! [`Bastion::add_middleware`], or for the elements of a children
+//! group using [`Children::with_middleware`]. The global ones run
+//! first, in the order they were added.
+//!
+//! [`Bastion::add_middleware`]: ../struct.Bastion.html#method.add_middleware
+//! [`Children::with_middleware`]: ../children/struct.Children.html#method.with_middleware
+use crate::child_ref::ChildRef;
+use crate::envelope::SignedMessage;
+use crate::path::BastionPath;
+use lazy_static::lazy_static;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+lazy_static! {
+    static ref GLOBAL: Mutex<Vec<Arc<dyn Middleware>>> = Mutex::new(Vec::new());
+}
+
+/// A trait implemented by the components run around the delivery
+/// of every message to a child.
+///
+/// It is implemented for the closures taking a [`SignedMessage`]
+/// and a [`Next`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// #[derive(Debug)]
+/// struct Logger;
+///
+/// impl Middleware for Logger {
+///     fn around(&self, msg: SignedMessage, next: Next) {
+///         println!("{}: Received {:?}", next.path(), msg.msg());
+///         next.run(msg);
+///     }
+/// }
+/// ```
+///
+/// [`SignedMessage`]: ../envelope/struct.SignedMessage.html
+/// [`Next`]: struct.Next.html
+pub trait Middleware: Send + Sync + 'static {
+    /// Called with every message delivered to a child, which is
+    /// only pushed to the child's mailbox once it is passed to
+    /// [`Next::run`] (which allows to modify it or to drop it,
+    /// by not running `next`).
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message delivered to the child.
+    /// * `next` - The rest of the middleware chain.
+    ///
+    /// [`Next::run`]: struct.Next.html#method.run
+    fn around(&self, msg: SignedMessage, next: Next);
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(SignedMessage, Next) + Send + Sync + 'static,
+{
+    fn around(&self, msg: SignedMessage, next: Next) {
+        self(msg, next)
+    }
+}
+
+/// The rest of the middleware chain a message goes through
+/// before being pushed to the mailbox of the child it is
+/// delivered to.
+pub struct Next<'a> {
+    child: &'a ChildRef,
+    chain: &'a [Arc<dyn Middleware>],
+    deliver: &'a mut dyn FnMut(SignedMessage),
+}
+
+#[derive(Default, Clone)]
+// The middleware registered for the elements of a children
+// group.
+pub(crate) struct Chain(Vec<Arc<dyn Middleware>>);
+
+impl<'a> Next<'a> {
+    /// Returns the path of the child the message is delivered to.
+    pub fn path(&self) -> &Arc<BastionPath> {
+        self.child.path()
+    }
+
+    /// Returns the child the message is delivered to.
+    pub fn child(&self) -> &ChildRef {
+        self.child
+    }
+
+    /// Passes the message to the next middleware of the chain, or
+    /// pushes it to the child's mailbox if there is none left.
+    ///
+    /// # Argument
+    ///
+    /// * `msg` - The message delivered to the child.
+    pub fn run(self, msg: SignedMessage) {
+        match self.chain.split_first() {
+            Some((middleware, chain)) => {
+                let next = Next {
+                    child: self.child,
+                    chain,
+                    deliver: self.deliver,
+                };
+                middleware.around(msg, next);
+            }
+            None => (self.deliver)(msg),
+        }
+    }
+}
+
+impl Chain {
+    pub(crate) fn push(&mut self, middleware: Arc<dyn Middleware>) {
+        self.0.push(middleware);
+    }
+}
+
+pub(crate) fn add(middleware: Arc<dyn Middleware>) {
+    // FIXME: panics
+    GLOBAL.lock().unwrap().push(middleware);
+}
+
+/// Runs the global middleware and the given chain around the
+/// delivery of `msg`, which is eventually passed to `deliver`.
+pub(crate) fn deliver<D>(child: &ChildRef, chain: &Chain, msg: SignedMessage, mut deliver: D)
+where
+    D: FnMut(SignedMessage),
+{
+    // FIXME: panics
+    let mut middleware = GLOBAL.lock().unwrap().clone();
+    if middleware.is_empty() && chain.0.is_empty() {
+        return deliver(msg);
+    }
+
+    middleware.extend(chain.0.iter().cloned());
+    let next = Next {
+        child,
+        chain: &middleware,
+        deliver: &mut deliver,
+    };
+    next.run(msg);
+}
+
+impl<'a> Debug for Next<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Next")
+            .field("child", &self.child.id())
+            .field("remaining", &self.chain.len())
+            .finish()
+    }
+}
+
+impl Debug for Chain {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Chain")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::BastionId;
+    use crate::envelope::RefAddr;
+    use crate::message::Msg;
+    use futures::channel::mpsc;
+
+    fn signed(msg: &'static str) -> SignedMessage {
+        let (sender, _) = mpsc::unbounded();
+        let path = Arc::new(BastionPath::root());
+        let sign = RefAddr::new(path, sender.into());
+
+        SignedMessage::new(Msg::tell(msg), sign)
+    }
+
+    fn child() -> ChildRef {
+        let (sender, _) = mpsc::unbounded();
+        let path = Arc::new(BastionPath::root());
+
+        ChildRef::new(BastionId::new(), sender.into(), "child".to_string(), path)
+    }
+
+    fn recording(calls: &Arc<Mutex<Vec<&'static str>>>, name: &'static str) -> Arc<dyn Middleware> {
+        let calls = calls.clone();
+        Arc::new(move |msg: SignedMessage, next: Next| {
+            calls.lock().unwrap().push(name);
+            next.run(msg);
+        })
+    }
+
+    #[test]
+    fn runs_the_chain_in_order() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = Chain::default();
+        chain.push(recording(&calls, "first"));
+        chain.push(recording(&calls, "second"));
+
+        let mut delivered = 0;
+        deliver(&child(), &chain, signed("ping"), |_| delivered += 1);
+
+        assert_eq!(delivered, 1);
+        assert_eq!(*calls.lock().unwrap(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn middleware_can_drop_messages() {
+        let mut chain = Chain::default();
+        chain.push(Arc::new(|_: SignedMessage, _: Next| ()));
+
+        let mut delivered = 0;
+        deliver(&child(), &chain, signed("ping"), |_| delivered += 1);
+
+        assert_eq!(delivered, 0);
+    }
+}
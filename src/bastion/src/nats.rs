@@ -0,0 +1,294 @@
+//!
+//! The NATS connector bridges the subjects of a NATS server to the
+//! dispatchers of the system, and is available with the `nats`
+//! feature.
+//!
+//! Subscribing a dispatcher to a subject (using [`Nats::subscribe`])
+//! spawns a subscriber child under the connector's supervisor, which
+//! broadcasts every message published on the subject to the children
+//! groups registered with the dispatcher's name, as a
+//! [`NatsMessage`]. Messages are published back using
+//! [`Nats::tell_remote`].
+//!
+//! The subscriber children share the connector's connection, which
+//! reconnects to the server on its own. A subscriber child fails when
+//! its subscription ends without being asked to (e.g. because the
+//! connection was closed for good), and is restarted by the
+//! supervisor, which subscribes to the subject again. The messages
+//! sent to a subscriber child are forwarded to the dead letters.
+//!
+//! [`Nats::subscribe`]: struct.Nats.html#method.subscribe
+//! [`NatsMessage`]: struct.NatsMessage.html
+//! [`Nats::tell_remote`]: struct.Nats.html#method.tell_remote
+use crate::children_ref::ChildrenRef;
+use crate::context::BastionContext;
+use crate::dead_letters;
+use crate::dispatcher::BroadcastTarget;
+use crate::executor;
+use crate::supervisor::SupervisorRef;
+use crate::Bastion;
+use futures::channel::mpsc;
+use futures::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::{self, Debug, Formatter};
+use std::io;
+use tracing::{debug, error, trace, warn};
+
+#[derive(Clone)]
+/// A connection to a NATS server, bridging its subjects to the
+/// dispatchers of the system.
+///
+/// Clones of a connector share its connection and supervisor.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use bastion::prelude::*;
+/// # use std::sync::Arc;
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// # Bastion::start();
+/// #
+/// Bastion::children(|children| {
+///     children
+///         .with_dispatcher(Dispatcher::with_type(DispatcherType::Named("orders".to_string())))
+///         .with_exec(|ctx| async move {
+///             loop {
+///                 msg! { ctx.recv().await?,
+///                     raw_message: Arc<SignedMessage> => {
+///                         let message = Arc::try_unwrap(raw_message).unwrap();
+///                         msg! { message,
+///                             ref order: NatsMessage => {
+///                                 // Handle the order...
+///                                 order.respond(b"accepted").ok();
+///                             };
+///                             _: _ => ();
+///                         }
+///                     };
+///                     _: _ => ();
+///                 }
+///             }
+///         })
+/// }).expect("Couldn't create the children group.");
+///
+/// let nats = Nats::connect("nats://localhost:4222").expect("Couldn't connect.");
+/// nats.subscribe("orders.created", "orders").expect("Couldn't subscribe.");
+/// nats.tell_remote("orders.audited", &"order #1").expect("Couldn't publish.");
+/// # }
+/// ```
+pub struct Nats {
+    url: String,
+    connection: nats::Connection,
+    supervisor: SupervisorRef,
+}
+
+#[derive(Clone)]
+/// A message published on a NATS subject, broadcasted to the
+/// children groups of the dispatcher subscribed to it.
+pub struct NatsMessage(nats::Message);
+
+// Unsubscribes once the subscriber child is stopped, which ends the
+// thread of the handler.
+struct Unsubscribe(Option<nats::Handler>);
+
+impl Nats {
+    /// Connects to a NATS server, creating the supervisor of the
+    /// subscriber children.
+    ///
+    /// # Argument
+    ///
+    /// * `url` - The url of the server (or the comma-separated urls
+    ///     of the servers of a cluster).
+    pub fn connect(url: &str) -> io::Result<Self> {
+        let connection = nats::connect(url)?;
+        let supervisor = Bastion::supervisor(|sp| sp).map_err(|()| {
+            io::Error::new(io::ErrorKind::Other, "Couldn't create the supervisor.")
+        })?;
+
+        debug!("Nats({}): Connected.", url);
+        Ok(Nats {
+            url: url.to_string(),
+            connection,
+            supervisor,
+        })
+    }
+
+    /// Subscribes a dispatcher to a subject, broadcasting the
+    /// messages published on it to the children groups of the
+    /// dispatcher.
+    ///
+    /// This method returns the children group of the subscriber
+    /// child if it succeeded, or an error otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `subject` - The subject to subscribe to (which can contain
+    ///     wildcards).
+    /// * `dispatcher` - The name of the dispatcher the messages are
+    ///     broadcasted to.
+    pub fn subscribe(&self, subject: &str, dispatcher: &str) -> Result<ChildrenRef, ()> {
+        let nats = self.clone();
+        let subject = subject.to_string();
+        let dispatcher = dispatcher.to_string();
+        self.supervisor.children(|children| {
+            children
+                .with_name(format!("nats/{}", subject))
+                .with_exec(move |ctx: BastionContext| {
+                    let nats = nats.clone();
+                    let subject = subject.clone();
+                    let dispatcher = dispatcher.clone();
+                    async move { nats.run_subscriber(ctx, subject, dispatcher).await }
+                })
+        })
+    }
+
+    /// Publishes a message on a subject, serialized as JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `subject` - The subject to publish the message on.
+    /// * `msg` - The published message.
+    pub fn tell_remote<M: Serialize>(&self, subject: &str, msg: &M) -> io::Result<()> {
+        let data = serde_json::to_vec(msg)?;
+        self.publish(subject, data)
+    }
+
+    /// Publishes raw data on a subject.
+    ///
+    /// # Arguments
+    ///
+    /// * `subject` - The subject to publish the data on.
+    /// * `data` - The published data.
+    pub fn publish(&self, subject: &str, data: impl AsRef<[u8]>) -> io::Result<()> {
+        trace!("Nats({}): Publishing on: {}", self.url, subject);
+        self.connection.publish(subject, data)
+    }
+
+    /// Returns the supervisor of the subscriber children.
+    pub fn supervisor(&self) -> &SupervisorRef {
+        &self.supervisor
+    }
+
+    async fn run_subscriber(
+        self,
+        ctx: BastionContext,
+        subject: String,
+        dispatcher: String,
+    ) -> Result<(), ()> {
+        // The subscription is made again every time the child is
+        // restarted.
+        let subscribe = {
+            let (connection, subject) = (self.connection.clone(), subject.clone());
+            executor::blocking(async move { connection.subscribe(&subject) })
+        };
+        let subscription = match subscribe.await {
+            Some(Ok(subscription)) => subscription,
+            Some(Err(err)) => {
+                error!(
+                    "Nats({}): Couldn't subscribe to {}: {}",
+                    self.url, subject, err
+                );
+                return Err(());
+            }
+            None => return Err(()),
+        };
+
+        // The handler's thread blocks until a message is published
+        // on the subject, and ends (closing the channel) with the
+        // subscription.
+        let (sender, mut messages) = mpsc::unbounded();
+        let handler = subscription.with_handler(move |msg| {
+            sender.unbounded_send(msg).map_err(|_| {
+                io::Error::new(io::ErrorKind::BrokenPipe, "The subscriber child stopped.")
+            })
+        });
+        let _unsubscribe = Unsubscribe(Some(handler));
+
+        debug!("Nats({}): Subscribed to: {}", self.url, subject);
+        let target = BroadcastTarget::Group(dispatcher);
+        loop {
+            futures::select! {
+                msg = messages.next() => match msg {
+                    Some(msg) => {
+                        trace!("Nats({}): Received message on: {}", self.url, msg.subject);
+                        ctx.broadcast_message(target.clone(), NatsMessage(msg));
+                    }
+                    None => {
+                        warn!("Nats({}): Lost the subscription to {}.", self.url, subject);
+                        return Err(());
+                    }
+                },
+                msg = ctx.recv().fuse() => {
+                    let (msg, sign) = msg?.extract();
+                    debug!(
+                        "Nats({}): Forwarding message to the dead letters: {:?}",
+                        self.url, msg
+                    );
+                    dead_letters::publish_message(ctx.current().path().clone(), msg, sign);
+                }
+            }
+        }
+    }
+}
+
+impl NatsMessage {
+    /// Returns the subject the message was published on.
+    pub fn subject(&self) -> &str {
+        &self.0.subject
+    }
+
+    /// Returns the raw data of the message.
+    pub fn data(&self) -> &[u8] {
+        &self.0.data
+    }
+
+    /// Returns the subject the replies to the message are
+    /// expected on, if any.
+    pub fn reply(&self) -> Option<&str> {
+        self.0.reply.as_deref()
+    }
+
+    /// Deserializes the data of the message from JSON.
+    pub fn decode<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.0.data)
+    }
+
+    /// Publishes a reply to the message, failing if it doesn't
+    /// expect any.
+    ///
+    /// # Argument
+    ///
+    /// * `data` - The data of the reply.
+    pub fn respond(&self, data: impl AsRef<[u8]>) -> io::Result<()> {
+        self.0.respond(data)
+    }
+}
+
+impl Debug for Nats {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Nats")
+            .field("url", &self.url)
+            .field("supervisor", &self.supervisor.id())
+            .finish()
+    }
+}
+
+impl Drop for Unsubscribe {
+    fn drop(&mut self) {
+        if let Some(handler) = self.0.take() {
+            handler.unsubscribe().ok();
+        }
+    }
+}
+
+impl Debug for NatsMessage {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("NatsMessage")
+            .field("subject", &self.0.subject)
+            .field("reply", &self.0.reply)
+            .field("len", &self.0.data.len())
+            .finish()
+    }
+}
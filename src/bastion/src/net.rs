@@ -0,0 +1,493 @@
+//!
+//! Network helpers running the connections of a server as
+//! supervised children.
+//!
+//! A [`TcpServer`] accepts the connections made to the address it
+//! was bound to, and serves every one of them with a new children
+//! group (containing a single element), created under the server's
+//! supervisor by the builder given to [`TcpServer::serve`]. The
+//! element gets its connection using
+//! [`BastionContext::tcp_connection`].
+//!
+//! When the element fails, it is restarted (following the server's
+//! restart strategy) and gets the same connection again. Once it
+//! finishes successfully, its children group is stopped and the
+//! connection is closed.
+//!
+//! A [`UdpEndpoint`] delivers the datagrams it receives to the
+//! elements of a children group, in turn, as [`Datagram`] messages.
+//! The elements reply to their peers using
+//! [`BastionContext::reply_datagram`].
+//!
+//! [`TcpServer`]: struct.TcpServer.html
+//! [`TcpServer::serve`]: struct.TcpServer.html#method.serve
+//! [`BastionContext::tcp_connection`]: ../context/struct.BastionContext.html#method.tcp_connection
+//! [`UdpEndpoint`]: struct.UdpEndpoint.html
+//! [`Datagram`]: struct.Datagram.html
+//! [`BastionContext::reply_datagram`]: ../context/struct.BastionContext.html#method.reply_datagram
+use crate::child::{Exec, Init};
+use crate::children::Children;
+use crate::children_ref::ChildrenRef;
+use crate::context::{BastionContext, BastionId};
+use crate::source::Feeder;
+use crate::supervisor::{RestartStrategy, SupervisorRef};
+use crate::Bastion;
+use futures_timer::Delay;
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+use nuclei::Handle;
+use std::fmt::{self, Debug, Formatter};
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, trace, warn};
+
+lazy_static! {
+    // The sockets of the endpoints delivering datagrams to each
+    // children group, used by their elements to reply.
+    static ref ENDPOINTS: Mutex<FxHashMap<BastionId, Arc<Handle<UdpSocket>>>> =
+        Mutex::new(FxHashMap::default());
+}
+
+// How long the server waits before checking again whether it can
+// accept a new connection, once it serves as many as it can.
+const ACCEPT_DELAY: Duration = Duration::from_millis(10);
+
+/// A TCP server serving every connection it accepts with a
+/// supervised children group.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use bastion::prelude::*;
+/// # use futures::io;
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// # Bastion::start();
+/// #
+/// TcpServer::bind("127.0.0.1:2000")
+///     .expect("Couldn't bind the server.")
+///     .with_max_connections(1024)
+///     .serve(|children| {
+///         children.with_exec(|ctx: BastionContext| async move {
+///             let conn = ctx.tcp_connection().unwrap();
+///
+///             // Echoes the messages of the client back to it.
+///             io::copy(conn.stream(), &mut conn.stream()).await.ok();
+///             Ok(())
+///         })
+///     })
+///     .expect("Couldn't serve the connections.");
+/// # }
+/// ```
+pub struct TcpServer {
+    listener: Arc<Handle<TcpListener>>,
+    max_connections: Option<usize>,
+    restart_strategy: RestartStrategy,
+}
+
+#[derive(Clone)]
+/// A connection accepted by a [`TcpServer`], given to the element
+/// serving it.
+///
+/// The connection is closed once the children group serving it was
+/// stopped and all its clones were dropped.
+///
+/// [`TcpServer`]: struct.TcpServer.html
+pub struct TcpConnection {
+    stream: Arc<Handle<TcpStream>>,
+    peer_addr: SocketAddr,
+    _slot: Arc<Slot>,
+}
+
+// Released once the children group serving a connection was
+// stopped.
+struct Slot(Arc<AtomicUsize>);
+
+/// A UDP endpoint delivering the datagrams it receives to a
+/// children group.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use bastion::prelude::*;
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// # Bastion::start();
+/// #
+/// let handlers = Bastion::children(|children| {
+///     children
+///         .with_redundancy(4)
+///         .with_exec(|ctx| async move {
+///             loop {
+///                 msg! { ctx.recv().await?,
+///                     datagram: Datagram => {
+///                         // Resolve the query...
+///                         ctx.reply_datagram(datagram.peer(), b"answer").await.ok();
+///                     };
+///                     _: _ => ();
+///                 }
+///             }
+///         })
+/// }).expect("Couldn't create the children group.");
+///
+/// UdpEndpoint::bind("127.0.0.1:5353")
+///     .expect("Couldn't bind the endpoint.")
+///     .deliver_to(&handlers)
+///     .expect("Couldn't receive the datagrams.");
+/// # }
+/// ```
+pub struct UdpEndpoint {
+    socket: Arc<Handle<UdpSocket>>,
+    max_datagram_size: usize,
+}
+
+#[derive(Debug, Clone)]
+/// A datagram received by a [`UdpEndpoint`].
+///
+/// [`UdpEndpoint`]: struct.UdpEndpoint.html
+pub struct Datagram {
+    peer: SocketAddr,
+    data: Vec<u8>,
+}
+
+impl TcpServer {
+    /// Creates a new server listening on the given address.
+    ///
+    /// # Argument
+    ///
+    /// * `addr` - The address to listen on.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = Handle::<TcpListener>::bind(addr)?;
+
+        Ok(TcpServer {
+            listener: Arc::new(listener),
+            max_connections: None,
+            restart_strategy: RestartStrategy::default(),
+        })
+    }
+
+    /// Sets the maximum amount of connections served at once, above
+    /// which the server waits for connections to be closed before
+    /// accepting new ones.
+    ///
+    /// By default, the amount of connections isn't limited.
+    ///
+    /// # Argument
+    ///
+    /// * `max_connections` - The maximum amount of connections
+    ///     served at once (at least `1`).
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = Some(max_connections.max(1));
+        self
+    }
+
+    /// Sets the strategy used to restart the element serving a
+    /// connection when it fails.
+    ///
+    /// # Argument
+    ///
+    /// * `restart_strategy` - The [`RestartStrategy`] of the
+    ///     connections.
+    ///
+    /// [`RestartStrategy`]: ../supervisor/struct.RestartStrategy.html
+    pub fn with_restart_strategy(mut self, restart_strategy: RestartStrategy) -> Self {
+        self.restart_strategy = restart_strategy;
+        self
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.get_ref().local_addr()
+    }
+
+    /// Starts accepting the connections, under a new supervisor,
+    /// and serving every one of them with a children group built
+    /// by `builder`.
+    ///
+    /// This method returns the supervisor of the connections if it
+    /// succeeded, or `Err(())` otherwise.
+    ///
+    /// # Argument
+    ///
+    /// * `builder` - The closure taking a new [`Children`] and
+    ///     returning it once configured, called for every
+    ///     connection.
+    ///
+    /// [`Children`]: ../children/struct.Children.html
+    pub fn serve<C>(self, builder: C) -> Result<SupervisorRef, ()>
+    where
+        C: Fn(Children) -> Children + Send + Sync + 'static,
+    {
+        let restart_strategy = self.restart_strategy.clone();
+        let connections = Bastion::supervisor(|sp| sp.with_restart_strategy(restart_strategy))?;
+
+        let builder = Arc::new(builder);
+        let served = Arc::new(AtomicUsize::new(0));
+        let supervisor = connections.clone();
+        connections.children(|children| {
+            children
+                .with_name("tcp-server")
+                .with_exec(move |_: BastionContext| {
+                    let server = self.clone();
+                    let builder = builder.clone();
+                    let served = served.clone();
+                    let supervisor = supervisor.clone();
+                    async move { server.accept(supervisor, builder, served).await }
+                })
+        })?;
+
+        Ok(connections)
+    }
+
+    async fn accept<C>(
+        self,
+        supervisor: SupervisorRef,
+        builder: Arc<C>,
+        served: Arc<AtomicUsize>,
+    ) -> Result<(), ()>
+    where
+        C: Fn(Children) -> Children + Send + Sync + 'static,
+    {
+        let addr = self.local_addr().map_err(|_| ())?;
+        debug!("TcpServer({}): Accepting connections.", addr);
+        loop {
+            if let Some(max_connections) = self.max_connections {
+                while served.load(Ordering::SeqCst) >= max_connections {
+                    Delay::new(ACCEPT_DELAY).await;
+                }
+            }
+
+            let (stream, peer_addr) = self.listener.accept().await.map_err(|err| {
+                warn!("TcpServer({}): Couldn't accept connection: {}", addr, err);
+            })?;
+
+            trace!("TcpServer({}): Accepted connection of: {}", addr, peer_addr);
+            served.fetch_add(1, Ordering::SeqCst);
+            let conn = TcpConnection {
+                stream: Arc::new(stream),
+                peer_addr,
+                _slot: Arc::new(Slot(served.clone())),
+            };
+
+            let builder = builder.clone();
+            let res = supervisor.children(move |children| {
+                builder(children.with_name(format!("tcp/{}", peer_addr)))
+                    .with_redundancy(1)
+                    .map_init(|init| conn.serve(init))
+            });
+            if res.is_err() {
+                warn!("TcpServer({}): Couldn't serve: {}", addr, peer_addr);
+            }
+        }
+    }
+}
+
+impl UdpEndpoint {
+    /// Creates a new endpoint bound to the given address.
+    ///
+    /// # Argument
+    ///
+    /// * `addr` - The address to bind to.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let socket = Handle::<UdpSocket>::bind(addr)?;
+
+        Ok(UdpEndpoint {
+            socket: Arc::new(socket),
+            max_datagram_size: 65_507,
+        })
+    }
+
+    /// Sets the size of the largest datagram that can be received,
+    /// above which datagrams are truncated.
+    ///
+    /// By default, datagrams of up to 65507 bytes are received.
+    ///
+    /// # Argument
+    ///
+    /// * `size` - The size of the largest datagram, in bytes.
+    pub fn with_max_datagram_size(mut self, size: usize) -> Self {
+        self.max_datagram_size = size;
+        self
+    }
+
+    /// Returns the address the endpoint is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.get_ref().local_addr()
+    }
+
+    /// Starts receiving the datagrams, under a new supervisor, and
+    /// delivering them to the elements of `handlers` in turn.
+    ///
+    /// This method returns the supervisor of the endpoint's receiver
+    /// if it succeeded, or `Err(())` otherwise.
+    ///
+    /// # Argument
+    ///
+    /// * `handlers` - The children group the datagrams are
+    ///     delivered to.
+    pub fn deliver_to(self, handlers: &ChildrenRef) -> Result<SupervisorRef, ()> {
+        // FIXME: panics
+        ENDPOINTS
+            .lock()
+            .unwrap()
+            .insert(handlers.id().clone(), self.socket.clone());
+
+        let supervisor = Bastion::supervisor(|sp| sp)?;
+        let group = handlers.id().clone();
+        supervisor.children(|children| {
+            children
+                .with_name("udp-endpoint")
+                .with_exec(move |_: BastionContext| {
+                    let socket = self.socket.clone();
+                    let feeder = Feeder::new(group.clone());
+                    let max_datagram_size = self.max_datagram_size;
+                    async move { receive(socket, feeder, max_datagram_size).await }
+                })
+        })?;
+
+        Ok(supervisor)
+    }
+}
+
+impl Datagram {
+    /// Returns the address of the peer that sent the datagram.
+    pub fn peer(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// Returns the data of the datagram.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the data of the datagram, consuming it.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+async fn receive(
+    socket: Arc<Handle<UdpSocket>>,
+    mut feeder: Feeder,
+    max_datagram_size: usize,
+) -> Result<(), ()> {
+    let addr = socket.get_ref().local_addr().map_err(|_| ())?;
+    debug!("UdpEndpoint({}): Receiving datagrams.", addr);
+    let mut buf = vec![0; max_datagram_size];
+    loop {
+        let (len, peer) = socket.recv_from(&mut buf).await.map_err(|err| {
+            warn!("UdpEndpoint({}): Couldn't receive: {}", addr, err);
+        })?;
+
+        trace!(
+            "UdpEndpoint({}): Received {} bytes from: {}",
+            addr,
+            len,
+            peer
+        );
+        let data = buf[..len].to_vec();
+        feeder.feed(Datagram { peer, data }).await;
+    }
+}
+
+/// Sends a datagram to `peer` from the socket of the endpoint
+/// delivering datagrams to the given children group.
+pub(crate) async fn send_datagram(
+    group: &BastionId,
+    peer: SocketAddr,
+    data: &[u8],
+) -> io::Result<usize> {
+    // FIXME: panics
+    let socket = ENDPOINTS.lock().unwrap().get(group).cloned();
+    match socket {
+        Some(socket) => socket.send_to(data, peer).await,
+        None => Err(io::Error::new(
+            io::ErrorKind::NotConnected,
+            "No endpoint delivers datagrams to the group.",
+        )),
+    }
+}
+
+impl TcpConnection {
+    /// Returns the stream of the connection.
+    pub fn stream(&self) -> &Handle<TcpStream> {
+        &self.stream
+    }
+
+    /// Returns the address of the client.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    #[cfg(feature = "websocket")]
+    pub(crate) fn shared_stream(&self) -> Arc<Handle<TcpStream>> {
+        self.stream.clone()
+    }
+
+    // Gives the connection to every incarnation of the element
+    // serving it, and stops its group once it finished.
+    fn serve(self, init: Init) -> Init {
+        Init(Box::new(move |ctx: BastionContext| {
+            let group = ctx.parent().clone();
+            ctx.state_bag().insert(self.clone());
+
+            let Exec(exec) = (init.0)(ctx);
+            Exec(Box::pin(async move {
+                let res = exec.await;
+                if res.is_ok() {
+                    group.stop().ok();
+                }
+
+                res
+            }))
+        }))
+    }
+}
+
+impl Clone for TcpServer {
+    fn clone(&self) -> Self {
+        TcpServer {
+            listener: self.listener.clone(),
+            max_connections: self.max_connections,
+            restart_strategy: self.restart_strategy.clone(),
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Debug for TcpServer {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("TcpServer")
+            .field("local_addr", &self.listener.get_ref().local_addr().ok())
+            .field("max_connections", &self.max_connections)
+            .field("restart_strategy", &self.restart_strategy)
+            .finish()
+    }
+}
+
+impl Debug for UdpEndpoint {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("UdpEndpoint")
+            .field("local_addr", &self.socket.get_ref().local_addr().ok())
+            .field("max_datagram_size", &self.max_datagram_size)
+            .finish()
+    }
+}
+
+impl Debug for TcpConnection {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("TcpConnection")
+            .field("peer_addr", &self.peer_addr)
+            .finish()
+    }
+}
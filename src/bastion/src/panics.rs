@@ -0,0 +1,94 @@
+//! Catches the panics of the children's futures, capturing their
+//! message and backtrace.
+//!
+//! The backtrace of a panic is only available from within the panic
+//! hook, so a hook is installed the first time a child is polled. It
+//! only captures the backtraces of the panics happening while a
+//! child's future is polled and then calls the previous hook, leaving
+//! the other panics untouched.
+//!
+//! Backtraces are captured following the `RUST_BACKTRACE` and
+//! `RUST_LIB_BACKTRACE` environment variables.
+use crate::errors::ChildError;
+use std::any::Any;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+static INSTALL_HOOK: Once = Once::new();
+
+thread_local! {
+    // Whether a child's future is being polled on this thread.
+    static CAPTURING: Cell<bool> = Cell::new(false);
+    // The backtrace of the last panic of a child's future.
+    static BACKTRACE: RefCell<Option<String>> = RefCell::new(None);
+}
+
+fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CAPTURING.with(Cell::get) {
+                let backtrace = Backtrace::capture();
+                if backtrace.status() == BacktraceStatus::Captured {
+                    BACKTRACE.with(|last| *last.borrow_mut() = Some(backtrace.to_string()));
+                }
+            }
+
+            previous(info);
+        }));
+    });
+}
+
+/// Calls the closure, returning a [`ChildError::Panic`] if it
+/// panicked.
+pub(crate) fn catch_unwind<F, R>(f: F) -> Result<R, ChildError>
+where
+    F: FnOnce() -> R,
+{
+    install_hook();
+
+    BACKTRACE.with(|last| last.borrow_mut().take());
+    let capturing = CAPTURING.with(|capturing| capturing.replace(true));
+    let res = panic::catch_unwind(AssertUnwindSafe(f));
+    CAPTURING.with(|current| current.set(capturing));
+
+    res.map_err(|payload| ChildError::Panic {
+        message: panic_message(&*payload),
+        backtrace: BACKTRACE.with(|last| last.borrow_mut().take()),
+    })
+}
+
+/// Returns whether the panics happening on this thread are caught
+/// (i.e. whether a child's future is being polled).
+pub(crate) fn is_catching() -> bool {
+    CAPTURING.with(Cell::get)
+}
+
+/// Returns the message of a panic, if it was a string.
+fn panic_message(payload: &(dyn Any + Send)) -> Option<String> {
+    match payload.downcast_ref::<&'static str>() {
+        Some(msg) => Some(msg.to_string()),
+        None => payload.downcast_ref::<String>().cloned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_the_panic_message() {
+        let res: Result<(), _> = catch_unwind(|| panic!("boom"));
+        match res {
+            Err(ChildError::Panic { message, .. }) => assert_eq!(message.as_deref(), Some("boom")),
+            _ => panic!("the panic wasn't caught"),
+        }
+    }
+
+    #[test]
+    fn returns_the_result_without_panic() {
+        assert_eq!(catch_unwind(|| 42).ok(), Some(42));
+    }
+}
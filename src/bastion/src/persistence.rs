@@ -0,0 +1,841 @@
+//!
+//! Persistence allows children to keep their state across
+//! restarts using event sourcing, and is available with the
+//! `persistence` feature.
+//!
+//! A [`PersistentChild`] is a state that changes by applying
+//! events. Instead of changing the state directly, a child
+//! persists events to a [`Journal`] using [`Persistent::persist`],
+//! which applies them once they were appended. When the child
+//! gets restarted, its state is recovered by replaying all the
+//! events of the journal, in the order they were persisted in.
+//!
+//! The journal of a children group is set using
+//! [`Children::with_journal`], and the state of a child is
+//! recovered using [`BastionContext::persistent`]. Because a child's
+//! future is created again every time it is restarted, calling it at
+//! the start of the future is all it takes for the state to survive
+//! crashes.
+//!
+//! Replaying every event can get slow once a journal grows large:
+//! [`Persistent::save_snapshot`] saves the whole state to the journal,
+//! and [`BastionContext::persistent_with_snapshots`] recovers the state
+//! from the latest snapshot by only replaying the events persisted
+//! after it. The amount of snapshots kept by a children group is set
+//! using [`Children::with_snapshot_retention`].
+//!
+//! [`MemoryJournal`] keeps events in memory (and thus only allows the
+//! state to survive restarts of children, not of the process), while
+//! `SledJournal`, available with the `persistence-sled` feature, keeps
+//! them in a [sled] database. Other backends can be plugged by
+//! implementing [`Journal`].
+//!
+//! [sled]: https://docs.rs/sled/
+//! [`PersistentChild`]: trait.PersistentChild.html
+//! [`Journal`]: trait.Journal.html
+//! [`Persistent::persist`]: struct.Persistent.html#method.persist
+//! [`Children::with_journal`]: ../children/struct.Children.html#method.with_journal
+//! [`BastionContext::persistent`]: ../context/struct.BastionContext.html#method.persistent
+//! [`MemoryJournal`]: struct.MemoryJournal.html
+//! [`Persistent::save_snapshot`]: struct.Persistent.html#method.save_snapshot
+//! [`BastionContext::persistent_with_snapshots`]: ../context/struct.BastionContext.html#method.persistent_with_snapshots
+//! [`Children::with_snapshot_retention`]: ../children/struct.Children.html#method.with_snapshot_retention
+use crate::errors::PersistenceError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "persistence-sled")]
+use tracing::warn;
+use tracing::{debug, trace};
+
+/// A state that changes by applying events, which can be persisted
+/// to a [`Journal`] to recover the state after a restart.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// #
+/// #[derive(Default)]
+/// struct Counter {
+///     value: i64,
+/// }
+///
+/// #[derive(Serialize, Deserialize)]
+/// enum CounterEvent {
+///     Added(i64),
+/// }
+///
+/// impl PersistentChild for Counter {
+///     type Event = CounterEvent;
+///
+///     fn apply(&mut self, event: &CounterEvent) {
+///         match event {
+///             CounterEvent::Added(value) => self.value += value,
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`Journal`]: trait.Journal.html
+pub trait PersistentChild: Send + 'static {
+    /// The type of the events changing the state.
+    type Event: Serialize + DeserializeOwned + Send;
+
+    /// Changes the state by applying the event. This is called
+    /// once the event was persisted, and for every persisted event
+    /// when the state is recovered.
+    fn apply(&mut self, event: &Self::Event);
+}
+
+/// A storage for the events persisted by persistent children,
+/// identified by their persistence id.
+///
+/// Events are given to the journal serialized, along with their
+/// sequence number, which starts at `1` and is incremented by one
+/// for each event of a persistence id.
+pub trait Journal: Debug + Send + Sync + 'static {
+    /// Appends the event to the events of the persistence id.
+    ///
+    /// This should fail with [`PersistenceError::SequenceMismatch`]
+    /// if `seq_nr` isn't the sequence number following the highest
+    /// one of the persistence id.
+    ///
+    /// [`PersistenceError::SequenceMismatch`]: ../errors/enum.PersistenceError.html#variant.SequenceMismatch
+    fn append(
+        &self,
+        persistence_id: &str,
+        seq_nr: u64,
+        event: Vec<u8>,
+    ) -> Result<(), PersistenceError>;
+
+    /// Returns the events of the persistence id whose sequence
+    /// number is greater than or equal to `from_seq_nr`, in order,
+    /// along with their sequence number.
+    fn replay(
+        &self,
+        persistence_id: &str,
+        from_seq_nr: u64,
+    ) -> Result<Vec<(u64, Vec<u8>)>, PersistenceError>;
+
+    /// Returns the highest sequence number of the persistence id,
+    /// or `0` if it doesn't have any event.
+    fn highest_seq_nr(&self, persistence_id: &str) -> Result<u64, PersistenceError>;
+
+    /// Saves a snapshot of the state of the persistence id, taken
+    /// once the event with the sequence number `seq_nr` was applied.
+    fn save_snapshot(
+        &self,
+        persistence_id: &str,
+        seq_nr: u64,
+        snapshot: Vec<u8>,
+    ) -> Result<(), PersistenceError>;
+
+    /// Returns the latest snapshot of the persistence id, along with
+    /// the sequence number it was taken at, if there is one.
+    fn load_snapshot(
+        &self,
+        persistence_id: &str,
+    ) -> Result<Option<(u64, Vec<u8>)>, PersistenceError>;
+
+    /// Deletes the snapshots of the persistence id, except for the
+    /// `keep` latest ones.
+    fn delete_snapshots(&self, persistence_id: &str, keep: usize) -> Result<(), PersistenceError>;
+}
+
+#[derive(Debug, Clone, Default)]
+/// A [`Journal`] keeping events in memory.
+///
+/// Clones of a `MemoryJournal` share their events, so a journal
+/// created outside of a children group's closure will survive
+/// restarts of the group.
+///
+/// [`Journal`]: trait.Journal.html
+pub struct MemoryJournal {
+    events: Arc<Mutex<HashMap<String, Vec<Vec<u8>>>>>,
+    // The snapshots of each persistence id, along with their
+    // sequence number, from the oldest to the latest.
+    snapshots: Arc<Mutex<HashMap<String, Vec<(u64, Vec<u8>)>>>>,
+}
+
+#[cfg(feature = "persistence-sled")]
+#[derive(Clone)]
+/// A [`Journal`] keeping events in a [sled] database, allowing the
+/// state of persistent children to survive restarts of the process.
+///
+/// Each event is stored along with a checksum, and the events are
+/// checked to be complete and intact when they are replayed: replaying
+/// a journal that was corrupted (e.g. by a crash during a write) fails
+/// with [`PersistenceError::Corrupted`]. Corrupted snapshots are
+/// skipped in favor of older ones.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// let journal = SledJournal::open("/var/lib/my-app/journal")
+///     .expect("Couldn't open the journal.");
+///
+/// Bastion::children(|children| {
+///     children
+///         .with_journal(journal.clone())
+///         .with_exec(|ctx| {
+///             async move {
+///                 // ...
+///                 # Ok(())
+///             }
+///         })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`Journal`]: trait.Journal.html
+/// [sled]: https://docs.rs/sled/
+/// [`PersistenceError::Corrupted`]: ../errors/enum.PersistenceError.html#variant.Corrupted
+pub struct SledJournal {
+    db: sled::Db,
+}
+
+/// The state of a persistent child, as recovered using
+/// [`BastionContext::persistent`] or [`Persistent::recover`].
+///
+/// [`BastionContext::persistent`]: ../context/struct.BastionContext.html#method.persistent
+/// [`Persistent::recover`]: #method.recover
+pub struct Persistent<S: PersistentChild> {
+    persistence_id: String,
+    journal: Arc<dyn Journal>,
+    state: S,
+    // The sequence number of the last event applied to
+    // the state.
+    seq_nr: u64,
+    // The amount of snapshots to keep when saving a new one,
+    // or `None` to keep all of them.
+    snapshot_retention: Option<usize>,
+}
+
+impl MemoryJournal {
+    /// Creates a new empty `MemoryJournal`.
+    pub fn new() -> Self {
+        MemoryJournal::default()
+    }
+}
+
+impl Journal for MemoryJournal {
+    fn append(
+        &self,
+        persistence_id: &str,
+        seq_nr: u64,
+        event: Vec<u8>,
+    ) -> Result<(), PersistenceError> {
+        let mut events = self.events.lock().unwrap();
+        let events = events.entry(persistence_id.to_string()).or_default();
+
+        let expected = events.len() as u64 + 1;
+        if seq_nr != expected {
+            return Err(PersistenceError::SequenceMismatch {
+                expected,
+                found: seq_nr,
+            });
+        }
+
+        events.push(event);
+        Ok(())
+    }
+
+    fn replay(
+        &self,
+        persistence_id: &str,
+        from_seq_nr: u64,
+    ) -> Result<Vec<(u64, Vec<u8>)>, PersistenceError> {
+        let events = self.events.lock().unwrap();
+        let events = match events.get(persistence_id) {
+            Some(events) => events,
+            None => return Ok(Vec::new()),
+        };
+
+        let replayed = (1..)
+            .zip(events.iter())
+            .filter(|(seq_nr, _)| *seq_nr >= from_seq_nr)
+            .map(|(seq_nr, event)| (seq_nr, event.clone()))
+            .collect();
+
+        Ok(replayed)
+    }
+
+    fn highest_seq_nr(&self, persistence_id: &str) -> Result<u64, PersistenceError> {
+        let events = self.events.lock().unwrap();
+        let highest = events
+            .get(persistence_id)
+            .map(|events| events.len() as u64)
+            .unwrap_or(0);
+
+        Ok(highest)
+    }
+
+    fn save_snapshot(
+        &self,
+        persistence_id: &str,
+        seq_nr: u64,
+        snapshot: Vec<u8>,
+    ) -> Result<(), PersistenceError> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let snapshots = snapshots.entry(persistence_id.to_string()).or_default();
+        snapshots.retain(|(snapshot_seq_nr, _)| *snapshot_seq_nr != seq_nr);
+        snapshots.push((seq_nr, snapshot));
+        snapshots.sort_by_key(|(seq_nr, _)| *seq_nr);
+
+        Ok(())
+    }
+
+    fn load_snapshot(
+        &self,
+        persistence_id: &str,
+    ) -> Result<Option<(u64, Vec<u8>)>, PersistenceError> {
+        let snapshots = self.snapshots.lock().unwrap();
+        let latest = snapshots
+            .get(persistence_id)
+            .and_then(|snapshots| snapshots.last().cloned());
+
+        Ok(latest)
+    }
+
+    fn delete_snapshots(&self, persistence_id: &str, keep: usize) -> Result<(), PersistenceError> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        if let Some(snapshots) = snapshots.get_mut(persistence_id) {
+            let deleted = snapshots.len().saturating_sub(keep);
+            snapshots.drain(..deleted);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "persistence-sled")]
+impl SledJournal {
+    /// Opens (or creates) the sled database at the given path and
+    /// uses it as a journal.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the database's directory.
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, PersistenceError> {
+        let db = sled::open(path).map_err(journal_error)?;
+        Ok(SledJournal::from_db(db))
+    }
+
+    /// Uses an already opened sled database as a journal. Events
+    /// are stored in a tree per persistence id, named after it.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The database to use.
+    pub fn from_db(db: sled::Db) -> Self {
+        SledJournal { db }
+    }
+
+    fn tree(&self, persistence_id: &str) -> Result<sled::Tree, PersistenceError> {
+        self.db
+            .open_tree(format!("bastion/journal/{}", persistence_id))
+            .map_err(journal_error)
+    }
+
+    fn snapshots_tree(&self, persistence_id: &str) -> Result<sled::Tree, PersistenceError> {
+        self.db
+            .open_tree(format!("bastion/snapshots/{}", persistence_id))
+            .map_err(journal_error)
+    }
+
+    fn highest(tree: &sled::Tree) -> Result<u64, PersistenceError> {
+        match tree.last().map_err(journal_error)? {
+            Some((key, _)) => decode_seq_nr(&key).ok_or(PersistenceError::Corrupted { seq_nr: 0 }),
+            None => Ok(0),
+        }
+    }
+}
+
+#[cfg(feature = "persistence-sled")]
+impl Journal for SledJournal {
+    fn append(
+        &self,
+        persistence_id: &str,
+        seq_nr: u64,
+        event: Vec<u8>,
+    ) -> Result<(), PersistenceError> {
+        let tree = self.tree(persistence_id)?;
+        let expected = SledJournal::highest(&tree)? + 1;
+        if seq_nr != expected {
+            return Err(PersistenceError::SequenceMismatch {
+                expected,
+                found: seq_nr,
+            });
+        }
+
+        let mut value = checksum(&event).to_be_bytes().to_vec();
+        value.extend(event);
+        // The event is only inserted if another one wasn't appended
+        // with the same sequence number in the meantime.
+        tree.compare_and_swap(seq_nr.to_be_bytes(), None as Option<&[u8]>, Some(value))
+            .map_err(journal_error)?
+            .map_err(|_| PersistenceError::SequenceMismatch {
+                expected: seq_nr + 1,
+                found: seq_nr,
+            })?;
+        tree.flush().map_err(journal_error)?;
+
+        Ok(())
+    }
+
+    fn replay(
+        &self,
+        persistence_id: &str,
+        from_seq_nr: u64,
+    ) -> Result<Vec<(u64, Vec<u8>)>, PersistenceError> {
+        let tree = self.tree(persistence_id)?;
+        let mut expected = from_seq_nr.max(1);
+        let mut replayed = Vec::new();
+        for entry in tree.range(expected.to_be_bytes()..) {
+            let (key, value) = entry.map_err(journal_error)?;
+            // Missing events are considered as corrupted.
+            if decode_seq_nr(&key) != Some(expected) || value.len() < 4 {
+                return Err(PersistenceError::Corrupted { seq_nr: expected });
+            }
+
+            let (sum, event) = value.split_at(4);
+            if sum != checksum(event).to_be_bytes() {
+                return Err(PersistenceError::Corrupted { seq_nr: expected });
+            }
+
+            replayed.push((expected, event.to_vec()));
+            expected += 1;
+        }
+
+        Ok(replayed)
+    }
+
+    fn highest_seq_nr(&self, persistence_id: &str) -> Result<u64, PersistenceError> {
+        SledJournal::highest(&self.tree(persistence_id)?)
+    }
+
+    fn save_snapshot(
+        &self,
+        persistence_id: &str,
+        seq_nr: u64,
+        snapshot: Vec<u8>,
+    ) -> Result<(), PersistenceError> {
+        let tree = self.snapshots_tree(persistence_id)?;
+        let mut value = checksum(&snapshot).to_be_bytes().to_vec();
+        value.extend(snapshot);
+        tree.insert(seq_nr.to_be_bytes(), value)
+            .map_err(journal_error)?;
+        tree.flush().map_err(journal_error)?;
+
+        Ok(())
+    }
+
+    fn load_snapshot(
+        &self,
+        persistence_id: &str,
+    ) -> Result<Option<(u64, Vec<u8>)>, PersistenceError> {
+        let tree = self.snapshots_tree(persistence_id)?;
+        for entry in tree.iter().rev() {
+            let (key, value) = entry.map_err(journal_error)?;
+            let seq_nr = decode_seq_nr(&key);
+            if let (Some(seq_nr), true) = (seq_nr, value.len() >= 4) {
+                let (sum, snapshot) = value.split_at(4);
+                if sum == checksum(snapshot).to_be_bytes() {
+                    return Ok(Some((seq_nr, snapshot.to_vec())));
+                }
+            }
+
+            warn!(
+                "SledJournal: Skipping corrupted snapshot of {}: {:?}",
+                persistence_id, seq_nr
+            );
+        }
+
+        Ok(None)
+    }
+
+    fn delete_snapshots(&self, persistence_id: &str, keep: usize) -> Result<(), PersistenceError> {
+        let tree = self.snapshots_tree(persistence_id)?;
+        for entry in tree.iter().rev().skip(keep) {
+            let (key, _) = entry.map_err(journal_error)?;
+            tree.remove(key).map_err(journal_error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: PersistentChild> Persistent<S> {
+    /// Recovers the state of the persistence id by applying all
+    /// the events of the journal to the initial state.
+    ///
+    /// This method returns the recovered state if it succeeded, or
+    /// `Err(error)` if the events couldn't be replayed.
+    ///
+    /// # Arguments
+    ///
+    /// * `persistence_id` - The id the events are persisted with.
+    /// * `journal` - The journal the events are persisted to.
+    /// * `initial` - The state before any event was applied.
+    pub fn recover<P>(
+        persistence_id: P,
+        journal: Arc<dyn Journal>,
+        initial: S,
+    ) -> Result<Self, PersistenceError>
+    where
+        P: Into<String>,
+    {
+        let persistence_id = persistence_id.into();
+        debug!("Persistent({}): Recovering.", persistence_id);
+        let mut persistent = Persistent {
+            persistence_id,
+            journal,
+            state: initial,
+            seq_nr: 0,
+            snapshot_retention: None,
+        };
+
+        persistent.replay()?;
+        Ok(persistent)
+    }
+
+    /// Recovers the state of the persistence id from its latest
+    /// snapshot, by only applying the events of the journal that
+    /// were persisted after the snapshot was saved. If there isn't
+    /// any snapshot, all the events are applied to the initial
+    /// state.
+    ///
+    /// This method returns the recovered state if it succeeded, or
+    /// `Err(error)` if the snapshot or the events couldn't be
+    /// replayed.
+    ///
+    /// # Arguments
+    ///
+    /// * `persistence_id` - The id the events are persisted with.
+    /// * `journal` - The journal the events are persisted to.
+    /// * `initial` - The state before any event was applied.
+    pub fn recover_from_snapshot<P>(
+        persistence_id: P,
+        journal: Arc<dyn Journal>,
+        initial: S,
+    ) -> Result<Self, PersistenceError>
+    where
+        S: DeserializeOwned,
+        P: Into<String>,
+    {
+        let persistence_id = persistence_id.into();
+        debug!("Persistent({}): Recovering from snapshot.", persistence_id);
+        let (state, seq_nr) = match journal.load_snapshot(&persistence_id)? {
+            Some((seq_nr, snapshot)) => {
+                debug!(
+                    "Persistent({}): Loading snapshot at sequence number {}.",
+                    persistence_id, seq_nr
+                );
+                let state = serde_json::from_slice(&snapshot)
+                    .map_err(|err| PersistenceError::Serialization(err.to_string()))?;
+                (state, seq_nr)
+            }
+            None => (initial, 0),
+        };
+
+        let mut persistent = Persistent {
+            persistence_id,
+            journal,
+            state,
+            seq_nr,
+            snapshot_retention: None,
+        };
+
+        persistent.replay()?;
+        Ok(persistent)
+    }
+
+    /// Sets the amount of snapshots kept when saving a new one
+    /// using [`save_snapshot`]. By default, all of them are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep` - The amount of snapshots to keep.
+    ///
+    /// [`save_snapshot`]: #method.save_snapshot
+    pub fn with_snapshot_retention(mut self, keep: usize) -> Self {
+        self.snapshot_retention = Some(keep);
+        self
+    }
+
+    // Applies the events persisted after the current sequence
+    // number to the state.
+    fn replay(&mut self) -> Result<(), PersistenceError> {
+        for (seq_nr, event) in self.journal.replay(&self.persistence_id, self.seq_nr + 1)? {
+            trace!(
+                "Persistent({}): Replaying event {}.",
+                self.persistence_id,
+                seq_nr
+            );
+            let event = serde_json::from_slice(&event)
+                .map_err(|err| PersistenceError::Serialization(err.to_string()))?;
+            self.state.apply(&event);
+            self.seq_nr = seq_nr;
+        }
+
+        debug!(
+            "Persistent({}): Recovered at sequence number {}.",
+            self.persistence_id, self.seq_nr
+        );
+        Ok(())
+    }
+
+    /// Appends the event to the journal and, once it was, applies
+    /// it to the state.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(error)`
+    /// if the event couldn't be persisted, in which case it wasn't
+    /// applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event to persist.
+    pub fn persist(&mut self, event: S::Event) -> Result<(), PersistenceError> {
+        let seq_nr = self.seq_nr + 1;
+        trace!(
+            "Persistent({}): Persisting event {}.",
+            self.persistence_id,
+            seq_nr
+        );
+        let bytes = serde_json::to_vec(&event)
+            .map_err(|err| PersistenceError::Serialization(err.to_string()))?;
+        self.journal.append(&self.persistence_id, seq_nr, bytes)?;
+
+        self.state.apply(&event);
+        self.seq_nr = seq_nr;
+        Ok(())
+    }
+
+    /// Saves a snapshot of the state to the journal, allowing
+    /// [`recover_from_snapshot`] to only replay the events persisted
+    /// after it. The oldest snapshots are then deleted, depending
+    /// on the snapshot retention (see [`with_snapshot_retention`]).
+    ///
+    /// This method returns `()` if it succeeded, or `Err(error)`
+    /// if the snapshot couldn't be saved.
+    ///
+    /// [`recover_from_snapshot`]: #method.recover_from_snapshot
+    /// [`with_snapshot_retention`]: #method.with_snapshot_retention
+    pub fn save_snapshot(&self) -> Result<(), PersistenceError>
+    where
+        S: Serialize,
+    {
+        debug!(
+            "Persistent({}): Saving snapshot at sequence number {}.",
+            self.persistence_id, self.seq_nr
+        );
+        let snapshot = serde_json::to_vec(&self.state)
+            .map_err(|err| PersistenceError::Serialization(err.to_string()))?;
+        self.journal
+            .save_snapshot(&self.persistence_id, self.seq_nr, snapshot)?;
+
+        if let Some(keep) = self.snapshot_retention {
+            self.journal.delete_snapshots(&self.persistence_id, keep)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the state, with all the persisted events applied.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Returns the sequence number of the last event applied to
+    /// the state, or `0` if none was.
+    pub fn seq_nr(&self) -> u64 {
+        self.seq_nr
+    }
+
+    /// Returns the id the events are persisted with.
+    pub fn persistence_id(&self) -> &str {
+        &self.persistence_id
+    }
+}
+
+#[cfg(feature = "persistence-sled")]
+impl Debug for SledJournal {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("SledJournal").finish()
+    }
+}
+
+impl<S: PersistentChild> Debug for Persistent<S> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Persistent")
+            .field("persistence_id", &self.persistence_id)
+            .field("journal", &self.journal)
+            .field("seq_nr", &self.seq_nr)
+            .field("snapshot_retention", &self.snapshot_retention)
+            .finish()
+    }
+}
+
+#[cfg(feature = "persistence-sled")]
+fn journal_error(err: sled::Error) -> PersistenceError {
+    PersistenceError::Journal(err.to_string())
+}
+
+#[cfg(feature = "persistence-sled")]
+fn decode_seq_nr(key: &[u8]) -> Option<u64> {
+    let mut bytes = [0; 8];
+    if key.len() != bytes.len() {
+        return None;
+    }
+
+    bytes.copy_from_slice(key);
+    Some(u64::from_be_bytes(bytes))
+}
+
+/// Returns the CRC-32 (IEEE) checksum of the bytes.
+#[cfg(feature = "persistence-sled")]
+fn checksum(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Counter(i64);
+
+    impl PersistentChild for Counter {
+        type Event = i64;
+
+        fn apply(&mut self, event: &i64) {
+            self.0 += event;
+        }
+    }
+
+    #[test]
+    fn recovers_persisted_events() {
+        let journal: Arc<dyn Journal> = Arc::new(MemoryJournal::new());
+
+        let mut counter = Persistent::recover("counter", journal.clone(), Counter(0)).unwrap();
+        counter.persist(1).unwrap();
+        counter.persist(2).unwrap();
+        assert_eq!(counter.state().0, 3);
+        drop(counter);
+
+        let counter = Persistent::recover("counter", journal.clone(), Counter(0)).unwrap();
+        assert_eq!(counter.state().0, 3);
+        assert_eq!(counter.seq_nr(), 2);
+
+        let other = Persistent::recover("other", journal, Counter(0)).unwrap();
+        assert_eq!(other.state().0, 0);
+    }
+
+    #[test]
+    fn rejects_out_of_sequence_events() {
+        let journal = MemoryJournal::new();
+        journal.append("counter", 1, b"1".to_vec()).unwrap();
+
+        match journal.append("counter", 3, b"1".to_vec()) {
+            Err(PersistenceError::SequenceMismatch { expected, found }) => {
+                assert_eq!((expected, found), (2, 3));
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert_eq!(journal.highest_seq_nr("counter").unwrap(), 1);
+    }
+
+    #[test]
+    fn recovers_from_the_latest_snapshot() {
+        #[derive(Default, Serialize, serde::Deserialize)]
+        struct Snapshotted(Vec<i64>);
+
+        impl PersistentChild for Snapshotted {
+            type Event = i64;
+
+            fn apply(&mut self, event: &i64) {
+                self.0.push(*event);
+            }
+        }
+
+        let memory = MemoryJournal::new();
+        let journal: Arc<dyn Journal> = Arc::new(memory.clone());
+
+        let mut persistent =
+            Persistent::recover("numbers", journal.clone(), Snapshotted::default())
+                .unwrap()
+                .with_snapshot_retention(1);
+        for event in 1..=3 {
+            persistent.persist(event).unwrap();
+            persistent.save_snapshot().unwrap();
+        }
+        persistent.persist(4).unwrap();
+        assert_eq!(memory.snapshots.lock().unwrap()["numbers"].len(), 1);
+
+        let recovered =
+            Persistent::recover_from_snapshot("numbers", journal, Snapshotted::default()).unwrap();
+        assert_eq!(recovered.state().0, vec![1, 2, 3, 4]);
+        assert_eq!(recovered.seq_nr(), 4);
+    }
+
+    #[cfg(feature = "persistence-sled")]
+    #[test]
+    fn computes_crc32_checksums() {
+        assert_eq!(checksum(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[cfg(feature = "persistence-sled")]
+    #[test]
+    fn detects_corrupted_sled_events() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let journal = SledJournal::from_db(db.clone());
+        journal.append("counter", 1, b"1".to_vec()).unwrap();
+        journal.append("counter", 2, b"2".to_vec()).unwrap();
+        assert_eq!(journal.highest_seq_nr("counter").unwrap(), 2);
+        assert_eq!(
+            journal.replay("counter", 2).unwrap(),
+            vec![(2, b"2".to_vec())]
+        );
+
+        let tree = db.open_tree("bastion/journal/counter").unwrap();
+        tree.insert(1u64.to_be_bytes(), b"corrupted".to_vec())
+            .unwrap();
+        match journal.replay("counter", 1) {
+            Err(PersistenceError::Corrupted { seq_nr }) => assert_eq!(seq_nr, 1),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,412 @@
+//! The QUIC transport of the connections between nodes, used when
+//! [`RemotingConfig::with_transport`] was given [`Transport::Quic`].
+//!
+//! Every connection has a bidirectional control stream, used for the
+//! handshake and the frames that aren't sent to a child (e.g. the
+//! answers), and a unidirectional stream per group of children the
+//! frames are sent to, so that a group of children that is slow to
+//! receive its messages doesn't delay the messages of the others.
+//!
+//! QUIC connections are driven by a dedicated Tokio runtime, whatever
+//! the runtime Bastion runs on is. Frames are written by a task per
+//! stream, so that sending one never blocks.
+//!
+//! [`RemotingConfig::with_transport`]: ../remote/struct.RemotingConfig.html#method.with_transport
+//! [`Transport::Quic`]: ../remote/enum.Transport.html#variant.Quic
+use futures::executor::block_on;
+use futures::future::{self, Either};
+use futures_timer::Delay;
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::{self, Runtime};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tracing::{debug, warn};
+
+/// The interval at which idle connections are kept alive, which
+/// also allows detecting broken links early.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(5);
+/// The maximum number of groups of children a node can send
+/// messages to at the same time over a connection.
+const MAX_STREAMS: u32 = 1024;
+/// The length of the chunks the frames are received by.
+const READ_CHUNK_LEN: usize = 8 * 1024;
+
+lazy_static! {
+    static ref RUNTIME: Runtime = runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_name("bastion-quic")
+        .enable_all()
+        .build()
+        .expect("Couldn't start the QUIC runtime.");
+}
+
+/// A UDP socket used to open (and possibly accept) connections.
+pub(crate) struct QuicEndpoint {
+    endpoint: quinn::Endpoint,
+}
+
+/// A connection that was accepted but isn't established yet.
+pub(crate) struct Incoming {
+    connecting: quinn::Connecting,
+}
+
+/// A QUIC connection with another node.
+pub(crate) struct QuicLink {
+    conn: quinn::Connection,
+    // The senders of the frames to write, by group of children.
+    writers: Mutex<FxHashMap<String, UnboundedSender<Vec<u8>>>>,
+}
+
+/// The control stream of a connection, as used for the handshake.
+pub(crate) struct ControlStream {
+    send: quinn::SendStream,
+    recv: quinn::RecvStream,
+    timeout: Duration,
+}
+
+impl QuicEndpoint {
+    /// Binds an endpoint to the address, which also accepts
+    /// connections if a server configuration is given.
+    pub(crate) fn bind(
+        addr: SocketAddr,
+        server: Option<Arc<rustls::ServerConfig>>,
+        client: Arc<rustls::ClientConfig>,
+    ) -> io::Result<Self> {
+        let _runtime = RUNTIME.enter();
+        let mut endpoint = match server {
+            Some(server) => {
+                let mut config = quinn::ServerConfig::with_crypto(server);
+                config.transport_config(transport());
+                quinn::Endpoint::server(config, addr)?
+            }
+            None => quinn::Endpoint::client(addr)?,
+        };
+
+        let mut config = quinn::ClientConfig::new(client);
+        config.transport_config(transport());
+        endpoint.set_default_client_config(config);
+
+        Ok(QuicEndpoint { endpoint })
+    }
+
+    pub(crate) fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.endpoint.local_addr()
+    }
+
+    /// Opens a connection with the node bound to the address,
+    /// verifying its certificate against the name.
+    pub(crate) fn connect(&self, addr: SocketAddr, name: &str) -> io::Result<QuicLink> {
+        let connecting = self.endpoint.connect(addr, name).map_err(other)?;
+        let conn = block_on(connecting).map_err(other)?;
+        Ok(QuicLink::new(conn))
+    }
+
+    /// Waits for another node to open a connection, returning `None`
+    /// once the endpoint is closed.
+    pub(crate) fn accept(&self) -> Option<Incoming> {
+        let connecting = block_on(self.endpoint.accept())?;
+        Some(Incoming { connecting })
+    }
+
+    /// Closes the endpoint, which stops accepting connections.
+    pub(crate) fn close(&self) {
+        self.endpoint.close(0u32.into(), b"");
+    }
+}
+
+impl Incoming {
+    /// Waits for the connection to be established, which fails if
+    /// the other node didn't complete the TLS handshake.
+    pub(crate) fn establish(self) -> io::Result<QuicLink> {
+        let conn = block_on(self.connecting).map_err(other)?;
+        Ok(QuicLink::new(conn))
+    }
+}
+
+impl QuicLink {
+    fn new(conn: quinn::Connection) -> Self {
+        QuicLink {
+            conn,
+            writers: Mutex::new(FxHashMap::default()),
+        }
+    }
+
+    pub(crate) fn remote_addr(&self) -> SocketAddr {
+        self.conn.remote_address()
+    }
+
+    /// Opens the control stream of the connection, or accepts it if
+    /// the connection was accepted, waiting for the other node at
+    /// most for the timeout.
+    pub(crate) fn control(&self, accepted: bool, timeout: Duration) -> io::Result<ControlStream> {
+        let opened = if accepted {
+            with_timeout(self.conn.accept_bi(), timeout)?
+        } else {
+            with_timeout(self.conn.open_bi(), timeout)?
+        };
+
+        let (send, recv) = opened.map_err(other)?;
+
+        Ok(ControlStream {
+            send,
+            recv,
+            timeout,
+        })
+    }
+
+    /// Starts handling the frames sent by the other node on any
+    /// stream, until the connection gets closed.
+    ///
+    /// # Arguments
+    ///
+    /// * `control` - The control stream, once the handshake was made.
+    /// * `max_len` - The maximum length of a frame.
+    /// * `on_frame` - Called with every frame received (without its
+    ///     length), in order for each stream.
+    /// * `on_close` - Called once the connection is closed.
+    pub(crate) fn serve<F, C>(
+        &self,
+        control: ControlStream,
+        max_len: usize,
+        on_frame: F,
+        on_close: C,
+    ) where
+        F: Fn(Vec<u8>) + Send + Sync + 'static,
+        C: FnOnce() + Send + 'static,
+    {
+        let on_frame = Arc::new(on_frame);
+        let writer = self.writer(Some(control.send));
+        self.writers.lock().unwrap().insert(String::new(), writer);
+        RUNTIME.spawn(read_frames(control.recv, max_len, on_frame.clone()));
+
+        let conn = self.conn.clone();
+        RUNTIME.spawn(async move {
+            loop {
+                match conn.accept_uni().await {
+                    Ok(recv) => {
+                        RUNTIME.spawn(read_frames(recv, max_len, on_frame.clone()));
+                    }
+                    Err(err) => {
+                        debug!("Remote: QUIC connection closed: {}", err);
+                        break;
+                    }
+                }
+            }
+
+            on_close();
+        });
+    }
+
+    /// Sends the encoded frame on the stream of the group of
+    /// children it is sent to, opening it if needed.
+    pub(crate) fn send(&self, group: &str, frame: Vec<u8>) -> io::Result<()> {
+        let mut writers = self.writers.lock().unwrap();
+        let writer = writers
+            .entry(group.to_string())
+            .or_insert_with(|| self.writer(None));
+
+        if writer.send(frame).is_err() {
+            // The stream was closed, which is only the case when the
+            // connection failed.
+            writers.remove(group);
+            return Err(io::ErrorKind::NotConnected.into());
+        }
+
+        Ok(())
+    }
+
+    // Spawns the task writing the frames on the stream (or on a new
+    // unidirectional stream).
+    fn writer(&self, stream: Option<quinn::SendStream>) -> UnboundedSender<Vec<u8>> {
+        let (sender, mut frames) = mpsc::unbounded_channel::<Vec<u8>>();
+        let conn = self.conn.clone();
+        RUNTIME.spawn(async move {
+            let mut stream = match stream {
+                Some(stream) => stream,
+                None => match conn.open_uni().await {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        debug!("Remote: Couldn't open a QUIC stream: {}", err);
+                        return;
+                    }
+                },
+            };
+
+            while let Some(frame) = frames.recv().await {
+                if let Err(err) = stream.write_all(&frame).await {
+                    debug!("Remote: Couldn't write to a QUIC stream: {}", err);
+                    return;
+                }
+            }
+        });
+
+        sender
+    }
+
+    /// Closes the connection, which then calls the `on_close`
+    /// callback given to [`serve`](#method.serve).
+    pub(crate) fn close(&self) {
+        self.conn.close(0u32.into(), b"");
+    }
+}
+
+impl Drop for QuicLink {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl Read for ControlStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match with_timeout(self.recv.read(buf), self.timeout)? {
+            Ok(Some(len)) => Ok(len),
+            Ok(None) => Ok(0),
+            Err(err) => Err(other(err)),
+        }
+    }
+}
+
+impl Write for ControlStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        with_timeout(self.send.write(buf), self.timeout)?.map_err(other)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+async fn read_frames(
+    mut recv: quinn::RecvStream,
+    max_len: usize,
+    on_frame: Arc<dyn Fn(Vec<u8>) + Send + Sync>,
+) {
+    loop {
+        let mut len = [0; 4];
+        // The stream was finished, or the connection closed.
+        if recv.read_exact(&mut len).await.is_err() {
+            return;
+        }
+
+        let len = u32::from_be_bytes(len) as usize;
+        if len > max_len {
+            warn!("Remote: Closing QUIC stream with a frame too long: {}", len);
+            recv.stop(0u32.into()).ok();
+            return;
+        }
+
+        // The frame is received chunk by chunk, rather than being
+        // allocated upfront for the length the other node announced.
+        let mut frame = Vec::with_capacity(len.min(READ_CHUNK_LEN));
+        let mut chunk = [0; READ_CHUNK_LEN];
+        while frame.len() < len {
+            let chunk = &mut chunk[..(len - frame.len()).min(READ_CHUNK_LEN)];
+            if recv.read_exact(chunk).await.is_err() {
+                return;
+            }
+
+            frame.extend_from_slice(chunk);
+        }
+
+        on_frame(frame);
+    }
+}
+
+fn transport() -> Arc<quinn::TransportConfig> {
+    let mut transport = quinn::TransportConfig::default();
+    transport
+        .keep_alive_interval(Some(KEEP_ALIVE_INTERVAL))
+        .max_concurrent_uni_streams(MAX_STREAMS.into());
+    Arc::new(transport)
+}
+
+fn with_timeout<F: Future>(fut: F, timeout: Duration) -> io::Result<F::Output> {
+    match block_on(future::select(Box::pin(fut), Delay::new(timeout))) {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(io::ErrorKind::TimedOut.into()),
+    }
+}
+
+fn other<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::Other, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::{Certificate, PrivateKey, RootCertStore};
+    use std::sync::mpsc as std_mpsc;
+    use std::thread;
+
+    fn endpoints() -> (QuicEndpoint, QuicEndpoint) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let der = Certificate(cert.serialize_der().unwrap());
+        let key = PrivateKey(cert.serialize_private_key_der());
+
+        let server = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(vec![der.clone()], key)
+            .unwrap();
+
+        let mut roots = RootCertStore::empty();
+        roots.add(&der).unwrap();
+        let client = Arc::new(
+            rustls::ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(roots)
+                .with_no_client_auth(),
+        );
+
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let server = QuicEndpoint::bind(addr, Some(Arc::new(server)), client.clone()).unwrap();
+        let client = QuicEndpoint::bind(addr, None, client).unwrap();
+        (server, client)
+    }
+
+    #[test]
+    fn multiplexes_frames() {
+        let (server, client) = endpoints();
+        let addr = server.local_addr().unwrap();
+        let (sender, received) = std_mpsc::channel();
+        let peer = thread::spawn(move || {
+            let link = server.accept().unwrap().establish().unwrap();
+            let mut control = link.control(true, Duration::from_secs(5)).unwrap();
+            let mut hello = [0; 5];
+            control.read_exact(&mut hello).unwrap();
+
+            let sender = Mutex::new(sender);
+            link.serve(
+                control,
+                1024,
+                move |frame| {
+                    sender.lock().unwrap().send(frame).unwrap();
+                },
+                || (),
+            );
+            link
+        });
+
+        let link = client.connect(addr, "localhost").unwrap();
+        let mut control = link.control(false, Duration::from_secs(5)).unwrap();
+        control.write_all(b"hello").unwrap();
+        let _peer = peer.join().unwrap();
+
+        link.send("/a", b"\0\0\0\x01a".to_vec()).unwrap();
+        link.send("/b", b"\0\0\0\x01b".to_vec()).unwrap();
+        let mut frames = vec![
+            received.recv_timeout(Duration::from_secs(5)).unwrap(),
+            received.recv_timeout(Duration::from_secs(5)).unwrap(),
+        ];
+        frames.sort();
+        assert_eq!(frames, vec![b"a".to_vec(), b"b".to_vec()]);
+    }
+}
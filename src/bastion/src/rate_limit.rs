@@ -0,0 +1,226 @@
+//!
+//! Rate limits cap the amount of messages the elements of a
+//! children group process per period of time (e.g. to respect the
+//! quotas of a downstream API), using a token bucket shared by all
+//! the group's elements.
+//!
+//! The messages received above the limit stay in the elements'
+//! mailboxes (following their usual rules, e.g. their overflow
+//! strategy) until they can be processed.
+//!
+//! Rate limits are set using [`Children::with_rate_limit`].
+//!
+//! [`Children::with_rate_limit`]: ../children/struct.Children.html#method.with_rate_limit
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The maximum rate at which the elements of a children group
+/// process their messages.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// // At most 100 messages per second, and at most 10 at once...
+/// let rate_limit = RateLimit::per_second(100).with_burst(10);
+/// ```
+pub struct RateLimit {
+    // The amount of messages allowed per second.
+    rate: f64,
+    // The amount of messages that can be processed at once.
+    burst: u32,
+}
+
+#[derive(Debug, Clone)]
+// The token bucket shared by the elements of a children group.
+pub(crate) struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    // The limit is kept with the tokens since it can be changed
+    // while the group is running (see `Bastion::reload_config`).
+    limit: RateLimit,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimit {
+    /// Allows at most `count` messages per second, all of which can
+    /// be processed at once (see [`with_burst`]).
+    ///
+    /// # Argument
+    ///
+    /// * `count` - The amount of messages allowed per second.
+    ///
+    /// [`with_burst`]: #method.with_burst
+    pub fn per_second(count: u32) -> Self {
+        RateLimit::per(count, Duration::from_secs(1))
+    }
+
+    /// Allows at most `count` messages per minute, all of which can
+    /// be processed at once (see [`with_burst`]).
+    ///
+    /// # Argument
+    ///
+    /// * `count` - The amount of messages allowed per minute.
+    ///
+    /// [`with_burst`]: #method.with_burst
+    pub fn per_minute(count: u32) -> Self {
+        RateLimit::per(count, Duration::from_secs(60))
+    }
+
+    /// Allows at most `count` messages per `period`, all of which
+    /// can be processed at once (see [`with_burst`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The amount of messages allowed per period.
+    /// * `period` - The period of time.
+    ///
+    /// [`with_burst`]: #method.with_burst
+    pub fn per(count: u32, period: Duration) -> Self {
+        let count = count.max(1);
+        let period = period.as_secs_f64().max(f64::EPSILON);
+
+        RateLimit {
+            rate: f64::from(count) / period,
+            burst: count,
+        }
+    }
+
+    /// Sets the amount of messages that can be processed at once
+    /// after the group's elements were idle, spreading the other
+    /// ones evenly over time.
+    ///
+    /// # Argument
+    ///
+    /// * `burst` - The amount of messages that can be processed at
+    ///     once (at least `1`).
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// Returns the amount of messages allowed per second.
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Returns the amount of messages that can be processed at
+    /// once.
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        let bucket = Bucket {
+            limit,
+            tokens: f64::from(limit.burst),
+            refilled_at: Instant::now(),
+        };
+
+        RateLimiter {
+            bucket: Arc::new(Mutex::new(bucket)),
+        }
+    }
+
+    /// Replaces the limit of the bucket, keeping the tokens that
+    /// were left (up to the new burst).
+    pub(crate) fn set_limit(&self, limit: RateLimit) {
+        // FIXME: panics
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+        bucket.limit = limit;
+        bucket.tokens = bucket.tokens.min(f64::from(limit.burst));
+    }
+
+    /// Calls `take` if a token is available, consuming it if `take`
+    /// returned something.
+    pub(crate) fn acquire<T>(&self, take: impl FnOnce() -> Option<T>) -> Option<T> {
+        // FIXME: panics
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+        if bucket.tokens < 1.0 {
+            return None;
+        }
+
+        let taken = take()?;
+        bucket.tokens -= 1.0;
+        Some(taken)
+    }
+
+    /// Returns how long it takes for a token to be available, if
+    /// none is.
+    pub(crate) fn throttled_for(&self) -> Option<Duration> {
+        // FIXME: panics
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+        if bucket.tokens >= 1.0 {
+            return None;
+        }
+
+        let missing = 1.0 - bucket.tokens;
+        Some(Duration::from_secs_f64(missing / bucket.limit.rate))
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        let burst = f64::from(bucket.limit.burst);
+        bucket.tokens = (bucket.tokens + elapsed * bucket.limit.rate).min(burst);
+        bucket.refilled_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn allows_bursts_up_to_the_limit() {
+        let limiter = RateLimiter::new(RateLimit::per_minute(3));
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire(|| Some(())), Some(()));
+        }
+
+        assert_eq!(limiter.acquire(|| Some(())), None);
+        assert!(limiter.throttled_for().unwrap() > Duration::from_secs(1));
+    }
+
+    #[test]
+    fn only_consumes_tokens_when_taking_something() {
+        let limiter = RateLimiter::new(RateLimit::per_minute(1));
+        assert_eq!(limiter.acquire(|| None::<()>), None);
+        assert_eq!(limiter.acquire(|| Some(())), Some(()));
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let limiter = RateLimiter::new(RateLimit::per_second(100).with_burst(1));
+        assert_eq!(limiter.acquire(|| Some(())), Some(()));
+        assert_eq!(limiter.acquire(|| Some(())), None);
+
+        thread::sleep(Duration::from_millis(20));
+        assert!(limiter.throttled_for().is_none());
+        assert_eq!(limiter.acquire(|| Some(())), Some(()));
+    }
+
+    #[test]
+    fn applies_a_new_limit() {
+        let limiter = RateLimiter::new(RateLimit::per_minute(3));
+        limiter.set_limit(RateLimit::per_minute(1));
+        assert_eq!(limiter.acquire(|| Some(())), Some(()));
+        assert_eq!(limiter.acquire(|| Some(())), None);
+
+        limiter.set_limit(RateLimit::per_second(100));
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(limiter.acquire(|| Some(())), Some(()));
+    }
+}
@@ -0,0 +1,78 @@
+//!
+//! Runs the elements of the children groups created using
+//! `Children::with_realtime_threads`, on a set of threads
+//! dedicated to the group and pinned onto the given cores.
+//!
+//! Every element is assigned to one of the threads when it is
+//! launched, and always runs on it (and thus on its core) instead
+//! of being migrated by the work-stealing scheduler of the
+//! executor.
+use bastion_executor::placement::{self, CoreId};
+use futures::channel::mpsc;
+use futures::executor::block_on_stream;
+use lightproc::lightproc::LightProc;
+use lightproc::proc_stack::ProcStack;
+use lightproc::recoverable_handle::RecoverableHandle;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tracing::{debug, trace};
+
+#[derive(Debug, Clone)]
+// The pinned threads of a children group, which stop once the
+// group and its elements are dropped.
+pub(crate) struct RealtimeThreads {
+    senders: Arc<Vec<mpsc::UnboundedSender<LightProc>>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl RealtimeThreads {
+    pub(crate) fn new(name: String, cores: &[usize]) -> Self {
+        let senders = cores
+            .iter()
+            .map(|&id| {
+                let (sender, receiver) = mpsc::unbounded::<LightProc>();
+                let name = name.clone();
+                thread::Builder::new()
+                    .name(format!("{}-{}", name, id))
+                    .spawn(move || {
+                        placement::set_for_current(CoreId { id });
+                        debug!("RealtimeThread({}): Started on core {}.", name, id);
+                        for proc in block_on_stream(receiver) {
+                            trace!("RealtimeThread({}): Running process.", name);
+                            proc.run();
+                        }
+                        debug!("RealtimeThread({}): Stopped.", name);
+                    })
+                    .expect("cannot start a realtime thread");
+
+                sender
+            })
+            .collect();
+
+        RealtimeThreads {
+            senders: Arc::new(senders),
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Spawns a process onto the next thread of the set, which will
+    /// poll it every time it is woken up.
+    pub(crate) fn spawn<F, T>(&self, future: F, stack: ProcStack) -> RecoverableHandle<T>
+    where
+        F: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let next = self.next.fetch_add(1, Ordering::Relaxed);
+        let sender = self.senders[next % self.senders.len()].clone();
+        let schedule = move |proc: LightProc| {
+            // The process is dropped (and thus cancelled) if the
+            // thread stopped.
+            sender.unbounded_send(proc).ok();
+        };
+        let (proc, recoverable) = LightProc::recoverable(future, schedule, stack);
+        proc.schedule();
+        recoverable
+    }
+}
@@ -0,0 +1,543 @@
+//!
+//! Redis mailboxes keep the messages sent to the elements of a
+//! children group in a Redis stream, so that they survive the
+//! restarts of the process, and are available with the
+//! `redis-mailbox` feature.
+//!
+//! The elements of a group using a [`RedisMailbox`] (see
+//! [`MailboxConfig::redis`]) are the consumers of a consumer group
+//! of the stream. The messages told to them whose type was
+//! registered with [`RedisMailbox::with_message`] are appended to
+//! the stream instead of being kept in memory, and received by any
+//! of the elements. A received message is acknowledged once it was
+//! handled, that is once the element that received it waits for its
+//! next message.
+//!
+//! The messages received by an element that failed while handling
+//! them are received again once the element was restarted. The ones
+//! that weren't acknowledged for longer than the mailbox's visibility
+//! timeout (e.g. because the process running their consumer crashed)
+//! are reclaimed by the other consumers. Messages are thus delivered
+//! at least once.
+//!
+//! The other messages (asked or broadcasted messages, and the ones
+//! of types that weren't registered) are kept in memory as usual,
+//! and received before the ones of the stream.
+//!
+//! [`RedisMailbox`]: struct.RedisMailbox.html
+//! [`MailboxConfig::redis`]: ../mailbox/struct.MailboxConfig.html#method.redis
+//! [`RedisMailbox::with_message`]: struct.RedisMailbox.html#method.with_message
+use crate::envelope::{RefAddr, SignedMessage};
+use crate::message::{Message, Msg};
+use redis::streams::{StreamId, StreamRangeReply, StreamReadOptions, StreamReadReply};
+use redis::{Client, Commands, Connection, RedisResult, Value};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::type_name;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, trace, warn};
+use uuid::Uuid;
+
+// The maximum amount of entries read from the stream at once.
+const FETCH_COUNT: usize = 16;
+// How often a consumer looks for the entries it can reclaim.
+const CLAIM_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+/// The configuration of mailboxes persisted in a Redis stream.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use bastion::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct Order(u64);
+///
+/// let mailbox = RedisMailbox::open("redis://localhost", "orders")
+///     .expect("Couldn't open the mailbox.")
+///     .with_message::<Order>();
+///
+/// Bastion::children(|children| {
+///     children
+///         .with_mailbox(MailboxConfig::redis(mailbox))
+///         .with_exec(|ctx| async move {
+///             loop {
+///                 msg! { ctx.recv().await?,
+///                     order: Order => {
+///                         // Handle the order...
+///                     };
+///                     _: _ => ();
+///                 }
+///             }
+///         })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub struct RedisMailbox {
+    url: String,
+    client: Client,
+    stream: String,
+    group: String,
+    visibility_timeout: Duration,
+    poll_interval: Duration,
+    messages: Arc<HashMap<&'static str, MessageEntry>>,
+}
+
+#[derive(Clone, Copy)]
+// The type-erased functions allowing to persist a registered
+// message.
+struct MessageEntry {
+    // Returns `None` if the message isn't of the entry's type.
+    encode: fn(&Msg) -> Option<serde_json::Result<Vec<u8>>>,
+    decode: fn(&[u8]) -> serde_json::Result<Msg>,
+}
+
+// The state of the mailbox of an element, which is a consumer of
+// the stream.
+pub(crate) struct RedisConsumer {
+    mailbox: RedisMailbox,
+    name: String,
+    conn: Mutex<Option<Connection>>,
+    // The entries that were read but not received yet.
+    fetched: Mutex<VecDeque<(String, SignedMessage)>>,
+    // The entry the element is handling, which is acknowledged
+    // once it waits for its next message.
+    handling: Mutex<Option<String>>,
+    // Whether the entries that were read before the element was
+    // restarted need to be received again.
+    recovering: AtomicBool,
+    last_claim: Mutex<Option<Instant>>,
+}
+
+impl RedisMailbox {
+    /// Creates the configuration of mailboxes persisted in the
+    /// given stream, whose consumer group is named `bastion`.
+    ///
+    /// The connections are only opened once the mailboxes are
+    /// used, so this method only fails if the url is invalid.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The url of the Redis server.
+    /// * `stream` - The key of the stream.
+    pub fn open(url: &str, stream: &str) -> RedisResult<Self> {
+        Ok(RedisMailbox {
+            url: url.to_string(),
+            client: Client::open(url)?,
+            stream: stream.to_string(),
+            group: "bastion".to_string(),
+            visibility_timeout: Duration::from_secs(30),
+            poll_interval: Duration::from_millis(100),
+            messages: Arc::new(HashMap::new()),
+        })
+    }
+
+    /// Sets the name of the consumer group of the stream, which
+    /// should be the same for all the processes running the
+    /// children group.
+    ///
+    /// # Argument
+    ///
+    /// * `group` - The name of the consumer group.
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = group.to_string();
+        self
+    }
+
+    /// Sets how long a message can stay unacknowledged before it
+    /// is reclaimed by another consumer. It should be longer than
+    /// it takes to handle a message.
+    ///
+    /// By default, messages are reclaimed after 30 seconds.
+    ///
+    /// # Argument
+    ///
+    /// * `timeout` - The visibility timeout of the messages.
+    pub fn with_visibility_timeout(mut self, timeout: Duration) -> Self {
+        self.visibility_timeout = timeout;
+        self
+    }
+
+    /// Sets how often an element waiting for a message reads the
+    /// stream.
+    ///
+    /// By default, the stream is read every 100 milliseconds.
+    ///
+    /// # Argument
+    ///
+    /// * `interval` - The interval between two reads.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Registers a type of message persisted in the stream, which
+    /// is serialized as JSON.
+    ///
+    /// Messages are identified by their type name, which means
+    /// that the processes should be built with the same version
+    /// of the types.
+    pub fn with_message<M>(mut self) -> Self
+    where
+        M: Message + Serialize + DeserializeOwned,
+    {
+        let entry = MessageEntry {
+            encode: |msg| msg.tell_ref::<M>().map(serde_json::to_vec),
+            decode: |payload| serde_json::from_slice::<M>(payload).map(Msg::tell),
+        };
+
+        Arc::make_mut(&mut self.messages).insert(type_name::<M>(), entry);
+        self
+    }
+
+    /// Returns the key of the stream.
+    pub fn stream(&self) -> &str {
+        &self.stream
+    }
+
+    /// Returns the name of the consumer group of the stream.
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// Returns how long a message can stay unacknowledged before
+    /// it is reclaimed by another consumer.
+    pub fn visibility_timeout(&self) -> Duration {
+        self.visibility_timeout
+    }
+
+    /// Returns how often an element waiting for a message reads
+    /// the stream.
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+}
+
+impl RedisConsumer {
+    pub(crate) fn new(mailbox: RedisMailbox) -> Self {
+        RedisConsumer {
+            mailbox,
+            name: Uuid::new_v4().to_string(),
+            conn: Mutex::new(None),
+            fetched: Mutex::new(VecDeque::new()),
+            handling: Mutex::new(None),
+            recovering: AtomicBool::new(false),
+            last_claim: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn poll_interval(&self) -> Duration {
+        self.mailbox.poll_interval
+    }
+
+    /// Appends the message to the stream, returning `false` if it
+    /// isn't of a registered type or if it couldn't be appended.
+    pub(crate) fn append(&self, msg: &SignedMessage) -> bool {
+        let encoded = self
+            .mailbox
+            .messages
+            .iter()
+            .find_map(|(type_name, entry)| Some((*type_name, (entry.encode)(&msg.msg)?)));
+        let (type_name, payload) = match encoded {
+            Some((type_name, Ok(payload))) => (type_name, payload),
+            Some((type_name, Err(err))) => {
+                warn!("RedisMailbox: Couldn't serialize {}: {}", type_name, err);
+                return false;
+            }
+            None => return false,
+        };
+
+        trace!(
+            "RedisMailbox({}): Appending {}.",
+            self.mailbox.stream,
+            type_name
+        );
+        let fields: [(&str, &[u8]); 2] = [("type", type_name.as_bytes()), ("payload", &payload)];
+        self.with_conn(|conn| {
+            conn.xadd::<_, _, _, _, String>(&self.mailbox.stream, "*", &fields[..])
+        })
+        .is_some()
+    }
+
+    /// Returns the next message of the stream.
+    pub(crate) fn pop(&self) -> Option<SignedMessage> {
+        // FIXME: panics
+        let mut fetched = self.fetched.lock().unwrap();
+        if fetched.is_empty() {
+            fetched.extend(self.fetch());
+        }
+
+        let (id, msg) = fetched.pop_front()?;
+        // FIXME: panics
+        *self.handling.lock().unwrap() = Some(id);
+        Some(msg)
+    }
+
+    /// Forgets about the messages the element received before it
+    /// was restarted, without acknowledging them, so that they are
+    /// received again.
+    pub(crate) fn recover(&self) {
+        // FIXME: panics
+        self.handling.lock().unwrap().take();
+        self.fetched.lock().unwrap().clear();
+        self.recovering.store(true, Ordering::SeqCst);
+    }
+
+    /// Acknowledges the message the element was handling, if any.
+    pub(crate) fn ack_handled(&self) {
+        // FIXME: panics
+        let id = match self.handling.lock().unwrap().take() {
+            Some(id) => id,
+            None => return,
+        };
+
+        trace!(
+            "RedisMailbox({}): Acknowledging {}.",
+            self.mailbox.stream,
+            id
+        );
+        self.ack(&id);
+    }
+
+    fn ack(&self, id: &str) {
+        let stream = &self.mailbox.stream;
+        let group = &self.mailbox.group;
+        self.with_conn(|conn| conn.xack::<_, _, _, i64>(stream, group, &[id]));
+    }
+
+    fn fetch(&self) -> Vec<(String, SignedMessage)> {
+        let mut entries = Vec::new();
+        // The entries read before a restart are the consumer's
+        // pending ones.
+        if self.recovering.load(Ordering::SeqCst) {
+            entries = self.read("0");
+            if entries.is_empty() {
+                self.recovering.store(false, Ordering::SeqCst);
+            }
+        }
+
+        if entries.is_empty() && self.claim_due() {
+            entries = self.claim();
+        }
+
+        if entries.is_empty() {
+            entries = self.read(">");
+        }
+
+        entries
+            .into_iter()
+            .filter_map(|entry| self.decode(entry))
+            .collect()
+    }
+
+    fn read(&self, from: &str) -> Vec<StreamId> {
+        let opts = StreamReadOptions::default()
+            .group(&self.mailbox.group, &self.name)
+            .count(FETCH_COUNT);
+        let reply: Option<StreamReadReply> =
+            self.with_conn(|conn| conn.xread_options(&[&self.mailbox.stream], &[from], &opts));
+
+        reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .collect()
+    }
+
+    fn claim_due(&self) -> bool {
+        // FIXME: panics
+        let mut last_claim = self.last_claim.lock().unwrap();
+        match *last_claim {
+            Some(last_claim) if last_claim.elapsed() < CLAIM_INTERVAL => false,
+            _ => {
+                *last_claim = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    // Claims the entries that weren't acknowledged by their
+    // consumer for longer than the visibility timeout.
+    fn claim(&self) -> Vec<StreamId> {
+        let reply: Option<Vec<Value>> = self.with_conn(|conn| {
+            redis::cmd("XAUTOCLAIM")
+                .arg(&self.mailbox.stream)
+                .arg(&self.mailbox.group)
+                .arg(&self.name)
+                .arg(self.mailbox.visibility_timeout.as_millis() as u64)
+                .arg("0-0")
+                .arg("COUNT")
+                .arg(FETCH_COUNT)
+                .query(conn)
+        });
+
+        let claimed = reply
+            .as_ref()
+            .and_then(|reply| reply.get(1))
+            .map(redis::from_redis_value::<StreamRangeReply>);
+        match claimed {
+            Some(Ok(claimed)) => {
+                if !claimed.ids.is_empty() {
+                    debug!(
+                        "RedisMailbox({}): Reclaimed {} messages.",
+                        self.mailbox.stream,
+                        claimed.ids.len()
+                    );
+                }
+
+                claimed.ids
+            }
+            Some(Err(err)) => {
+                warn!(
+                    "RedisMailbox({}): Couldn't claim: {}",
+                    self.mailbox.stream, err
+                );
+                Vec::new()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn decode(&self, entry: StreamId) -> Option<(String, SignedMessage)> {
+        let type_name = entry.get::<String>("type");
+        let decoded = type_name
+            .as_deref()
+            .and_then(|type_name| self.mailbox.messages.get(type_name))
+            .zip(entry.get::<Vec<u8>>("payload"))
+            .map(|(registered, payload)| (registered.decode)(&payload));
+
+        match decoded {
+            Some(Ok(msg)) => Some((entry.id, SignedMessage::new(msg, RefAddr::dead_letters()))),
+            _ => {
+                // The entries that can't be received are dropped
+                // instead of being reclaimed forever.
+                warn!(
+                    "RedisMailbox({}): Dropping entry {} of type {:?}.",
+                    self.mailbox.stream, entry.id, type_name
+                );
+                self.ack(&entry.id);
+                None
+            }
+        }
+    }
+
+    // Runs a command on the consumer's connection, opening it (and
+    // creating the consumer group) if needed and closing it if the
+    // command failed.
+    fn with_conn<T, F>(&self, command: F) -> Option<T>
+    where
+        F: FnOnce(&mut Connection) -> RedisResult<T>,
+    {
+        // FIXME: panics
+        let mut conn = self.conn.lock().unwrap();
+        if conn.is_none() {
+            match self.connect() {
+                Ok(connected) => *conn = Some(connected),
+                Err(err) => {
+                    warn!(
+                        "RedisMailbox({}): Couldn't connect: {}",
+                        self.mailbox.stream, err
+                    );
+                    return None;
+                }
+            }
+        }
+
+        match command(conn.as_mut()?) {
+            Ok(reply) => Some(reply),
+            Err(err) => {
+                warn!(
+                    "RedisMailbox({}): Command failed: {}",
+                    self.mailbox.stream, err
+                );
+                *conn = None;
+                None
+            }
+        }
+    }
+
+    fn connect(&self) -> RedisResult<Connection> {
+        let mut conn = self.mailbox.client.get_connection()?;
+        let created: RedisResult<()> =
+            conn.xgroup_create_mkstream(&self.mailbox.stream, &self.mailbox.group, "0");
+        match created {
+            Err(err) if err.code() != Some("BUSYGROUP") => return Err(err),
+            _ => (),
+        }
+
+        debug!(
+            "RedisMailbox({}): Consuming as {} of {}.",
+            self.mailbox.stream, self.name, self.mailbox.group
+        );
+        Ok(conn)
+    }
+}
+
+impl Debug for RedisMailbox {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("RedisMailbox")
+            .field("stream", &self.stream)
+            .field("group", &self.group)
+            .field("visibility_timeout", &self.visibility_timeout)
+            .field("poll_interval", &self.poll_interval)
+            .field("messages", &self.messages.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl PartialEq for RedisMailbox {
+    fn eq(&self, other: &Self) -> bool {
+        self.url == other.url
+            && self.stream == other.stream
+            && self.group == other.group
+            && self.visibility_timeout == other.visibility_timeout
+            && self.poll_interval == other.poll_interval
+            && Arc::ptr_eq(&self.messages, &other.messages)
+    }
+}
+
+impl Eq for RedisMailbox {}
+
+impl Debug for RedisConsumer {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("RedisConsumer")
+            .field("stream", &self.mailbox.stream)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Order(u64);
+
+    #[test]
+    fn encodes_registered_messages() {
+        let mailbox = RedisMailbox::open("redis://localhost", "orders")
+            .unwrap()
+            .with_message::<Order>();
+        let entry = mailbox.messages[type_name::<Order>()];
+
+        let payload = (entry.encode)(&Msg::tell(Order(42))).unwrap().unwrap();
+        let msg = (entry.decode)(&payload).unwrap();
+        assert_eq!(msg.downcast::<Order>().unwrap(), Order(42));
+
+        assert!((entry.encode)(&Msg::tell("unregistered")).is_none());
+    }
+}
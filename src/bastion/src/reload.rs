@@ -0,0 +1,352 @@
+//!
+//! Changes the configuration of a running system.
+//!
+//! [`Bastion::reload_config`] applies the settings of a new
+//! [`Config`] which can safely be changed while the system is running
+//! (e.g. the capacity of the mailboxes or the rate limits of the
+//! children groups, or the log level), and returns a [`ReloadReport`]
+//! listing them along with the changed settings which only take
+//! effect once the process is restarted (e.g. the executor threads).
+//!
+//! The log level (see [`Config::with_log_level`]) is applied by the
+//! [`LogLevelLayer`], which has to be added to the subscriber that
+//! the application installs.
+//!
+//! [`Bastion::reload_config`]: ../struct.Bastion.html#method.reload_config
+//! [`Config`]: ../struct.Config.html
+//! [`ReloadReport`]: struct.ReloadReport.html
+//! [`Config::with_log_level`]: ../struct.Config.html#method.with_log_level
+//! [`LogLevelLayer`]: struct.LogLevelLayer.html
+use crate::config::Config;
+use crate::mailbox;
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use crate::rate_limit::RateLimit;
+#[cfg(feature = "scaling")]
+use crate::resizer::UpperBound;
+use crate::rt;
+use crate::supervisor;
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use tracing::subscriber::Interest;
+use tracing::{debug, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+lazy_static! {
+    // The configuration the system was last initialized or
+    // reloaded with.
+    static ref CURRENT: Mutex<Option<Config>> = Mutex::new(None);
+    // The settings of the children groups, by name.
+    static ref GROUPS: RwLock<FxHashMap<String, GroupSettings>> =
+        RwLock::new(FxHashMap::default());
+}
+
+// The verbosity of the most verbose logs let through by the
+// `LogLevelLayer`s (from `1` for the errors to `5` for the traces),
+// or `0` if every log is let through.
+static LOG_LEVEL: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+/// The outcome of [`Bastion::reload_config`], listing the names of
+/// the settings that were changed (e.g. `"rate_limits"` or
+/// `"executor_threads"`), depending on whether they were applied or
+/// require the process to be restarted to take effect.
+///
+/// # Example
+///
+/// ```rust
+/// use bastion::prelude::*;
+///
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// Bastion::init_with(Config::new().with_mailbox_capacity("workers", 128));
+/// Bastion::start();
+///
+/// let config = Config::new()
+///     .with_mailbox_capacity("workers", 1024)
+///     .with_executor_threads(16);
+/// let report = Bastion::reload_config(config);
+///
+/// assert_eq!(report.applied(), &["mailbox_capacities"]);
+/// assert_eq!(report.requires_restart(), &["executor_threads"]);
+/// #
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`Bastion::reload_config`]: ../struct.Bastion.html#method.reload_config
+pub struct ReloadReport {
+    applied: Vec<&'static str>,
+    requires_restart: Vec<&'static str>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+/// A [`Layer`] of `tracing-subscriber` filtering out the logs
+/// which are more verbose than the log level of the system's
+/// configuration (see [`Config::with_log_level`]), which can be
+/// changed using [`Bastion::reload_config`].
+///
+/// Every log is let through when no log level is set.
+///
+/// # Example
+///
+/// ```rust
+/// use bastion::prelude::*;
+/// use tracing_subscriber::layer::SubscriberExt;
+///
+/// let subscriber = tracing_subscriber::registry().with(LogLevelLayer::new());
+/// tracing::subscriber::set_global_default(subscriber).ok();
+///
+/// Bastion::init_with(Config::new().with_log_level(tracing::Level::INFO));
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// ```
+///
+/// [`Layer`]: https://docs.rs/tracing-subscriber/0.2/tracing_subscriber/layer/trait.Layer.html
+/// [`Config::with_log_level`]: ../struct.Config.html#method.with_log_level
+/// [`Bastion::reload_config`]: ../struct.Bastion.html#method.reload_config
+pub struct LogLevelLayer {
+    _priv: (),
+}
+
+#[derive(Debug, Default, Clone)]
+// The settings of a children group that can be changed while it
+// is running, set on the `Config` with the group's name.
+pub(crate) struct GroupSettings {
+    pub(crate) mailbox_capacity: Option<usize>,
+    pub(crate) rate_limit: Option<RateLimit>,
+    #[cfg(feature = "scaling")]
+    pub(crate) resizer_bounds: Option<(u64, UpperBound)>,
+}
+
+impl ReloadReport {
+    /// Returns the names of the settings that changed and were
+    /// applied.
+    pub fn applied(&self) -> &[&'static str] {
+        &self.applied
+    }
+
+    /// Returns the names of the settings that changed but only take
+    /// effect once the process is restarted.
+    pub fn requires_restart(&self) -> &[&'static str] {
+        &self.requires_restart
+    }
+
+    /// Returns whether every changed setting was applied.
+    pub fn is_fully_applied(&self) -> bool {
+        self.requires_restart.is_empty()
+    }
+
+    fn compare(&mut self, name: &'static str, changed: bool, live: bool) {
+        match (changed, live) {
+            (false, _) => (),
+            (true, true) => self.applied.push(name),
+            (true, false) => self.requires_restart.push(name),
+        }
+    }
+}
+
+impl LogLevelLayer {
+    /// Creates a new layer following the log level of the system's
+    /// configuration.
+    pub fn new() -> Self {
+        LogLevelLayer::default()
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogLevelLayer {
+    fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest {
+        // The log level might change, so the callsites can't be
+        // enabled or disabled once and for all.
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _: Context<'_, S>) -> bool {
+        match LOG_LEVEL.load(Ordering::Relaxed) {
+            0 => true,
+            max => verbosity(metadata.level()) <= max,
+        }
+    }
+}
+
+fn verbosity(level: &Level) -> usize {
+    if *level == Level::ERROR {
+        1
+    } else if *level == Level::WARN {
+        2
+    } else if *level == Level::INFO {
+        3
+    } else if *level == Level::DEBUG {
+        4
+    } else {
+        5
+    }
+}
+
+/// Applies the settings of `config` which can be changed while the
+/// system is running, and keeps it to compare it with the next one.
+pub(crate) fn init(config: &Config) {
+    apply(config);
+    // FIXME: panics
+    *CURRENT.lock().unwrap() = Some(config.clone());
+}
+
+/// Applies the settings of `config` which can be changed while the
+/// system is running, returning the ones that changed along with
+/// the ones that changed but can't be applied.
+///
+/// The children groups still need to be told to reconfigure
+/// themselves using their new settings.
+pub(crate) fn reload(config: Config) -> ReloadReport {
+    // FIXME: panics
+    let mut current = CURRENT.lock().unwrap();
+    let old = current.take().unwrap_or_default();
+
+    let mut report = ReloadReport::default();
+    report.compare("backtraces", old.backtraces() != config.backtraces(), false);
+    report.compare(
+        "executor",
+        !old.executor().is_same(config.executor()),
+        false,
+    );
+    report.compare(
+        "executor_threads",
+        old.executor_threads() != config.executor_threads(),
+        false,
+    );
+    report.compare(
+        "thread_affinity",
+        old.thread_affinity() != config.thread_affinity(),
+        false,
+    );
+    report.compare(
+        "blocking_pool",
+        old.blocking_pool() != config.blocking_pool(),
+        false,
+    );
+    report.compare("budget", old.budget() != config.budget(), true);
+    report.compare(
+        "default_mailbox",
+        old.default_mailbox() != config.default_mailbox(),
+        true,
+    );
+    report.compare(
+        "restart_strategies",
+        old.restart_strategies() != config.restart_strategies(),
+        true,
+    );
+    report.compare(
+        "mailbox_capacities",
+        old.mailbox_capacities() != config.mailbox_capacities(),
+        true,
+    );
+    report.compare(
+        "rate_limits",
+        old.rate_limits() != config.rate_limits(),
+        true,
+    );
+    #[cfg(feature = "scaling")]
+    report.compare(
+        "resizer_bounds",
+        old.resizer_bounds() != config.resizer_bounds(),
+        true,
+    );
+    report.compare("log_level", old.log_level() != config.log_level(), true);
+    #[cfg(feature = "remote")]
+    report.compare("remoting", !same_remoting(&old, &config), false);
+    #[cfg(feature = "cluster")]
+    report.compare("cluster", !same_cluster(&old, &config), false);
+    #[cfg(feature = "metrics")]
+    report.compare("metrics", old.metrics() != config.metrics(), true);
+
+    debug!("Bastion: Reloading config: {:?}", report);
+    apply(&config);
+    *current = Some(config);
+
+    report
+}
+
+fn apply(config: &Config) {
+    rt::set_budget(config.budget());
+    mailbox::set_default_config(config.default_mailbox().cloned());
+    supervisor::set_group_restart_strategies(config.restart_strategies().clone());
+    set_group_settings(config);
+    let log_level = config.log_level().map(|level| verbosity(&level));
+    LOG_LEVEL.store(log_level.unwrap_or(0), Ordering::Relaxed);
+    #[cfg(feature = "metrics")]
+    metrics::set_enabled(config.metrics().unwrap_or(true));
+}
+
+fn set_group_settings(config: &Config) {
+    let mut groups = FxHashMap::<String, GroupSettings>::default();
+    for (name, capacity) in config.mailbox_capacities() {
+        groups.entry(name.clone()).or_default().mailbox_capacity = Some(*capacity);
+    }
+    for (name, rate_limit) in config.rate_limits() {
+        groups.entry(name.clone()).or_default().rate_limit = Some(*rate_limit);
+    }
+    #[cfg(feature = "scaling")]
+    for (name, bounds) in config.resizer_bounds() {
+        groups.entry(name.clone()).or_default().resizer_bounds = Some(bounds.clone());
+    }
+
+    // FIXME: panics
+    *GROUPS.write().unwrap() = groups;
+}
+
+/// Returns the settings of the children groups with the given name,
+/// if any were set.
+pub(crate) fn group_settings(name: &str) -> Option<GroupSettings> {
+    // FIXME: panics
+    GROUPS.read().unwrap().get(name).cloned()
+}
+
+#[cfg(feature = "remote")]
+fn same_remoting(old: &Config, new: &Config) -> bool {
+    match (old.remoting(), new.remoting()) {
+        (None, None) => true,
+        (Some((old_addr, old)), Some((new_addr, new))) => {
+            old_addr == new_addr && old.handshake_timeout() == new.handshake_timeout()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(feature = "cluster")]
+fn same_cluster(old: &Config, new: &Config) -> bool {
+    match (old.cluster(), new.cluster()) {
+        (None, None) => true,
+        (Some(old), Some(new)) => {
+            old.seeds() == new.seeds()
+                && old.gossip_interval() == new.gossip_interval()
+                && old.phi_threshold() == new.phi_threshold()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_the_levels_by_verbosity() {
+        assert!(verbosity(&Level::ERROR) < verbosity(&Level::WARN));
+        assert!(verbosity(&Level::INFO) < verbosity(&Level::DEBUG));
+        assert!(verbosity(&Level::DEBUG) < verbosity(&Level::TRACE));
+    }
+}
@@ -0,0 +1,2265 @@
+//!
+//! Remoting allows children to receive messages sent from other
+//! nodes (i.e. other processes running Bastion, possibly on other
+//! hosts) over TCP, and is available with the `remote` feature.
+//!
+//! A node accepts connections from other nodes once it was bound to
+//! an address with [`Bastion::bind`], and connects to another node
+//! with [`Bastion::connect`]. Both nodes then exchange a handshake,
+//! after which the [`RemoteNode`] returned by [`Bastion::connect`]
+//! allows to get a [`RemoteChildRef`] to any of the other node's
+//! children, by path. Telling or asking a message to it serializes
+//! the message and sends it to the other node, which deserializes it
+//! and sends it to the child as if it was sent locally.
+//!
+//! Because messages are received as their concrete type, every type
+//! of message (and of answer) sent between nodes needs to be a
+//! [`RemoteMessage`] registered with [`register_message`] on both
+//! nodes. They are serialized using the node's [`MessageCodec`] (see
+//! [`RemotingConfig::with_codec`]).
+//!
+//! With the `tls` feature, the connections between nodes can be
+//! encrypted (and authenticated) using TLS, see
+//! [`RemotingConfig::with_tls`]. With the `quic` feature, nodes can
+//! also be connected over QUIC instead of TCP, see
+//! [`RemotingConfig::with_transport`]. With the `compression` feature,
+//! large messages can be compressed, see
+//! [`RemotingConfig::with_compression`].
+//!
+//! [`Bastion::bind`]: ../struct.Bastion.html#method.bind
+//! [`Bastion::connect`]: ../struct.Bastion.html#method.connect
+//! [`RemoteNode`]: struct.RemoteNode.html
+//! [`RemoteChildRef`]: struct.RemoteChildRef.html
+//! [`register_message`]: fn.register_message.html
+//! [`RemoteMessage`]: ../codec/trait.RemoteMessage.html
+//! [`MessageCodec`]: ../codec/trait.MessageCodec.html
+//! [`RemotingConfig::with_codec`]: struct.RemotingConfig.html#method.with_codec
+//! [`RemotingConfig::with_tls`]: struct.RemotingConfig.html#method.with_tls
+//! [`RemotingConfig::with_transport`]: struct.RemotingConfig.html#method.with_transport
+//! [`RemotingConfig::with_compression`]: struct.RemotingConfig.html#method.with_compression
+use crate::chaos;
+use crate::child_ref::ChildRef;
+#[cfg(feature = "cluster")]
+use crate::cluster;
+use crate::codec::{self, JsonCodec, MessageCodec, RemoteMessage};
+use crate::dead_letters;
+use crate::dispatcher::BroadcastTarget;
+use crate::envelope::{Envelope, RefAddr, SignedMessage};
+use crate::errors::RemoteError;
+use crate::executor;
+use crate::message::{Answer, AnswerSender, BastionMessage, Msg};
+use crate::path::BastionPath;
+#[cfg(feature = "quic")]
+use crate::quic::{Incoming, QuicEndpoint, QuicLink};
+use crate::system::SYSTEM;
+#[cfg(feature = "tls")]
+use crate::tls::TlsStream;
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+#[cfg(feature = "tls")]
+use rustls::{ClientConfig, ServerConfig, ServerName};
+use serde::{Deserialize, Serialize};
+use std::any::type_name;
+use std::borrow::Cow;
+#[cfg(feature = "tls")]
+use std::convert::TryFrom;
+use std::convert::TryInto;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::io::{self, Read, Write};
+use std::iter;
+use std::net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tracing::{debug, trace, warn};
+use uuid::Uuid;
+
+/// The version of the protocol spoken between nodes, which must
+/// be the same on both ends of a connection.
+const PROTOCOL_VERSION: u32 = 2;
+
+/// The maximum length of a frame by default. Longer frames are
+/// considered to be corrupted.
+const DEFAULT_MAX_FRAME_LEN: usize = 4 * 1024 * 1024;
+
+/// The capacity the buffer of a frame being received starts with,
+/// before growing as the rest of the frame is received.
+const INITIAL_FRAME_CAPACITY: usize = 8 * 1024;
+
+/// The number of bytes of frames that can be queued on a connection
+/// before sending more fails.
+const WRITE_QUEUE_BYTES: usize = 16 * 1024 * 1024;
+
+/// The maximum number of connections accepted from other nodes at
+/// the same time, including the ones still exchanging a handshake.
+/// The other connections are refused.
+const MAX_CONNECTIONS: usize = 256;
+
+/// The prefix of the paths of the named dispatchers of a node,
+/// which broadcast the messages sent to them to their children
+/// groups.
+const DISPATCHER_PREFIX: &str = "/dispatchers/";
+
+lazy_static! {
+    static ref NODE: Node = Node::new();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// The identifier of a node, randomly generated when the process
+/// starts.
+pub struct NodeId(Uuid);
+
+#[derive(Debug, Clone)]
+/// The configuration of the remoting subsystem of a node, as
+/// given to [`Bastion::bind_with`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::time::Duration;
+/// #
+/// let config = RemotingConfig::default()
+///     .with_handshake_timeout(Duration::from_secs(1));
+/// # drop(config);
+/// ```
+///
+/// [`Bastion::bind_with`]: ../struct.Bastion.html#method.bind_with
+pub struct RemotingConfig {
+    handshake_timeout: Duration,
+    max_frame_len: usize,
+    codec: Arc<dyn MessageCodec>,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConfig>,
+    // The name the certificates of the other nodes are verified
+    // against, instead of their IP address.
+    #[cfg(feature = "tls")]
+    tls_server_name: Option<String>,
+    #[cfg(feature = "quic")]
+    transport: Transport,
+    #[cfg(feature = "compression")]
+    compression: Option<Compression>,
+    #[cfg(feature = "compression")]
+    compression_threshold: usize,
+}
+
+#[cfg(feature = "compression")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An algorithm compressing the messages sent to other nodes, as
+/// set with [`RemotingConfig::with_compression`], which is
+/// available with the `compression` feature.
+///
+/// [`RemotingConfig::with_compression`]: struct.RemotingConfig.html#method.with_compression
+pub enum Compression {
+    /// LZ4, which is fast but compresses less.
+    Lz4,
+    /// Zstandard, which compresses more but is slower.
+    Zstd,
+}
+
+#[cfg(feature = "quic")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The transport used by the connections between nodes, as set
+/// with [`RemotingConfig::with_transport`], which is available with
+/// the `quic` feature.
+///
+/// [`RemotingConfig::with_transport`]: struct.RemotingConfig.html#method.with_transport
+pub enum Transport {
+    /// Every connection is a TCP connection, possibly encrypted
+    /// using TLS. This is the default transport.
+    Tcp,
+    /// Every connection is a QUIC connection, which is always
+    /// encrypted using the TLS configuration of the node. The
+    /// messages sent to each group of children use their own
+    /// stream, so that a lost packet only delays the messages of
+    /// one group, and broken links are detected and connected
+    /// again faster than over TCP.
+    Quic,
+}
+
+#[cfg(feature = "tls")]
+#[derive(Clone)]
+struct TlsConfig {
+    server: Arc<ServerConfig>,
+    client: Arc<ClientConfig>,
+}
+
+#[derive(Debug, Clone)]
+/// A connection to another node, as returned by
+/// [`Bastion::connect`].
+///
+/// [`Bastion::connect`]: ../struct.Bastion.html#method.connect
+pub struct RemoteNode {
+    conn: Arc<Connection>,
+}
+
+#[derive(Debug, Clone)]
+/// A "reference" to a child of another node, allowing to send it
+/// messages, as returned by [`RemoteNode::child_ref`].
+///
+/// [`RemoteNode::child_ref`]: struct.RemoteNode.html#method.child_ref
+pub struct RemoteChildRef {
+    conn: Arc<Connection>,
+    path: String,
+}
+
+// The state of the remoting subsystem of this node.
+struct Node {
+    id: NodeId,
+    config: RwLock<RemotingConfig>,
+    // The public children of this node, by path, that can
+    // receive messages from other nodes.
+    children: Mutex<FxHashMap<String, ChildRef>>,
+    // The messages that can be sent between nodes, by type name.
+    messages: RwLock<FxHashMap<&'static str, MessageEntry>>,
+    // The connections to other nodes, by id.
+    connections: Mutex<FxHashMap<NodeId, Arc<Connection>>>,
+    // The address this node was bound to, if it was.
+    bound: Mutex<Option<SocketAddr>>,
+    // The thread accepting the connections of other nodes, if
+    // this node was bound.
+    listener: Mutex<Option<Listener>>,
+    // The QUIC endpoint of this node, once it was bound or
+    // connected to another node using QUIC.
+    #[cfg(feature = "quic")]
+    quic: Mutex<Option<Arc<QuicEndpoint>>>,
+}
+
+struct Connection {
+    peer: NodeId,
+    addr: SocketAddr,
+    max_frame_len: usize,
+    codec: Arc<dyn MessageCodec>,
+    compressor: Compressor,
+    writer: Writer,
+    // Whether this connection was opened by the other node.
+    accepted: bool,
+    // The slot taken by this connection among the `MAX_CONNECTIONS`
+    // accepted ones, until it gets closed.
+    permit: Mutex<Option<Permit>>,
+    connected: AtomicBool,
+    next_request_id: AtomicU64,
+    // The senders of the answers to the messages asked to the
+    // other node, by request id.
+    pending: Mutex<FxHashMap<u64, AnswerSender>>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+// How the payloads of the messages sent over a connection are
+// compressed. Every payload is prefixed by the id of the algorithm
+// it was compressed with, or by `Compressor::NONE`.
+struct Compressor {
+    // The algorithm supported by the other node, if this node
+    // compresses the payloads it sends.
+    #[cfg(feature = "compression")]
+    compression: Option<Compression>,
+    #[cfg(feature = "compression")]
+    threshold: usize,
+}
+
+// The writing half of a connection.
+enum Writer {
+    Stream {
+        // The queue of the frames written by the connection's
+        // writer thread, which is dropped once the connection was
+        // closed.
+        queue: Mutex<Option<Sender<Vec<u8>>>>,
+        // The number of bytes of the frames in the queue.
+        queued: Arc<AtomicUsize>,
+    },
+    #[cfg(feature = "quic")]
+    Quic(Arc<QuicLink>),
+}
+
+// The thread accepting the connections of other nodes, along with
+// how to make it stop.
+struct Listener {
+    thread: JoinHandle<()>,
+    stop: Box<dyn FnOnce() + Send>,
+}
+
+// A slot among the `MAX_CONNECTIONS` accepted connections, which is
+// released once dropped.
+struct Permit(Arc<AtomicUsize>);
+
+// The stream of a connection, which is encrypted if TLS is
+// enabled.
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(TlsStream),
+}
+
+#[derive(Clone, Copy)]
+// The type-erased functions allowing to send and receive a
+// registered message.
+struct MessageEntry {
+    tell: fn(&dyn MessageCodec, &[u8]) -> Result<Msg, String>,
+    ask: fn(&dyn MessageCodec, &[u8]) -> Result<(Msg, Answer), String>,
+    // Returns `Err(msg)` if the message isn't of the entry's type.
+    encode: fn(&dyn MessageCodec, Msg) -> Result<Result<Vec<u8>, String>, Msg>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Frame {
+    Hello {
+        node: NodeId,
+        version: u32,
+        codec: String,
+        // The ids of the compression algorithms the node supports.
+        compressions: Vec<u8>,
+    },
+    Tell {
+        path: String,
+        type_name: String,
+        payload: Vec<u8>,
+    },
+    Ask {
+        request_id: u64,
+        path: String,
+        type_name: String,
+        payload: Vec<u8>,
+    },
+    Reply {
+        request_id: u64,
+        type_name: String,
+        payload: Vec<u8>,
+    },
+    // The message asked with this request id couldn't be
+    // delivered or answered.
+    Failed {
+        request_id: u64,
+    },
+    // A message of the cluster membership protocol.
+    Cluster {
+        payload: Vec<u8>,
+    },
+}
+
+/// Registers a type of message that can be sent to or received
+/// from other nodes, either as a message or as an answer. This
+/// should be called on every node, before connecting to other
+/// nodes.
+///
+/// Messages are identified by their type name, which means that the
+/// nodes should be built with the same version of the types.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct Ping(u64);
+///
+/// bastion::remote::register_message::<Ping>();
+/// ```
+pub fn register_message<M: RemoteMessage>() {
+    let entry = MessageEntry {
+        tell: |codec, payload| codec::decode::<M>(codec, payload).map(Msg::tell),
+        ask: |codec, payload| codec::decode::<M>(codec, payload).map(Msg::ask),
+        encode: |codec, msg| {
+            let msg = msg.downcast::<M>()?;
+            Ok(codec::encode(codec, &msg))
+        },
+    };
+
+    trace!("Remote: Registering message: {}", type_name::<M>());
+    NODE.messages
+        .write()
+        .unwrap()
+        .insert(type_name::<M>(), entry);
+}
+
+/// Returns the id of this node.
+pub fn local_node_id() -> NodeId {
+    NODE.id
+}
+
+impl NodeId {
+    pub(crate) fn new() -> Self {
+        NodeId(Uuid::new_v4())
+    }
+}
+
+impl Display for NodeId {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.0)
+    }
+}
+
+impl RemotingConfig {
+    /// Sets the time a node waits for another node to answer its
+    /// handshake before closing the connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The handshake timeout.
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Returns the time a node waits for another node to answer
+    /// its handshake.
+    pub fn handshake_timeout(&self) -> Duration {
+        self.handshake_timeout
+    }
+
+    /// Sets the maximum length (in bytes) of the frames sent and
+    /// received by a node, which should be the same on every node.
+    /// A node closes the connection with a node sending it a longer
+    /// frame, and fails to send the messages that don't fit in one.
+    /// By default, frames of up to 4 MiB are accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - The maximum length of a frame.
+    pub fn with_max_frame_len(mut self, len: usize) -> Self {
+        self.max_frame_len = len;
+        self
+    }
+
+    /// Returns the maximum length of the frames sent and received
+    /// by a node.
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+
+    /// Sets the codec used to serialize the messages sent to other
+    /// nodes, which must be the same on both ends of a connection.
+    /// By default, messages are serialized using [`JsonCodec`].
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - The [`MessageCodec`] used by the node.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let config = RemotingConfig::default().with_codec(BincodeCodec);
+    /// # drop(config);
+    /// ```
+    ///
+    /// [`JsonCodec`]: ../codec/struct.JsonCodec.html
+    /// [`MessageCodec`]: ../codec/trait.MessageCodec.html
+    pub fn with_codec<C: MessageCodec>(mut self, codec: C) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+
+    /// Returns the codec used to serialize the messages sent
+    /// to other nodes.
+    pub fn codec(&self) -> &dyn MessageCodec {
+        &*self.codec
+    }
+
+    #[cfg(feature = "tls")]
+    /// Encrypts the connections with the other nodes using TLS,
+    /// which is available with the `tls` feature. Once enabled, the
+    /// connections that don't start with a TLS handshake (e.g. from
+    /// nodes that don't use TLS) are rejected.
+    ///
+    /// The node uses the server configuration for the connections it
+    /// accepts and the client configuration for the connections it
+    /// opens. By default, the certificates of the nodes it connects
+    /// to are verified against their IP address (see
+    /// [`with_tls_server_name`]). Mutual TLS, where every node also
+    /// verifies the certificate of the nodes connecting to it, is
+    /// enabled by giving the server configuration a client
+    /// certificate verifier (e.g. `AllowAnyAuthenticatedClient`) and
+    /// the client configuration the node's certificate.
+    ///
+    /// The certificates only authenticate the names they are verified
+    /// against, not the [`NodeId`] a node claims once connected: a
+    /// node holding a trusted certificate can claim to be any other
+    /// node of the cluster. They should thus only be issued to the
+    /// nodes of a single cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `server` - The configuration used to accept connections.
+    /// * `client` - The configuration used to open connections.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::sync::Arc;
+    /// #
+    /// fn config(server: rustls::ServerConfig, client: rustls::ClientConfig) -> RemotingConfig {
+    ///     RemotingConfig::default().with_tls(Arc::new(server), Arc::new(client))
+    /// }
+    /// ```
+    ///
+    /// [`with_tls_server_name`]: #method.with_tls_server_name
+    /// [`NodeId`]: struct.NodeId.html
+    pub fn with_tls(mut self, server: Arc<ServerConfig>, client: Arc<ClientConfig>) -> Self {
+        self.tls = Some(TlsConfig { server, client });
+        self
+    }
+
+    #[cfg(feature = "tls")]
+    /// Sets the name the certificates of the nodes this node
+    /// connects to are verified against, instead of their IP
+    /// address, when TLS is enabled using [`with_tls`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The DNS name (e.g. `bastion.example.com`) the
+    ///     certificates of the nodes are issued for.
+    ///
+    /// [`with_tls`]: #method.with_tls
+    pub fn with_tls_server_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.tls_server_name = Some(name.into());
+        self
+    }
+
+    #[cfg(feature = "tls")]
+    /// Returns whether the connections with the other nodes are
+    /// encrypted using TLS.
+    pub fn is_tls_enabled(&self) -> bool {
+        self.tls.is_some()
+    }
+
+    #[cfg(feature = "quic")]
+    /// Sets the transport used by the connections this node opens
+    /// and accepts, which must be the same on every node. By
+    /// default, nodes are connected using [`Transport::Tcp`].
+    ///
+    /// [`Transport::Quic`] requires TLS to be enabled using
+    /// [`with_tls`], the server and client configurations being
+    /// used by QUIC's handshake.
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` - The transport used by the node.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::sync::Arc;
+    /// #
+    /// fn config(server: rustls::ServerConfig, client: rustls::ClientConfig) -> RemotingConfig {
+    ///     RemotingConfig::default()
+    ///         .with_tls(Arc::new(server), Arc::new(client))
+    ///         .with_transport(Transport::Quic)
+    /// }
+    /// ```
+    ///
+    /// [`Transport::Tcp`]: enum.Transport.html#variant.Tcp
+    /// [`Transport::Quic`]: enum.Transport.html#variant.Quic
+    /// [`with_tls`]: #method.with_tls
+    pub fn with_transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    #[cfg(feature = "quic")]
+    /// Returns the transport used by the connections between nodes.
+    pub fn transport(&self) -> Transport {
+        self.transport
+    }
+
+    #[cfg(feature = "compression")]
+    /// Compresses the payloads of the messages sent to other nodes
+    /// that are larger than the compression threshold (see
+    /// [`with_compression_threshold`]) using the algorithm, which is
+    /// available with the `compression` feature. By default,
+    /// messages aren't compressed.
+    ///
+    /// The algorithms supported by both nodes are negotiated during
+    /// their handshake, so the messages sent to a node that doesn't
+    /// support the algorithm (e.g. that was built without the
+    /// `compression` feature) aren't compressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `compression` - The algorithm compressing the messages.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// let config = RemotingConfig::default()
+    ///     .with_compression(Compression::Zstd)
+    ///     .with_compression_threshold(64 * 1024);
+    /// # drop(config);
+    /// ```
+    ///
+    /// [`with_compression_threshold`]: #method.with_compression_threshold
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    #[cfg(feature = "compression")]
+    /// Returns the algorithm compressing the messages sent to other
+    /// nodes, if they are compressed.
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    #[cfg(feature = "compression")]
+    /// Sets the size (in bytes) from which the serialized messages
+    /// are compressed, when compression is enabled using
+    /// [`with_compression`]. By default, messages of 8 KiB and more
+    /// are compressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - The minimum size of the compressed messages.
+    ///
+    /// [`with_compression`]: #method.with_compression
+    pub fn with_compression_threshold(mut self, threshold: usize) -> Self {
+        self.compression_threshold = threshold;
+        self
+    }
+
+    #[cfg(feature = "compression")]
+    /// Returns the size from which the serialized messages are
+    /// compressed.
+    pub fn compression_threshold(&self) -> usize {
+        self.compression_threshold
+    }
+}
+
+impl Default for RemotingConfig {
+    fn default() -> Self {
+        RemotingConfig {
+            handshake_timeout: Duration::from_secs(5),
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            codec: Arc::new(JsonCodec),
+            #[cfg(feature = "tls")]
+            tls: None,
+            #[cfg(feature = "tls")]
+            tls_server_name: None,
+            #[cfg(feature = "quic")]
+            transport: Transport::Tcp,
+            #[cfg(feature = "compression")]
+            compression: None,
+            #[cfg(feature = "compression")]
+            compression_threshold: 8 * 1024,
+        }
+    }
+}
+
+impl RemoteNode {
+    /// Returns the id of the node.
+    pub fn id(&self) -> NodeId {
+        self.conn.peer
+    }
+
+    /// Returns the address the node was connected to.
+    pub fn addr(&self) -> SocketAddr {
+        self.conn.addr
+    }
+
+    /// Returns whether the connection to the node is still open.
+    pub fn is_connected(&self) -> bool {
+        self.conn.connected.load(Ordering::SeqCst)
+    }
+
+    /// Returns a [`RemoteChildRef`] referencing the child of the
+    /// node with the given path.
+    ///
+    /// This doesn't check that the child exists: messages sent
+    /// to a child that doesn't exist are dropped by the node.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the child, as formatted by the
+    ///     `Display` implementation of [`BastionPath`].
+    ///
+    /// [`RemoteChildRef`]: struct.RemoteChildRef.html
+    /// [`BastionPath`]: ../path/struct.BastionPath.html
+    pub fn child_ref<P: Into<String>>(&self, path: P) -> RemoteChildRef {
+        RemoteChildRef {
+            conn: self.conn.clone(),
+            path: path.into(),
+        }
+    }
+
+    pub(crate) fn send_cluster(&self, payload: Vec<u8>) -> io::Result<()> {
+        self.conn.send(&Frame::Cluster { payload })
+    }
+}
+
+impl RemoteChildRef {
+    /// Returns the path of the child on its node.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the id of the node the child belongs to.
+    pub fn node(&self) -> NodeId {
+        self.conn.peer
+    }
+
+    /// Sends a message to the child this `RemoteChildRef` is
+    /// referencing, serializing it and sending it to its node.
+    ///
+    /// This method returns `()` if the message was sent to the
+    /// node, or `Err(msg)` if it couldn't be serialized or sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send, whose type must be
+    ///     registered on both nodes (see [`register_message`]).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use bastion::prelude::*;
+    /// #
+    /// # fn run() -> Result<(), RemoteError> {
+    /// let node = Bastion::connect("10.0.0.2:4222")?;
+    /// let child_ref = node.child_ref("/0000/0001/0002");
+    /// child_ref.tell_anonymously(42u64).expect("Couldn't send the message.");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`register_message`]: fn.register_message.html
+    pub fn tell_anonymously<M: RemoteMessage>(&self, msg: M) -> Result<(), M> {
+        debug!(
+            "RemoteChildRef({}@{}): Telling message: {:?}",
+            self.path, self.conn.peer, msg
+        );
+        let payload = match codec::encode(&*self.conn.codec, &msg) {
+            Ok(payload) => self.conn.compressor.pack(payload),
+            Err(err) => {
+                warn!("Remote: Couldn't serialize message {:?}: {}", msg, err);
+                return Err(msg);
+            }
+        };
+
+        let frame = Frame::Tell {
+            path: self.path.clone(),
+            type_name: type_name::<M>().to_string(),
+            payload,
+        };
+        self.conn.send(&frame).map_err(|_| msg)
+    }
+
+    /// Sends a message to the child this `RemoteChildRef` is
+    /// referencing, serializing it and sending it to its node,
+    /// allowing the child to answer.
+    ///
+    /// This method returns [`Answer`] if the message was sent to
+    /// the node, or `Err(msg)` if it couldn't be serialized or sent.
+    /// The answer fails if the message couldn't be delivered or if
+    /// the connection was closed before the child answered.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send, whose type (and the type of
+    ///     its answer) must be registered on both nodes (see
+    ///     [`register_message`]).
+    ///
+    /// [`Answer`]: ../message/struct.Answer.html
+    /// [`register_message`]: fn.register_message.html
+    pub fn ask_anonymously<M: RemoteMessage>(&self, msg: M) -> Result<Answer, M> {
+        debug!(
+            "RemoteChildRef({}@{}): Asking message: {:?}",
+            self.path, self.conn.peer, msg
+        );
+        let payload = match codec::encode(&*self.conn.codec, &msg) {
+            Ok(payload) => self.conn.compressor.pack(payload),
+            Err(err) => {
+                warn!("Remote: Couldn't serialize message {:?}: {}", msg, err);
+                return Err(msg);
+            }
+        };
+
+        let (sender, answer) = Answer::channel();
+        let request_id = self.conn.next_request_id.fetch_add(1, Ordering::SeqCst);
+        self.conn.pending.lock().unwrap().insert(request_id, sender);
+
+        let frame = Frame::Ask {
+            request_id,
+            path: self.path.clone(),
+            type_name: type_name::<M>().to_string(),
+            payload,
+        };
+        if self.conn.send(&frame).is_err() {
+            self.conn.pending.lock().unwrap().remove(&request_id);
+            return Err(msg);
+        }
+
+        Ok(answer)
+    }
+}
+
+impl Node {
+    fn new() -> Self {
+        Node {
+            id: NodeId::new(),
+            config: RwLock::new(RemotingConfig::default()),
+            children: Mutex::new(FxHashMap::default()),
+            messages: RwLock::new(FxHashMap::default()),
+            connections: Mutex::new(FxHashMap::default()),
+            bound: Mutex::new(None),
+            listener: Mutex::new(None),
+            #[cfg(feature = "quic")]
+            quic: Mutex::new(None),
+        }
+    }
+
+    fn message(&self, type_name: &str) -> Option<MessageEntry> {
+        self.messages.read().unwrap().get(type_name).copied()
+    }
+
+    /// Serializes an answer, returning its type name along with
+    /// the payload if its type was registered.
+    fn encode(&self, codec: &dyn MessageCodec, mut msg: Msg) -> Option<(&'static str, Vec<u8>)> {
+        for (type_name, entry) in self.messages.read().unwrap().iter() {
+            match (entry.encode)(codec, msg) {
+                Ok(Ok(payload)) => return Some((type_name, payload)),
+                Ok(Err(err)) => {
+                    warn!(
+                        "Remote: Couldn't serialize answer of type {}: {}",
+                        type_name, err
+                    );
+                    return None;
+                }
+                Err(unmatched) => msg = unmatched,
+            }
+        }
+
+        warn!("Remote: Dropping answer of unregistered type: {:?}", msg);
+        None
+    }
+
+    fn child(&self, path: &str) -> Option<ChildRef> {
+        self.children.lock().unwrap().get(path).cloned()
+    }
+}
+
+impl Connection {
+    fn new(
+        peer: NodeId,
+        addr: SocketAddr,
+        config: &RemotingConfig,
+        compressor: Compressor,
+        writer: Writer,
+        accepted: bool,
+        permit: Option<Permit>,
+    ) -> Self {
+        Connection {
+            peer,
+            addr,
+            max_frame_len: config.max_frame_len,
+            codec: config.codec.clone(),
+            compressor,
+            writer,
+            accepted,
+            permit: Mutex::new(permit),
+            connected: AtomicBool::new(true),
+            next_request_id: AtomicU64::new(0),
+            pending: Mutex::new(FxHashMap::default()),
+        }
+    }
+
+    fn send(&self, frame: &Frame) -> io::Result<()> {
+        trace!("Remote({}): Sending frame: {:?}", self.peer, frame);
+        if !self.connected.load(Ordering::SeqCst) || chaos::is_partitioned(&self.peer) {
+            return Err(io::ErrorKind::NotConnected.into());
+        }
+
+        let encoded = frame.encode();
+        // The length of the frame isn't part of it.
+        if encoded.len() - 4 > self.max_frame_len {
+            let err = format!("frame too long: {}", encoded.len() - 4);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, err));
+        }
+
+        match &self.writer {
+            Writer::Stream { queue, queued } => {
+                let queue = queue.lock().unwrap();
+                let queue = queue.as_ref().ok_or(io::ErrorKind::NotConnected)?;
+                // A frame is always queued when the queue is empty, even
+                // if it is longer than the queue.
+                let len = encoded.len();
+                let before = queued.fetch_add(len, Ordering::SeqCst);
+                if before > 0 && before + len > WRITE_QUEUE_BYTES {
+                    queued.fetch_sub(len, Ordering::SeqCst);
+                    return Err(io::Error::new(
+                        io::ErrorKind::WouldBlock,
+                        "the write queue is full",
+                    ));
+                }
+
+                queue.send(encoded).map_err(|_| {
+                    queued.fetch_sub(len, Ordering::SeqCst);
+                    io::ErrorKind::NotConnected.into()
+                })
+            }
+            #[cfg(feature = "quic")]
+            Writer::Quic(link) => link.send(frame.group(), encoded),
+        }
+    }
+
+    /// Reads and handles the frames sent by the other node until
+    /// the connection gets closed.
+    fn serve(self: Arc<Self>, mut reader: Stream) {
+        loop {
+            match read_frame(&mut reader, self.max_frame_len) {
+                Ok(frame) => self.handle(frame),
+                Err(err) => {
+                    debug!("Remote({}): Connection closed: {}", self.peer, err);
+                    break;
+                }
+            }
+        }
+
+        self.close();
+    }
+
+    /// Closes the connection, then forgets it.
+    fn close(self: &Arc<Self>) {
+        self.connected.store(false, Ordering::SeqCst);
+        match &self.writer {
+            // The writer thread shuts the stream down once it wrote
+            // the queued frames.
+            Writer::Stream { queue, .. } => {
+                queue.lock().unwrap().take();
+            }
+            #[cfg(feature = "quic")]
+            Writer::Quic(link) => link.close(),
+        }
+
+        self.permit.lock().unwrap().take();
+
+        let mut connections = NODE.connections.lock().unwrap();
+        if let Some(conn) = connections.get(&self.peer) {
+            if Arc::ptr_eq(conn, self) {
+                connections.remove(&self.peer);
+            }
+        }
+
+        // Dropping the senders makes the answers fail.
+        self.pending.lock().unwrap().clear();
+    }
+
+    fn handle(self: &Arc<Self>, frame: Frame) {
+        trace!("Remote({}): Received frame: {:?}", self.peer, frame);
+        if chaos::is_partitioned(&self.peer) {
+            debug!("Remote({}): Chaos dropped a frame.", self.peer);
+            return;
+        }
+
+        match frame {
+            Frame::Tell {
+                path,
+                type_name,
+                payload,
+            } => {
+                let msg = match self.decode(&type_name, &payload, |entry| entry.tell) {
+                    Some(msg) => msg,
+                    None => return,
+                };
+
+                deliver(&path, msg);
+            }
+            Frame::Ask {
+                request_id,
+                path,
+                type_name,
+                payload,
+            } => {
+                let (msg, answer) = match self.decode(&type_name, &payload, |entry| entry.ask) {
+                    Some(decoded) => decoded,
+                    None => {
+                        self.send(&Frame::Failed { request_id }).ok();
+                        return;
+                    }
+                };
+
+                if !deliver(&path, msg) {
+                    self.send(&Frame::Failed { request_id }).ok();
+                    return;
+                }
+
+                let conn = self.clone();
+                executor::spawn(async move {
+                    let answer = answer.await.ok();
+                    let encoded = answer.and_then(|sm| NODE.encode(&*conn.codec, sm.msg));
+                    let frame = match encoded {
+                        Some((type_name, payload)) => Frame::Reply {
+                            request_id,
+                            type_name: type_name.to_string(),
+                            payload: conn.compressor.pack(payload),
+                        },
+                        None => Frame::Failed { request_id },
+                    };
+
+                    conn.send(&frame).ok();
+                });
+            }
+            Frame::Reply {
+                request_id,
+                type_name,
+                payload,
+            } => {
+                let sender = match self.pending.lock().unwrap().remove(&request_id) {
+                    Some(sender) => sender,
+                    None => return,
+                };
+
+                if let Some(msg) = self.decode(&type_name, &payload, |entry| entry.tell) {
+                    // The asker might not be waiting for the answer
+                    // anymore.
+                    sender.send_msg(msg, RefAddr::dead_letters()).ok();
+                }
+            }
+            Frame::Failed { request_id } => {
+                self.pending.lock().unwrap().remove(&request_id);
+            }
+            #[cfg(feature = "cluster")]
+            Frame::Cluster { payload } => {
+                let node = RemoteNode { conn: self.clone() };
+                cluster::handle(node, &payload);
+            }
+            #[cfg(not(feature = "cluster"))]
+            Frame::Cluster { .. } => {
+                trace!("Remote({}): Ignoring cluster message.", self.peer);
+            }
+            Frame::Hello { .. } => {
+                warn!("Remote({}): Ignoring unexpected handshake.", self.peer);
+            }
+        }
+    }
+
+    fn decode<T>(
+        &self,
+        type_name: &str,
+        payload: &[u8],
+        decoder: impl FnOnce(MessageEntry) -> fn(&dyn MessageCodec, &[u8]) -> Result<T, String>,
+    ) -> Option<T> {
+        let entry = match NODE.message(type_name) {
+            Some(entry) => entry,
+            None => {
+                warn!(
+                    "Remote: Dropping message of unregistered type: {}",
+                    type_name
+                );
+                return None;
+            }
+        };
+
+        let payload = match Compressor::unpack(payload, self.max_frame_len) {
+            Ok(payload) => payload,
+            Err(err) => {
+                warn!("Remote: Couldn't decompress {}: {}", type_name, err);
+                return None;
+            }
+        };
+
+        match decoder(entry)(&*self.codec, &payload) {
+            Ok(decoded) => Some(decoded),
+            Err(err) => {
+                warn!("Remote: Couldn't deserialize {}: {}", type_name, err);
+                None
+            }
+        }
+    }
+}
+
+impl Frame {
+    const HELLO: u8 = 0;
+    const TELL: u8 = 1;
+    const ASK: u8 = 2;
+    const REPLY: u8 = 3;
+    const FAILED: u8 = 4;
+    const CLUSTER: u8 = 5;
+
+    /// Returns the group of children the frame is sent to (i.e. the
+    /// path of their parent), or an empty string if it isn't sent to
+    /// a child. The frames sent to the same group are received in
+    /// order.
+    #[cfg(feature = "quic")]
+    fn group(&self) -> &str {
+        match self {
+            Frame::Tell { path, .. } | Frame::Ask { path, .. } => {
+                path.rsplit_once('/').map(|(group, _)| group).unwrap_or("")
+            }
+            _ => "",
+        }
+    }
+
+    /// Encodes the frame, prefixed by its length.
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = vec![0; 4];
+        match self {
+            Frame::Hello {
+                node,
+                version,
+                codec,
+                compressions,
+            } => {
+                buf.push(Frame::HELLO);
+                buf.extend_from_slice(node.0.as_bytes());
+                buf.extend_from_slice(&version.to_be_bytes());
+                put_bytes(&mut buf, codec.as_bytes());
+                put_bytes(&mut buf, compressions);
+            }
+            Frame::Tell {
+                path,
+                type_name,
+                payload,
+            } => {
+                buf.push(Frame::TELL);
+                put_bytes(&mut buf, path.as_bytes());
+                put_bytes(&mut buf, type_name.as_bytes());
+                put_bytes(&mut buf, payload);
+            }
+            Frame::Ask {
+                request_id,
+                path,
+                type_name,
+                payload,
+            } => {
+                buf.push(Frame::ASK);
+                buf.extend_from_slice(&request_id.to_be_bytes());
+                put_bytes(&mut buf, path.as_bytes());
+                put_bytes(&mut buf, type_name.as_bytes());
+                put_bytes(&mut buf, payload);
+            }
+            Frame::Reply {
+                request_id,
+                type_name,
+                payload,
+            } => {
+                buf.push(Frame::REPLY);
+                buf.extend_from_slice(&request_id.to_be_bytes());
+                put_bytes(&mut buf, type_name.as_bytes());
+                put_bytes(&mut buf, payload);
+            }
+            Frame::Failed { request_id } => {
+                buf.push(Frame::FAILED);
+                buf.extend_from_slice(&request_id.to_be_bytes());
+            }
+            Frame::Cluster { payload } => {
+                buf.push(Frame::CLUSTER);
+                put_bytes(&mut buf, payload);
+            }
+        }
+
+        let len = (buf.len() - 4) as u32;
+        buf[..4].copy_from_slice(&len.to_be_bytes());
+        buf
+    }
+
+    /// Decodes a frame, without its length prefix.
+    fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut buf = Cursor(buf);
+        let frame = match buf.take(1)?[0] {
+            Frame::HELLO => Frame::Hello {
+                node: NodeId(Uuid::from_slice(buf.take(16)?).map_err(invalid_data)?),
+                version: buf.u32()?,
+                codec: buf.string()?,
+                compressions: buf.bytes()?,
+            },
+            Frame::TELL => Frame::Tell {
+                path: buf.string()?,
+                type_name: buf.string()?,
+                payload: buf.bytes()?,
+            },
+            Frame::ASK => Frame::Ask {
+                request_id: buf.u64()?,
+                path: buf.string()?,
+                type_name: buf.string()?,
+                payload: buf.bytes()?,
+            },
+            Frame::REPLY => Frame::Reply {
+                request_id: buf.u64()?,
+                type_name: buf.string()?,
+                payload: buf.bytes()?,
+            },
+            Frame::FAILED => Frame::Failed {
+                request_id: buf.u64()?,
+            },
+            Frame::CLUSTER => Frame::Cluster {
+                payload: buf.bytes()?,
+            },
+            kind => return Err(invalid_data(format!("unknown frame kind: {}", kind))),
+        };
+
+        Ok(frame)
+    }
+}
+
+// A reader of the fields of a frame.
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(invalid_data("truncated frame"));
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        String::from_utf8(self.bytes()?).map_err(invalid_data)
+    }
+}
+
+/// Binds this node to the address, allowing other nodes to
+/// connect to it.
+pub(crate) fn bind<A: ToSocketAddrs>(
+    addr: A,
+    config: RemotingConfig,
+) -> Result<SocketAddr, RemoteError> {
+    #[cfg(feature = "quic")]
+    {
+        if config.transport == Transport::Quic {
+            return bind_quic(addr, config);
+        }
+    }
+
+    let listener = TcpListener::bind(addr).map_err(RemoteError::Io)?;
+    let local_addr = listener.local_addr().map_err(RemoteError::Io)?;
+    debug!("Remote: Listening on {}.", local_addr);
+    *NODE.config.write().unwrap() = config;
+    *NODE.bound.lock().unwrap() = Some(local_addr);
+
+    let stopping = Arc::new(AtomicBool::new(false));
+    let stopped = stopping.clone();
+    let thread = thread::Builder::new()
+        .name("bastion-remote-listener".to_string())
+        .spawn(move || {
+            let active = Arc::new(AtomicUsize::new(0));
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                match stream {
+                    Ok(stream) => accept(stream, &active),
+                    Err(err) => warn!("Remote: Couldn't accept a connection: {}", err),
+                }
+            }
+        })
+        .map_err(RemoteError::Io)?;
+
+    let stop = move || {
+        stopping.store(true, Ordering::SeqCst);
+        // The listener only checks whether it has to stop once it
+        // accepted a connection.
+        let mut wake = local_addr;
+        if wake.ip().is_unspecified() {
+            match wake {
+                SocketAddr::V4(_) => wake.set_ip(Ipv4Addr::LOCALHOST.into()),
+                SocketAddr::V6(_) => wake.set_ip(Ipv6Addr::LOCALHOST.into()),
+            }
+        }
+
+        TcpStream::connect(wake).ok();
+    };
+
+    listen(Listener {
+        thread,
+        stop: Box::new(stop),
+    });
+    Ok(local_addr)
+}
+
+/// Stops accepting the connections of other nodes, waiting for the
+/// listener's thread to stop. The opened connections stay open.
+pub(crate) fn unbind() {
+    let listener = NODE.listener.lock().unwrap().take();
+    if let Some(listener) = listener {
+        listener.stop();
+        debug!("Remote: Stopped listening.");
+    }
+
+    *NODE.bound.lock().unwrap() = None;
+    #[cfg(feature = "quic")]
+    {
+        NODE.quic.lock().unwrap().take();
+    }
+}
+
+// Makes the listener the one of this node, stopping the previous
+// one if this node was already bound.
+fn listen(listener: Listener) {
+    let previous = NODE.listener.lock().unwrap().replace(listener);
+    if let Some(previous) = previous {
+        previous.stop();
+    }
+}
+
+/// Connects this node to the node listening on the address.
+pub(crate) fn connect<A: ToSocketAddrs>(addr: A) -> Result<RemoteNode, RemoteError> {
+    #[cfg(feature = "quic")]
+    {
+        if NODE.config.read().unwrap().transport == Transport::Quic {
+            return connect_quic(addr);
+        }
+    }
+
+    let stream = TcpStream::connect(addr).map_err(RemoteError::Io)?;
+    let conn = open(stream, false, None)?;
+    Ok(RemoteNode { conn })
+}
+
+/// Returns the address this node was bound to, if it was.
+pub(crate) fn bound_addr() -> Option<SocketAddr> {
+    *NODE.bound.lock().unwrap()
+}
+
+/// Makes the child able to receive messages from other nodes.
+pub(crate) fn register_child(child_ref: &ChildRef) {
+    if !child_ref.is_public() {
+        return;
+    }
+
+    NODE.children
+        .lock()
+        .unwrap()
+        .insert(child_ref.path().to_string(), child_ref.clone());
+}
+
+pub(crate) fn unregister_child(path: &BastionPath) {
+    NODE.children.lock().unwrap().remove(&path.to_string());
+}
+
+/// Allows other nodes to send messages to the child using the
+/// name instead of its path, which is only known by this node.
+pub(crate) fn register_named(name: String, child_ref: &ChildRef) {
+    NODE.children
+        .lock()
+        .unwrap()
+        .insert(name, child_ref.clone());
+}
+
+/// Returns the node with the id if this node is connected to it.
+pub(crate) fn node(id: NodeId) -> Option<RemoteNode> {
+    let connections = NODE.connections.lock().unwrap();
+    connections
+        .get(&id)
+        .filter(|conn| conn.connected.load(Ordering::SeqCst))
+        .map(|conn| RemoteNode { conn: conn.clone() })
+}
+
+/// Makes the connection the one to its node, returning the one that
+/// is kept if this node is already connected to it.
+fn register(conn: &Arc<Connection>) -> Arc<Connection> {
+    let mut connections = NODE.connections.lock().unwrap();
+    // Both ends of a connection of this node to itself are kept.
+    if conn.peer == NODE.id {
+        connections.insert(conn.peer, conn.clone());
+        return conn.clone();
+    }
+
+    if let Some(old) = connections.get(&conn.peer) {
+        let keeps_old = old.connected.load(Ordering::SeqCst)
+            && !keeps_new(NODE.id, conn.peer, conn.accepted, old.accepted);
+        if keeps_old {
+            debug!(
+                "Remote({}): Already connected, closing the new connection.",
+                conn.peer
+            );
+            return old.clone();
+        }
+    }
+
+    let old = connections.insert(conn.peer, conn.clone());
+    drop(connections);
+    if let Some(old) = old {
+        debug!(
+            "Remote({}): Reconnected, closing the old connection.",
+            conn.peer
+        );
+        old.close();
+    }
+
+    conn.clone()
+}
+
+/// Returns whether a new connection with the other node replaces
+/// the one it already has. A new connection opened by the same node
+/// is a reconnection, while when both nodes opened one at the same
+/// time, both keep the one opened by the node with the lowest id.
+fn keeps_new(local: NodeId, peer: NodeId, new_accepted: bool, old_accepted: bool) -> bool {
+    if new_accepted == old_accepted {
+        return true;
+    }
+
+    (peer < local) == new_accepted
+}
+
+fn accept(stream: TcpStream, active: &Arc<AtomicUsize>) {
+    let permit = match Permit::acquire(active) {
+        Some(permit) => permit,
+        None => {
+            warn!("Remote: Too many connections, refusing one.");
+            return;
+        }
+    };
+
+    // The handshake is made by the connection's thread to avoid
+    // having a slow node blocking the listener.
+    let spawned = thread::Builder::new()
+        .name("bastion-remote-conn".to_string())
+        .spawn(move || {
+            if let Err(err) = open(stream, true, Some(permit)) {
+                warn!("Remote: Couldn't accept a connection: {:?}", err);
+            }
+        });
+
+    if let Err(err) = spawned {
+        warn!("Remote: Couldn't accept a connection: {}", err);
+    }
+}
+
+/// Exchanges a handshake with the other node, then starts handling
+/// the frames it sends in a dedicated thread.
+fn open(
+    stream: TcpStream,
+    accepted: bool,
+    permit: Option<Permit>,
+) -> Result<Arc<Connection>, RemoteError> {
+    let addr = stream.peer_addr().map_err(RemoteError::Io)?;
+    stream.set_nodelay(true).map_err(RemoteError::Io)?;
+    let config = NODE.config.read().unwrap().clone();
+    stream
+        .set_read_timeout(Some(config.handshake_timeout))
+        .map_err(RemoteError::Io)?;
+
+    let mut stream = secure(stream, addr, accepted, &config)?;
+    // FIXME: the id isn't bound to the certificate the TLS session
+    // verified, so the peer can claim any id.
+    let (peer, supported) = handshake(&mut stream, &config)?;
+    let compressor = Compressor::negotiate(&config, &supported);
+    stream
+        .tcp()
+        .set_read_timeout(None)
+        .map_err(RemoteError::Io)?;
+    debug!("Remote: Connected to {} ({}).", peer, addr);
+
+    // The frames are written by a dedicated thread, so that sending
+    // a message never blocks on a slow node.
+    let writer = stream.try_clone().map_err(RemoteError::Io)?;
+    let (queue, frames) = mpsc::channel();
+    let queued = Arc::new(AtomicUsize::new(0));
+    let written = queued.clone();
+    thread::Builder::new()
+        .name("bastion-remote-writer".to_string())
+        .spawn(move || write_frames(peer, writer, frames, written))
+        .map_err(RemoteError::Io)?;
+
+    let writer = Writer::Stream {
+        queue: Mutex::new(Some(queue)),
+        queued,
+    };
+    let conn = Connection::new(peer, addr, &config, compressor, writer, accepted, permit);
+    let conn = Arc::new(conn);
+    let kept = register(&conn);
+    if !Arc::ptr_eq(&kept, &conn) {
+        conn.close();
+        return Ok(kept);
+    }
+
+    let serving = conn.clone();
+    thread::Builder::new()
+        .name("bastion-remote-conn".to_string())
+        .spawn(move || serving.serve(stream))
+        .map_err(RemoteError::Io)?;
+
+    Ok(conn)
+}
+
+#[cfg(feature = "quic")]
+fn bind_quic<A: ToSocketAddrs>(addr: A, config: RemotingConfig) -> Result<SocketAddr, RemoteError> {
+    let tls = config.tls.as_ref().ok_or_else(quic_requires_tls)?;
+    let addr = resolve(addr)?;
+    let endpoint = QuicEndpoint::bind(addr, Some(tls.server.clone()), tls.client.clone())
+        .map_err(RemoteError::Io)?;
+    let endpoint = Arc::new(endpoint);
+    let local_addr = endpoint.local_addr().map_err(RemoteError::Io)?;
+    debug!("Remote: Listening on {} (QUIC).", local_addr);
+    *NODE.config.write().unwrap() = config;
+    *NODE.bound.lock().unwrap() = Some(local_addr);
+    *NODE.quic.lock().unwrap() = Some(endpoint.clone());
+
+    let accepting = endpoint.clone();
+    let thread = thread::Builder::new()
+        .name("bastion-remote-listener".to_string())
+        .spawn(move || {
+            let active = Arc::new(AtomicUsize::new(0));
+            while let Some(incoming) = accepting.accept() {
+                accept_quic(incoming, &active);
+            }
+        })
+        .map_err(RemoteError::Io)?;
+
+    listen(Listener {
+        thread,
+        stop: Box::new(move || endpoint.close()),
+    });
+    Ok(local_addr)
+}
+
+#[cfg(feature = "quic")]
+fn connect_quic<A: ToSocketAddrs>(addr: A) -> Result<RemoteNode, RemoteError> {
+    let addr = resolve(addr)?;
+    let config = NODE.config.read().unwrap().clone();
+    let endpoint = {
+        let mut quic = NODE.quic.lock().unwrap();
+        match &*quic {
+            Some(endpoint) => endpoint.clone(),
+            None => {
+                // This node wasn't bound, so it only opens connections.
+                let tls = config.tls.as_ref().ok_or_else(quic_requires_tls)?;
+                let local = if addr.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let local = local.parse().unwrap();
+                let endpoint =
+                    QuicEndpoint::bind(local, None, tls.client.clone()).map_err(RemoteError::Io)?;
+                quic.get_or_insert(Arc::new(endpoint)).clone()
+            }
+        }
+    };
+
+    let name = match &config.tls_server_name {
+        Some(name) => name.clone(),
+        None => addr.ip().to_string(),
+    };
+
+    let link = endpoint
+        .connect(addr, &name)
+        .map_err(|err| RemoteError::Handshake(format!("QUIC handshake failed: {}", err)))?;
+    let conn = open_quic(link, false, None)?;
+    Ok(RemoteNode { conn })
+}
+
+#[cfg(feature = "quic")]
+fn accept_quic(incoming: Incoming, active: &Arc<AtomicUsize>) {
+    let permit = match Permit::acquire(active) {
+        Some(permit) => permit,
+        None => {
+            // Dropping the incoming connection refuses it.
+            warn!("Remote: Too many connections, refusing one.");
+            return;
+        }
+    };
+
+    // Like for TCP, the handshake is made by the connection's thread.
+    let spawned = thread::Builder::new()
+        .name("bastion-remote-conn".to_string())
+        .spawn(move || {
+            let opened = incoming
+                .establish()
+                .map_err(RemoteError::Io)
+                .and_then(|link| open_quic(link, true, Some(permit)));
+            if let Err(err) = opened {
+                warn!("Remote: Couldn't accept a connection: {:?}", err);
+            }
+        });
+
+    if let Err(err) = spawned {
+        warn!("Remote: Couldn't accept a connection: {}", err);
+    }
+}
+
+#[cfg(feature = "quic")]
+/// Exchanges a handshake with the other node on the control stream
+/// of the QUIC connection, then starts handling the frames it sends
+/// on any of its streams.
+fn open_quic(
+    link: QuicLink,
+    accepted: bool,
+    permit: Option<Permit>,
+) -> Result<Arc<Connection>, RemoteError> {
+    let addr = link.remote_addr();
+    let config = NODE.config.read().unwrap().clone();
+    let mut control = link
+        .control(accepted, config.handshake_timeout)
+        .map_err(RemoteError::Io)?;
+    // FIXME: as over TCP, the id isn't bound to the certificate.
+    let (peer, supported) = handshake(&mut control, &config)?;
+    let compressor = Compressor::negotiate(&config, &supported);
+    debug!("Remote: Connected to {} ({}, QUIC).", peer, addr);
+
+    let link = Arc::new(link);
+    let writer = Writer::Quic(link.clone());
+    let conn = Connection::new(peer, addr, &config, compressor, writer, accepted, permit);
+    let conn = Arc::new(conn);
+    let kept = register(&conn);
+    if !Arc::ptr_eq(&kept, &conn) {
+        conn.close();
+        return Ok(kept);
+    }
+
+    let serving = conn.clone();
+    let closed = conn.clone();
+    link.serve(
+        control,
+        config.max_frame_len,
+        move |frame| match Frame::decode(&frame) {
+            Ok(frame) => serving.handle(frame),
+            Err(err) => warn!("Remote({}): Dropping invalid frame: {}", serving.peer, err),
+        },
+        move || {
+            debug!("Remote({}): Connection closed.", closed.peer);
+            closed.close();
+        },
+    );
+
+    Ok(conn)
+}
+
+#[cfg(feature = "quic")]
+fn resolve<A: ToSocketAddrs>(addr: A) -> Result<SocketAddr, RemoteError> {
+    let mut addrs = addr.to_socket_addrs().map_err(RemoteError::Io)?;
+    addrs.next().ok_or_else(|| {
+        let err = io::Error::new(io::ErrorKind::InvalidInput, "no address to use");
+        RemoteError::Io(err)
+    })
+}
+
+#[cfg(feature = "quic")]
+fn quic_requires_tls() -> RemoteError {
+    let err = "the QUIC transport requires TLS to be enabled";
+    RemoteError::Io(io::Error::new(io::ErrorKind::InvalidInput, err))
+}
+
+#[cfg(feature = "tls")]
+/// Starts a TLS session with the other node if TLS is enabled,
+/// acting as the server if the connection was accepted.
+fn secure(
+    stream: TcpStream,
+    addr: SocketAddr,
+    accepted: bool,
+    config: &RemotingConfig,
+) -> Result<Stream, RemoteError> {
+    let tls = match &config.tls {
+        Some(tls) => tls,
+        None => return Ok(Stream::Tcp(stream)),
+    };
+
+    let secured = if accepted {
+        TlsStream::accept(stream, tls.server.clone())
+    } else {
+        let name = match &config.tls_server_name {
+            Some(name) => ServerName::try_from(name.as_str()).map_err(|err| {
+                RemoteError::Handshake(format!("invalid server name: {}: {}", name, err))
+            })?,
+            None => ServerName::IpAddress(addr.ip()),
+        };
+
+        TlsStream::connect(stream, tls.client.clone(), name)
+    };
+
+    secured
+        .map(Stream::Tls)
+        .map_err(|err| RemoteError::Handshake(format!("TLS handshake failed: {}", err)))
+}
+
+#[cfg(not(feature = "tls"))]
+fn secure(
+    stream: TcpStream,
+    _addr: SocketAddr,
+    _accepted: bool,
+    _config: &RemotingConfig,
+) -> Result<Stream, RemoteError> {
+    Ok(Stream::Tcp(stream))
+}
+
+/// Exchanges a handshake with the other node, returning its id and
+/// the compression algorithms it supports.
+fn handshake<S: Read + Write>(
+    stream: &mut S,
+    config: &RemotingConfig,
+) -> Result<(NodeId, Vec<u8>), RemoteError> {
+    let codec = config.codec();
+    let hello = Frame::Hello {
+        node: NODE.id,
+        version: PROTOCOL_VERSION,
+        codec: codec.name().to_string(),
+        compressions: Compressor::supported(),
+    };
+    stream.write_all(&hello.encode()).map_err(RemoteError::Io)?;
+
+    let peer = match read_frame(stream, config.max_frame_len).map_err(RemoteError::Io)? {
+        Frame::Hello { version, .. } if version != PROTOCOL_VERSION => {
+            return Err(RemoteError::Handshake(format!(
+                "unsupported protocol version: {}",
+                version
+            )))
+        }
+        Frame::Hello { codec: other, .. } if other != codec.name() => {
+            return Err(RemoteError::Handshake(format!(
+                "mismatched codecs: {} and {}",
+                codec.name(),
+                other
+            )))
+        }
+        Frame::Hello {
+            node, compressions, ..
+        } => (node, compressions),
+        frame => {
+            return Err(RemoteError::Handshake(format!(
+                "unexpected frame: {:?}",
+                frame
+            )))
+        }
+    };
+
+    Ok(peer)
+}
+
+fn read_frame<R: Read>(reader: &mut R, max_len: usize) -> io::Result<Frame> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > max_len {
+        return Err(invalid_data(format!("frame too long: {}", len)));
+    }
+
+    // The buffer grows as the frame is received, rather than being
+    // allocated upfront for the length the other node announced.
+    let mut buf = Vec::with_capacity(len.min(INITIAL_FRAME_CAPACITY));
+    reader.by_ref().take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Frame::decode(&buf)
+}
+
+/// Writes the frames queued on a connection until it gets closed,
+/// then shuts its stream down.
+fn write_frames(
+    peer: NodeId,
+    mut writer: Stream,
+    frames: Receiver<Vec<u8>>,
+    queued: Arc<AtomicUsize>,
+) {
+    while let Ok(frame) = frames.recv() {
+        if let Err(err) = write_batch(&mut writer, frame, &frames, &queued) {
+            debug!("Remote({}): Couldn't write: {}", peer, err);
+            break;
+        }
+    }
+
+    // The connection's thread then closes it, if it didn't already.
+    writer.tcp().shutdown(Shutdown::Both).ok();
+}
+
+/// Writes the frame along with the ones queued after it, up to
+/// `WRITE_QUEUE_BYTES`, then flushes the stream.
+fn write_batch(
+    writer: &mut Stream,
+    frame: Vec<u8>,
+    frames: &Receiver<Vec<u8>>,
+    queued: &AtomicUsize,
+) -> io::Result<()> {
+    let mut written = 0;
+    for frame in iter::once(frame).chain(frames.try_iter()) {
+        queued.fetch_sub(frame.len(), Ordering::SeqCst);
+        writer.write_all(&frame)?;
+        written += frame.len();
+        if written >= WRITE_QUEUE_BYTES {
+            break;
+        }
+    }
+
+    writer.flush()
+}
+
+/// Sends the message to the local child with the given path,
+/// returning whether it exists.
+fn deliver(path: &str, msg: Msg) -> bool {
+    if let Some(name) = path.strip_prefix(DISPATCHER_PREFIX) {
+        return dispatch(name, msg);
+    }
+
+    let child = match NODE.child(path) {
+        Some(child) => child,
+        None => {
+            warn!("Remote: Dropping message sent to unknown child: {}", path);
+            return false;
+        }
+    };
+
+    let env = Envelope::from_dead_letters(BastionMessage::Message(msg));
+    if let Err(env) = child.send(env) {
+        dead_letters::publish(child.path().clone(), env);
+        return false;
+    }
+
+    true
+}
+
+/// Broadcasts the message to the children groups of the local
+/// named dispatcher, returning whether it exists.
+pub(crate) fn dispatch(name: &str, msg: Msg) -> bool {
+    let dispatcher = SYSTEM.dispatcher();
+    if !dispatcher.has_named(name) {
+        warn!(
+            "Remote: Dropping message sent to unknown dispatcher: {}",
+            name
+        );
+        return false;
+    }
+
+    let msg = Arc::new(SignedMessage::new(msg, RefAddr::dead_letters()));
+    dispatcher.broadcast_message(BroadcastTarget::Group(name.to_string()), &msg);
+    true
+}
+
+/// Returns the path the messages broadcasted to the named
+/// dispatcher of another node are sent to.
+pub(crate) fn dispatcher_path(name: &str) -> String {
+    format!("{}{}", DISPATCHER_PREFIX, name)
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+fn invalid_data<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+impl Compressor {
+    const NONE: u8 = 0;
+    #[cfg(feature = "compression")]
+    const LZ4: u8 = 1;
+    #[cfg(feature = "compression")]
+    const ZSTD: u8 = 2;
+
+    /// Returns the ids of the algorithms this node can decompress.
+    fn supported() -> Vec<u8> {
+        #[cfg(feature = "compression")]
+        {
+            vec![Compressor::LZ4, Compressor::ZSTD]
+        }
+        #[cfg(not(feature = "compression"))]
+        {
+            Vec::new()
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    /// Compresses the payloads using the algorithm of the
+    /// configuration, if the other node supports it.
+    fn negotiate(config: &RemotingConfig, supported: &[u8]) -> Self {
+        let id = |compression| match compression {
+            Compression::Lz4 => Compressor::LZ4,
+            Compression::Zstd => Compressor::ZSTD,
+        };
+
+        Compressor {
+            compression: config
+                .compression
+                .filter(|compression| supported.contains(&id(*compression))),
+            threshold: config.compression_threshold,
+        }
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn negotiate(_config: &RemotingConfig, _supported: &[u8]) -> Self {
+        Compressor::default()
+    }
+
+    /// Prefixes the payload by the id of the algorithm it was
+    /// compressed with, which is only the case if it is larger than
+    /// the threshold and compressing it made it smaller.
+    fn pack(&self, payload: Vec<u8>) -> Vec<u8> {
+        #[cfg(feature = "compression")]
+        {
+            if let Some(compression) = self.compression {
+                if payload.len() >= self.threshold {
+                    let compressed = match compression {
+                        Compression::Lz4 => {
+                            let mut compressed = vec![Compressor::LZ4];
+                            compressed.extend(lz4_flex::compress_prepend_size(&payload));
+                            Some(compressed)
+                        }
+                        Compression::Zstd => zstd::bulk::compress(&payload, 0).ok().map(|data| {
+                            let mut compressed = vec![Compressor::ZSTD];
+                            compressed.extend(data);
+                            compressed
+                        }),
+                    };
+
+                    match compressed {
+                        Some(compressed) if compressed.len() <= payload.len() => return compressed,
+                        _ => (),
+                    }
+                }
+            }
+        }
+
+        let mut packed = Vec::with_capacity(payload.len() + 1);
+        packed.push(Compressor::NONE);
+        packed.extend(payload);
+        packed
+    }
+
+    /// Returns the payload without its prefix, decompressing it if
+    /// needed.
+    fn unpack(payload: &[u8], max_len: usize) -> io::Result<Cow<[u8]>> {
+        let (id, data) = match payload.split_first() {
+            Some(split) => split,
+            None => return Err(invalid_data("empty payload")),
+        };
+
+        match *id {
+            Compressor::NONE => Ok(Cow::Borrowed(data)),
+            #[cfg(feature = "compression")]
+            Compressor::LZ4 => {
+                let (len, _) = lz4_flex::block::uncompressed_size(data).map_err(invalid_data)?;
+                if len > max_len {
+                    return Err(invalid_data(format!("payload too long: {}", len)));
+                }
+
+                let data = lz4_flex::decompress_size_prepended(data).map_err(invalid_data)?;
+                Ok(Cow::Owned(data))
+            }
+            #[cfg(feature = "compression")]
+            Compressor::ZSTD => {
+                // Like for frames, the payload is decompressed without
+                // allocating its maximum length upfront.
+                let mut decompressed = Vec::new();
+                zstd::stream::read::Decoder::new(data)?
+                    .take(max_len as u64 + 1)
+                    .read_to_end(&mut decompressed)?;
+                if decompressed.len() > max_len {
+                    let err = format!("payload too long: {}", decompressed.len());
+                    return Err(invalid_data(err));
+                }
+
+                Ok(Cow::Owned(decompressed))
+            }
+            id => Err(invalid_data(format!("unsupported compression: {}", id))),
+        }
+    }
+}
+
+impl Listener {
+    fn stop(self) {
+        (self.stop)();
+        if self.thread.join().is_err() {
+            warn!("Remote: The listener panicked.");
+        }
+    }
+}
+
+impl Permit {
+    /// Takes one of the slots counted by `active`, unless
+    /// `MAX_CONNECTIONS` were already taken.
+    fn acquire(active: &Arc<AtomicUsize>) -> Option<Self> {
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+
+        Some(Permit(active.clone()))
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Stream {
+    fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Stream::Tcp(stream) => stream.try_clone().map(Stream::Tcp),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.try_clone().map(Stream::Tls),
+        }
+    }
+
+    fn tcp(&self) -> &TcpStream {
+        match self {
+            Stream::Tcp(stream) => stream,
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.get_ref(),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Debug for TlsConfig {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("TlsConfig").finish()
+    }
+}
+
+impl Debug for Connection {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Connection")
+            .field("peer", &self.peer)
+            .field("addr", &self.addr)
+            .field("connected", &self.connected)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::BincodeCodec;
+
+    #[test]
+    fn encodes_and_decodes_frames() {
+        let frames = vec![
+            Frame::Hello {
+                node: NodeId::new(),
+                version: PROTOCOL_VERSION,
+                codec: JsonCodec.name().to_string(),
+                compressions: vec![1, 2],
+            },
+            Frame::Tell {
+                path: "/a/b".to_string(),
+                type_name: "u64".to_string(),
+                payload: b"42".to_vec(),
+            },
+            Frame::Ask {
+                request_id: 7,
+                path: "/a/b".to_string(),
+                type_name: "u64".to_string(),
+                payload: b"42".to_vec(),
+            },
+            Frame::Reply {
+                request_id: 7,
+                type_name: "u64".to_string(),
+                payload: Vec::new(),
+            },
+            Frame::Failed { request_id: 7 },
+            Frame::Cluster {
+                payload: b"{}".to_vec(),
+            },
+        ];
+
+        for frame in frames {
+            let encoded = frame.encode();
+            let decoded = read_frame(&mut &encoded[..], DEFAULT_MAX_FRAME_LEN).unwrap();
+            assert_eq!(decoded, frame);
+        }
+    }
+
+    #[test]
+    fn rejects_mismatched_codecs() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let peer = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let hello = Frame::Hello {
+                node: NodeId::new(),
+                version: PROTOCOL_VERSION,
+                codec: BincodeCodec.name().to_string(),
+                compressions: Vec::new(),
+            };
+            stream.write_all(&hello.encode()).unwrap();
+            read_frame(&mut stream, DEFAULT_MAX_FRAME_LEN).unwrap()
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        match handshake(&mut stream, &RemotingConfig::default()) {
+            Err(RemoteError::Handshake(_)) => (),
+            other => panic!("Unexpected handshake result: {:?}", other),
+        }
+
+        match peer.join().unwrap() {
+            Frame::Hello { codec, .. } => assert_eq!(codec, "json"),
+            frame => panic!("Unexpected frame: {:?}", frame),
+        }
+    }
+
+    #[test]
+    fn packs_payloads() {
+        let packed = Compressor::default().pack(b"42".to_vec());
+        assert_eq!(packed, b"\x0042");
+        assert_eq!(&*Compressor::unpack(&packed, 2).unwrap(), b"42");
+        assert!(Compressor::unpack(b"\x0742", 2).is_err());
+        assert!(Compressor::unpack(b"", 2).is_err());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compresses_large_payloads() {
+        let payload = vec![42; 64 * 1024];
+        for compression in &[Compression::Lz4, Compression::Zstd] {
+            let config = RemotingConfig::default().with_compression(*compression);
+            let compressor = Compressor::negotiate(&config, &Compressor::supported());
+
+            let packed = compressor.pack(payload.clone());
+            assert!(packed.len() < payload.len());
+            assert_eq!(
+                &*Compressor::unpack(&packed, payload.len()).unwrap(),
+                &payload[..]
+            );
+            assert!(Compressor::unpack(&packed, payload.len() - 1).is_err());
+
+            // Small payloads aren't compressed.
+            assert_eq!(compressor.pack(b"42".to_vec()), b"\x0042");
+        }
+
+        // Nor payloads sent to nodes not supporting the algorithm.
+        let config = RemotingConfig::default().with_compression(Compression::Zstd);
+        let compressor = Compressor::negotiate(&config, &[Compressor::LZ4]);
+        assert_eq!(compressor.pack(payload.clone()).len(), payload.len() + 1);
+    }
+
+    #[test]
+    fn rejects_truncated_frames() {
+        let encoded = Frame::Failed { request_id: 7 }.encode();
+        assert!(Frame::decode(&encoded[4..8]).is_err());
+        assert!(read_frame(&mut &encoded[..8], DEFAULT_MAX_FRAME_LEN).is_err());
+    }
+
+    #[test]
+    fn rejects_frames_too_long() {
+        let encoded = Frame::Cluster {
+            payload: vec![0; 64],
+        }
+        .encode();
+        assert!(read_frame(&mut &encoded[..], 64).is_err());
+        assert!(read_frame(&mut &encoded[..], encoded.len() - 4).is_ok());
+
+        // The announced length isn't allocated before being received.
+        let mut announced = u32::MAX.to_be_bytes().to_vec();
+        announced.push(Frame::CLUSTER);
+        assert!(read_frame(&mut &announced[..], usize::MAX).is_err());
+    }
+
+    #[test]
+    fn bounds_write_queue_by_bytes() {
+        let (queue, frames) = mpsc::channel();
+        let queued = Arc::new(AtomicUsize::new(0));
+        let writer = Writer::Stream {
+            queue: Mutex::new(Some(queue)),
+            queued: queued.clone(),
+        };
+        let config = RemotingConfig::default();
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let conn = Connection::new(
+            NodeId::new(),
+            addr,
+            &config,
+            Compressor::default(),
+            writer,
+            false,
+            None,
+        );
+
+        let frame = Frame::Cluster {
+            payload: vec![0; 1024 * 1024],
+        };
+        let len = frame.encode().len();
+        let mut sent = 0;
+        while conn.send(&frame).is_ok() {
+            sent += 1;
+        }
+
+        assert_eq!(sent, WRITE_QUEUE_BYTES / len);
+        let err = conn.send(&frame).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        // Writing a frame makes room for another one.
+        let written: Vec<u8> = frames.recv().unwrap();
+        queued.fetch_sub(written.len(), Ordering::SeqCst);
+        assert!(conn.send(&frame).is_ok());
+        assert!(conn.send(&frame).is_err());
+    }
+
+    #[test]
+    fn caps_accepted_connections() {
+        let active = Arc::new(AtomicUsize::new(0));
+        let mut permits: Vec<_> = (0..MAX_CONNECTIONS)
+            .map(|_| Permit::acquire(&active).unwrap())
+            .collect();
+        assert!(Permit::acquire(&active).is_none());
+
+        permits.pop();
+        assert!(Permit::acquire(&active).is_some());
+        drop(permits);
+        assert_eq!(active.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn keeps_one_connection_per_node() {
+        let (low, high) = {
+            let (a, b) = (NodeId::new(), NodeId::new());
+            (a.min(b), a.max(b))
+        };
+
+        // Reconnections replace the old connection.
+        assert!(keeps_new(low, high, false, false));
+        assert!(keeps_new(low, high, true, true));
+
+        // Otherwise, both nodes keep the connection opened by `low`.
+        assert!(!keeps_new(low, high, true, false));
+        assert!(keeps_new(low, high, false, true));
+        assert!(keeps_new(high, low, true, false));
+        assert!(!keeps_new(high, low, false, true));
+    }
+}
@@ -5,15 +5,21 @@
 //! * Configuring limits and used strategies for resizers.
 //! * Strategy based on statistics given by spawned actors.
 //! * Auto-creation / deletion actors on demand.
+//! * Custom scaling policies, deciding how many actors must be
+//!     running from the mailbox sizes, the processing latency and
+//!     the load of the executor.
 //!
 use crate::broadcast::Sender;
 use crate::context::BastionId;
+use bastion_executor::load_balancer::{self, SmpStats};
 use fxhash::FxHashMap;
 use lever::table::lotable::LOTable;
 use lightproc::recoverable_handle::RecoverableHandle;
-use std::cmp::min;
+use std::cmp::{max, min};
+use std::fmt::Debug;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 #[cfg(feature = "scaling")]
 #[derive(Debug)]
@@ -41,6 +47,114 @@ pub struct OptimalSizeExploringResizer {
     // Determines how much actors needs to be removed (in percentages
     // relatively to the active actors).
     downscale_rate: f64,
+    // How long each actor took to process its last message, in
+    // microseconds.
+    actor_latencies: Arc<LOTable<BastionId, u64>>,
+    // The policy deciding how many actors must be running instead
+    // of the upscale strategy, if any.
+    policy: Option<Arc<dyn ScalingPolicy>>,
+    // How long the actors must be idle before all of them are
+    // stopped, if the group can scale down to zero actors.
+    idle_timeout: Option<Duration>,
+}
+
+#[cfg(feature = "scaling")]
+/// A policy deciding how many elements a children group must have,
+/// used by a resizer instead of its upscale strategy (see
+/// [`OptimalSizeExploringResizer::with_policy`]).
+///
+/// The resizer keeps the amount of elements within its lower and
+/// upper bounds, and stops the elements with the smallest mailboxes
+/// first when scaling down.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// #[derive(Debug)]
+/// // One element for every 10 queued messages.
+/// struct OnePerTenMessages;
+///
+/// impl ScalingPolicy for OnePerTenMessages {
+///     fn target_replicas(&self, stats: &ScalingStats) -> usize {
+///         let queued: u32 = stats.mailbox_sizes().iter().sum();
+///         (queued as usize + 9) / 10
+///     }
+/// }
+///
+/// let resizer = OptimalSizeExploringResizer::default().with_policy(OnePerTenMessages);
+/// ```
+///
+/// [`OptimalSizeExploringResizer::with_policy`]: struct.OptimalSizeExploringResizer.html#method.with_policy
+pub trait ScalingPolicy: Debug + Send + Sync + 'static {
+    /// Returns how many elements the children group must have.
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - The statistics of the children group.
+    fn target_replicas(&self, stats: &ScalingStats) -> usize;
+}
+
+#[cfg(feature = "scaling")]
+#[derive(Debug, Clone)]
+/// The statistics of a children group given to a
+/// [`ScalingPolicy`].
+///
+/// [`ScalingPolicy`]: trait.ScalingPolicy.html
+pub struct ScalingStats {
+    replicas: usize,
+    mailbox_sizes: Vec<u32>,
+    latencies: Vec<Duration>,
+    cpus: usize,
+    cpu_load: usize,
+}
+
+#[cfg(feature = "scaling")]
+#[derive(Debug, Clone)]
+/// A [`ScalingPolicy`] adding elements while the average size of
+/// their mailboxes is above a threshold, and removing elements
+/// while it is below another one.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// // Adding two elements at a time while more than 10 messages are
+/// // queued on average, removing one while there is less than one.
+/// let policy = QueueLengthPolicy::new(10, 1).with_step(2);
+/// let resizer = OptimalSizeExploringResizer::default().with_policy(policy);
+/// ```
+///
+/// [`ScalingPolicy`]: trait.ScalingPolicy.html
+pub struct QueueLengthPolicy {
+    upscale_threshold: u32,
+    downscale_threshold: u32,
+    step: usize,
+}
+
+#[cfg(feature = "scaling")]
+#[derive(Debug, Clone)]
+/// A [`ScalingPolicy`] sizing the children group so that the
+/// average time its elements take to process a message gets close
+/// to a target, assuming that it is proportional to the load of
+/// each element.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::time::Duration;
+/// #
+/// let policy = LatencyTargetPolicy::new(Duration::from_millis(50));
+/// let resizer = OptimalSizeExploringResizer::default().with_policy(policy);
+/// ```
+///
+/// [`ScalingPolicy`]: trait.ScalingPolicy.html
+pub struct LatencyTargetPolicy {
+    target: Duration,
+    tolerance: f64,
 }
 
 #[cfg(feature = "scaling")]
@@ -69,7 +183,7 @@ pub(crate) struct ActorGroupStats {
 }
 
 #[cfg(feature = "scaling")]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// An enumeration that describe acceptable upper boundaries
 /// for the spawned actors in runtime.
 pub enum UpperBound {
@@ -96,6 +210,8 @@ pub(crate) enum ScalingRule {
     Upscale(u64),
     /// Defines what actors must be stopped or removed.
     Downscale(Vec<BastionId>),
+    /// Defines what running actors must be stopped.
+    Shutdown(Vec<BastionId>),
     /// Special result kind that defines that no needed to scale up/down.
     DoNothing,
 }
@@ -112,6 +228,18 @@ impl OptimalSizeExploringResizer {
         self.actor_stats.clone()
     }
 
+    /// Returns a reference to the table with the processing latency
+    /// of actors.
+    pub(crate) fn actor_latencies(&self) -> Arc<LOTable<BastionId, u64>> {
+        self.actor_latencies.clone()
+    }
+
+    /// Returns how long the actors must be idle before the group
+    /// scales down to zero actors, if it can.
+    pub(crate) fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
     /// Returns lower bound of the number of actors in the scaling group.
     pub(crate) fn lower_bound(&self) -> u64 {
         self.lower_bound
@@ -122,6 +250,11 @@ impl OptimalSizeExploringResizer {
         self.lower_bound = lower_bound;
     }
 
+    /// Set upper bound of the autoscaling group.
+    pub(crate) fn set_upper_bound(&mut self, upper_bound: UpperBound) {
+        self.upper_bound = upper_bound;
+    }
+
     /// Overrides the minimal amount of actors available to use.
     pub fn with_lower_bound(mut self, lower_bound: u64) -> Self {
         if lower_bound == u64::MIN {
@@ -162,6 +295,131 @@ impl OptimalSizeExploringResizer {
         self
     }
 
+    /// Sets the policy deciding how many actors must be running,
+    /// which is used instead of the upscale strategy and downscale
+    /// parameters. The lower and upper bounds still apply.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The [`ScalingPolicy`] to use, like a
+    ///     [`QueueLengthPolicy`] or a [`LatencyTargetPolicy`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::children(|children| {
+    ///     children
+    ///         .with_resizer(
+    ///             OptimalSizeExploringResizer::default()
+    ///                 .with_lower_bound(2)
+    ///                 .with_upper_bound(UpperBound::Limit(8))
+    ///                 .with_policy(QueueLengthPolicy::new(10, 1)),
+    ///         )
+    ///         .with_exec(|ctx: BastionContext| async move {
+    ///             loop {
+    ///                 let _ = ctx.recv().await?;
+    ///             }
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`ScalingPolicy`]: trait.ScalingPolicy.html
+    /// [`QueueLengthPolicy`]: struct.QueueLengthPolicy.html
+    /// [`LatencyTargetPolicy`]: struct.LatencyTargetPolicy.html
+    pub fn with_policy(mut self, policy: impl ScalingPolicy) -> Self {
+        self.policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Allows the group to scale down to zero actors once all of
+    /// them were idle (waiting for a message with an empty mailbox)
+    /// for the given duration.
+    ///
+    /// The group starts without any actor and, once it has none,
+    /// launches one when a message is broadcasted to it (the
+    /// message being kept in the actor's mailbox until it is
+    /// started). The lower bound only applies while the group has
+    /// actors.
+    ///
+    /// Note that the group checks whether its actors are idle when
+    /// it receives a message, and at least at every heartbeat (see
+    /// [`Children::with_heartbeat_tick`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `idle_timeout` - How long the actors must be idle before
+    ///     they are stopped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// let pipeline = Bastion::children(|children| {
+    ///     children
+    ///         .with_resizer(
+    ///             OptimalSizeExploringResizer::default()
+    ///                 .with_scale_to_zero(Duration::from_secs(300)),
+    ///         )
+    ///         .with_heartbeat_tick(Duration::from_secs(10))
+    ///         .with_exec(|ctx: BastionContext| async move {
+    ///             loop {
+    ///                 let _ = ctx.recv().await?;
+    ///             }
+    ///         })
+    /// }).expect("Couldn't create the children group.");
+    ///
+    /// // An element is launched to receive the message.
+    /// pipeline.broadcast("process").expect("Couldn't send the message.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Children::with_heartbeat_tick`]: ../children/struct.Children.html#method.with_heartbeat_tick
+    pub fn with_scale_to_zero(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+
     /// Applies checks and does scaling up/down depends on stats.
     pub(crate) async fn scale(
         &self,
@@ -176,6 +434,10 @@ impl OptimalSizeExploringResizer {
             return ScalingRule::Upscale(additional_actors_count);
         }
 
+        if let Some(policy) = &self.policy {
+            return self.apply_policy(policy.as_ref(), actors);
+        }
+
         let mut stats = ActorGroupStats::load(self.stats.clone());
 
         if let Some(scaling_rule) = self.do_upscaling(&mut stats, actors) {
@@ -256,6 +518,54 @@ impl OptimalSizeExploringResizer {
         None
     }
 
+    // Scaling up or down to the amount of actors asked by the policy,
+    // within the limits.
+    fn apply_policy(
+        &self,
+        policy: &dyn ScalingPolicy,
+        actors: &FxHashMap<BastionId, (Sender, RecoverableHandle<()>)>,
+    ) -> ScalingRule {
+        let mut mailbox_sizes = Vec::with_capacity(actors.len());
+        let mut latencies = Vec::with_capacity(actors.len());
+        for actor_id in actors.keys() {
+            let mailbox_size = self.actor_stats.get(actor_id).unwrap_or(0);
+            mailbox_sizes.push((actor_id.clone(), mailbox_size));
+            if let Some(latency) = self.actor_latencies.get(actor_id) {
+                latencies.push(Duration::from_micros(latency));
+            }
+        }
+
+        let stats = ScalingStats {
+            replicas: actors.len(),
+            mailbox_sizes: mailbox_sizes.iter().map(|(_, size)| *size).collect(),
+            latencies,
+            cpus: *load_balancer::core_count(),
+            cpu_load: load_balancer::stats().mean(),
+        };
+
+        let target = max(policy.target_replicas(&stats) as u64, self.lower_bound);
+        let target = match self.upper_bound {
+            UpperBound::Limit(actors_limit) => min(target, actors_limit),
+            UpperBound::Unlimited => target,
+        };
+
+        let active_actors = actors.len() as u64;
+        if target > active_actors {
+            ScalingRule::Upscale(target - active_actors)
+        } else if target < active_actors {
+            // The least busy actors are stopped first.
+            mailbox_sizes.sort_by_key(|(_, size)| *size);
+            let actors_to_stop = mailbox_sizes
+                .into_iter()
+                .take((active_actors - target) as usize)
+                .map(|(actor_id, _)| actor_id)
+                .collect();
+            ScalingRule::Shutdown(actors_to_stop)
+        } else {
+            ScalingRule::DoNothing
+        }
+    }
+
     // Adjusting upscaling in according to the upper_bound limits.
     fn adjustment_upscaling(
         &self,
@@ -290,10 +600,151 @@ impl Default for OptimalSizeExploringResizer {
             upscale_rate: 0.1,
             downscale_threshold: 0.3,
             downscale_rate: 0.1,
+            actor_latencies: Arc::new(LOTable::new()),
+            policy: None,
+            idle_timeout: None,
         }
     }
 }
 
+#[cfg(feature = "scaling")]
+impl ScalingStats {
+    /// Returns how many elements the children group has.
+    pub fn replicas(&self) -> usize {
+        self.replicas
+    }
+
+    /// Returns how many messages are queued in the mailbox of each
+    /// element.
+    pub fn mailbox_sizes(&self) -> &[u32] {
+        &self.mailbox_sizes
+    }
+
+    /// Returns the average amount of messages queued in the
+    /// elements' mailboxes.
+    pub fn average_mailbox_size(&self) -> f64 {
+        if self.mailbox_sizes.is_empty() {
+            return 0.0;
+        }
+
+        let queued: u64 = self.mailbox_sizes.iter().map(|size| *size as u64).sum();
+        queued as f64 / self.mailbox_sizes.len() as f64
+    }
+
+    /// Returns how long the elements took to process their last
+    /// message, for the elements that processed one.
+    pub fn latencies(&self) -> &[Duration] {
+        &self.latencies
+    }
+
+    /// Returns the average time the elements took to process their
+    /// last message, or `None` if none of them processed one yet.
+    pub fn average_latency(&self) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+
+        let total: Duration = self.latencies.iter().sum();
+        Some(total / self.latencies.len() as u32)
+    }
+
+    /// Returns how many cores the executor uses.
+    pub fn cpus(&self) -> usize {
+        self.cpus
+    }
+
+    /// Returns the average amount of tasks waiting to be run by the
+    /// executor's cores.
+    pub fn cpu_load(&self) -> usize {
+        self.cpu_load
+    }
+}
+
+#[cfg(feature = "scaling")]
+impl QueueLengthPolicy {
+    /// Creates a policy adding elements while the average size of
+    /// their mailboxes is above `upscale_threshold`, and removing
+    /// elements while it is below `downscale_threshold`. One element
+    /// is added or removed at a time by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `upscale_threshold` - The average mailbox size above which
+    ///     elements are added.
+    /// * `downscale_threshold` - The average mailbox size below
+    ///     which elements are removed.
+    pub fn new(upscale_threshold: u32, downscale_threshold: u32) -> Self {
+        QueueLengthPolicy {
+            upscale_threshold,
+            downscale_threshold,
+            step: 1,
+        }
+    }
+
+    /// Overrides how many elements are added or removed at a time.
+    pub fn with_step(mut self, step: usize) -> Self {
+        self.step = step;
+        self
+    }
+}
+
+#[cfg(feature = "scaling")]
+impl ScalingPolicy for QueueLengthPolicy {
+    fn target_replicas(&self, stats: &ScalingStats) -> usize {
+        let average = stats.average_mailbox_size();
+        if average > self.upscale_threshold as f64 {
+            stats.replicas().saturating_add(self.step)
+        } else if average < self.downscale_threshold as f64 {
+            stats.replicas().saturating_sub(self.step)
+        } else {
+            stats.replicas()
+        }
+    }
+}
+
+#[cfg(feature = "scaling")]
+impl LatencyTargetPolicy {
+    /// Creates a policy sizing the children group so that its
+    /// elements take `target` on average to process a message. The
+    /// group is only resized once the average is more than 10% away
+    /// from the target by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - How long the elements should take to process a
+    ///     message.
+    pub fn new(target: Duration) -> Self {
+        LatencyTargetPolicy {
+            target,
+            tolerance: 0.1,
+        }
+    }
+
+    /// Overrides how far from the target (as a fraction of it) the
+    /// average latency can be without resizing the group.
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+}
+
+#[cfg(feature = "scaling")]
+impl ScalingPolicy for LatencyTargetPolicy {
+    fn target_replicas(&self, stats: &ScalingStats) -> usize {
+        let latency = match stats.average_latency() {
+            Some(latency) => latency,
+            None => return stats.replicas(),
+        };
+
+        let ratio = latency.as_secs_f64() / self.target.as_secs_f64();
+        if (ratio - 1.0).abs() <= self.tolerance {
+            return stats.replicas();
+        }
+
+        (stats.replicas() as f64 * ratio).ceil() as usize
+    }
+}
+
 #[cfg(feature = "scaling")]
 impl ActorGroupStats {
     fn actors_count_mask() -> u64 {
@@ -353,7 +804,21 @@ impl ActorGroupStats {
 
 #[cfg(test)]
 mod tests {
-    use crate::resizer::{ActorGroupStats, OptimalSizeExploringResizer};
+    use crate::resizer::{
+        ActorGroupStats, LatencyTargetPolicy, OptimalSizeExploringResizer, QueueLengthPolicy,
+        ScalingPolicy, ScalingStats,
+    };
+    use std::time::Duration;
+
+    fn scaling_stats(mailbox_sizes: Vec<u32>, latencies: Vec<Duration>) -> ScalingStats {
+        ScalingStats {
+            replicas: mailbox_sizes.len(),
+            mailbox_sizes,
+            latencies,
+            cpus: 4,
+            cpu_load: 0,
+        }
+    }
 
     #[test]
     fn test_resizer_stores_empty_stats_by_default() {
@@ -406,4 +871,35 @@ mod tests {
         assert_eq!(updated_stats.actors_count, 10);
         assert_eq!(updated_stats.average_mailbox_size, 50);
     }
+
+    #[test]
+    fn test_queue_length_policy_follows_thresholds() {
+        let policy = QueueLengthPolicy::new(10, 2).with_step(2);
+
+        let busy = scaling_stats(vec![20, 12, 15], Vec::new());
+        assert_eq!(policy.target_replicas(&busy), 5);
+
+        let steady = scaling_stats(vec![5, 3, 7], Vec::new());
+        assert_eq!(policy.target_replicas(&steady), 3);
+
+        let idle = scaling_stats(vec![0, 1, 0], Vec::new());
+        assert_eq!(policy.target_replicas(&idle), 1);
+    }
+
+    #[test]
+    fn test_latency_target_policy_scales_proportionally() {
+        let policy = LatencyTargetPolicy::new(Duration::from_millis(10));
+
+        let slow = scaling_stats(vec![0, 0], vec![Duration::from_millis(30); 2]);
+        assert_eq!(policy.target_replicas(&slow), 6);
+
+        let close = scaling_stats(vec![0, 0], vec![Duration::from_micros(10_500); 2]);
+        assert_eq!(policy.target_replicas(&close), 2);
+
+        let fast = scaling_stats(vec![0; 4], vec![Duration::from_millis(5); 4]);
+        assert_eq!(policy.target_replicas(&fast), 2);
+
+        let unknown = scaling_stats(vec![0; 3], Vec::new());
+        assert_eq!(policy.target_replicas(&unknown), 3);
+    }
 }
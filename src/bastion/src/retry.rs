@@ -0,0 +1,243 @@
+//!
+//! Retrying fallible futures with a backoff.
+//!
+//! [`retry`] calls a closure returning a fallible future until it
+//! succeeds or the [`RetryPolicy`] gives up, waiting between the
+//! attempts as its backoff strategy (the same strategies as the
+//! restarts of the children) tells it to. Once it gives up, the last
+//! error is either returned, or escalated to the supervisor of the
+//! child calling it by faulting the child.
+//!
+//! [`retry`]: fn.retry.html
+//! [`RetryPolicy`]: struct.RetryPolicy.html
+use crate::executor;
+use crate::panics;
+use crate::supervisor::ActorRestartStrategy;
+use std::fmt::Debug;
+use std::future::Future;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, PartialEq)]
+/// How many times [`retry`] calls its closure and how long it
+/// waits between the attempts.
+///
+/// The default policy makes 3 attempts, with an exponential
+/// backoff starting at 100ms (and capped at 10s), and returns the
+/// last error without escalating it.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::time::Duration;
+/// #
+/// let policy = RetryPolicy::new()
+///     .with_max_attempts(5)
+///     .with_backoff(ActorRestartStrategy::LinearBackOff {
+///         timeout: Duration::from_millis(50),
+///     })
+///     .with_escalation(true);
+/// ```
+///
+/// [`retry`]: fn.retry.html
+pub struct RetryPolicy {
+    max_attempts: usize,
+    backoff: ActorRestartStrategy,
+    escalate: bool,
+}
+
+impl RetryPolicy {
+    /// Creates the default policy.
+    pub fn new() -> Self {
+        RetryPolicy::default()
+    }
+
+    /// Sets how many times the closure is called before giving up.
+    ///
+    /// # Argument
+    ///
+    /// * `max_attempts` - The amount of attempts (at least 1).
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Sets how long to wait before each new attempt.
+    ///
+    /// # Argument
+    ///
+    /// * `backoff` - The strategy computing the delays, given how
+    ///     many attempts failed before the last one.
+    pub fn with_backoff(mut self, backoff: ActorRestartStrategy) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets whether to fault the child calling [`retry`] once it
+    /// gives up (instead of returning the last error), letting its
+    /// supervisor handle the failure.
+    ///
+    /// Outside of a child, the last error is always returned.
+    ///
+    /// # Argument
+    ///
+    /// * `escalate` - Whether to escalate the last error.
+    ///
+    /// [`retry`]: fn.retry.html
+    pub fn with_escalation(mut self, escalate: bool) -> Self {
+        self.escalate = escalate;
+        self
+    }
+
+    /// Returns how many times the closure is called before giving
+    /// up.
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    /// Returns the strategy computing the delays between the
+    /// attempts.
+    pub fn backoff(&self) -> &ActorRestartStrategy {
+        &self.backoff
+    }
+
+    /// Returns whether the last error is escalated to the
+    /// supervisor once the policy gives up.
+    pub fn escalates(&self) -> bool {
+        self.escalate
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            backoff: ActorRestartStrategy::ExponentialBackOff {
+                base: Duration::from_millis(100),
+                max: Duration::from_secs(10),
+                jitter: 0.0,
+            },
+            escalate: false,
+        }
+    }
+}
+
+/// Calls the closure and awaits the future it returns until it
+/// succeeds or the policy gives up, waiting between the attempts
+/// as the policy's backoff tells it to.
+///
+/// This function returns the output of the first successful
+/// attempt, or the error of the last one. If the policy escalates
+/// the errors and it is called by a child, the child faults
+/// instead of returning the error (and is restarted as its
+/// supervisor decides).
+///
+/// # Arguments
+///
+/// * `policy` - How many times to call the closure and how long to
+///     wait between the attempts.
+/// * `f` - The closure returning the future to await.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| async move {
+///         let policy = RetryPolicy::new().with_escalation(true);
+///         let response = bastion::retry(policy, || async {
+///             // Sends a request which might fail...
+///             # Ok::<_, std::io::Error>(())
+///         })
+///         .await;
+///
+///         Ok(())
+///     })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub async fn retry<F, Fut, T, E>(policy: RetryPolicy, mut f: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Debug,
+{
+    let mut failures = 0;
+    loop {
+        let err = match f().await {
+            Ok(output) => return Ok(output),
+            Err(err) => err,
+        };
+
+        failures += 1;
+        if failures >= policy.max_attempts {
+            warn!("Retry: Giving up after {} attempts: {:?}", failures, err);
+            if policy.escalate && panics::is_catching() {
+                // The child's poll catches the panic, faulting it.
+                panic!("Retry: Gave up after {} attempts: {:?}", failures, err);
+            }
+
+            return Err(err);
+        }
+
+        debug!("Retry: Attempt {} failed: {:?}", failures, err);
+        if let Some(delay) = policy.backoff.calculate(failures - 1) {
+            executor::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn gives_up_after_the_max_attempts() {
+        let policy = RetryPolicy::new()
+            .with_max_attempts(3)
+            .with_backoff(ActorRestartStrategy::Immediate)
+            .with_escalation(true);
+
+        let mut attempts = 0;
+        let res: Result<(), _> = block_on(retry(policy.clone(), || {
+            attempts += 1;
+            async move { Err(attempts) }
+        }));
+        // The error isn't escalated outside of a child.
+        assert_eq!(res, Err(3));
+
+        let mut attempts = 0;
+        let res = block_on(retry(policy, || {
+            attempts += 1;
+            async move {
+                if attempts < 2 {
+                    Err(attempts)
+                } else {
+                    Ok(attempts)
+                }
+            }
+        }));
+        assert_eq!(res, Ok(2));
+    }
+}
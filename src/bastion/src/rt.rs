@@ -0,0 +1,356 @@
+//!
+//! Cooperative scheduling of the children of the system.
+//!
+//! Futures can't be preempted, so a child receiving messages in a
+//! loop while its mailbox is never empty would otherwise keep its
+//! worker thread busy, starving the other tasks of the thread. To
+//! prevent this, every poll of a child's future gets a [`Budget`]
+//! (a maximum amount of operations and/or a maximum duration), which
+//! is consumed by [`BastionContext::recv`] and [`consume_budget`].
+//! Once it is exhausted, they yield to the executor before
+//! proceeding, and the budget is renewed the next time the child is
+//! polled.
+//!
+//! The budget is set using [`Config::with_budget`], and long-running
+//! loops that don't receive messages can call [`consume_budget`] (or
+//! [`yield_now`]) to cooperate.
+//!
+//! [`Budget`]: struct.Budget.html
+//! [`BastionContext::recv`]: ../context/struct.BastionContext.html#method.recv
+//! [`consume_budget`]: fn.consume_budget.html
+//! [`yield_now`]: fn.yield_now.html
+//! [`Config::with_budget`]: ../struct.Config.html#method.with_budget
+use futures::future;
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+// The budget of the children, read every time one is polled. The
+// amount of operations is `0` if it isn't limited (as it is at
+// least 1 otherwise), and the duration (in nanoseconds) is
+// `u64::MAX` if it isn't.
+static OPERATIONS: AtomicU32 = AtomicU32::new(128);
+static DURATION: AtomicU64 = AtomicU64::new(u64::MAX);
+
+thread_local! {
+    // The budget left to the child being polled on this thread, if
+    // any.
+    static REMAINING: Cell<Option<Remaining>> = Cell::new(None);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The amount of work a child can do every time it is polled
+/// before being forced to yield, set using
+/// [`Config::with_budget`].
+///
+/// By default, a child yields after 128 operations (e.g. received
+/// messages) and isn't limited in time.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::time::Duration;
+/// #
+/// // Yields after 32 operations or 500µs, whichever comes first...
+/// let budget = Budget::new()
+///     .with_operations(32)
+///     .with_duration(Duration::from_micros(500));
+///
+/// let config = Config::new().with_budget(budget);
+/// ```
+///
+/// [`Config::with_budget`]: ../struct.Config.html#method.with_budget
+pub struct Budget {
+    operations: Option<u32>,
+    duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Remaining {
+    operations: Option<u32>,
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    /// Creates the default budget (128 operations per poll).
+    pub fn new() -> Self {
+        Budget::default()
+    }
+
+    /// Creates a budget which never forces the children to yield,
+    /// disabling cooperative scheduling.
+    pub fn unlimited() -> Self {
+        Budget {
+            operations: None,
+            duration: None,
+        }
+    }
+
+    /// Sets the amount of operations a child can do every time it
+    /// is polled.
+    ///
+    /// # Argument
+    ///
+    /// * `operations` - The amount of operations (at least 1).
+    pub fn with_operations(mut self, operations: u32) -> Self {
+        self.operations = Some(operations.max(1));
+        self
+    }
+
+    /// Sets how long a child can run every time it is polled (which
+    /// is only checked when it does an operation).
+    ///
+    /// # Argument
+    ///
+    /// * `duration` - How long the child can run.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Returns the amount of operations a child can do every time
+    /// it is polled, if it is limited.
+    pub fn operations(&self) -> Option<u32> {
+        self.operations
+    }
+
+    /// Returns how long a child can run every time it is polled, if
+    /// it is limited.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.operations.is_none() && self.duration.is_none()
+    }
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget {
+            operations: Some(128),
+            duration: None,
+        }
+    }
+}
+
+/// Yields to the executor once, letting the other tasks of the
+/// worker thread run before the current one is polled again.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| async move {
+///         for chunk in 0..1_000 {
+///             // Crunches the chunk...
+///             bastion::rt::yield_now().await;
+///         }
+///
+///         Ok(())
+///     })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub async fn yield_now() {
+    let mut yielded = false;
+    future::poll_fn(|ctx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            ctx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+/// Consumes one operation of the budget of the current child,
+/// yielding to the executor first if the budget is exhausted.
+///
+/// Outside of a child (or with an unlimited budget), this never
+/// yields.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| async move {
+///         for chunk in 0..1_000_000 {
+///             // Only yields once in a while...
+///             bastion::rt::consume_budget().await;
+///         }
+///
+///         Ok(())
+///     })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub async fn consume_budget() {
+    future::poll_fn(poll_proceed).await
+}
+
+pub(crate) fn set_budget(budget: Budget) {
+    // A child polled while the budget is being set might get the
+    // new amount of operations along with the old duration.
+    OPERATIONS.store(budget.operations.unwrap_or(0), Ordering::Relaxed);
+    let duration = budget.duration.map_or(u64::MAX, |duration| {
+        u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX - 1)
+    });
+    DURATION.store(duration, Ordering::Relaxed);
+}
+
+fn budget() -> Budget {
+    let operations = OPERATIONS.load(Ordering::Relaxed);
+    let duration = DURATION.load(Ordering::Relaxed);
+    Budget {
+        operations: Some(operations).filter(|operations| *operations > 0),
+        duration: Some(duration)
+            .filter(|duration| *duration != u64::MAX)
+            .map(Duration::from_nanos),
+    }
+}
+
+/// Runs `f` (which polls the future of a child) with a new budget,
+/// restoring the previous one (if any) afterwards.
+pub(crate) fn budgeted<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    with_budget(budget(), f)
+}
+
+// Runs `f` with the budget, as `budgeted` does with the one of the
+// children.
+fn with_budget<F, R>(budget: Budget, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    if budget.is_unlimited() {
+        return f();
+    }
+
+    let remaining = Remaining {
+        operations: budget.operations,
+        deadline: budget.duration.map(|duration| Instant::now() + duration),
+    };
+
+    // The previous budget is restored even if `f` panics.
+    struct Restore(Option<Remaining>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            REMAINING.with(|cell| cell.set(self.0));
+        }
+    }
+
+    let _restore = Restore(REMAINING.with(|cell| cell.replace(Some(remaining))));
+    f()
+}
+
+/// Consumes one operation of the current budget, returning
+/// `Poll::Pending` (after waking the task up) if it is exhausted.
+pub(crate) fn poll_proceed(ctx: &mut Context) -> Poll<()> {
+    REMAINING.with(|cell| {
+        let mut remaining = match cell.get() {
+            Some(remaining) => remaining,
+            None => return Poll::Ready(()),
+        };
+
+        let exhausted = remaining.operations == Some(0)
+            || remaining
+                .deadline
+                .map_or(false, |deadline| Instant::now() >= deadline);
+        if exhausted {
+            ctx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        if let Some(operations) = &mut remaining.operations {
+            *operations -= 1;
+        }
+        cell.set(Some(remaining));
+
+        Poll::Ready(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::task::noop_waker;
+
+    #[test]
+    fn yields_once_the_budget_is_exhausted() {
+        let waker = noop_waker();
+        let mut ctx = Context::from_waker(&waker);
+
+        let budget = Budget::new().with_operations(2);
+        with_budget(budget, || {
+            assert!(poll_proceed(&mut ctx).is_ready());
+            assert!(poll_proceed(&mut ctx).is_ready());
+            assert!(poll_proceed(&mut ctx).is_pending());
+        });
+
+        // The budget is renewed on every poll, and doesn't apply
+        // outside of them.
+        with_budget(budget, || assert!(poll_proceed(&mut ctx).is_ready()));
+        assert!(poll_proceed(&mut ctx).is_ready());
+
+        with_budget(Budget::unlimited(), || {
+            for _ in 0..1_000 {
+                assert!(poll_proceed(&mut ctx).is_ready());
+            }
+        });
+    }
+
+    #[test]
+    fn starts_with_the_default_budget() {
+        // No test sets the budget of the children, which is global.
+        assert_eq!(budget(), Budget::default());
+    }
+}
@@ -0,0 +1,191 @@
+//!
+//! Scopes let a child run concurrent sub-tasks which can't
+//! outlive it (see [`BastionContext::scope`]).
+//!
+//! The sub-tasks are spawned using the scope's [`Nursery`]. Once
+//! the scope's future completes, the scope waits for all of them
+//! to complete too; and if the scope is dropped before (e.g.
+//! because its child was stopped or restarted), all of them are
+//! cancelled.
+//!
+//! [`BastionContext::scope`]: ../context/struct.BastionContext.html#method.scope
+//! [`Nursery`]: struct.Nursery.html
+use crate::executor;
+use futures::channel::oneshot;
+use futures::future;
+use lightproc::proc_stack::ProcStack;
+use lightproc::recoverable_handle::RecoverableHandle;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tracing::trace;
+
+#[derive(Clone)]
+/// A handle allowing to spawn sub-tasks in the scope it was
+/// given by (see [`BastionContext::scope`]).
+///
+/// The handle can be cloned and moved into the sub-tasks, but
+/// the tasks spawned once the scope exited are cancelled right
+/// away.
+///
+/// [`BastionContext::scope`]: ../context/struct.BastionContext.html#method.scope
+pub struct Nursery {
+    tasks: Arc<Mutex<Tasks>>,
+}
+
+#[derive(Default)]
+struct Tasks {
+    handles: Vec<RecoverableHandle<()>>,
+    closed: bool,
+}
+
+/// A future resolving to the output of a sub-task spawned using
+/// [`Nursery::spawn`], or to `None` if it panicked or was
+/// cancelled.
+///
+/// Dropping it doesn't cancel the sub-task.
+///
+/// [`Nursery::spawn`]: struct.Nursery.html#method.spawn
+pub struct TaskHandle<T> {
+    receiver: oneshot::Receiver<T>,
+}
+
+impl Nursery {
+    fn new() -> Self {
+        Nursery {
+            tasks: Arc::default(),
+        }
+    }
+
+    /// Spawns a sub-task in the scope, which will be awaited when
+    /// the scope exits, or cancelled if the scope is dropped.
+    ///
+    /// This method returns a [`TaskHandle`] resolving to the
+    /// output of the sub-task.
+    ///
+    /// # Argument
+    ///
+    /// * `future` - The sub-task to spawn.
+    ///
+    /// [`TaskHandle`]: struct.TaskHandle.html
+    pub fn spawn<F, T>(&self, future: F) -> TaskHandle<T>
+    where
+        F: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        // FIXME: panics
+        let mut tasks = self.tasks.lock().unwrap();
+        if tasks.closed {
+            trace!("Nursery: Scope exited, cancelling sub-task.");
+            return TaskHandle { receiver };
+        }
+
+        let handle = executor::spawn_proc(
+            async move {
+                sender.send(future.await).ok();
+            },
+            ProcStack::default(),
+        );
+        tasks.handles.push(handle);
+
+        TaskHandle { receiver }
+    }
+
+    /// Cancels all the sub-tasks running in the scope.
+    pub fn cancel(&self) {
+        // FIXME: panics
+        let handles = std::mem::take(&mut self.tasks.lock().unwrap().handles);
+        trace!("Nursery: Cancelling {} sub-tasks.", handles.len());
+        for handle in handles {
+            handle.cancel();
+        }
+    }
+
+    /// Waits for all the sub-tasks of the scope (including the
+    /// ones spawned while waiting) and closes it.
+    async fn join(&self) {
+        future::poll_fn(|ctx| {
+            // The handles stay in the nursery while they are
+            // awaited, to be cancelled if the scope is dropped.
+            // FIXME: panics
+            let mut tasks = self.tasks.lock().unwrap();
+            let mut idx = 0;
+            while idx < tasks.handles.len() {
+                match Pin::new(&mut tasks.handles[idx]).poll(ctx) {
+                    Poll::Ready(_) => {
+                        tasks.handles.swap_remove(idx);
+                    }
+                    Poll::Pending => idx += 1,
+                }
+            }
+
+            if tasks.handles.is_empty() {
+                tasks.closed = true;
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    // Cancels the remaining sub-tasks and closes the scope.
+    fn close(&self) {
+        self.cancel();
+        // FIXME: panics
+        self.tasks.lock().unwrap().closed = true;
+    }
+}
+
+impl Debug for Nursery {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        // FIXME: panics
+        let tasks = self.tasks.lock().unwrap();
+        fmt.debug_struct("Nursery")
+            .field("tasks", &tasks.handles.len())
+            .field("closed", &tasks.closed)
+            .finish()
+    }
+}
+
+impl<T> Future for TaskHandle<T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver).poll(ctx).map(Result::ok)
+    }
+}
+
+impl<T> Debug for TaskHandle<T> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("TaskHandle").finish()
+    }
+}
+
+// Cancels the sub-tasks of a scope that was dropped before
+// exiting.
+struct Guard(Nursery);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+pub(crate) async fn scope<F, Fut, T>(f: F) -> T
+where
+    F: FnOnce(Nursery) -> Fut,
+    Fut: Future<Output = T>,
+{
+    let nursery = Nursery::new();
+    let guard = Guard(nursery.clone());
+
+    let output = f(nursery.clone()).await;
+    nursery.join().await;
+
+    drop(guard);
+    output
+}
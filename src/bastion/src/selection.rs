@@ -0,0 +1,196 @@
+//!
+//! Selections of the running children whose path matches a pattern,
+//! allowing to send messages to a dynamic subtree of the system
+//! without registering a dispatcher for every children group.
+//!
+//! A pattern is made of segments separated by `/`, each one matching
+//! an element of a child's path:
+//! - an identifier matches the element with this identifier.
+//! - the name of a children group (see [`Children::with_name`]) or
+//!     of a child (see [`Children::with_child_name`]) matches the
+//!     group or the child.
+//! - `*` matches any element.
+//! - `**` matches any number of elements, including none.
+//!
+//! Selections are created using [`Bastion::select`].
+//!
+//! [`Children::with_name`]: ../children/struct.Children.html#method.with_name
+//! [`Children::with_child_name`]: ../children/struct.Children.html#method.with_child_name
+//! [`Bastion::select`]: ../struct.Bastion.html#method.select
+use crate::child_ref::ChildRef;
+use crate::envelope::Envelope;
+use crate::message::{BastionMessage, Message};
+use crate::system;
+use crate::tree;
+use tracing::debug;
+
+#[derive(Debug, Clone)]
+/// The running children whose path matched a pattern when
+/// [`Bastion::select`] was called.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// Bastion::init();
+///
+/// Bastion::children(|children| {
+///     children.with_name("workers").with_redundancy(4)
+/// }).expect("Couldn't create the children group.");
+///
+/// Bastion::start();
+///
+/// // Once the children started...
+/// let workers: Selection = Bastion::select("/**/workers/*");
+/// workers.tell_everyone("reload").ok();
+/// #
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`Bastion::select`]: ../struct.Bastion.html#method.select
+pub struct Selection {
+    elems: Vec<ChildRef>,
+}
+
+impl Selection {
+    pub(crate) fn new(pattern: &str) -> Self {
+        let pattern = pattern
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>();
+
+        let elems = tree::running_children()
+            .into_iter()
+            .filter(|(child_ref, name)| {
+                let path = child_ref.path().iter().map(ToString::to_string);
+                let mut elems = path.map(|id| (id, None)).collect::<Vec<_>>();
+                let len = elems.len();
+                if len >= 2 {
+                    elems[len - 2].1 = Some(child_ref.name());
+                }
+                if let Some(elem) = elems.last_mut() {
+                    elem.1 = name.as_deref();
+                }
+
+                matches(&pattern, &elems)
+            })
+            .map(|(child_ref, _)| child_ref)
+            .collect();
+
+        Selection { elems }
+    }
+
+    /// Returns the selected children.
+    pub fn elems(&self) -> &[ChildRef] {
+        &self.elems
+    }
+
+    /// Returns the number of selected children.
+    pub fn len(&self) -> usize {
+        self.elems.len()
+    }
+
+    /// Returns whether no child was selected.
+    pub fn is_empty(&self) -> bool {
+        self.elems.is_empty()
+    }
+
+    /// Sends a message to every selected child, without waiting for
+    /// their answers.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)`
+    /// otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send.
+    pub fn tell_everyone<M: Message>(&self, msg: M) -> Result<(), M> {
+        debug!(
+            "Selection: Telling {} children: {:?}",
+            self.elems.len(),
+            msg
+        );
+        let msg = BastionMessage::broadcast(msg);
+        let env = Envelope::from_dead_letters(msg);
+        if system::refuses(&env) {
+            // FIXME: panics?
+            return Err(env.into_msg().unwrap());
+        }
+
+        for child in &self.elems {
+            if let Some(env) = env.try_clone() {
+                // The child might have stopped since.
+                child.send(env).ok();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns whether the path elements, given with their name if they
+/// have one, match the pattern's segments.
+fn matches(pattern: &[&str], elems: &[(String, Option<&str>)]) -> bool {
+    match pattern.split_first() {
+        None => elems.is_empty(),
+        Some((&"**", rest)) => (0..=elems.len()).any(|skip| matches(rest, &elems[skip..])),
+        Some((segment, rest)) => match elems.split_first() {
+            Some(((id, name), others)) => {
+                let matched = *segment == "*" || *segment == id.as_str() || Some(*segment) == *name;
+                matched && matches(rest, others)
+            }
+            None => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elems<'a>(elems: &[(&str, Option<&'a str>)]) -> Vec<(String, Option<&'a str>)> {
+        elems
+            .iter()
+            .map(|(id, name)| (id.to_string(), *name))
+            .collect()
+    }
+
+    #[test]
+    fn matches_ids_names_and_wildcards() {
+        let path = elems(&[("a", None), ("b", Some("workers")), ("c", Some("worker-0"))]);
+        assert!(matches(&["a", "b", "c"], &path));
+        assert!(matches(&["a", "workers", "worker-0"], &path));
+        assert!(matches(&["*", "workers", "*"], &path));
+        assert!(!matches(&["*", "workers"], &path));
+        assert!(!matches(&["a", "pipelines", "*"], &path));
+    }
+
+    #[test]
+    fn matches_any_number_of_elements() {
+        let path = elems(&[
+            ("a", None),
+            ("b", None),
+            ("c", Some("workers")),
+            ("d", None),
+        ]);
+        assert!(matches(&["**", "workers", "*"], &path));
+        assert!(matches(&["a", "**", "d"], &path));
+        assert!(matches(&["**"], &path));
+        assert!(!matches(&["**", "a"], &path));
+    }
+}
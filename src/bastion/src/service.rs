@@ -0,0 +1,164 @@
+//!
+//! The `tower` integration exposes a children group as a
+//! [`tower::Service`], and is available with the `tower` feature.
+//!
+//! A [`GroupService`] (created using [`ChildrenRef::into_service`])
+//! asks every request it is called with to an element of its
+//! children group and resolves to the element's answer, so that a
+//! group can be mounted as the handler of an `axum` or `hyper`
+//! server as-is.
+//!
+//! [`tower::Service`]: https://docs.rs/tower/0.4/tower/trait.Service.html
+//! [`GroupService`]: struct.GroupService.html
+//! [`ChildrenRef::into_service`]: ../children_ref/struct.ChildrenRef.html#method.into_service
+use crate::context::BastionId;
+use crate::errors::AskError;
+use crate::message::Message;
+use crate::tree;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::Service;
+use tracing::trace;
+
+/// A [`tower::Service`] asking its requests to the elements of a
+/// children group.
+///
+/// The requests are asked to the running elements of the group in
+/// turn, the same way the group's default dispatcher delivers its
+/// messages, and the service resolves to the answer of the element
+/// downcasted to `Res`. It fails with [`AskError::SendFailed`] when
+/// the group doesn't have any running element, and with
+/// [`AskError::Timeout`] when the element didn't answer on time.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use bastion::prelude::*;
+/// # use futures::future;
+/// # use tower::Service;
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// # Bastion::start();
+/// #
+/// let mut service = Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| async move {
+///         loop {
+///             msg! { ctx.recv().await?,
+///                 name: String =!> {
+///                     answer!(ctx, format!("Hello, {}!", name)).expect("Couldn't answer.");
+///                 };
+///                 _: _ => ();
+///             }
+///         }
+///     })
+/// })
+/// .expect("Couldn't create the children group.")
+/// .into_service::<String, String>();
+///
+/// # run!(async move {
+/// future::poll_fn(|cx| service.poll_ready(cx)).await.expect("Couldn't ask.");
+/// let greeting = service.call("world".to_string()).await;
+/// assert_eq!(greeting.ok().as_deref(), Some("Hello, world!"));
+/// # });
+/// # }
+/// ```
+///
+/// [`tower::Service`]: https://docs.rs/tower/0.4/tower/trait.Service.html
+/// [`AskError::SendFailed`]: ../errors/enum.AskError.html#variant.SendFailed
+/// [`AskError::Timeout`]: ../errors/enum.AskError.html#variant.Timeout
+pub struct GroupService<Req, Res> {
+    group: BastionId,
+    timeout: Duration,
+    next: usize,
+    _types: PhantomData<fn(Req) -> Res>,
+}
+
+impl<Req, Res> GroupService<Req, Res> {
+    pub(crate) fn new(group: BastionId) -> Self {
+        GroupService {
+            group,
+            timeout: Duration::from_secs(5),
+            next: 0,
+            _types: PhantomData,
+        }
+    }
+
+    /// Sets how long an element has to answer a request before the
+    /// call fails with [`AskError::Timeout`].
+    ///
+    /// # Argument
+    ///
+    /// * `timeout` - How long to wait for an answer.
+    ///
+    /// [`AskError::Timeout`]: ../errors/enum.AskError.html#variant.Timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns the identifier of the children group the requests
+    /// are asked to.
+    pub fn group(&self) -> &BastionId {
+        &self.group
+    }
+
+    /// Returns how long an element has to answer a request.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl<Req, Res> Service<Req> for GroupService<Req, Res>
+where
+    Req: Message,
+    Res: Message,
+{
+    type Response = Res;
+    type Error = AskError;
+    type Future = Pin<Box<dyn Future<Output = Result<Res, AskError>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), AskError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        // The elements are looked up every time because they get a
+        // new `ChildRef` once restarted.
+        let elems = tree::running_elems(&self.group);
+        if elems.is_empty() {
+            return Box::pin(async { Err(AskError::SendFailed) });
+        }
+
+        let elem = elems[self.next % elems.len()].clone();
+        self.next = self.next.wrapping_add(1);
+
+        trace!("GroupService({}): Asking {}.", self.group, elem.path());
+        let timeout = self.timeout;
+        Box::pin(async move { elem.ask_timeout(req, timeout).await })
+    }
+}
+
+impl<Req, Res> Clone for GroupService<Req, Res> {
+    fn clone(&self) -> Self {
+        GroupService {
+            group: self.group.clone(),
+            timeout: self.timeout,
+            next: self.next,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<Req, Res> Debug for GroupService<Req, Res> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("GroupService")
+            .field("group", &self.group)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
@@ -0,0 +1,591 @@
+//!
+//! Loads the settings of `Config::from_file` and `Config::from_env`.
+//!
+//! The settings of a file (e.g. `bastion.toml`) follow this
+//! structure, where every key is optional:
+//!
+//! ```toml
+//! hide_backtraces = false
+//! log_level = "info" # or "error", "warn", "debug", "trace"
+//!
+//! [executor]
+//! threads = 8
+//! budget_operations = 128
+//!
+//! [blocking_pool]
+//! min_threads = 1
+//! max_threads = 16
+//! keep_alive_ms = 30000
+//! queue_limit = 256
+//!
+//! [mailbox]
+//! capacity = 1024
+//! overflow = "drop_oldest" # or "drop_newest", "backpressure", "fail"
+//!
+//! [restart_strategies.workers] # The name of a children group.
+//! policy = "tries" # or "always", "never"
+//! max_restarts = 5
+//! backoff = "exponential" # or "immediate", "linear"
+//! base_ms = 100 # or `timeout_ms` with "linear"
+//! max_ms = 10000
+//! jitter = 0.1
+//! reset_after_ms = 60000
+//!
+//! [groups.workers] # The name of a children group.
+//! mailbox_capacity = 256
+//! rate_limit = 100 # Messages per second.
+//! rate_limit_burst = 20
+//! min_elements = 2 # Requires the `scaling` feature.
+//! max_elements = 16
+//!
+//! [remote] # Requires the `remote` feature.
+//! bind = "0.0.0.0:4222"
+//! handshake_timeout_ms = 5000
+//!
+//! [cluster] # Requires the `cluster` feature.
+//! seeds = ["10.0.0.2:4222", "10.0.0.3:4222"]
+//! advertised_addr = "10.0.0.1:4222"
+//! gossip_interval_ms = 1000
+//! phi_threshold = 8.0
+//! acceptable_pause_ms = 3000
+//!
+//! [metrics] # Requires the `metrics` feature.
+//! enabled = true
+//! ```
+//!
+//! Every setting except the restart strategies and the settings of
+//! the groups can then be overridden by an environment variable,
+//! named after its table and key (e.g. `BASTION_MAILBOX_CAPACITY` or
+//! `BASTION_CLUSTER_SEEDS`, whose addresses are separated by commas).
+//!
+//! The groups' settings and the log level can be changed while the
+//! system is running (see `Bastion::reload_config`).
+use crate::blocking_pool::BlockingPoolConfig;
+#[cfg(feature = "cluster")]
+use crate::cluster::ClusterConfig;
+use crate::config::Config;
+use crate::errors::ConfigError;
+use crate::mailbox::{MailboxConfig, OverflowStrategy};
+use crate::rate_limit::RateLimit;
+#[cfg(feature = "remote")]
+use crate::remote::RemotingConfig;
+#[cfg(feature = "scaling")]
+use crate::resizer::UpperBound;
+use crate::rt::Budget;
+use crate::supervisor::{ActorRestartStrategy, RestartPolicy, RestartStrategy};
+use fxhash::FxHashMap;
+use serde::Deserialize;
+#[cfg(feature = "remote")]
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+#[cfg(not(all(feature = "cluster", feature = "metrics", feature = "scaling")))]
+use tracing::warn;
+use tracing::Level;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Settings {
+    hide_backtraces: Option<bool>,
+    log_level: Option<String>,
+    executor: ExecutorSettings,
+    blocking_pool: BlockingPoolSettings,
+    mailbox: MailboxSettings,
+    restart_strategies: FxHashMap<String, RestartSettings>,
+    groups: FxHashMap<String, GroupSettings>,
+    remote: RemoteSettings,
+    cluster: ClusterSettings,
+    metrics: MetricsSettings,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ExecutorSettings {
+    threads: Option<usize>,
+    budget_operations: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BlockingPoolSettings {
+    min_threads: Option<usize>,
+    max_threads: Option<usize>,
+    keep_alive_ms: Option<u64>,
+    queue_limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MailboxSettings {
+    capacity: Option<usize>,
+    overflow: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RestartSettings {
+    policy: Option<String>,
+    max_restarts: Option<usize>,
+    backoff: Option<String>,
+    timeout_ms: Option<u64>,
+    base_ms: Option<u64>,
+    max_ms: Option<u64>,
+    jitter: Option<f64>,
+    reset_after_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GroupSettings {
+    mailbox_capacity: Option<usize>,
+    rate_limit: Option<u32>,
+    rate_limit_burst: Option<u32>,
+    min_elements: Option<u64>,
+    max_elements: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[cfg_attr(not(feature = "remote"), allow(dead_code))]
+struct RemoteSettings {
+    bind: Option<String>,
+    handshake_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[cfg_attr(not(feature = "cluster"), allow(dead_code))]
+struct ClusterSettings {
+    seeds: Option<Vec<String>>,
+    advertised_addr: Option<String>,
+    gossip_interval_ms: Option<u64>,
+    phi_threshold: Option<f64>,
+    acceptable_pause_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MetricsSettings {
+    enabled: Option<bool>,
+}
+
+impl Settings {
+    #[cfg(feature = "config-file")]
+    pub(crate) fn from_toml(toml: &str) -> Result<Self, ConfigError> {
+        toml::from_str(toml).map_err(|err| ConfigError::Parse(err.to_string()))
+    }
+
+    /// Overrides the settings with the environment variables
+    /// returned by `var`.
+    pub(crate) fn override_with<F>(&mut self, var: F) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        set_if_some(
+            &mut self.hide_backtraces,
+            parse_var(&var, "BASTION_HIDE_BACKTRACES")?,
+        );
+        set_if_some(&mut self.log_level, var("BASTION_LOG_LEVEL"));
+        set_if_some(
+            &mut self.executor.threads,
+            parse_var(&var, "BASTION_EXECUTOR_THREADS")?,
+        );
+        set_if_some(
+            &mut self.executor.budget_operations,
+            parse_var(&var, "BASTION_EXECUTOR_BUDGET_OPERATIONS")?,
+        );
+
+        let pool = &mut self.blocking_pool;
+        set_if_some(
+            &mut pool.min_threads,
+            parse_var(&var, "BASTION_BLOCKING_POOL_MIN_THREADS")?,
+        );
+        set_if_some(
+            &mut pool.max_threads,
+            parse_var(&var, "BASTION_BLOCKING_POOL_MAX_THREADS")?,
+        );
+        set_if_some(
+            &mut pool.keep_alive_ms,
+            parse_var(&var, "BASTION_BLOCKING_POOL_KEEP_ALIVE_MS")?,
+        );
+        set_if_some(
+            &mut pool.queue_limit,
+            parse_var(&var, "BASTION_BLOCKING_POOL_QUEUE_LIMIT")?,
+        );
+
+        set_if_some(
+            &mut self.mailbox.capacity,
+            parse_var(&var, "BASTION_MAILBOX_CAPACITY")?,
+        );
+        set_if_some(&mut self.mailbox.overflow, var("BASTION_MAILBOX_OVERFLOW"));
+
+        set_if_some(&mut self.remote.bind, var("BASTION_REMOTE_BIND"));
+        set_if_some(
+            &mut self.remote.handshake_timeout_ms,
+            parse_var(&var, "BASTION_REMOTE_HANDSHAKE_TIMEOUT_MS")?,
+        );
+
+        let cluster = &mut self.cluster;
+        let seeds = var("BASTION_CLUSTER_SEEDS").map(|seeds| {
+            seeds
+                .split(',')
+                .map(str::trim)
+                .filter(|seed| !seed.is_empty())
+                .map(String::from)
+                .collect()
+        });
+        set_if_some(&mut cluster.seeds, seeds);
+        set_if_some(
+            &mut cluster.advertised_addr,
+            var("BASTION_CLUSTER_ADVERTISED_ADDR"),
+        );
+        set_if_some(
+            &mut cluster.gossip_interval_ms,
+            parse_var(&var, "BASTION_CLUSTER_GOSSIP_INTERVAL_MS")?,
+        );
+        set_if_some(
+            &mut cluster.phi_threshold,
+            parse_var(&var, "BASTION_CLUSTER_PHI_THRESHOLD")?,
+        );
+        set_if_some(
+            &mut cluster.acceptable_pause_ms,
+            parse_var(&var, "BASTION_CLUSTER_ACCEPTABLE_PAUSE_MS")?,
+        );
+
+        set_if_some(
+            &mut self.metrics.enabled,
+            parse_var(&var, "BASTION_METRICS_ENABLED")?,
+        );
+
+        Ok(())
+    }
+
+    /// Applies the settings to `config`.
+    pub(crate) fn apply(self, mut config: Config) -> Result<Config, ConfigError> {
+        match self.hide_backtraces {
+            Some(true) => config = config.hide_backtraces(),
+            Some(false) => config = config.show_backtraces(),
+            None => (),
+        }
+
+        if let Some(level) = self.log_level {
+            config = config.with_log_level(parse::<Level>("log_level", &level)?);
+        }
+
+        if let Some(threads) = self.executor.threads {
+            config = config.with_executor_threads(threads);
+        }
+        if let Some(operations) = self.executor.budget_operations {
+            config = config.with_budget(Budget::new().with_operations(operations));
+        }
+
+        let pool = self.blocking_pool;
+        if pool.min_threads.is_some()
+            || pool.max_threads.is_some()
+            || pool.keep_alive_ms.is_some()
+            || pool.queue_limit.is_some()
+        {
+            let mut blocking_pool = BlockingPoolConfig::new();
+            if let Some(min_threads) = pool.min_threads {
+                blocking_pool = blocking_pool.with_min_threads(min_threads);
+            }
+            if let Some(max_threads) = pool.max_threads {
+                blocking_pool = blocking_pool.with_max_threads(max_threads);
+            }
+            if let Some(keep_alive) = pool.keep_alive_ms {
+                blocking_pool = blocking_pool.with_keep_alive(Duration::from_millis(keep_alive));
+            }
+            if let Some(queue_limit) = pool.queue_limit {
+                blocking_pool = blocking_pool.with_queue_limit(queue_limit);
+            }
+
+            config = config.with_blocking_pool(blocking_pool);
+        }
+
+        if let Some(mailbox) = self.mailbox.apply()? {
+            config = config.with_default_mailbox(mailbox);
+        }
+
+        for (name, restart) in self.restart_strategies {
+            let strategy = restart.apply(&name)?;
+            config = config.with_restart_strategy(name, strategy);
+        }
+
+        for (name, group) in self.groups {
+            config = group.apply(&name, config)?;
+        }
+
+        config = self.remote.apply(config)?;
+        config = self.cluster.apply(config)?;
+
+        #[cfg(feature = "metrics")]
+        if let Some(enabled) = self.metrics.enabled {
+            config = config.with_metrics(enabled);
+        }
+        #[cfg(not(feature = "metrics"))]
+        if self.metrics.enabled.is_some() {
+            warn!("Config: Ignoring the metrics settings, the `metrics` feature is disabled.");
+        }
+
+        Ok(config)
+    }
+}
+
+impl MailboxSettings {
+    fn apply(self) -> Result<Option<MailboxConfig>, ConfigError> {
+        let mut mailbox = match self.capacity {
+            Some(capacity) => MailboxConfig::bounded(capacity),
+            None if self.overflow.is_some() => MailboxConfig::unbounded(),
+            None => return Ok(None),
+        };
+
+        if let Some(overflow) = self.overflow {
+            let overflow = match overflow.as_str() {
+                "drop_newest" => OverflowStrategy::DropNewest,
+                "drop_oldest" => OverflowStrategy::DropOldest,
+                "backpressure" => OverflowStrategy::Backpressure,
+                "fail" => OverflowStrategy::Fail,
+                _ => return Err(invalid("mailbox.overflow", &overflow)),
+            };
+
+            mailbox = mailbox.with_overflow(overflow);
+        }
+
+        Ok(Some(mailbox))
+    }
+}
+
+impl RestartSettings {
+    fn apply(self, name: &str) -> Result<RestartStrategy, ConfigError> {
+        let key = |key: &str| format!("restart_strategies.{}.{}", name, key);
+        let millis = |millis_key: &str, value: Option<u64>| {
+            value
+                .map(Duration::from_millis)
+                .ok_or_else(|| ConfigError::Missing(key(millis_key)))
+        };
+
+        let policy = match (self.policy.as_deref(), self.max_restarts) {
+            (None, None) | (Some("always"), None) => RestartPolicy::Always,
+            (Some("never"), None) => RestartPolicy::Never,
+            (None, Some(tries)) | (Some("tries"), Some(tries)) => RestartPolicy::Tries(tries),
+            (Some("tries"), None) => return Err(ConfigError::Missing(key("max_restarts"))),
+            (Some(policy), _) => return Err(invalid(&key("policy"), policy)),
+        };
+
+        let backoff = match self.backoff.as_deref() {
+            None | Some("immediate") => ActorRestartStrategy::Immediate,
+            Some("linear") => ActorRestartStrategy::LinearBackOff {
+                timeout: millis("timeout_ms", self.timeout_ms)?,
+            },
+            Some("exponential") => ActorRestartStrategy::ExponentialBackOff {
+                base: millis("base_ms", self.base_ms)?,
+                max: millis("max_ms", self.max_ms)?,
+                jitter: self.jitter.unwrap_or(0.0),
+            },
+            Some(backoff) => return Err(invalid(&key("backoff"), backoff)),
+        };
+
+        let mut strategy = RestartStrategy::new(policy, backoff);
+        if let Some(reset_after) = self.reset_after_ms {
+            strategy = strategy.with_reset_after(Duration::from_millis(reset_after));
+        }
+
+        Ok(strategy)
+    }
+}
+
+impl GroupSettings {
+    fn apply(self, name: &str, mut config: Config) -> Result<Config, ConfigError> {
+        if let Some(capacity) = self.mailbox_capacity {
+            config = config.with_mailbox_capacity(name, capacity);
+        }
+
+        match (self.rate_limit, self.rate_limit_burst) {
+            (Some(rate), burst) => {
+                let mut rate_limit = RateLimit::per_second(rate);
+                if let Some(burst) = burst {
+                    rate_limit = rate_limit.with_burst(burst);
+                }
+
+                config = config.with_rate_limit(name, rate_limit);
+            }
+            (None, Some(_)) => {
+                return Err(ConfigError::Missing(format!("groups.{}.rate_limit", name)));
+            }
+            (None, None) => (),
+        }
+
+        #[cfg(feature = "scaling")]
+        if self.min_elements.is_some() || self.max_elements.is_some() {
+            let upper_bound = match self.max_elements {
+                Some(max) => UpperBound::Limit(max),
+                None => UpperBound::Unlimited,
+            };
+
+            let lower_bound = self.min_elements.unwrap_or(1);
+            config = config.with_resizer_bounds(name, lower_bound, upper_bound);
+        }
+        #[cfg(not(feature = "scaling"))]
+        if self.min_elements.is_some() || self.max_elements.is_some() {
+            warn!("Config: Ignoring the resizer settings, the `scaling` feature is disabled.");
+        }
+
+        Ok(config)
+    }
+}
+
+impl RemoteSettings {
+    #[cfg(feature = "remote")]
+    fn apply(self, config: Config) -> Result<Config, ConfigError> {
+        let bind = match self.bind {
+            Some(bind) => bind,
+            None => return Ok(config),
+        };
+
+        let addr = parse::<SocketAddr>("remote.bind", &bind)?;
+        let mut remoting = RemotingConfig::default();
+        if let Some(timeout) = self.handshake_timeout_ms {
+            remoting = remoting.with_handshake_timeout(Duration::from_millis(timeout));
+        }
+
+        Ok(config.with_remoting(addr, remoting))
+    }
+
+    #[cfg(not(feature = "remote"))]
+    fn apply(self, config: Config) -> Result<Config, ConfigError> {
+        if self.bind.is_some() {
+            warn!("Config: Ignoring the remote settings, the `remote` feature is disabled.");
+        }
+
+        Ok(config)
+    }
+}
+
+impl ClusterSettings {
+    #[cfg(feature = "cluster")]
+    fn apply(self, config: Config) -> Result<Config, ConfigError> {
+        let seeds = match self.seeds {
+            Some(seeds) => seeds,
+            None => return Ok(config),
+        };
+
+        let mut cluster = ClusterConfig::default();
+        for seed in seeds {
+            cluster = cluster.with_seed(parse("cluster.seeds", &seed)?);
+        }
+        if let Some(addr) = self.advertised_addr {
+            cluster = cluster.with_advertised_addr(parse("cluster.advertised_addr", &addr)?);
+        }
+        if let Some(interval) = self.gossip_interval_ms {
+            cluster = cluster.with_gossip_interval(Duration::from_millis(interval));
+        }
+        if let Some(threshold) = self.phi_threshold {
+            cluster = cluster.with_phi_threshold(threshold);
+        }
+        if let Some(pause) = self.acceptable_pause_ms {
+            cluster = cluster.with_acceptable_pause(Duration::from_millis(pause));
+        }
+
+        Ok(config.with_cluster(cluster))
+    }
+
+    #[cfg(not(feature = "cluster"))]
+    fn apply(self, config: Config) -> Result<Config, ConfigError> {
+        if self.seeds.is_some() {
+            warn!("Config: Ignoring the cluster settings, the `cluster` feature is disabled.");
+        }
+
+        Ok(config)
+    }
+}
+
+fn set_if_some<T>(setting: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *setting = value;
+    }
+}
+
+fn parse_var<T, F>(var: &F, key: &str) -> Result<Option<T>, ConfigError>
+where
+    T: FromStr,
+    F: Fn(&str) -> Option<String>,
+{
+    var(key).map(|value| parse(key, &value)).transpose()
+}
+
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, ConfigError> {
+    value.trim().parse().map_err(|_| invalid(key, value))
+}
+
+fn invalid(key: &str, value: &str) -> ConfigError {
+    ConfigError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_the_settings_with_the_environment() {
+        let mut settings = Settings::default();
+        settings.mailbox.capacity = Some(16);
+        settings.executor.threads = Some(2);
+
+        settings
+            .override_with(|key| match key {
+                "BASTION_MAILBOX_CAPACITY" => Some("64".to_string()),
+                "BASTION_CLUSTER_SEEDS" => Some("10.0.0.2:4222, 10.0.0.3:4222".to_string()),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(settings.mailbox.capacity, Some(64));
+        assert_eq!(settings.executor.threads, Some(2));
+        assert_eq!(
+            settings.cluster.seeds,
+            Some(vec![
+                "10.0.0.2:4222".to_string(),
+                "10.0.0.3:4222".to_string()
+            ])
+        );
+
+        let err = settings
+            .override_with(|key| match key {
+                "BASTION_EXECUTOR_THREADS" => Some("many".to_string()),
+                _ => None,
+            })
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { .. }));
+    }
+
+    #[test]
+    fn builds_the_restart_strategies() {
+        let restart = RestartSettings {
+            max_restarts: Some(3),
+            backoff: Some("linear".to_string()),
+            timeout_ms: Some(100),
+            ..RestartSettings::default()
+        };
+
+        let strategy = restart.apply("workers").unwrap();
+        assert_eq!(strategy.restart_policy(), RestartPolicy::Tries(3));
+        assert_eq!(
+            strategy.strategy(),
+            ActorRestartStrategy::LinearBackOff {
+                timeout: Duration::from_millis(100)
+            }
+        );
+
+        let restart = RestartSettings {
+            backoff: Some("exponential".to_string()),
+            ..RestartSettings::default()
+        };
+        assert!(matches!(
+            restart.apply("workers"),
+            Err(ConfigError::Missing(_))
+        ));
+    }
+}
@@ -0,0 +1,556 @@
+//!
+//! Sharding distributes entities (children identified by an id, like
+//! a user or an order) across the nodes of a cluster, and is
+//! available with the `sharding` feature.
+//!
+//! The entities of an [`Entity`] type are split into a fixed number
+//! of shards, and each shard is owned by one of the members of the
+//! cluster that are up. Messages sent using a [`ShardRegion`] are
+//! routed to the node owning the shard of their entity id (as
+//! returned by the region's extractor), which spawns the entity
+//! child when it receives its first message.
+//!
+//! The owners of the shards are computed from the membership of the
+//! cluster using rendezvous hashing, so that every node agrees on
+//! them without coordination and that only the shards of the nodes
+//! joining or leaving move. When a shard moves, its entities are
+//! stopped on the previous owner and spawned on the new one with
+//! their next message.
+//!
+//! Entities that didn't receive any message for the passivation
+//! timeout of the region are stopped, to free their resources.
+//!
+//! [`Entity`]: trait.Entity.html
+//! [`ShardRegion`]: struct.ShardRegion.html
+use crate::children_ref::ChildrenRef;
+use crate::cluster::{Cluster, MemberEvent, MemberStatus};
+use crate::codec::RemoteMessage;
+use crate::context::BastionContext;
+use crate::errors::ShardingError;
+use crate::executor;
+use crate::remote::{self, NodeId};
+use crate::supervisor::SupervisorRef;
+use crate::{msg, Bastion};
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+use std::any::{type_name, Any};
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, trace};
+
+lazy_static! {
+    // The regions initialized on this node, by entity type.
+    static ref REGIONS: Mutex<FxHashMap<&'static str, Box<dyn Any + Send>>> =
+        Mutex::new(FxHashMap::default());
+}
+
+/// A child that is spawned on demand by a [`ShardRegion`], on the
+/// node owning the shard of its id.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct Deposit {
+///     account: String,
+///     amount: u64,
+/// }
+///
+/// struct Account {
+///     balance: u64,
+/// }
+///
+/// impl Entity for Account {
+///     type Message = Deposit;
+///
+///     fn new(_id: &str) -> Self {
+///         Account { balance: 0 }
+///     }
+///
+///     fn handle(&mut self, msg: Deposit) {
+///         self.balance += msg.amount;
+///     }
+/// }
+/// ```
+///
+/// [`ShardRegion`]: struct.ShardRegion.html
+pub trait Entity: Send + 'static {
+    /// The type of the messages sent to the entities, which are
+    /// sent to other nodes when they own the entities' shard.
+    type Message: RemoteMessage;
+
+    /// Creates the entity with the given id, when it receives its
+    /// first message on this node.
+    fn new(id: &str) -> Self;
+
+    /// Handles a message sent to the entity.
+    fn handle(&mut self, msg: Self::Message);
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A `struct` allowing to initialize the shard regions of entity
+/// types.
+pub struct Sharding {
+    _private: (),
+}
+
+#[derive(Debug, Clone)]
+/// The configuration of a shard region, as given to
+/// [`Sharding::init_with`].
+///
+/// [`Sharding::init_with`]: struct.Sharding.html#method.init_with
+pub struct ShardingConfig {
+    passivation_timeout: Duration,
+}
+
+/// A handle to the shard region of an [`Entity`] type on this node,
+/// which allows to send messages to the entities wherever they are.
+///
+/// [`Entity`]: trait.Entity.html
+pub struct ShardRegion<E: Entity> {
+    region: Arc<Region>,
+    extractor: Arc<dyn Fn(&E::Message) -> String + Send + Sync>,
+    _entity: PhantomData<fn() -> E>,
+}
+
+struct Region {
+    type_name: &'static str,
+    num_shards: u32,
+    passivation_timeout: Duration,
+    // The supervisor of the entities' children groups.
+    supervisor: SupervisorRef,
+    entities: Mutex<FxHashMap<String, LocalEntity>>,
+}
+
+struct LocalEntity {
+    shard: u32,
+    children: ChildrenRef,
+    last_used: Instant,
+}
+
+impl Sharding {
+    /// Initializes the shard region of an entity type on this node,
+    /// with the default configuration. This needs to be done on
+    /// every node of the cluster, preferably after joining it.
+    ///
+    /// This method returns the region if it succeeded, or
+    /// `Err(error)` otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `num_shards` - The number of shards the entities are split
+    ///     into, which must be the same on every node. A good
+    ///     number is ten times the maximum number of nodes.
+    /// * `extractor` - The function returning the id of the entity
+    ///     a message is sent to.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use serde::{Deserialize, Serialize};
+    /// #
+    /// # #[derive(Debug, Serialize, Deserialize)]
+    /// # struct Deposit {
+    /// #     account: String,
+    /// #     amount: u64,
+    /// # }
+    /// #
+    /// # struct Account {
+    /// #     balance: u64,
+    /// # }
+    /// #
+    /// # impl Entity for Account {
+    /// #     type Message = Deposit;
+    /// #
+    /// #     fn new(_id: &str) -> Self {
+    /// #         Account { balance: 0 }
+    /// #     }
+    /// #
+    /// #     fn handle(&mut self, msg: Deposit) {
+    /// #         self.balance += msg.amount;
+    /// #     }
+    /// # }
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    /// Bastion::start();
+    ///
+    /// let accounts = Sharding::init::<Account>(100, |deposit: &Deposit| {
+    ///     deposit.account.clone()
+    /// })
+    /// .expect("Couldn't initialize the shard region.");
+    ///
+    /// let deposit = Deposit {
+    ///     account: "alice".to_string(),
+    ///     amount: 42,
+    /// };
+    /// accounts.tell(deposit).expect("Couldn't send the message.");
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    pub fn init<E: Entity>(
+        num_shards: u32,
+        extractor: impl Fn(&E::Message) -> String + Send + Sync + 'static,
+    ) -> Result<ShardRegion<E>, ShardingError> {
+        Sharding::init_with::<E>(num_shards, extractor, ShardingConfig::default())
+    }
+
+    /// Initializes the shard region of an entity type on this node,
+    /// like [`Sharding::init`] does, with the given configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `num_shards` - The number of shards the entities are split
+    ///     into, which must be the same on every node.
+    /// * `extractor` - The function returning the id of the entity
+    ///     a message is sent to.
+    /// * `config` - The configuration of the region.
+    ///
+    /// [`Sharding::init`]: #method.init
+    pub fn init_with<E: Entity>(
+        num_shards: u32,
+        extractor: impl Fn(&E::Message) -> String + Send + Sync + 'static,
+        config: ShardingConfig,
+    ) -> Result<ShardRegion<E>, ShardingError> {
+        let mut regions = REGIONS.lock().unwrap();
+        let type_name = type_name::<E>();
+        if regions.contains_key(type_name) {
+            return Err(ShardingError::AlreadyInitialized);
+        }
+
+        debug!(
+            "Sharding({}): Initializing {} shards.",
+            type_name, num_shards
+        );
+        remote::register_message::<E::Message>();
+
+        let supervisor = Bastion::supervisor(|sp| sp).map_err(|_| ShardingError::SpawnFailed)?;
+        let region = Arc::new(Region {
+            type_name,
+            num_shards: num_shards.max(1),
+            passivation_timeout: config.passivation_timeout,
+            supervisor,
+            entities: Mutex::new(FxHashMap::default()),
+        });
+
+        let shard_region = ShardRegion {
+            region: region.clone(),
+            extractor: Arc::new(extractor),
+            _entity: PhantomData,
+        };
+
+        // The router receives the messages sent by the other nodes
+        // and the membership changes of the cluster.
+        let routed = shard_region.clone();
+        let router = Bastion::supervisor(|sp| {
+            sp.children(|children| {
+                children.with_exec(move |ctx: BastionContext| {
+                    let routed = routed.clone();
+                    async move {
+                        let name = router_name(routed.region.type_name);
+                        remote::register_named(name, ctx.current());
+                        loop {
+                            msg! { ctx.recv().await?,
+                                msg: E::Message => {
+                                    let id = (routed.extractor)(&msg);
+                                    routed.deliver(id, msg).ok();
+                                };
+                                _event: MemberEvent => routed.region.rebalance();
+                                _: _ => ();
+                            }
+                        }
+                    }
+                })
+            })
+        })
+        .map_err(|_| ShardingError::SpawnFailed)?;
+
+        if Cluster::local_member().is_some() {
+            Cluster::subscribe(&router);
+        }
+
+        // Also rebalances the shards in case a membership change
+        // was missed.
+        let interval = (region.passivation_timeout / 2).max(Duration::from_millis(100));
+        let checked = region.clone();
+        executor::schedule_interval(interval, move || {
+            checked.passivate();
+            checked.rebalance();
+            true
+        });
+
+        regions.insert(type_name, Box::new(shard_region.clone()));
+        Ok(shard_region)
+    }
+
+    /// Returns the shard region of an entity type, if it was
+    /// initialized on this node.
+    pub fn region<E: Entity>() -> Option<ShardRegion<E>> {
+        let regions = REGIONS.lock().unwrap();
+        regions
+            .get(type_name::<E>())
+            .and_then(|region| region.downcast_ref::<ShardRegion<E>>())
+            .cloned()
+    }
+}
+
+impl ShardingConfig {
+    /// Sets the time after which an entity that didn't receive any
+    /// message is stopped. By default, it is two minutes.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The passivation timeout.
+    pub fn with_passivation_timeout(mut self, timeout: Duration) -> Self {
+        self.passivation_timeout = timeout;
+        self
+    }
+
+    /// Returns the time after which an idle entity is stopped.
+    pub fn passivation_timeout(&self) -> Duration {
+        self.passivation_timeout
+    }
+}
+
+impl Default for ShardingConfig {
+    fn default() -> Self {
+        ShardingConfig {
+            passivation_timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+impl<E: Entity> ShardRegion<E> {
+    /// Sends a message to the entity whose id is returned by the
+    /// region's extractor, on the node owning its shard.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)` if
+    /// the owner of the shard couldn't be reached or the entity
+    /// couldn't be spawned.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send.
+    pub fn tell(&self, msg: E::Message) -> Result<(), E::Message> {
+        let id = (self.extractor)(&msg);
+        let shard = shard_of(&id, self.region.num_shards);
+        match owner(self.region.type_name, shard) {
+            None => self.deliver(id, msg),
+            Some(owner) => {
+                trace!(
+                    "Sharding({}): Routing message to {}: {:?}",
+                    self.region.type_name,
+                    owner,
+                    msg
+                );
+                match remote::node(owner) {
+                    Some(node) => node
+                        .child_ref(router_name(self.region.type_name))
+                        .tell_anonymously(msg),
+                    None => Err(msg),
+                }
+            }
+        }
+    }
+
+    /// Returns the number of shards of the region.
+    pub fn num_shards(&self) -> u32 {
+        self.region.num_shards
+    }
+
+    /// Returns the shard of the entity id.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the entity.
+    pub fn shard_of(&self, id: &str) -> u32 {
+        shard_of(id, self.region.num_shards)
+    }
+
+    /// Returns the ids of the entities running on this node.
+    pub fn local_entities(&self) -> Vec<String> {
+        let entities = self.region.entities.lock().unwrap();
+        entities.keys().cloned().collect()
+    }
+
+    /// Delivers the message to the entity on this node, spawning it
+    /// if needed.
+    fn deliver(&self, id: String, msg: E::Message) -> Result<(), E::Message> {
+        let mut entities = self.region.entities.lock().unwrap();
+        if !entities.contains_key(&id) {
+            let children = match self.region.spawn::<E>(&id) {
+                Ok(children) => children,
+                Err(()) => return Err(msg),
+            };
+
+            let entity = LocalEntity {
+                shard: shard_of(&id, self.region.num_shards),
+                children,
+                last_used: Instant::now(),
+            };
+            entities.insert(id.clone(), entity);
+        }
+
+        let entity = entities.get_mut(&id).unwrap();
+        entity.last_used = Instant::now();
+        entity.children.elems()[0].tell_anonymously(msg)
+    }
+}
+
+impl<E: Entity> Clone for ShardRegion<E> {
+    fn clone(&self) -> Self {
+        ShardRegion {
+            region: self.region.clone(),
+            extractor: self.extractor.clone(),
+            _entity: PhantomData,
+        }
+    }
+}
+
+impl<E: Entity> Debug for ShardRegion<E> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("ShardRegion")
+            .field("type_name", &self.region.type_name)
+            .field("num_shards", &self.region.num_shards)
+            .finish()
+    }
+}
+
+impl Region {
+    fn spawn<E: Entity>(&self, id: &str) -> Result<ChildrenRef, ()> {
+        debug!("Sharding({}): Spawning entity: {}", self.type_name, id);
+        let name = format!("{}/{}", self.type_name, id);
+        let id = id.to_string();
+        self.supervisor.children(|children| {
+            children
+                .with_name(name)
+                .with_exec(move |ctx: BastionContext| {
+                    let id = id.clone();
+                    async move {
+                        let mut entity = E::new(&id);
+                        loop {
+                            msg! { ctx.recv().await?,
+                                msg: E::Message => entity.handle(msg);
+                                _: _ => ();
+                            }
+                        }
+                    }
+                })
+        })
+    }
+
+    /// Stops the entities that didn't receive any message for the
+    /// passivation timeout.
+    fn passivate(&self) {
+        let now = Instant::now();
+        let mut entities = self.entities.lock().unwrap();
+        entities.retain(|id, entity| {
+            if now.duration_since(entity.last_used) < self.passivation_timeout {
+                return true;
+            }
+
+            debug!("Sharding({}): Passivating entity: {}", self.type_name, id);
+            entity.children.stop().ok();
+            false
+        });
+    }
+
+    /// Stops the entities whose shard is now owned by another node.
+    fn rebalance(&self) {
+        let mut entities = self.entities.lock().unwrap();
+        entities.retain(|id, entity| {
+            if owner(self.type_name, entity.shard).is_none() {
+                return true;
+            }
+
+            debug!("Sharding({}): Handing off entity: {}", self.type_name, id);
+            entity.children.stop().ok();
+            false
+        });
+    }
+}
+
+fn router_name(type_name: &str) -> String {
+    format!("/sharding/{}", type_name)
+}
+
+fn shard_of(id: &str, num_shards: u32) -> u32 {
+    (fxhash::hash64(id) % u64::from(num_shards)) as u32
+}
+
+/// Returns the id of the node owning the shard, or `None` if it is
+/// owned by this node.
+fn owner(type_name: &str, shard: u32) -> Option<NodeId> {
+    let local = remote::local_node_id();
+    let members: Vec<_> = Cluster::members()
+        .into_iter()
+        .filter(|member| member.status() == MemberStatus::Up)
+        .map(|member| member.id())
+        .collect();
+
+    let owner = rendezvous(type_name, shard, &members).unwrap_or(local);
+    if owner == local {
+        None
+    } else {
+        Some(owner)
+    }
+}
+
+/// Returns the node with the highest weight for the shard.
+fn rendezvous(type_name: &str, shard: u32, nodes: &[NodeId]) -> Option<NodeId> {
+    nodes
+        .iter()
+        .copied()
+        .max_by_key(|node| fxhash::hash64(&(type_name, shard, node)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_few_shards_when_nodes_join() {
+        let mut nodes: Vec<_> = (0..4).map(|_| NodeId::new()).collect();
+        let before: Vec<_> = (0..100)
+            .map(|shard| rendezvous("test", shard, &nodes))
+            .collect();
+
+        nodes.push(NodeId::new());
+        let after: Vec<_> = (0..100)
+            .map(|shard| rendezvous("test", shard, &nodes))
+            .collect();
+
+        // Only the shards taken over by the new node moved.
+        for (before, after) in before.iter().zip(after.iter()) {
+            assert!(before == after || *after == Some(nodes[4]));
+        }
+
+        assert!(after.iter().any(|owner| *owner == Some(nodes[4])));
+    }
+
+    #[test]
+    fn assigns_entities_to_shards() {
+        assert_eq!(shard_of("alice", 10), shard_of("alice", 10));
+        assert!((0..1000).all(|i| shard_of(&i.to_string(), 10) < 10));
+        assert_eq!(shard_of("alice", 1), 0);
+    }
+}
@@ -0,0 +1,275 @@
+//!
+//! Turns the signals the process receives into actions of the system,
+//! and is available with the `signals` feature on Unix platforms.
+//!
+//! Once [`Bastion::handle_signals`] was called:
+//! - `SIGTERM` and `SIGINT` shut the system down gracefully (see
+//!   [`Bastion::shutdown_gracefully`]), using the drain deadline of
+//!   the [`SignalConfig`]. Receiving one of them again while the
+//!   system is draining kills it right away.
+//! - `SIGHUP` reloads the configuration of the system (see
+//!   [`SignalConfig::with_config_loader`]) and sends a
+//!   [`ReloadSignal`] to the children subscribed with
+//!   [`Signals::subscribe`].
+//!
+//! [`Bastion::handle_signals`]: ../struct.Bastion.html#method.handle_signals
+//! [`Bastion::shutdown_gracefully`]: ../struct.Bastion.html#method.shutdown_gracefully
+//! [`SignalConfig`]: struct.SignalConfig.html
+//! [`SignalConfig::with_config_loader`]: struct.SignalConfig.html#method.with_config_loader
+//! [`ReloadSignal`]: struct.ReloadSignal.html
+//! [`Signals::subscribe`]: struct.Signals.html#method.subscribe
+use crate::bastion::Bastion;
+use crate::child_ref::ChildRef;
+use crate::config::Config;
+use crate::errors::ConfigError;
+use crate::reload::ReloadReport;
+use crate::system::SYSTEM;
+use lazy_static::lazy_static;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals as SignalIterator;
+use std::fmt::{self, Debug, Formatter};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+lazy_static! {
+    // The children sent a `ReloadSignal` when the process
+    // receives `SIGHUP`.
+    static ref SUBSCRIBERS: Mutex<Vec<ChildRef>> = Mutex::new(Vec::new());
+}
+
+type ConfigLoader = Arc<dyn Fn() -> Result<Config, ConfigError> + Send + Sync>;
+
+#[derive(Clone)]
+/// The configuration of the handling of the signals of the process,
+/// as given to [`Bastion::handle_signals`].
+///
+/// By default, the system has 30 seconds to drain its children
+/// before they are killed, and `SIGHUP` only notifies the
+/// subscribed children, without reloading the configuration.
+///
+/// # Example
+///
+/// ```rust
+/// use bastion::prelude::*;
+/// use std::time::Duration;
+///
+/// let signals = SignalConfig::new()
+///     .with_drain_timeout(Duration::from_secs(10))
+///     .with_config_loader(|| Config::from_env());
+/// ```
+///
+/// [`Bastion::handle_signals`]: ../struct.Bastion.html#method.handle_signals
+pub struct SignalConfig {
+    drain_timeout: Duration,
+    config_loader: Option<ConfigLoader>,
+}
+
+#[derive(Debug)]
+/// Allows children to be notified when the process receives
+/// `SIGHUP` (see [`Bastion::handle_signals`]).
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| async move {
+///         Signals::subscribe(ctx.current());
+///         loop {
+///             msg! { ctx.recv().await?,
+///                 _: ReloadSignal => println!("Reopening the log files...");
+///                 _: _ => ();
+///             }
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`Bastion::handle_signals`]: ../struct.Bastion.html#method.handle_signals
+pub struct Signals {
+    _private: (),
+}
+
+#[derive(Debug, Clone)]
+/// The message sent to the children subscribed with
+/// [`Signals::subscribe`] when the process receives `SIGHUP`, once
+/// the configuration was reloaded (if it was).
+///
+/// [`Signals::subscribe`]: struct.Signals.html#method.subscribe
+pub struct ReloadSignal {
+    report: Option<ReloadReport>,
+}
+
+impl SignalConfig {
+    /// Creates a new configuration with a drain deadline of 30
+    /// seconds and without any configuration loader.
+    pub fn new() -> Self {
+        SignalConfig::default()
+    }
+
+    /// Sets how long the children have to finish handling the
+    /// messages of their mailboxes, when the process receives
+    /// `SIGTERM` or `SIGINT`, before they are killed.
+    ///
+    /// # Argument
+    ///
+    /// * `timeout` - The drain deadline.
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Sets the closure returning the new configuration of the
+    /// system, which is applied using [`Bastion::reload_config`]
+    /// when the process receives `SIGHUP`.
+    ///
+    /// If the closure returns an error, the configuration is left
+    /// unchanged.
+    ///
+    /// # Argument
+    ///
+    /// * `loader` - The closure loading the configuration (e.g.
+    ///     with [`Config::from_file`]).
+    ///
+    /// [`Bastion::reload_config`]: ../struct.Bastion.html#method.reload_config
+    /// [`Config::from_file`]: ../struct.Config.html#method.from_file
+    pub fn with_config_loader<F>(mut self, loader: F) -> Self
+    where
+        F: Fn() -> Result<Config, ConfigError> + Send + Sync + 'static,
+    {
+        self.config_loader = Some(Arc::new(loader));
+        self
+    }
+
+    /// Returns the drain deadline of a graceful shutdown.
+    pub fn drain_timeout(&self) -> Duration {
+        self.drain_timeout
+    }
+}
+
+impl Signals {
+    /// Subscribes a child to be sent a [`ReloadSignal`] every time
+    /// the process receives `SIGHUP`, unless the system is draining.
+    /// The children that are stopped are unsubscribed the next time
+    /// one is sent.
+    ///
+    /// # Argument
+    ///
+    /// * `child` - The child to subscribe.
+    ///
+    /// [`ReloadSignal`]: struct.ReloadSignal.html
+    pub fn subscribe(child: &ChildRef) {
+        // FIXME: panics
+        SUBSCRIBERS.lock().unwrap().push(child.clone());
+    }
+}
+
+impl ReloadSignal {
+    /// Returns the outcome of the reload of the configuration, or
+    /// `None` if no configuration loader was set or if it failed.
+    pub fn report(&self) -> Option<&ReloadReport> {
+        self.report.as_ref()
+    }
+}
+
+impl Default for SignalConfig {
+    fn default() -> Self {
+        SignalConfig {
+            drain_timeout: Duration::from_secs(30),
+            config_loader: None,
+        }
+    }
+}
+
+impl Debug for SignalConfig {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("SignalConfig")
+            .field("drain_timeout", &self.drain_timeout)
+            .field("config_loader", &self.config_loader.is_some())
+            .finish()
+    }
+}
+
+/// Registers the handlers of the signals and starts the thread
+/// turning them into actions of the system.
+pub(crate) fn handle(config: SignalConfig) -> io::Result<()> {
+    let mut signals = SignalIterator::new(&[SIGTERM, SIGINT, SIGHUP])?;
+
+    thread::Builder::new()
+        .name("bastion-signals".to_string())
+        .spawn(move || {
+            let mut draining = false;
+            for signal in signals.forever() {
+                match signal {
+                    SIGHUP => reload(&config),
+                    _ if draining => {
+                        info!("Signals: Received {} while draining, killing.", signal);
+                        Bastion::kill();
+                        return;
+                    }
+                    _ => {
+                        info!("Signals: Received {}, shutting down gracefully.", signal);
+                        draining = true;
+                        // The next termination signal needs to be
+                        // received while the system drains.
+                        let timeout = config.drain_timeout;
+                        thread::spawn(move || Bastion::shutdown_gracefully(timeout));
+                    }
+                }
+            }
+        })?;
+
+    Ok(())
+}
+
+fn reload(config: &SignalConfig) {
+    info!("Signals: Received SIGHUP, reloading.");
+    let report = config
+        .config_loader
+        .as_ref()
+        .and_then(|loader| match loader() {
+            Ok(config) => Some(Bastion::reload_config(config)),
+            Err(err) => {
+                error!("Signals: Couldn't load the configuration: {:?}", err);
+                None
+            }
+        });
+
+    // The messages are refused while the system drains, which
+    // doesn't mean that the subscribers stopped.
+    if SYSTEM.is_draining() {
+        debug!("Signals: Draining, not notifying the children.");
+        return;
+    }
+
+    let signal = ReloadSignal { report };
+    // FIXME: panics
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    // The system could have started to drain in the meantime.
+    subscribers
+        .retain(|child| child.tell_anonymously(signal.clone()).is_ok() || SYSTEM.is_draining());
+    debug!("Signals: Notified {} children.", subscribers.len());
+}
@@ -0,0 +1,508 @@
+//!
+//! Cluster singletons are children groups that run on exactly one
+//! node of a cluster, and are available with the `cluster` feature.
+//!
+//! Every node spawns the singleton with [`ClusterSingleton::spawn`],
+//! but only the leader of the cluster (see [`Cluster::leader`]) runs
+//! it. When the leader changes, the singleton is stopped on the
+//! previous leader and started on the new one.
+//!
+//! When the node running the singleton leaves the cluster
+//! gracefully, it hands the singleton over to the next leader: the
+//! handover hook of the [`SingletonConfig`] returns a snapshot of
+//! the singleton's state, which is sent to the next leader and given
+//! to its takeover hook before it starts the singleton. A leader
+//! that is unreachable can't hand the singleton over, in which case
+//! the next one starts it right away.
+//!
+//! [`ClusterSingleton::spawn`]: struct.ClusterSingleton.html#method.spawn
+//! [`Cluster::leader`]: ../cluster/struct.Cluster.html#method.leader
+//! [`SingletonConfig`]: struct.SingletonConfig.html
+use crate::children::Children;
+use crate::children_ref::ChildrenRef;
+use crate::cluster::{self, Cluster, MemberStatus};
+use crate::codec::RemoteMessage;
+use crate::context::BastionContext;
+use crate::dead_letters;
+use crate::envelope::{Envelope, SignedMessage};
+use crate::errors::SingletonError;
+use crate::executor;
+use crate::message::{BastionMessage, Msg};
+use crate::remote::{self, NodeId};
+use crate::supervisor::SupervisorRef;
+use crate::Bastion;
+use fxhash::FxHashSet;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, trace};
+
+lazy_static! {
+    // The names of the singletons spawned on this node.
+    static ref SINGLETONS: Mutex<FxHashSet<String>> = Mutex::new(FxHashSet::default());
+}
+
+#[derive(Clone)]
+/// A handle to a children group running on exactly one node of the
+/// cluster, which allows to send messages to it wherever it runs.
+pub struct ClusterSingleton {
+    inner: Arc<Singleton>,
+}
+
+#[derive(Clone)]
+/// The configuration of a cluster singleton, as given to
+/// [`ClusterSingleton::spawn_with`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::sync::{Arc, Mutex};
+/// #
+/// // The state of the singleton, which is shared with its children.
+/// let scheduled = Arc::new(Mutex::new(Vec::<String>::new()));
+///
+/// let handed_over = scheduled.clone();
+/// let taken_over = scheduled.clone();
+/// let config = SingletonConfig::default()
+///     .with_handover(move || serde_json::to_vec(&*handed_over.lock().unwrap()).ok())
+///     .with_takeover(move |snapshot| {
+///         if let Ok(jobs) = serde_json::from_slice(&snapshot) {
+///             *taken_over.lock().unwrap() = jobs;
+///         }
+///     });
+/// # drop(config);
+/// ```
+///
+/// [`ClusterSingleton::spawn_with`]: struct.ClusterSingleton.html#method.spawn_with
+pub struct SingletonConfig {
+    handover: Option<Arc<dyn Fn() -> Option<Vec<u8>> + Send + Sync>>,
+    takeover: Option<Arc<dyn Fn(Vec<u8>) + Send + Sync>>,
+    handover_timeout: Duration,
+    check_interval: Duration,
+}
+
+struct Singleton {
+    name: String,
+    init: Box<dyn Fn(Children) -> Children + Send + Sync>,
+    config: SingletonConfig,
+    supervisor: SupervisorRef,
+    state: Mutex<SingletonState>,
+}
+
+struct SingletonState {
+    // The singleton's children group, if it runs on this node.
+    running: Option<ChildrenRef>,
+    // The leader elected during the last check.
+    leader: Option<NodeId>,
+    // The snapshot sent by the previous node running the singleton.
+    handover: Option<Vec<u8>>,
+    // When this node started waiting for the previous leader to
+    // hand the singleton over.
+    waiting_since: Option<Instant>,
+    // Whether this node left the cluster.
+    left: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Handover {
+    snapshot: Vec<u8>,
+}
+
+impl ClusterSingleton {
+    /// Spawns a cluster singleton on this node, with the default
+    /// configuration. This needs to be done on every node of the
+    /// cluster, preferably after joining it: the children group is
+    /// only started on the leader of the cluster (or on this node
+    /// if it didn't join a cluster).
+    ///
+    /// This method returns a handle to the singleton if it
+    /// succeeded, or `Err(error)` otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the singleton, which must be the same
+    ///     on every node.
+    /// * `init` - The closure taking the new [`Children`] as an
+    ///     argument and returning it once configured, which is
+    ///     called every time the singleton starts on this node.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    /// Bastion::start();
+    ///
+    /// let coordinator = ClusterSingleton::spawn("coordinator", |children| {
+    ///     children.with_exec(|ctx: BastionContext| async move {
+    ///         loop {
+    ///             msg! { ctx.recv().await?,
+    ///                 job: String => println!("Scheduling {}...", job);
+    ///                 _: _ => ();
+    ///             }
+    ///         }
+    ///     })
+    /// })
+    /// .expect("Couldn't spawn the singleton.");
+    ///
+    /// coordinator.tell("backup".to_string()).ok();
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`Children`]: ../children/struct.Children.html
+    pub fn spawn<N, C>(name: N, init: C) -> Result<Self, SingletonError>
+    where
+        N: Into<String>,
+        C: Fn(Children) -> Children + Send + Sync + 'static,
+    {
+        ClusterSingleton::spawn_with(name, init, SingletonConfig::default())
+    }
+
+    /// Spawns a cluster singleton on this node, like
+    /// [`ClusterSingleton::spawn`] does, with the given
+    /// configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the singleton, which must be the same
+    ///     on every node.
+    /// * `init` - The closure configuring the singleton's children
+    ///     group.
+    /// * `config` - The configuration of the singleton.
+    ///
+    /// [`ClusterSingleton::spawn`]: #method.spawn
+    pub fn spawn_with<N, C>(
+        name: N,
+        init: C,
+        config: SingletonConfig,
+    ) -> Result<Self, SingletonError>
+    where
+        N: Into<String>,
+        C: Fn(Children) -> Children + Send + Sync + 'static,
+    {
+        let name = name.into();
+        let mut singletons = SINGLETONS.lock().unwrap();
+        if singletons.contains(&name) {
+            return Err(SingletonError::AlreadySpawned);
+        }
+
+        debug!("ClusterSingleton({}): Spawning.", name);
+        remote::register_message::<Handover>();
+
+        let supervisor = Bastion::supervisor(|sp| sp).map_err(|_| SingletonError::SpawnFailed)?;
+        let singleton = ClusterSingleton {
+            inner: Arc::new(Singleton {
+                name: name.clone(),
+                init: Box::new(init),
+                config,
+                supervisor,
+                state: Mutex::new(SingletonState {
+                    running: None,
+                    leader: None,
+                    handover: None,
+                    waiting_since: None,
+                    left: false,
+                }),
+            }),
+        };
+
+        // The manager receives the handovers and the messages sent
+        // by the other nodes.
+        let managed = singleton.inner.clone();
+        singleton
+            .inner
+            .supervisor
+            .children(|children| {
+                children.with_exec(move |ctx: BastionContext| {
+                    let managed = managed.clone();
+                    async move {
+                        remote::register_named(manager_name(&managed.name), ctx.current());
+                        loop {
+                            let SignedMessage { msg, .. } = ctx.recv().await?;
+                            match msg.downcast::<Handover>() {
+                                Ok(handover) => managed.receive(handover.snapshot),
+                                Err(msg) => managed.forward(msg),
+                            }
+                        }
+                    }
+                })
+            })
+            .map_err(|_| SingletonError::SpawnFailed)?;
+
+        let leaving = singleton.inner.clone();
+        cluster::on_leave(move || leaving.leave());
+
+        let checked = singleton.inner.clone();
+        checked.check();
+        executor::schedule_interval(checked.config.check_interval, move || {
+            checked.check();
+            true
+        });
+
+        singletons.insert(name);
+        Ok(singleton)
+    }
+
+    /// Sends a message to the singleton's children group, wherever
+    /// it runs. The type of the message needs to be registered on
+    /// every node using [`remote::register_message`].
+    ///
+    /// This method returns `()` if it succeeded, or `Err(msg)` if
+    /// the singleton isn't running or its node couldn't be reached.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to send.
+    ///
+    /// [`remote::register_message`]: ../remote/fn.register_message.html
+    pub fn tell<M: RemoteMessage>(&self, msg: M) -> Result<(), M> {
+        let state = self.inner.state.lock().unwrap();
+        if let Some(running) = &state.running {
+            return running.broadcast(msg);
+        }
+
+        let local = remote::local_node_id();
+        match state.leader.filter(|leader| *leader != local) {
+            Some(leader) => {
+                trace!(
+                    "ClusterSingleton({}): Routing message to {}: {:?}",
+                    self.inner.name,
+                    leader,
+                    msg
+                );
+                match remote::node(leader) {
+                    Some(node) => node
+                        .child_ref(manager_name(&self.inner.name))
+                        .tell_anonymously(msg),
+                    None => Err(msg),
+                }
+            }
+            None => Err(msg),
+        }
+    }
+
+    /// Returns the name of the singleton.
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// Returns whether the singleton runs on this node.
+    pub fn is_running_locally(&self) -> bool {
+        self.inner.state.lock().unwrap().running.is_some()
+    }
+}
+
+impl Debug for ClusterSingleton {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("ClusterSingleton")
+            .field("name", &self.inner.name)
+            .field("running_locally", &self.is_running_locally())
+            .finish()
+    }
+}
+
+impl SingletonConfig {
+    /// Sets the hook called on the node running the singleton when
+    /// it hands the singleton over to another node, and returning
+    /// a snapshot of its state (if any).
+    ///
+    /// # Arguments
+    ///
+    /// * `handover` - The closure returning the snapshot.
+    pub fn with_handover<F>(mut self, handover: F) -> Self
+    where
+        F: Fn() -> Option<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.handover = Some(Arc::new(handover));
+        self
+    }
+
+    /// Sets the hook called with the snapshot returned by the
+    /// handover hook of the previous node, before the singleton is
+    /// started on this node.
+    ///
+    /// # Arguments
+    ///
+    /// * `takeover` - The closure restoring the snapshot.
+    pub fn with_takeover<F>(mut self, takeover: F) -> Self
+    where
+        F: Fn(Vec<u8>) + Send + Sync + 'static,
+    {
+        self.takeover = Some(Arc::new(takeover));
+        self
+    }
+
+    /// Sets how long the new leader waits for the previous one to
+    /// hand the singleton over when it left the cluster, before
+    /// starting it without any snapshot. By default, it is five
+    /// seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The handover timeout.
+    pub fn with_handover_timeout(mut self, timeout: Duration) -> Self {
+        self.handover_timeout = timeout;
+        self
+    }
+
+    /// Returns how long the new leader waits for a handover.
+    pub fn handover_timeout(&self) -> Duration {
+        self.handover_timeout
+    }
+}
+
+impl Default for SingletonConfig {
+    fn default() -> Self {
+        SingletonConfig {
+            handover: None,
+            takeover: None,
+            handover_timeout: Duration::from_secs(5),
+            check_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+impl Debug for SingletonConfig {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("SingletonConfig")
+            .field("handover", &self.handover.is_some())
+            .field("takeover", &self.takeover.is_some())
+            .field("handover_timeout", &self.handover_timeout)
+            .finish()
+    }
+}
+
+impl Singleton {
+    /// Starts or stops the singleton depending on whether this node
+    /// is the leader of the cluster.
+    fn check(&self) {
+        let local = remote::local_node_id();
+        let leader = Cluster::leader().map(|leader| leader.id());
+        let mut state = self.state.lock().unwrap();
+        if state.left {
+            return;
+        }
+
+        let previous = std::mem::replace(&mut state.leader, leader);
+        let leading = leader.map_or(true, |leader| leader == local);
+        if !leading {
+            self.hand_over(&mut state, leader);
+            return;
+        }
+
+        if state.running.is_some() {
+            return;
+        }
+
+        // Only a leader that left gracefully hands the singleton over.
+        if let Some(previous) = previous.filter(|previous| *previous != local) {
+            if state.handover.is_none() && has_left(previous) {
+                state.waiting_since = Some(Instant::now());
+            }
+        }
+
+        if let Some(since) = state.waiting_since {
+            if state.handover.is_none() && since.elapsed() < self.config.handover_timeout {
+                trace!("ClusterSingleton({}): Waiting for the handover.", self.name);
+                return;
+            }
+        }
+
+        state.waiting_since = None;
+        if let Some(snapshot) = state.handover.take() {
+            if let Some(takeover) = &self.config.takeover {
+                takeover(snapshot);
+            }
+        }
+
+        debug!("ClusterSingleton({}): Starting on this node.", self.name);
+        let init = &self.init;
+        state.running = self.supervisor.children(|children| init(children)).ok();
+    }
+
+    /// Stops the singleton if it runs on this node, sending the
+    /// snapshot of its state to the next leader.
+    fn hand_over(&self, state: &mut SingletonState, to: Option<NodeId>) {
+        let running = match state.running.take() {
+            Some(running) => running,
+            None => return,
+        };
+
+        debug!("ClusterSingleton({}): Handing over to {:?}.", self.name, to);
+        let snapshot = self
+            .config
+            .handover
+            .as_ref()
+            .and_then(|handover| handover());
+        running.stop().ok();
+
+        if let (Some(snapshot), Some(node)) = (snapshot, to.and_then(remote::node)) {
+            let handover = Handover { snapshot };
+            node.child_ref(manager_name(&self.name))
+                .tell_anonymously(handover)
+                .ok();
+        }
+    }
+
+    /// Hands the singleton over to the member that will be the
+    /// leader once this node left.
+    fn leave(&self) {
+        let local = remote::local_node_id();
+        let next = Cluster::members()
+            .into_iter()
+            .filter(|member| member.id() != local && member.status() == MemberStatus::Up)
+            .min_by_key(|member| (member.joined_at(), member.id()))
+            .map(|member| member.id());
+
+        let mut state = self.state.lock().unwrap();
+        state.left = true;
+        self.hand_over(&mut state, next);
+    }
+
+    fn receive(&self, snapshot: Vec<u8>) {
+        debug!("ClusterSingleton({}): Received a handover.", self.name);
+        self.state.lock().unwrap().handover = Some(snapshot);
+    }
+
+    fn forward(&self, msg: Msg) {
+        let env = Envelope::from_dead_letters(BastionMessage::Message(msg));
+        let state = self.state.lock().unwrap();
+        let result = match &state.running {
+            Some(running) => running.send(env),
+            None => Err(env),
+        };
+
+        if let Err(env) = result {
+            dead_letters::publish(self.supervisor.path().clone(), env);
+        }
+    }
+}
+
+fn manager_name(name: &str) -> String {
+    format!("/singleton/{}", name)
+}
+
+fn has_left(id: NodeId) -> bool {
+    Cluster::members()
+        .iter()
+        .any(|member| member.id() == id && member.status() == MemberStatus::Left)
+}
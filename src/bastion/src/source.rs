@@ -0,0 +1,129 @@
+//!
+//! Stream sources feed the items of a [`Stream`] (like the records
+//! of a message queue's consumer) to the elements of a children
+//! group, in turn.
+//!
+//! A source runs as a helper actor of the group. When its stream
+//! fails, it is created again (following the source's restart
+//! strategy) from the resume point of the last item that was
+//! delivered.
+//!
+//! [`Stream`]: https://docs.rs/futures/0.3/futures/stream/trait.Stream.html
+use crate::child::Init;
+use crate::child_ref::ChildRef;
+use crate::context::{BastionContext, BastionId};
+use crate::message::Message;
+use crate::supervisor::{RestartPolicy, RestartStrategy};
+use crate::tree;
+use futures::prelude::*;
+use futures_timer::Delay;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, error, trace, warn};
+
+// How long to wait before delivering an item again when no
+// element of the group could receive it.
+const RETRY_DELAY: Duration = Duration::from_millis(10);
+
+// Delivers the items of a source to the running elements of a
+// children group, in turn.
+pub(crate) struct Feeder {
+    group: BastionId,
+    next: usize,
+}
+
+/// Returns the closure of the helper actor feeding the items of
+/// the streams created by `source` to its children group.
+pub(crate) fn init<F, S, R, M, E>(source: F, restart_strategy: RestartStrategy) -> Init
+where
+    F: Fn(Option<R>) -> S + Send + Sync + 'static,
+    S: Stream<Item = Result<(R, M), E>> + Send + 'static,
+    R: Clone + Debug + Send + 'static,
+    M: Message,
+    E: Debug + Send + 'static,
+{
+    let source = Arc::new(source);
+    let resume = Arc::new(Mutex::new(None));
+    Init::new(move |ctx: BastionContext| {
+        let source = source.clone();
+        let resume = resume.clone();
+        let restart_strategy = restart_strategy.clone();
+        async move {
+            let group = ctx.parent().id().clone();
+            let mut feeder = Feeder::new(group.clone());
+            let mut restarts = 0;
+            loop {
+                // FIXME: panics
+                let from = resume.lock().unwrap().clone();
+                debug!("StreamSource({}): Starting from: {:?}", group, from);
+                let mut stream = Box::pin(source(from));
+                let err = loop {
+                    match stream.next().await {
+                        Some(Ok((point, msg))) => {
+                            feeder.feed(msg).await;
+                            // FIXME: panics
+                            *resume.lock().unwrap() = Some(point);
+                            restarts = 0;
+                        }
+                        Some(Err(err)) => break err,
+                        None => {
+                            debug!("StreamSource({}): Ended.", group);
+                            return Ok(());
+                        }
+                    }
+                };
+
+                warn!("StreamSource({}): Failed: {:?}", group, err);
+                let restart = match restart_strategy.restart_policy() {
+                    RestartPolicy::Always => true,
+                    RestartPolicy::Never => false,
+                    RestartPolicy::Tries(tries) => restarts < tries,
+                };
+                if !restart {
+                    error!(
+                        "StreamSource({}): Giving up after {} restarts.",
+                        group, restarts
+                    );
+                    return Ok(());
+                }
+
+                restart_strategy.apply_strategy(restarts).await;
+                restarts += 1;
+            }
+        }
+    })
+}
+
+impl Feeder {
+    pub(crate) fn new(group: BastionId) -> Self {
+        Feeder { group, next: 0 }
+    }
+
+    pub(crate) async fn feed<M: Message>(&mut self, mut msg: M) {
+        loop {
+            // The elements are looked up every time because they
+            // get a new `ChildRef` once restarted.
+            let elems = tree::running_elems(&self.group);
+            if let Some(elem) = self.next_elem(&elems) {
+                trace!("StreamSource({}): Feeding {}.", self.group, elem.path());
+                match elem.tell_anonymously_async(msg).await {
+                    Ok(()) => return,
+                    Err(unsent) => msg = unsent,
+                }
+            }
+
+            Delay::new(RETRY_DELAY).await;
+        }
+    }
+
+    fn next_elem<'a>(&mut self, elems: &'a [ChildRef]) -> Option<&'a ChildRef> {
+        if elems.is_empty() {
+            return None;
+        }
+
+        let elem = &elems[self.next % elems.len()];
+        self.next = self.next.wrapping_add(1);
+        Some(elem)
+    }
+}
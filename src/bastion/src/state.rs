@@ -0,0 +1,178 @@
+//!
+//! Typed state shared by the children of the system.
+//!
+//! A [`StateRegistry`] holds at most one value of every type, which
+//! the children can then retrieve with [`BastionContext::state`]
+//! instead of capturing clones of it in the closures of their groups
+//! (e.g. their configuration, connection pools or clients).
+//!
+//! The registry returned by [`Bastion::state`] is shared by the
+//! whole system, while the one of a supervisor (see
+//! [`SupervisorRef::state`] and [`Supervisor::with_state`]) is only
+//! seen by the elements it supervises (directly or not), and takes
+//! precedence over the ones of its parents.
+//!
+//! [`StateRegistry`]: struct.StateRegistry.html
+//! [`BastionContext::state`]: ../context/struct.BastionContext.html#method.state
+//! [`Bastion::state`]: ../struct.Bastion.html#method.state
+//! [`SupervisorRef::state`]: ../supervisor/struct.SupervisorRef.html#method.state
+//! [`Supervisor::with_state`]: ../supervisor/struct.Supervisor.html#method.with_state
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+use std::any::{Any, TypeId};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, RwLock};
+
+lazy_static! {
+    // The registry shared by the whole system.
+    static ref GLOBAL: StateRegistry = StateRegistry::new(None);
+}
+
+#[derive(Clone)]
+/// A registry of values, retrieved by their type.
+///
+/// Looking a value up in the registry of a supervisor falls back to
+/// the registries of its parents, up to the one shared by the whole
+/// system.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use std::sync::Arc;
+/// #
+/// struct Config {
+///     url: String,
+/// }
+///
+/// Bastion::state().insert(Config {
+///     url: "redis://localhost".to_string(),
+/// });
+///
+/// let config: Arc<Config> = Bastion::state().get().unwrap();
+/// assert_eq!(config.url, "redis://localhost");
+/// ```
+pub struct StateRegistry {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    values: RwLock<FxHashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+    // The registry values are looked up in when they aren't in
+    // this one.
+    parent: Option<StateRegistry>,
+}
+
+impl StateRegistry {
+    fn new(parent: Option<StateRegistry>) -> Self {
+        let values = RwLock::new(FxHashMap::default());
+        let inner = Arc::new(Inner { values, parent });
+
+        StateRegistry { inner }
+    }
+
+    pub(crate) fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    // Creates the registry of a supervisor, whose parent is the
+    // registry of the supervisor's parent.
+    pub(crate) fn scoped(parent: StateRegistry) -> Self {
+        StateRegistry::new(Some(parent))
+    }
+
+    /// Adds a value to this registry, replacing (and returning) the
+    /// value of the same type it held, if any.
+    ///
+    /// # Argument
+    ///
+    /// * `value` - The value to add.
+    pub fn insert<T: Send + Sync + 'static>(&self, value: T) -> Option<Arc<T>> {
+        self.insert_arc(Arc::new(value))
+    }
+
+    /// Adds a value which is already shared to this registry,
+    /// replacing (and returning) the value of the same type it held,
+    /// if any.
+    ///
+    /// # Argument
+    ///
+    /// * `value` - The value to add.
+    pub fn insert_arc<T: Send + Sync + 'static>(&self, value: Arc<T>) -> Option<Arc<T>> {
+        // FIXME: panics
+        let mut values = self.inner.values.write().unwrap();
+        let previous = values.insert(TypeId::of::<T>(), value)?;
+
+        previous.downcast().ok()
+    }
+
+    /// Returns the value of the given type held by this registry
+    /// (or by the registries of its parents), if any.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        // FIXME: panics
+        let value = self
+            .inner
+            .values
+            .read()
+            .unwrap()
+            .get(&TypeId::of::<T>())
+            .cloned();
+
+        match value {
+            Some(value) => value.downcast().ok(),
+            None => self.inner.parent.as_ref()?.get(),
+        }
+    }
+
+    /// Returns whether this registry (or the registries of its
+    /// parents) holds a value of the given type.
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+
+    /// Removes the value of the given type from this registry (but
+    /// not from the registries of its parents), returning it if it
+    /// held one.
+    pub fn remove<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        // FIXME: panics
+        let mut values = self.inner.values.write().unwrap();
+        let value = values.remove(&TypeId::of::<T>())?;
+
+        value.downcast().ok()
+    }
+}
+
+impl Debug for StateRegistry {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        // FIXME: panics
+        let len = self.inner.values.read().unwrap().len();
+        fmt.debug_struct("StateRegistry")
+            .field("len", &len)
+            .field("scoped", &self.inner.parent.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_values_up_in_the_parents() {
+        let parent = StateRegistry::new(None);
+        let scoped = StateRegistry::scoped(parent.clone());
+
+        parent.insert(1u32);
+        parent.insert("parent");
+        assert_eq!(scoped.insert("scoped"), None);
+
+        assert_eq!(scoped.get::<u32>().as_deref(), Some(&1));
+        assert_eq!(scoped.get::<&str>().as_deref(), Some(&"scoped"));
+        assert_eq!(parent.get::<&str>().as_deref(), Some(&"parent"));
+        assert!(!scoped.contains::<u64>());
+
+        assert_eq!(scoped.remove::<&str>().as_deref(), Some(&"scoped"));
+        assert_eq!(scoped.get::<&str>().as_deref(), Some(&"parent"));
+        assert_eq!(scoped.remove::<u32>(), None);
+    }
+}
@@ -2,27 +2,35 @@
 //! Supervisors enable users to supervise a subtree of children
 //! or other supervisor trees under themselves.
 use crate::broadcast::{Broadcast, Parent, Sender};
-use crate::callbacks::Callbacks;
+use crate::callbacks::{Callbacks, ChildFailure};
 use crate::children::Children;
 use crate::children_ref::ChildrenRef;
-use crate::context::{BastionId, ContextState};
+use crate::context::{BastionContext, BastionId, ContextState};
 use crate::envelope::Envelope;
+use crate::events::{self, SupervisionEvent};
+use crate::executor;
 use crate::message::{BastionMessage, Deployment, Message};
+#[cfg(feature = "metrics")]
+use crate::metrics;
 use crate::path::{BastionPath, BastionPathElement};
+use crate::state::StateRegistry;
+use crate::watch::StopReason;
 
-use bastion_executor::pool;
+use futures::channel::oneshot;
 use futures::prelude::*;
 use futures::stream::FuturesOrdered;
 use futures::{pending, poll};
-use futures_timer::Delay;
 use fxhash::FxHashMap;
+use lazy_static::lazy_static;
 use lightproc::prelude::*;
 use std::cmp::{Eq, PartialEq};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Formatter};
 use std::ops::Range;
 use std::pin::Pin;
-use std::sync::Arc;
-use std::task::Poll;
-use std::time::Duration;
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 use tracing::{debug, trace, warn};
 
 #[derive(Debug)]
@@ -114,6 +122,29 @@ pub struct Supervisor {
     subtree_restarts: usize,
     // Store the maximum acceptable restarts for the supervisor.
     subtree_restarts_limit: usize,
+    // The maximum amount of restarts allowed within a time window.
+    restart_intensity: Option<(usize, Duration)>,
+    // What to do once the restart intensity was exceeded.
+    intensity_decision: IntensityDecision,
+    // When the restarts within the restart intensity window happened.
+    restarts_history: VecDeque<Instant>,
+    // Why the supervised elements get killed, given to them
+    // with the kill messages.
+    kill_reason: StopReason,
+    // Decides how to handle the failures of the elements of the
+    // supervised children groups, instead of applying the
+    // strategy to all of them.
+    decider: Option<Decider>,
+    // The values shared with the supervised elements, which
+    // take precedence over the ones of the supervisor's parents.
+    state: StateRegistry,
+}
+
+lazy_static! {
+    // The restart strategies configured using
+    // `Config::with_restart_strategy`, by children group name.
+    static ref GROUP_RESTART_STRATEGIES: RwLock<FxHashMap<String, RestartStrategy>> =
+        RwLock::new(FxHashMap::default());
 }
 
 #[derive(Debug, Clone)]
@@ -121,6 +152,8 @@ struct TrackedChildState {
     id: BastionId,
     state: Arc<Pin<Box<ContextState>>>,
     restarts_counts: usize,
+    // When the actor was restarted for the last time.
+    last_restart: Option<Instant>,
 }
 
 #[derive(Debug)]
@@ -145,6 +178,18 @@ pub struct SupervisorRef {
     id: BastionId,
     sender: Sender,
     path: Arc<BastionPath>,
+    state: StateRegistry,
+}
+
+/// A handle to a future supervised using
+/// [`SupervisorRef::supervise_future`], resolving to its output,
+/// or to `None` if it panicked and couldn't be restarted anymore
+/// (or if it was stopped before completing).
+///
+/// [`SupervisorRef::supervise_future`]: supervisor/struct.SupervisorRef.html#method.supervise_future
+pub struct SupervisedHandle<T> {
+    children: ChildrenRef,
+    receiver: oneshot::Receiver<Option<T>>,
 }
 
 #[derive(Debug, Clone)]
@@ -172,8 +217,72 @@ pub enum SupervisionStrategy {
     /// were stopped) in the same order they were added to
     /// the supervisor.
     RestForOne,
+    /// When a children group dies (either because it got
+    /// killed, it panicked or returned an error), the failure
+    /// is propagated to the parent supervisor, which applies
+    /// its own strategy and restarts the whole subtree of this
+    /// supervisor (along with its siblings if its strategy
+    /// requires it).
+    ///
+    /// Supervisors created with [`Bastion::supervisor`] don't
+    /// have a parent supervisor and are faulted instead, which
+    /// makes the system restart them.
+    ///
+    /// [`Bastion::supervisor`]: struct.Bastion.html#method.supervisor
+    Escalate,
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// What a supervisor should do when its supervised elements
+/// restarted more often than allowed by its restart intensity
+/// (see [`Supervisor::with_restart_intensity`]).
+///
+/// The default decision is `Escalate`.
+///
+/// [`Supervisor::with_restart_intensity`]: supervisor/struct.Supervisor.html#method.with_restart_intensity
+pub enum IntensityDecision {
+    /// The supervisor gives up and lets its parent handle the
+    /// failure, using the parent's supervision strategy, which
+    /// restarts the whole subtree of the supervisor. A supervisor
+    /// created with [`Bastion::supervisor`] faults instead and is
+    /// restarted by the system.
+    ///
+    /// [`Bastion::supervisor`]: struct.Bastion.html#method.supervisor
+    Escalate,
+    /// The supervisor gives up and stops, along with all its
+    /// supervised elements.
+    StopSubtree,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// What a supervisor should do when an element of one of its
+/// supervised children groups fails, as returned by the closure
+/// defined using [`Supervisor::with_decider`].
+///
+/// [`Supervisor::with_decider`]: supervisor/struct.Supervisor.html#method.with_decider
+pub enum Directive {
+    /// The supervisor applies its [`SupervisionStrategy`].
+    ///
+    /// [`SupervisionStrategy`]: supervisor/enum.SupervisionStrategy.html
+    Restart,
+    /// The supervisor stops the element, without restarting it.
+    Stop,
+    /// The supervisor gives up and lets its parent handle the
+    /// failure, as if its strategy was
+    /// [`SupervisionStrategy::Escalate`].
+    ///
+    /// [`SupervisionStrategy::Escalate`]: supervisor/enum.SupervisionStrategy.html#variant.Escalate
+    Escalate,
+    /// The supervisor only restarts the element, keeping its state
+    /// and whatever its strategy is, without counting it towards
+    /// its restart intensity.
+    Resume,
+}
+
+// The closure deciding how to handle the failures of the elements
+// of the supervised children groups.
+struct Decider(Arc<dyn Fn(&ChildFailure) -> Directive + Send + Sync>);
+
 #[derive(Debug)]
 enum Supervised {
     Supervisor(Supervisor),
@@ -206,6 +315,9 @@ pub enum RestartPolicy {
 pub struct RestartStrategy {
     restart_policy: RestartPolicy,
     strategy: ActorRestartStrategy,
+    // The period after which an actor that didn't fail is
+    // considered healthy, resetting its restarts counter.
+    reset_after: Option<Duration>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -224,15 +336,19 @@ pub enum ActorRestartStrategy {
         timeout: Duration,
     },
     /// Restart an actor after with the timeout. Each next timeout
-    /// is increasing exponentially.
-    /// When passed a multiplier that equals to 1, the strategy works as the
-    /// linear back off strategy. Passing the multiplier that equals to 0 leads
-    /// to constant restart delays which is equal to the given timeout.
+    /// is doubled, until it reaches the given maximum delay.
+    /// A random jitter can be applied to the delays to prevent the
+    /// actors failing at the same time from being restarted all
+    /// at once.
     ExponentialBackOff {
         /// An initial delay before the restarting an actor.
-        timeout: Duration,
-        /// Defines a multiplier how fast the timeout will be increasing.
-        multiplier: f64,
+        base: Duration,
+        /// The maximum delay before restarting an actor.
+        max: Duration,
+        /// The maximum fraction of the delay (between `0.0` and `1.0`)
+        /// that can be randomly removed from it. Passing `0.0` disables
+        /// the jitter.
+        jitter: f64,
     },
 }
 
@@ -241,22 +357,43 @@ impl ActorRestartStrategy {
     pub fn calculate(&self, restarts_count: usize) -> Option<Duration> {
         match *self {
             ActorRestartStrategy::LinearBackOff { timeout } => {
-                let delay = timeout.mul_f64(restarts_count as f64);
-                Some(timeout + delay)
+                let restarts_count = restarts_count.min(u32::MAX as usize) as u32;
+                let delay = timeout.saturating_mul(restarts_count);
+                Some(timeout.saturating_add(delay))
             }
-            ActorRestartStrategy::ExponentialBackOff {
-                timeout,
-                multiplier,
-            } => {
-                let factor = multiplier * restarts_count as f64;
-                let delay = timeout.mul_f64(factor);
-                Some(timeout + delay)
+            ActorRestartStrategy::ExponentialBackOff { base, max, jitter } => {
+                // Limit the exponent to avoid computing huge powers
+                // that will be capped by `max` anyway. `Duration`s can
+                // only be multiplied by `u32`s, so the factor is
+                // applied in two steps.
+                let exponent = restarts_count.min(62) as u32;
+                let low = exponent.min(31);
+                let delay = base
+                    .saturating_mul(1 << low)
+                    .saturating_mul(1 << (exponent - low))
+                    .min(max);
+
+                let jitter = jitter.max(0.0).min(1.0);
+                let removed = delay.mul_f64(jitter * rand::random::<f64>());
+                Some(delay.saturating_sub(removed))
             }
             _ => None,
         }
     }
 }
 
+pub(crate) fn set_group_restart_strategies(strategies: FxHashMap<String, RestartStrategy>) {
+    // FIXME: panics
+    *GROUP_RESTART_STRATEGIES.write().unwrap() = strategies;
+}
+
+/// Returns the restart strategy configured for the children groups
+/// with the given name, if any.
+pub(crate) fn group_restart_strategy(name: &str) -> Option<RestartStrategy> {
+    // FIXME: panics
+    GROUP_RESTART_STRATEGIES.read().unwrap().get(name).cloned()
+}
+
 impl Supervisor {
     pub(crate) fn new(bcast: Broadcast) -> Self {
         debug!("Supervisor({}): Initializing.", bcast.id());
@@ -274,6 +411,15 @@ impl Supervisor {
         let started = false;
         let subtree_restarts = 0;
         let subtree_restarts_limit = 3;
+        let restart_intensity = None;
+        let intensity_decision = IntensityDecision::default();
+        let restarts_history = VecDeque::new();
+        let kill_reason = StopReason::Killed;
+        let decider = None;
+        let state = match bcast.parent().clone().into_supervisor() {
+            Some(parent) => StateRegistry::scoped(parent.state().clone()),
+            None => StateRegistry::scoped(StateRegistry::global()),
+        };
 
         Supervisor {
             bcast,
@@ -291,12 +437,19 @@ impl Supervisor {
             started,
             subtree_restarts,
             subtree_restarts_limit,
+            restart_intensity,
+            intensity_decision,
+            restarts_history,
+            kill_reason,
+            decider,
+            state,
         }
     }
 
     pub(crate) fn system(bcast: Broadcast) -> Self {
         let mut supervisor = Supervisor::new(bcast);
         supervisor.is_system_supervisor = true;
+        supervisor.state = StateRegistry::global();
 
         supervisor
     }
@@ -304,7 +457,8 @@ impl Supervisor {
     fn stack(&self) -> ProcStack {
         trace!("Supervisor({}): Creating ProcStack.", self.id());
         // FIXME: with_pid
-        ProcStack::default()
+        // Supervisors run before the children they supervise.
+        ProcStack::default().with_priority(ProcPriority::High)
     }
 
     pub(crate) async fn reset(&mut self, bcast: Option<Broadcast>) {
@@ -324,6 +478,7 @@ impl Supervisor {
 
         // TODO: stop or kill?
         self.kill(0..self.order.len()).await;
+        self.kill_reason = StopReason::Killed;
 
         if let Some(bcast) = bcast {
             self.bcast = bcast;
@@ -419,8 +574,9 @@ impl Supervisor {
         let id = self.bcast.id().clone();
         let sender = self.bcast.sender().clone();
         let path = self.bcast.path().clone();
+        let state = self.state.clone();
 
-        SupervisorRef::new(id, sender, path)
+        SupervisorRef::new(id, sender, path, state)
     }
 
     /// Creates a new supervisor, passes it through the specified
@@ -856,8 +1012,9 @@ impl Supervisor {
     ///         .with_restart_policy(RestartPolicy::Tries(5))
     ///         .with_actor_restart_strategy(           
     ///             ActorRestartStrategy::ExponentialBackOff {
-    ///                 timeout: Duration::from_millis(5000),
-    ///                 multiplier: 3.0,
+    ///                 base: Duration::from_millis(500),
+    ///                 max: Duration::from_secs(30),
+    ///                 jitter: 0.2,
     ///             }
     ///         )
     /// )
@@ -878,6 +1035,186 @@ impl Supervisor {
         self
     }
 
+    /// Sets the restart intensity of this supervisor: if its
+    /// supervised elements need to be restarted more than
+    /// `max_restarts` times within `within`, the supervisor gives up
+    /// and applies its [`IntensityDecision`] instead of restarting
+    /// them (see [`with_intensity_decision`]).
+    ///
+    /// By default, a supervisor doesn't have any restart intensity
+    /// and will restart its supervised elements as long as their
+    /// [`RestartPolicy`] allows it.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_restarts` - The maximum amount of restarts allowed
+    ///     within the time window.
+    /// * `within` - The duration of the time window.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::supervisor(|sp| {
+    ///     // Give up after more than 3 restarts within 5 seconds...
+    ///     sp.with_restart_intensity(3, Duration::from_secs(5))
+    ///         // ...and let the parent supervisor handle it.
+    ///         .with_intensity_decision(IntensityDecision::Escalate)
+    /// }).expect("Couldn't create the supervisor.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`IntensityDecision`]: supervisor/enum.IntensityDecision.html
+    /// [`RestartPolicy`]: supervisor/enum.RestartPolicy.html
+    /// [`with_intensity_decision`]: #method.with_intensity_decision
+    pub fn with_restart_intensity(mut self, max_restarts: usize, within: Duration) -> Self {
+        trace!(
+            "Supervisor({}): Setting restart intensity: {} restarts within {:?}",
+            self.id(),
+            max_restarts,
+            within
+        );
+        self.restart_intensity = Some((max_restarts, within));
+        self
+    }
+
+    /// Sets what this supervisor should do once its restart
+    /// intensity was exceeded (see [`with_restart_intensity`]).
+    ///
+    /// The default decision is [`IntensityDecision::Escalate`].
+    ///
+    /// # Arguments
+    ///
+    /// * `decision` - What the supervisor should do.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::supervisor(|sp| {
+    ///     sp.with_restart_intensity(10, Duration::from_secs(60))
+    ///         .with_intensity_decision(IntensityDecision::StopSubtree)
+    /// }).expect("Couldn't create the supervisor.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`with_restart_intensity`]: #method.with_restart_intensity
+    /// [`IntensityDecision::Escalate`]: supervisor/enum.IntensityDecision.html#variant.Escalate
+    pub fn with_intensity_decision(mut self, decision: IntensityDecision) -> Self {
+        trace!(
+            "Supervisor({}): Setting intensity decision: {:?}",
+            self.id(),
+            decision
+        );
+        self.intensity_decision = decision;
+        self
+    }
+
+    /// Sets the closure deciding what the supervisor should do when
+    /// an element of one of its supervised children groups fails,
+    /// instead of applying its strategy to every kind of failure.
+    ///
+    /// The closure is given the [`ChildFailure`] describing the
+    /// failure (e.g. the typed error the element returned or the
+    /// message it panicked with) and returns a [`Directive`]. The
+    /// failures of supervised supervisors always use the strategy.
+    ///
+    /// # Arguments
+    ///
+    /// * `decider` - The closure returning what the supervisor
+    ///     should do with a failed element.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::io;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// Bastion::supervisor(|sp| {
+    ///     sp.with_decider(|failure: &ChildFailure| {
+    ///         // Retrying after I/O errors, but not after panics...
+    ///         if failure.downcast_error::<io::Error>().is_some() {
+    ///             Directive::Restart
+    ///         } else if failure.is_panic() {
+    ///             Directive::Stop
+    ///         } else {
+    ///             // ...and letting the parent handle the others.
+    ///             Directive::Escalate
+    ///         }
+    ///     })
+    /// }).expect("Couldn't create the supervisor.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`ChildFailure`]: struct.ChildFailure.html
+    /// [`Directive`]: supervisor/enum.Directive.html
+    pub fn with_decider<D>(mut self, decider: D) -> Self
+    where
+        D: Fn(&ChildFailure) -> Directive + Send + Sync + 'static,
+    {
+        trace!("Supervisor({}): Setting decider.", self.id());
+        self.decider = Some(Decider(Arc::new(decider)));
+        self
+    }
+
     /// Sets the callbacks that will get called at this supervisor's
     /// different lifecycle events.
     ///
@@ -933,6 +1270,68 @@ impl Supervisor {
         self
     }
 
+    /// Shares a value with the elements this supervisor supervises
+    /// (directly or not), which they can then retrieve using
+    /// [`BastionContext::state`], taking precedence over a value of
+    /// the same type shared by its parents or by the system.
+    ///
+    /// # Argument
+    ///
+    /// * `value` - The value to share.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// # use std::sync::Arc;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// struct Pool {
+    ///     size: usize,
+    /// }
+    ///
+    /// Bastion::supervisor(|sp| {
+    ///     sp.with_state(Pool { size: 8 })
+    ///         .children(|children| {
+    ///             children.with_exec(|ctx: BastionContext| async move {
+    ///                 let pool: Arc<Pool> = ctx.state();
+    ///                 assert_eq!(pool.size, 8);
+    ///
+    ///                 Ok(())
+    ///             })
+    ///         })
+    /// }).expect("Couldn't create the supervisor.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`BastionContext::state`]: context/struct.BastionContext.html#method.state
+    pub fn with_state<T: Send + Sync + 'static>(self, value: T) -> Self {
+        trace!(
+            "Supervisor({}): Sharing state: {}",
+            self.id(),
+            std::any::type_name::<T>()
+        );
+        self.state.insert(value);
+        self
+    }
+
     async fn restart(&mut self, objects: Vec<RestartedElement>) {
         debug!(
             "Supervisor({}): Restarting {:?} elements",
@@ -962,9 +1361,19 @@ impl Supervisor {
                         Some(tracked_state) => tracked_state,
                         None => continue,
                     };
+                    // The restart strategy configured for the child's
+                    // group takes precedence over the supervisor's one.
+                    let restart_strategy = tracked_state
+                        .state
+                        .restart_strategy()
+                        .unwrap_or(&self.restart_strategy)
+                        .clone();
+                    if let Some(reset_after) = restart_strategy.reset_after() {
+                        tracked_state.reset_restarts_counter_if_healthy(reset_after);
+                    }
                     let restarts_count = tracked_state.restarts_count();
 
-                    let restart_required = match self.restart_strategy.restart_policy() {
+                    let restart_required = match restart_strategy.restart_policy() {
                         RestartPolicy::Always => true,
                         RestartPolicy::Never => false,
                         RestartPolicy::Tries(max_retries) => restarts_count < max_retries,
@@ -972,6 +1381,9 @@ impl Supervisor {
 
                     let msg = match restart_required {
                         true => {
+                            #[cfg(feature = "metrics")]
+                            metrics::record_restart(self.bcast.path());
+
                             tracked_state.increase_restarts_counter();
                             let state = tracked_state.state();
                             BastionMessage::restore_child(id, state)
@@ -981,7 +1393,6 @@ impl Supervisor {
                             BastionMessage::drop_child(id)
                         }
                     };
-                    let restart_strategy = self.restart_strategy.clone();
 
                     restart_futures.push(async move {
                         if restart_required {
@@ -1000,6 +1411,26 @@ impl Supervisor {
         }
     }
 
+    /// Returns what to do with the failed element of a supervised
+    /// children group, according to the decider.
+    fn decide(&self, id: &BastionId, parent_id: &BastionId) -> Directive {
+        let decider = match &self.decider {
+            Some(decider) => decider,
+            None => return Directive::Restart,
+        };
+
+        let failure = self
+            .tracked_groups_order
+            .get(id)
+            .and_then(|index| self.tracked_groups.get(parent_id)?.get(*index))
+            .and_then(|tracked_state| tracked_state.state.failure());
+        match failure {
+            Some(failure) => (decider.0)(&failure),
+            // Supervised supervisors don't describe their failures.
+            None => Directive::Restart,
+        }
+    }
+
     fn remove_child(&mut self, id: &BastionId, parent_id: &BastionId) {
         let index = match self.tracked_groups_order.get(id) {
             Some(index) => *index,
@@ -1015,6 +1446,15 @@ impl Supervisor {
             let child_id = state.id.clone();
             self.tracked_groups_order.insert(child_id, new_index);
         }
+
+        #[cfg(feature = "metrics")]
+        self.update_active_children();
+    }
+
+    #[cfg(feature = "metrics")]
+    fn update_active_children(&self) {
+        let count = self.tracked_groups.values().map(Vec::len).sum();
+        metrics::set_active_children(self.bcast.path(), count);
     }
 
     async fn stop(&mut self, range: Range<usize>) {
@@ -1061,12 +1501,12 @@ impl Supervisor {
     async fn kill(&mut self, range: Range<usize>) {
         debug!("Supervisor({}): Killing range: {:?}", self.id(), range);
         if range.start == 0 {
-            self.bcast.kill_children();
+            self.bcast.kill_children_with(self.kill_reason.clone());
         } else {
             // FIXME: panics
             for id in self.order.get(range.clone()).unwrap() {
                 trace!("Supervised({}): Killing Supervised({}).", self.id(), id);
-                self.bcast.kill_child(id);
+                self.bcast.kill_child_with(id, self.kill_reason.clone());
             }
         }
 
@@ -1107,6 +1547,81 @@ impl Supervisor {
         self.bcast.faulted();
     }
 
+    // Records a new restart and returns whether the restart
+    // intensity was exceeded.
+    fn intensity_exceeded(&mut self) -> bool {
+        let (max_restarts, within) = match self.restart_intensity {
+            Some(restart_intensity) => restart_intensity,
+            None => return false,
+        };
+
+        let now = Instant::now();
+        while let Some(restart) = self.restarts_history.front() {
+            if now.duration_since(*restart) <= within {
+                break;
+            }
+
+            self.restarts_history.pop_front();
+        }
+
+        self.restarts_history.push_back(now);
+        self.restarts_history.len() > max_restarts
+    }
+
+    // Lets the parent supervisor handle the failure, which will
+    // restart this supervisor's subtree if its own supervision
+    // strategy requires it. Supervisors without a parent supervisor
+    // can't escalate and should fault instead, so that the system
+    // restarts them.
+    fn escalate(&mut self) -> Result<(), ()> {
+        let parent = match self.bcast.parent().clone().into_supervisor() {
+            Some(parent) => parent,
+            None => return Err(()),
+        };
+
+        debug!(
+            "Supervisor({}): Escalating to Supervisor({}).",
+            self.id(),
+            parent.id()
+        );
+        self.kill_reason = StopReason::Escalated;
+        let path = self.bcast.path().clone();
+        events::emit(SupervisionEvent::SupervisorEscalated { path });
+        let msg = BastionMessage::restart_required(self.id().clone(), parent.id().clone());
+        let env = Envelope::new(msg, self.bcast.path().clone(), self.bcast.sender().clone());
+        // FIXME: Err(msg)
+        self.bcast.send_parent(env).ok();
+
+        Ok(())
+    }
+
+    async fn give_up(&mut self) -> Result<(), ()> {
+        warn!(
+            "Supervisor({}): Restart intensity exceeded, applying: {:?}",
+            self.id(),
+            self.intensity_decision
+        );
+        self.restarts_history.clear();
+
+        match self.intensity_decision {
+            IntensityDecision::Escalate => {
+                if self.escalate().is_err() {
+                    self.kill(0..self.order.len()).await;
+                    self.faulted();
+
+                    return Err(());
+                }
+
+                Ok(())
+            }
+            IntensityDecision::StopSubtree => {
+                self.deinit_with_stop().await;
+
+                Err(())
+            }
+        }
+    }
+
     async fn recover(&mut self, id: BastionId, parent_id: BastionId) -> Result<(), ()> {
         debug!(
             "Supervisor({}): Recovering using strategy: {:?}",
@@ -1134,6 +1649,7 @@ impl Supervisor {
                 let objects = self.search_restarted_objects(search_method);
                 self.restart(objects).await;
             }
+            SupervisionStrategy::Escalate => self.escalate()?,
         }
 
         Ok(())
@@ -1151,22 +1667,34 @@ impl Supervisor {
                 objects.push(element)
             }
             ActorSearchMethod::FromActor { id, parent_id } => {
-                let childs = self.tracked_groups.get(&parent_id).unwrap();
-                let start_index = *self.tracked_groups_order.get(&id).unwrap();
-
-                // Adding all elements in the group from the given actor
-                childs.iter().skip(start_index).for_each(|tracked_state| {
-                    let id = tracked_state.id();
-                    let element = RestartedElement::Child {
-                        id,
-                        parent_id: parent_id.clone(),
-                    };
-                    objects.push(element)
-                });
+                let rest_index = match self.tracked_groups.get(&parent_id) {
+                    Some(childs) => {
+                        let start_index = *self.tracked_groups_order.get(&id).unwrap();
+
+                        // Adding all elements in the group from the given actor
+                        childs.iter().skip(start_index).for_each(|tracked_state| {
+                            let id = tracked_state.id();
+                            let element = RestartedElement::Child {
+                                id,
+                                parent_id: parent_id.clone(),
+                            };
+                            objects.push(element)
+                        });
+
+                        self.launched.get(&parent_id).unwrap().0
+                    }
+                    // The failed element is a supervisor which escalated.
+                    None => {
+                        objects.push(RestartedElement::Supervisor(id.clone()));
+                        match self.launched.get(&id) {
+                            Some((index, _)) => *index,
+                            None => return objects,
+                        }
+                    }
+                };
 
                 // And then a rest after the failed group
-                let (rest_index, _) = self.launched.get(&parent_id).unwrap();
-                for index in *rest_index + 1..self.order.len() {
+                for index in rest_index + 1..self.order.len() {
                     let element_id = &self.order[index];
 
                     match self.tracked_groups.get(element_id) {
@@ -1180,7 +1708,8 @@ impl Supervisor {
                             }
                         }
                         None => {
-                            let restarted_element = RestartedElement::Supervisor(id.clone());
+                            let restarted_element =
+                                RestartedElement::Supervisor(element_id.clone());
                             objects.push(restarted_element);
                         }
                     }
@@ -1248,6 +1777,7 @@ impl Supervisor {
                 children.callbacks().before_start();
                 Supervised::children(children)
             }
+            Deployment::Child { .. } => unreachable!(),
         };
 
         self.bcast.register(supervised.bcast());
@@ -1292,6 +1822,46 @@ impl Supervisor {
             warn!("Supervisor({}): Supervised({}) faulted.", self.id(), id);
         }
 
+        let directive = self.decide(&id, &parent_id);
+        debug!(
+            "Supervisor({}): Supervised({}) failed, applying: {:?}",
+            self.id(),
+            id,
+            directive
+        );
+        match directive {
+            Directive::Restart => (),
+            Directive::Stop => {
+                self.remove_child(&id, &parent_id);
+                let msg = BastionMessage::drop_child(id);
+                let env =
+                    Envelope::new(msg, self.bcast.path().clone(), self.bcast.sender().clone());
+                self.bcast.send_child(&parent_id, env);
+
+                return Ok(());
+            }
+            Directive::Escalate => {
+                if self.escalate().is_err() {
+                    self.kill(0..self.order.len()).await;
+                    self.faulted();
+
+                    return Err(());
+                }
+
+                return Ok(());
+            }
+            Directive::Resume => {
+                let objects = vec![RestartedElement::Child { id, parent_id }];
+                self.restart(objects).await;
+
+                return Ok(());
+            }
+        }
+
+        if self.intensity_exceeded() {
+            return self.give_up().await;
+        }
+
         if self.recover(id, parent_id).await.is_err() {
             // TODO: stop or kill?
             self.kill(0..self.order.len()).await;
@@ -1317,12 +1887,50 @@ impl Supervisor {
                 return Err(());
             }
             Envelope {
-                msg: BastionMessage::Kill,
+                msg: BastionMessage::Drain,
                 ..
             } => {
+                debug!("Supervisor({}): Draining.", self.id());
+                self.bcast.send_children(env);
+            }
+            Envelope {
+                msg: BastionMessage::Kill { reason },
+                ..
+            } => {
+                // The reason of an escalation outlives the kill that
+                // its parent sends in return.
+                if self.kill_reason == StopReason::Killed {
+                    self.kill_reason = reason;
+                }
+
                 self.deinit_with_kill().await;
                 return Err(());
             }
+            Envelope {
+                msg: BastionMessage::Pause,
+                ..
+            } => {
+                debug!("Supervisor({}): Pausing.", self.id());
+                self.bcast.send_children(env);
+            }
+            Envelope {
+                msg: BastionMessage::Resume,
+                ..
+            } => {
+                debug!("Supervisor({}): Resuming.", self.id());
+                self.bcast.send_children(env);
+            }
+            Envelope {
+                msg: BastionMessage::Restart,
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Reconfigure,
+                ..
+            } => {
+                debug!("Supervisor({}): Reconfiguring.", self.id());
+                self.bcast.send_children(env);
+            }
             Envelope {
                 msg: BastionMessage::Deploy(deployment),
                 ..
@@ -1367,6 +1975,9 @@ impl Supervisor {
                         self.tracked_groups_order.insert(child_id, 0);
                     }
                 }
+
+                #[cfg(feature = "metrics")]
+                self.update_active_children();
             }
             Envelope {
                 msg: BastionMessage::Message(ref message),
@@ -1419,6 +2030,22 @@ impl Supervisor {
                 msg: BastionMessage::Heartbeat,
                 ..
             } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Watch { .. },
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Unwatch { .. },
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Link { .. },
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Exit { .. },
+                ..
+            } => unreachable!(),
         }
 
         Ok(())
@@ -1497,13 +2124,23 @@ impl Supervisor {
     pub(crate) fn launch(self) -> RecoverableHandle<Self> {
         debug!("Supervisor({}): Launching.", self.id());
         let stack = self.stack();
-        pool::spawn(self.run(), stack)
+        executor::spawn_proc(self.run(), stack)
     }
 }
 
 impl SupervisorRef {
-    pub(crate) fn new(id: BastionId, sender: Sender, path: Arc<BastionPath>) -> Self {
-        SupervisorRef { id, sender, path }
+    pub(crate) fn new(
+        id: BastionId,
+        sender: Sender,
+        path: Arc<BastionPath>,
+        state: StateRegistry,
+    ) -> Self {
+        SupervisorRef {
+            id,
+            sender,
+            path,
+            state,
+        }
     }
 
     /// Returns the identifier of the supervisor this `SupervisorRef`
@@ -1547,6 +2184,19 @@ impl SupervisorRef {
         &self.id
     }
 
+    /// Returns the [`StateRegistry`] holding the values the
+    /// supervisor this `SupervisorRef` is referencing shares with
+    /// the elements it supervises (directly or not).
+    ///
+    /// Values inserted into it take precedence over the values of
+    /// the same type shared by the supervisor's parents or by the
+    /// system.
+    ///
+    /// [`StateRegistry`]: state/struct.StateRegistry.html
+    pub fn state(&self) -> &StateRegistry {
+        &self.state
+    }
+
     /// Creates a new [`Supervisor`], passes it through the specified
     /// `init` closure and then sends it to the supervisor this
     /// `SupervisorRef` is referencing to supervise it.
@@ -1717,6 +2367,125 @@ impl SupervisorRef {
         Ok(children_ref)
     }
 
+    /// Runs a future under the supervision of the supervisor this
+    /// `SupervisorRef` is referencing, restarting it when it panics
+    /// as allowed by the given restart policy.
+    ///
+    /// The future runs in a new children group (with one element)
+    /// which is stopped once the future completes or can't be
+    /// restarted anymore, so that it is stopped along with the
+    /// supervisor.
+    ///
+    /// This method returns a [`SupervisedHandle`] resolving to the
+    /// output of the future if it succeeded, or `Err(())`
+    /// otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The closure returning the future to run, which is
+    ///     called again every time the future is restarted.
+    /// * `restart_policy` - How many times the future can be
+    ///     restarted after panicking.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// # Bastion::start();
+    /// #
+    /// # let sp_ref = Bastion::supervisor(|sp| sp).unwrap();
+    /// let job = sp_ref
+    ///     .supervise_future(|| async { 6 * 7 }, RestartPolicy::Tries(3))
+    ///     .expect("Couldn't supervise the future.");
+    ///
+    /// // `None` if the future couldn't be restarted anymore...
+    /// let output: Option<u32> = run!(job);
+    /// assert_eq!(output, Some(42));
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`SupervisedHandle`]: supervisor/struct.SupervisedHandle.html
+    pub fn supervise_future<F, Fut, T>(
+        &self,
+        f: F,
+        restart_policy: RestartPolicy,
+    ) -> Result<SupervisedHandle<T>, ()>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        debug!("SupervisorRef({}): Supervising a future.", self.id());
+        let (sender, receiver) = oneshot::channel();
+        let sender = Arc::new(Mutex::new(Some(sender)));
+        let f = Arc::new(f);
+
+        let children = self.children(move |children| {
+            children.with_exec(move |ctx: BastionContext| {
+                let f = f.clone();
+                let sender = sender.clone();
+                let restart_policy = restart_policy.clone();
+                async move {
+                    let id = ctx.current().id().clone();
+                    let output = ctx
+                        .scope(|nursery| async move {
+                            let mut restarts = 0;
+                            loop {
+                                // The sub-task resolves to `None` if it panicked.
+                                if let Some(output) = nursery.spawn((*f)()).await {
+                                    return Some(output);
+                                }
+
+                                let restart = match restart_policy {
+                                    RestartPolicy::Always => true,
+                                    RestartPolicy::Never => false,
+                                    RestartPolicy::Tries(tries) => restarts < tries,
+                                };
+                                if !restart {
+                                    warn!("Child({}): Supervised future gave up.", id);
+                                    return None;
+                                }
+
+                                restarts += 1;
+                                warn!(
+                                    "Child({}): Supervised future panicked, restarting it ({}).",
+                                    id, restarts
+                                );
+                            }
+                        })
+                        .await;
+
+                    // FIXME: panics
+                    if let Some(sender) = sender.lock().unwrap().take() {
+                        sender.send(output).ok();
+                    }
+
+                    ctx.parent().stop().ok();
+                    Ok(())
+                }
+            })
+        })?;
+
+        Ok(SupervisedHandle { children, receiver })
+    }
+
     /// Sends to the supervisor this `SupervisorRef` is
     /// referencing the strategy that it should start
     /// using when one of its supervised children groups or
@@ -1943,6 +2712,103 @@ impl SupervisorRef {
         self.send(env).map_err(|_| ())
     }
 
+    /// Sends a message to the supervisor this `SupervisorRef`
+    /// is referencing to tell the elements of every children
+    /// group it supervises (recursively) to stop dequeuing
+    /// messages until [`resume`] is called.
+    ///
+    /// The messages received in the meantime are kept in the
+    /// elements' mailbox, and the elements can still be stopped
+    /// or killed while they are paused.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(())`
+    /// otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// # let sp_ref = Bastion::supervisor(|sp| sp).unwrap();
+    /// sp_ref.pause().expect("Couldn't send the message.");
+    ///
+    /// // Migrate the workers, the queued messages being kept...
+    ///
+    /// sp_ref.resume().expect("Couldn't send the message.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`resume`]: #method.resume
+    pub fn pause(&self) -> Result<(), ()> {
+        debug!("SupervisorRef({}): Pausing.", self.id());
+        let msg = BastionMessage::pause();
+        let env = Envelope::from_dead_letters(msg);
+        self.send(env).map_err(|_| ())
+    }
+
+    /// Sends a message to the supervisor this `SupervisorRef`
+    /// is referencing to tell the elements of every children
+    /// group it supervises (recursively), paused with [`pause`],
+    /// to dequeue their messages again.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(())`
+    /// otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();    
+    /// # }
+    /// #
+    /// # fn run() {
+    /// # Bastion::init();
+    /// #
+    /// # let sp_ref = Bastion::supervisor(|sp| sp).unwrap();
+    /// # sp_ref.pause().unwrap();
+    /// sp_ref.resume().expect("Couldn't send the message.");
+    /// #
+    /// # Bastion::start();
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    ///
+    /// [`pause`]: #method.pause
+    pub fn resume(&self) -> Result<(), ()> {
+        debug!("SupervisorRef({}): Resuming.", self.id());
+        let msg = BastionMessage::resume();
+        let env = Envelope::from_dead_letters(msg);
+        self.send(env).map_err(|_| ())
+    }
+
     pub(crate) fn send(&self, env: Envelope) -> Result<(), Envelope> {
         trace!("SupervisorRef({}): Sending message: {:?}", self.id(), env);
         self.sender
@@ -1961,6 +2827,7 @@ impl TrackedChildState {
             id,
             state,
             restarts_counts: 0,
+            last_restart: None,
         }
     }
 
@@ -1978,6 +2845,15 @@ impl TrackedChildState {
 
     fn increase_restarts_counter(&mut self) {
         self.restarts_counts += 1;
+        self.last_restart = Some(Instant::now());
+    }
+
+    fn reset_restarts_counter_if_healthy(&mut self, reset_after: Duration) {
+        if let Some(last_restart) = self.last_restart {
+            if last_restart.elapsed() >= reset_after {
+                self.restarts_counts = 0;
+            }
+        }
     }
 }
 
@@ -1993,7 +2869,7 @@ impl Supervised {
     fn stack(&self) -> ProcStack {
         trace!("Supervised({}): Creating ProcStack.", self.id());
         // FIXME: with_id
-        ProcStack::default()
+        ProcStack::default().with_priority(ProcPriority::High)
     }
 
     fn id(&self) -> &BastionId {
@@ -2022,7 +2898,7 @@ impl Supervised {
         let stack = self.stack();
         match self {
             Supervised::Supervisor(supervisor) => {
-                pool::spawn(
+                executor::spawn_proc(
                     async {
                         // FIXME: panics?
                         let supervisor = supervisor.launch().await.unwrap();
@@ -2032,7 +2908,7 @@ impl Supervised {
                 )
             }
             Supervised::Children(children) => {
-                pool::spawn(
+                executor::spawn_proc(
                     async {
                         // FIXME: panics?
                         let children = children.launch().await.unwrap();
@@ -2065,7 +2941,8 @@ impl RestartStrategy {
     ///     - [`ActorRestartStrategy::LinearBackOff`] would restart the
     ///         failed actor with the delay increasing linearly.
     ///     - [`ActorRestartStrategy::ExponentialBackOff`] would restart the
-    ///         failed actor with the delay, multiplied by given coefficient.
+    ///         failed actor with the delay doubling on each restart, up to
+    ///         a maximum delay.
     ///
     /// # Example
     ///
@@ -2090,6 +2967,7 @@ impl RestartStrategy {
         RestartStrategy {
             restart_policy,
             strategy,
+            reset_after: None,
         }
     }
 
@@ -2104,6 +2982,12 @@ impl RestartStrategy {
         self.strategy.clone()
     }
 
+    /// Returns the period after which a restarted actor that didn't
+    /// fail again gets its restarts counter reset, if any.
+    pub fn reset_after(&self) -> Option<Duration> {
+        self.reset_after
+    }
+
     /// Sets the limit of attempts for restoring failed actors.
     pub fn with_restart_policy(mut self, restart_policy: RestartPolicy) -> Self {
         self.restart_policy = restart_policy;
@@ -2118,9 +3002,38 @@ impl RestartStrategy {
         self
     }
 
+    /// Sets the period after which a restarted actor that didn't
+    /// fail again is considered healthy, resetting its restarts
+    /// counter (and thus the delay computed by the actor restart
+    /// strategy, and the amount of tries left with
+    /// [`RestartPolicy::Tries`]).
+    ///
+    /// By default, the restarts counter is never reset.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use bastion::prelude::*;
+    /// #
+    /// let restart_strategy = RestartStrategy::default()
+    ///     .with_actor_restart_strategy(ActorRestartStrategy::ExponentialBackOff {
+    ///         base: Duration::from_millis(100),
+    ///         max: Duration::from_secs(10),
+    ///         jitter: 0.2,
+    ///     })
+    ///     .with_reset_after(Duration::from_secs(60));
+    /// ```
+    ///
+    /// [`RestartPolicy::Tries`]: enum.RestartPolicy.html#variant.Tries
+    pub fn with_reset_after(mut self, reset_after: Duration) -> Self {
+        self.reset_after = Some(reset_after);
+        self
+    }
+
     pub(crate) async fn apply_strategy(&self, restarts_count: usize) {
         if let Some(dur) = self.strategy.calculate(restarts_count) {
-            Delay::new(dur).await;
+            executor::sleep(dur).await;
         }
     }
 }
@@ -2131,11 +3044,18 @@ impl Default for SupervisionStrategy {
     }
 }
 
+impl Default for IntensityDecision {
+    fn default() -> Self {
+        IntensityDecision::Escalate
+    }
+}
+
 impl Default for RestartStrategy {
     fn default() -> Self {
         RestartStrategy {
             restart_policy: RestartPolicy::Always,
             strategy: ActorRestartStrategy::default(),
+            reset_after: None,
         }
     }
 }
@@ -2146,6 +3066,46 @@ impl Default for ActorRestartStrategy {
     }
 }
 
+impl Debug for Decider {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Decider").finish()
+    }
+}
+
+impl<T> SupervisedHandle<T> {
+    /// Returns the children group running the supervised future.
+    pub fn children(&self) -> &ChildrenRef {
+        &self.children
+    }
+
+    /// Stops the supervised future, making this handle resolve to
+    /// `None` if it didn't complete yet.
+    ///
+    /// This method returns `()` if it succeeded, or `Err(())`
+    /// otherwise.
+    pub fn stop(&self) -> Result<(), ()> {
+        self.children.stop()
+    }
+}
+
+impl<T> Future for SupervisedHandle<T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver)
+            .poll(ctx)
+            .map(|output| output.ok().flatten())
+    }
+}
+
+impl<T> Debug for SupervisedHandle<T> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("SupervisedHandle")
+            .field("children", &self.children)
+            .finish()
+    }
+}
+
 impl PartialEq for SupervisorRef {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
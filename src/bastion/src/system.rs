@@ -1,38 +1,64 @@
 use crate::broadcast::{Broadcast, Parent, Sender};
+use crate::callbacks::{ChildFailure, StateBag};
+use crate::child_ref::ChildRef;
 use crate::children_ref::ChildrenRef;
 use crate::context::{BastionContext, BastionId, NIL_ID};
+use crate::dead_letters::{DeadLetter, DeadLettersState};
 use crate::dispatcher::GlobalDispatcher;
 use crate::envelope::Envelope;
+use crate::executor;
 use crate::message::{BastionMessage, Deployment};
 use crate::path::{BastionPath, BastionPathElement};
 use crate::supervisor::{Supervisor, SupervisorRef};
+use crate::watch::StopReason;
 use async_mutex::Mutex as AsyncMutex;
-use bastion_executor::pool;
 use futures::prelude::*;
 use futures::stream::FuturesUnordered;
 use futures::{pending, poll};
 use fxhash::{FxHashMap, FxHashSet};
 use lazy_static::lazy_static;
 use lightproc::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
 use std::task::Poll;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, trace, warn};
 
 lazy_static! {
     pub(crate) static ref SYSTEM: GlobalSystem = System::init();
 }
 
+/// Returns whether the envelope contains a new message that is
+/// refused because the system is shutting down gracefully.
+pub(crate) fn refuses(env: &Envelope) -> bool {
+    matches!(env.msg, BastionMessage::Message(_)) && SYSTEM.is_draining()
+}
+
 pub(crate) struct GlobalSystem {
     sender: Sender,
     supervisor: SupervisorRef,
     dead_letters: ChildrenRef,
+    dead_letters_state: Arc<DeadLettersState>,
     path: Arc<BastionPath>,
     handle: Arc<AsyncMutex<Option<RecoverableHandle<()>>>>,
     running: Mutex<bool>,
     stopping_cvar: Condvar,
+    // Whether the system is shutting down gracefully, refusing
+    // new messages while the children drain their mailboxes.
+    draining: AtomicBool,
+    // The number of children that are running, excluding the
+    // dead letters actor.
+    running_children: Mutex<usize>,
+    drained_cvar: Condvar,
     dispatcher: GlobalDispatcher,
+    // The running children that were named by their children
+    // group, by name.
+    names: Mutex<FxHashMap<String, ChildRef>>,
 }
 
+/// Counts a child as running until it is dropped.
+pub(crate) struct RunningChild(());
+
 #[derive(Debug)]
 struct System {
     bcast: Broadcast,
@@ -50,6 +76,7 @@ impl GlobalSystem {
         sender: Sender,
         supervisor: SupervisorRef,
         dead_letters: ChildrenRef,
+        dead_letters_state: Arc<DeadLettersState>,
         handle: RecoverableHandle<()>,
     ) -> Self {
         let handle = Some(handle);
@@ -57,17 +84,26 @@ impl GlobalSystem {
         let path = Arc::new(BastionPath::root());
         let running = Mutex::new(true);
         let stopping_cvar = Condvar::new();
+        let draining = AtomicBool::new(false);
+        let running_children = Mutex::new(0);
+        let drained_cvar = Condvar::new();
         let dispatcher = GlobalDispatcher::new();
+        let names = Mutex::new(FxHashMap::default());
 
         GlobalSystem {
             sender,
             supervisor,
             dead_letters,
+            dead_letters_state,
             path,
             handle,
             running,
             stopping_cvar,
+            draining,
+            running_children,
+            drained_cvar,
             dispatcher,
+            names,
         }
     }
 
@@ -83,6 +119,10 @@ impl GlobalSystem {
         &self.dead_letters
     }
 
+    pub(crate) fn dead_letters_state(&self) -> &Arc<DeadLettersState> {
+        &self.dead_letters_state
+    }
+
     pub(crate) fn handle(&self) -> Arc<AsyncMutex<Option<RecoverableHandle<()>>>> {
         self.handle.clone()
     }
@@ -95,6 +135,28 @@ impl GlobalSystem {
         &self.dispatcher
     }
 
+    /// Makes the child findable using its name, replacing the child
+    /// that was registered with the same name if any.
+    pub(crate) fn register_name(&self, name: String, child_ref: ChildRef) {
+        // FIXME: panics
+        self.names.lock().unwrap().insert(name, child_ref);
+    }
+
+    /// Stops making the child findable using its name, unless
+    /// another child was registered with the same name since.
+    pub(crate) fn unregister_name(&self, name: &str, id: &BastionId) {
+        // FIXME: panics
+        let mut names = self.names.lock().unwrap();
+        if names.get(name).map(ChildRef::id) == Some(id) {
+            names.remove(name);
+        }
+    }
+
+    pub(crate) fn child_by_name(&self, name: &str) -> Option<ChildRef> {
+        // FIXME: panics
+        self.names.lock().unwrap().get(name).cloned()
+    }
+
     pub(crate) fn notify_stopped(&self) {
         // FIXME: panics
         *self.running.lock().unwrap() = false;
@@ -108,6 +170,53 @@ impl GlobalSystem {
             running = self.stopping_cvar.wait(running).unwrap();
         }
     }
+
+    pub(crate) fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn running_child(&self) -> RunningChild {
+        // FIXME: panics
+        *self.running_children.lock().unwrap() += 1;
+        RunningChild(())
+    }
+
+    /// Blocks until every child stopped or the timeout elapsed,
+    /// returning whether every child stopped.
+    pub(crate) fn wait_until_drained(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        // FIXME: panics
+        let mut running = self.running_children.lock().unwrap();
+        while *running > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+
+            running = self
+                .drained_cvar
+                .wait_timeout(running, deadline - now)
+                .unwrap()
+                .0;
+        }
+
+        true
+    }
+}
+
+impl Drop for RunningChild {
+    fn drop(&mut self) {
+        // FIXME: panics
+        let mut running = SYSTEM.running_children.lock().unwrap();
+        *running -= 1;
+        if *running == 0 {
+            SYSTEM.drained_cvar.notify_all();
+        }
+    }
 }
 
 impl System {
@@ -149,25 +258,47 @@ impl System {
 
         debug!("System: Launching.");
         let stack = system.stack();
-        let handle = pool::spawn(system.run(), stack);
+        let handle = executor::spawn_proc(system.run(), stack);
 
+        let dead_letters_state = Arc::new(DeadLettersState::default());
         let dead_letters_ref =
-            Self::spawn_dead_letters(&supervisor_ref).expect("Can't spawn dead letters");
+            Self::spawn_dead_letters(&supervisor_ref, dead_letters_state.clone())
+                .expect("Can't spawn dead letters");
 
-        GlobalSystem::new(sender, supervisor_ref, dead_letters_ref, handle)
+        GlobalSystem::new(
+            sender,
+            supervisor_ref,
+            dead_letters_ref,
+            dead_letters_state,
+            handle,
+        )
     }
 
     fn stack(&self) -> ProcStack {
         // FIXME: with_id
-        ProcStack::default()
+        ProcStack::default().with_priority(ProcPriority::High)
     }
 
-    fn spawn_dead_letters(root_sv: &SupervisorRef) -> Result<ChildrenRef, ()> {
-        root_sv.children_with_id(NIL_ID, |children| {
-            children.with_exec(|ctx: BastionContext| async move {
-                loop {
-                    let smsg = ctx.recv().await?;
-                    debug!("Received dead letter: {:?}", smsg);
+    fn spawn_dead_letters(
+        root_sv: &SupervisorRef,
+        state: Arc<DeadLettersState>,
+    ) -> Result<ChildrenRef, ()> {
+        root_sv.children_with_id(NIL_ID, move |children| {
+            children.with_exec(move |ctx: BastionContext| {
+                let state = state.clone();
+                async move {
+                    loop {
+                        let smsg = ctx.recv().await?;
+                        debug!("Received dead letter: {:?}", smsg);
+                        // Messages that are directly sent to the dead letters
+                        // (e.g. answers to anonymous messages) are dead letters
+                        // too, but they aren't wrapped in a `DeadLetter`.
+                        let letter = match smsg.msg.downcast_ref::<DeadLetter>() {
+                            Some(letter) => letter,
+                            None => Arc::new(DeadLetter::new(ctx.current().path().clone(), smsg)),
+                        };
+                        state.record(letter);
+                    }
                 }
             })
         })
@@ -176,7 +307,11 @@ impl System {
     // TODO: set a limit?
     async fn recover(&mut self, mut supervisor: Supervisor) {
         warn!("System: Recovering Supervisor({}).", supervisor.id());
-        supervisor.callbacks().before_restart();
+        // The supervisor is reset in place, so its callbacks only
+        // share a bag with each other.
+        let state = StateBag::new();
+        let failure = ChildFailure::new(supervisor.bcast().path().clone(), StopReason::Failed, 0);
+        supervisor.callbacks().before_restart(&state, &failure);
 
         let parent = Parent::system();
         let bcast = if supervisor.id() == &NIL_ID {
@@ -189,7 +324,9 @@ impl System {
         };
 
         supervisor.reset(bcast).await;
-        supervisor.callbacks().after_restart();
+        supervisor
+            .callbacks()
+            .after_restart(&state, &failure.restarted());
 
         self.bcast.register(supervisor.bcast());
 
@@ -222,8 +359,8 @@ impl System {
         }
     }
 
-    async fn kill(&mut self) {
-        self.bcast.kill_children();
+    async fn kill(&mut self, reason: StopReason) {
+        self.bcast.kill_children_with(reason);
 
         for launched in self.waiting.iter_mut() {
             launched.cancel();
@@ -270,6 +407,7 @@ impl System {
             }
             // FIXME
             Deployment::Children(_) => unimplemented!(),
+            Deployment::Child { .. } => unreachable!(),
         }
     }
 
@@ -309,14 +447,40 @@ impl System {
                 return Err(());
             }
             Envelope {
-                msg: BastionMessage::Kill,
+                msg: BastionMessage::Drain,
+                ..
+            } => {
+                info!("System: Draining.");
+                self.bcast.send_children(env);
+            }
+            Envelope {
+                msg: BastionMessage::Kill { reason },
                 ..
             } => {
                 info!("System: Killing.");
-                self.kill().await;
+                self.kill(reason).await;
 
                 return Err(());
             }
+            Envelope {
+                msg: BastionMessage::Pause,
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Resume,
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Restart,
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Reconfigure,
+                ..
+            } => {
+                info!("System: Reconfiguring.");
+                self.bcast.send_children(env);
+            }
             Envelope {
                 msg: BastionMessage::Deploy(deployment),
                 ..
@@ -381,6 +545,22 @@ impl System {
                 msg: BastionMessage::Heartbeat,
                 ..
             } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Watch { .. },
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Unwatch { .. },
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Link { .. },
+                ..
+            } => unreachable!(),
+            Envelope {
+                msg: BastionMessage::Exit { .. },
+                ..
+            } => unreachable!(),
         }
 
         Ok(())
@@ -0,0 +1,1087 @@
+//!
+//! Helpers to test the children of a system.
+//!
+//! A [`TestProbe`] is a child which records the messages it
+//! receives, so that a test can hand its [`ChildRef`] to the
+//! children it tests and then assert which messages they sent to it
+//! (and answer the ones they asked).
+//!
+//! A [`DeterministicExecutor`] (installed by [`Bastion::init_test`])
+//! runs the whole system on a single thread, polling the processes
+//! woken up in an order picked using a seed, so that a failing
+//! interleaving of messages can be replayed from its seed. Its
+//! timers run in virtual time, which elapses whenever no process is
+//! ready to run.
+//!
+//! A [`TestClock`] drives the timers of the system in virtual time,
+//! only elapsing when the tests advance it.
+//!
+//! [`TestProbe`]: struct.TestProbe.html
+//! [`TestClock`]: struct.TestClock.html
+//! [`ChildRef`]: ../child_ref/struct.ChildRef.html
+//! [`DeterministicExecutor`]: struct.DeterministicExecutor.html
+//! [`Bastion::init_test`]: ../struct.Bastion.html#method.init_test
+use crate::bastion::Bastion;
+use crate::child_ref::ChildRef;
+use crate::children_ref::ChildrenRef;
+use crate::context::BastionContext;
+use crate::envelope::SignedMessage;
+use crate::executor::{self, AgnosticExecutor};
+use crate::message::{Message, Msg};
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use futures::future::{self, BoxFuture, Either};
+use futures::task::{waker, ArcWake};
+use futures::StreamExt;
+use lazy_static::lazy_static;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::any::type_name;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{debug, error};
+
+// How long the executor waits for another thread to wake up one of
+// its tasks before advancing its clock to the next timer.
+const IDLE_GRACE: Duration = Duration::from_millis(1);
+
+/// A child recording the messages it receives, whose reference can
+/// be handed to the children being tested.
+///
+/// The probe runs in its own children group, which is stopped when
+/// the probe is dropped.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use bastion::testing::TestProbe;
+/// # use std::time::Duration;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// Bastion::init();
+/// Bastion::start();
+///
+/// let mut probe = TestProbe::new();
+/// // Answers the numbers it is asked with their successor...
+/// probe.reply_with(|n: &u32| n + 1);
+///
+/// let peer = probe.child_ref().clone();
+/// Bastion::children(|children| {
+///     children.with_exec(move |ctx: BastionContext| {
+///         let peer = peer.clone();
+///         async move {
+///             ctx.tell(&peer.addr(), 42u8).ok();
+///             let answer = ctx.ask(&peer.addr(), 1u32).unwrap().await?;
+///             msg! { answer,
+///                 n: u32 => assert_eq!(n, 2);
+///                 _: _ => ();
+///             }
+///
+///             Ok(())
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+///
+/// assert_eq!(run!(probe.expect_msg::<u8>(Duration::from_secs(1))), 42);
+/// assert_eq!(run!(probe.expect_msg::<u32>(Duration::from_secs(1))), 1);
+/// run!(probe.expect_no_msg(Duration::from_millis(50)));
+/// #
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub struct TestProbe {
+    children: ChildrenRef,
+    child: ChildRef,
+    received: UnboundedReceiver<SignedMessage>,
+    replies: Arc<Mutex<Vec<Reply>>>,
+}
+
+// Answers the asked messages of a type, returning whether it
+// answered the message.
+struct Reply(Box<dyn Fn(&mut Msg, &BastionContext) -> bool + Send + Sync>);
+
+impl TestProbe {
+    /// Creates a new probe, in a new children group of the system.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the children group couldn't be created (e.g. if
+    /// the system wasn't initialized).
+    pub fn new() -> Self {
+        let (sender, received) = mpsc::unbounded();
+        let replies: Arc<Mutex<Vec<Reply>>> = Arc::default();
+
+        let replies_inner = replies.clone();
+        let children = Bastion::children(move |children| {
+            children.with_exec(move |ctx: BastionContext| {
+                let sender = sender.clone();
+                let replies = replies_inner.clone();
+                async move {
+                    loop {
+                        let mut msg = ctx.recv().await?;
+                        debug!("TestProbe: Received a message: {:?}", msg);
+                        // FIXME: panics
+                        for reply in replies.lock().unwrap().iter() {
+                            if (reply.0)(&mut msg.msg, &ctx) {
+                                break;
+                            }
+                        }
+
+                        if sender.unbounded_send(msg).is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+            })
+        })
+        .expect("TestProbe: Couldn't create the children group.");
+        let child = children.elems()[0].clone();
+
+        TestProbe {
+            children,
+            child,
+            received,
+            replies,
+        }
+    }
+
+    /// Returns a reference to the probe, to send messages to it.
+    pub fn child_ref(&self) -> &ChildRef {
+        &self.child
+    }
+
+    /// Answers the messages of type `M` asked to the probe with the
+    /// output of the closure (the messages are still recorded).
+    ///
+    /// If many closures answer the same type, only the first one
+    /// registered is called.
+    ///
+    /// # Argument
+    ///
+    /// * `reply` - The closure returning the answer to an asked
+    ///     message.
+    pub fn reply_with<M, R, F>(&mut self, reply: F)
+    where
+        M: Message,
+        R: Message,
+        F: Fn(&M) -> R + Send + Sync + 'static,
+    {
+        let reply = Reply(Box::new(move |msg: &mut Msg, ctx: &BastionContext| {
+            if !msg.is_ask() {
+                return false;
+            }
+
+            let answer = match msg.tell_or_ask_ref::<M>() {
+                Some(msg) => reply(msg),
+                None => return false,
+            };
+
+            match msg.take_responder() {
+                Some(responder) => responder.respond(answer, ctx).is_ok(),
+                None => false,
+            }
+        }));
+
+        // FIXME: panics
+        self.replies.lock().unwrap().push(reply);
+    }
+
+    /// Waits for the next message received by the probe, which
+    /// should be a message of type `M` that was told or asked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no message was received before the timeout, or if
+    /// the message isn't of type `M`.
+    ///
+    /// # Argument
+    ///
+    /// * `timeout` - How long to wait for the message.
+    pub async fn expect_msg<M: Message>(&mut self, timeout: Duration) -> M {
+        let msg = match self.next(timeout).await {
+            Some(msg) => msg,
+            None => panic!(
+                "TestProbe: Timed out waiting for a message of type {}.",
+                type_name::<M>()
+            ),
+        };
+
+        match msg.msg.downcast::<M>() {
+            Ok(msg) => msg,
+            Err(msg) => panic!(
+                "TestProbe: Expected a message of type {}, received: {:?}",
+                type_name::<M>(),
+                msg
+            ),
+        }
+    }
+
+    /// Waits for the next message received by the probe, which
+    /// should be a message of type `M` that was broadcasted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no message was received before the timeout, or if
+    /// the message isn't a broadcast of type `M`.
+    ///
+    /// # Argument
+    ///
+    /// * `timeout` - How long to wait for the message.
+    pub async fn expect_broadcast<M: Message>(&mut self, timeout: Duration) -> Arc<M> {
+        let msg = match self.next(timeout).await {
+            Some(msg) => msg,
+            None => panic!(
+                "TestProbe: Timed out waiting for a broadcast of type {}.",
+                type_name::<M>()
+            ),
+        };
+
+        match msg.msg.downcast_ref::<M>() {
+            Some(msg) => msg,
+            None => panic!(
+                "TestProbe: Expected a broadcast of type {}, received: {:?}",
+                type_name::<M>(),
+                msg.msg
+            ),
+        }
+    }
+
+    /// Waits for the next message received by the probe, whatever
+    /// its type, returning `None` if none was received before the
+    /// timeout.
+    ///
+    /// # Argument
+    ///
+    /// * `timeout` - How long to wait for the message.
+    pub async fn receive(&mut self, timeout: Duration) -> Option<SignedMessage> {
+        self.next(timeout).await
+    }
+
+    /// Checks that the probe doesn't receive any message during the
+    /// given duration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a message was received.
+    ///
+    /// # Argument
+    ///
+    /// * `duration` - How long to wait.
+    pub async fn expect_no_msg(&mut self, duration: Duration) {
+        if let Some(msg) = self.next(duration).await {
+            panic!("TestProbe: Expected no message, received: {:?}", msg.msg);
+        }
+    }
+
+    async fn next(&mut self, timeout: Duration) -> Option<SignedMessage> {
+        match future::select(self.received.next(), executor::sleep(timeout)).await {
+            Either::Left((msg, _)) => msg,
+            Either::Right(_) => None,
+        }
+    }
+}
+
+impl Default for TestProbe {
+    fn default() -> Self {
+        TestProbe::new()
+    }
+}
+
+impl Drop for TestProbe {
+    fn drop(&mut self) {
+        self.children.stop().ok();
+    }
+}
+
+impl Debug for TestProbe {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("TestProbe")
+            .field("child", &self.child)
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+/// An executor running every process of the system on a single
+/// thread, installed by [`Bastion::init_test`].
+///
+/// Every time it polls a process, the executor picks one of the
+/// processes that were woken up using a random number generator
+/// seeded with its seed. As long as the children don't depend on
+/// other threads (e.g. the messages sent by the test itself while
+/// they run), running the same test with the same seed thus
+/// interleaves their messages the same way.
+///
+/// The timers of the system (e.g. the timeouts of receives and
+/// asks) run on the executor's virtual [`clock`]: once no process
+/// is ready to run (and none was woken up by another thread within
+/// a millisecond), the clock jumps to the deadline of the next
+/// timer. A test thus never waits for the real time to elapse.
+///
+/// Blocking tasks also run on the executor's thread, blocking the
+/// other processes until they complete. The thread runs until
+/// [`shutdown`] is called.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use bastion::testing::DeterministicExecutor;
+/// # use futures::FutureExt;
+/// # use std::sync::{Arc, Mutex};
+/// # use std::time::Duration;
+/// #
+/// let executor = DeterministicExecutor::new(42);
+/// let order = Arc::new(Mutex::new(Vec::new()));
+///
+/// // Queues the tasks before letting the executor pick them...
+/// executor.pause();
+/// for n in 0..3 {
+///     let order = order.clone();
+///     executor.spawn(async move { order.lock().unwrap().push(n) }.boxed());
+/// }
+///
+/// executor.resume();
+/// assert!(executor.wait_until_idle(Duration::from_secs(1)));
+/// assert_eq!(order.lock().unwrap().len(), 3);
+///
+/// executor.shutdown();
+/// ```
+///
+/// [`Bastion::init_test`]: ../struct.Bastion.html#method.init_test
+/// [`clock`]: #method.clock
+/// [`shutdown`]: #method.shutdown
+pub struct DeterministicExecutor {
+    scheduler: Arc<Scheduler>,
+    // The scheduler's thread, until it is joined.
+    thread: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+struct Scheduler {
+    seed: u64,
+    state: Mutex<SchedulerState>,
+    changed: Condvar,
+    // The virtual clock of the executor's timers.
+    clock: TestClock,
+}
+
+struct SchedulerState {
+    rng: StdRng,
+    tasks: HashMap<u64, Task>,
+    // The ids of the tasks that were woken up, in the order they
+    // were woken up in.
+    ready: Vec<u64>,
+    next_id: u64,
+    // Whether the executor stopped picking tasks, whether it
+    // is running one, and whether it was shut down.
+    paused: bool,
+    running: bool,
+    stopped: bool,
+    polls: u64,
+}
+
+enum Task {
+    Future(BoxFuture<'static, ()>),
+    Blocking(Box<dyn FnOnce() + Send>),
+}
+
+// Wakes up a task by marking it as ready.
+struct TaskWaker {
+    scheduler: Arc<Scheduler>,
+    id: u64,
+}
+
+impl DeterministicExecutor {
+    /// Creates a new executor picking the tasks it runs using the
+    /// given seed, and starts its thread.
+    ///
+    /// # Argument
+    ///
+    /// * `seed` - The seed of the order the tasks are picked in.
+    pub fn new(seed: u64) -> Self {
+        let scheduler = Arc::new(Scheduler {
+            seed,
+            state: Mutex::new(SchedulerState {
+                rng: StdRng::seed_from_u64(seed),
+                tasks: HashMap::new(),
+                ready: Vec::new(),
+                next_id: 0,
+                paused: false,
+                running: false,
+                stopped: false,
+                polls: 0,
+            }),
+            changed: Condvar::new(),
+            clock: TestClock::default(),
+        });
+
+        let runner = scheduler.clone();
+        let thread = thread::Builder::new()
+            .name("bastion-deterministic".to_string())
+            .spawn(move || runner.run())
+            .expect("DeterministicExecutor: Couldn't spawn the thread.");
+
+        DeterministicExecutor {
+            scheduler,
+            thread: Arc::new(Mutex::new(Some(thread))),
+        }
+    }
+
+    /// Returns the seed of the order the tasks are picked in, to
+    /// replay a test with [`Bastion::init_test_with_seed`].
+    ///
+    /// [`Bastion::init_test_with_seed`]: ../struct.Bastion.html#method.init_test_with_seed
+    pub fn seed(&self) -> u64 {
+        self.scheduler.seed
+    }
+
+    /// Returns how many times the executor ran a task.
+    pub fn polls(&self) -> u64 {
+        self.scheduler.lock().polls
+    }
+
+    /// Returns the virtual clock the executor's timers are driven
+    /// by, which can also be advanced explicitly.
+    pub fn clock(&self) -> &TestClock {
+        &self.scheduler.clock
+    }
+
+    /// Stops running tasks (once the current one returns) until
+    /// [`resume`] is called. The tasks woken up meanwhile are
+    /// queued.
+    ///
+    /// [`resume`]: #method.resume
+    pub fn pause(&self) {
+        self.scheduler.lock().paused = true;
+    }
+
+    /// Resumes running the tasks after [`pause`] was called.
+    ///
+    /// [`pause`]: #method.pause
+    pub fn resume(&self) {
+        self.scheduler.lock().paused = false;
+        self.scheduler.changed.notify_all();
+    }
+
+    /// Blocks the current thread until no task is running or
+    /// waiting to run (the tasks waiting for a timer or another
+    /// thread aren't), or until the timeout elapsed.
+    ///
+    /// This method returns whether the executor became idle.
+    ///
+    /// # Argument
+    ///
+    /// * `timeout` - How long to wait for the executor.
+    pub fn wait_until_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.scheduler.lock();
+        while state.running || (!state.ready.is_empty() && !state.paused) {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+
+            // FIXME: panics
+            state = self
+                .scheduler
+                .changed
+                .wait_timeout(state, deadline - now)
+                .unwrap()
+                .0;
+        }
+
+        state.ready.is_empty()
+    }
+
+    /// Stops the executor once the task it is running returns, and
+    /// waits for its thread to exit. The pending tasks are dropped,
+    /// and the tasks spawned afterwards are never run.
+    ///
+    /// Calling this method from one of the executor's tasks only
+    /// stops the executor, without waiting for its thread.
+    pub fn shutdown(&self) {
+        self.scheduler.lock().stopped = true;
+        self.scheduler.changed.notify_all();
+
+        // FIXME: panics
+        let thread = self.thread.lock().unwrap().take();
+        if let Some(thread) = thread {
+            if thread.thread().id() == thread::current().id() {
+                return;
+            }
+
+            if thread.join().is_err() {
+                error!("DeterministicExecutor: The thread panicked.");
+            }
+        }
+
+        // Dropping the tasks outside of the lock, as they might
+        // wake up other ones.
+        let tasks = std::mem::take(&mut self.scheduler.lock().tasks);
+        debug!("DeterministicExecutor: Dropping {} tasks.", tasks.len());
+        drop(tasks);
+    }
+}
+
+impl Scheduler {
+    // FIXME: panics
+    fn lock(&self) -> MutexGuard<SchedulerState> {
+        self.state.lock().unwrap()
+    }
+
+    fn push(&self, task: Task) {
+        let mut state = self.lock();
+        if state.stopped {
+            debug!("DeterministicExecutor: Dropping a task spawned after the shutdown.");
+            return;
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.tasks.insert(id, task);
+        state.ready.push(id);
+        drop(state);
+
+        self.changed.notify_all();
+    }
+
+    fn wake(&self, id: u64) {
+        let mut state = self.lock();
+        if !state.ready.contains(&id) {
+            state.ready.push(id);
+        }
+        drop(state);
+
+        self.changed.notify_all();
+    }
+
+    /// Runs the tasks woken up, until the executor is shut down.
+    fn run(self: Arc<Self>) {
+        loop {
+            let (id, task) = {
+                let mut state = self.lock();
+                while !state.stopped && (state.paused || state.ready.is_empty()) {
+                    if state.paused || self.clock.pending_timers() == 0 {
+                        // FIXME: panics
+                        state = self.changed.wait(state).unwrap();
+                        continue;
+                    }
+
+                    // FIXME: panics
+                    let (guard, waited) = self.changed.wait_timeout(state, IDLE_GRACE).unwrap();
+                    state = guard;
+                    // Nothing is ready to run: time flies to the next
+                    // timer (waking it up takes the lock).
+                    if waited.timed_out() && state.ready.is_empty() && !state.paused {
+                        drop(state);
+                        self.clock.advance_to_next();
+                        state = self.lock();
+                    }
+                }
+
+                if state.stopped {
+                    debug!("DeterministicExecutor: Shut down.");
+                    return;
+                }
+
+                let idx = state.rng.gen_range(0..state.ready.len());
+                let id = state.ready.swap_remove(idx);
+                // The task might have completed since it was woken up.
+                let task = match state.tasks.remove(&id) {
+                    Some(task) => task,
+                    None => continue,
+                };
+
+                state.running = true;
+                state.polls += 1;
+                (id, task)
+            };
+
+            // A task panicking mustn't stop the other ones.
+            let pending = panic::catch_unwind(AssertUnwindSafe(|| self.poll(id, task)));
+            let mut state = self.lock();
+            match pending {
+                Ok(Some(task)) => {
+                    state.tasks.insert(id, task);
+                }
+                Ok(None) => (),
+                Err(_) => error!("DeterministicExecutor: Task {} panicked.", id),
+            }
+
+            state.running = false;
+            drop(state);
+            self.changed.notify_all();
+        }
+    }
+
+    // Runs the task once, returning it if it is still pending.
+    fn poll(self: &Arc<Self>, id: u64, task: Task) -> Option<Task> {
+        match task {
+            Task::Blocking(task) => {
+                task();
+                None
+            }
+            Task::Future(mut future) => {
+                let waker = waker(Arc::new(TaskWaker {
+                    scheduler: self.clone(),
+                    id,
+                }));
+                let mut ctx = Context::from_waker(&waker);
+                match future.as_mut().poll(&mut ctx) {
+                    Poll::Ready(()) => None,
+                    Poll::Pending => Some(Task::Future(future)),
+                }
+            }
+        }
+    }
+}
+
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.scheduler.wake(arc_self.id);
+    }
+}
+
+impl AgnosticExecutor for DeterministicExecutor {
+    fn spawn(&self, task: BoxFuture<'static, ()>) {
+        self.scheduler.push(Task::Future(task));
+    }
+
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+        self.scheduler.push(Task::Blocking(task));
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        let sleep = self.scheduler.clock.sleep(duration);
+        // The idle scheduler now has a timer to advance its clock to.
+        self.scheduler.changed.notify_all();
+        Box::pin(sleep)
+    }
+}
+
+impl Debug for DeterministicExecutor {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("DeterministicExecutor")
+            .field("seed", &self.scheduler.seed)
+            .field("clock", &self.scheduler.clock)
+            .finish()
+    }
+}
+
+/// A virtual clock driving the timers of the system, installed
+/// using [`TestClock::install`] and advanced explicitly by the
+/// tests, so that timer-heavy logic can be tested without sleeping.
+///
+/// Once installed, the timers scheduled using
+/// [`BastionContext::schedule_once`] and
+/// [`BastionContext::schedule_interval`], the timeouts of asks and
+/// receives, and the backoffs of restarts and retries only elapse
+/// when the clock is advanced.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// # use bastion::testing::TestClock;
+/// # use std::time::Duration;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// let clock = TestClock::install();
+/// Bastion::init();
+/// Bastion::start();
+///
+/// Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| async move {
+///         ctx.schedule_once(Duration::from_secs(3600), "an hour later");
+///         msg! { ctx.recv().await?,
+///             msg: &'static str => {
+///                 // Received once the clock is advanced...
+///             };
+///             _: _ => ();
+///         }
+///
+///         Ok(())
+///     })
+/// }).expect("Couldn't create the children group.");
+///
+/// # while clock.pending_timers() == 0 {
+/// #     std::thread::sleep(Duration::from_millis(1));
+/// # }
+/// clock.advance(Duration::from_secs(3600));
+/// #
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`TestClock::install`]: #method.install
+/// [`BastionContext::schedule_once`]: ../context/struct.BastionContext.html#method.schedule_once
+/// [`BastionContext::schedule_interval`]: ../context/struct.BastionContext.html#method.schedule_interval
+#[derive(Clone, Default)]
+pub struct TestClock {
+    inner: Arc<Mutex<ClockState>>,
+}
+
+#[derive(Default)]
+struct ClockState {
+    elapsed: Duration,
+    // The deadlines of the sleeping futures and the tasks waiting
+    // for them, by id.
+    sleepers: HashMap<u64, (Duration, Option<Waker>)>,
+    next_id: u64,
+}
+
+/// A future resolving once a [`TestClock`] was advanced past its
+/// deadline.
+///
+/// [`TestClock`]: struct.TestClock.html
+pub struct Sleep {
+    clock: TestClock,
+    id: u64,
+    deadline: Duration,
+}
+
+lazy_static! {
+    // The clock the timers of the system are driven by, if one
+    // was installed.
+    static ref CLOCK: RwLock<Option<TestClock>> = RwLock::new(None);
+}
+
+impl TestClock {
+    /// Installs a new clock as the one driving the timers of the
+    /// system (replacing the one installed before, if any), and
+    /// returns it.
+    pub fn install() -> Self {
+        let clock = TestClock::default();
+        // FIXME: panics
+        *CLOCK.write().unwrap() = Some(clock.clone());
+
+        clock
+    }
+
+    /// Stops driving the timers of the system using a clock,
+    /// letting them use the real time again. The timers which were
+    /// waiting for the clock keep waiting for it.
+    pub fn uninstall() {
+        // FIXME: panics
+        CLOCK.write().unwrap().take();
+    }
+
+    /// Advances the clock, waking up the timers whose deadline it
+    /// reached.
+    ///
+    /// # Argument
+    ///
+    /// * `duration` - How much time elapses.
+    pub fn advance(&self, duration: Duration) {
+        let wakers: Vec<_> = {
+            let mut state = self.lock();
+            state.elapsed += duration;
+            let elapsed = state.elapsed;
+            state
+                .sleepers
+                .values_mut()
+                .filter(|(deadline, _)| *deadline <= elapsed)
+                .filter_map(|(_, waker)| waker.take())
+                .collect()
+        };
+
+        debug!(
+            "TestClock: Advanced by {:?}, waking up {} timers.",
+            duration,
+            wakers.len()
+        );
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Advances the clock to the deadline of the next timer which
+    /// didn't elapse yet, returning whether there is one.
+    fn advance_to_next(&self) -> bool {
+        let next = {
+            let state = self.lock();
+            state
+                .sleepers
+                .values()
+                .map(|(deadline, _)| *deadline)
+                .filter(|deadline| *deadline > state.elapsed)
+                .min()
+                .map(|deadline| deadline - state.elapsed)
+        };
+
+        match next {
+            Some(duration) => {
+                self.advance(duration);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns how much time elapsed on the clock since it was
+    /// created.
+    pub fn elapsed(&self) -> Duration {
+        self.lock().elapsed
+    }
+
+    /// Returns how many timers are waiting for the clock.
+    pub fn pending_timers(&self) -> usize {
+        self.lock().sleepers.len()
+    }
+
+    /// Returns a future resolving once the clock was advanced by
+    /// `duration`.
+    ///
+    /// # Argument
+    ///
+    /// * `duration` - How long to sleep.
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        let mut state = self.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        let deadline = state.elapsed + duration;
+        state.sleepers.insert(id, (deadline, None));
+
+        Sleep {
+            clock: self.clone(),
+            id,
+            deadline,
+        }
+    }
+
+    // FIXME: panics
+    fn lock(&self) -> MutexGuard<ClockState> {
+        self.inner.lock().unwrap()
+    }
+}
+
+/// Returns the clock driving the timers of the system, if one was
+/// installed.
+pub(crate) fn clock() -> Option<TestClock> {
+    // FIXME: panics
+    CLOCK.read().unwrap().clone()
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let mut state = self.clock.lock();
+        if state.elapsed >= self.deadline {
+            state.sleepers.remove(&self.id);
+            return Poll::Ready(());
+        }
+
+        if let Some((_, waker)) = state.sleepers.get_mut(&self.id) {
+            *waker = Some(ctx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        self.clock.lock().sleepers.remove(&self.id);
+    }
+}
+
+impl Debug for TestClock {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let state = self.lock();
+        fmt.debug_struct("TestClock")
+            .field("elapsed", &state.elapsed)
+            .field("pending_timers", &state.sleepers.len())
+            .finish()
+    }
+}
+
+impl Debug for Sleep {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Sleep")
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    fn order(seed: u64) -> Vec<usize> {
+        let executor = DeterministicExecutor::new(seed);
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        executor.pause();
+        for n in 0..32 {
+            let order = order.clone();
+            executor.spawn(async move { order.lock().unwrap().push(n) }.boxed());
+        }
+
+        executor.resume();
+        assert!(executor.wait_until_idle(Duration::from_secs(5)));
+        assert_eq!(executor.polls(), 32);
+        executor.shutdown();
+
+        let order = order.lock().unwrap();
+        order.clone()
+    }
+
+    // Returns the order in which a consumer receives the messages of
+    // producers which sleep and yield between their messages.
+    fn messages(seed: u64) -> Vec<(usize, usize)> {
+        let executor = DeterministicExecutor::new(seed);
+        let (sender, mut receiver) = mpsc::unbounded();
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        executor.pause();
+        for producer in 0..4 {
+            let sender = sender.clone();
+            let sleep = executor.sleep(Duration::from_secs(3600));
+            executor.spawn(
+                async move {
+                    sleep.await;
+                    for n in 0..8 {
+                        sender.unbounded_send((producer, n)).unwrap();
+                        let mut yielded = false;
+                        future::poll_fn(|ctx| {
+                            if yielded {
+                                return Poll::Ready(());
+                            }
+
+                            yielded = true;
+                            ctx.waker().wake_by_ref();
+                            Poll::Pending
+                        })
+                        .await;
+                    }
+                }
+                .boxed(),
+            );
+        }
+        drop(sender);
+
+        let received_inner = received.clone();
+        executor.spawn(
+            async move {
+                while let Some(msg) = receiver.next().await {
+                    received_inner.lock().unwrap().push(msg);
+                }
+            }
+            .boxed(),
+        );
+        executor.resume();
+
+        // The hour the producers sleep elapses in virtual time.
+        for _ in 0..100 {
+            if received.lock().unwrap().len() == 32 {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(executor.clock().elapsed() >= Duration::from_secs(3600));
+        executor.shutdown();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 32);
+        received.clone()
+    }
+
+    #[test]
+    fn sleeps_until_the_clock_is_advanced() {
+        let waker = futures::task::noop_waker();
+        let mut ctx = Context::from_waker(&waker);
+
+        let clock = TestClock::default();
+        let mut sleep = clock.sleep(Duration::from_secs(10));
+        assert!(Pin::new(&mut sleep).poll(&mut ctx).is_pending());
+        assert_eq!(clock.pending_timers(), 1);
+
+        clock.advance(Duration::from_secs(9));
+        assert!(Pin::new(&mut sleep).poll(&mut ctx).is_pending());
+        clock.advance(Duration::from_secs(1));
+        assert!(Pin::new(&mut sleep).poll(&mut ctx).is_ready());
+        assert_eq!(clock.pending_timers(), 0);
+        assert_eq!(clock.elapsed(), Duration::from_secs(10));
+
+        // Dropping a sleeping future unregisters it.
+        drop(clock.sleep(Duration::from_secs(1)));
+        assert_eq!(clock.pending_timers(), 0);
+    }
+
+    #[test]
+    fn replays_the_order_of_a_seed() {
+        let replayed = order(7);
+        assert_eq!(replayed.len(), 32);
+        assert_eq!(order(7), replayed);
+    }
+
+    #[test]
+    fn replays_the_messages_of_a_seed() {
+        let replayed = messages(7);
+        assert_eq!(messages(7), replayed);
+    }
+
+    #[test]
+    fn advances_to_the_next_timer() {
+        let clock = TestClock::default();
+        assert!(!clock.advance_to_next());
+
+        let _later = clock.sleep(Duration::from_secs(20));
+        let _sooner = clock.sleep(Duration::from_secs(10));
+        assert!(clock.advance_to_next());
+        assert_eq!(clock.elapsed(), Duration::from_secs(10));
+        assert!(clock.advance_to_next());
+        assert_eq!(clock.elapsed(), Duration::from_secs(20));
+        assert!(!clock.advance_to_next());
+    }
+
+    #[test]
+    fn drops_the_tasks_once_shut_down() {
+        let executor = DeterministicExecutor::new(7);
+        executor.shutdown();
+
+        let ran = Arc::new(Mutex::new(false));
+        let ran_inner = ran.clone();
+        executor.spawn(async move { *ran_inner.lock().unwrap() = true }.boxed());
+        assert!(executor.wait_until_idle(Duration::from_secs(1)));
+        assert!(!*ran.lock().unwrap());
+
+        // Shutting down again doesn't wait for anything.
+        executor.shutdown();
+    }
+}
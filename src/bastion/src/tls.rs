@@ -0,0 +1,243 @@
+//! The TLS streams used by the connections between nodes when
+//! [`RemotingConfig::with_tls`] was used.
+//!
+//! A connection is read and written from different threads, so a
+//! stream can be cloned, every clone sharing the same TLS session.
+//! Reading never writes to the socket: the records the session has
+//! to send in response (e.g. alerts) are sent with the next write.
+//! The session is only locked to encrypt and decrypt the data, never
+//! while waiting for the socket.
+//!
+//! [`RemotingConfig::with_tls`]: ../remote/struct.RemotingConfig.html#method.with_tls
+use rustls::{
+    ClientConfig, ClientConnection, Connection, ServerConfig, ServerConnection, ServerName,
+};
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+/// The size of the buffer the encrypted data is read into.
+const READ_BUF_LEN: usize = 16 * 1024;
+
+pub(crate) struct TlsStream {
+    session: Arc<Mutex<Connection>>,
+    // Held while the encrypted records are written to the socket,
+    // so that the ones of different writes don't interleave.
+    writing: Arc<Mutex<()>>,
+    sock: TcpStream,
+}
+
+impl TlsStream {
+    /// Opens a TLS session with the node that accepted the
+    /// connection, verifying its certificate against the name.
+    pub(crate) fn connect(
+        sock: TcpStream,
+        config: Arc<ClientConfig>,
+        name: ServerName,
+    ) -> io::Result<Self> {
+        let session = ClientConnection::new(config, name).map_err(invalid_data)?;
+        TlsStream::handshake(sock, session.into())
+    }
+
+    /// Opens a TLS session with the node that opened the connection,
+    /// which fails if it didn't start a TLS handshake.
+    pub(crate) fn accept(sock: TcpStream, config: Arc<ServerConfig>) -> io::Result<Self> {
+        let session = ServerConnection::new(config).map_err(invalid_data)?;
+        TlsStream::handshake(sock, session.into())
+    }
+
+    fn handshake(mut sock: TcpStream, mut session: Connection) -> io::Result<Self> {
+        while session.is_handshaking() {
+            session.complete_io(&mut sock)?;
+        }
+
+        Ok(TlsStream {
+            session: Arc::new(Mutex::new(session)),
+            writing: Arc::new(Mutex::new(())),
+            sock,
+        })
+    }
+
+    /// Returns a stream sharing the same TLS session.
+    pub(crate) fn try_clone(&self) -> io::Result<Self> {
+        Ok(TlsStream {
+            session: self.session.clone(),
+            writing: self.writing.clone(),
+            sock: self.sock.try_clone()?,
+        })
+    }
+
+    pub(crate) fn get_ref(&self) -> &TcpStream {
+        &self.sock
+    }
+
+    /// Writes the records the session has to send to the socket,
+    /// after encrypting them without keeping the session locked
+    /// while writing.
+    fn write_records<F, T>(&mut self, encrypt: F) -> io::Result<T>
+    where
+        F: FnOnce(&mut Connection) -> io::Result<T>,
+    {
+        let _writing = self.writing.lock().unwrap();
+        let mut records = Vec::new();
+        let res = {
+            let mut session = self.session.lock().unwrap();
+            let res = encrypt(&mut session)?;
+            while session.wants_write() {
+                session.write_tls(&mut records)?;
+            }
+
+            res
+        };
+
+        self.sock.write_all(&records)?;
+        Ok(res)
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut encrypted = [0; READ_BUF_LEN];
+        loop {
+            match self.session.lock().unwrap().reader().read(buf) {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => (),
+                res => return res,
+            }
+
+            // The session isn't locked while waiting for the data, to
+            // allow writing in the meantime.
+            let len = self.sock.read(&mut encrypted)?;
+            if len == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            let mut session = self.session.lock().unwrap();
+            let mut data = &encrypted[..len];
+            while !data.is_empty() {
+                session.read_tls(&mut data)?;
+                session.process_new_packets().map_err(invalid_data)?;
+            }
+        }
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_records(|session| session.writer().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_records(|session| session.writer().flush())?;
+        self.sock.flush()
+    }
+}
+
+fn invalid_data(err: rustls::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::{Certificate, PrivateKey, RootCertStore};
+    use std::convert::TryFrom;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn configs() -> (Arc<ServerConfig>, Arc<ClientConfig>) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let der = Certificate(cert.serialize_der().unwrap());
+        let key = PrivateKey(cert.serialize_private_key_der());
+
+        let server = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(vec![der.clone()], key)
+            .unwrap();
+
+        let mut roots = RootCertStore::empty();
+        roots.add(&der).unwrap();
+        let client = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        (Arc::new(server), Arc::new(client))
+    }
+
+    #[test]
+    fn exchanges_encrypted_data() {
+        let (server, client) = configs();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let peer = thread::spawn(move || {
+            let (sock, _) = listener.accept().unwrap();
+            let mut stream = TlsStream::accept(sock, server).unwrap();
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).unwrap();
+            stream.write_all(b"pong").unwrap();
+            buf
+        });
+
+        let sock = TcpStream::connect(addr).unwrap();
+        let name = ServerName::try_from("localhost").unwrap();
+        let mut stream = TlsStream::connect(sock, client, name).unwrap();
+        let mut reader = stream.try_clone().unwrap();
+        stream.write_all(b"ping").unwrap();
+
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"pong");
+        assert_eq!(&peer.join().unwrap(), b"ping");
+    }
+
+    #[test]
+    fn reads_while_writing() {
+        // Enough data for both sides to fill the socket buffers, so
+        // that each side only finishes writing once it read what the
+        // other one wrote.
+        const LEN: usize = 8 * 1024 * 1024;
+
+        fn exchange(mut stream: TlsStream) -> usize {
+            let mut reader = stream.try_clone().unwrap();
+            let read = thread::spawn(move || {
+                let mut buf = vec![0; LEN];
+                reader.read_exact(&mut buf).unwrap();
+                buf.iter().filter(|byte| **byte == 1).count()
+            });
+
+            stream.write_all(&vec![1; LEN]).unwrap();
+            stream.flush().unwrap();
+            read.join().unwrap()
+        }
+
+        let (server, client) = configs();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let peer = thread::spawn(move || {
+            let (sock, _) = listener.accept().unwrap();
+            exchange(TlsStream::accept(sock, server).unwrap())
+        });
+
+        let sock = TcpStream::connect(addr).unwrap();
+        let name = ServerName::try_from("localhost").unwrap();
+        let stream = TlsStream::connect(sock, client, name).unwrap();
+        assert_eq!(exchange(stream), LEN);
+        assert_eq!(peer.join().unwrap(), LEN);
+    }
+
+    #[test]
+    fn rejects_plaintext() {
+        let (server, _) = configs();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let peer = thread::spawn(move || {
+            let (sock, _) = listener.accept().unwrap();
+            TlsStream::accept(sock, server).is_err()
+        });
+
+        let mut sock = TcpStream::connect(addr).unwrap();
+        sock.write_all(b"\0\0\0\x05hello").unwrap();
+        assert!(peer.join().unwrap());
+    }
+}
@@ -0,0 +1,530 @@
+//!
+//! Snapshots of the supervision tree, describing the supervisors,
+//! the children groups they supervise and the state of their
+//! elements, that can be serialized (e.g. to expose the live tree
+//! from a health endpoint).
+//!
+//! Snapshots are taken using [`Bastion::tree`].
+//!
+//! [`Bastion::tree`]: ../struct.Bastion.html#method.tree
+use crate::child_ref::ChildRef;
+use crate::children_ref::ChildrenRef;
+use crate::context::{BastionId, ContextState};
+use crate::path::BastionPath;
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::fmt::Write;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+lazy_static! {
+    static ref REGISTRY: Mutex<Registry> = Mutex::new(Registry::default());
+}
+
+#[derive(Default)]
+// The launched children groups and their elements.
+struct Registry {
+    groups: FxHashMap<BastionId, Group>,
+    children: FxHashMap<BastionId, Child>,
+}
+
+struct Group {
+    path: Arc<BastionPath>,
+    name: String,
+    children_ref: ChildrenRef,
+}
+
+struct Child {
+    child_ref: ChildRef,
+    name: Option<String>,
+    state: Arc<Pin<Box<ContextState>>>,
+    status: ChildStatus,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+/// A snapshot of the supervision tree, returned by
+/// [`Bastion::tree`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// Bastion::init();
+/// Bastion::start();
+///
+/// let tree: TreeSnapshot = Bastion::tree();
+/// for supervisor in &tree.supervisors {
+///     println!("Supervisor {}: {} groups", supervisor.id, supervisor.children.len());
+/// }
+///
+/// let json = serde_json::to_string(&tree).unwrap();
+/// #
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+///
+/// [`Bastion::tree`]: ../struct.Bastion.html#method.tree
+pub struct TreeSnapshot {
+    /// The supervisors at the root of the tree.
+    pub supervisors: Vec<SupervisorSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// A snapshot of a supervisor, part of a [`TreeSnapshot`].
+///
+/// [`TreeSnapshot`]: struct.TreeSnapshot.html
+pub struct SupervisorSnapshot {
+    /// The identifier of the supervisor.
+    pub id: String,
+    /// The supervisors it supervises.
+    pub supervisors: Vec<SupervisorSnapshot>,
+    /// The children groups it supervises.
+    pub children: Vec<ChildrenSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// A snapshot of a children group, part of a [`TreeSnapshot`].
+///
+/// [`TreeSnapshot`]: struct.TreeSnapshot.html
+pub struct ChildrenSnapshot {
+    /// The identifier of the children group.
+    pub id: String,
+    /// The name of the children group.
+    pub name: String,
+    /// The elements of the children group.
+    pub elems: Vec<ChildSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// A snapshot of an element of a children group, part of a
+/// [`TreeSnapshot`].
+///
+/// [`TreeSnapshot`]: struct.TreeSnapshot.html
+pub struct ChildSnapshot {
+    /// The identifier of the child.
+    pub id: String,
+    /// The name of the child, if its children group names its
+    /// elements.
+    pub name: Option<String>,
+    /// Whether the child is running, restarting or stopped.
+    pub status: ChildStatus,
+    /// How many times the child was restarted.
+    pub restarts: usize,
+    /// How many messages are waiting in the child's mailbox.
+    pub mailbox_len: usize,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+/// The state of a child in a [`TreeSnapshot`].
+///
+/// [`TreeSnapshot`]: struct.TreeSnapshot.html
+pub enum ChildStatus {
+    /// The child is running.
+    Running,
+    /// The child failed and waits to be restarted.
+    Restarting,
+    /// The child stopped and will be removed from its children
+    /// group.
+    Stopped,
+}
+
+impl TreeSnapshot {
+    pub(crate) fn new() -> Self {
+        // FIXME: panics
+        let registry = REGISTRY.lock().unwrap();
+
+        let mut elems = FxHashMap::<BastionId, Vec<ChildSnapshot>>::default();
+        for (id, child) in &registry.children {
+            let group_id = match child.child_ref.path().iter().rev().nth(1) {
+                Some(group_id) => group_id.clone(),
+                None => continue,
+            };
+
+            elems.entry(group_id).or_default().push(ChildSnapshot {
+                id: id.to_string(),
+                name: child.name.clone(),
+                status: child.status,
+                restarts: child.state.restarts(),
+                mailbox_len: child.state.mailbox().len(),
+            });
+        }
+
+        let mut tree = TreeSnapshot::default();
+        for (id, group) in &registry.groups {
+            let mut supervisors = group
+                .path
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            // The last element of the path is the group itself.
+            supervisors.pop();
+            let (root, rest) = match supervisors.split_first() {
+                Some(supervisors) => supervisors,
+                None => continue,
+            };
+
+            let mut supervisor = SupervisorSnapshot::find(&mut tree.supervisors, root);
+            for id in rest {
+                supervisor = SupervisorSnapshot::find(&mut supervisor.supervisors, id);
+            }
+
+            let mut elems = elems.remove(id).unwrap_or_default();
+            elems.sort_by(|a, b| a.id.cmp(&b.id));
+            supervisor.children.push(ChildrenSnapshot {
+                id: id.to_string(),
+                name: group.name.clone(),
+                elems,
+            });
+        }
+
+        tree
+    }
+
+    /// Renders the tree in the Graphviz DOT language, with a box
+    /// for every supervisor and children group and an ellipse for
+    /// every child.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    /// Bastion::start();
+    ///
+    /// // Can be rendered using `dot -Tsvg`.
+    /// let dot = Bastion::tree().to_dot();
+    /// assert!(dot.starts_with("digraph"));
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph bastion {\n");
+        for supervisor in &self.supervisors {
+            supervisor.write_dot(&mut dot);
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the tree as a Mermaid flowchart, with a box for
+    /// every supervisor and children group and a rounded box for
+    /// every child.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bastion::prelude::*;
+    /// #
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # #[cfg(not(feature = "tokio-runtime"))]
+    /// # fn main() {
+    /// #    run();
+    /// # }
+    /// #
+    /// # fn run() {
+    /// Bastion::init();
+    /// Bastion::start();
+    ///
+    /// let mermaid = Bastion::tree().to_mermaid();
+    /// assert!(mermaid.starts_with("graph TD"));
+    /// #
+    /// # Bastion::stop();
+    /// # Bastion::block_until_stopped();
+    /// # }
+    /// ```
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("graph TD\n");
+        for supervisor in &self.supervisors {
+            supervisor.write_mermaid(&mut mermaid);
+        }
+
+        mermaid
+    }
+}
+
+impl SupervisorSnapshot {
+    /// Returns the supervisor with the identifier, adding it to the
+    /// supervisors if it isn't part of them yet.
+    fn find<'a>(supervisors: &'a mut Vec<SupervisorSnapshot>, id: &str) -> &'a mut Self {
+        let index = match supervisors
+            .iter()
+            .position(|supervisor| supervisor.id == id)
+        {
+            Some(index) => index,
+            None => {
+                supervisors.push(SupervisorSnapshot {
+                    id: id.to_string(),
+                    supervisors: Vec::new(),
+                    children: Vec::new(),
+                });
+                supervisors.len() - 1
+            }
+        };
+
+        &mut supervisors[index]
+    }
+
+    fn write_dot(&self, dot: &mut String) {
+        let label = format!("supervisor\n{}", self.id);
+        writeln!(
+            dot,
+            "    \"{}\" [label={}, shape=box];",
+            self.id,
+            dot_str(&label)
+        )
+        .ok();
+
+        for supervisor in &self.supervisors {
+            writeln!(dot, "    \"{}\" -> \"{}\";", self.id, supervisor.id).ok();
+            supervisor.write_dot(dot);
+        }
+
+        for children in &self.children {
+            writeln!(dot, "    \"{}\" -> \"{}\";", self.id, children.id).ok();
+            let label = dot_str(&children.name);
+            writeln!(dot, "    \"{}\" [label={}, shape=box];", children.id, label).ok();
+
+            for child in &children.elems {
+                writeln!(dot, "    \"{}\" -> \"{}\";", children.id, child.id).ok();
+                let label = dot_str(&child.label("\n"));
+                writeln!(dot, "    \"{}\" [label={}];", child.id, label).ok();
+            }
+        }
+    }
+
+    fn write_mermaid(&self, mermaid: &mut String) {
+        let id = mermaid_id(&self.id);
+        let label = mermaid_str(&format!("supervisor<br/>{}", self.id));
+        writeln!(mermaid, "    {}[{}]", id, label).ok();
+
+        for supervisor in &self.supervisors {
+            writeln!(mermaid, "    {} --> {}", id, mermaid_id(&supervisor.id)).ok();
+            supervisor.write_mermaid(mermaid);
+        }
+
+        for children in &self.children {
+            let children_id = mermaid_id(&children.id);
+            writeln!(mermaid, "    {} --> {}", id, children_id).ok();
+            let label = mermaid_str(&children.name);
+            writeln!(mermaid, "    {}[{}]", children_id, label).ok();
+
+            for child in &children.elems {
+                let child_id = mermaid_id(&child.id);
+                writeln!(mermaid, "    {} --> {}", children_id, child_id).ok();
+                let label = mermaid_str(&child.label("<br/>"));
+                writeln!(mermaid, "    {}({})", child_id, label).ok();
+            }
+        }
+    }
+}
+
+impl ChildSnapshot {
+    /// Returns the name (or identifier) of the child, its status,
+    /// restarts count and mailbox depth, separated by `separator`.
+    fn label(&self, separator: &str) -> String {
+        let name = self.name.as_deref().unwrap_or(&self.id);
+        format!(
+            "{}{}{:?}{}restarts: {}, mailbox: {}",
+            name, separator, self.status, separator, self.restarts, self.mailbox_len,
+        )
+    }
+}
+
+/// Returns the label as a quoted DOT string.
+fn dot_str(label: &str) -> String {
+    let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+    // Keeping the line breaks as DOT line breaks.
+    format!("\"{}\"", label.replace('\n', "\\n"))
+}
+
+/// Returns an identifier that can be used as a Mermaid node id.
+fn mermaid_id(id: &str) -> String {
+    let id = id.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+    format!("n_{}", id)
+}
+
+/// Returns the label as a quoted Mermaid string.
+fn mermaid_str(label: &str) -> String {
+    format!("\"{}\"", label.replace('"', "#quot;"))
+}
+
+pub(crate) fn register_group(children_ref: ChildrenRef, name: String) {
+    let path = children_ref.path().clone();
+    let id = path.id().clone();
+    let group = Group {
+        path,
+        name,
+        children_ref,
+    };
+    // FIXME: panics
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.groups.insert(id, group);
+}
+
+/// Returns a reference to the launched children group with the
+/// given path (e.g. `/<supervisor id>/<group id>`).
+pub(crate) fn group_by_path(path: &str) -> Option<ChildrenRef> {
+    // FIXME: panics
+    REGISTRY
+        .lock()
+        .unwrap()
+        .groups
+        .values()
+        .find(|group| group.path.to_string() == path)
+        .map(|group| group.children_ref.clone())
+}
+
+pub(crate) fn unregister_group(id: &BastionId) {
+    // FIXME: panics
+    REGISTRY.lock().unwrap().groups.remove(id);
+}
+
+/// Adds the child to the tree as a running child, if it receives
+/// user defined messages.
+pub(crate) fn register_child(
+    child_ref: &ChildRef,
+    name: Option<String>,
+    state: Arc<Pin<Box<ContextState>>>,
+) {
+    if !child_ref.is_public() {
+        return;
+    }
+
+    let child = Child {
+        child_ref: child_ref.clone(),
+        name,
+        state,
+        status: ChildStatus::Running,
+    };
+    // FIXME: panics
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.children.insert(child_ref.id().clone(), child);
+}
+
+pub(crate) fn set_child_status(id: &BastionId, status: ChildStatus) {
+    // FIXME: panics
+    if let Some(child) = REGISTRY.lock().unwrap().children.get_mut(id) {
+        child.status = status;
+    }
+}
+
+pub(crate) fn unregister_child(id: &BastionId) {
+    // FIXME: panics
+    REGISTRY.lock().unwrap().children.remove(id);
+}
+
+/// Returns the children that are running, with their name if they
+/// have one.
+pub(crate) fn running_children() -> Vec<(ChildRef, Option<String>)> {
+    // FIXME: panics
+    REGISTRY
+        .lock()
+        .unwrap()
+        .children
+        .values()
+        .filter(|child| child.status == ChildStatus::Running)
+        .map(|child| (child.child_ref.clone(), child.name.clone()))
+        .collect()
+}
+
+/// Returns the running elements of the children group.
+pub(crate) fn running_elems(group: &BastionId) -> Vec<ChildRef> {
+    running_children()
+        .into_iter()
+        .map(|(child_ref, _)| child_ref)
+        .filter(|child_ref| child_ref.path().iter().rev().nth(1) == Some(group))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> TreeSnapshot {
+        let child = ChildSnapshot {
+            id: "c-1".to_string(),
+            name: Some("worker \"0\"".to_string()),
+            status: ChildStatus::Restarting,
+            restarts: 2,
+            mailbox_len: 5,
+        };
+        let children = ChildrenSnapshot {
+            id: "g-1".to_string(),
+            name: "workers".to_string(),
+            elems: vec![child],
+        };
+        let supervisor = SupervisorSnapshot {
+            id: "s-2".to_string(),
+            supervisors: Vec::new(),
+            children: vec![children],
+        };
+
+        TreeSnapshot {
+            supervisors: vec![SupervisorSnapshot {
+                id: "s-1".to_string(),
+                supervisors: vec![supervisor],
+                children: Vec::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn renders_dot() {
+        let dot = tree().to_dot();
+        assert!(dot.starts_with("digraph bastion {\n"));
+        assert!(dot.contains("    \"s-1\" -> \"s-2\";\n"));
+        assert!(dot.contains("    \"s-2\" -> \"g-1\";\n"));
+        assert!(dot.contains("    \"g-1\" -> \"c-1\";\n"));
+        assert!(dot.contains(
+            "    \"c-1\" [label=\"worker \\\"0\\\"\\nRestarting\\nrestarts: 2, mailbox: 5\"];\n"
+        ));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn renders_mermaid() {
+        let mermaid = tree().to_mermaid();
+        assert!(mermaid.starts_with("graph TD\n"));
+        assert!(mermaid.contains("    n_s_1 --> n_s_2\n"));
+        assert!(mermaid.contains("    n_g_1[\"workers\"]\n"));
+        assert!(mermaid.contains(
+            "    n_c_1(\"worker #quot;0#quot;<br/>Restarting<br/>restarts: 2, mailbox: 5\")\n"
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+//!
+//! Watching a child allows to be notified when it stops or
+//! crashes, similarly to Erlang's monitors.
+//!
+//! A child that called [`BastionContext::watch`] will receive a
+//! [`Terminated`] message once the watched child terminates (once
+//! per termination: a restarted child isn't watched anymore).
+//!
+//! Linking two children with [`ChildRef::link`] goes further: once
+//! one of them terminates, the other one is stopped too, unless it
+//! traps exits (see [`Children::with_trap_exits`]), in which case it
+//! receives an [`ExitSignal`] message instead.
+//!
+//! [`BastionContext::watch`]: ../context/struct.BastionContext.html#method.watch
+//! [`Terminated`]: struct.Terminated.html
+//! [`ChildRef::link`]: ../child_ref/struct.ChildRef.html#method.link
+//! [`Children::with_trap_exits`]: ../children/struct.Children.html#method.with_trap_exits
+//! [`ExitSignal`]: struct.ExitSignal.html
+use crate::path::BastionPath;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The reason why a child terminated, given to the children watching
+/// or linked to it, to the callback defined using
+/// [`Callbacks::with_after_stop_reason`] and with the
+/// [`SupervisionEvent::ChildStopped`] events.
+///
+/// [`Callbacks::with_after_stop_reason`]: ../struct.Callbacks.html#method.with_after_stop_reason
+/// [`SupervisionEvent::ChildStopped`]: ../events/enum.SupervisionEvent.html#variant.ChildStopped
+pub enum StopReason {
+    /// The child's future finished executing successfully or it
+    /// was stopped.
+    Normal,
+    /// The child's future returned an error.
+    Failed,
+    /// The child's future panicked, with the panic's message if it
+    /// was a string.
+    Panic(Option<String>),
+    /// The child was killed.
+    Killed,
+    /// The child was killed because its supervisor escalated a
+    /// failure to its own supervisor.
+    Escalated,
+    /// The child was killed because it didn't stop before the
+    /// deadline of [`Bastion::shutdown_gracefully`].
+    ///
+    /// [`Bastion::shutdown_gracefully`]: ../struct.Bastion.html#method.shutdown_gracefully
+    ShutdownTimeout,
+    /// The child was stopped because a child it was linked to
+    /// terminated.
+    Linked,
+    /// The child was already terminated when it was watched or
+    /// linked.
+    Unreachable,
+    /// The connection the child was serving was lost.
+    Disconnected,
+    /// The child was killed because it didn't answer the
+    /// heartbeats of its group (see [`Children::with_heartbeat`])
+    /// before their timeout.
+    ///
+    /// [`Children::with_heartbeat`]: ../children/struct.Children.html#method.with_heartbeat
+    Stalled,
+}
+
+#[derive(Debug, Clone)]
+/// The message received by the children watching a child that
+/// terminated, containing the path of the terminated child and the
+/// reason why it terminated.
+///
+/// Its signature is the one of the terminated child.
+///
+/// # Example
+///
+/// ```rust
+/// # use bastion::prelude::*;
+/// #
+/// # #[cfg(feature = "tokio-runtime")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// #    run();
+/// # }
+/// #
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {
+/// #    run();
+/// # }
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// #
+/// let peers = Bastion::children(|children| {
+///     children.with_exec(|ctx: BastionContext| async move {
+///         // ...
+///         # Ok(())
+///     })
+/// }).expect("Couldn't create the children group.");
+///
+/// Bastion::children(|children| {
+///     children.with_exec(move |ctx: BastionContext| {
+///         let peer = peers.elems()[0].clone();
+///         async move {
+///             ctx.watch(&peer);
+///
+///             msg! { ctx.recv().await?,
+///                 msg: Terminated => {
+///                     let Terminated(path, reason) = msg;
+///                     println!("{} terminated: {:?}", path, reason);
+///                 };
+///                 _: _ => ();
+///             }
+///
+///             Ok(())
+///         }
+///     })
+/// }).expect("Couldn't create the children group.");
+/// #
+/// # Bastion::start();
+/// # Bastion::stop();
+/// # Bastion::block_until_stopped();
+/// # }
+/// ```
+pub struct Terminated(pub Arc<BastionPath>, pub StopReason);
+
+#[derive(Debug, Clone)]
+/// The message received by a child trapping exits (see
+/// [`Children::with_trap_exits`]) when a child it was linked to
+/// terminated, containing the path of the terminated child and the
+/// reason why it terminated.
+///
+/// Its signature is the one of the terminated child.
+///
+/// [`Children::with_trap_exits`]: ../children/struct.Children.html#method.with_trap_exits
+pub struct ExitSignal(pub Arc<BastionPath>, pub StopReason);
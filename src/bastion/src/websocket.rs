@@ -0,0 +1,523 @@
+//!
+//! The WebSocket integration runs every WebSocket connection as a
+//! supervised child, and is available with the `websocket` feature.
+//!
+//! A [`WebSocketServer`] serves every connection it accepts with a
+//! new children group (like a [`TcpServer`] does), and a
+//! [`WebSocketClient`] connects to a server from a new children
+//! group. Either way, the element of the group gets its connection
+//! using [`BastionContext::websocket`], and receives the text and
+//! binary frames sent by its peer in its mailbox, as [`WsFrame`]
+//! messages.
+//!
+//! The connections are kept alive by the runtime, which answers the
+//! peer's pings and pings it when it stays silent. When the
+//! connection is lost (or when the peer stops answering the pings),
+//! the element is stopped with [`StopReason::Disconnected`]. When
+//! the element fails, it is restarted and keeps the same connection.
+//!
+//! [`WebSocketServer`]: struct.WebSocketServer.html
+//! [`TcpServer`]: ../net/struct.TcpServer.html
+//! [`WebSocketClient`]: struct.WebSocketClient.html
+//! [`BastionContext::websocket`]: ../context/struct.BastionContext.html#method.websocket
+//! [`WsFrame`]: enum.WsFrame.html
+//! [`StopReason::Disconnected`]: ../watch/enum.StopReason.html#variant.Disconnected
+use crate::child::{Exec, Init};
+use crate::child_ref::ChildRef;
+use crate::children::Children;
+use crate::children_ref::ChildrenRef;
+use crate::context::BastionContext;
+use crate::errors::ChildError;
+use crate::net::{TcpConnection, TcpServer};
+use crate::supervisor::SupervisorRef;
+use crate::watch::StopReason;
+use crate::Bastion;
+use async_tungstenite::tungstenite::client::IntoClientRequest;
+use async_tungstenite::tungstenite::{Error as WsError, Message as WsMessage};
+use async_tungstenite::WebSocketStream;
+use futures::future::{self, Either};
+use futures::lock::Mutex;
+use futures::prelude::*;
+use futures::stream::{SplitSink, SplitStream};
+use futures_timer::Delay;
+use nuclei::Handle;
+use std::fmt::{self, Debug, Formatter};
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tracing::{debug, trace, warn};
+
+type WsStream = WebSocketStream<SharedStream>;
+
+/// A WebSocket server serving every connection it accepts with a
+/// supervised children group.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use bastion::prelude::*;
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// # Bastion::start();
+/// #
+/// WebSocketServer::bind("127.0.0.1:8080")
+///     .expect("Couldn't bind the server.")
+///     .serve(|children| {
+///         children.with_exec(|ctx: BastionContext| async move {
+///             let ws = ctx.websocket().unwrap();
+///             loop {
+///                 msg! { ctx.recv().await?,
+///                     frame: WsFrame => {
+///                         // Echoes the frames of the client back to it.
+///                         ws.send(frame).await.ok();
+///                     };
+///                     _: _ => ();
+///                 }
+///             }
+///         })
+///     })
+///     .expect("Couldn't serve the connections.");
+/// # }
+/// ```
+pub struct WebSocketServer {
+    tcp: TcpServer,
+    keepalive: KeepAlive,
+}
+
+/// A WebSocket client connecting to a server from a supervised
+/// children group.
+///
+/// Only plain (`ws://`) connections are supported.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use bastion::prelude::*;
+/// #
+/// # fn run() {
+/// # Bastion::init();
+/// # Bastion::start();
+/// #
+/// WebSocketClient::new("ws://127.0.0.1:8080/feed")
+///     .connect(|children| {
+///         children.with_exec(|ctx: BastionContext| async move {
+///             loop {
+///                 msg! { ctx.recv().await?,
+///                     frame: WsFrame => {
+///                         // Handle the frame...
+///                     };
+///                     _: _ => ();
+///                 }
+///             }
+///         })
+///     })
+///     .expect("Couldn't connect.");
+/// # }
+/// ```
+pub struct WebSocketClient {
+    url: String,
+    keepalive: KeepAlive,
+}
+
+#[derive(Clone)]
+/// A WebSocket connection, given to the element running it.
+///
+/// Clones of a connection share its socket.
+pub struct WebSocket {
+    sink: Arc<Mutex<SplitSink<WsStream, WsMessage>>>,
+    stream: Arc<Mutex<SplitStream<WsStream>>>,
+    peer_addr: SocketAddr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A data frame received from (or sent to) the peer of a
+/// [`WebSocket`].
+///
+/// [`WebSocket`]: struct.WebSocket.html
+pub enum WsFrame {
+    /// A text frame.
+    Text(String),
+    /// A binary frame.
+    Binary(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy)]
+// How often the peer of a connection is pinged when it stays
+// silent, and how long it can stay silent before the connection
+// is considered lost.
+struct KeepAlive {
+    interval: Duration,
+    timeout: Duration,
+}
+
+// The stream of a connection, shared by the reading and writing
+// halves of its socket.
+struct SharedStream(Arc<Handle<TcpStream>>);
+
+#[derive(Clone)]
+// How the connection of an element is opened.
+enum Target {
+    // The connection was accepted by a server and needs to be
+    // handshaken.
+    Server,
+    Client(String),
+}
+
+impl WebSocketServer {
+    /// Creates a new server listening on the given address.
+    ///
+    /// # Argument
+    ///
+    /// * `addr` - The address to listen on.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Ok(WebSocketServer {
+            tcp: TcpServer::bind(addr)?,
+            keepalive: KeepAlive::default(),
+        })
+    }
+
+    /// Sets the maximum amount of connections served at once (see
+    /// [`TcpServer::with_max_connections`]).
+    ///
+    /// # Argument
+    ///
+    /// * `max_connections` - The maximum amount of connections
+    ///     served at once.
+    ///
+    /// [`TcpServer::with_max_connections`]: ../net/struct.TcpServer.html#method.with_max_connections
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.tcp = self.tcp.with_max_connections(max_connections);
+        self
+    }
+
+    /// Sets how often the clients are pinged when they stay silent,
+    /// and how long they can stay silent before their connection is
+    /// considered lost.
+    ///
+    /// By default, silent clients are pinged every 30 seconds and
+    /// disconnected after 60 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The interval between two pings.
+    /// * `timeout` - How long a client can stay silent.
+    pub fn with_keepalive(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.keepalive = KeepAlive { interval, timeout };
+        self
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp.local_addr()
+    }
+
+    /// Starts accepting the connections, under a new supervisor,
+    /// and serving every one of them with a children group built
+    /// by `builder`.
+    ///
+    /// This method returns the supervisor of the connections if it
+    /// succeeded, or `Err(())` otherwise.
+    ///
+    /// # Argument
+    ///
+    /// * `builder` - The closure taking a new [`Children`] and
+    ///     returning it once configured, called for every
+    ///     connection.
+    ///
+    /// [`Children`]: ../children/struct.Children.html
+    pub fn serve<C>(self, builder: C) -> Result<SupervisorRef, ()>
+    where
+        C: Fn(Children) -> Children + Send + Sync + 'static,
+    {
+        let keepalive = self.keepalive;
+        self.tcp.serve(move |children| {
+            builder(children).map_init(|init| run(init, Target::Server, keepalive))
+        })
+    }
+}
+
+impl WebSocketClient {
+    /// Creates a new client of the server at the given url.
+    ///
+    /// # Argument
+    ///
+    /// * `url` - The url of the server.
+    pub fn new(url: &str) -> Self {
+        WebSocketClient {
+            url: url.to_string(),
+            keepalive: KeepAlive::default(),
+        }
+    }
+
+    /// Sets how often the server is pinged when it stays silent,
+    /// and how long it can stay silent before the connection is
+    /// considered lost.
+    ///
+    /// By default, a silent server is pinged every 30 seconds and
+    /// disconnected after 60 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The interval between two pings.
+    /// * `timeout` - How long the server can stay silent.
+    pub fn with_keepalive(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.keepalive = KeepAlive { interval, timeout };
+        self
+    }
+
+    /// Creates a children group built by `builder`, whose element
+    /// connects to the server (again every time it is restarted
+    /// before it could connect).
+    ///
+    /// This method returns the children group if it succeeded, or
+    /// `Err(())` otherwise.
+    ///
+    /// # Argument
+    ///
+    /// * `builder` - The closure taking a new [`Children`] and
+    ///     returning it once configured.
+    ///
+    /// [`Children`]: ../children/struct.Children.html
+    pub fn connect<C>(self, builder: C) -> Result<ChildrenRef, ()>
+    where
+        C: FnOnce(Children) -> Children,
+    {
+        let target = Target::Client(self.url.clone());
+        let keepalive = self.keepalive;
+        Bastion::children(|children| {
+            builder(children.with_name(format!("ws/{}", self.url)))
+                .with_redundancy(1)
+                .map_init(|init| run(init, target, keepalive))
+        })
+    }
+}
+
+impl WebSocket {
+    /// Sends a data frame to the peer.
+    ///
+    /// # Argument
+    ///
+    /// * `frame` - The frame to send.
+    pub async fn send(&self, frame: WsFrame) -> Result<(), WsError> {
+        let msg = match frame {
+            WsFrame::Text(text) => WsMessage::Text(text),
+            WsFrame::Binary(data) => WsMessage::Binary(data),
+        };
+
+        self.sink.lock().await.send(msg).await
+    }
+
+    /// Closes the connection, which stops the element running it
+    /// with [`StopReason::Disconnected`].
+    ///
+    /// [`StopReason::Disconnected`]: ../watch/enum.StopReason.html#variant.Disconnected
+    pub async fn close(&self) -> Result<(), WsError> {
+        self.sink.lock().await.close().await
+    }
+
+    /// Returns the address of the peer.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    async fn open(target: &Target, conn: Option<TcpConnection>) -> Result<Self, WsError> {
+        let (stream, peer_addr) = match (target, conn) {
+            (Target::Server, Some(conn)) => {
+                let stream = SharedStream(conn.shared_stream());
+                let stream = async_tungstenite::accept_async(stream).await?;
+                (stream, conn.peer_addr())
+            }
+            (Target::Server, None) => return Err(WsError::ConnectionClosed),
+            (Target::Client(url), _) => {
+                let request = url.as_str().into_client_request()?;
+                let host = request.uri().host().unwrap_or_default().to_string();
+                let port = request.uri().port_u16().unwrap_or(80);
+                let stream = Handle::<TcpStream>::connect((host.as_str(), port)).await?;
+                let peer_addr = stream.get_ref().peer_addr()?;
+                let stream = SharedStream(Arc::new(stream));
+                let (stream, _) = async_tungstenite::client_async(request, stream).await?;
+                (stream, peer_addr)
+            }
+        };
+
+        let (sink, stream) = stream.split();
+        Ok(WebSocket {
+            sink: Arc::new(Mutex::new(sink)),
+            stream: Arc::new(Mutex::new(stream)),
+            peer_addr,
+        })
+    }
+
+    // Delivers the data frames of the peer to the element running
+    // the connection, and keeps the connection alive until it is
+    // lost.
+    async fn pump(self, elem: ChildRef, keepalive: KeepAlive) {
+        let mut stream = self.stream.lock().await;
+        let mut last_seen = Instant::now();
+        let mut ping = Delay::new(keepalive.interval).fuse();
+        loop {
+            futures::select! {
+                msg = stream.next().fuse() => {
+                    let frame = match msg {
+                        Some(Ok(WsMessage::Text(text))) => WsFrame::Text(text),
+                        Some(Ok(WsMessage::Binary(data))) => WsFrame::Binary(data),
+                        // The answers to the pings are sent once
+                        // the socket is flushed.
+                        Some(Ok(WsMessage::Ping(_))) => {
+                            last_seen = Instant::now();
+                            self.sink.lock().await.flush().await.ok();
+                            continue;
+                        }
+                        Some(Ok(WsMessage::Pong(_))) | Some(Ok(WsMessage::Frame(_))) => {
+                            last_seen = Instant::now();
+                            continue;
+                        }
+                        Some(Ok(WsMessage::Close(_))) | None => return,
+                        Some(Err(err)) => {
+                            debug!("WebSocket({}): Connection lost: {}", self.peer_addr, err);
+                            return;
+                        }
+                    };
+
+                    last_seen = Instant::now();
+                    trace!("WebSocket({}): Delivering frame.", self.peer_addr);
+                    if elem.tell_anonymously(frame).is_err() {
+                        return;
+                    }
+                }
+                _ = ping => {
+                    if last_seen.elapsed() >= keepalive.timeout {
+                        warn!("WebSocket({}): Peer stopped answering.", self.peer_addr);
+                        return;
+                    }
+
+                    trace!("WebSocket({}): Pinging peer.", self.peer_addr);
+                    self.sink.lock().await.send(WsMessage::Ping(Vec::new())).await.ok();
+                    ping = Delay::new(keepalive.interval).fuse();
+                }
+            }
+        }
+    }
+}
+
+// Opens the connection of the element (unless a previous
+// incarnation already did) and runs it along with the element's
+// future, stopping its group once either of them finished.
+fn run(init: Init, target: Target, keepalive: KeepAlive) -> Init {
+    Init(Box::new(move |ctx: BastionContext| {
+        let elem = ctx.current().clone();
+        let group = ctx.parent().clone();
+        let state = ctx.context_state().clone();
+        let bag = ctx.state_bag().clone();
+        let conn = ctx.tcp_connection();
+        let target = target.clone();
+
+        let Exec(exec) = (init.0)(ctx);
+        Exec(Box::pin(async move {
+            let ws = match bag.get::<WebSocket>() {
+                Some(ws) => ws,
+                None => match WebSocket::open(&target, conn).await {
+                    Ok(ws) => {
+                        bag.insert(ws.clone());
+                        ws
+                    }
+                    // A client connects again once restarted.
+                    Err(err) => {
+                        warn!("WebSocket: Couldn't open connection: {}", err);
+                        if let Target::Client(_) = target {
+                            return Err(ChildError::Failed);
+                        }
+
+                        state.set_stop_reason(StopReason::Disconnected);
+                        group.stop().ok();
+                        return Ok(());
+                    }
+                },
+            };
+
+            let pump = Box::pin(ws.clone().pump(elem, keepalive));
+            let res = match future::select(exec, pump).await {
+                Either::Left((res, _)) => {
+                    if res.is_ok() {
+                        ws.close().await.ok();
+                    }
+
+                    res
+                }
+                Either::Right(((), _)) => {
+                    state.set_stop_reason(StopReason::Disconnected);
+                    Ok(())
+                }
+            };
+
+            if res.is_ok() {
+                group.stop().ok();
+            }
+
+            res
+        }))
+    }))
+}
+
+impl Default for KeepAlive {
+    fn default() -> Self {
+        KeepAlive {
+            interval: Duration::from_secs(30),
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl AsyncRead for SharedStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        ctx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &*self.0).poll_read(ctx, buf)
+    }
+}
+
+impl AsyncWrite for SharedStream {
+    fn poll_write(self: Pin<&mut Self>, ctx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &*self.0).poll_write(ctx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut &*self.0).poll_flush(ctx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut &*self.0).poll_close(ctx)
+    }
+}
+
+impl Debug for WebSocketServer {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("WebSocketServer")
+            .field("tcp", &self.tcp)
+            .field("keepalive", &self.keepalive)
+            .finish()
+    }
+}
+
+impl Debug for WebSocketClient {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("WebSocketClient")
+            .field("url", &self.url)
+            .field("keepalive", &self.keepalive)
+            .finish()
+    }
+}
+
+impl Debug for WebSocket {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("WebSocket")
+            .field("peer_addr", &self.peer_addr)
+            .finish()
+    }
+}
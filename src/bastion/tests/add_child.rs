@@ -0,0 +1,89 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_add_child() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_add_child() {
+        super::run()
+    }
+}
+
+fn elems_count() -> usize {
+    Bastion::tree()
+        .supervisors
+        .iter()
+        .flat_map(|supervisor| supervisor.children.iter())
+        .filter(|children| children.name == "devices")
+        .map(|children| children.elems.len())
+        .sum()
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let devices = Bastion::children(|children| {
+        children
+            .with_name("devices")
+            .with_exec(|ctx: BastionContext| async move {
+                loop {
+                    let _ = ctx.recv().await?;
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let received = Arc::new(AtomicUsize::new(0));
+    let received_inner = received.clone();
+    let device = devices.add_child(move |ctx: BastionContext| {
+        let received = received_inner.clone();
+        async move {
+            loop {
+                let _ = ctx.recv().await?;
+                received.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    });
+
+    device.tell_anonymously("ping").unwrap();
+    for _ in 0..100 {
+        if received.load(Ordering::SeqCst) == 1 && elems_count() == 2 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(received.load(Ordering::SeqCst), 1);
+    assert_eq!(elems_count(), 2);
+
+    let others =
+        Bastion::children(|children| children).expect("Couldn't create the children group.");
+    assert!(others.remove_child(&device).is_err());
+
+    devices.remove_child(&device).unwrap();
+    for _ in 0..100 {
+        if elems_count() == 1 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(elems_count(), 1);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,86 @@
+use bastion::prelude::*;
+use futures::future::BoxFuture;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// Counts the tasks it spawns on the thread pools of
+// `bastion-executor`.
+struct Counting(Arc<AtomicUsize>);
+
+impl AgnosticExecutor for Counting {
+    fn spawn(&self, task: BoxFuture<'static, ()>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        BastionExecutor.spawn(task)
+    }
+
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        BastionExecutor.spawn_blocking(task)
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        BastionExecutor.sleep(duration)
+    }
+}
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_agnostic_executor() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_agnostic_executor() {
+        super::run()
+    }
+}
+
+fn run() {
+    let spawned = Arc::new(AtomicUsize::new(0));
+    let config = Config::new().with_executor(Executor::custom(Counting(spawned.clone())));
+    Bastion::init_with(config);
+    Bastion::start();
+
+    let scheduled = Arc::new(AtomicBool::new(false));
+    let scheduled_inner = scheduled.clone();
+    Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let scheduled = scheduled_inner.clone();
+            async move {
+                let answer = blocking!(42).await;
+                assert_eq!(answer, Some(42));
+
+                ctx.schedule_once(Duration::from_millis(10), "tick");
+                msg! { ctx.recv().await?,
+                    _tick: &'static str => {
+                        scheduled.store(true, Ordering::SeqCst);
+                    };
+                    _: _ => ();
+                }
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if scheduled.load(Ordering::SeqCst) {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(scheduled.load(Ordering::SeqCst));
+    assert!(spawned.load(Ordering::SeqCst) > 0);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,76 @@
+use bastion::prelude::*;
+use futures::StreamExt;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_answer_stream() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_answer_stream() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let counter = Bastion::children(|children| {
+        children.with_exec(|ctx: BastionContext| async move {
+            loop {
+                msg! { ctx.recv().await?,
+                    count: usize =!> {
+                        for chunk in 0..count {
+                            answer_stream!(ctx, chunk).unwrap();
+                        }
+                    };
+                    _: _ => ();
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let counter = counter.elems()[0].clone();
+    let received = Arc::new(Mutex::new(None));
+    let received_inner = received.clone();
+    Bastion::children(move |children| {
+        children.with_exec(move |_: BastionContext| {
+            let counter = counter.clone();
+            let received = received_inner.clone();
+            async move {
+                let chunks = counter.ask_stream_anonymously(3usize).unwrap();
+                let chunks: Vec<usize> = chunks
+                    .map(|chunk| chunk.extract().0.downcast::<usize>().unwrap())
+                    .collect()
+                    .await;
+                *received.lock().unwrap() = Some(chunks);
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if received.lock().unwrap().is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(*received.lock().unwrap(), Some(vec![0, 1, 2]));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
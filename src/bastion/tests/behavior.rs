@@ -0,0 +1,104 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_behavior() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_behavior() {
+        super::run()
+    }
+}
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+fn off(log: Log) -> Behavior {
+    Behavior::new(move |ctx, msg| {
+        let log = log.clone();
+        msg! { msg,
+            msg: &'static str => {
+                log.lock().unwrap().push(format!("off: {}", msg));
+                match msg {
+                    "switch" => ctx.become_with(on(log)),
+                    "done" => {
+                        ctx.unbecome();
+                    }
+                    _ => (),
+                }
+            };
+            _: _ => ();
+        }
+    })
+}
+
+fn on(log: Log) -> Behavior {
+    Behavior::new(move |ctx, msg| {
+        msg! { msg,
+            msg: &'static str => {
+                log.lock().unwrap().push(format!("on: {}", msg));
+                if msg == "switch" {
+                    ctx.unbecome();
+                }
+            };
+            _: _ => ();
+        }
+    })
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let log = Log::default();
+    let log_inner = log.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let log = log_inner.clone();
+            async move {
+                ctx.become_with(off(log));
+                ctx.run_behaviors().await?;
+
+                // The behaviors stack is empty once done.
+                assert!(!ctx.unbecome());
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = &children.elems()[0];
+    for msg in &["ping", "switch", "ping", "switch", "done"] {
+        child.tell_anonymously(*msg).unwrap();
+    }
+
+    for _ in 0..100 {
+        if log.lock().unwrap().len() == 5 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "off: ping",
+            "off: switch",
+            "on: ping",
+            "on: switch",
+            "off: done"
+        ]
+    );
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,78 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_blocking_pool() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_blocking_pool() {
+        super::run()
+    }
+}
+
+fn wait_for(started: &AtomicUsize, count: usize) {
+    for _ in 0..100 {
+        if started.load(Ordering::SeqCst) == count {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(started.load(Ordering::SeqCst), count);
+}
+
+fn run() {
+    let blocking_pool = BlockingPoolConfig::new()
+        .with_min_threads(1)
+        .with_max_threads(2)
+        .with_queue_limit(1);
+    Bastion::init_with(Config::new().with_blocking_pool(blocking_pool));
+    Bastion::start();
+
+    let started = Arc::new(AtomicUsize::new(0));
+    let released = Arc::new(AtomicBool::new(false));
+    let spawn = || {
+        let started = started.clone();
+        let released = released.clone();
+        blocking! {
+            started.fetch_add(1, Ordering::SeqCst);
+            while !released.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(10));
+            }
+
+            true
+        }
+    };
+
+    // Both threads of the pool are busy...
+    let first = spawn();
+    wait_for(&started, 1);
+    let second = spawn();
+    wait_for(&started, 2);
+
+    // ...so the next task is queued, and the one after it is
+    // rejected.
+    let queued = spawn();
+    let rejected = spawn();
+    assert_eq!(run!(rejected), None);
+
+    released.store(true, Ordering::SeqCst);
+    assert_eq!(run!(first), Some(true));
+    assert_eq!(run!(second), Some(true));
+    assert_eq!(run!(queued), Some(true));
+    assert_eq!(started.load(Ordering::SeqCst), 3);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
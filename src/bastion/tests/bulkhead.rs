@@ -0,0 +1,103 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_bulkhead() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_bulkhead() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    // The worker doesn't answer the queries until it is told to.
+    let bulkhead = Bulkhead::new(1).with_max_queued(1);
+    let pending = Arc::new(Mutex::new(Vec::new()));
+    let pending_inner = pending.clone();
+    let workers = Bastion::children(|children| {
+        children
+            .with_bulkhead(bulkhead.clone())
+            .with_exec(move |ctx: BastionContext| {
+                let pending = pending_inner.clone();
+                async move {
+                    loop {
+                        msg! { ctx.recv().await?,
+                            query: u32 =!> {
+                                pending.lock().unwrap().push((query, responder!()));
+                            };
+                            _answer: &'static str => {
+                                for (query, responder) in pending.lock().unwrap().drain(..) {
+                                    responder.respond(query * 2, &ctx).unwrap();
+                                }
+                            };
+                            _: _ => ();
+                        }
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let worker = workers.elems()[0].clone();
+    let first = worker.ask_anonymously(1u32).unwrap();
+    let second = worker.ask_anonymously(2u32).unwrap();
+    let third = worker.ask_anonymously(3u32).unwrap();
+
+    // The first query is in flight, the second one is queued and
+    // the third one is rejected.
+    assert!(run!(third).is_err());
+    for _ in 0..100 {
+        if pending.lock().unwrap().len() == 1 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(pending.lock().unwrap().len(), 1);
+    assert_eq!(bulkhead.in_flight(), 1);
+    assert_eq!(bulkhead.queued(), 1);
+
+    // Answering the first query delivers the second one.
+    worker.tell_anonymously("answer").unwrap();
+    let (msg, _) = run!(first).unwrap().extract();
+    assert_eq!(msg.downcast::<u32>().ok(), Some(2));
+
+    for _ in 0..100 {
+        if bulkhead.queued() == 0 && pending.lock().unwrap().len() == 1 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    worker.tell_anonymously("answer").unwrap();
+    let (msg, _) = run!(second).unwrap().extract();
+    assert_eq!(msg.downcast::<u32>().ok(), Some(4));
+
+    for _ in 0..100 {
+        if bulkhead.in_flight() == 0 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(bulkhead.in_flight(), 0);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
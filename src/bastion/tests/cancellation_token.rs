@@ -0,0 +1,82 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_cancellation_token() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_cancellation_token() {
+        super::run()
+    }
+}
+
+fn wait_for_tokens(tokens: &Mutex<Vec<CancellationToken>>, len: usize) {
+    for _ in 0..100 {
+        if tokens.lock().unwrap().len() >= len {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(tokens.lock().unwrap().len(), len);
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let tokens = Arc::new(Mutex::new(Vec::new()));
+    let tokens_inner = tokens.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let tokens = tokens_inner.clone();
+            async move {
+                let token = ctx.cancellation_token();
+                let first = {
+                    let mut tokens = tokens.lock().unwrap();
+                    tokens.push(token.clone());
+                    tokens.len() == 1
+                };
+
+                // The first incarnation faults...
+                if first {
+                    return Err(());
+                }
+
+                // ...while the second one waits until it's stopped.
+                token.cancelled().await;
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    // Restarting the child cancels the token of its previous
+    // incarnation...
+    wait_for_tokens(&tokens, 2);
+    {
+        let tokens = tokens.lock().unwrap();
+        assert!(tokens[0].is_cancelled());
+        assert!(!tokens[1].is_cancelled());
+    }
+
+    // ...and stopping it cancels its current one.
+    children.stop().expect("Couldn't stop the children group.");
+    let token = tokens.lock().unwrap()[1].clone();
+    let cancelled =
+        run!(token.run_until_cancelled(futures_timer::Delay::new(Duration::from_secs(1))));
+    assert_eq!(cancelled, None);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,100 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_chaos() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_chaos() {
+        super::run()
+    }
+}
+
+fn wait_until(counter: &AtomicUsize, count: usize) {
+    for _ in 0..100 {
+        if counter.load(Ordering::SeqCst) >= count {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(counter.load(Ordering::SeqCst), count);
+}
+
+fn wait_for_stats(cond: impl Fn(ChaosStats) -> bool) {
+    for _ in 0..100 {
+        if cond(bastion::chaos::stats().unwrap()) {
+            return;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    panic!("The faults weren't injected.");
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let incarnations = Arc::new(AtomicUsize::new(0));
+    let received = Arc::new(AtomicUsize::new(0));
+    let incarnations_inner = incarnations.clone();
+    let received_inner = received.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let received = received_inner.clone();
+            incarnations_inner.fetch_add(1, Ordering::SeqCst);
+            async move {
+                loop {
+                    ctx.recv().await?;
+                    received.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = &children.elems()[0];
+    wait_until(&incarnations, 1);
+
+    // The child panics and gets restarted, still receiving the
+    // message...
+    bastion::chaos::enable(ChaosConfig::new(1).with_panic_probability(1.0));
+    child.tell_anonymously("panic").unwrap();
+    wait_until(&incarnations, 2);
+    wait_until(&received, 1);
+    assert_eq!(bastion::chaos::stats().unwrap().panics, 1);
+
+    // ...then the message is dropped...
+    bastion::chaos::enable(ChaosConfig::new(2).with_drop_probability(1.0));
+    child.tell_anonymously("drop").unwrap();
+    wait_for_stats(|stats| stats.drops == 1);
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(received.load(Ordering::SeqCst), 1);
+
+    // ...or delivered late.
+    let delay = Duration::from_millis(50);
+    bastion::chaos::enable(ChaosConfig::new(3).with_delay(1.0, delay));
+    child.tell_anonymously("delay").unwrap();
+    wait_for_stats(|stats| stats.delays >= 1);
+    assert_eq!(received.load(Ordering::SeqCst), 1);
+
+    bastion::chaos::disable();
+    wait_until(&received, 2);
+    assert_eq!(incarnations.load(Ordering::SeqCst), 2);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
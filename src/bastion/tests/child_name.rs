@@ -0,0 +1,64 @@
+use bastion::prelude::*;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_child_name() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_child_name() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let children = Bastion::children(|children| {
+        children
+            .with_redundancy(3)
+            .with_child_name(|index| format!("worker-{}", index))
+            .with_exec(|ctx: BastionContext| async move {
+                loop {
+                    let _ = ctx.recv().await?;
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let mut worker = None;
+    for _ in 0..100 {
+        worker = Bastion::child_by_name("worker-2");
+        if worker.is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let worker = worker.expect("Couldn't find the child by name.");
+    assert!(children.elems().contains(&worker));
+    assert!(Bastion::child_by_name("worker-3").is_none());
+
+    children.stop().unwrap();
+    for _ in 0..100 {
+        if Bastion::child_by_name("worker-2").is_none() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(Bastion::child_by_name("worker-2").is_none());
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,64 @@
+use bastion::prelude::*;
+use futures::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_child_sink() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_child_sink() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_inner = received.clone();
+    let children = Bastion::children(|children| {
+        children
+            .with_mailbox(MailboxConfig::bounded(2).with_overflow(OverflowStrategy::Backpressure))
+            .with_exec(move |ctx: BastionContext| {
+                let received = received_inner.clone();
+                async move {
+                    loop {
+                        msg! { ctx.recv().await?,
+                            n: u32 => received.lock().unwrap().push(n);
+                            _: _ => ();
+                        }
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    // The messages are all delivered, in order, even though the
+    // child's mailbox can only contain two of them at once.
+    let child = children.elems()[0].clone();
+    let sent: Result<(), u32> = run!(stream::iter(0..32u32).map(Ok).forward(child));
+    assert!(sent.is_ok());
+
+    for _ in 0..100 {
+        if received.lock().unwrap().len() == 32 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(*received.lock().unwrap(), (0..32).collect::<Vec<_>>());
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
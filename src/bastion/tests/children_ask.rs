@@ -0,0 +1,134 @@
+use bastion::prelude::*;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_children_ask() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_children_ask() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    ask_all_collect();
+    ask_one_with_retries();
+    ask_typed_children();
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
+
+fn ask_all_collect() {
+    let children = Bastion::children(|children| {
+        children
+            .with_redundancy(3)
+            .with_exec(|ctx: BastionContext| async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        msg: &'static str =!> {
+                            answer!(ctx, msg.len()).expect("Couldn't answer.");
+                        };
+                        _: _ => ();
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let lengths: Vec<Result<usize, AskError>> =
+        run!(children.ask_all_collect("Hello!", Duration::from_secs(1)));
+    assert_eq!(lengths.len(), 3);
+    for length in lengths {
+        assert_eq!(length.expect("Couldn't ask."), 6);
+    }
+
+    // Answers of another type are reported as such.
+    let lengths: Vec<Result<String, AskError>> =
+        run!(children.ask_all_collect("Hello!", Duration::from_secs(1)));
+    for length in lengths {
+        assert!(matches!(length, Err(AskError::UnexpectedResponse)));
+    }
+}
+
+fn ask_one_with_retries() {
+    let children = Bastion::children(|children| {
+        children.with_exec(|ctx: BastionContext| async move {
+            let mut ignored = false;
+            loop {
+                let msg = ctx.recv().await?;
+                // The first request is dropped without being answered.
+                if !ignored {
+                    ignored = true;
+                    continue;
+                }
+
+                msg! { msg,
+                    msg: &'static str =!> {
+                        answer!(ctx, msg.len()).expect("Couldn't answer.");
+                    };
+                    _: _ => ();
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let options = AskOptions::default()
+        .with_timeout(Duration::from_secs(1))
+        .with_retries(1);
+    let length: Result<usize, AskError> = run!(children.ask_one_with("Hello!", options));
+    assert_eq!(length.expect("Couldn't ask."), 6);
+}
+
+fn ask_typed_children() {
+    let children = Bastion::children(|children| {
+        children
+            .with_redundancy(2)
+            .with_message_type::<&'static str>()
+            .with_exec(|ctx: BastionContext| async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        msg: &'static str =!> {
+                            answer!(ctx, msg.len()).expect("Couldn't answer.");
+                        };
+                        _: _ => ();
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    // Only the declared types of messages can be sent...
+    assert!(children.typed::<u64>().is_err());
+    let typed = children
+        .typed::<&'static str>()
+        .expect("The group doesn't handle &'static str.");
+
+    // ...to one of the elements or to all of them.
+    let answer = typed.ask_one("Hello!").expect("Couldn't ask.");
+    let length = run!(async move {
+        msg! { answer.await.expect("Couldn't receive the answer."),
+            length: usize => length;
+            _: _ => panic!("Unexpected answer.");
+        }
+    });
+    assert_eq!(length, 6);
+
+    let lengths: Vec<Result<usize, AskError>> =
+        run!(typed.ask_all_collect("Hello!", Duration::from_secs(1)));
+    assert_eq!(lengths.len(), 2);
+    for length in lengths {
+        assert_eq!(length.expect("Couldn't ask."), 6);
+    }
+}
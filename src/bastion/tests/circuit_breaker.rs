@@ -0,0 +1,82 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_circuit_breaker() {
+        super::run()
+    }
+}
+
+fn wait_for(breaker: &CircuitBreaker, state: CircuitState) {
+    for _ in 0..100 {
+        if breaker.state() == state {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(breaker.state(), state);
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let breaker = CircuitBreaker::new(1, Duration::from_millis(200));
+    let pings = Arc::new(AtomicUsize::new(0));
+    let pings_inner = pings.clone();
+    let children = Bastion::children(|children| {
+        children
+            .with_circuit_breaker(breaker.clone())
+            .with_exec(move |ctx: BastionContext| {
+                let pings = pings_inner.clone();
+                async move {
+                    loop {
+                        msg! { ctx.recv().await?,
+                            ref msg: &'static str => {
+                                if *msg == "fail" {
+                                    return Err(());
+                                }
+
+                                pings.fetch_add(1, Ordering::SeqCst);
+                            };
+                            _: _ => ();
+                        }
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    children.broadcast("fail").unwrap();
+    wait_for(&breaker, CircuitState::Open);
+
+    // The messages sent while the breaker is open are fast-failed.
+    children.broadcast("ping").unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(pings.load(Ordering::SeqCst), 0);
+
+    // Once the cool-down elapsed, a probe is delivered and closes
+    // the breaker once it was handled.
+    thread::sleep(Duration::from_millis(200));
+    children.broadcast("ping").unwrap();
+    wait_for(&breaker, CircuitState::Closed);
+    assert_eq!(pings.load(Ordering::SeqCst), 1);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
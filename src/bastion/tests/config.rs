@@ -0,0 +1,81 @@
+use bastion::prelude::*;
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_config() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_config() {
+        super::run()
+    }
+}
+
+fn run() {
+    env::set_var("BASTION_EXECUTOR_THREADS", "many");
+    assert!(matches!(
+        Config::from_env(),
+        Err(ConfigError::InvalidValue { .. })
+    ));
+
+    env::set_var("BASTION_EXECUTOR_THREADS", "2");
+    env::set_var("BASTION_MAILBOX_CAPACITY", "64");
+    let never = RestartStrategy::default().with_restart_policy(RestartPolicy::Never);
+    let config = Config::from_env()
+        .expect("Couldn't load the configuration.")
+        .with_restart_strategy("flaky", never);
+
+    Bastion::init_with(config);
+    Bastion::start();
+
+    // The elements of the named group aren't restarted, while the
+    // ones of the other groups still are.
+    let flaky = spawn_failing(Some("flaky"));
+    let others = spawn_failing(None);
+
+    for _ in 0..100 {
+        if others.load(Ordering::SeqCst) > 1 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(others.load(Ordering::SeqCst) > 1);
+    assert_eq!(flaky.load(Ordering::SeqCst), 1);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
+
+fn spawn_failing(name: Option<&'static str>) -> Arc<AtomicUsize> {
+    let incarnations = Arc::new(AtomicUsize::new(0));
+    let incarnations_inner = incarnations.clone();
+    Bastion::children(|children| {
+        let children = match name {
+            Some(name) => children.with_name(name),
+            None => children,
+        };
+
+        children.with_exec(move |_: BastionContext| {
+            let incarnations = incarnations_inner.clone();
+            async move {
+                incarnations.fetch_add(1, Ordering::SeqCst);
+                Err(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    incarnations
+}
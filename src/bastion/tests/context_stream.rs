@@ -0,0 +1,66 @@
+use bastion::prelude::*;
+use futures::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_context_stream() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_context_stream() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let received = Arc::new(Mutex::new(None));
+    let received_inner = received.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let received = received_inner.clone();
+            async move {
+                let even: Vec<u32> = ctx
+                    .stream()
+                    .filter_map(|msg| future::ready(msg.extract().0.downcast::<u32>().ok()))
+                    .filter(|n| future::ready(n % 2 == 0))
+                    .take(2)
+                    .collect()
+                    .await;
+                *received.lock().unwrap() = Some(even);
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = children.elems()[0].clone();
+    child.tell_anonymously("ignored").unwrap();
+    for i in 1..=5u32 {
+        child.tell_anonymously(i).unwrap();
+    }
+
+    for _ in 0..100 {
+        if received.lock().unwrap().is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(*received.lock().unwrap(), Some(vec![2, 4]));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,99 @@
+use bastion::prelude::*;
+use futures_timer::Delay;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_dead_letters() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_dead_letters() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let dead_letters = Bastion::dead_letters();
+
+    let children = Bastion::children(|children| {
+        children.with_exec(|ctx: BastionContext| async move {
+            // Only the first message is received, the other ones
+            // will still be in the mailbox once the child stopped.
+            let _ = ctx.recv().await?;
+            Ok(())
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = &children.elems()[0];
+    for msg in &["received", "lost", "lost again"] {
+        child.tell_anonymously(*msg).ok();
+    }
+
+    let mut count = 0;
+    for _ in 0..100 {
+        count = dead_letters.count(child.path());
+        if count > 0 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(count >= 1);
+    assert!(dead_letters.total() >= 1);
+
+    // The messages dropped because the mailbox of their recipient
+    // is full are dead letters too.
+    let receiving = Arc::new(AtomicBool::new(false));
+    let receiving_inner = receiving.clone();
+    let children = Bastion::children(|children| {
+        children
+            .with_mailbox(MailboxConfig::bounded(1).with_overflow(OverflowStrategy::DropNewest))
+            .with_exec(move |ctx: BastionContext| {
+                let receiving = receiving_inner.clone();
+                async move {
+                    while !receiving.load(Ordering::SeqCst) {
+                        Delay::new(Duration::from_millis(10)).await;
+                    }
+
+                    loop {
+                        let _ = ctx.recv().await?;
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = &children.elems()[0];
+    for msg in &["kept", "dropped", "dropped again"] {
+        child.tell_anonymously(*msg).ok();
+    }
+
+    for _ in 0..100 {
+        count = dead_letters.count(child.path());
+        if count >= 2 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(count, 2);
+    receiving.store(true, Ordering::SeqCst);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
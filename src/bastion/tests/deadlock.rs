@@ -0,0 +1,118 @@
+#![cfg(debug_assertions)]
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_deadlock_detection() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_deadlock_detection() {
+        super::run()
+    }
+}
+
+#[derive(Debug)]
+struct Ping;
+#[derive(Debug)]
+struct Pong;
+
+impl Request for Ping {
+    type Response = Pong;
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let first: Arc<Mutex<Option<ChildRef>>> = Arc::new(Mutex::new(None));
+    let cycle = Arc::new(Mutex::new(None));
+    let answered = Arc::new(Mutex::new(false));
+
+    // The second child asks back the first one while handling its
+    // request...
+    let first_inner = first.clone();
+    let cycle_inner = cycle.clone();
+    let second = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let first = first_inner.clone();
+            let cycle = cycle_inner.clone();
+            async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        _ping: Ping =!> {
+                            let first = first.lock().unwrap().clone().unwrap();
+                            if let Err(AskError::DeadlockDetected(paths)) =
+                                first.ask_typed(Ping).await
+                            {
+                                *cycle.lock().unwrap() = Some(paths);
+                            }
+
+                            answer!(ctx, Pong).unwrap();
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let second = second.elems()[0].clone();
+    let second_inner = second.clone();
+    let answered_inner = answered.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let second = second_inner.clone();
+            let answered = answered_inner.clone();
+            async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        _start: &'static str => {
+                            let answer = ctx.ask(&second.addr(), Ping).unwrap();
+                            *answered.lock().unwrap() = answer.await.is_ok();
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = children.elems()[0].clone();
+    *first.lock().unwrap() = Some(child.clone());
+    child.tell_anonymously("start").unwrap();
+
+    // ...which fails instead of deadlocking, so that the first
+    // child still gets its answer.
+    for _ in 0..100 {
+        if *answered.lock().unwrap() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(*answered.lock().unwrap());
+    let cycle = cycle.lock().unwrap().take().unwrap();
+    let ids = cycle
+        .iter()
+        .map(|path| path.id().clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ids,
+        vec![second.id().clone(), child.id().clone(), second.id().clone()]
+    );
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
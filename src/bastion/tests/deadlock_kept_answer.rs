@@ -0,0 +1,107 @@
+#![cfg(debug_assertions)]
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_deadlock_kept_answer() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_deadlock_kept_answer() {
+        super::run()
+    }
+}
+
+#[derive(Debug)]
+struct Ping;
+#[derive(Debug)]
+struct Pong;
+
+impl Request for Ping {
+    type Response = Pong;
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let first: Arc<Mutex<Option<ChildRef>>> = Arc::new(Mutex::new(None));
+    let asked_back = Arc::new(Mutex::new(None));
+
+    // The second child answers the first one, and then asks it back...
+    let first_inner = first.clone();
+    let asked_back_inner = asked_back.clone();
+    let second = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let first = first_inner.clone();
+            let asked_back = asked_back_inner.clone();
+            async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        _ping: Ping =!> {
+                            answer!(ctx, Pong).unwrap();
+
+                            let first = first.lock().unwrap().clone().unwrap();
+                            let answer = first.ask_typed(Ping).await;
+                            *asked_back.lock().unwrap() = Some(answer.is_ok());
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let second = second.elems()[0].clone();
+    let second_inner = second.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let second = second_inner.clone();
+            async move {
+                // ...while the first one keeps its answer without
+                // waiting on it.
+                let mut kept = Vec::new();
+                loop {
+                    msg! { ctx.recv().await?,
+                        _start: &'static str => {
+                            kept.push(ctx.ask(&second.addr(), Ping).unwrap());
+                        };
+                        _ping: Ping =!> {
+                            answer!(ctx, Pong).unwrap();
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = children.elems()[0].clone();
+    *first.lock().unwrap() = Some(child.clone());
+    child.tell_anonymously("start").unwrap();
+
+    for _ in 0..100 {
+        if asked_back.lock().unwrap().is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // The answered ask isn't pending anymore, so asking back isn't
+    // a deadlock.
+    assert_eq!(*asked_back.lock().unwrap(), Some(true));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,83 @@
+use bastion::prelude::*;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_decider() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_decider() {
+        super::run()
+    }
+}
+
+fn spawn_failing(supervisor: &SupervisorRef, runs: Arc<AtomicUsize>, panics: bool) {
+    supervisor
+        .children(move |children| {
+            children.with_typed_exec(move |ctx: BastionContext| {
+                let runs = runs.clone();
+                async move {
+                    // Only failing the first time.
+                    if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                        if panics {
+                            panic!("boom");
+                        }
+
+                        return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+                    }
+
+                    while ctx.recv().await.is_ok() {}
+                    Ok(())
+                }
+            })
+        })
+        .expect("Couldn't create the children group.");
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let supervisor = Bastion::supervisor(|sp| {
+        sp.with_decider(|failure: &ChildFailure| {
+            if failure.downcast_error::<io::Error>().is_some() {
+                Directive::Restart
+            } else {
+                Directive::Stop
+            }
+        })
+    })
+    .expect("Couldn't create the supervisor.");
+
+    let restarted = Arc::new(AtomicUsize::new(0));
+    spawn_failing(&supervisor, restarted.clone(), false);
+    let stopped = Arc::new(AtomicUsize::new(0));
+    spawn_failing(&supervisor, stopped.clone(), true);
+
+    for _ in 0..100 {
+        if restarted.load(Ordering::SeqCst) > 1 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // Gives the time to the panicking child to be restarted, if
+    // it was wrongly restarted.
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(restarted.load(Ordering::SeqCst), 2);
+    assert_eq!(stopped.load(Ordering::SeqCst), 1);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,84 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_deterministic_executor() {
+    let executor = Bastion::init_test_with_seed(42);
+    assert_eq!(executor.seed(), 42);
+    Bastion::start();
+
+    let threads = Arc::new(Mutex::new(Vec::new()));
+    let threads_inner = threads.clone();
+    let children = Bastion::children(|children| {
+        children
+            .with_redundancy(3)
+            .with_exec(move |ctx: BastionContext| {
+                let threads = threads_inner.clone();
+                async move {
+                    msg! { ctx.recv().await?,
+                        _: u32 => {
+                            let name = thread::current().name().map(ToString::to_string);
+                            threads.lock().unwrap().push(name);
+                        };
+                        _: _ => ();
+                    }
+
+                    Ok(())
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    for elem in children.elems() {
+        elem.tell_anonymously(0u32).unwrap();
+    }
+
+    for _ in 0..100 {
+        if threads.lock().unwrap().len() == 3 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // Every child ran on the executor's thread.
+    let threads = threads.lock().unwrap();
+    assert_eq!(threads.len(), 3);
+    for name in threads.iter() {
+        assert_eq!(name.as_deref(), Some("bastion-deterministic"));
+    }
+    assert!(executor.polls() > 0);
+    drop(threads);
+
+    // The timeouts elapse in the executor's virtual time.
+    let timed_out = Arc::new(Mutex::new(false));
+    let timed_out_inner = timed_out.clone();
+    Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let timed_out = timed_out_inner.clone();
+            async move {
+                let received = ctx.try_recv_timeout(Duration::from_secs(3600)).await;
+                *timed_out.lock().unwrap() = matches!(received, Err(ReceiveError::Timeout(_)));
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if *timed_out.lock().unwrap() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(*timed_out.lock().unwrap());
+    assert!(executor.clock().elapsed() >= Duration::from_secs(3600));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+    executor.shutdown();
+}
@@ -0,0 +1,76 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_entity_group() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_entity_group() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_inner = received.clone();
+    let devices = EntityGroup::new(
+        Duration::from_millis(200),
+        move |id: u64, ctx: BastionContext| {
+            let received = received_inner.clone();
+            async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        command: &'static str => {
+                            received.lock().unwrap().push((id, command));
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        },
+    )
+    .expect("Couldn't create the entity group.");
+
+    devices.tell(1, "reboot").unwrap();
+    devices.tell(2, "reboot").unwrap();
+    devices.tell(1, "ping").unwrap();
+    assert_eq!(devices.len(), 2);
+
+    for _ in 0..100 {
+        if received.lock().unwrap().len() == 3 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut received = received.lock().unwrap().clone();
+    received.sort();
+    assert_eq!(received, vec![(1, "ping"), (1, "reboot"), (2, "reboot")]);
+
+    // The devices are passivated once they are idle.
+    for _ in 0..100 {
+        if devices.is_empty() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(devices.is_empty());
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,92 @@
+use bastion::prelude::*;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_exec_local() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_exec_local() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    // The first incarnation panics, the restarted one sums the
+    // messages it receives in a `Rc`.
+    let started = Arc::new(AtomicUsize::new(0));
+    let summed = Arc::new(Mutex::new(None));
+    let threads = Arc::new(Mutex::new(Vec::new()));
+    let (started_inner, summed_inner, threads_inner) =
+        (started.clone(), summed.clone(), threads.clone());
+    let children = Bastion::children(|children| {
+        children.with_exec_local(move |ctx: BastionContext| {
+            let started = started_inner.clone();
+            let summed = summed_inner.clone();
+            let threads = threads_inner.clone();
+            async move {
+                threads.lock().unwrap().push(thread::current().id());
+                if started.fetch_add(1, Ordering::SeqCst) == 0 {
+                    panic!("first run");
+                }
+
+                let sum = Rc::new(Cell::new(0));
+                loop {
+                    msg! { ctx.recv().await?,
+                        ref n: u32 => {
+                            sum.set(sum.get() + *n);
+                            *summed.lock().unwrap() = Some((sum.get(), thread::current().id()));
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if started.load(Ordering::SeqCst) == 2 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    for n in 1..=3u32 {
+        children.broadcast(n).unwrap();
+    }
+
+    for _ in 0..100 {
+        if matches!(*summed.lock().unwrap(), Some((6, _))) {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let (sum, thread) = summed.lock().unwrap().take().unwrap();
+    assert_eq!(sum, 6);
+
+    // Both incarnations ran on the same thread.
+    let threads = threads.lock().unwrap();
+    assert_eq!(threads.len(), 2);
+    assert!(threads.iter().all(|id| *id == thread));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,89 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_forward() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_forward() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let workers = Bastion::children(|children| {
+        children.with_exec(|ctx: BastionContext| async move {
+            loop {
+                msg! { ctx.recv().await?,
+                    job: u32 =!> {
+                        answer!(ctx, job * 2).unwrap();
+                    };
+                    _: _ => ();
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let worker = workers.elems()[0].clone();
+    let worker_id = worker.id().clone();
+    let front = Bastion::children(move |children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let worker = worker.clone();
+            async move {
+                loop {
+                    let msg = ctx.recv().await?;
+                    ctx.forward(msg, &worker).unwrap();
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let front = front.elems()[0].clone();
+    let received = Arc::new(Mutex::new(None));
+    let received_inner = received.clone();
+    Bastion::children(move |children| {
+        children.with_exec(move |_: BastionContext| {
+            let front = front.clone();
+            let received = received_inner.clone();
+            async move {
+                let answer = front.ask_anonymously(21u32).unwrap();
+                let (msg, sign) = answer.await?.extract();
+                let answer = msg.downcast::<u32>().ok();
+                *received.lock().unwrap() = Some((answer, sign.path().id().clone()));
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if received.lock().unwrap().is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let (answer, sender_id) = received.lock().unwrap().take().unwrap();
+    assert_eq!(answer, Some(42));
+    // The answer was sent by the worker, directly to the caller.
+    assert_eq!(sender_id, worker_id);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
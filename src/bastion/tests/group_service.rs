@@ -0,0 +1,80 @@
+#![cfg(feature = "tower")]
+use bastion::errors::AskError;
+use bastion::prelude::*;
+use futures::future;
+use std::thread;
+use std::time::Duration;
+use tower::Service;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_group_service() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_group_service() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let children = Bastion::children(|children| {
+        children
+            .with_redundancy(2)
+            .with_exec(|ctx: BastionContext| async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        msg: &'static str =!> {
+                            answer!(ctx, msg.len()).expect("Couldn't answer.");
+                        };
+                        _: _ => ();
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let mut service = children
+        .into_service::<&'static str, usize>()
+        .with_timeout(Duration::from_secs(1));
+
+    // Waits for the elements to be running.
+    for _ in 0..100 {
+        if run!(service.call("")).is_ok() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let lengths = run!(async move {
+        let mut lengths = Vec::new();
+        for msg in &["a", "bb", "ccc"] {
+            future::poll_fn(|cx| service.poll_ready(cx)).await.unwrap();
+            lengths.push(service.call(*msg).await);
+        }
+
+        lengths
+    });
+
+    let lengths: Vec<_> = lengths.into_iter().map(Result::ok).collect();
+    assert_eq!(lengths, vec![Some(1), Some(2), Some(3)]);
+
+    // Calls fail when the group doesn't have any running element.
+    let mut service = Bastion::children(|children| children)
+        .unwrap()
+        .into_service::<&'static str, String>();
+    let res = run!(service.call("a"));
+    assert!(matches!(res, Err(AskError::SendFailed)));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
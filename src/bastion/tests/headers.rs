@@ -0,0 +1,67 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_headers() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_headers() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_inner = received.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let received = received_inner.clone();
+            async move {
+                loop {
+                    let msg = ctx.recv().await?;
+                    received.lock().unwrap().push(msg.headers().clone());
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = children.elems()[0].clone();
+    let headers = Headers::new()
+        .with_correlation_id("order-42")
+        .with_header("tenant", "acme");
+    child
+        .tell_anonymously_with_headers("ship", headers.clone())
+        .unwrap();
+    child.tell_anonymously("ship").unwrap();
+
+    for _ in 0..100 {
+        if received.lock().unwrap().len() == 2 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0], headers);
+    assert_eq!(received[0].correlation_id(), Some("order-42"));
+    assert_eq!(received[0].get("tenant"), Some("acme"));
+    assert!(received[1].is_empty());
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,68 @@
+use bastion::prelude::*;
+use futures::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_heartbeat() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_heartbeat() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let mut events = Bastion::events();
+    let incarnations = Arc::new(AtomicUsize::new(0));
+    let incarnations_inner = incarnations.clone();
+    Bastion::children(|children| {
+        children
+            .with_heartbeat(Duration::from_millis(20), Duration::from_millis(100))
+            .with_exec(move |ctx: BastionContext| {
+                let incarnations = incarnations_inner.clone();
+                async move {
+                    // Only the first incarnation gets stuck in a
+                    // blocking call...
+                    if incarnations.fetch_add(1, Ordering::SeqCst) == 0 {
+                        thread::sleep(Duration::from_millis(500));
+                    }
+
+                    loop {
+                        ctx.recv().await?;
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let mut stalled_for = None;
+    while let Some(event) = run!(events.next()) {
+        match event {
+            SupervisionEvent::ChildStalled { stalled_for: d, .. } => stalled_for = Some(d),
+            SupervisionEvent::ChildRestarted { .. } if stalled_for.is_some() => break,
+            _ => (),
+        }
+    }
+
+    assert!(stalled_for.unwrap() > Duration::from_millis(100));
+
+    // ...while the restarted one keeps answering the heartbeats.
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(incarnations.load(Ordering::SeqCst), 2);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,50 @@
+#![cfg(all(target_os = "linux", feature = "io-uring"))]
+use bastion::prelude::*;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_io_uring() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_io_uring() {
+        super::run()
+    }
+}
+
+fn run() {
+    // Files are written and read back...
+    let path = std::env::temp_dir().join(format!("bastion-io-uring-{}", std::process::id()));
+    let content: Vec<u8> = (0..200_000u32).map(|n| n as u8).collect();
+    run!(bastion::io::write_file(&path, content.clone())).expect("Couldn't write the file.");
+    let read = run!(bastion::io::read_file(&path)).expect("Couldn't read the file.");
+    std::fs::remove_file(&path).ok();
+    assert_eq!(read, content);
+
+    // ...and connections are accepted, read from and written to.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"ping").unwrap();
+        let mut pong = [0; 4];
+        stream.read_exact(&mut pong).unwrap();
+        pong
+    });
+
+    let stream = run!(bastion::io::accept(&listener)).expect("Couldn't accept.");
+    let (res, buf) = run!(bastion::io::read(&stream, vec![0; 4]));
+    assert_eq!(&buf[..res.unwrap()], b"ping");
+    let (res, _) = run!(bastion::io::write(&stream, b"pong".to_vec()));
+    assert_eq!(res.unwrap(), 4);
+
+    assert_eq!(&client.join().unwrap(), b"pong");
+}
@@ -0,0 +1,94 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_link() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_link() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let doomed = Bastion::children(|children| {
+        children.with_exec(|ctx: BastionContext| async move {
+            // Stops once told to.
+            let _ = ctx.recv().await?;
+            Ok(())
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let stopped = Arc::new(AtomicBool::new(false));
+    let stopped_inner = stopped.clone();
+    let linked = Bastion::children(|children| {
+        children
+            .with_callbacks(Callbacks::new().with_after_stop(move || {
+                stopped_inner.store(true, Ordering::SeqCst);
+            }))
+            .with_exec(|ctx: BastionContext| async move {
+                loop {
+                    let _ = ctx.recv().await?;
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let trapped = Arc::new(Mutex::new(None));
+    let trapped_inner = trapped.clone();
+    let trapper = Bastion::children(|children| {
+        children
+            .with_trap_exits(true)
+            .with_exec(move |ctx: BastionContext| {
+                let trapped = trapped_inner.clone();
+                async move {
+                    msg! { ctx.recv().await?,
+                        msg: ExitSignal => {
+                            *trapped.lock().unwrap() = Some(msg);
+                        };
+                        _: _ => ();
+                    }
+
+                    Ok(())
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let doomed = &doomed.elems()[0];
+    doomed.link(&linked.elems()[0]).unwrap();
+    doomed.link(&trapper.elems()[0]).unwrap();
+    doomed.tell_anonymously("stop").unwrap();
+
+    let mut signal = None;
+    for _ in 0..100 {
+        signal = trapped.lock().unwrap().take();
+        if signal.is_some() && stopped.load(Ordering::SeqCst) {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let ExitSignal(path, reason) = signal.expect("Didn't receive the ExitSignal message.");
+    assert_eq!(path.to_string(), doomed.path().to_string());
+    assert_eq!(reason, StopReason::Normal);
+    assert!(stopped.load(Ordering::SeqCst));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,78 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_local_storage() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_local_storage() {
+        super::run()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Cache(u32);
+#[derive(Debug, Clone, PartialEq)]
+struct Session(u32);
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let incarnations = Arc::new(AtomicUsize::new(0));
+    let restored = Arc::new(Mutex::new(None));
+
+    let incarnations_inner = incarnations.clone();
+    let restored_inner = restored.clone();
+    Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let incarnations = incarnations_inner.clone();
+            let restored = restored_inner.clone();
+            async move {
+                // The first incarnation stores its values and panics...
+                if incarnations.fetch_add(1, Ordering::SeqCst) == 0 {
+                    ctx.local_storage().insert(Cache(1));
+                    ctx.local_storage().insert_persistent(Session(2));
+                    panic!("first incarnation");
+                }
+
+                // ...and the next one only gets the persistent one.
+                let storage = ctx.local_storage();
+                *restored.lock().unwrap() =
+                    Some((storage.get::<Cache>(), storage.get::<Session>()));
+
+                loop {
+                    ctx.recv().await?;
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if restored.lock().unwrap().is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(incarnations.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        restored.lock().unwrap().take(),
+        Some((None, Some(Session(2))))
+    );
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
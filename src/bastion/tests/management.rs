@@ -0,0 +1,121 @@
+#![cfg(feature = "management")]
+use bastion::prelude::*;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_management() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_management() {
+        super::run()
+    }
+}
+
+fn request(addr: SocketAddr, method: &str, path: &str, token: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\n\r\n",
+        method, path, token
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.lines().next().unwrap().to_string();
+    (status, body.to_string())
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let config = ManagementConfig::new("s3cr3t").expect("The bearer token is empty.");
+    let addr = Bastion::serve_management("127.0.0.1:0", config)
+        .expect("Couldn't start the management server.");
+
+    let started = Arc::new(AtomicUsize::new(0));
+    let started_inner = started.clone();
+    let children = Bastion::children(|children| {
+        children
+            .with_name("workers")
+            .with_exec(move |ctx: BastionContext| {
+                let started = started_inner.clone();
+                async move {
+                    started.fetch_add(1, Ordering::SeqCst);
+                    loop {
+                        let _ = ctx.recv().await?;
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if started.load(Ordering::SeqCst) == 1 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(started.load(Ordering::SeqCst), 1);
+
+    // The requests need the bearer token...
+    let (status, _) = request(addr, "GET", "/tree", "wrong");
+    assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+
+    // ...but the health checks.
+    let (status, _) = request(addr, "GET", "/health", "wrong");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+
+    let (status, _) = request(addr, "GET", "/health", "s3cr3t");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+
+    // A client trickling its headers doesn't delay the other ones.
+    let mut slow = TcpStream::connect(addr).unwrap();
+    write!(slow, "GET /health HTTP/1.1\r\nHost: ").unwrap();
+    let started_at = Instant::now();
+    let (status, _) = request(addr, "GET", "/health", "s3cr3t");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(started_at.elapsed() < Duration::from_secs(2));
+    drop(slow);
+
+    let (status, body) = request(addr, "GET", "/tree", "s3cr3t");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(body.contains("\"workers\""));
+
+    // ...and the children groups are found by their path.
+    let (status, _) = request(addr, "POST", "/groups/unknown/restart", "s3cr3t");
+    assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+    let path = format!("/groups{}/restart", children.path());
+    let (status, _) = request(addr, "POST", &path, "s3cr3t");
+    assert_eq!(status, "HTTP/1.1 202 Accepted");
+
+    for _ in 0..100 {
+        if started.load(Ordering::SeqCst) == 2 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(started.load(Ordering::SeqCst), 2);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,79 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_middleware() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_middleware() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let delivered = Arc::new(AtomicUsize::new(0));
+    let delivered_inner = delivered.clone();
+    Bastion::add_middleware(move |msg: SignedMessage, next: Next| {
+        delivered_inner.fetch_add(1, Ordering::SeqCst);
+        next.run(msg);
+    });
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_inner = received.clone();
+    let children = Bastion::children(|children| {
+        children
+            // Drops the messages sent without a tenant.
+            .with_middleware(|msg: SignedMessage, next: Next| {
+                if msg.headers().get("tenant").is_some() {
+                    next.run(msg);
+                }
+            })
+            .with_exec(move |ctx: BastionContext| {
+                let received = received_inner.clone();
+                async move {
+                    loop {
+                        msg! { ctx.recv().await?,
+                            msg: &'static str => {
+                                received.lock().unwrap().push(msg);
+                            };
+                            _: _ => ();
+                        }
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = children.elems()[0].clone();
+    child.tell_anonymously("anonymous").unwrap();
+    child
+        .tell_anonymously_with_headers("authorized", Headers::new().with_header("tenant", "acme"))
+        .unwrap();
+
+    for _ in 0..100 {
+        if delivered.load(Ordering::SeqCst) >= 2 && !received.lock().unwrap().is_empty() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(delivered.load(Ordering::SeqCst) >= 2);
+    assert_eq!(*received.lock().unwrap(), vec!["authorized"]);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,107 @@
+#![cfg(feature = "nats")]
+use bastion::prelude::*;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// The test needs a local NATS server (e.g. `nats-server -p 4222`)
+// and is skipped unless this variable is set to its url.
+const URL_VAR: &str = "BASTION_NATS_URL";
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_nats() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_nats() {
+        super::run()
+    }
+}
+
+fn run() {
+    let url = match env::var(URL_VAR) {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+
+    Bastion::init();
+    Bastion::start();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_inner = received.clone();
+    Bastion::children(|children| {
+        children
+            .with_dispatcher(Dispatcher::with_type(DispatcherType::Named(
+                "orders".to_string(),
+            )))
+            .with_exec(move |ctx: BastionContext| {
+                let received = received_inner.clone();
+                async move {
+                    loop {
+                        msg! { ctx.recv().await?,
+                            raw_message: Arc<SignedMessage> => {
+                                let message = Arc::try_unwrap(raw_message).unwrap();
+                                msg! { message,
+                                    ref order: NatsMessage => {
+                                        let order: String = order.decode().unwrap();
+                                        received.lock().unwrap().push(order);
+                                    };
+                                    _: _ => ();
+                                }
+                            };
+                            _: _ => ();
+                        }
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let nats = Nats::connect(&url).expect("Couldn't connect.");
+    let subscriber = nats
+        .subscribe("bastion.orders", "orders")
+        .expect("Couldn't subscribe.");
+
+    // The subscription is made asynchronously by the subscriber child.
+    for _ in 0..100 {
+        nats.tell_remote("bastion.orders", &"order #1".to_string())
+            .unwrap();
+        if !received.lock().unwrap().is_empty() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(
+        received.lock().unwrap().first().map(String::as_str),
+        Some("order #1")
+    );
+
+    // The messages sent to the subscriber child aren't dropped.
+    let dead_letters = Bastion::dead_letters();
+    let subscriber = &subscriber.elems()[0];
+    subscriber.tell_anonymously("unexpected").unwrap();
+
+    let mut count = 0;
+    for _ in 0..100 {
+        count = dead_letters.count(subscriber.path());
+        if count > 0 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(count, 1);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
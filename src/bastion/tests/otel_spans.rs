@@ -0,0 +1,151 @@
+#![cfg(feature = "otel")]
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::span::{Attributes, Id};
+use tracing::{info_span, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_otel_spans() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_otel_spans() {
+        super::run()
+    }
+}
+
+#[derive(Debug)]
+struct Ping;
+#[derive(Debug)]
+struct Pong;
+
+impl Request for Ping {
+    type Response = Pong;
+}
+
+type Chains = Arc<Mutex<Vec<Vec<String>>>>;
+
+// Records the names of the ancestors of the `handler` spans, from
+// the span itself to the root one.
+struct Recorder(Chains);
+
+impl<S> Layer<S> for Recorder
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != "handler" {
+            return;
+        }
+
+        let mut chain = Vec::new();
+        let mut current = ctx.span(id);
+        while let Some(span) = current {
+            chain.push(span.name().to_string());
+            current = span.parent();
+        }
+
+        self.0.lock().unwrap().push(chain);
+    }
+}
+
+fn wait_for(chains: &Chains, len: usize) {
+    for _ in 0..100 {
+        if chains.lock().unwrap().len() >= len {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn run() {
+    let chains = Chains::default();
+    let subscriber = tracing_subscriber::registry().with(Recorder(chains.clone()));
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    Bastion::init();
+    Bastion::start();
+
+    let answerer = Bastion::children(|children| {
+        children.with_exec(|ctx: BastionContext| async move {
+            loop {
+                msg! { ctx.recv().await?,
+                    _ping: Ping =!> {
+                        let span = info_span!("handler");
+                        let _enter = span.enter();
+                        answer!(ctx, Pong).unwrap();
+                    };
+                    _msg: &'static str => {
+                        let span = info_span!("handler");
+                        let _enter = span.enter();
+                    };
+                    _: _ => ();
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+    let answerer = answerer.elems()[0].clone();
+
+    // The processing of a told message is in a span whose parent is
+    // the one it was sent from...
+    info_span!("sender").in_scope(|| answerer.tell_anonymously("hello").unwrap());
+
+    wait_for(&chains, 1);
+    assert_eq!(
+        chains.lock().unwrap().pop(),
+        Some(vec![
+            "handler".to_string(),
+            "bastion::message".to_string(),
+            "sender".to_string(),
+        ])
+    );
+
+    // ...and so is the processing of an asked one, even when it is
+    // asked while processing another message.
+    let answerer_inner = answerer.clone();
+    let asker = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let answerer = answerer_inner.clone();
+            async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        _msg: &'static str => {
+                            answerer.ask_typed(Ping).await.unwrap();
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+    let asker = asker.elems()[0].clone();
+
+    info_span!("sender").in_scope(|| asker.tell_anonymously("ask").unwrap());
+
+    wait_for(&chains, 1);
+    assert_eq!(
+        chains.lock().unwrap().pop(),
+        Some(vec![
+            "handler".to_string(),
+            "bastion::message".to_string(),
+            "bastion::message".to_string(),
+            "sender".to_string(),
+        ])
+    );
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
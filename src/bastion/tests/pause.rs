@@ -0,0 +1,69 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_pause() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_pause() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let handled = Arc::new(AtomicUsize::new(0));
+    let handled_inner = handled.clone();
+    let supervisor = Bastion::supervisor(|sp| sp).expect("Couldn't create the supervisor.");
+    let children = supervisor
+        .children(|children| {
+            children.with_exec(move |ctx: BastionContext| {
+                let handled = handled_inner.clone();
+                async move {
+                    loop {
+                        let _ = ctx.recv().await?;
+                        handled.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            })
+        })
+        .expect("Couldn't create the children group.");
+
+    supervisor.pause().unwrap();
+    // Gives the time to the children to be paused.
+    thread::sleep(Duration::from_millis(100));
+
+    let child = &children.elems()[0];
+    for _ in 0..5 {
+        child.tell_anonymously("work").unwrap();
+    }
+
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(handled.load(Ordering::SeqCst), 0);
+
+    supervisor.resume().unwrap();
+    for _ in 0..100 {
+        if handled.load(Ordering::SeqCst) == 5 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(handled.load(Ordering::SeqCst), 5);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,65 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_rate_limit() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_rate_limit() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let received = Arc::new(AtomicUsize::new(0));
+    let received_inner = received.clone();
+    let children = Bastion::children(|children| {
+        children
+            .with_rate_limit(RateLimit::per_second(10).with_burst(1))
+            .with_exec(move |ctx: BastionContext| {
+                let received = received_inner.clone();
+                async move {
+                    loop {
+                        let _ = ctx.recv().await?;
+                        received.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..5 {
+        children.broadcast("work").unwrap();
+    }
+
+    // At most one message is processed every 100 milliseconds.
+    thread::sleep(Duration::from_millis(150));
+    assert!(received.load(Ordering::SeqCst) < 5);
+
+    // The other ones are processed later, instead of being dropped.
+    for _ in 0..100 {
+        if received.load(Ordering::SeqCst) == 5 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(received.load(Ordering::SeqCst), 5);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
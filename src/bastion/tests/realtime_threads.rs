@@ -0,0 +1,63 @@
+use bastion::prelude::*;
+use bastion_executor::placement;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_realtime_threads() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_realtime_threads() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let core = placement::get_core_ids().unwrap()[0].id;
+    let ran_on = Arc::new(Mutex::new(None));
+    let ran_on_inner = ran_on.clone();
+    Bastion::children(|children| {
+        children
+            .with_realtime_threads(vec![core])
+            .with_exec(move |_: BastionContext| {
+                let ran_on = ran_on_inner.clone();
+                async move {
+                    let name = thread::current().name().map(ToString::to_string);
+                    let cores = placement::get_core_ids().unwrap();
+                    *ran_on.lock().unwrap() = Some((name, cores));
+                    Ok(())
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if ran_on.lock().unwrap().is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // The element ran on the thread pinned onto the core.
+    let (name, cores) = ran_on.lock().unwrap().take().unwrap();
+    assert!(name.unwrap().starts_with("bastion-realtime-"));
+    if cfg!(target_os = "linux") {
+        assert_eq!(cores.len(), 1);
+        assert_eq!(cores[0].id, core);
+    }
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,60 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_recv_batch() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_recv_batch() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let batches_inner = batches.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let batches = batches_inner.clone();
+            async move {
+                loop {
+                    let batch = ctx.recv_batch(3, Duration::from_millis(100)).await?;
+                    batches.lock().unwrap().push(batch.len());
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = children.elems()[0].clone();
+    for i in 0..5u32 {
+        child.tell_anonymously(i).unwrap();
+    }
+
+    // The first batch is full, the second one is returned once
+    // `max_wait` elapsed.
+    for _ in 0..100 {
+        if batches.lock().unwrap().iter().sum::<usize>() == 5 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(*batches.lock().unwrap(), vec![3, 2]);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
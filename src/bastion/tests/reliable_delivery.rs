@@ -0,0 +1,84 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_reliable_delivery() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_reliable_delivery() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_inner = received.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let received = received_inner.clone();
+            async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        delivery: Delivery<&'static str> => {
+                            received.lock().unwrap().push((*delivery.msg(), delivery.attempt()));
+                            // Only "acked" gets acknowledged, once it
+                            // was redelivered.
+                            if *delivery.msg() == "acked" && delivery.is_redelivery() {
+                                assert!(ctx.ack(delivery.id()));
+                                assert!(!ctx.ack(delivery.id()));
+                            }
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = &children.elems()[0];
+    let config = DeliveryConfig::default()
+        .with_redelivery_interval(Duration::from_millis(50))
+        .with_max_redeliveries(2);
+    child
+        .tell_reliable_with_config("acked", config.clone())
+        .unwrap();
+    child.tell_reliable_with_config("dropped", config).unwrap();
+
+    let dead_letters = Bastion::dead_letters();
+    for _ in 0..200 {
+        if dead_letters.count(child.path()) > 0 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(dead_letters.count(child.path()), 1);
+    let received = received.lock().unwrap().clone();
+    let acked: Vec<_> = received.iter().filter(|(msg, _)| *msg == "acked").collect();
+    let dropped: Vec<_> = received
+        .iter()
+        .filter(|(msg, _)| *msg == "dropped")
+        .collect();
+    assert_eq!(acked, vec![&("acked", 1), &("acked", 2)]);
+    assert_eq!(
+        dropped,
+        vec![&("dropped", 1), &("dropped", 2), &("dropped", 3)]
+    );
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,74 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_reload_config() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_reload_config() {
+        super::run()
+    }
+}
+
+fn run() {
+    let config = Config::new().with_rate_limit("throttled", RateLimit::per_minute(1));
+    Bastion::init_with(config);
+    Bastion::start();
+
+    let received = Arc::new(AtomicUsize::new(0));
+    let received_inner = received.clone();
+    let children = Bastion::children(|children| {
+        children
+            .with_name("throttled")
+            .with_exec(move |ctx: BastionContext| {
+                let received = received_inner.clone();
+                async move {
+                    loop {
+                        let _ = ctx.recv().await?;
+                        received.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..3 {
+        children.broadcast("work").unwrap();
+    }
+
+    // Only one message can be processed per minute...
+    thread::sleep(Duration::from_millis(150));
+    assert_eq!(received.load(Ordering::SeqCst), 1);
+
+    // ...until the rate limit is raised.
+    let config = Config::new()
+        .with_rate_limit("throttled", RateLimit::per_second(100))
+        .with_executor_threads(2);
+    let report = Bastion::reload_config(config);
+    assert_eq!(report.applied(), &["rate_limits"]);
+    assert_eq!(report.requires_restart(), &["executor_threads"]);
+    assert!(!report.is_fully_applied());
+
+    for _ in 0..100 {
+        if received.load(Ordering::SeqCst) == 3 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(received.load(Ordering::SeqCst), 3);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
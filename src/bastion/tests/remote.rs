@@ -0,0 +1,94 @@
+#![cfg(feature = "remote")]
+use bastion::prelude::*;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_remote() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_remote() {
+        super::run()
+    }
+}
+
+fn run() {
+    bastion::remote::register_message::<u64>();
+    bastion::remote::register_message::<String>();
+
+    Bastion::init();
+    Bastion::start();
+
+    let told = Arc::new(Mutex::new(Vec::new()));
+    let told_inner = told.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let told = told_inner.clone();
+            async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        msg: u64 =!> {
+                            answer!(ctx, format!("{}!", msg)).unwrap();
+                        };
+                        msg: String => {
+                            told.lock().unwrap().push(msg);
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    // The node connects to itself, which still sends the messages
+    // over TCP.
+    let config = RemotingConfig::default().with_codec(BincodeCodec);
+    let addr = Bastion::bind_with("127.0.0.1:0", config).expect("Couldn't bind the node.");
+    let node = Bastion::connect(addr).expect("Couldn't connect to the node.");
+    assert_eq!(node.id(), bastion::remote::local_node_id());
+    assert!(node.is_connected());
+
+    // Waiting for the child to be started...
+    thread::sleep(Duration::from_millis(100));
+    let child = &children.elems()[0];
+    let remote_child = node.child_ref(child.path().to_string());
+
+    remote_child
+        .tell_anonymously("hello".to_string())
+        .expect("Couldn't tell the message.");
+    let answer = remote_child
+        .ask_anonymously(42u64)
+        .expect("Couldn't ask the message.");
+
+    let reply = run!(async {
+        msg! { answer.await.expect("Couldn't receive the answer."),
+            msg: String => msg;
+            _: _ => panic!("Unexpected answer.");
+        }
+    });
+    assert_eq!(reply, "42!");
+    assert_eq!(*told.lock().unwrap(), vec!["hello".to_string()]);
+
+    // Messages sent to unknown children fail to be answered.
+    let answer = node
+        .child_ref("/unknown")
+        .ask_anonymously(42u64)
+        .expect("Couldn't ask the message.");
+    assert!(run!(answer).is_err());
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+
+    // The node stopped listening along with the system.
+    assert!(TcpStream::connect(addr).is_err());
+}
@@ -0,0 +1,89 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_responder() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_responder() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let workers = Bastion::children(|children| {
+        children.with_exec(|ctx: BastionContext| async move {
+            loop {
+                msg! { ctx.recv().await?,
+                    delegated: (u32, Responder) => {
+                        let (job, responder) = delegated;
+                        responder.respond(job * 2, &ctx).unwrap();
+                    };
+                    _: _ => ();
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let worker = workers.elems()[0].clone();
+    let front = Bastion::children(move |children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let worker = worker.clone();
+            async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        job: u32 =!> {
+                            worker.tell_anonymously((job, responder!())).unwrap();
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let front = front.elems()[0].clone();
+    let received = Arc::new(Mutex::new(None));
+    let received_inner = received.clone();
+    Bastion::children(move |children| {
+        children.with_exec(move |_: BastionContext| {
+            let front = front.clone();
+            let received = received_inner.clone();
+            async move {
+                let answer = front.ask_anonymously(21u32).unwrap();
+                let (msg, _) = answer.await?.extract();
+                *received.lock().unwrap() = msg.downcast::<u32>().ok();
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if received.lock().unwrap().is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(*received.lock().unwrap(), Some(42));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,93 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_restart_callbacks() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_restart_callbacks() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let failed = Arc::new(Mutex::new(None));
+    let failed_inner = failed.clone();
+    let restarted = Arc::new(Mutex::new(None));
+    let restarted_inner = restarted.clone();
+    let runs = Arc::new(AtomicUsize::new(0));
+    let runs_inner = runs.clone();
+    Bastion::children(|children| {
+        let callbacks = Callbacks::new()
+            .with_before_restart_failure(move |failure: &ChildFailure| {
+                *failed_inner.lock().unwrap() = Some(failure.clone());
+            })
+            .with_after_restart_failure(move |failure: &ChildFailure| {
+                *restarted_inner.lock().unwrap() = Some(failure.clone());
+            });
+
+        children
+            .with_callbacks(callbacks)
+            .with_exec(move |ctx: BastionContext| {
+                let runs = runs_inner.clone();
+                async move {
+                    // Only panicking the first time.
+                    if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                        panic!("boom");
+                    }
+
+                    loop {
+                        let _ = ctx.recv().await?;
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if restarted.lock().unwrap().is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let failure = failed
+        .lock()
+        .unwrap()
+        .take()
+        .expect("before_restart wasn't called.");
+    assert!(failure.is_panic());
+    assert_eq!(failure.panic_message(), Some("boom"));
+    match failure.error() {
+        Some(ChildError::Panic { message, .. }) => assert_eq!(message.as_deref(), Some("boom")),
+        error => panic!("unexpected error: {:?}", error),
+    }
+    assert_eq!(failure.restarts(), 0);
+    assert_eq!(failure.downtime(), None);
+
+    let failure = restarted
+        .lock()
+        .unwrap()
+        .take()
+        .expect("after_restart wasn't called.");
+    assert_eq!(failure.panic_message(), Some("boom"));
+    assert_eq!(failure.restarts(), 1);
+    assert!(failure.downtime().is_some());
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,56 @@
+use bastion::prelude::*;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_restart_intensity() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_restart_intensity() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let runs = Arc::new(AtomicUsize::new(0));
+    let runs_inner = runs.clone();
+
+    Bastion::supervisor(move |sp| {
+        let runs = runs_inner.clone();
+        sp.with_restart_intensity(2, Duration::from_secs(60))
+            .with_intensity_decision(IntensityDecision::StopSubtree)
+            .children(move |children| {
+                children.with_exec(move |_ctx: BastionContext| {
+                    let runs = runs.clone();
+                    async move {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        // Always failing, so that the child gets restarted.
+                        Err(())
+                    }
+                })
+            })
+    })
+    .expect("Couldn't create the supervisor.");
+
+    thread::sleep(Duration::from_millis(500));
+
+    // The first run and two restarts, then the supervisor gives up.
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -72,32 +72,78 @@ fn calculate_linear_strategy() {
 }
 
 #[test]
-fn calculate_exp_strategy_with_multiplier_zero() {
+fn calculate_exp_strategy_without_jitter() {
     let strategy = ActorRestartStrategy::ExponentialBackOff {
-        timeout: Duration::from_millis(100),
-        multiplier: 0.0,
+        base: Duration::from_millis(100),
+        max: Duration::from_secs(10),
+        jitter: 0.0,
     };
 
     assert_eq!(strategy.calculate(0), Some(Duration::from_millis(100)));
-    assert_eq!(strategy.calculate(1), Some(Duration::from_millis(100)));
-    assert_eq!(strategy.calculate(100), Some(Duration::from_millis(100)));
+    assert_eq!(strategy.calculate(1), Some(Duration::from_millis(200)));
+    assert_eq!(strategy.calculate(3), Some(Duration::from_millis(800)));
 }
 
 #[test]
-fn calculate_exp_strategy_with_multiplier_non_zero() {
+fn calculate_exp_strategy_is_capped() {
     let strategy = ActorRestartStrategy::ExponentialBackOff {
-        timeout: Duration::from_millis(100),
-        multiplier: 5.0,
+        base: Duration::from_millis(100),
+        max: Duration::from_secs(10),
+        jitter: 0.0,
     };
 
-    assert_eq!(strategy.calculate(0), Some(Duration::from_millis(100)));
+    assert_eq!(strategy.calculate(7), Some(Duration::from_secs(10)));
+    assert_eq!(strategy.calculate(100_000), Some(Duration::from_secs(10)));
+}
+
+#[test]
+fn calculate_exp_strategy_with_jitter() {
+    let strategy = ActorRestartStrategy::ExponentialBackOff {
+        base: Duration::from_millis(100),
+        max: Duration::from_secs(10),
+        jitter: 0.5,
+    };
+
+    for _ in 0..100 {
+        let delay = strategy.calculate(2).unwrap();
+        assert!(delay > Duration::from_millis(200));
+        assert!(delay <= Duration::from_millis(400));
+    }
+}
+
+#[test]
+fn set_reset_after() {
+    let restart_strategy = RestartStrategy::default().with_reset_after(Duration::from_secs(60));
 
     assert_eq!(
-        strategy.calculate(1),
-        Some(Duration::from_millis(100 + 1 * 5 * 100))
-    );
-    assert_eq!(
-        strategy.calculate(99),
-        Some(Duration::from_millis(100 + 99 * 5 * 100))
+        restart_strategy.reset_after(),
+        Some(Duration::from_secs(60))
     );
+    assert_eq!(RestartStrategy::default().reset_after(), None);
+}
+
+#[test]
+fn calculate_exp_strategy_saturates() {
+    let strategy = ActorRestartStrategy::ExponentialBackOff {
+        base: Duration::from_secs(u64::MAX),
+        max: Duration::from_secs(60),
+        jitter: 0.5,
+    };
+    assert!(strategy.calculate(usize::MAX).unwrap() <= Duration::from_secs(60));
+
+    let strategy = ActorRestartStrategy::ExponentialBackOff {
+        base: Duration::from_secs(0),
+        max: Duration::from_secs(60),
+        jitter: 0.0,
+    };
+    assert_eq!(strategy.calculate(usize::MAX), Some(Duration::from_secs(0)));
+}
+
+#[test]
+fn calculate_linear_strategy_saturates() {
+    let strategy = ActorRestartStrategy::LinearBackOff {
+        timeout: Duration::from_secs(u64::MAX),
+    };
+
+    assert_eq!(strategy.calculate(usize::MAX), Some(Duration::MAX));
 }
@@ -0,0 +1,73 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_retry_escalation() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_retry_escalation() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let attempts_inner = attempts.clone();
+    Bastion::children(|children| {
+        children.with_exec(move |_: BastionContext| {
+            let attempts = attempts_inner.clone();
+            async move {
+                let policy = RetryPolicy::new()
+                    .with_max_attempts(2)
+                    .with_backoff(ActorRestartStrategy::Immediate)
+                    .with_escalation(true);
+
+                // Only the first incarnation fails...
+                let incarnation = attempts.load(Ordering::SeqCst) / 2;
+                let res = bastion::retry(policy, || {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        if incarnation == 0 {
+                            Err("timeout")
+                        } else {
+                            Ok(())
+                        }
+                    }
+                })
+                .await;
+
+                // ...and never gets here, as it faults instead.
+                assert_eq!(res, Ok(()));
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    // The child was restarted after its two failed attempts.
+    for _ in 0..100 {
+        if attempts.load(Ordering::SeqCst) >= 3 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
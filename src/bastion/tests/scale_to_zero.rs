@@ -0,0 +1,92 @@
+#![cfg(feature = "scaling")]
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_scale_to_zero() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_scale_to_zero() {
+        super::run()
+    }
+}
+
+fn elems_count() -> usize {
+    Bastion::tree()
+        .supervisors
+        .iter()
+        .flat_map(|supervisor| supervisor.children.iter())
+        .filter(|children| children.name == "pipeline")
+        .map(|children| children.elems.len())
+        .sum()
+}
+
+fn wait_until(condition: impl Fn() -> bool) {
+    for _ in 0..100 {
+        if condition() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let launched = Arc::new(AtomicUsize::new(0));
+    let launched_inner = launched.clone();
+    let received = Arc::new(AtomicUsize::new(0));
+    let received_inner = received.clone();
+    let pipeline = Bastion::children(|children| {
+        children
+            .with_name("pipeline")
+            .with_resizer(
+                OptimalSizeExploringResizer::default()
+                    .with_scale_to_zero(Duration::from_millis(100)),
+            )
+            .with_heartbeat_tick(Duration::from_millis(10))
+            .with_exec(move |ctx: BastionContext| {
+                let received = received_inner.clone();
+                launched_inner.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    loop {
+                        let _ = ctx.recv().await?;
+                        received.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    // The group starts without elements.
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(launched.load(Ordering::SeqCst), 0);
+
+    pipeline.broadcast("process").unwrap();
+    wait_until(|| received.load(Ordering::SeqCst) == 1);
+    assert_eq!(received.load(Ordering::SeqCst), 1);
+    assert_eq!(launched.load(Ordering::SeqCst), 1);
+
+    wait_until(|| elems_count() == 0);
+    assert_eq!(elems_count(), 0);
+
+    pipeline.broadcast("process").unwrap();
+    wait_until(|| received.load(Ordering::SeqCst) == 2);
+    assert_eq!(received.load(Ordering::SeqCst), 2);
+    assert_eq!(launched.load(Ordering::SeqCst), 2);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
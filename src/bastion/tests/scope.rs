@@ -0,0 +1,108 @@
+use bastion::prelude::*;
+use futures_timer::Delay;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_scope() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_scope() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    // The sub-tasks are awaited when the scope exits...
+    let completed = Arc::new(AtomicUsize::new(0));
+    let joined = Arc::new(AtomicUsize::new(usize::MAX));
+    let completed_inner = completed.clone();
+    let joined_inner = joined.clone();
+    Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let completed = completed_inner.clone();
+            let joined = joined_inner.clone();
+            async move {
+                let spawned = completed.clone();
+                ctx.scope(|nursery| async move {
+                    for _ in 0..3 {
+                        let completed = spawned.clone();
+                        nursery.spawn(async move {
+                            Delay::new(Duration::from_millis(50)).await;
+                            completed.fetch_add(1, Ordering::SeqCst);
+                        });
+                    }
+                })
+                .await;
+
+                joined.store(completed.load(Ordering::SeqCst), Ordering::SeqCst);
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if joined.load(Ordering::SeqCst) != usize::MAX {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(completed.load(Ordering::SeqCst), 3);
+    assert_eq!(joined.load(Ordering::SeqCst), 3);
+
+    // ...and cancelled when the child is stopped.
+    let started = Arc::new(AtomicBool::new(false));
+    let finished = Arc::new(AtomicBool::new(false));
+    let started_inner = started.clone();
+    let finished_inner = finished.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let started = started_inner.clone();
+            let finished = finished_inner.clone();
+            async move {
+                ctx.scope(|nursery| async move {
+                    nursery.spawn(async move {
+                        started.store(true, Ordering::SeqCst);
+                        Delay::new(Duration::from_millis(200)).await;
+                        finished.store(true, Ordering::SeqCst);
+                    });
+                })
+                .await;
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if started.load(Ordering::SeqCst) {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(started.load(Ordering::SeqCst));
+    children.stop().expect("Couldn't stop the children group.");
+    thread::sleep(Duration::from_millis(400));
+    assert!(!finished.load(Ordering::SeqCst));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
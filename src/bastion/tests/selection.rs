@@ -0,0 +1,79 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_selection() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_selection() {
+        super::run()
+    }
+}
+
+fn spawn_group(supervisor: &SupervisorRef, name: &str, received: Arc<AtomicUsize>) {
+    let name = name.to_string();
+    supervisor
+        .children(move |children| {
+            children
+                .with_name(name)
+                .with_redundancy(2)
+                .with_exec(move |ctx: BastionContext| {
+                    let received = received.clone();
+                    async move {
+                        loop {
+                            let _ = ctx.recv().await?;
+                            received.fetch_add(1, Ordering::SeqCst);
+                        }
+                    }
+                })
+        })
+        .expect("Couldn't create the children group.");
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let supervisor = Bastion::supervisor(|sp| sp).expect("Couldn't create the supervisor.");
+    let workers = Arc::new(AtomicUsize::new(0));
+    spawn_group(&supervisor, "workers", workers.clone());
+    let others = Arc::new(AtomicUsize::new(0));
+    spawn_group(&supervisor, "others", others.clone());
+
+    let mut selection = Bastion::select("/**/workers/*");
+    for _ in 0..100 {
+        if selection.len() == 2 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+        selection = Bastion::select("/**/workers/*");
+    }
+
+    assert_eq!(selection.len(), 2);
+    selection.tell_everyone("reload").unwrap();
+
+    for _ in 0..100 {
+        if workers.load(Ordering::SeqCst) == 2 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(workers.load(Ordering::SeqCst), 2);
+    assert_eq!(others.load(Ordering::SeqCst), 0);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,60 @@
+use bastion::prelude::*;
+use futures_timer::Delay;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_shutdown_gracefully() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_shutdown_gracefully() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let handled = Arc::new(AtomicUsize::new(0));
+    let handled_inner = handled.clone();
+    let stopped = Arc::new(AtomicBool::new(false));
+    let stopped_inner = stopped.clone();
+    let children = Bastion::children(|children| {
+        children
+            .with_callbacks(Callbacks::new().with_after_stop(move || {
+                stopped_inner.store(true, Ordering::SeqCst);
+            }))
+            .with_exec(move |ctx: BastionContext| {
+                let handled = handled_inner.clone();
+                async move {
+                    loop {
+                        let _ = ctx.recv().await?;
+                        // Slow enough for the messages to still be in
+                        // the mailbox when shutting down.
+                        Delay::new(Duration::from_millis(10)).await;
+                        handled.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = &children.elems()[0];
+    for _ in 0..10 {
+        child.tell_anonymously("work").unwrap();
+    }
+
+    assert!(Bastion::shutdown_gracefully(Duration::from_secs(5)));
+    assert_eq!(handled.load(Ordering::SeqCst), 10);
+    assert!(stopped.load(Ordering::SeqCst));
+    assert!(child.tell_anonymously("late").is_err());
+}
@@ -0,0 +1,83 @@
+#![cfg(all(feature = "signals", not(target_os = "windows")))]
+use bastion::prelude::*;
+use signal_hook::consts::{SIGHUP, SIGTERM};
+use signal_hook::low_level::raise;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_signals() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_signals() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    let signals = SignalConfig::new()
+        .with_drain_timeout(Duration::from_secs(1))
+        .with_config_loader(|| Ok(Config::new().with_rate_limit("api", RateLimit::per_second(5))));
+    Bastion::handle_signals(signals).expect("Couldn't handle the signals.");
+    Bastion::start();
+
+    let subscribed = Arc::new(AtomicBool::new(false));
+    let reloaded = Arc::new(Mutex::new(None));
+
+    let subscribed_inner = subscribed.clone();
+    let reloaded_inner = reloaded.clone();
+    Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let subscribed = subscribed_inner.clone();
+            let reloaded = reloaded_inner.clone();
+            async move {
+                Signals::subscribe(ctx.current());
+                subscribed.store(true, Ordering::SeqCst);
+                loop {
+                    msg! { ctx.recv().await?,
+                        signal: ReloadSignal => {
+                            let applied = signal.report().map(|report| report.applied().to_vec());
+                            *reloaded.lock().unwrap() = applied;
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if subscribed.load(Ordering::SeqCst) {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // SIGHUP reloads the configuration and notifies the child...
+    raise(SIGHUP).unwrap();
+    for _ in 0..100 {
+        if reloaded.lock().unwrap().is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(reloaded.lock().unwrap().take(), Some(vec!["rate_limits"]));
+
+    // ...while SIGTERM stops the system.
+    raise(SIGTERM).unwrap();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "cluster")]
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_singleton() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_singleton() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_inner = received.clone();
+    let init = move |children: Children| {
+        let received = received_inner.clone();
+        children.with_exec(move |ctx: BastionContext| {
+            let received = received.clone();
+            async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        msg: String => received.lock().unwrap().push(msg);
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    };
+
+    // A node that didn't join a cluster is its own leader.
+    let singleton = ClusterSingleton::spawn("coordinator", init.clone())
+        .expect("Couldn't spawn the singleton.");
+    assert!(singleton.is_running_locally());
+    assert!(matches!(
+        ClusterSingleton::spawn("coordinator", init),
+        Err(SingletonError::AlreadySpawned)
+    ));
+
+    singleton
+        .tell("hello".to_string())
+        .expect("Couldn't send the message.");
+
+    for _ in 0..100 {
+        if !received.lock().unwrap().is_empty() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(*received.lock().unwrap(), vec!["hello".to_string()]);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,69 @@
+use bastion::prelude::*;
+use futures::prelude::*;
+use std::any::type_name;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_slow_handler() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_slow_handler() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let mut events = Bastion::events();
+    let children = Bastion::children(|children| {
+        children
+            .with_slow_handler_threshold(Duration::from_millis(50))
+            .with_exec(|ctx: BastionContext| async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        _fast: u32 => ();
+                        _slow: &'static str => {
+                            thread::sleep(Duration::from_millis(100));
+                        };
+                        _: _ => ();
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let child = &children.elems()[0];
+    child.tell_anonymously(42u32).unwrap();
+    child.tell_anonymously("slow").unwrap();
+
+    let mut slow = None;
+    while let Some(event) = run!(events.next()) {
+        if let SupervisionEvent::SlowHandler {
+            path,
+            message_type,
+            elapsed,
+        } = event
+        {
+            slow = Some((path, message_type, elapsed));
+            break;
+        }
+    }
+
+    let (path, message_type, elapsed) = slow.unwrap();
+    assert_eq!(path.id(), child.path().id());
+    assert_eq!(message_type, type_name::<&str>());
+    assert!(elapsed > Duration::from_millis(50));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
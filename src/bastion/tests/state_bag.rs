@@ -0,0 +1,74 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_state_bag() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_state_bag() {
+        super::run()
+    }
+}
+
+#[derive(Clone)]
+struct Runs(u32);
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let recovered = Arc::new(AtomicU32::new(0));
+    let recovered_inner = recovered.clone();
+    let handed_off = Arc::new(AtomicBool::new(false));
+    let handed_off_inner = handed_off.clone();
+    Bastion::children(|children| {
+        children
+            .with_callbacks(
+                Callbacks::new().with_after_restart_state(move |state: &StateBag| {
+                    handed_off_inner.store(state.contains::<Runs>(), Ordering::SeqCst);
+                }),
+            )
+            .with_exec(move |ctx: BastionContext| {
+                let recovered = recovered_inner.clone();
+                async move {
+                    let Runs(runs) = ctx.state_bag().get().unwrap_or(Runs(0));
+                    ctx.state_bag().insert(Runs(runs + 1));
+                    // Only failing the first time, so that the next
+                    // incarnation finds what this one left.
+                    if runs == 0 {
+                        return Err(());
+                    }
+
+                    recovered.store(runs, Ordering::SeqCst);
+                    loop {
+                        let _ = ctx.recv().await?;
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if recovered.load(Ordering::SeqCst) > 0 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(recovered.load(Ordering::SeqCst), 1);
+    assert!(handed_off.load(Ordering::SeqCst));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,82 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_state_registry() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_state_registry() {
+        super::run()
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint(&'static str);
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    Bastion::state().insert(Endpoint("global"));
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    // Children groups created by the system see the values
+    // shared by the system...
+    let seen_inner = seen.clone();
+    Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let seen = seen_inner.clone();
+            async move {
+                let endpoint: Arc<Endpoint> = ctx.state();
+                seen.lock().unwrap().push(endpoint.0);
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    // ...while the values shared by a supervisor take precedence
+    // for the elements it supervises.
+    let seen_inner = seen.clone();
+    Bastion::supervisor(|sp| {
+        sp.with_state(Endpoint("scoped")).children(|children| {
+            children.with_exec(move |ctx: BastionContext| {
+                let seen = seen_inner.clone();
+                async move {
+                    let endpoint: Arc<Endpoint> = ctx.state();
+                    assert!(ctx.try_state::<u64>().is_none());
+                    seen.lock().unwrap().push(endpoint.0);
+
+                    Ok(())
+                }
+            })
+        })
+    })
+    .expect("Couldn't create the supervisor.");
+
+    for _ in 0..100 {
+        if seen.lock().unwrap().len() == 2 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut seen = seen.lock().unwrap().clone();
+    seen.sort_unstable();
+    assert_eq!(seen, vec!["global", "scoped"]);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
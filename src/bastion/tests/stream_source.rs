@@ -0,0 +1,76 @@
+use bastion::prelude::*;
+use futures::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_stream_source() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_stream_source() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    // The first stream fails after its third item, the second one
+    // resumes after it.
+    let resumed = Arc::new(Mutex::new(Vec::new()));
+    let resumed_inner = resumed.clone();
+    let source = move |offset: Option<u32>| {
+        resumed_inner.lock().unwrap().push(offset);
+        let items: Vec<Result<(u32, u32), &'static str>> = match offset {
+            None => vec![Ok((0, 0)), Ok((1, 1)), Ok((2, 2)), Err("disconnected")],
+            Some(offset) => (offset + 1..5).map(|n| Ok((n, n))).collect(),
+        };
+
+        stream::iter(items)
+    };
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_inner = received.clone();
+    Bastion::children(|children| {
+        children
+            .with_redundancy(2)
+            .with_stream_source(source, RestartStrategy::default())
+            .with_exec(move |ctx: BastionContext| {
+                let received = received_inner.clone();
+                async move {
+                    loop {
+                        msg! { ctx.recv().await?,
+                            n: u32 => received.lock().unwrap().push(n);
+                            _: _ => ();
+                        }
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if received.lock().unwrap().len() == 5 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut received = received.lock().unwrap().clone();
+    received.sort();
+    assert_eq!(received, vec![0, 1, 2, 3, 4]);
+    assert_eq!(*resumed.lock().unwrap(), vec![None, Some(2)]);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
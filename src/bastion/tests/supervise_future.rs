@@ -0,0 +1,71 @@
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_supervise_future() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_supervise_future() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let supervisor = Bastion::supervisor(|sp| sp).expect("Couldn't create the supervisor.");
+
+    // The future is restarted until it succeeds...
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let attempts_inner = attempts.clone();
+    let job = supervisor
+        .supervise_future(
+            move || {
+                let attempts = attempts_inner.clone();
+                async move {
+                    if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                        panic!("Not yet.");
+                    }
+
+                    42
+                }
+            },
+            RestartPolicy::Tries(3),
+        )
+        .expect("Couldn't supervise the future.");
+
+    assert_eq!(run!(job), Some(42));
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+    // ...or until it can't be restarted anymore.
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let attempts_inner = attempts.clone();
+    let job = supervisor
+        .supervise_future(
+            move || {
+                let attempts = attempts_inner.clone();
+                async move {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    panic!("Never.");
+                }
+            },
+            RestartPolicy::Tries(1),
+        )
+        .expect("Couldn't supervise the future.");
+
+    let output: Option<()> = run!(job);
+    assert_eq!(output, None);
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,60 @@
+use bastion::prelude::*;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_supervision_escalate() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_supervision_escalate() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let runs = Arc::new(AtomicUsize::new(0));
+    let runs_inner = runs.clone();
+
+    Bastion::supervisor(move |parent| {
+        parent
+            .with_strategy(SupervisionStrategy::OneForOne)
+            .supervisor(move |sp| {
+                sp.with_strategy(SupervisionStrategy::Escalate)
+                    .children(move |children| {
+                        children.with_exec(move |_ctx: BastionContext| {
+                            let runs = runs_inner.clone();
+                            async move {
+                                // Only failing the first time, the restart
+                                // is then handled by the parent supervisor.
+                                match runs.fetch_add(1, Ordering::SeqCst) {
+                                    0 => Err(()),
+                                    _ => Ok(()),
+                                }
+                            }
+                        })
+                    })
+            })
+    })
+    .expect("Couldn't create the supervisor.");
+
+    thread::sleep(Duration::from_millis(500));
+
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,64 @@
+use bastion::prelude::*;
+use futures::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_supervision_events() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_supervision_events() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let mut events = Bastion::events();
+    let panicked = Arc::new(AtomicBool::new(false));
+
+    Bastion::children(|children| {
+        children.with_exec(move |_ctx: BastionContext| {
+            let panicked = panicked.clone();
+            async move {
+                // Only the first instance panics, its restarted
+                // instance then finishes successfully.
+                if !panicked.swap(true, Ordering::SeqCst) {
+                    panic!("first run");
+                }
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let mut error = None;
+    let mut restarted = false;
+    while let Some(event) = run!(events.next()) {
+        match event {
+            SupervisionEvent::ChildPanicked {
+                error: ChildError::Panic { message, .. },
+                ..
+            } => error = message,
+            SupervisionEvent::ChildRestarted { .. } => restarted = true,
+            SupervisionEvent::ChildStopped { .. } if restarted => break,
+            _ => (),
+        }
+    }
+
+    assert_eq!(error.as_deref(), Some("first run"));
+    assert!(restarted);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,67 @@
+use bastion::prelude::*;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_rest_for_one() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_rest_for_one() {
+        super::run()
+    }
+}
+
+fn group(children: Children, runs: Arc<AtomicUsize>, fail_once: bool) -> Children {
+    children.with_exec(move |ctx: BastionContext| {
+        let runs = runs.clone();
+        async move {
+            if runs.fetch_add(1, Ordering::SeqCst) == 0 && fail_once {
+                return Err(());
+            }
+
+            ctx.recv().await?;
+            Ok(())
+        }
+    })
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let runs = [
+        Arc::new(AtomicUsize::new(0)),
+        Arc::new(AtomicUsize::new(0)),
+        Arc::new(AtomicUsize::new(0)),
+    ];
+    let (first, second, third) = (runs[0].clone(), runs[1].clone(), runs[2].clone());
+
+    Bastion::supervisor(move |sp| {
+        sp.with_strategy(SupervisionStrategy::RestForOne)
+            .children(move |children| group(children, first, false))
+            .children(move |children| group(children, second, true))
+            .children(move |children| group(children, third, false))
+    })
+    .expect("Couldn't create the supervisor.");
+
+    thread::sleep(Duration::from_millis(500));
+
+    // Only the failed group and the ones added after it are restarted.
+    assert_eq!(runs[0].load(Ordering::SeqCst), 1);
+    assert_eq!(runs[1].load(Ordering::SeqCst), 2);
+    assert_eq!(runs[2].load(Ordering::SeqCst), 2);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,89 @@
+#![cfg(not(target_os = "windows"))]
+use bastion::prelude::*;
+use futures::io;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_tcp_server() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_tcp_server() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    // The first incarnation serving a connection fails, and the
+    // restarted one echoes the client's messages back to it.
+    let served = Arc::new(AtomicUsize::new(0));
+    let served_inner = served.clone();
+    let server = TcpServer::bind("127.0.0.1:0").expect("Couldn't bind the server.");
+    let addr = server.local_addr().unwrap();
+    server
+        .with_max_connections(1)
+        .serve(move |children| {
+            let served = served_inner.clone();
+            children.with_exec(move |ctx: BastionContext| {
+                let served = served.clone();
+                async move {
+                    let conn = ctx.tcp_connection().unwrap();
+                    if served.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return Err(());
+                    }
+
+                    io::copy(conn.stream(), &mut conn.stream()).await.ok();
+                    Ok(())
+                }
+            })
+        })
+        .expect("Couldn't serve the connections.");
+
+    let mut client = TcpStream::connect(addr).unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    client.write_all(b"ping").unwrap();
+
+    let mut echoed = [0; 4];
+    client.read_exact(&mut echoed).unwrap();
+    assert_eq!(&echoed, b"ping");
+    assert_eq!(served.load(Ordering::SeqCst), 2);
+
+    // Closing the connection frees its slot for the next client.
+    drop(client);
+    let mut client = TcpStream::connect(addr).unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    client.write_all(b"pong").unwrap();
+    client.read_exact(&mut echoed).unwrap();
+    assert_eq!(&echoed, b"pong");
+
+    for _ in 0..100 {
+        if served.load(Ordering::SeqCst) == 3 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(served.load(Ordering::SeqCst), 3);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
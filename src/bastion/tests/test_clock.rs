@@ -0,0 +1,75 @@
+use bastion::prelude::*;
+use bastion::testing::TestClock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_clock() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_clock() {
+        super::run()
+    }
+}
+
+fn run() {
+    let clock = TestClock::install();
+    Bastion::init();
+    Bastion::start();
+
+    let received = Arc::new(AtomicBool::new(false));
+    let received_inner = received.clone();
+    Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let received = received_inner.clone();
+            async move {
+                ctx.schedule_once(Duration::from_secs(3600), "an hour later");
+                msg! { ctx.recv().await?,
+                    _: &'static str => received.store(true, Ordering::SeqCst);
+                    _: _ => ();
+                }
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if clock.pending_timers() > 0 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // The timer only fires once the clock is advanced past it.
+    assert_eq!(clock.pending_timers(), 1);
+    clock.advance(Duration::from_secs(3599));
+    thread::sleep(Duration::from_millis(50));
+    assert!(!received.load(Ordering::SeqCst));
+
+    clock.advance(Duration::from_secs(1));
+    for _ in 0..100 {
+        if received.load(Ordering::SeqCst) {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(received.load(Ordering::SeqCst));
+    assert_eq!(clock.elapsed(), Duration::from_secs(3600));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,61 @@
+use bastion::prelude::*;
+use bastion::testing::TestProbe;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_probe() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_probe() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let mut probe = TestProbe::new();
+    probe.reply_with(|n: &u32| n * 2);
+
+    let peer = probe.child_ref().clone();
+    Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let peer = peer.clone();
+            async move {
+                ctx.tell(&peer.addr(), "hello").ok();
+                let answer = ctx.ask(&peer.addr(), 21u32).unwrap().await?;
+                msg! { answer,
+                    n: u32 => {
+                        ctx.tell(&peer.addr(), n as u64).ok();
+                    };
+                    _: _ => ();
+                }
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let timeout = Duration::from_secs(1);
+    assert_eq!(run!(probe.expect_msg::<&str>(timeout)), "hello");
+    // The asked messages are recorded and answered...
+    assert_eq!(run!(probe.expect_msg::<u32>(timeout)), 21);
+    assert_eq!(run!(probe.expect_msg::<u64>(timeout)), 42);
+    // ...and nothing else was received.
+    run!(probe.expect_no_msg(Duration::from_millis(100)));
+
+    probe.child_ref().tell_anonymously("bye").unwrap();
+    assert!(run!(probe.receive(timeout)).is_some());
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
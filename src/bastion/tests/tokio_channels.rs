@@ -0,0 +1,77 @@
+#![cfg(feature = "tokio-channels")]
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_tokio_channels() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_tokio_channels() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_inner = received.clone();
+    let children = Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let received = received_inner.clone();
+            async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        n: u32 => {
+                            received.lock().unwrap().push(n);
+                        };
+                        _: _ => ();
+                    }
+                }
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    // The items of the mpsc channel are delivered to the group, and
+    // the messages of the broadcast channel to its element.
+    let (sender, receiver) = mpsc::channel(16);
+    children
+        .attach_receiver(receiver)
+        .expect("Couldn't attach the receiver.");
+    for n in 0..3 {
+        sender.try_send(n).unwrap();
+    }
+
+    let (bcast, _) = broadcast::channel(16);
+    children.elems()[0]
+        .subscribe_to_broadcast(&bcast)
+        .expect("Couldn't subscribe.");
+    bcast.send(3u32).unwrap();
+
+    for _ in 0..100 {
+        if received.lock().unwrap().len() == 4 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut received = received.lock().unwrap().clone();
+    received.sort_unstable();
+    assert_eq!(received, vec![0, 1, 2, 3]);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "tokio-runtime")]
+use bastion::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_tokio_executor() {
+    // The system runs on its own runtime, which can't be dropped
+    // from the async context of a `tokio::test`. It also is the one
+    // `bastion-executor` uses with the `tokio-runtime` feature.
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let _guard = runtime.enter();
+    let config = Config::new().with_executor(Executor::Tokio(runtime.handle().clone()));
+    Bastion::init_with(config);
+    Bastion::start();
+
+    // The child runs in the context of the runtime, and receives
+    // the message scheduled by its timers.
+    let scheduled = Arc::new(AtomicBool::new(false));
+    let scheduled_inner = scheduled.clone();
+    Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let scheduled = scheduled_inner.clone();
+            async move {
+                assert!(tokio::runtime::Handle::try_current().is_ok());
+                tokio::time::sleep(Duration::from_millis(10)).await;
+
+                ctx.schedule_once(Duration::from_millis(10), "tick");
+                msg! { ctx.recv().await?,
+                    _tick: &'static str => {
+                        scheduled.store(true, Ordering::SeqCst);
+                    };
+                    _: _ => ();
+                }
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if scheduled.load(Ordering::SeqCst) {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(scheduled.load(Ordering::SeqCst));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
+
+#[test]
+fn test_tokio_executor_sleep() {
+    // The timers of a handle work outside of the context of its
+    // runtime.
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let handle = runtime.handle().clone();
+    thread::spawn(move || {
+        let sleep = AgnosticExecutor::sleep(&handle, Duration::from_millis(10));
+        futures::executor::block_on(sleep);
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,71 @@
+use bastion::prelude::*;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_tree() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_tree() {
+        super::run()
+    }
+}
+
+fn workers(tree: &TreeSnapshot) -> Option<ChildrenSnapshot> {
+    tree.supervisors
+        .iter()
+        .flat_map(|supervisor| supervisor.children.iter())
+        .find(|children| children.name == "workers")
+        .cloned()
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let supervisor = Bastion::supervisor(|sp| sp).expect("Couldn't create the supervisor.");
+    supervisor
+        .children(|children| {
+            children
+                .with_name("workers")
+                .with_redundancy(2)
+                .with_child_name(|index| format!("worker-{}", index))
+                .with_exec(|ctx: BastionContext| async move {
+                    loop {
+                        let _ = ctx.recv().await?;
+                    }
+                })
+        })
+        .expect("Couldn't create the children group.");
+
+    let mut group = None;
+    for _ in 0..100 {
+        group = workers(&Bastion::tree()).filter(|group| group.elems.len() == 2);
+        if group.is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let group = group.expect("Couldn't find the children group in the tree.");
+    for elem in &group.elems {
+        assert_eq!(elem.status, ChildStatus::Running);
+        assert_eq!(elem.restarts, 0);
+        assert!(elem.name.as_deref().unwrap().starts_with("worker-"));
+    }
+
+    let tree = Bastion::tree();
+    let json = serde_json::to_value(&tree).expect("Couldn't serialize the tree.");
+    assert!(json["supervisors"].is_array());
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
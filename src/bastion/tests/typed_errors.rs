@@ -0,0 +1,68 @@
+use bastion::prelude::*;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_typed_errors() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_typed_errors() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let failed = Arc::new(Mutex::new(None));
+    let failed_inner = failed.clone();
+    let runs = Arc::new(AtomicUsize::new(0));
+    let runs_inner = runs.clone();
+    Bastion::children(|children| {
+        let callbacks =
+            Callbacks::new().with_before_restart_failure(move |failure: &ChildFailure| {
+                let kind = failure.downcast_error::<io::Error>().map(io::Error::kind);
+                *failed_inner.lock().unwrap() = kind;
+            });
+
+        children
+            .with_callbacks(callbacks)
+            .with_typed_exec(move |ctx: BastionContext| {
+                let runs = runs_inner.clone();
+                async move {
+                    // Only failing the first time.
+                    if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+                    }
+
+                    while ctx.recv().await.is_ok() {}
+                    Ok(())
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    for _ in 0..100 {
+        if runs.load(Ordering::SeqCst) > 1 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(*failed.lock().unwrap(), Some(io::ErrorKind::TimedOut));
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,65 @@
+#![cfg(not(target_os = "windows"))]
+use bastion::prelude::*;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_udp_endpoint() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_udp_endpoint() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    // The handlers reply with the received data, uppercased.
+    let handlers = Bastion::children(|children| {
+        children
+            .with_redundancy(2)
+            .with_exec(|ctx: BastionContext| async move {
+                loop {
+                    msg! { ctx.recv().await?,
+                        datagram: Datagram => {
+                            let reply = datagram.data().to_ascii_uppercase();
+                            ctx.reply_datagram(datagram.peer(), reply).await.unwrap();
+                        };
+                        _: _ => ();
+                    }
+                }
+            })
+    })
+    .expect("Couldn't create the children group.");
+
+    let endpoint = UdpEndpoint::bind("127.0.0.1:0").expect("Couldn't bind the endpoint.");
+    let addr = endpoint.local_addr().unwrap();
+    endpoint
+        .deliver_to(&handlers)
+        .expect("Couldn't receive the datagrams.");
+
+    let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    let mut buf = [0; 16];
+    for query in &["ping", "pong"] {
+        client.send_to(query.as_bytes(), addr).unwrap();
+        let (len, from) = client.recv_from(&mut buf).unwrap();
+        assert_eq!(from, addr);
+        assert_eq!(&buf[..len], query.to_ascii_uppercase().as_bytes());
+    }
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
@@ -0,0 +1,74 @@
+use bastion::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_watch() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_watch() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let watched = Bastion::children(|children| {
+        children.with_exec(|ctx: BastionContext| async move {
+            // Stops once told to.
+            let _ = ctx.recv().await?;
+            Ok(())
+        })
+    })
+    .expect("Couldn't create the children group.");
+    let watched = watched.elems()[0].clone();
+
+    let terminated = Arc::new(Mutex::new(None));
+    let terminated_inner = terminated.clone();
+    Bastion::children(|children| {
+        children.with_exec(move |ctx: BastionContext| {
+            let watched = watched.clone();
+            let terminated = terminated_inner.clone();
+            async move {
+                ctx.watch(&watched);
+                ctx.tell(&watched.addr(), "stop").unwrap();
+
+                msg! { ctx.recv().await?,
+                    msg: Terminated => {
+                        *terminated.lock().unwrap() = Some(msg);
+                    };
+                    _: _ => ();
+                }
+
+                Ok(())
+            }
+        })
+    })
+    .expect("Couldn't create the children group.");
+
+    let mut msg = None;
+    for _ in 0..100 {
+        msg = terminated.lock().unwrap().take();
+        if msg.is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let Terminated(_, reason) = msg.expect("Didn't receive the Terminated message.");
+    assert_eq!(reason, StopReason::Normal);
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
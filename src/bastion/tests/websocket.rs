@@ -0,0 +1,88 @@
+#![cfg(all(feature = "websocket", not(target_os = "windows")))]
+use bastion::prelude::*;
+use futures::prelude::*;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_tests {
+    #[tokio::test]
+    async fn test_websocket() {
+        super::run()
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+mod not_tokio_tests {
+    #[test]
+    fn test_websocket() {
+        super::run()
+    }
+}
+
+fn run() {
+    Bastion::init();
+    Bastion::start();
+
+    let mut events = Bastion::events();
+
+    // The server echoes the frames of its clients back to them.
+    let server = WebSocketServer::bind("127.0.0.1:0").expect("Couldn't bind the server.");
+    let addr = server.local_addr().unwrap();
+    server
+        .serve(|children| {
+            children.with_exec(|ctx: BastionContext| async move {
+                let ws = ctx.websocket().unwrap();
+                loop {
+                    msg! { ctx.recv().await?,
+                        frame: WsFrame => {
+                            ws.send(frame).await.ok();
+                        };
+                        _: _ => ();
+                    }
+                }
+            })
+        })
+        .expect("Couldn't serve the connections.");
+
+    // The client closes the connection once its frame was echoed.
+    let echoed = Arc::new(Mutex::new(None));
+    let echoed_inner = echoed.clone();
+    WebSocketClient::new(&format!("ws://{}/echo", addr))
+        .connect(move |children| {
+            children.with_exec(move |ctx: BastionContext| {
+                let echoed = echoed_inner.clone();
+                async move {
+                    let ws = ctx.websocket().unwrap();
+                    ws.send(WsFrame::Text("ping".to_string())).await.ok();
+                    msg! { ctx.recv().await?,
+                        frame: WsFrame => {
+                            *echoed.lock().unwrap() = Some(frame);
+                        };
+                        _: _ => ();
+                    }
+
+                    Ok(())
+                }
+            })
+        })
+        .expect("Couldn't connect.");
+
+    // The element serving the connection stops once it was lost.
+    while let Some(event) = run!(events.next()) {
+        if let SupervisionEvent::ChildStopped {
+            reason: StopReason::Disconnected,
+            ..
+        } = event
+        {
+            break;
+        }
+    }
+
+    assert_eq!(
+        echoed.lock().unwrap().take(),
+        Some(WsFrame::Text("ping".to_string()))
+    );
+
+    Bastion::stop();
+    Bastion::block_until_stopped();
+}
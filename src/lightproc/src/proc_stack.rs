@@ -12,6 +12,35 @@ use std::fmt::{self, Debug, Formatter};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
+/// Scheduling class of a lightweight process
+///
+/// Executors respecting it run the processes of a higher class
+/// before the ones of a lower class.
+///
+/// # Example
+///
+/// ```rust
+/// use lightproc::proc_stack::ProcPriority;
+///
+/// assert!(ProcPriority::High > ProcPriority::Normal);
+/// assert!(ProcPriority::Normal > ProcPriority::Low);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProcPriority {
+    /// Bulk background work, mostly run when nothing else is ready
+    Low,
+    /// The default priority of processes
+    Normal,
+    /// System processes and latency-critical work
+    High,
+}
+
+impl Default for ProcPriority {
+    fn default() -> Self {
+        ProcPriority::Normal
+    }
+}
+
 /// Stack abstraction for lightweight processes
 ///
 /// # Example
@@ -31,6 +60,9 @@ pub struct ProcStack {
     /// Can be used to identify specific processes during any executor, reactor implementations.
     pub pid: AtomicUsize,
 
+    /// Scheduling class of the process
+    pub(crate) priority: ProcPriority,
+
     pub(crate) state: ProcState,
 
     /// Before start callback
@@ -69,6 +101,21 @@ impl ProcStack {
         self
     }
 
+    /// Sets the scheduling class of the process which is going to take this stack
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lightproc::proc_stack::{ProcPriority, ProcStack};
+    ///
+    /// ProcStack::default()
+    ///     .with_priority(ProcPriority::High);
+    /// ```
+    pub fn with_priority(mut self, priority: ProcPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
     /// Adds state for the process which is going to be embedded into this stack.
     ///
     /// # Example
@@ -159,6 +206,19 @@ impl ProcStack {
         self.pid.load(Ordering::Acquire)
     }
 
+    /// Utility function to get the scheduling class for the implementation of executors.
+    ///
+    /// ```rust
+    /// use lightproc::proc_stack::{ProcPriority, ProcStack};
+    ///
+    /// let proc = ProcStack::default().with_priority(ProcPriority::Low);
+    ///
+    /// assert_eq!(proc.get_priority(), ProcPriority::Low);
+    /// ```
+    pub fn get_priority(&self) -> ProcPriority {
+        self.priority
+    }
+
     /// Get the state which is embedded into this [ProcStack].
     ///
     /// ```rust
@@ -210,6 +270,7 @@ impl Default for ProcStack {
     fn default() -> Self {
         ProcStack {
             pid: AtomicUsize::new(0xDEAD_BEEF),
+            priority: ProcPriority::default(),
             state: Arc::new(Mutex::new(EmptyState)),
             before_start: None,
             after_complete: None,
@@ -222,6 +283,7 @@ impl Debug for ProcStack {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         fmt.debug_struct("ProcStack")
             .field("pid", &self.pid.load(Ordering::SeqCst))
+            .field("priority", &self.priority)
             .field("state", &self.state)
             .field("before_start", &self.before_start.is_some())
             .field("after_complete", &self.after_complete.is_some())
@@ -234,6 +296,7 @@ impl Clone for ProcStack {
     fn clone(&self) -> Self {
         ProcStack {
             pid: AtomicUsize::new(self.pid.load(Ordering::Acquire)),
+            priority: self.priority,
             state: self.state.clone(),
             before_start: self.before_start.clone(),
             after_complete: self.after_complete.clone(),
@@ -1,4 +1,4 @@
-use lightproc::proc_stack::ProcStack;
+use lightproc::proc_stack::{ProcPriority, ProcStack};
 use lightproc::proc_state::EmptyProcState;
 
 #[test]
@@ -13,3 +13,12 @@ fn stack_copy() {
 
     assert_eq!(stack2.get_pid(), 12);
 }
+
+#[test]
+fn stack_priority() {
+    let stack = ProcStack::default().with_priority(ProcPriority::High);
+    let stack2 = stack.clone();
+
+    assert_eq!(stack2.get_priority(), ProcPriority::High);
+    assert_eq!(ProcStack::default().get_priority(), ProcPriority::Normal);
+}